@@ -45,6 +45,21 @@ export interface ProactiveInsight {
   createdAt: string;
   expiresAt: string | null;
   estimatedTimeSavedSeconds: number;
+  /** Source items and rationale that produced this insight. Absent for legacy rows and extensions that don't supply it. */
+  evidence?: InsightEvidence;
+}
+
+export interface InsightEvidenceItem {
+  kind: 'email' | 'calendar_event' | 'file';
+  /** Email messageId, calendar event uid, or file path. */
+  id: string;
+  /** Human-readable label (subject, event title, document title). */
+  label: string;
+}
+
+export interface InsightEvidence {
+  items: InsightEvidenceItem[];
+  rationale: string;
 }
 
 export interface SuggestedAction {
@@ -84,6 +99,7 @@ export interface EmailSummary {
 export interface DocumentRef {
   title: string;
   source: string;
+  sourcePath?: string;
   score: number;
 }
 
@@ -103,7 +119,8 @@ const CREATE_INSIGHTS_TABLE = `
     created_at TEXT NOT NULL,
     expires_at TEXT,
     estimated_time_saved_seconds INTEGER NOT NULL DEFAULT 0,
-    dismissed INTEGER NOT NULL DEFAULT 0
+    dismissed INTEGER NOT NULL DEFAULT 0,
+    evidence TEXT
   );
 
   CREATE INDEX IF NOT EXISTS idx_insights_type ON proactive_insights(type);
@@ -177,6 +194,12 @@ export class ProactiveEngine {
     this.autonomy = config.autonomy;
     this.pollIntervalMs = config.pollIntervalMs ?? 15 * 60 * 1000; // default 15 minutes
     this.db.exec(CREATE_INSIGHTS_TABLE);
+    // Migration: add evidence column to existing proactive_insights tables
+    try {
+      this.db.exec('ALTER TABLE proactive_insights ADD COLUMN evidence TEXT');
+    } catch {
+      // Column already exists — ignore
+    }
   }
 
   onEvent(handler: ProactiveEventHandler): void {
@@ -277,6 +300,16 @@ export class ProactiveEngine {
         createdAt: new Date().toISOString(),
         expiresAt: event.endTime, // expires after the meeting
         estimatedTimeSavedSeconds: 600, // 10 minutes per meeting prep
+        evidence: {
+          items: [
+            { kind: 'calendar_event', id: event.uid, label: event.title },
+            ...brief.relevantEmails.map(e => ({ kind: 'email' as const, id: e.messageId, label: e.subject })),
+            ...brief.relevantDocuments
+              .filter(d => d.sourcePath)
+              .map(d => ({ kind: 'file' as const, id: d.sourcePath!, label: d.title })),
+          ],
+          rationale: `Meeting starts within 24 hours with ${brief.attendees.length} attendee${brief.attendees.length !== 1 ? 's' : ''}`,
+        },
       };
 
       insights.push(insight);
@@ -337,6 +370,7 @@ export class ProactiveEngine {
     const relevantDocuments: DocumentRef[] = docResults.map(r => ({
       title: r.document.title,
       source: r.document.source,
+      sourcePath: r.document.sourcePath,
       score: r.score,
     }));
 
@@ -416,6 +450,10 @@ export class ProactiveEngine {
         createdAt: new Date().toISOString(),
         expiresAt: null,
         estimatedTimeSavedSeconds: 30,
+        evidence: {
+          items: [{ kind: 'email', id: email.messageId, label: email.subject }],
+          rationale: `Unread for ${daysSince} day${daysSince !== 1 ? 's' : ''} and reads like a question or request`,
+        },
       };
 
       insights.push(insight);
@@ -461,6 +499,10 @@ export class ProactiveEngine {
         createdAt: new Date().toISOString(),
         expiresAt: null,
         estimatedTimeSavedSeconds: 120,
+        evidence: {
+          items: [{ kind: 'email', id: email.messageId, label: email.subject }],
+          rationale: 'Received in the past week and mentions a deadline or due date',
+        },
       };
 
       insights.push(insight);
@@ -492,6 +534,7 @@ export class ProactiveEngine {
       created_at: string;
       expires_at: string | null;
       estimated_time_saved_seconds: number;
+      evidence: string | null;
     }>;
 
     return rows.map(r => ({
@@ -505,9 +548,22 @@ export class ProactiveEngine {
       createdAt: r.created_at,
       expiresAt: r.expires_at,
       estimatedTimeSavedSeconds: r.estimated_time_saved_seconds,
+      ...(r.evidence ? { evidence: JSON.parse(r.evidence) as InsightEvidence } : {}),
     }));
   }
 
+  /**
+   * Get the evidence (source items + rationale) behind an insight.
+   * Returns null if the insight doesn't exist or was stored without evidence.
+   */
+  getInsightEvidence(insightId: string): InsightEvidence | null {
+    const row = this.db.prepare(
+      'SELECT evidence FROM proactive_insights WHERE id = ?'
+    ).get(insightId) as { evidence: string | null } | undefined;
+    if (!row?.evidence) return null;
+    return JSON.parse(row.evidence) as InsightEvidence;
+  }
+
   /**
    * Get a meeting prep brief for a specific event.
    */
@@ -557,8 +613,8 @@ export class ProactiveEngine {
     this.db.prepare(`
       INSERT OR IGNORE INTO proactive_insights (
         id, type, priority, title, summary, source_ids, suggested_action,
-        created_at, expires_at, estimated_time_saved_seconds
-      ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+        created_at, expires_at, estimated_time_saved_seconds, evidence
+      ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
     `).run(
       insight.id,
       insight.type,
@@ -570,6 +626,7 @@ export class ProactiveEngine {
       insight.createdAt,
       insight.expiresAt,
       insight.estimatedTimeSavedSeconds,
+      insight.evidence ? JSON.stringify(insight.evidence) : null,
     );
   }
 
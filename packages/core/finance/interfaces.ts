@@ -46,7 +46,8 @@ export interface RecurringCharge {
   chargeCount: number;
   estimatedAnnualCost: number;
   transactions: ParsedTransaction[];
-  status: 'active' | 'forgotten' | 'cancelled' | 'user_confirmed';
+  /** `merged`: folded into another charge by a confirmed merge, and hidden. */
+  status: 'active' | 'forgotten' | 'cancelled' | 'user_confirmed' | 'merged';
 }
 
 export interface SubscriptionSummary {
@@ -0,0 +1,114 @@
+// Embedding Cache — Chunk embeddings keyed by chunk content hash and model.
+//
+// Re-indexing a changed document re-chunks it, but most chunks are usually
+// identical to the previous version. The Indexer looks each chunk up here by
+// content hash and only embeds the misses. Entries are independent of the
+// document they came from, so they survive the old document being deleted.
+// Bounded: least recently used entries are evicted past `maxEntries`.
+
+import type { DatabaseHandle } from '../platform/types.js';
+
+const CREATE_TABLE = `
+  CREATE TABLE IF NOT EXISTS chunk_embeddings (
+    chunk_hash TEXT NOT NULL,
+    model TEXT NOT NULL,
+    vector BLOB NOT NULL,
+    last_used_at INTEGER NOT NULL,
+    PRIMARY KEY (chunk_hash, model)
+  );
+
+  CREATE INDEX IF NOT EXISTS idx_chunk_embeddings_lru ON chunk_embeddings(last_used_at);
+`;
+
+/** ~150MB at 768 dimensions. */
+const DEFAULT_MAX_ENTRIES = 50_000;
+
+/** SQLite's default limit on bound parameters is 999. */
+const LOOKUP_BATCH = 500;
+
+function encodeVector(v: number[]): Buffer {
+  return Buffer.from(new Float32Array(v).buffer);
+}
+
+function decodeVector(buf: Buffer): number[] {
+  return Array.from(new Float32Array(buf.buffer, buf.byteOffset, buf.byteLength / 4));
+}
+
+export class EmbeddingCache {
+  private db: DatabaseHandle;
+  private maxEntries: number;
+
+  constructor(db: DatabaseHandle, options?: { maxEntries?: number }) {
+    this.db = db;
+    this.maxEntries = options?.maxEntries ?? DEFAULT_MAX_ENTRIES;
+    this.db.exec(CREATE_TABLE);
+  }
+
+  /**
+   * Look up cached embeddings. Returns only the hits, keyed by chunk hash,
+   * and marks them as recently used.
+   */
+  getMany(chunkHashes: string[], model: string): Map<string, number[]> {
+    const hits = new Map<string, number[]>();
+    const unique = [...new Set(chunkHashes)];
+    for (let i = 0; i < unique.length; i += LOOKUP_BATCH) {
+      const batch = unique.slice(i, i + LOOKUP_BATCH);
+      const placeholders = batch.map(() => '?').join(', ');
+      const rows = this.db.prepare(
+        `SELECT chunk_hash, vector FROM chunk_embeddings WHERE model = ? AND chunk_hash IN (${placeholders})`
+      ).all(model, ...batch) as Array<{ chunk_hash: string; vector: Buffer }>;
+      for (const row of rows) {
+        hits.set(row.chunk_hash, decodeVector(row.vector));
+      }
+    }
+
+    if (hits.size > 0) {
+      const now = Date.now();
+      const touch = this.db.prepare(
+        'UPDATE chunk_embeddings SET last_used_at = ? WHERE chunk_hash = ? AND model = ?'
+      );
+      this.db.transaction(() => {
+        for (const hash of hits.keys()) touch.run(now, hash, model);
+      })();
+    }
+    return hits;
+  }
+
+  /**
+   * Store embeddings, then evict the least recently used entries past the cap.
+   */
+  putMany(entries: Array<{ chunkHash: string; embedding: number[] }>, model: string): void {
+    if (entries.length === 0) return;
+    const now = Date.now();
+    const insert = this.db.prepare(
+      'INSERT OR REPLACE INTO chunk_embeddings (chunk_hash, model, vector, last_used_at) VALUES (?, ?, ?, ?)'
+    );
+    this.db.transaction(() => {
+      for (const entry of entries) {
+        if (entry.embedding.length === 0) continue;
+        insert.run(entry.chunkHash, model, encodeVector(entry.embedding), now);
+      }
+    })();
+    this.prune();
+  }
+
+  /**
+   * Evict least recently used entries until at most `maxEntries` remain.
+   * Returns the number evicted.
+   */
+  prune(): number {
+    const { count } = this.db.prepare('SELECT COUNT(*) as count FROM chunk_embeddings').get() as { count: number };
+    const excess = count - this.maxEntries;
+    if (excess <= 0) return 0;
+    return this.db.prepare(`
+      DELETE FROM chunk_embeddings WHERE rowid IN (
+        SELECT rowid FROM chunk_embeddings ORDER BY last_used_at ASC LIMIT ?
+      )
+    `).run(excess).changes;
+  }
+
+  count(): number {
+    const row = this.db.prepare('SELECT COUNT(*) as count FROM chunk_embeddings').get() as { count: number };
+    return row.count;
+  }
+}
@@ -55,16 +55,74 @@ export interface FileContent {
   mimeType: string;
 }
 
+export interface ScanOptions {
+  /**
+   * User exclusion globs. A pattern without `/` matches any file or directory
+   * name (`node_modules`, `*.log`); a pattern with `/` matches the path relative
+   * to the scanned root (`private/**`). `*` and `?` stay within one path segment,
+   * `**` spans segments. Matching is case-insensitive.
+   */
+  exclude?: string[];
+}
+
+/**
+ * Compile exclusion globs into a predicate over (entry name, path relative to root).
+ */
+export function compileExclusions(patterns: string[]): (name: string, relativePath: string) => boolean {
+  const compiled = patterns
+    .map(p => p.trim().replace(/\\/g, '/').replace(/^\/+|\/+$/g, ''))
+    .filter(p => p.length > 0)
+    .map(p => ({ matchPath: p.includes('/'), regex: globToRegExp(p) }));
+  if (compiled.length === 0) return () => false;
+  return (name, relativePath) => compiled.some(c => c.regex.test(c.matchPath ? relativePath : name));
+}
+
+function globToRegExp(glob: string): RegExp {
+  // `dir/**` also matches `dir` itself so the directory is pruned, not walked.
+  const prunesDir = glob.endsWith('/**');
+  if (prunesDir) glob = glob.slice(0, -3);
+  let re = '';
+  for (let i = 0; i < glob.length; i++) {
+    const ch = glob[i]!;
+    if (ch === '*') {
+      if (glob[i + 1] === '*') {
+        // `**/` matches zero or more whole segments; a trailing `**` matches everything below.
+        if (glob[i + 2] === '/') {
+          re += '(?:.*/)?';
+          i += 2;
+        } else {
+          re += '.*';
+          i += 1;
+        }
+      } else {
+        re += '[^/]*';
+      }
+    } else if (ch === '?') {
+      re += '[^/]';
+    } else {
+      re += ch.replace(/[.+^${}()|[\]\\]/g, '\\$&');
+    }
+  }
+  // Case-insensitive: excluding too much is safer than leaking a private folder on a case-insensitive filesystem.
+  return new RegExp(`^${re}${prunesDir ? '(?:/.*)?' : ''}$`, 'i');
+}
+
 /**
  * Scan a directory for indexable files.
  */
-export async function scanDirectory(dirPath: string): Promise<ScannedFile[]> {
+export async function scanDirectory(dirPath: string, options: ScanOptions = {}): Promise<ScannedFile[]> {
   const files: ScannedFile[] = [];
-  await scanRecursive(dirPath, files);
+  const isExcluded = compileExclusions(options.exclude ?? []);
+  await scanRecursive(dirPath, '', files, isExcluded);
   return files;
 }
 
-async function scanRecursive(dirPath: string, results: ScannedFile[]): Promise<void> {
+async function scanRecursive(
+  dirPath: string,
+  relativeDir: string,
+  results: ScannedFile[],
+  isExcluded: (name: string, relativePath: string) => boolean,
+): Promise<void> {
   const p = getPlatform();
   let entries;
   try {
@@ -78,10 +136,12 @@ async function scanRecursive(dirPath: string, results: ScannedFile[]): Promise<v
     if (entry.name.startsWith('.')) continue;
 
     const fullPath = p.path.join(dirPath, entry.name);
+    const relativePath = relativeDir ? `${relativeDir}/${entry.name}` : entry.name;
+    if (isExcluded(entry.name, relativePath)) continue;
 
     if (entry.isDirectory()) {
       if (!EXCLUDED_DIRS.has(entry.name)) {
-        await scanRecursive(fullPath, results);
+        await scanRecursive(fullPath, relativePath, results, isExcluded);
       }
       continue;
     }
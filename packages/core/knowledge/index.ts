@@ -13,6 +13,7 @@ import { VectorStore } from './vector-store.js';
 import { Indexer } from './indexer.js';
 import { SemanticSearch } from './search.js';
 import { EmbeddingPipeline } from './embedding-pipeline.js';
+import { EmbeddingCache } from './embedding-cache.js';
 import { KnowledgeCurator } from './knowledge-curator.js';
 import { scanDirectory, readFileContent } from './file-scanner.js';
 
@@ -34,6 +35,7 @@ export { SemanticSearch } from './search.js';
 export type { SearchOptions } from './search.js';
 export { EmbeddingPipeline } from './embedding-pipeline.js';
 export type { EmbeddingPipelineConfig, EmbeddingResult, EmbeddingProgress } from './embedding-pipeline.js';
+export { EmbeddingCache } from './embedding-cache.js';
 export { RetroactiveEmbedder } from './retroactive-embedder.js';
 export type { RetroactiveEmbedderConfig, RetroactiveResult, RetroactiveStatus } from './retroactive-embedder.js';
 export { scanDirectory, readFileContent } from './file-scanner.js';
@@ -54,7 +56,7 @@ export interface KnowledgeGraph {
     sourcePath?: string;
     mimeType: string;
     metadata?: Record<string, unknown>;
-  }): Promise<{ documentId: string; chunksCreated: number; durationMs: number }>;
+  }): Promise<{ documentId: string; chunksCreated: number; durationMs: number; chunksReused?: number }>;
 
   /** Search the knowledge graph */
   search(query: string, options?: {
@@ -125,12 +127,13 @@ class KnowledgeGraphImpl implements KnowledgeGraph {
     sourcePath?: string;
     mimeType: string;
     metadata?: Record<string, unknown>;
-  }): Promise<{ documentId: string; chunksCreated: number; durationMs: number }> {
+  }): Promise<{ documentId: string; chunksCreated: number; durationMs: number; chunksReused?: number }> {
     const result = await this.indexer.indexDocument(doc);
     return {
       documentId: result.documentId,
       chunksCreated: result.chunksCreated,
       durationMs: result.durationMs,
+      chunksReused: result.chunksReused,
     };
   }
 
@@ -264,6 +267,7 @@ export async function createKnowledgeGraph(config: {
     vectorStore,
     embeddingModel,
     embeddingPipeline,
+    embeddingCache: new EmbeddingCache(db),
   });
 
   const semanticSearch = new SemanticSearch({
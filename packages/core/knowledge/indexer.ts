@@ -4,6 +4,7 @@ import { nanoid } from 'nanoid';
 import { sha256 } from '../types/signing.js';
 import type { LLMProvider } from '../llm/types.js';
 import type { EmbeddingPipeline } from './embedding-pipeline.js';
+import type { EmbeddingCache } from './embedding-cache.js';
 import type { DocumentSource, DocumentChunk } from './types.js';
 import type { DocumentStore } from './document-store.js';
 import type { VectorStore } from './vector-store.js';
@@ -14,11 +15,16 @@ export interface IndexResult {
   chunksCreated: number;
   durationMs: number;
   deduplicated: boolean;
+  /** Chunks whose embedding came from the cache (unchanged since the last index). */
+  chunksReused?: number;
+  /** Chunks that had to be embedded. */
+  chunksEmbedded?: number;
 }
 
 export class Indexer {
   private llm: LLMProvider;
   private embeddingPipeline: EmbeddingPipeline | null;
+  private embeddingCache: EmbeddingCache | null;
   private documentStore: DocumentStore;
   private vectorStore: VectorStore;
   private embeddingModel: string;
@@ -29,9 +35,11 @@ export class Indexer {
     vectorStore: VectorStore;
     embeddingModel: string;
     embeddingPipeline?: EmbeddingPipeline;
+    embeddingCache?: EmbeddingCache;
   }) {
     this.llm = config.llm;
     this.embeddingPipeline = config.embeddingPipeline ?? null;
+    this.embeddingCache = config.embeddingCache ?? null;
     this.documentStore = config.documentStore;
     this.vectorStore = config.vectorStore;
     this.embeddingModel = config.embeddingModel;
@@ -40,6 +48,7 @@ export class Indexer {
   /**
    * Index a document: chunk → embed → store.
    * Handles re-indexing if a document with the same source path exists.
+   * With an embedding cache, only chunks whose content hash changed are re-embedded.
    */
   async indexDocument(params: {
     content: string;
@@ -101,19 +110,25 @@ export class Indexer {
       };
     }
 
-    // Generate embeddings in batch (prefer pipeline if available)
+    // Reuse cached embeddings for unchanged chunks, embed the rest in batch
     const chunkTexts = textChunks.map(c => c.content);
-    let embeddings: number[][];
+    const chunkHashes = chunkTexts.map(text => sha256(text));
+    const model = this.embeddingPipeline?.getModel() ?? this.embeddingModel;
+    const cached = this.embeddingCache?.getMany(chunkHashes, model) ?? new Map<string, number[]>();
+    const embeddings: number[][] = chunkHashes.map(hash => cached.get(hash) ?? []);
+    const missing = chunkHashes
+      .map((hash, i) => (cached.has(hash) ? -1 : i))
+      .filter(i => i >= 0);
     try {
-      if (this.embeddingPipeline) {
-        const pipelineResult = await this.embeddingPipeline.embedBatch(chunkTexts);
-        embeddings = pipelineResult.embeddings;
-      } else {
-        const embedResponse = await this.llm.embed({
-          model: this.embeddingModel,
-          input: chunkTexts,
+      if (missing.length > 0) {
+        const fresh = await this.embedTexts(missing.map(i => chunkTexts[i]!));
+        missing.forEach((chunkIdx, j) => {
+          embeddings[chunkIdx] = fresh[j] ?? [];
         });
-        embeddings = embedResponse.embeddings;
+        this.embeddingCache?.putMany(
+          missing.map(i => ({ chunkHash: chunkHashes[i]!, embedding: embeddings[i]! })),
+          model,
+        );
       }
     } catch (embedErr) {
       // Embedding failed — store document metadata without vectors
@@ -165,6 +180,23 @@ export class Indexer {
       chunksCreated: vectorChunks.length,
       durationMs: Date.now() - startMs,
       deduplicated: false,
+      chunksReused: textChunks.length - missing.length,
+      chunksEmbedded: missing.length,
     };
   }
+
+  /**
+   * Embed texts in batch (prefer pipeline if available).
+   */
+  private async embedTexts(texts: string[]): Promise<number[][]> {
+    if (this.embeddingPipeline) {
+      const pipelineResult = await this.embeddingPipeline.embedBatch(texts);
+      return pipelineResult.embeddings;
+    }
+    const embedResponse = await this.llm.embed({
+      model: this.embeddingModel,
+      input: texts,
+    });
+    return embedResponse.embeddings;
+  }
 }
@@ -16,12 +16,20 @@ export interface HardwareProfile {
   tier: HardwareProfileTier;
   cpuCores: number;
   cpuArch: 'x64' | 'arm64' | 'unknown';
+  /** SIMD extensions detected at runtime (avx2, avx512f, f16c, fma, neon, dotprod) */
+  cpuFeatures?: string[];
   totalRamMb: number;
   availableRamMb: number;
   os: 'windows' | 'macos' | 'linux' | 'unknown';
   gpu: GpuInfo | null;
+  /** Every GPU detected, primary (`gpu`) first */
+  gpus?: GpuInfo[];
   /** Whether this device has enough resources to run Whisper.cpp for local STT */
   voiceCapable: boolean;
+  /** Free space on the volume holding the model directory, in MB */
+  diskFreeMb?: number;
+  /** Size of that volume, in MB */
+  diskTotalMb?: number;
 }
 
 /**
@@ -264,6 +264,15 @@ export class InferenceRouter implements LLMProvider {
     return this.embeddingModel;
   }
 
+  /**
+   * Get the provider and model currently serving primary-tier generations
+   * (BitNet if set, otherwise the reasoning provider). Used to report which
+   * backend is active.
+   */
+  getPrimaryRoute(): { provider: LLMProvider; model: string } {
+    return this.resolveProviderAndModel('primary');
+  }
+
   /**
    * Get the fast tier model name, or null if not configured.
    */
@@ -25,9 +25,11 @@ export interface NativeRuntimeBridge {
   embed(params: NativeBridgeEmbedParams): Promise<NativeBridgeEmbedResult>;
 
   /**
-   * Load a reasoning model from a GGUF file path.
+   * Load a reasoning model from a GGUF file path. `nGpuLayers` offloads that
+   * many layers to the GPU, or as many as fit with 'auto'; omitted, the model
+   * runs on CPU.
    */
-  loadModel(modelPath: string): Promise<void>;
+  loadModel(modelPath: string, nGpuLayers?: number | 'auto'): Promise<void>;
 
   /**
    * Load an embedding model from a GGUF file path.
@@ -45,6 +47,12 @@ export interface NativeRuntimeBridge {
    */
   generateFast?(params: NativeBridgeGenerateParams): Promise<NativeBridgeGenerateResult>;
 
+  /**
+   * Token ids of `text` with the reasoning model's tokenizer, e.g. to build `tokenBias`.
+   * `addBos` prepends BOS as generation does.
+   */
+  tokenize?(text: string, addBos?: boolean): Promise<number[]>;
+
   /**
    * Load a vision model (main GGUF + mmproj GGUF). Both files must exist on disk.
    */
@@ -72,34 +80,113 @@ export interface NativeRuntimeBridge {
 }
 
 export interface NativeBridgeGenerateParams {
+  /**
+   * GGUF path of the reasoning model to run on. With several resident models it is loaded
+   * (or kept warm) and picked; unset, or with a single resident model, uses the active one.
+   */
+  modelPath?: string;
+  /** A single user turn; ignored when `messages` is set. */
   prompt: string;
   systemPrompt?: string;
   maxTokens?: number;
   temperature?: number;
   stop?: string[];
+  /** Stop once the output reaches this many characters (cut at a codepoint boundary). */
+  maxChars?: number;
+  /** Bias added to these tokens' logits before sampling. Keys must each be a single token. */
+  logitBias?: Record<string, number>;
+  /** Like `logitBias`, keyed by token id (see `tokenize`). Wins for the same token; ids outside the vocabulary fail the request. */
+  tokenBias?: Record<number, number>;
+  /** Seed for sampling; the same seed, prompt and settings reproduce a generation. Unset picks a random one. */
+  seed?: number;
+  /** Top-p for the default sampling mode (reasoning default 0.95, fast 0.9). */
+  topP?: number;
+  /** Min-p for the default sampling mode (default 0.05). */
+  minP?: number;
+  /** Mirostat instead of top-p/min-p (which must then be unset). Runs after temperature. */
+  samplingMode?: { mode: 'top_p' } | { mode: 'mirostat' | 'mirostat_v2'; tau: number; eta: number };
+  /** Use flash attention for this generation; unset follows the desktop setting. Falls back if unsupported. */
+  flashAttention?: boolean;
+  /** Return the interval before each generated token as `tokenIntervalsMs`. */
+  profileTiming?: boolean;
+  /** Scheduler priority; 'high' preempts lower-priority generations between tokens. Unset uses the desktop default. */
+  priority?: 'low' | 'normal' | 'high';
+  /** Cut a prompt over the desktop's max prompt tokens to the limit instead of failing. */
+  truncatePrompt?: boolean;
+  /** Groups generations (records, sampler state) on the Rust side. */
+  sessionId?: string;
+  /**
+   * With `sessionId` (reasoning model only): keep the context resident so the next turn only
+   * prefills tokens appended to this prompt. Each live session pins a KV cache in memory.
+   */
+  residentSession?: boolean;
+  /** Context window in tokens; unset uses 4096. Clamped to the model's training context. */
+  nCtx?: number;
+  /** GBNF grammar (root rule `root`) the output must match, e.g. from `json_schema_to_gbnf`. */
+  grammar?: string;
+  /** Divide logits of recently generated tokens by this (>1 discourages loops). Unset or 1 is off. */
+  repeatPenalty?: number;
+  /** Generated tokens the penalties look at; unset uses 64, -1 is the whole context, 0 is off. */
+  repeatLastN?: number;
+  /** Subtracted from a token's logit per earlier occurrence in the window. */
+  frequencyPenalty?: number;
+  /** Subtracted from a token's logit once if it already occurred in the window. */
+  presencePenalty?: number;
+  /** Format with the chat template embedded in the GGUF; models without one keep the built-in format. */
+  applyChatTemplate?: boolean;
+  /**
+   * The whole conversation, oldest first, in place of `prompt`. Always formatted with the model's
+   * chat template (the built-in format, turn by turn, if the GGUF has none).
+   */
+  messages?: Array<{ role: string; content: string }>;
 }
 
 export interface NativeBridgeGenerateResult {
   text: string;
   tokensGenerated: number;
   durationMs: number;
+  /** `logitBias` keys that weren't a single token and were not applied. */
+  unmappedLogitBias?: string[];
+  /** With `profileTiming`: ms before each token, the first from the end of prompt prefill. */
+  tokenIntervalsMs?: number[];
+  /** Time to first token: ms from the start of the call, through prefill, to the first sampled token. */
+  firstTokenMs?: number;
+  /** Tokens in the formatted prompt. */
+  promptTokens?: number;
+  /** Time spent decoding the prompt batch. */
+  prefillMs?: number;
+  /** Time spent in the generation loop, after prefill. */
+  decodeMs?: number;
+  /** Generated tokens per second of `decodeMs`. */
+  tokensPerSecond?: number;
 }
 
 export interface NativeBridgeEmbedParams {
   input: string[];
+  /** Token window per input; unset uses 2048. Clamped to the model's training context. */
+  nCtx?: number;
+  /** Pooling override ('none' is rejected); unset uses the model's own pooling type. */
+  pooling?: 'mean' | 'cls' | 'last' | 'none';
 }
 
 export interface NativeBridgeEmbedResult {
   embeddings: number[][];
   dimensions: number;
   durationMs: number;
+  /** Inputs whose embedding had NaN/inf values; their entry in `embeddings` is empty. */
+  failedInputs?: number[];
 }
 
 export interface NativeBridgeStatus {
-  status: 'uninitialized' | 'loading' | 'ready' | 'error';
+  /** 'embedding_only': embeddings work, generation is refused until a reasoning model loads. */
+  status: 'uninitialized' | 'loading' | 'ready' | 'embedding_only' | 'error';
   reasoningModel: string | null;
   embeddingModel: string | null;
   fastModel: string | null;
   visionModel: string | null;
+  /** The runtime is `ready`, so a generation would run now. */
+  canGenerate: boolean;
+  /** An embedding request would be accepted now. */
+  canEmbed: boolean;
   error?: string;
 }
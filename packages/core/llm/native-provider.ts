@@ -62,9 +62,9 @@ export class NativeProvider implements LLMProvider {
       text: result.text,
       model: request.model || this.modelName,
       tokensUsed: {
-        prompt: 0, // Native runtime doesn't expose prompt token count yet
+        prompt: result.promptTokens ?? 0,
         completion: result.tokensGenerated,
-        total: result.tokensGenerated,
+        total: (result.promptTokens ?? 0) + result.tokensGenerated,
       },
       durationMs: result.durationMs,
     };
@@ -77,21 +77,21 @@ export class NativeProvider implements LLMProvider {
       : request.messages;
 
     // Extract system prompt and user messages separately so the Rust side
-    // can apply the correct ChatML template. DO NOT double-template here —
-    // native_runtime.rs wraps with <|im_start|>system/user/assistant<|im_end|>.
+    // can apply the correct chat template. DO NOT double-template here —
+    // native_runtime.rs formats with the built-in template, or the GGUF's
+    // own with `applyChatTemplate`.
     const systemMessages = messages.filter(m => m.role === 'system');
     const nonSystemMessages = messages.filter(m => m.role !== 'system');
     const systemPrompt = systemMessages.map(m => m.content).join('\n\n') || undefined;
 
-    // Build the user/assistant turns into a single prompt for the Rust ChatML template.
-    // The Rust side wraps the prompt as the "user" turn, so we concatenate history + latest.
-    const prompt = nonSystemMessages.map(m => {
-      if (m.role === 'assistant') return `Assistant: ${m.content}`;
-      return m.content;
-    }).join('\n\n');
+    // A single turn goes as the prompt; with history, the Rust side renders
+    // every user/assistant turn through the model's chat template.
+    const multiTurn = nonSystemMessages.length > 1;
+    const prompt = multiTurn ? '' : nonSystemMessages.map(m => m.content).join('\n\n');
 
     const result = await this.bridge.generate({
       prompt,
+      ...(multiTurn && { messages: nonSystemMessages.map(m => ({ role: m.role, content: m.content })) }),
       systemPrompt,
       maxTokens: request.maxTokens,
       temperature: request.temperature,
@@ -115,9 +115,9 @@ export class NativeProvider implements LLMProvider {
       },
       model: request.model || this.modelName,
       tokensUsed: {
-        prompt: 0,
+        prompt: result.promptTokens ?? 0,
         completion: result.tokensGenerated,
-        total: result.tokensGenerated,
+        total: (result.promptTokens ?? 0) + result.tokensGenerated,
       },
       durationMs: result.durationMs,
       toolCalls,
@@ -56,7 +56,7 @@ pub struct llama_model_params {
     pub main_gpu: i32,
     pub tensor_split: *const c_float,
     pub rpc_servers: *const c_char,
-    pub progress_callback: *const c_void, // function pointer, we never set it
+    pub progress_callback: llama_progress_callback,
     pub progress_callback_user_data: *mut c_void,
     pub kv_overrides: *const c_void, // const struct llama_model_kv_override *
     pub vocab_only: bool,
@@ -114,8 +114,49 @@ pub struct llama_sampler_chain_params {
     pub no_perf: bool,
 }
 
+/// llama_chat_message — one turn passed to llama_chat_apply_template.
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct llama_chat_message {
+    pub role: *const c_char,
+    pub content: *const c_char,
+}
+
+/// llama_logit_bias — bias added to one token's logit by the logit-bias sampler.
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct llama_logit_bias {
+    pub token: llama_token,
+    pub bias: c_float,
+}
+
+/// llama_token_data — one sampling candidate.
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct llama_token_data {
+    pub id: llama_token,
+    pub logit: c_float,
+    pub p: c_float,
+}
+
+/// llama_token_data_array — candidates passed to llama_sampler_apply.
+#[repr(C)]
+#[derive(Debug)]
+pub struct llama_token_data_array {
+    pub data: *mut llama_token_data,
+    pub size: usize,
+    /// Index into `data` of the selected token, -1 if none.
+    pub selected: i64,
+    pub sorted: bool,
+}
+
 // ─── Logging ─────────────────────────────────────────────────────────────────
 
+/// llama_progress_callback — called during model load with the fraction loaded.
+/// Returning false aborts the load.
+pub type llama_progress_callback =
+    Option<unsafe extern "C" fn(progress: c_float, user_data: *mut c_void) -> bool>;
+
 /// ggml_log_callback type (for llama_log_set)
 pub type ggml_log_callback =
     Option<unsafe extern "C" fn(level: c_int, text: *const c_char, user_data: *mut c_void)>;
@@ -143,6 +184,29 @@ extern "C" {
     // Model info
     pub fn llama_n_embd(model: *const llama_model) -> i32;
     pub fn llama_model_n_params(model: *const llama_model) -> u64;
+    pub fn llama_n_layer(model: *const llama_model) -> i32;
+    pub fn llama_n_vocab(model: *const llama_model) -> i32;
+    pub fn llama_vocab_type(model: *const llama_model) -> c_int; // enum llama_vocab_type
+    pub fn llama_model_meta_val_str(
+        model: *const llama_model,
+        key: *const c_char,
+        buf: *mut c_char,
+        buf_size: usize,
+    ) -> i32;
+
+    // Chat templates
+    pub fn llama_chat_apply_template(
+        model: *const llama_model,
+        tmpl: *const c_char,
+        chat: *const llama_chat_message,
+        n_msg: usize,
+        add_ass: bool,
+        buf: *mut c_char,
+        length: i32,
+    ) -> i32;
+
+    // System capabilities
+    pub fn llama_supports_gpu_offload() -> bool;
 
     // Context lifecycle
     pub fn llama_new_context_with_model(
@@ -166,6 +230,23 @@ extern "C" {
 
     // KV cache
     pub fn llama_kv_cache_clear(ctx: *mut llama_context);
+    pub fn llama_kv_cache_seq_rm(
+        ctx: *mut llama_context,
+        seq_id: llama_seq_id,
+        p0: llama_pos,
+        p1: llama_pos,
+    ) -> bool;
+    pub fn llama_kv_cache_seq_add(
+        ctx: *mut llama_context,
+        seq_id: llama_seq_id,
+        p0: llama_pos,
+        p1: llama_pos,
+        delta: llama_pos,
+    );
+    pub fn llama_n_ctx(ctx: *const llama_context) -> u32;
+
+    // Logits
+    pub fn llama_get_logits_ith(ctx: *mut llama_context, i: i32) -> *mut c_float;
 
     // Embeddings
     pub fn llama_get_embeddings(ctx: *mut llama_context) -> *mut c_float;
@@ -193,10 +274,13 @@ extern "C" {
         special: bool,
     ) -> i32;
 
+    pub fn llama_token_get_text(model: *const llama_model, token: llama_token) -> *const c_char;
+
     // Special tokens
     pub fn llama_token_is_eog(model: *const llama_model, token: llama_token) -> bool;
     pub fn llama_token_bos(model: *const llama_model) -> llama_token;
     pub fn llama_token_eos(model: *const llama_model) -> llama_token;
+    pub fn llama_token_nl(model: *const llama_model) -> llama_token;
 
     // Sampler chain
     pub fn llama_sampler_chain_init(params: llama_sampler_chain_params) -> *mut llama_sampler;
@@ -209,6 +293,7 @@ extern "C" {
         idx: i32,
     ) -> llama_token;
     pub fn llama_sampler_accept(smpl: *mut llama_sampler, token: llama_token);
+    pub fn llama_sampler_apply(smpl: *mut llama_sampler, cur_p: *mut llama_token_data_array);
     pub fn llama_sampler_free(smpl: *mut llama_sampler);
 
     // Sampler constructors
@@ -216,6 +301,28 @@ extern "C" {
     pub fn llama_sampler_init_min_p(p: c_float, min_keep: usize) -> *mut llama_sampler;
     pub fn llama_sampler_init_temp(t: c_float) -> *mut llama_sampler;
     pub fn llama_sampler_init_dist(seed: u32) -> *mut llama_sampler;
+    pub fn llama_sampler_init_greedy() -> *mut llama_sampler;
+    pub fn llama_sampler_init_grammar(
+        model: *const llama_model,
+        grammar_str: *const c_char,
+        grammar_root: *const c_char,
+    ) -> *mut llama_sampler;
+    pub fn llama_sampler_init_penalties(
+        n_vocab: i32,
+        special_eos_id: llama_token,
+        linefeed_id: llama_token,
+        penalty_last_n: i32,
+        penalty_repeat: c_float,
+        penalty_freq: c_float,
+        penalty_present: c_float,
+        penalize_nl: bool,
+        ignore_eos: bool,
+    ) -> *mut llama_sampler;
+    pub fn llama_sampler_init_logit_bias(
+        n_vocab: i32,
+        n_logit_bias: i32,
+        logit_bias: *const llama_logit_bias,
+    ) -> *mut llama_sampler;
 }
 
 // ─── Vision FFI (clip.h + llava.h) ──────────────────────────────────────────
@@ -17,11 +17,12 @@
 
 pub mod ffi;
 
-use std::ffi::CString;
+use std::ffi::{CStr, CString};
 use std::num::NonZeroU32;
-use std::os::raw::c_char;
+use std::os::raw::{c_char, c_float, c_int, c_void};
 use std::path::Path;
 use std::ptr;
+use std::sync::Mutex;
 
 // Re-export the token type
 pub type LlamaToken = ffi::llama_token;
@@ -35,6 +36,21 @@ pub enum AddBos {
     Never,
 }
 
+// ─── PoolingType ─────────────────────────────────────────────────────────────
+
+/// How an embedding context combines per-token embeddings into one vector per
+/// sequence (llama_pooling_type).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PoolingType {
+    /// No pooling: only per-token embeddings, no sequence embedding.
+    None = 0,
+    Mean = 1,
+    /// The first token's (CLS) embedding.
+    Cls = 2,
+    /// The last token's embedding.
+    Last = 3,
+}
+
 // ─── LlamaBackend ────────────────────────────────────────────────────────────
 
 /// Global llama.cpp backend. Init once, free on drop.
@@ -53,12 +69,27 @@ impl LlamaBackend {
         })
     }
 
+    /// Whether this build has a GPU backend that can take offloaded layers.
+    pub fn supports_gpu_offload(&self) -> bool {
+        unsafe { ffi::llama_supports_gpu_offload() }
+    }
+
     /// Suppress all log output from llama.cpp.
     pub fn void_logs(&mut self) {
         unsafe {
             ffi::llama_log_set(Some(null_log_callback), ptr::null_mut());
         }
     }
+
+    /// Suppress llama.cpp's log output like `void_logs`, but keep the most
+    /// recent lines for `take_captured_logs`. They are the only report of what
+    /// the backend actually did: the GPU device it initialized and how many
+    /// layers a load offloaded.
+    pub fn capture_logs(&mut self) {
+        unsafe {
+            ffi::llama_log_set(Some(capture_log_callback), ptr::null_mut());
+        }
+    }
 }
 
 impl Drop for LlamaBackend {
@@ -69,6 +100,42 @@ impl Drop for LlamaBackend {
     }
 }
 
+/// Log lines `capture_logs` keeps; older ones are dropped.
+const MAX_CAPTURED_LOG_LINES: usize = 512;
+
+/// Complete lines captured since the last `take_captured_logs`, and the
+/// unterminated line being written (llama.cpp logs lines in pieces).
+static CAPTURED_LOGS: Mutex<(Vec<String>, String)> = Mutex::new((Vec::new(), String::new()));
+
+/// Take the llama.cpp log lines captured since the last call, oldest first.
+/// Empty unless `LlamaBackend::capture_logs` is active.
+pub fn take_captured_logs() -> Vec<String> {
+    let mut captured = CAPTURED_LOGS.lock().unwrap_or_else(|e| e.into_inner());
+    std::mem::take(&mut captured.0)
+}
+
+/// Log callback used by capture_logs(). Runs on whichever thread logs.
+unsafe extern "C" fn capture_log_callback(
+    _level: std::os::raw::c_int,
+    text: *const c_char,
+    _user_data: *mut std::os::raw::c_void,
+) {
+    if text.is_null() {
+        return;
+    }
+    let text = CStr::from_ptr(text).to_string_lossy();
+    let mut captured = CAPTURED_LOGS.lock().unwrap_or_else(|e| e.into_inner());
+    let (lines, partial) = &mut *captured;
+    partial.push_str(&text);
+    while let Some(end) = partial.find('\n') {
+        let line: String = partial.drain(..=end).collect();
+        if lines.len() == MAX_CAPTURED_LOG_LINES {
+            lines.remove(0);
+        }
+        lines.push(line.trim_end().to_string());
+    }
+}
+
 /// No-op log callback used by void_logs().
 unsafe extern "C" fn null_log_callback(
     _level: std::os::raw::c_int,
@@ -80,15 +147,21 @@ unsafe extern "C" fn null_log_callback(
 
 // ─── LlamaModelParams ────────────────────────────────────────────────────────
 
+/// Called during model loading with the fraction loaded, 0.0 to 1.0. Returning
+/// false aborts the load, which then fails.
+pub type ProgressCallback = Box<dyn Fn(f32) -> bool + Send + Sync>;
+
 /// Builder for model loading parameters.
 pub struct LlamaModelParams {
     pub(crate) inner: ffi::llama_model_params,
+    progress: Option<ProgressCallback>,
 }
 
 impl Default for LlamaModelParams {
     fn default() -> Self {
         Self {
             inner: unsafe { ffi::llama_model_default_params() },
+            progress: None,
         }
     }
 }
@@ -99,6 +172,26 @@ impl LlamaModelParams {
         self.inner.n_gpu_layers = n;
         self
     }
+
+    /// Load only the vocabulary and hyperparameters, skipping the weights.
+    pub fn with_vocab_only(mut self, vocab_only: bool) -> Self {
+        self.inner.vocab_only = vocab_only;
+        self
+    }
+
+    /// Report load progress to `callback` instead of llama.cpp's default dots.
+    /// The load is aborted when it returns false.
+    pub fn with_progress_callback(mut self, callback: impl Fn(f32) -> bool + Send + Sync + 'static) -> Self {
+        self.progress = Some(Box::new(callback));
+        self
+    }
+}
+
+/// Forwards llama.cpp's load progress to the `ProgressCallback` in `user_data`.
+unsafe extern "C" fn progress_trampoline(progress: c_float, user_data: *mut c_void) -> bool {
+    let callback = &*(user_data as *const ProgressCallback);
+    // A panic must not unwind into C; the load carries on regardless.
+    std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| callback(progress))).unwrap_or(true)
 }
 
 // ─── LlamaModel ──────────────────────────────────────────────────────────────
@@ -126,7 +219,13 @@ impl LlamaModel {
         let c_path =
             CString::new(path_str).map_err(|e| format!("Invalid path string: {}", e))?;
 
-        let ptr = unsafe { ffi::llama_load_model_from_file(c_path.as_ptr(), params.inner) };
+        let mut inner = params.inner;
+        if let Some(callback) = &params.progress {
+            // `params` outlives the call, so the pointer stays valid while loading.
+            inner.progress_callback = Some(progress_trampoline);
+            inner.progress_callback_user_data = callback as *const ProgressCallback as *mut c_void;
+        }
+        let ptr = unsafe { ffi::llama_load_model_from_file(c_path.as_ptr(), inner) };
 
         if ptr.is_null() {
             Err(format!("Failed to load model from {:?}", path))
@@ -241,6 +340,16 @@ impl LlamaModel {
         buf
     }
 
+    /// Raw vocabulary text of a token (no detokenization; special tokens included).
+    pub fn token_text(&self, token: LlamaToken) -> &[u8] {
+        let ptr = unsafe { ffi::llama_token_get_text(self.ptr, token) };
+        if ptr.is_null() {
+            &[]
+        } else {
+            unsafe { CStr::from_ptr(ptr) }.to_bytes()
+        }
+    }
+
     /// Check if a token is end-of-generation.
     pub fn is_eog_token(&self, token: LlamaToken) -> bool {
         unsafe { ffi::llama_token_is_eog(self.ptr, token) }
@@ -255,6 +364,115 @@ impl LlamaModel {
     pub fn n_embd(&self) -> i32 {
         unsafe { ffi::llama_n_embd(self.ptr) }
     }
+
+    /// Get the number of transformer layers (repeating blocks) in the model.
+    pub fn n_layer(&self) -> i32 {
+        unsafe { ffi::llama_n_layer(self.ptr) }
+    }
+
+    /// Get the vocabulary size.
+    pub fn n_vocab(&self) -> i32 {
+        unsafe { ffi::llama_n_vocab(self.ptr) }
+    }
+
+    /// Get the tokenizer type (llama_vocab_type: SPM, BPE, WPM, ...).
+    pub fn vocab_type(&self) -> i32 {
+        unsafe { ffi::llama_vocab_type(self.ptr) }
+    }
+
+    /// Beginning-of-sequence token id.
+    pub fn token_bos(&self) -> LlamaToken {
+        unsafe { ffi::llama_token_bos(self.ptr) }
+    }
+
+    /// End-of-sequence token id.
+    pub fn token_eos(&self) -> LlamaToken {
+        unsafe { ffi::llama_token_eos(self.ptr) }
+    }
+
+    /// Newline token id.
+    pub fn token_nl(&self) -> LlamaToken {
+        unsafe { ffi::llama_token_nl(self.ptr) }
+    }
+
+    /// GGUF metadata value for `key` as a string, or `None` if the key is absent.
+    pub fn meta_val_str(&self, key: &str) -> Option<String> {
+        let c_key = CString::new(key).ok()?;
+        let mut buf = vec![0u8; 256];
+        loop {
+            let n = unsafe {
+                ffi::llama_model_meta_val_str(self.ptr, c_key.as_ptr(), buf.as_mut_ptr() as *mut c_char, buf.len())
+            };
+            if n < 0 {
+                return None;
+            }
+            let n = n as usize;
+            if n < buf.len() {
+                buf.truncate(n);
+                return Some(String::from_utf8_lossy(&buf).into_owned());
+            }
+            // Value was truncated — n excludes the terminator.
+            buf.resize(n + 1, 0);
+        }
+    }
+
+    /// Jinja chat template embedded in the GGUF (`tokenizer.chat_template`).
+    pub fn chat_template(&self) -> Option<String> {
+        self.meta_val_str("tokenizer.chat_template")
+    }
+
+    /// Render `messages` (role, content) with llama.cpp's built-in template engine.
+    /// `tmpl` of `None` uses the model's embedded template, falling back to ChatML
+    /// if it has none. Errors if the template is not one llama.cpp recognizes.
+    pub fn apply_chat_template(
+        &self,
+        tmpl: Option<&str>,
+        messages: &[(&str, &str)],
+        add_assistant: bool,
+    ) -> Result<String, String> {
+        let c_tmpl = match tmpl {
+            Some(t) => Some(CString::new(t).map_err(|_| "Chat template contains a NUL byte".to_string())?),
+            None => None,
+        };
+        let owned: Vec<(CString, CString)> = messages
+            .iter()
+            .map(|(role, content)| Ok((CString::new(*role)?, CString::new(*content)?)))
+            .collect::<Result<_, std::ffi::NulError>>()
+            .map_err(|_| "Chat message contains a NUL byte".to_string())?;
+        let chat: Vec<ffi::llama_chat_message> = owned
+            .iter()
+            .map(|(role, content)| ffi::llama_chat_message {
+                role: role.as_ptr(),
+                content: content.as_ptr(),
+            })
+            .collect();
+        let tmpl_ptr = c_tmpl.as_ref().map_or(std::ptr::null(), |t| t.as_ptr());
+
+        let content_len: usize = messages.iter().map(|(r, c)| r.len() + c.len()).sum();
+        let mut buf = vec![0u8; (content_len * 2).max(256)];
+        loop {
+            let n = unsafe {
+                ffi::llama_chat_apply_template(
+                    self.ptr,
+                    tmpl_ptr,
+                    chat.as_ptr(),
+                    chat.len(),
+                    add_assistant,
+                    buf.as_mut_ptr() as *mut c_char,
+                    buf.len() as i32,
+                )
+            };
+            if n < 0 {
+                return Err("Chat template is not supported by llama.cpp's template engine".to_string());
+            }
+            let n = n as usize;
+            if n <= buf.len() {
+                buf.truncate(n);
+                return String::from_utf8(buf).map_err(|_| "Rendered chat template is not valid UTF-8".to_string());
+            }
+            buf.resize(n, 0);
+        }
+    }
 }
 
 impl Drop for LlamaModel {
@@ -270,6 +488,7 @@ impl Drop for LlamaModel {
 // ─── LlamaContextParams ─────────────────────────────────────────────────────
 
 /// Builder for inference context parameters.
+#[derive(Clone)]
 pub struct LlamaContextParams {
     pub(crate) inner: ffi::llama_context_params,
 }
@@ -296,6 +515,31 @@ impl LlamaContextParams {
         self.inner.embeddings = embeddings;
         self
     }
+
+    /// Set the logical (n_batch) and physical (n_ubatch) max tokens per decode call.
+    pub fn with_n_batch(mut self, n_batch: u32) -> Self {
+        self.inner.n_batch = n_batch;
+        self.inner.n_ubatch = n_batch;
+        self
+    }
+
+    /// Override the model's pooling type for sequence embeddings.
+    pub fn with_pooling_type(mut self, pooling: PoolingType) -> Self {
+        self.inner.pooling_type = pooling as c_int;
+        self
+    }
+
+    /// Set the maximum number of distinct sequences the context can hold.
+    pub fn with_n_seq_max(mut self, n_seq_max: u32) -> Self {
+        self.inner.n_seq_max = n_seq_max;
+        self
+    }
+
+    /// Use flash attention (fused attention kernel). Off by default in llama.cpp.
+    pub fn with_flash_attn(mut self, enabled: bool) -> Self {
+        self.inner.flash_attn = enabled;
+        self
+    }
 }
 
 // ─── LlamaContext ────────────────────────────────────────────────────────────
@@ -336,6 +580,33 @@ impl LlamaContext {
         self.ptr
     }
 
+    /// Remove positions [p0, p1) of `seq_id` from the KV cache. p1 < 0 means to the end.
+    /// Used to roll back rejected speculative tokens.
+    pub fn kv_cache_seq_rm(&mut self, seq_id: i32, p0: i32, p1: i32) -> bool {
+        unsafe { ffi::llama_kv_cache_seq_rm(self.ptr, seq_id, p0, p1) }
+    }
+
+    /// Shift positions [p0, p1) of `seq_id` by `delta`. p1 < 0 means to the end.
+    /// Used after removing a span so the remaining positions stay contiguous.
+    pub fn kv_cache_seq_add(&mut self, seq_id: i32, p0: i32, p1: i32, delta: i32) {
+        unsafe { ffi::llama_kv_cache_seq_add(self.ptr, seq_id, p0, p1, delta) }
+    }
+
+    /// Context window size in tokens.
+    pub fn n_ctx(&self) -> u32 {
+        unsafe { ffi::llama_n_ctx(self.ptr) }
+    }
+
+    /// Logits of the batch output at `idx` (-1 for the last), one per vocabulary
+    /// entry. `None` if that output didn't request logits.
+    pub fn logits_ith(&self, idx: i32, n_vocab: i32) -> Option<&[f32]> {
+        let ptr = unsafe { ffi::llama_get_logits_ith(self.ptr, idx) };
+        if ptr.is_null() || n_vocab <= 0 {
+            return None;
+        }
+        Some(unsafe { std::slice::from_raw_parts(ptr, n_vocab as usize) })
+    }
+
     /// Clear the KV cache (needed between embedding batches).
     pub fn clear_kv_cache(&mut self) {
         unsafe {
@@ -496,6 +767,108 @@ impl LlamaSampler {
         }
     }
 
+    /// Create a greedy (argmax) sampler.
+    pub fn greedy() -> Self {
+        LlamaSampler {
+            ptr: unsafe { ffi::llama_sampler_init_greedy() },
+            _is_chain: false,
+        }
+    }
+
+    /// Create a sampler that only allows tokens continuing a string accepted by
+    /// the GBNF `grammar`, starting from rule `root`. Errors if llama.cpp
+    /// rejects the grammar.
+    pub fn grammar(model: &LlamaModel, grammar: &str, root: &str) -> Result<Self, String> {
+        let c_grammar = CString::new(grammar).map_err(|e| format!("Invalid grammar string: {}", e))?;
+        let c_root = CString::new(root).map_err(|e| format!("Invalid grammar root: {}", e))?;
+        let ptr = unsafe { ffi::llama_sampler_init_grammar(model.ptr, c_grammar.as_ptr(), c_root.as_ptr()) };
+        if ptr.is_null() {
+            return Err("llama.cpp failed to parse the grammar".to_string());
+        }
+        Ok(LlamaSampler { ptr, _is_chain: false })
+    }
+
+    /// Create a repetition penalty sampler over the last `last_n` accepted
+    /// tokens: logits of repeated tokens are divided by `repeat` (multiplied
+    /// when negative), then lowered by `frequency` per occurrence and by
+    /// `presence` once. The `newline` token is left unpenalized.
+    pub fn penalties(
+        n_vocab: i32,
+        newline: LlamaToken,
+        last_n: i32,
+        repeat: f32,
+        frequency: f32,
+        presence: f32,
+    ) -> Self {
+        LlamaSampler {
+            ptr: unsafe {
+                // No EOS id needed: it's only read when ignoring EOS.
+                ffi::llama_sampler_init_penalties(n_vocab, -1, newline, last_n, repeat, frequency, presence, false, false)
+            },
+            _is_chain: false,
+        }
+    }
+
+    /// Create a sampler that adds `bias` to the logit of each `(token, bias)` pair.
+    /// llama.cpp copies the biases, so `biases` need not outlive the sampler.
+    pub fn logit_bias(n_vocab: i32, biases: &[(LlamaToken, f32)]) -> Self {
+        let entries: Vec<ffi::llama_logit_bias> = biases
+            .iter()
+            .map(|&(token, bias)| ffi::llama_logit_bias { token, bias })
+            .collect();
+        LlamaSampler {
+            ptr: unsafe {
+                ffi::llama_sampler_init_logit_bias(n_vocab, entries.len() as i32, entries.as_ptr())
+            },
+            _is_chain: false,
+        }
+    }
+
+    /// Run the sampler over raw logits (indexed by token id) instead of a
+    /// context's output, returning the selected token. `None` if the sampler
+    /// selects nothing (e.g. a chain without a final selecting stage).
+    pub fn select_from_logits(&mut self, logits: &[f32]) -> Option<LlamaToken> {
+        self.apply_to_logits(logits, |array| {
+            usize::try_from(array.selected)
+                .ok()
+                .filter(|&i| i < array.size)
+                .map(|i| unsafe { (*array.data.add(i)).id })
+        })
+    }
+
+    /// Run a filtering sampler (one without a selecting stage) over raw logits
+    /// and return the surviving `(token, logit)` candidates, logits as left by
+    /// the chain (e.g. temperature-scaled).
+    pub fn filter_logits(&mut self, logits: &[f32]) -> Vec<(LlamaToken, f32)> {
+        self.apply_to_logits(logits, |array| {
+            (0..array.size)
+                .map(|i| {
+                    let data = unsafe { &*array.data.add(i) };
+                    (data.id, data.logit)
+                })
+                .collect()
+        })
+    }
+
+    fn apply_to_logits<R>(&mut self, logits: &[f32], read: impl FnOnce(&ffi::llama_token_data_array) -> R) -> R {
+        let mut candidates: Vec<ffi::llama_token_data> = logits
+            .iter()
+            .enumerate()
+            .map(|(id, &logit)| ffi::llama_token_data { id: id as LlamaToken, logit, p: 0.0 })
+            .collect();
+        let mut array = ffi::llama_token_data_array {
+            data: candidates.as_mut_ptr(),
+            size: candidates.len(),
+            selected: -1,
+            sorted: false,
+        };
+        unsafe {
+            ffi::llama_sampler_apply(self.ptr, &mut array);
+        }
+        // Samplers reorder and shrink the array in place, so read through it.
+        read(&array)
+    }
+
     /// Sample the next token from the context at the given logit index.
     /// Use idx = -1 for the last token in the batch.
     pub fn sample(&mut self, ctx: &LlamaContext, idx: i32) -> LlamaToken {
@@ -30,14 +30,18 @@ try {
 //   Request (stdin):  {"id": 1, "method": "send_message", "params": {"message": "..."}}
 //   Response (stdout): {"id": 1, "result": {...}}
 //   Error (stdout):    {"id": 1, "error": "..."}
-//   Event (stdout):    {"event": "chat-token", "data": "..."}
+//   Event (stdout):    {"event": "chat-token", "data": {"responseId": "...", "delta": "...", "done": false}}
 //
 // The sidecar is spawned by the Rust backend on app startup and killed on shutdown.
 // All communication is local — no network access from this process except to
 // localhost Ollama (via @semblance/core's OllamaProvider, which enforces localhost-only).
 
 import { createInterface } from 'node:readline';
-import { join } from 'node:path';
+import { lookup } from 'node:dns/promises';
+import { connect as netConnect } from 'node:net';
+import { connect as tlsConnect } from 'node:tls';
+import { AsyncLocalStorage } from 'node:async_hooks';
+import { basename, join } from 'node:path';
 import { homedir, hostname, totalmem } from 'node:os';
 import { mkdirSync, existsSync, readFileSync } from 'node:fs';
 
@@ -70,6 +74,7 @@ import { extractLicenseKey } from '../../../core/premium/license-email-detector.
 
 // IP Adapter Registry — runtime access to @semblance/dr implementations
 import { ipAdapters } from '../../../core/extensions/ip-adapter-registry.js';
+import type { ParsedTransaction, RecurringCharge } from '../../../core/finance/interfaces.js';
 
 // Step 7 imports (finance moved to @semblance/dr — access via ipAdapters)
 import { EscalationEngine } from '../../../core/agent/autonomy-escalation.js';
@@ -170,7 +175,7 @@ import { generateSovereigntyReport, verifySovereigntyReport, renderSovereigntyRe
 import type { SovereigntyReport } from '../../../core/reporting/sovereignty-report.js';
 
 // Model download imports
-import { getModelsForTier, getEmbeddingModel, getRecommendedReasoningModel, getModelById, MODEL_CATALOG, BITNET_MODEL_CATALOG, getRecommendedBitNetModel, getBitNetModelsForTier, getAnyModelById, getFastTierModel, getRecommendedVisionModel } from '../../../core/llm/model-registry.js';
+import { getModelsForTier, getTotalDownloadSize, getEmbeddingModel, getRecommendedReasoningModel, getModelById, MODEL_CATALOG, BITNET_MODEL_CATALOG, getRecommendedBitNetModel, getBitNetModelsForTier, getAnyModelById, getFastTierModel, getRecommendedVisionModel } from '../../../core/llm/model-registry.js';
 import type { ModelRegistryEntry } from '../../../core/llm/model-registry.js';
 import { getModelsDir, getModelPath, isModelDownloaded, getModelFileSize, getBitNetModelsDir, getBitNetModelPath, isBitNetModelDownloaded, listDownloadedBitNetModels } from '../../../core/llm/model-storage.js';
 import { WHISPER_MODELS } from '../../../core/voice/whisper-model-manager.js';
@@ -222,20 +227,26 @@ process.on('unhandledRejection', (reason) => {
 
 // ─── NDJSON Protocol ──────────────────────────────────────────────────────────
 
-function emit(event: string, data: unknown): void {
-  process.stdout.write(JSON.stringify({ event, data }) + '\n');
+/** Returns false when stdout's buffer is full (the host isn't keeping up). */
+function emit(event: string, data: unknown): boolean {
+  return process.stdout.write(JSON.stringify({ event, data }) + '\n');
 }
 
 function respond(id: number | string, result: unknown): void {
   process.stdout.write(JSON.stringify({ id, result }) + '\n');
 }
 
-function respondError(id: number | string, error: string): void {
-  process.stdout.write(JSON.stringify({ id, error }) + '\n');
+/** `detail`: the Rust error a failed callback rejected with, handed back so it keeps its kind. */
+function respondError(id: number | string, error: string, detail?: CallbackErrorDetail): void {
+  process.stdout.write(JSON.stringify(detail ? { id, error, detail } : { id, error }) + '\n');
 }
 
+// Most recent provider transition, kept in memory for get_active_inference_backend.
+let lastProviderTransition: { from: string; to: string; model: string; reason: string; at: string } | null = null;
+
 /** Log a provider transition to the audit trail for user visibility. */
 function logProviderTransition(from: string, to: string, model: string, reason: string): void {
+  lastProviderTransition = { from, to, model, reason, at: new Date().toISOString() };
   if (!gateway) return;
   try {
     const trail = gateway.getAuditTrail();
@@ -270,7 +281,8 @@ function logProviderTransition(from: string, to: string, model: string, reason:
 
 // Callback protocol extracted to ndjson-callback.ts for testability.
 // Re-exported here for backward compatibility within bridge.ts.
-import { createCallbackProtocol } from './ndjson-callback';
+import { CallbackError, createCallbackProtocol, type CallbackErrorDetail } from './ndjson-callback';
+import { createIndexingControl } from './indexing-control';
 
 const CALLBACK_TIMEOUT_MS = 300_000; // 5 minutes — CPU inference on 7B model is slow
 
@@ -290,7 +302,9 @@ let prefsDb: Database.Database | null = null;
 let credentialStore: CredentialStore | null = null;
 let emailAdapter: EmailAdapter | null = null;
 let calendarAdapter: CalendarAdapter | null = null;
-let indexingInProgress = false;
+const indexingControl = createIndexingControl((state, previous) =>
+  emit('indexing-state-changed', { state, previous }),
+);
 let currentConversationId: string | null = null;
 let dataDir = '';
 let documentsDb: Database.Database | null = null;
@@ -452,6 +466,13 @@ const importHistory: Array<{
 // (knowledge graph embeddings, orchestrator, model management) uses this
 // instead of requiring Ollama.
 
+/**
+ * Scheduler priority for native generations started inside `run()`. Chat turns
+ * run as 'high' so they preempt background work (proactive engine, digests);
+ * everything else falls back to the desktop default.
+ */
+const generationPriority = new AsyncLocalStorage<'low' | 'normal' | 'high'>();
+
 const nativeRuntimeBridge: NativeRuntimeBridge = {
   async generate(params) {
     const sysLen = (params.systemPrompt ?? '').length;
@@ -461,16 +482,45 @@ const nativeRuntimeBridge: NativeRuntimeBridge = {
       console.error(`[sidecar] WARNING: very large prompt (${sysLen + promptLen} chars) — may exceed context window`);
     }
     const result = await sendCallback('native_generate', {
+      model_path: params.modelPath ?? '',
       prompt: params.prompt,
       system_prompt: params.systemPrompt ?? '',
       max_tokens: params.maxTokens ?? 512,
       temperature: params.temperature ?? 0.7,
       stop: params.stop ?? ['<|im_end|>', '<|endoftext|>'],
-    }) as { text: string; tokens_generated: number; duration_ms: number };
+      max_chars: params.maxChars,
+      logit_bias: params.logitBias,
+      token_bias: params.tokenBias,
+      seed: params.seed,
+      top_p: params.topP,
+      min_p: params.minP,
+      sampling_mode: params.samplingMode,
+      flash_attention: params.flashAttention,
+      profile_timing: params.profileTiming,
+      priority: params.priority ?? generationPriority.getStore(),
+      truncate_prompt: params.truncatePrompt,
+      n_ctx: params.nCtx,
+      grammar: params.grammar,
+      repeat_penalty: params.repeatPenalty,
+      repeat_last_n: params.repeatLastN,
+      frequency_penalty: params.frequencyPenalty,
+      presence_penalty: params.presencePenalty,
+      apply_chat_template: params.applyChatTemplate,
+      messages: params.messages,
+      session_id: params.sessionId,
+      resident_session: params.residentSession,
+    }) as { text: string; tokens_generated: number; duration_ms: number; unmapped_logit_bias?: string[]; token_intervals_ms?: number[]; first_token_ms?: number; prompt_tokens?: number; prefill_ms?: number; decode_ms?: number; tokens_per_second?: number };
     return {
       text: result.text,
       tokensGenerated: result.tokens_generated,
       durationMs: result.duration_ms,
+      unmappedLogitBias: result.unmapped_logit_bias,
+      tokenIntervalsMs: result.token_intervals_ms,
+      firstTokenMs: result.first_token_ms,
+      promptTokens: result.prompt_tokens,
+      prefillMs: result.prefill_ms,
+      decodeMs: result.decode_ms,
+      tokensPerSecond: result.tokens_per_second,
     };
   },
 
@@ -483,16 +533,22 @@ const nativeRuntimeBridge: NativeRuntimeBridge = {
     const result = await sendCallback('native_embed', {
       model_path: '',
       input: params.input,
-    }) as { embeddings: number[][]; dimensions: number; duration_ms: number };
+      n_ctx: params.nCtx,
+      pooling: params.pooling,
+    }) as { embeddings: number[][]; dimensions: number; duration_ms: number; failed_inputs?: number[] };
+    if (result.failed_inputs?.length) {
+      console.error(`[sidecar] native_embed: ${result.failed_inputs.length} embedding(s) had NaN/inf values and were skipped`);
+    }
     return {
       embeddings: result.embeddings,
       dimensions: result.dimensions,
       durationMs: result.duration_ms,
+      failedInputs: result.failed_inputs,
     };
   },
 
-  async loadModel(modelPath: string) {
-    await sendCallback('native_load_model', { model_path: modelPath, model_type: 'reasoning' });
+  async loadModel(modelPath: string, nGpuLayers?: number | 'auto') {
+    await sendCallback('native_load_model', { model_path: modelPath, model_type: 'reasoning', n_gpu_layers: nGpuLayers });
   },
 
   async loadEmbeddingModel(modelPath: string) {
@@ -510,11 +566,37 @@ const nativeRuntimeBridge: NativeRuntimeBridge = {
       max_tokens: params.maxTokens ?? 256,
       temperature: params.temperature ?? 0.3,
       stop: params.stop ?? ['<|im_end|>', '<|endoftext|>'],
-    }) as { text: string; tokens_generated: number; duration_ms: number };
+      max_chars: params.maxChars,
+      logit_bias: params.logitBias,
+      token_bias: params.tokenBias,
+      seed: params.seed,
+      top_p: params.topP,
+      min_p: params.minP,
+      sampling_mode: params.samplingMode,
+      flash_attention: params.flashAttention,
+      profile_timing: params.profileTiming,
+      priority: params.priority ?? generationPriority.getStore(),
+      truncate_prompt: params.truncatePrompt,
+      n_ctx: params.nCtx,
+      grammar: params.grammar,
+      repeat_penalty: params.repeatPenalty,
+      repeat_last_n: params.repeatLastN,
+      frequency_penalty: params.frequencyPenalty,
+      presence_penalty: params.presencePenalty,
+      apply_chat_template: params.applyChatTemplate,
+      messages: params.messages,
+    }) as { text: string; tokens_generated: number; duration_ms: number; unmapped_logit_bias?: string[]; token_intervals_ms?: number[]; first_token_ms?: number; prompt_tokens?: number; prefill_ms?: number; decode_ms?: number; tokens_per_second?: number };
     return {
       text: result.text,
       tokensGenerated: result.tokens_generated,
       durationMs: result.duration_ms,
+      unmappedLogitBias: result.unmapped_logit_bias,
+      tokenIntervalsMs: result.token_intervals_ms,
+      firstTokenMs: result.first_token_ms,
+      promptTokens: result.prompt_tokens,
+      prefillMs: result.prefill_ms,
+      decodeMs: result.decode_ms,
+      tokensPerSecond: result.tokens_per_second,
     };
   },
 
@@ -539,6 +621,11 @@ const nativeRuntimeBridge: NativeRuntimeBridge = {
     // NativeRuntime doesn't have explicit unload yet — no-op
   },
 
+  async tokenize(text, addBos) {
+    const result = await sendCallback('native_tokenize', { text, add_bos: addBos ?? false }) as { tokens: number[] };
+    return result.tokens;
+  },
+
   async getStatus() {
     const result = await Promise.race([
       sendCallback('native_status', {}),
@@ -547,11 +634,14 @@ const nativeRuntimeBridge: NativeRuntimeBridge = {
       status: string;
       reasoning_model: string | null;
       embedding_model: string | null;
+      can_generate?: boolean;
+      can_embed?: boolean;
     } | null;
-    if (!result) return { status: 'uninitialized' as const, reasoningModel: null, embeddingModel: null, fastModel: null, visionModel: null };
+    if (!result) return { status: 'uninitialized' as const, reasoningModel: null, embeddingModel: null, fastModel: null, visionModel: null, canGenerate: false, canEmbed: false };
     const statusStr = result.status?.toLowerCase() ?? '';
-    let status: 'uninitialized' | 'loading' | 'ready' | 'error' = 'uninitialized';
-    if (statusStr.includes('ready')) status = 'ready';
+    let status: 'uninitialized' | 'loading' | 'ready' | 'embedding_only' | 'error' = 'uninitialized';
+    if (statusStr.includes('embedding_only')) status = 'embedding_only';
+    else if (statusStr.includes('ready')) status = 'ready';
     else if (statusStr.includes('loading')) status = 'loading';
     else if (statusStr.includes('error')) status = 'error';
     return {
@@ -560,6 +650,8 @@ const nativeRuntimeBridge: NativeRuntimeBridge = {
       embeddingModel: result.embedding_model ?? null,
       fastModel: (result as Record<string, unknown>).fast_model as string | null ?? null,
       visionModel: (result as Record<string, unknown>).vision_model as string | null ?? null,
+      canGenerate: result.can_generate ?? false,
+      canEmbed: result.can_embed ?? false,
     };
   },
 
@@ -1686,6 +1778,159 @@ async function handleInitialize(): Promise<unknown> {
   };
 }
 
+// ─── Chat Stream Stats ────────────────────────────────────────────────────────
+// While stdout is backed up, chat tokens are merged into one pending event
+// instead of being written one by one; if the pending text grows past
+// MAX_PENDING_DISPLAY_CHARS it is dropped from the live display. Neither
+// touches the final text, which `chat-complete` always carries in full.
+// Stats are kept per conversation and reset at the start of each message.
+
+const MAX_PENDING_DISPLAY_CHARS = 16_384;
+const MAX_STREAM_STATS_SESSIONS = 50;
+
+interface StreamStats {
+  sessionId: string;
+  responseId: string;
+  startedAt: string;
+  completedAt: string | null;
+  /** Tokens produced by the model (or chunks of a non-streamed response). */
+  tokens: number;
+  /** `chat-token` events actually written. */
+  emitted: number;
+  /** Tokens merged into another event while stdout was backed up. */
+  coalesced: number;
+  /** Tokens never shown while streaming. Still present in the final text. */
+  droppedForDisplay: number;
+  streamedChars: number;
+  droppedChars: number;
+  /** Length of the text sent with `chat-complete`. */
+  finalChars: number | null;
+}
+
+const streamStats = new Map<string, StreamStats>();
+
+class ChatTokenStream {
+  readonly stats: StreamStats;
+  private pending = '';
+  private pendingTokens = 0;
+  private backedUp = false;
+
+  constructor(sessionId: string, responseId: string) {
+    this.stats = {
+      sessionId,
+      responseId,
+      startedAt: new Date().toISOString(),
+      completedAt: null,
+      tokens: 0,
+      emitted: 0,
+      coalesced: 0,
+      droppedForDisplay: 0,
+      streamedChars: 0,
+      droppedChars: 0,
+      finalChars: null,
+    };
+    streamStats.delete(sessionId);
+    streamStats.set(sessionId, this.stats);
+    if (streamStats.size > MAX_STREAM_STATS_SESSIONS) {
+      streamStats.delete(streamStats.keys().next().value!);
+    }
+  }
+
+  push(token: string): void {
+    if (!token) return;
+    this.stats.tokens++;
+    if (!this.backedUp) {
+      this.write(token, 1);
+      return;
+    }
+    this.pending += token;
+    this.pendingTokens++;
+    if (this.pending.length > MAX_PENDING_DISPLAY_CHARS) {
+      this.stats.droppedForDisplay += this.pendingTokens;
+      this.stats.droppedChars += this.pending.length;
+      this.pending = '';
+      this.pendingTokens = 0;
+    }
+  }
+
+  /** Flush anything still pending, end the token stream and emit `chat-complete`. */
+  complete(payload: { id: string; content: string; actions: unknown[] }, error?: string): void {
+    this.flush();
+    this.stats.finalChars = payload.content.length;
+    this.stats.completedAt = new Date().toISOString();
+    emit('chat-token', { responseId: this.stats.responseId, delta: '', done: true, error: error ?? null });
+    emit('chat-complete', payload);
+  }
+
+  private flush(): void {
+    if (!this.pending) return;
+    const text = this.pending;
+    const tokens = this.pendingTokens;
+    this.pending = '';
+    this.pendingTokens = 0;
+    this.write(text, tokens);
+  }
+
+  private write(text: string, tokens: number): void {
+    this.stats.emitted++;
+    this.stats.coalesced += tokens - 1;
+    this.stats.streamedChars += text.length;
+    if (!emit('chat-token', { responseId: this.stats.responseId, delta: text, done: false })) {
+      this.backedUp = true;
+      process.stdout.once('drain', () => {
+        this.backedUp = false;
+        this.flush();
+      });
+    }
+  }
+}
+
+// ─── Request Cancellation ─────────────────────────────────────────────────────
+// Requests that respond immediately and keep working (send_message,
+// email:startIndex) register here under their request id. A `cancel` frame
+// from Rust aborts the signal; the work checks it at its next checkpoint.
+
+class RequestCancelledError extends Error {
+  constructor() {
+    super('Request cancelled');
+  }
+}
+
+const cancellableRequests = new Map<string, AbortController>();
+
+function trackCancellable(id: number | string): AbortSignal {
+  const controller = new AbortController();
+  cancellableRequests.set(String(id), controller);
+  return controller.signal;
+}
+
+function finishCancellable(id: number | string): void {
+  cancellableRequests.delete(String(id));
+}
+
+function throwIfCancelled(signal: AbortSignal): void {
+  if (signal.aborted) throw new RequestCancelledError();
+}
+
+/** Rejects once `signal` aborts, for racing work that can't check it itself. */
+function whenCancelled(signal: AbortSignal): Promise<never> {
+  return new Promise((_, reject) => {
+    signal.addEventListener('abort', () => reject(new RequestCancelledError()), { once: true });
+  });
+}
+
+function handleCancelFrame(id: number | string): void {
+  const controller = cancellableRequests.get(String(id));
+  if (!controller) return; // already finished — nothing to abort
+  console.error(`[sidecar] Cancelling request ${id}`);
+  controller.abort();
+  cancellableRequests.delete(String(id));
+}
+
+function handleGetStreamStats(params: { session_id: string }): StreamStats | null {
+  return streamStats.get(params.session_id) ?? null;
+}
+
 async function handleSendMessage(
   id: number | string,
   params: { message: string; conversation_id?: string; attachments?: Array<{ id: string; fileName: string; filePath: string; mimeType: string }> },
@@ -1792,6 +2037,8 @@ async function handleSendMessage(
   // Return response ID immediately so frontend can start showing the streaming bubble
   respond(id, { responseId, conversationId: convId });
 
+  const stream = new ChatTokenStream(convId, responseId);
+  const signal = trackCancellable(id);
   try {
     let fullResponse = '';
     let actions: Array<{ id: string; type: string; status: string; payload: unknown }> = [];
@@ -1867,6 +2114,7 @@ async function handleSendMessage(
       const orchResult = await Promise.race([
         core.agent.processMessage(augmentedMessage, convId),
         orchTimeout,
+        whenCancelled(signal),
       ]);
 
       fullResponse = orchResult.message;
@@ -1891,7 +2139,8 @@ async function handleSendMessage(
       // Emit response in chunks with small delays for streaming UX
       const chunkSize = 12;
       for (let i = 0; i < fullResponse.length; i += chunkSize) {
-        emit('chat-token', fullResponse.substring(i, i + chunkSize));
+        throwIfCancelled(signal);
+        stream.push(fullResponse.substring(i, i + chunkSize));
         if (i % 120 === 0) await new Promise(r => setTimeout(r, 10));
       }
     } else {
@@ -1921,13 +2170,14 @@ async function handleSendMessage(
 
       if (core.llm.chatStream) {
         for await (const token of core.llm.chatStream({ model, messages })) {
-          emit('chat-token', token);
+          throwIfCancelled(signal);
+          stream.push(token);
           fullResponse += token;
         }
       } else {
         const response = await core.llm.chat({ model, messages });
         fullResponse = response.message.content;
-        emit('chat-token', fullResponse);
+        stream.push(fullResponse);
       }
 
       // Store conversation turns (orchestrator handles this in the primary path)
@@ -1941,7 +2191,7 @@ async function handleSendMessage(
     }
 
     // Emit completion with actions (empty if fallback path)
-    emit('chat-complete', { id: responseId, content: fullResponse, actions });
+    stream.complete({ id: responseId, content: fullResponse, actions });
 
     // Async, non-blocking semantic indexing of assistant response
     if (conversationIndexer) {
@@ -1955,10 +2205,17 @@ async function handleSendMessage(
       }).catch(err => console.error('[sidecar] Conversation indexing error:', err));
     }
   } catch (err) {
+    if (err instanceof RequestCancelledError) {
+      console.error(`[sidecar] handleSendMessage cancelled (${responseId})`);
+      stream.complete({ id: responseId, content: '', actions: [] }, err.message);
+      return;
+    }
     const errMsg = err instanceof Error ? err.message : String(err);
     console.error('[sidecar] handleSendMessage error:', errMsg);
-    emit('chat-token', `\n\nError: ${errMsg}`);
-    emit('chat-complete', { id: responseId, content: `Error: ${errMsg}`, actions: [] });
+    stream.push(`\n\nError: ${errMsg}`);
+    stream.complete({ id: responseId, content: `Error: ${errMsg}`, actions: [] }, errMsg);
+  } finally {
+    finishCancellable(id);
   }
 }
 
@@ -2011,7 +2268,151 @@ async function handleGetOllamaStatus(): Promise<unknown> {
   };
 }
 
-async function handleSelectModel(params: { model_id: string }): Promise<unknown> {
+/**
+ * Which backend is serving generations right now (the InferenceRouter's primary
+ * route), what each backend has loaded, and why a backend is unavailable.
+ * Priority matches startup: Ollama (GPU) > NativeRuntime (BitNet / standard GGUF).
+ */
+async function handleGetActiveInferenceBackend(): Promise<unknown> {
+  // ── NativeRuntime (Rust side, via callback) ──
+  type NativeStatus = {
+    status?: string;
+    reasoning_model?: string | null;
+    fast_model?: string | null;
+    embedding_model?: string | null;
+    error?: string | null;
+    backend_error?: string | null;
+  };
+  let nativeStatus: NativeStatus | null = null;
+  let nativeUnavailable: string | null = null;
+  try {
+    nativeStatus = await Promise.race([
+      sendCallback('native_status', {}) as Promise<NativeStatus>,
+      new Promise<null>((resolve) => setTimeout(() => resolve(null), 5000)),
+    ]);
+    if (!nativeStatus) nativeUnavailable = 'native runtime did not respond within 5s';
+  } catch (err) {
+    nativeUnavailable = `native runtime unreachable: ${err instanceof Error ? err.message : String(err)}`;
+  }
+  if (nativeStatus) {
+    if (nativeStatus.backend_error) {
+      nativeUnavailable = `native backend failed to init: ${nativeStatus.backend_error}`;
+    } else if (nativeStatus.status === 'error') {
+      nativeUnavailable = `native model failed to load: ${nativeStatus.error ?? 'unknown error'}`;
+    } else if (nativeStatus.status === 'loading') {
+      nativeUnavailable = 'native model is still loading';
+    } else if (nativeStatus.status !== 'ready') {
+      nativeUnavailable = 'no native reasoning model loaded';
+    }
+  }
+
+  // ── Ollama (localhost) ──
+  let ollamaModels: string[] = [];
+  let ollamaUnavailable: string | null = null;
+  try {
+    const { Ollama } = await import('ollama');
+    const client = new Ollama({ host: 'http://localhost:11434' });
+    const list = await Promise.race([
+      client.list(),
+      new Promise<never>((_, rej) => setTimeout(() => rej(new Error('timeout')), 3000)),
+    ]);
+    ollamaModels = list.models
+      .map((m: { name: string }) => m.name)
+      .filter((m: string) => !m.includes('embed') && !m.includes('nomic'));
+    if (ollamaModels.length === 0) ollamaUnavailable = 'Ollama is running but has no chat models';
+  } catch (err) {
+    ollamaUnavailable = `Ollama not reachable at localhost:11434 (${err instanceof Error ? err.message : String(err)})`;
+  }
+
+  // ── What the router will actually use for the next generation ──
+  let active: 'native' | 'ollama' | 'none' = 'none';
+  let provider: 'ollama' | 'bitnet' | 'native' | null = null;
+  let activeModel: string | null = null;
+  if (core) {
+    const { OllamaProvider } = await import('../../../core/llm/ollama-provider.js');
+    const router = core.llm as InstanceType<typeof InferenceRouter>;
+    if (router.getPrimaryRoute) {
+      const route = router.getPrimaryRoute();
+      activeModel = route.model;
+      if (route.provider instanceof OllamaProvider) {
+        active = 'ollama';
+        provider = 'ollama';
+      } else {
+        active = 'native';
+        provider = route.provider instanceof BitNetProvider ? 'bitnet' : 'native';
+      }
+    }
+  }
+
+  const serving = active === 'ollama' ? !ollamaUnavailable : active === 'native' ? !nativeUnavailable : false;
+  const reasons: string[] = [];
+  if (active === 'none') {
+    reasons.push('core not initialized — no inference backend wired');
+  } else if (active === 'native') {
+    if (ollamaUnavailable) reasons.push(`${ollamaUnavailable}, using native runtime`);
+    else reasons.push('Ollama is available but not selected, using native runtime');
+    if (nativeUnavailable) reasons.push(`active backend not serving: ${nativeUnavailable}`);
+  } else {
+    if (nativeUnavailable) reasons.push(`${nativeUnavailable}, using Ollama`);
+    if (ollamaUnavailable) reasons.push(`active backend not serving: ${ollamaUnavailable}`);
+  }
+
+  return {
+    active,
+    provider,
+    activeModel,
+    serving,
+    reason: reasons.length > 0 ? reasons.join('; ') : null,
+    native: {
+      available: !nativeUnavailable,
+      status: nativeStatus?.status ?? null,
+      reasoningModel: nativeStatus?.reasoning_model ?? null,
+      fastModel: nativeStatus?.fast_model ?? null,
+      embeddingModel: nativeStatus?.embedding_model ?? null,
+      unavailableReason: nativeUnavailable,
+    },
+    ollama: {
+      available: !ollamaUnavailable,
+      models: ollamaModels,
+      activeModel: active === 'ollama' ? activeModel : null,
+      unavailableReason: ollamaUnavailable,
+    },
+    lastTransition: lastProviderTransition,
+  };
+}
+
+/** Catalog identity of a model id (either catalog), or null if unknown. */
+function handleLookupModel(params: { model_id: string }): unknown {
+  const entry = getAnyModelById(params.model_id);
+  if (!entry) return null;
+  return {
+    modelId: entry.id,
+    displayName: entry.displayName,
+    // Empty in the catalog until the first verified download populates it.
+    sha256: entry.sha256 || null,
+  };
+}
+
+/** Downloaded model files from both catalogs with their expected checksums, for manifest verification. */
+function handleGetModelManifest(params: { model_ids?: string[] }): unknown[] {
+  const baseDir = dataDir ? join(dataDir, 'models').replace(/[/\\]models$/, '') : undefined;
+  const wanted = params.model_ids && params.model_ids.length > 0 ? new Set(params.model_ids) : null;
+  const entries: unknown[] = [];
+  const add = (id: string, path: string, sha256: string | undefined) => {
+    entries.push({ model_id: id, file_name: basename(path), path, sha256: sha256 || null });
+  };
+  for (const model of MODEL_CATALOG) {
+    if (wanted && !wanted.has(model.id)) continue;
+    if (isModelDownloaded(model.id, baseDir)) add(model.id, getModelPath(model.id, baseDir), model.sha256);
+  }
+  for (const model of BITNET_MODEL_CATALOG) {
+    if (wanted && !wanted.has(model.id)) continue;
+    if (isBitNetModelDownloaded(model.id, baseDir)) add(model.id, getBitNetModelPath(model.id, baseDir), model.sha256);
+  }
+  return entries;
+}
+
+async function handleSelectModel(params: { model_id: string; skip_memory_check?: boolean }): Promise<unknown> {
   if (!core) throw new Error('Core not initialized');
 
   // Check if it's a locally downloaded model
@@ -2021,10 +2422,14 @@ async function handleSelectModel(params: { model_id: string }): Promise<unknown>
     const modelPath = getModelPath(catalogEntry.id, baseDir);
     const modelType = catalogEntry.id.includes('embed') ? 'embedding' : 'reasoning';
     try {
-      await sendCallback('native_load_model', { model_path: modelPath, model_type: modelType });
+      await sendCallback('native_load_model', { model_path: modelPath, model_type: modelType, skip_memory_check: params.skip_memory_check });
       return { success: true, engine: 'native' };
     } catch (err) {
       console.error(`[sidecar] NativeRuntime load failed for "${params.model_id}":`, err);
+      // Refused by the memory check (the app can offer to load anyway) or
+      // cancelled by the user: report it as-is instead of falling back.
+      const kind = err instanceof CallbackError ? err.detail.kind : null;
+      if (kind === 'insufficient_memory' || kind === 'model_load_cancelled') throw err;
     }
   }
 
@@ -2041,15 +2446,16 @@ async function handleSelectModel(params: { model_id: string }): Promise<unknown>
 
 async function handleStartIndexing(
   id: number | string,
-  params: { directories: string[] },
+  params: { directories: string[]; exclusions?: string[] },
 ): Promise<void> {
   if (!core) {
     respondError(id, 'Core not initialized');
     return;
   }
 
-  if (indexingInProgress) {
-    respondError(id, 'Indexing already in progress');
+  const indexingState = indexingControl.state();
+  if (indexingState === 'indexing' || indexingState === 'paused') {
+    respondError(id, `Indexing already in progress (${indexingState})`);
     return;
   }
 
@@ -2060,20 +2466,25 @@ async function handleStartIndexing(
 
   // Respond immediately
   respond(id, 'ok');
-  indexingInProgress = true;
+  // This run's own token: once it is cancelled, a later start doesn't revive it.
+  const run = indexingControl.start();
   console.error(`[sidecar] Starting indexing for ${params.directories.length} directories: ${params.directories.join(', ')}`);
+  if (params.exclusions?.length) {
+    console.error(`[sidecar] Excluding patterns: ${params.exclusions.join(', ')}`);
+  }
 
   // Run indexing asynchronously
   (async () => {
     try {
       let totalFilesScanned = 0;
       let totalChunksCreated = 0;
+      let totalChunksReused = 0;
 
       // Step 1: Scan all directories for files
       const allFiles: Awaited<ReturnType<typeof scanDirectory>> = [];
       for (const dir of params.directories) {
         try {
-          const files = await scanDirectory(dir);
+          const files = await scanDirectory(dir, { exclude: params.exclusions });
           allFiles.push(...files);
         } catch (err) {
           console.error(`[sidecar] Failed to scan ${dir}:`, err);
@@ -2135,7 +2546,8 @@ async function handleStartIndexing(
       const MAX_INDEXABLE_FILE_BYTES = 50 * 1024 * 1024; // 50MB — skip files larger than this
       const HEAP_PRESSURE_THRESHOLD = 512 * 1024 * 1024; // 512MB — pause if heap exceeds this
 
-      for (let batchStart = 0; batchStart < allFiles.length; batchStart += BATCH_SIZE) {
+      let cancelled = false;
+      batches: for (let batchStart = 0; batchStart < allFiles.length; batchStart += BATCH_SIZE) {
         const batch = allFiles.slice(batchStart, batchStart + BATCH_SIZE);
 
         // Check heap pressure before each batch
@@ -2147,6 +2559,11 @@ async function handleStartIndexing(
         }
 
         for (const file of batch) {
+          // Pause/cancel take effect between files, never mid-document
+          if (!(await run.checkpoint())) {
+            cancelled = true;
+            break batches;
+          }
           try {
             // Skip files that are too large to index safely
             if (file.size > MAX_INDEXABLE_FILE_BYTES) {
@@ -2194,6 +2611,7 @@ async function handleStartIndexing(
                 },
               });
               totalChunksCreated += result.chunksCreated;
+              totalChunksReused += result.chunksReused ?? 0;
             } else {
               // Large file — multi-pass indexing
               const totalSegments = Math.ceil(fullText.length / SEGMENT_SIZE);
@@ -2221,6 +2639,7 @@ async function handleStartIndexing(
                     },
                   });
                   totalChunksCreated += result.chunksCreated;
+                  totalChunksReused += result.chunksReused ?? 0;
                 } catch (segErr) {
                   console.error(`[sidecar] Failed to index segment ${segIdx + 1}/${totalSegments} of ${file.name}:`, segErr);
                 }
@@ -2249,6 +2668,17 @@ async function handleStartIndexing(
         }
       }
 
+      if (cancelled) {
+        console.error(`[sidecar] Indexing cancelled after ${totalFilesScanned}/${filesTotal} files`);
+        emit('indexing-complete', {
+          filesScanned: totalFilesScanned,
+          filesTotal,
+          chunksCreated: totalChunksCreated,
+          cancelled: true,
+        });
+        return;
+      }
+
       // Step 3: Persist indexed directories
       const existingDirs = JSON.parse(getPref('indexed_directories') ?? '[]') as string[];
       const allDirs = [...new Set([...existingDirs, ...params.directories])];
@@ -2264,6 +2694,9 @@ async function handleStartIndexing(
         filesScanned: totalFilesScanned,
         filesTotal,
         chunksCreated: totalChunksCreated,
+        // Share of stored chunks whose embedding was reused from a previous index
+        chunksReused: totalChunksReused,
+        embeddingReuseRatio: totalChunksCreated > 0 ? totalChunksReused / totalChunksCreated : 0,
         documentCount: stats.totalDocuments,
         chunkCount: stats.totalChunks,
         indexSizeBytes: 0, // LanceDB doesn't expose this directly
@@ -2277,14 +2710,29 @@ async function handleStartIndexing(
         error: err instanceof Error ? err.message : String(err),
       });
     } finally {
-      indexingInProgress = false;
+      run.finish();
     }
   })();
 }
 
+async function handlePauseIndexing(): Promise<unknown> {
+  indexingControl.pause();
+  return handleGetIndexingStatus();
+}
+
+async function handleResumeIndexing(): Promise<unknown> {
+  indexingControl.resume();
+  return handleGetIndexingStatus();
+}
+
+async function handleCancelIndexing(): Promise<unknown> {
+  indexingControl.cancel();
+  return handleGetIndexingStatus();
+}
+
 async function handleGetIndexingStatus(): Promise<unknown> {
   return {
-    state: indexingInProgress ? 'indexing' : 'idle',
+    state: indexingControl.state(),
     files_scanned: 0,
     files_total: 0,
     chunks_created: 0,
@@ -2316,6 +2764,40 @@ async function handleGetIndexedDirectories(): Promise<string[]> {
   return dirs ? (JSON.parse(dirs) as string[]) : [];
 }
 
+/**
+ * Stable digest of the current index. Inputs, in order: sorted indexed directories,
+ * document count, chunk count, embedding model id, and a hash over every document's
+ * content hash (sorted). Changing any of them changes the fingerprint.
+ */
+async function handleGetIndexFingerprint(): Promise<unknown> {
+  const { createHash } = await import('node:crypto');
+  const directories = [...(await handleGetIndexedDirectories())].sort();
+  const embeddingModel = getEmbeddingModel().id;
+
+  let documentCount = 0;
+  let chunkCount = 0;
+  const contentHashes: string[] = [];
+  if (core) {
+    const stats = await core.knowledge.getStats();
+    documentCount = stats.totalDocuments;
+    chunkCount = stats.totalChunks;
+    for (const doc of await core.knowledge.listDocuments()) {
+      contentHashes.push(doc.contentHash);
+    }
+  }
+  contentHashes.sort();
+
+  const contentHash = createHash('sha256').update(contentHashes.join('\n')).digest('hex');
+  const fingerprint = createHash('sha256')
+    .update(JSON.stringify({ directories, documentCount, chunkCount, embeddingModel, contentHash }))
+    .digest('hex');
+
+  return {
+    fingerprint,
+    inputs: { directories, documentCount, chunkCount, embeddingModel, contentHash },
+  };
+}
+
 async function handleGetActionLog(params: { limit: number; offset: number }): Promise<unknown[]> {
   if (!gateway) return [];
 
@@ -2569,6 +3051,47 @@ function handleRemoveCredential(params: { id: string }): unknown {
   return { success: true };
 }
 
+/** Phases of a credential test, reported as `credential-test-progress` events. */
+type CredentialTestPhase = 'dns' | 'tls' | 'auth' | 'list_calendars';
+
+/**
+ * Resolve the credential's host and connect to its port — with the TLS handshake
+ * where the adapter uses implicit TLS — so a mistyped server fails in its own
+ * phase, quickly, instead of somewhere inside the adapter. Returns an error
+ * message, or null if the server is reachable.
+ */
+async function probeCredentialServer(
+  credential: ServiceCredential,
+  onPhase: (phase: CredentialTestPhase) => void,
+): Promise<string | null> {
+  onPhase('dns');
+  try {
+    await lookup(credential.host);
+  } catch {
+    return 'Server not found — check the hostname';
+  }
+
+  // Matches the adapters: IMAP honours useTLS, SMTP is implicit TLS only on 465, CalDAV is https.
+  onPhase('tls');
+  const implicitTls = credential.protocol === 'caldav'
+    || (credential.protocol === 'imap' ? credential.useTLS : credential.port === 465);
+  return new Promise((resolve) => {
+    const onConnect = () => {
+      socket.destroy();
+      resolve(null);
+    };
+    const socket = implicitTls
+      ? tlsConnect({ host: credential.host, port: credential.port, servername: credential.host, rejectUnauthorized: true }, onConnect)
+      : netConnect({ host: credential.host, port: credential.port }, onConnect);
+    socket.once('error', (err: NodeJS.ErrnoException) => {
+      socket.destroy();
+      if (err.code === 'ECONNREFUSED') resolve('Connection refused — check the server address and port');
+      else if (implicitTls) resolve(`TLS handshake failed — ${err.message}`);
+      else resolve(`Could not connect — ${err.message}`);
+    });
+  });
+}
+
 async function handleTestCredential(params: { id: string }): Promise<unknown> {
   if (!credentialStore) throw new Error('Credential store not initialized');
 
@@ -2577,8 +3100,18 @@ async function handleTestCredential(params: { id: string }): Promise<unknown> {
 
   const password = credentialStore.decryptPassword(credential);
 
+  let phase: CredentialTestPhase = 'dns';
+  const onPhase = (next: CredentialTestPhase) => {
+    phase = next;
+    emit('credential-test-progress', { id: params.id, phase });
+  };
+
+  const probeError = await probeCredentialServer(credential, onPhase);
+  if (probeError) return { success: false, error: probeError, phase };
+
   let result: { success: boolean; error?: string; calendars?: unknown[] };
 
+  onPhase('auth');
   switch (credential.protocol) {
     case 'imap':
       if (!emailAdapter) throw new Error('Email adapter not initialized');
@@ -2590,7 +3123,7 @@ async function handleTestCredential(params: { id: string }): Promise<unknown> {
       break;
     case 'caldav':
       if (!calendarAdapter) throw new Error('Calendar adapter not initialized');
-      result = await calendarAdapter.caldav.testConnection(credential, password);
+      result = await calendarAdapter.caldav.testConnection(credential, password, () => onPhase('list_calendars'));
       break;
     default:
       result = { success: false, error: `Unknown protocol: ${credential.protocol}` };
@@ -2599,9 +3132,10 @@ async function handleTestCredential(params: { id: string }): Promise<unknown> {
   // Update lastVerifiedAt on success
   if (result.success) {
     credentialStore.update(params.id, { lastVerifiedAt: new Date().toISOString() });
+    return result;
   }
 
-  return result;
+  return { ...result, phase };
 }
 
 async function handleDiscoverCalendars(params: { credential_id: string }): Promise<unknown[]> {
@@ -2651,20 +3185,29 @@ async function handleEmailStartIndex(
     emailIndexer.onEvent((event, data) => emit(event, data));
   }
 
-  respond(id, { started: true });
+  const responseId = `email_index_${Date.now()}`;
+  respond(id, { started: true, responseId });
+  const signal = trackCancellable(id);
 
   // Fetch and index in background
   try {
-    const result = await emailAdapter.execute('email.fetch', {
-      folder: 'INBOX',
-      limit: 200,
-      sort: 'date_desc',
-    });
+    const result = await Promise.race([
+      emailAdapter.execute('email.fetch', {
+        folder: 'INBOX',
+        limit: 200,
+        sort: 'date_desc',
+      }),
+      whenCancelled(signal),
+    ]);
+    throwIfCancelled(signal);
 
     if (result.success && result.data) {
       const messages = (result.data as { messages: unknown[] }).messages ?? [];
-      const indexed = await emailIndexer.indexMessages(messages as Parameters<EmailIndexer['indexMessages']>[0], params.account_id);
-      emit('email-index-complete', { indexed, total: messages.length });
+      const indexed = await Promise.race([
+        emailIndexer.indexMessages(messages as Parameters<EmailIndexer['indexMessages']>[0], params.account_id),
+        whenCancelled(signal),
+      ]);
+      emit('email-index-complete', { responseId, indexed, total: messages.length });
 
       // License auto-detection: scan email bodies for SEMBLANCE_LICENSE_KEY pattern
       if (premiumGate) {
@@ -2685,9 +3228,23 @@ async function handleEmailStartIndex(
           }
         }
       }
+    } else {
+      emit('email-index-complete', { responseId, indexed: 0, total: 0 });
     }
   } catch (err) {
-    console.error('[sidecar] Email indexing error:', err);
+    if (err instanceof RequestCancelledError) {
+      console.error(`[sidecar] Email indexing cancelled (${responseId})`);
+    } else {
+      console.error('[sidecar] Email indexing error:', err);
+    }
+    emit('email-index-complete', {
+      responseId,
+      indexed: 0,
+      total: 0,
+      error: err instanceof Error ? err.message : String(err),
+    });
+  } finally {
+    finishCancellable(id);
   }
 }
 
@@ -2898,6 +3455,11 @@ function handleDismissInsight(params: { insight_id: string }): unknown {
   return { success: true };
 }
 
+function handleGetInsightEvidence(params: { insight_id: string }): unknown {
+  if (!proactiveEngine) return null;
+  return proactiveEngine.getInsightEvidence(params.insight_id);
+}
+
 async function handleSendEmailAction(params: {
   to: string[];
   subject: string;
@@ -2916,9 +3478,7 @@ async function handleSendEmailAction(params: {
   };
 
   // Check autonomy — if guardian, queue for approval
-  const tier = core.agent.getApprovalCount
-    ? 'partner' // default for user-initiated
-    : 'guardian';
+  const tier = userSendTier();
 
   // Store as pending action for audit trail
   if (prefsDb) {
@@ -2956,6 +3516,80 @@ async function handleDraftEmailAction(params: {
   }
 }
 
+/** The tier `email:sendAction` records a user-initiated send under. */
+function userSendTier(): string {
+  return core?.agent.getApprovalCount
+    ? 'partner' // default for user-initiated
+    : 'guardian';
+}
+
+/** An indexed email as `action:preview` shows it, or null if it isn't indexed. */
+function previewMessage(messageId: string): unknown | null {
+  const email = emailIndexer?.getByMessageId(messageId);
+  if (!email) return null;
+  return {
+    messageId: email.messageId,
+    from: email.fromName ? `${email.fromName} <${email.from}>` : email.from,
+    subject: email.subject,
+    receivedAt: email.receivedAt,
+    folder: email.folder,
+  };
+}
+
+/**
+ * What `email:sendAction` or `email:archive` would do with `payload`, without
+ * doing it. Mirrors those handlers: user-initiated sends are queued for
+ * approval under `userSendTier()`; archives run at once, outside the action
+ * log, so neither can be undone with `action:undo`.
+ */
+function handleActionPreview(params: { action: string; payload: Record<string, unknown> }): unknown {
+  if (!core) throw new Error('Core not initialized');
+
+  switch (params.action) {
+    case 'email.send': {
+      const payload = params.payload as { to: string[]; subject: string; body: string; replyToMessageId?: string | null };
+      // EmailAdapter sends through the first SMTP account, else through Gmail.
+      const smtp = credentialStore?.getByType('email').find(c => c.protocol === 'smtp');
+      return {
+        action: params.action,
+        tier: userSendTier(),
+        requiresApproval: true,
+        undoable: false,
+        undoWindowMs: null,
+        email: {
+          from: smtp?.username ?? null,
+          to: payload.to,
+          subject: payload.subject,
+          body: payload.body,
+          inReplyTo: payload.replyToMessageId ? previewMessage(payload.replyToMessageId) : null,
+        },
+        archive: null,
+      };
+    }
+    case 'email.archive': {
+      const { messageIds } = params.payload as { messageIds: string[] };
+      const messages: unknown[] = [];
+      const unknownMessageIds: string[] = [];
+      for (const messageId of messageIds) {
+        const message = previewMessage(messageId);
+        if (message) messages.push(message);
+        else unknownMessageIds.push(messageId);
+      }
+      return {
+        action: params.action,
+        tier: null,
+        requiresApproval: false,
+        undoable: false,
+        undoWindowMs: null,
+        email: null,
+        archive: { messages, unknownMessageIds },
+      };
+    }
+    default:
+      throw new Error(`No preview for action ${params.action}`);
+  }
+}
+
 async function handleUndoAction(params: { action_id: string }): Promise<unknown> {
   if (!prefsDb) return { success: false, error: 'Not initialized' };
 
@@ -2983,15 +3617,164 @@ function handleGetApprovalThreshold(params: { action_type: string; payload: Reco
 
 // Finance components accessed via ipAdapters (moved to @semblance/dr)
 
-async function handleImportStatement(params: { file_path: string }): Promise<unknown> {
+/** Rows handed to the parser at a time; a progress event follows each batch. */
+const STATEMENT_IMPORT_BATCH_ROWS = 500;
+
+/** Split CSV text into rows, keeping newlines inside quoted fields. */
+function splitCsvRows(content: string): string[] {
+  const rows: string[] = [];
+  let start = 0;
+  let quoted = false;
+  for (let i = 0; i < content.length; i++) {
+    const ch = content[i];
+    if (ch === '"') quoted = !quoted;
+    else if (ch === '\n' && !quoted) {
+      rows.push(content.slice(start, i).replace(/\r$/, ''));
+      start = i + 1;
+    }
+  }
+  rows.push(content.slice(start).replace(/\r$/, ''));
+  return rows.filter(row => row.trim() !== '');
+}
+
+/**
+ * Split OFX text into its `<STMTTRN>` blocks and the document around them, so
+ * batches of blocks can be parsed as smaller documents.
+ */
+function splitOfxTransactions(content: string): { head: string; blocks: string[]; tail: string } {
+  const starts = [...content.matchAll(/<STMTTRN>/gi)].map(m => m.index ?? 0);
+  if (starts.length === 0) return { head: content, blocks: [], tail: '' };
+  const listEnd = content.slice(starts[starts.length - 1]).search(/<\/BANKTRANLIST>/i);
+  const end = listEnd === -1 ? content.length : starts[starts.length - 1] + listEnd;
+  const blocks = starts.map((s, i) => content.slice(s, i + 1 < starts.length ? starts[i + 1] : end));
+  return { head: content.slice(0, starts[0]), blocks, tail: content.slice(end) };
+}
+
+/**
+ * Rewrite an OFX 2.x (XML) document in the 1.x SGML form the statement parser
+ * reads: `OFXHEADER:` lines instead of the XML prolog, and leaf elements
+ * without their closing tags.
+ */
+function ofxXmlToSgml(content: string): string {
+  const body = content.slice(Math.max(0, content.search(/<OFX>/i)));
+  return 'OFXHEADER:100\nDATA:OFXSGML\nVERSION:102\n\n' + body.replace(/<([A-Z0-9.]+)>([^<]*)<\/\1>/gi, '<$1>$2');
+}
+
+/** `<FITID>` of an OFX transaction block: the bank's id, stable across exports. */
+function ofxFitid(block: string): string | null {
+  return /<FITID>\s*([^<\r\n]+)/i.exec(block)?.[1].trim() || null;
+}
+
+/**
+ * Ledger of imported statement transactions in core.db, one row per
+ * transaction under a key that identifies it across imports: the account and
+ * FITID for OFX, the row's date, amount and description plus its occurrence
+ * in the file otherwise. `subscription_transactions` records which stored
+ * charge each was detected in. The detector's own store can't be queried for
+ * either: its stored charges come back without their transactions.
+ */
+function ensureStatementLedger(): Database.Database | null {
+  if (!prefsDb) return null;
+  prefsDb.exec(`
+    CREATE TABLE IF NOT EXISTS statement_transactions (
+      dedupe_key TEXT PRIMARY KEY,
+      import_id TEXT NOT NULL,
+      tx_id TEXT NOT NULL,
+      tx_json TEXT NOT NULL
+    );
+    CREATE INDEX IF NOT EXISTS idx_statement_transactions_tx ON statement_transactions(tx_id);
+    CREATE TABLE IF NOT EXISTS subscription_transactions (
+      charge_id TEXT NOT NULL,
+      tx_id TEXT NOT NULL,
+      PRIMARY KEY (charge_id, tx_id)
+    );
+  `);
+  return prefsDb;
+}
+
+async function handleImportStatement(params: {
+  file_path: string;
+  content: string;
+  format?: 'csv' | 'ofx' | 'ofx_xml' | 'qfx';
+}): Promise<unknown> {
   const sp = ipAdapters.statementParser;
   const mn = ipAdapters.merchantNormalizer;
   const rd = ipAdapters.recurringDetector;
   if (!sp || !mn || !rd) {
-    return { error: 'Financial intelligence requires Digital Representative' };
+    throw new Error('Financial intelligence requires Digital Representative');
+  }
+
+  // The content arrives decoded from Rust, which reads the file so that
+  // non-UTF-8 exports are transcoded rather than mangled, and detects its
+  // format from the content. Older callers send no format: go by extension.
+  const ext = params.file_path.split('.').pop()?.toLowerCase();
+  const format = params.format ?? ext;
+  if (format !== 'csv' && format !== 'ofx' && format !== 'ofx_xml' && format !== 'qfx') {
+    throw new Error(`Unsupported statement format: .${ext ?? ''}`);
+  }
+
+  // Parse in batches: rows of a CSV (each batch under the header row), or
+  // transaction blocks of an OFX document, each transaction with its FITID.
+  type ParsedRow = { tx: ParsedTransaction; fitid: string | null };
+  let parseBatch: (start: number, end: number) => Promise<ParsedRow[]>;
+  let rowsTotal: number;
+  let account = '';
+  if (format === 'csv') {
+    const [header = '', ...rows] = splitCsvRows(params.content);
+    rowsTotal = rows.length;
+    parseBatch = async (start, end) =>
+      (await sp.parseCSV([header, ...rows.slice(start, end)].join('\n'))).map(tx => ({ tx, fitid: null }));
+  } else {
+    // QFX is OFX with Intuit tags, in either syntax.
+    const xml = format === 'ofx_xml' || /^\s*<\?xml/i.test(params.content);
+    const { head, blocks, tail } = splitOfxTransactions(xml ? ofxXmlToSgml(params.content) : params.content);
+    rowsTotal = blocks.length;
+    account = /<ACCTID>\s*([^<\r\n]+)/i.exec(head)?.[1].trim() ?? '';
+    parseBatch = async (start, end) => {
+      const batch = blocks.slice(start, end);
+      const parsed = sp.parseOFX(head + batch.join('') + tail);
+      if (parsed.length === batch.length) return parsed.map((tx, i) => ({ tx, fitid: ofxFitid(batch[i]) }));
+      // Some block didn't parse, so the FITIDs no longer line up: parse one by one.
+      return batch.flatMap(block => sp.parseOFX(head + block + tail).map(tx => ({ tx, fitid: ofxFitid(block) })));
+    };
+  }
+
+  const emitProgress = (rowsParsed: number) =>
+    emit('statement-import-progress', { filePath: params.file_path, rowsParsed, rowsTotal });
+  emitProgress(0);
+
+  // Skip transactions an earlier import already stored, as overlapping
+  // exports repeat them. Identical rows within this file are separate
+  // purchases (two coffees on one day) and are all kept: the occurrence
+  // count keys them apart, so only as many as the earlier import had match.
+  const ledger = ensureStatementLedger();
+  const stored = ledger?.prepare('SELECT 1 FROM statement_transactions WHERE dedupe_key = ?');
+  const occurrences = new Map<string, number>();
+  const transactions: ParsedTransaction[] = [];
+  const dedupeKeys: string[] = [];
+  let duplicatesSkipped = 0;
+  let unparseableRows = 0;
+  for (let start = 0; start < rowsTotal; start += STATEMENT_IMPORT_BATCH_ROWS) {
+    const end = Math.min(start + STATEMENT_IMPORT_BATCH_ROWS, rowsTotal);
+    const parsed = await parseBatch(start, end);
+    unparseableRows += Math.max(0, end - start - parsed.length);
+    for (const { tx, fitid } of parsed) {
+      const identity = fitid
+        ? `ofx:${account}:${fitid}`
+        : `row:${tx.date}|${tx.amount}|${tx.description.trim().toLowerCase()}`;
+      const occurrence = (occurrences.get(identity) ?? 0) + 1;
+      occurrences.set(identity, occurrence);
+      const key = `${identity}#${occurrence}`;
+      if (stored?.get(key)) {
+        duplicatesSkipped++;
+        continue;
+      }
+      transactions.push(tx);
+      dedupeKeys.push(key);
+    }
+    emitProgress(end);
   }
 
-  const { transactions, import: importRecord } = await sp.parseStatement(params.file_path);
   const normalized = mn.normalizeAll(transactions);
   const charges = rd.detect(normalized);
 
@@ -3003,18 +3786,46 @@ async function handleImportStatement(params: { file_path: string }): Promise<unk
   const flaggedCharges = await rd.flagForgotten(charges, emailSearchFn);
 
   // Store
-  rd.storeImport(importRecord, normalized);
+  const dates = normalized.map(t => t.date).filter(Boolean).sort();
+  const dateRange = { start: dates[0] ?? '', end: dates[dates.length - 1] ?? '' };
+  const importId = nanoid();
+  rd.storeImport({
+    id: importId,
+    fileName: basename(params.file_path),
+    fileFormat: format === 'ofx_xml' ? 'ofx' : format,
+    transactionCount: normalized.length,
+    dateRange,
+    importedAt: new Date().toISOString(),
+  }, normalized);
+  if (ledger) {
+    const insert = ledger.prepare(
+      'INSERT OR IGNORE INTO statement_transactions (dedupe_key, import_id, tx_id, tx_json) VALUES (?, ?, ?, ?)'
+    );
+    ledger.transaction(() => {
+      normalized.forEach((tx, i) => insert.run(dedupeKeys[i], importId, tx.id, JSON.stringify(tx)));
+    })();
+  }
   rd.storeCharges(flaggedCharges);
+  if (ledger) {
+    const link = ledger.prepare('INSERT OR IGNORE INTO subscription_transactions (charge_id, tx_id) VALUES (?, ?)');
+    ledger.transaction(() => {
+      for (const charge of flaggedCharges) {
+        for (const tx of charge.transactions) link.run(charge.id, tx.id);
+      }
+    })();
+  }
 
   const forgotten = flaggedCharges.filter(c => c.status === 'forgotten');
   const summary = rd.getSummary();
 
   return {
-    transactionCount: importRecord.transactionCount,
+    transactionsImported: normalized.length,
+    duplicatesSkipped,
+    unparseableRows,
+    detectedSubscriptions: flaggedCharges.length,
+    forgottenSubscriptions: forgotten.length,
     merchantCount: new Set(normalized.map(t => t.normalizedMerchant)).size,
-    dateRange: importRecord.dateRange,
-    recurringCount: flaggedCharges.length,
-    forgottenCount: forgotten.length,
+    dateRange,
     potentialSavings: summary.potentialSavings,
   };
 }
@@ -3022,7 +3833,9 @@ async function handleImportStatement(params: { file_path: string }): Promise<unk
 function handleGetSubscriptions(params: { status?: string }): unknown[] {
   const rd = ipAdapters.recurringDetector;
   if (!rd) return [];
-  return rd.getStoredCharges(params.status);
+  // Charges folded into another by a merge only show when asked for by status.
+  if (params.status) return rd.getStoredCharges(params.status);
+  return rd.getStoredCharges().filter(c => c.status !== 'merged');
 }
 
 function handleUpdateSubscriptionStatus(params: { charge_id: string; status: string }): unknown {
@@ -3032,6 +3845,98 @@ function handleUpdateSubscriptionStatus(params: { charge_id: string; status: str
   return { success: true };
 }
 
+const CHARGES_PER_YEAR: Record<RecurringCharge['frequency'], number> = {
+  weekly: 52,
+  monthly: 12,
+  quarterly: 4,
+  annual: 1,
+};
+
+function median(values: number[]): number {
+  const sorted = [...values].sort((a, b) => a - b);
+  const mid = Math.floor(sorted.length / 2);
+  return sorted.length % 2 ? sorted[mid] : (sorted[mid - 1] + sorted[mid]) / 2;
+}
+
+/** Cadence of charges on `dates`, from the median gap between them. */
+function chargeFrequency(dates: string[], fallback: RecurringCharge['frequency']): RecurringCharge['frequency'] {
+  const times = dates.map(d => Date.parse(d)).filter(t => !Number.isNaN(t)).sort((a, b) => a - b);
+  if (times.length < 2) return fallback;
+  const gapDays = median(times.slice(1).map((t, i) => (t - times[i]) / 86_400_000));
+  if (gapDays <= 10) return 'weekly';
+  if (gapDays <= 45) return 'monthly';
+  if (gapDays <= 120) return 'quarterly';
+  return 'annual';
+}
+
+function handleMergeSubscriptions(params: { charge_ids: string[] }): unknown {
+  const rd = ipAdapters.recurringDetector;
+  if (!rd) return { success: false };
+  const charges = rd.getStoredCharges().filter(c => params.charge_ids.includes(c.id) && c.status !== 'merged');
+  if (charges.length < 2) return { success: false, error: 'Fewer than two of the given subscriptions exist' };
+
+  // The primary is the one merge_subscription_candidates suggests: most
+  // charges, stored order breaking ties (the sort is stable).
+  const [primary, ...duplicates] = [...charges].sort((a, b) => b.chargeCount - a.chargeCount);
+
+  // Stored charges come back without transactions; the import ledger has them.
+  const ledger = ensureStatementLedger();
+  const ids = charges.map(c => c.id);
+  const rows = (ledger?.prepare(
+    `SELECT t.tx_json FROM subscription_transactions s
+     JOIN statement_transactions t ON t.tx_id = s.tx_id
+     WHERE s.charge_id IN (${ids.map(() => '?').join(', ')})
+     GROUP BY t.tx_id`
+  ).all(...ids) ?? []) as Array<{ tx_json: string }>;
+  const transactions = rows
+    .map(r => JSON.parse(r.tx_json) as ParsedTransaction)
+    .sort((a, b) => a.date.localeCompare(b.date));
+
+  // Recompute what the detector derives from the transactions. Charges
+  // imported before the ledger existed have none: weight by charge count.
+  let amount: number;
+  let frequency: RecurringCharge['frequency'];
+  let chargeCount: number;
+  let lastChargeDate: string;
+  if (transactions.length > 0) {
+    amount = median(transactions.map(t => t.amount));
+    frequency = chargeFrequency(transactions.map(t => t.date), primary.frequency);
+    chargeCount = transactions.length;
+    lastChargeDate = transactions[transactions.length - 1].date;
+  } else {
+    chargeCount = charges.reduce((sum, c) => sum + c.chargeCount, 0);
+    amount = chargeCount > 0
+      ? charges.reduce((sum, c) => sum + c.amount * c.chargeCount, 0) / chargeCount
+      : primary.amount;
+    frequency = primary.frequency;
+    lastChargeDate = charges.map(c => c.lastChargeDate).sort().pop() ?? primary.lastChargeDate;
+  }
+  amount = Math.round(amount * 100) / 100;
+  const merged: RecurringCharge = {
+    ...primary,
+    amount,
+    frequency,
+    chargeCount,
+    lastChargeDate,
+    estimatedAnnualCost: Math.round(Math.abs(amount) * CHARGES_PER_YEAR[frequency] * 100) / 100,
+    transactions,
+  };
+  rd.storeCharges([merged]);
+
+  // Retired as merged, not cancelled: the user didn't cancel anything, and
+  // merged charges are hidden from the subscription list.
+  for (const dup of duplicates) {
+    rd.updateStatus(dup.id, 'merged');
+  }
+  if (ledger) {
+    const link = ledger.prepare('INSERT OR IGNORE INTO subscription_transactions (charge_id, tx_id) VALUES (?, ?)');
+    ledger.transaction(() => {
+      for (const tx of transactions) link.run(primary.id, tx.id);
+    })();
+  }
+  return { success: true, primaryId: primary.id, mergedIds: duplicates.map(c => c.id) };
+}
+
 function handleGetImportHistory(): unknown[] {
   const rd = ipAdapters.recurringDetector;
   if (!rd) return [];
@@ -3200,6 +4105,42 @@ function handleGetNetworkAllowlist(): unknown[] {
   return networkMonitor.getEnrichedAllowlist();
 }
 
+function handleGetBlocklist(): unknown[] {
+  if (!gateway) return [];
+  return gateway.getBlocklist().list();
+}
+
+function logBlocklistChange(operation: 'add' | 'remove', domain: string, reason?: string): void {
+  if (!gateway) return;
+  gateway.getAuditTrail().append({
+    requestId: `blocklist-${operation}-${Date.now()}`,
+    timestamp: new Date().toISOString(),
+    action: 'service.api_call',
+    direction: 'response',
+    status: 'success',
+    payloadHash: 'blocklist_update',
+    signature: 'blocklist_update',
+    metadata: { event: 'blocklist_updated', domain, reason, operation },
+  });
+}
+
+function handleAddBlocklistEntry(params: { domain: string; reason?: string }): unknown {
+  if (!gateway) throw new Error('Gateway not initialized');
+  const entry = gateway.getBlocklist().add({ domain: params.domain, reason: params.reason, addedBy: 'user' });
+  logBlocklistChange('add', entry.domain, entry.reason);
+  return entry;
+}
+
+function handleRemoveBlocklistEntry(params: { id: string }): unknown {
+  if (!gateway) throw new Error('Gateway not initialized');
+  const blocklist = gateway.getBlocklist();
+  // `remove` also takes a domain; log the entry's domain either way.
+  const domain = blocklist.list().find(entry => entry.id === params.id)?.domain ?? params.id;
+  const removed = blocklist.remove(params.id);
+  if (removed) logBlocklistChange('remove', domain);
+  return { removed };
+}
+
 function handleGetUnauthorizedAttempts(params: { period?: string }): unknown[] {
   ensureNetworkMonitor();
   if (!networkMonitor) return [];
@@ -3233,7 +4174,7 @@ function handleGeneratePrivacyReport(params: { start_date: string; end_date: str
 
 function handleGetNetworkTrustStatus(): unknown {
   ensureNetworkMonitor();
-  if (!networkMonitor) return { clean: true, unauthorizedCount: 0, activeServiceCount: 0 };
+  if (!networkMonitor) return { clean: true, unauthorizedCount: 0, blocklistHits: 0, activeServiceCount: 0 };
   return networkMonitor.getTrustStatus();
 }
 
@@ -3266,6 +4207,25 @@ function handleAssessTask(params: { task: Record<string, unknown> }): unknown {
 
 // ─── Hardware & Runtime Handlers (Step 9) ────────────────────────────────────
 
+function handleRecommendModels(params: { tier: string }): unknown {
+  const tier = (params.tier || 'standard') as HardwareProfileTier;
+  const summarize = (m: ModelRegistryEntry) => ({
+    id: m.id,
+    displayName: m.displayName,
+    parameterCount: m.parameterCount,
+    quantization: m.quantization,
+    fileSizeBytes: m.fileSizeBytes,
+    ramRequiredMb: m.ramRequiredMb,
+    isEmbedding: m.isEmbedding,
+  });
+  return {
+    tier,
+    models: getModelsForTier(tier).map(summarize),
+    bitnetModel: summarize(getRecommendedBitNetModel(tier)),
+    totalDownloadBytes: getTotalDownloadSize(tier),
+  };
+}
+
 function handleDetectHardware(): unknown {
   // Hardware detection runs in-process using Node.js os module.
   // The Rust-side detection is used when called from a Tauri command directly.
@@ -5029,6 +5989,11 @@ async function handleRequest(req: Request): Promise<void> {
         respond(id, result);
         break;
 
+      case 'ping':
+        // Rust heartbeat: answering at all shows the event loop isn't blocked.
+        respond(id, { ok: true });
+        break;
+
       case 'get_model_status': {
         // Return current model/engine status — check Ollama first, then NativeRuntime
         const userName = getPref('user_name');
@@ -5086,8 +6051,14 @@ async function handleRequest(req: Request): Promise<void> {
       }
 
       case 'send_message':
-        // send_message responds and emits events internally
-        await handleSendMessage(id, params as { message: string; conversation_id?: string; attachments?: Array<{ id: string; fileName: string; filePath: string; mimeType: string }> });
+        // send_message responds and emits events internally. Interactive, so
+        // its generations preempt background work.
+        await generationPriority.run('high', () => handleSendMessage(id, params as { message: string; conversation_id?: string; attachments?: Array<{ id: string; fileName: string; filePath: string; mimeType: string }> }));
+        break;
+
+      case 'chat:getStreamStats':
+        result = handleGetStreamStats(params as { session_id: string });
+        respond(id, result);
         break;
 
       case 'get_ollama_status':
@@ -5095,14 +6066,29 @@ async function handleRequest(req: Request): Promise<void> {
         respond(id, result);
         break;
 
+      case 'get_active_inference_backend':
+        result = await handleGetActiveInferenceBackend();
+        respond(id, result);
+        break;
+
+      case 'model:lookup':
+        result = handleLookupModel(params as { model_id: string });
+        respond(id, result);
+        break;
+
+      case 'model:getManifest':
+        result = handleGetModelManifest(params as { model_ids?: string[] });
+        respond(id, result);
+        break;
+
       case 'select_model':
-        result = await handleSelectModel(params as { model_id: string });
+        result = await handleSelectModel(params as { model_id: string; skip_memory_check?: boolean });
         respond(id, result);
         break;
 
       case 'start_indexing':
         // start_indexing responds and emits events internally
-        await handleStartIndexing(id, params as { directories: string[] });
+        await handleStartIndexing(id, params as { directories: string[]; exclusions?: string[] });
         break;
 
       case 'get_indexing_status':
@@ -5110,6 +6096,21 @@ async function handleRequest(req: Request): Promise<void> {
         respond(id, result);
         break;
 
+      case 'pause_indexing':
+        result = await handlePauseIndexing();
+        respond(id, result);
+        break;
+
+      case 'resume_indexing':
+        result = await handleResumeIndexing();
+        respond(id, result);
+        break;
+
+      case 'cancel_indexing':
+        result = await handleCancelIndexing();
+        respond(id, result);
+        break;
+
       case 'get_knowledge_stats':
         result = await handleGetKnowledgeStats();
         respond(id, result);
@@ -5120,6 +6121,11 @@ async function handleRequest(req: Request): Promise<void> {
         respond(id, result);
         break;
 
+      case 'get_index_fingerprint':
+        result = await handleGetIndexFingerprint();
+        respond(id, result);
+        break;
+
       case 'get_action_log':
         result = await handleGetActionLog(params as { limit: number; offset: number });
         respond(id, result);
@@ -5345,6 +6351,11 @@ async function handleRequest(req: Request): Promise<void> {
         respond(id, result);
         break;
 
+      case 'insight:getEvidence':
+        result = handleGetInsightEvidence(params as { insight_id: string });
+        respond(id, result);
+        break;
+
       case 'email:sendAction':
         result = await handleSendEmailAction(params as { to: string[]; subject: string; body: string; replyToMessageId?: string });
         respond(id, result);
@@ -5355,6 +6366,11 @@ async function handleRequest(req: Request): Promise<void> {
         respond(id, result);
         break;
 
+      case 'action:preview':
+        result = handleActionPreview(params as { action: string; payload: Record<string, unknown> });
+        respond(id, result);
+        break;
+
       case 'action:undo':
         result = await handleUndoAction(params as { action_id: string });
         respond(id, result);
@@ -5368,7 +6384,7 @@ async function handleRequest(req: Request): Promise<void> {
       // ── Step 7: Subscription Detection ──
 
       case 'finance:importStatement':
-        result = await handleImportStatement(params as { file_path: string });
+        result = await handleImportStatement(params as { file_path: string; content: string });
         respond(id, result);
         break;
 
@@ -5382,6 +6398,11 @@ async function handleRequest(req: Request): Promise<void> {
         respond(id, result);
         break;
 
+      case 'finance:mergeSubscriptions':
+        result = handleMergeSubscriptions(params as { charge_ids: string[] });
+        respond(id, result);
+        break;
+
       case 'finance:getImportHistory':
         result = handleGetImportHistory();
         respond(id, result);
@@ -5450,6 +6471,21 @@ async function handleRequest(req: Request): Promise<void> {
         respond(id, result);
         break;
 
+      case 'network:getBlocklist':
+        result = handleGetBlocklist();
+        respond(id, result);
+        break;
+
+      case 'network:addBlocklistEntry':
+        result = handleAddBlocklistEntry(params as { domain: string; reason?: string });
+        respond(id, result);
+        break;
+
+      case 'network:removeBlocklistEntry':
+        result = handleRemoveBlocklistEntry(params as { id: string });
+        respond(id, result);
+        break;
+
       case 'network:getUnauthorizedAttempts':
         result = handleGetUnauthorizedAttempts(params as { period?: string });
         respond(id, result);
@@ -5504,6 +6540,11 @@ async function handleRequest(req: Request): Promise<void> {
         respond(id, result);
         break;
 
+      case 'hardware:recommendModels':
+        result = handleRecommendModels(params as { tier: string });
+        respond(id, result);
+        break;
+
       // ── Contacts (Step 14) ──
 
       case 'contacts:import':
@@ -6714,7 +7755,7 @@ async function handleRequest(req: Request): Promise<void> {
           break;
         }
         const finSummary = rd.getSummary();
-        const finCharges = rd.getStoredCharges();
+        const finCharges = rd.getStoredCharges().filter(c => c.status !== 'merged');
         respond(id, {
           overview: {
             totalSpending: finSummary.totalMonthly,
@@ -9180,7 +10221,7 @@ async function handleRequest(req: Request): Promise<void> {
         try {
           const detector = ipAdapters.recurringDetector;
           if (detector) {
-            const charges = detector.getStoredCharges();
+            const charges = detector.getStoredCharges().filter(c => c.status !== 'merged');
             respond(id, { chargeCount: charges.length });
           } else {
             respond(id, { skipped: true, reason: 'RecurringDetector not loaded (requires DR)' });
@@ -9284,7 +10325,7 @@ async function handleRequest(req: Request): Promise<void> {
         respondError(id, `Unknown method: ${method}`);
     }
   } catch (err) {
-    respondError(id, err instanceof Error ? err.message : String(err));
+    respondError(id, err instanceof Error ? err.message : String(err), err instanceof CallbackError ? err.detail : undefined);
   }
 }
 
@@ -9342,7 +10383,13 @@ rl.on('line', (line: string) => {
 
     // Check for callback_response from Rust (Step 9 NDJSON reverse-call)
     if (msg.type === 'callback_response' && typeof msg.id === 'string') {
-      handleCallbackResponse(msg as { id: string; result?: unknown; error?: string });
+      handleCallbackResponse(msg as { id: string; result?: unknown; error?: string; detail?: CallbackErrorDetail });
+      return;
+    }
+
+    // Cancel frame from Rust: abort background work of an answered request
+    if (msg.type === 'cancel' && typeof msg.id !== 'undefined') {
+      handleCancelFrame(msg.id as number | string);
       return;
     }
 
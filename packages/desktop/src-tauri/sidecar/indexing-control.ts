@@ -0,0 +1,96 @@
+// Indexing Control — pause, resume and cancel for directory indexing runs.
+//
+// Each `start()` begins a new run with its own generation. A run's
+// `checkpoint()` only lets it continue while it is still the current run, so
+// a run cancelled mid-file stops at its next checkpoint even if another run
+// has started since; and only the current run may put the state back to idle
+// when it finishes.
+//
+// Extracted from bridge.ts for testability.
+
+/** idle → indexing ⇄ paused → cancelled (or back to idle when a run finishes). */
+export type IndexingState = 'idle' | 'indexing' | 'paused' | 'cancelled';
+
+export interface IndexingRun {
+  /** Blocks while indexing is paused. Returns false once this run is cancelled or superseded. */
+  checkpoint: () => Promise<boolean>;
+  /** Mark this run done. Returns to idle unless it was cancelled or another run has started. */
+  finish: () => void;
+}
+
+export interface IndexingControl {
+  state: () => IndexingState;
+  /** Begin a run. Throws while one is indexing or paused. */
+  start: () => IndexingRun;
+  pause: () => void;
+  resume: () => void;
+  cancel: () => void;
+}
+
+/**
+ * Create the indexing state machine.
+ * @param onStateChange — called on every transition, e.g. to emit `indexing-state-changed`
+ */
+export function createIndexingControl(
+  onStateChange: (state: IndexingState, previous: IndexingState) => void = () => {},
+): IndexingControl {
+  let state: IndexingState = 'idle';
+  let generation = 0;
+  /** Runs blocked in `checkpoint` while paused. */
+  let resumeWaiters: Array<() => void> = [];
+
+  function setState(next: IndexingState): void {
+    if (next === state) return;
+    const previous = state;
+    state = next;
+    if (next !== 'paused') {
+      const waiters = resumeWaiters;
+      resumeWaiters = [];
+      for (const wake of waiters) wake();
+    }
+    onStateChange(next, previous);
+  }
+
+  function start(): IndexingRun {
+    if (state === 'indexing' || state === 'paused') {
+      throw new Error(`Indexing already in progress (${state})`);
+    }
+    const run = ++generation;
+    setState('indexing');
+    return {
+      checkpoint: async () => {
+        while (run === generation && state === 'paused') {
+          await new Promise<void>(resolve => resumeWaiters.push(resolve));
+        }
+        return run === generation && state === 'indexing';
+      },
+      finish: () => {
+        // A cancelled run stays 'cancelled' until the next start
+        if (run === generation && state !== 'cancelled') setState('idle');
+      },
+    };
+  }
+
+  function pause(): void {
+    if (state !== 'indexing') {
+      throw new Error(`Cannot pause indexing while ${state}`);
+    }
+    setState('paused');
+  }
+
+  function resume(): void {
+    if (state !== 'paused') {
+      throw new Error(`Cannot resume indexing while ${state}`);
+    }
+    setState('indexing');
+  }
+
+  function cancel(): void {
+    if (state !== 'indexing' && state !== 'paused') {
+      throw new Error(`Cannot cancel indexing while ${state}`);
+    }
+    setState('cancelled');
+  }
+
+  return { state: () => state, start, pause, resume, cancel };
+}
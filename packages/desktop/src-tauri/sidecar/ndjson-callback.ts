@@ -7,16 +7,35 @@
 // 2. Rust reads this from stdout, dispatches to NativeRuntime
 // 3. Rust writes back: {"type":"callback_response","id":"cb-xxx","result":{...}}
 // 4. Sidecar reads this from stdin and resolves the pending Promise
+//
+// A failed callback answers {"type":"callback_response","id":"cb-xxx","error":"...","detail":{"kind":...}}
+// where `detail` is the Rust SemblanceError. The Promise rejects with a
+// CallbackError carrying it, so handlers branch on `detail.kind`, and a request
+// failing on it passes `detail` back to Rust with its own error response.
+//
+// A callback that times out is abandoned with {"type":"callback_cancel","id":"cb-xxx"},
+// so Rust stops a generation behind it instead of running it to the end.
+
+/** The serialized Rust `SemblanceError` of a failed callback. */
+export type CallbackErrorDetail = { kind: string; message: string } & Record<string, unknown>;
+
+/** Rejection of a callback Rust answered with a `detail`. */
+export class CallbackError extends Error {
+  constructor(message: string, readonly detail: CallbackErrorDetail) {
+    super(message);
+    this.name = 'CallbackError';
+  }
+}
 
 export type CallbackResolver = {
   resolve: (value: unknown) => void;
-  reject: (reason: string) => void;
+  reject: (reason: string | CallbackError) => void;
   timeout: ReturnType<typeof setTimeout>;
 };
 
 export interface CallbackProtocol {
   sendCallback: (method: string, params: Record<string, unknown>) => Promise<unknown>;
-  handleCallbackResponse: (msg: { id: string; result?: unknown; error?: string }) => void;
+  handleCallbackResponse: (msg: { id: string; result?: unknown; error?: string; detail?: CallbackErrorDetail }) => void;
   pendingCallbacks: Map<string, CallbackResolver>;
 }
 
@@ -38,6 +57,7 @@ export function createCallbackProtocol(
 
       const timeout = setTimeout(() => {
         pendingCallbacks.delete(id);
+        writer(JSON.stringify({ type: 'callback_cancel', id }) + '\n');
         reject(`Callback ${method} timed out after ${timeoutMs}ms`);
       }, timeoutMs);
 
@@ -47,7 +67,7 @@ export function createCallbackProtocol(
     });
   }
 
-  function handleCallbackResponse(msg: { id: string; result?: unknown; error?: string }): void {
+  function handleCallbackResponse(msg: { id: string; result?: unknown; error?: string; detail?: CallbackErrorDetail }): void {
     const pending = pendingCallbacks.get(msg.id);
     if (!pending) {
       return;
@@ -57,7 +77,7 @@ export function createCallbackProtocol(
     pendingCallbacks.delete(msg.id);
 
     if (msg.error) {
-      pending.reject(msg.error);
+      pending.reject(msg.detail ? new CallbackError(msg.error, msg.detail) : msg.error);
     } else {
       pending.resolve(msg.result);
     }
@@ -0,0 +1,82 @@
+// Autonomy Reconciliation — keep the sidecar's autonomy tiers in line with the
+// tiers persisted in desktop settings.
+//
+// The sidecar keeps tiers in core.db, which can be reset or lost independently
+// of the Rust-side settings. After every `initialize` the sidecar's reported
+// config is compared against the persisted one: persisted tiers win and are
+// pushed back, and domains the sidecar knows but settings don't (new domains
+// added by an update) are adopted as-is.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct AutonomyTierChange {
+    pub domain: String,
+    /// The tier the sidecar reported.
+    pub sidecar_tier: String,
+    /// The tier that now applies.
+    pub tier: String,
+}
+
+/// Emitted as `semblance://autonomy-reconciled`.
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq, Eq)]
+pub struct AutonomyReconciliation {
+    /// Domains where the sidecar diverged and the persisted tier was pushed back.
+    pub restored: Vec<AutonomyTierChange>,
+    /// Domains unknown to settings whose sidecar tier was adopted and persisted.
+    pub adopted: Vec<AutonomyTierChange>,
+    /// Restores the sidecar rejected, with the error.
+    pub failed: Vec<(String, String)>,
+    pub unchanged: usize,
+}
+
+/// Compare `persisted` tiers with the sidecar's `reported` ones. Returns the
+/// domains to push back and the domains to adopt, both sorted by domain.
+/// Persisted domains the sidecar doesn't report are left alone.
+pub fn plan(
+    persisted: &HashMap<String, String>,
+    reported: &HashMap<String, String>,
+) -> AutonomyReconciliation {
+    let mut plan = AutonomyReconciliation::default();
+    for (domain, sidecar_tier) in reported {
+        let change = |tier: &str| AutonomyTierChange {
+            domain: domain.clone(),
+            sidecar_tier: sidecar_tier.clone(),
+            tier: tier.to_string(),
+        };
+        match persisted.get(domain) {
+            Some(tier) if tier == sidecar_tier => plan.unchanged += 1,
+            Some(tier) => plan.restored.push(change(tier)),
+            None => plan.adopted.push(change(sidecar_tier)),
+        }
+    }
+    plan.restored.sort_by(|a, b| a.domain.cmp(&b.domain));
+    plan.adopted.sort_by(|a, b| a.domain.cmp(&b.domain));
+    plan
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tiers(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+        pairs.iter().map(|(d, t)| (d.to_string(), t.to_string())).collect()
+    }
+
+    #[test]
+    fn test_plan_restores_diverged_and_adopts_new_domains() {
+        let persisted = tiers(&[("email", "alter_ego"), ("calendar", "partner"), ("legacy", "guardian")]);
+        let reported = tiers(&[("email", "partner"), ("calendar", "partner"), ("health", "guardian")]);
+        let plan = plan(&persisted, &reported);
+
+        assert_eq!(plan.restored, vec![AutonomyTierChange {
+            domain: "email".to_string(),
+            sidecar_tier: "partner".to_string(),
+            tier: "alter_ego".to_string(),
+        }]);
+        assert_eq!(plan.adopted.len(), 1);
+        assert_eq!((plan.adopted[0].domain.as_str(), plan.adopted[0].tier.as_str()), ("health", "guardian"));
+        assert_eq!(plan.unchanged, 1);
+    }
+}
@@ -0,0 +1,140 @@
+// Bridge Metrics — per-method latency of sidecar requests, for diagnosing
+// "the app feels slow".
+//
+// Every request `SidecarBridge` sends is timed from before it joins the write
+// queue until its response (or error, or timeout) arrives, so a backlog of
+// queued writes shows up in the numbers instead of hiding in front of them.
+// The writer task separately reports how long each request took to get onto
+// stdin, queueing included. Only the most recent `SAMPLES_PER_METHOD` requests
+// of each method feed the percentiles; counts and maxima cover the bridge's
+// lifetime. In memory only.
+
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::time::Duration;
+
+/// Recent requests per method the percentiles are computed over.
+const SAMPLES_PER_METHOD: usize = 256;
+
+#[derive(Default)]
+struct MethodSamples {
+    count: u64,
+    errors: u64,
+    max: Duration,
+    round_trips: VecDeque<Duration>,
+    writes: VecDeque<Duration>,
+}
+
+#[derive(Default)]
+pub struct BridgeMetrics {
+    methods: HashMap<String, MethodSamples>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct MethodLatency {
+    pub count: u64,
+    /// Requests that failed, timed out, or were cut short by a crash.
+    pub errors: u64,
+    pub p50_ms: f64,
+    pub p95_ms: f64,
+    pub max_ms: f64,
+    /// Time to get the request onto stdin, queueing included.
+    pub write_p95_ms: f64,
+}
+
+fn push_sample(samples: &mut VecDeque<Duration>, sample: Duration) {
+    if samples.len() == SAMPLES_PER_METHOD {
+        samples.pop_front();
+    }
+    samples.push_back(sample);
+}
+
+/// Nearest-rank percentile of `samples` in ms; 0 when empty.
+fn percentile_ms(samples: &VecDeque<Duration>, pct: f64) -> f64 {
+    if samples.is_empty() {
+        return 0.0;
+    }
+    let mut sorted: Vec<Duration> = samples.iter().copied().collect();
+    sorted.sort_unstable();
+    let rank = ((pct / 100.0) * sorted.len() as f64).ceil() as usize;
+    as_ms(sorted[rank.clamp(1, sorted.len()) - 1])
+}
+
+fn as_ms(duration: Duration) -> f64 {
+    (duration.as_secs_f64() * 1000.0 * 100.0).round() / 100.0
+}
+
+impl BridgeMetrics {
+    /// Record one request to `method` that resolved after `round_trip`.
+    pub fn record(&mut self, method: &str, round_trip: Duration, ok: bool) {
+        let samples = self.methods.entry(method.to_string()).or_default();
+        samples.count += 1;
+        if !ok {
+            samples.errors += 1;
+        }
+        samples.max = samples.max.max(round_trip);
+        push_sample(&mut samples.round_trips, round_trip);
+    }
+
+    /// Record that a request to `method` reached stdin `write` after it was queued.
+    pub fn record_write(&mut self, method: &str, write: Duration) {
+        push_sample(&mut self.methods.entry(method.to_string()).or_default().writes, write);
+    }
+
+    pub fn snapshot(&self) -> BTreeMap<String, MethodLatency> {
+        self.methods
+            .iter()
+            .map(|(method, samples)| {
+                let latency = MethodLatency {
+                    count: samples.count,
+                    errors: samples.errors,
+                    p50_ms: percentile_ms(&samples.round_trips, 50.0),
+                    p95_ms: percentile_ms(&samples.round_trips, 95.0),
+                    max_ms: as_ms(samples.max),
+                    write_p95_ms: percentile_ms(&samples.writes, 95.0),
+                };
+                (method.clone(), latency)
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bridge_metrics_percentiles_per_method() {
+        let mut metrics = BridgeMetrics::default();
+        for ms in 1..=100 {
+            metrics.record_write("get_knowledge_stats", Duration::from_millis(1));
+            metrics.record("get_knowledge_stats", Duration::from_millis(ms), ms != 100);
+        }
+        metrics.record_write("ping", Duration::from_micros(250));
+        metrics.record("ping", Duration::from_micros(1500), true);
+
+        let snapshot = metrics.snapshot();
+        let stats = &snapshot["get_knowledge_stats"];
+        assert_eq!(stats.count, 100);
+        assert_eq!(stats.errors, 1);
+        assert_eq!(stats.p50_ms, 50.0);
+        assert_eq!(stats.p95_ms, 95.0);
+        assert_eq!(stats.max_ms, 100.0);
+        assert_eq!(stats.write_p95_ms, 1.0);
+        assert_eq!(snapshot["ping"].p50_ms, 1.5);
+        assert_eq!(snapshot["ping"].write_p95_ms, 0.25);
+    }
+
+    #[test]
+    fn test_bridge_metrics_keep_lifetime_max_beyond_window() {
+        let mut metrics = BridgeMetrics::default();
+        metrics.record("send_message", Duration::from_secs(9), true);
+        for _ in 0..SAMPLES_PER_METHOD {
+            metrics.record("send_message", Duration::from_millis(10), true);
+        }
+        let stats = &metrics.snapshot()["send_message"];
+        assert_eq!(stats.count, SAMPLES_PER_METHOD as u64 + 1);
+        assert_eq!(stats.p95_ms, 10.0);
+        assert_eq!(stats.max_ms, 9000.0);
+    }
+}
@@ -0,0 +1,134 @@
+// Chat Stream — the typed contract for `semblance://chat-token` events.
+//
+// `send_message` returns a response id right away and the sidecar streams the
+// reply as `chat-token` events. Each event is deserialized into `ChatToken`
+// before it is forwarded, so a malformed stream is logged and dropped here
+// instead of surfacing as garbage in the webview. `ChatStreams` tracks the
+// streams that have finished or been cancelled: tokens still arriving for a
+// cancelled response (the sidecar keeps generating) are dropped, as is its
+// `chat-complete`.
+
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+
+/// Closed response ids remembered; older ones are forgotten, by which time
+/// their sidecar generation has long finished.
+const MAX_CLOSED_STREAMS: usize = 64;
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ChatToken {
+    /// The `responseId` returned by `send_message`.
+    pub response_id: String,
+    /// Text to append; empty on the final event.
+    #[serde(default)]
+    pub delta: String,
+    /// Set on the last event of the stream.
+    #[serde(default)]
+    pub done: bool,
+    /// Why the stream ended early, on the final event.
+    #[serde(default)]
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Closed {
+    Finished,
+    Cancelled,
+}
+
+#[derive(Default)]
+pub struct ChatStreams {
+    closed: VecDeque<(String, Closed)>,
+}
+
+impl ChatStreams {
+    fn closed(&self, response_id: &str) -> Option<Closed> {
+        self.closed.iter().find(|(id, _)| id == response_id).map(|(_, how)| *how)
+    }
+
+    fn close(&mut self, response_id: &str, how: Closed) {
+        self.closed.retain(|(id, _)| id != response_id);
+        if self.closed.len() == MAX_CLOSED_STREAMS {
+            self.closed.pop_front();
+        }
+        self.closed.push_back((response_id.to_string(), how));
+    }
+
+    /// Whether `token` should be forwarded. Tokens after the stream's `done`
+    /// event or after it was cancelled are orphans and dropped.
+    pub fn accept(&mut self, token: &ChatToken) -> bool {
+        if self.closed(&token.response_id).is_some() {
+            return false;
+        }
+        if token.done {
+            self.close(&token.response_id, Closed::Finished);
+        }
+        true
+    }
+
+    /// Whether the `chat-complete` for `response_id` should be forwarded.
+    pub fn accept_complete(&self, response_id: &str) -> bool {
+        self.closed(response_id) != Some(Closed::Cancelled)
+    }
+
+    /// Stop forwarding a response. Returns false if it had already finished
+    /// or been cancelled.
+    pub fn cancel(&mut self, response_id: &str) -> bool {
+        if self.closed(response_id).is_some() {
+            return false;
+        }
+        self.close(response_id, Closed::Cancelled);
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn token(id: &str, delta: &str, done: bool) -> ChatToken {
+        ChatToken { response_id: id.to_string(), delta: delta.to_string(), done, error: None }
+    }
+
+    #[test]
+    fn test_chat_token_rejects_malformed_events() {
+        let parsed: ChatToken =
+            serde_json::from_value(serde_json::json!({"responseId": "msg_1", "delta": "Hi"})).unwrap();
+        assert_eq!(parsed, token("msg_1", "Hi", false));
+        // The old untyped payload was a bare string.
+        assert!(serde_json::from_value::<ChatToken>(serde_json::json!("Hi")).is_err());
+        assert!(serde_json::from_value::<ChatToken>(serde_json::json!({"delta": "Hi"})).is_err());
+    }
+
+    #[test]
+    fn test_chat_streams_drop_orphaned_tokens() {
+        let mut streams = ChatStreams::default();
+        assert!(streams.accept(&token("a", "Hel", false)));
+        assert!(streams.accept(&token("a", "", true)));
+        assert!(!streams.accept(&token("a", "lo", false)));
+        assert!(streams.accept_complete("a"));
+        assert!(!streams.cancel("a"));
+
+        assert!(streams.accept(&token("b", "One", false)));
+        assert!(streams.cancel("b"));
+        assert!(!streams.accept(&token("b", " two", false)));
+        assert!(!streams.accept(&token("b", "", true)));
+        assert!(!streams.accept_complete("b"));
+
+        // A response cancelled before its first token never streams.
+        assert!(streams.cancel("c"));
+        assert!(!streams.accept(&token("c", "x", false)));
+    }
+
+    #[test]
+    fn test_chat_streams_forget_oldest_closed() {
+        let mut streams = ChatStreams::default();
+        for i in 0..=MAX_CLOSED_STREAMS {
+            streams.cancel(&format!("msg_{}", i));
+        }
+        assert_eq!(streams.closed.len(), MAX_CLOSED_STREAMS);
+        assert!(streams.accept(&token("msg_0", "late", false)));
+        assert!(!streams.accept(&token("msg_1", "late", false)));
+    }
+}
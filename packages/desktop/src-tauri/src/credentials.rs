@@ -0,0 +1,193 @@
+// Credential Validation — catch mistyped server settings before they reach the
+// sidecar.
+//
+// `add_credential` used to forward whatever the form sent, so an IMAP account
+// on the SMTP submission port or a host pasted as a URL only failed at the
+// first connection, after a 10s timeout, with an error that didn't say which
+// field was wrong. Fields are checked and normalized here instead; a failure
+// names the field so the form can highlight it.
+
+/// Protocols the gateway's credential store accepts, by service type.
+const PROTOCOLS: &[(&str, &str)] = &[("imap", "email"), ("smtp", "email"), ("caldav", "calendar")];
+
+/// Well-known ports of each protocol, with implicit TLS or not.
+const WELL_KNOWN_PORTS: &[(u16, &str, bool)] = &[
+    (143, "imap", false),
+    (993, "imap", true),
+    (25, "smtp", false),
+    (465, "smtp", true),
+    (587, "smtp", false),
+    (80, "caldav", false),
+    (443, "caldav", true),
+];
+
+/// A field of an `add_credential` request that failed validation.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CredentialFieldError {
+    /// The parameter name, as the form sends it (`host`, `port`, ...).
+    pub field: &'static str,
+    pub message: String,
+}
+
+fn field_error(field: &'static str, message: impl Into<String>) -> CredentialFieldError {
+    CredentialFieldError { field, message: message.into() }
+}
+
+/// An `add_credential` request after validation: trimmed, with service type,
+/// protocol and host lowercased.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CredentialSettings {
+    pub service_type: String,
+    pub protocol: String,
+    pub host: String,
+    pub port: u16,
+    pub username: String,
+    pub use_tls: bool,
+    pub display_name: String,
+}
+
+fn check_no_control_chars(field: &'static str, value: &str) -> Result<(), CredentialFieldError> {
+    if value.chars().any(char::is_control) {
+        return Err(field_error(field, "Contains control characters"));
+    }
+    Ok(())
+}
+
+fn check_host(host: &str) -> Result<String, CredentialFieldError> {
+    let host = host.trim().trim_end_matches('.').to_ascii_lowercase();
+    if host.is_empty() {
+        return Err(field_error("host", "Server address is required"));
+    }
+    check_no_control_chars("host", &host)?;
+    if host.contains("://") || host.contains('/') {
+        return Err(field_error("host", "Enter only the server name (like imap.example.com), not a URL"));
+    }
+    if host.contains('@') {
+        return Err(field_error("host", "Enter the server name (like imap.example.com), not an email address"));
+    }
+    if host.chars().any(char::is_whitespace) {
+        return Err(field_error("host", "Server address can't contain spaces"));
+    }
+    // A bracketed IPv6 address keeps its colons; anything else with one has a port in it.
+    if !host.starts_with('[') && host.contains(':') {
+        return Err(field_error("host", "Enter the port in the port field, not after the server name"));
+    }
+    Ok(host)
+}
+
+/// Reject a port that belongs to another protocol, like IMAP on 587. Implicit
+/// TLS ports used with TLS off are only logged: some servers really do that.
+fn check_port(protocol: &str, port: u16, use_tls: bool) -> Result<(), CredentialFieldError> {
+    if port == 0 {
+        return Err(field_error("port", "Port must be between 1 and 65535"));
+    }
+    let Some(&(_, port_protocol, implicit_tls)) = WELL_KNOWN_PORTS.iter().find(|(p, _, _)| *p == port) else {
+        return Ok(());
+    };
+    if port_protocol != protocol {
+        let usual: Vec<String> = WELL_KNOWN_PORTS
+            .iter()
+            .filter(|(_, p, _)| *p == protocol)
+            .map(|(p, _, _)| p.to_string())
+            .collect();
+        return Err(field_error(
+            "port",
+            format!(
+                "Port {} is for {}; {} usually uses {}",
+                port,
+                port_protocol.to_uppercase(),
+                protocol.to_uppercase(),
+                usual.join(" or ")
+            ),
+        ));
+    }
+    if implicit_tls && !use_tls {
+        eprintln!("[credentials] {} on port {} with TLS off; the server likely expects TLS", protocol, port);
+    }
+    Ok(())
+}
+
+/// Validate and normalize the fields of an `add_credential` request. The
+/// password is passed through as typed.
+pub fn validate(
+    service_type: &str,
+    protocol: &str,
+    host: &str,
+    port: u16,
+    username: &str,
+    use_tls: bool,
+    display_name: &str,
+) -> Result<CredentialSettings, CredentialFieldError> {
+    let service_type = service_type.trim().to_ascii_lowercase();
+    let protocol = protocol.trim().to_ascii_lowercase();
+    let Some(&(_, protocol_service)) = PROTOCOLS.iter().find(|(p, _)| *p == protocol) else {
+        return Err(field_error("protocol", "Protocol must be IMAP, SMTP or CalDAV"));
+    };
+    if !PROTOCOLS.iter().any(|(_, s)| *s == service_type) {
+        return Err(field_error("serviceType", "Service type must be email or calendar"));
+    }
+    if protocol_service != service_type {
+        return Err(field_error(
+            "protocol",
+            format!("{} is not a {} protocol", protocol.to_uppercase(), service_type),
+        ));
+    }
+    let host = check_host(host)?;
+    check_port(&protocol, port, use_tls)?;
+    check_no_control_chars("username", username)?;
+    check_no_control_chars("displayName", display_name)?;
+    Ok(CredentialSettings {
+        service_type,
+        protocol,
+        host,
+        port,
+        username: username.trim().to_string(),
+        use_tls,
+        display_name: display_name.trim().to_string(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn imap(host: &str, port: u16) -> Result<CredentialSettings, CredentialFieldError> {
+        validate("email", "imap", host, port, "me@example.com", true, "Work")
+    }
+
+    #[test]
+    fn test_validate_normalizes_fields() {
+        let settings = validate(" Email ", "IMAP", " Imap.Example.com. ", 993, " me@example.com ", true, " Work ").unwrap();
+        assert_eq!(settings.service_type, "email");
+        assert_eq!(settings.protocol, "imap");
+        assert_eq!(settings.host, "imap.example.com");
+        assert_eq!(settings.username, "me@example.com");
+        assert_eq!(settings.display_name, "Work");
+        // Nonstandard and IPv6 hosts are fine.
+        assert!(imap("[::1]", 1143).is_ok());
+    }
+
+    #[test]
+    fn test_validate_names_the_bad_field() {
+        let field = |r: Result<CredentialSettings, CredentialFieldError>| r.unwrap_err().field;
+        assert_eq!(field(validate("email", "pop3", "mail.example.com", 995, "", true, "")), "protocol");
+        assert_eq!(field(validate("calendar", "smtp", "mail.example.com", 587, "", true, "")), "protocol");
+        assert_eq!(field(validate("contacts", "caldav", "dav.example.com", 443, "", true, "")), "serviceType");
+        assert_eq!(field(imap("  ", 993)), "host");
+        assert_eq!(field(imap("https://imap.example.com", 993)), "host");
+        assert_eq!(field(imap("imap.example.com:993", 993)), "host");
+        assert_eq!(field(imap("imap.example\n.com", 993)), "host");
+        assert_eq!(field(imap("imap.example.com", 0)), "port");
+        assert_eq!(field(validate("email", "imap", "imap.example.com", 993, "me\u{0}", true, "")), "username");
+    }
+
+    #[test]
+    fn test_validate_rejects_other_protocols_ports() {
+        let error = imap("imap.example.com", 587).unwrap_err();
+        assert_eq!(error.field, "port");
+        assert_eq!(error.message, "Port 587 is for SMTP; IMAP usually uses 143 or 993");
+        assert!(validate("email", "smtp", "smtp.example.com", 993, "", true, "").is_err());
+        // A protocol's own ports pass, with or without TLS.
+        assert!(validate("email", "smtp", "smtp.example.com", 465, "", false, "").is_ok());
+    }
+}
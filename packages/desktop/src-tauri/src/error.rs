@@ -0,0 +1,332 @@
+// Semblance Error — the error type every Tauri command returns.
+//
+// Commands used to fail with a plain `String`, leaving the frontend to match on
+// error text to tell "sidecar starting" from a timeout or a missing model.
+// `SemblanceError` serializes to a tagged object the UI can switch on:
+//
+//   {"kind": "timeout", "method": "get_indexing_status", "timeoutMs": 5000,
+//    "message": "Sidecar request get_indexing_status timed out (5s)"}
+//
+// `message` is the `Display` text, identical to the string the command used to
+// return, so logs and fallback UI stay readable. The native runtime's
+// `RuntimeError` keeps its kind; errors raised as `String` inside the app are
+// `Other`. A runtime error that fails a sidecar request on its way back (the
+// sidecar's `native_*` callbacks) travels as this serialized object, so it
+// keeps its kind too; see `from_sidecar`.
+
+use crate::credentials::CredentialFieldError;
+use crate::native_runtime::{ContextOverflow, InsufficientMemory, ModelKind, PromptTooLarge, RuntimeError, NO_REASONING_MODEL};
+use serde::ser::{Serialize, SerializeMap, Serializer};
+use serde_json::Value;
+use std::time::Duration;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum SemblanceError {
+    /// The sidecar process is still being spawned and initialized.
+    SidecarNotReady,
+    /// The sidecar didn't answer `method` within `timeout`.
+    Timeout { method: String, timeout: Duration },
+    /// The sidecar exited with the request in flight; `restarting` if it is
+    /// being respawned.
+    SidecarCrashed { restarting: bool },
+    /// The operation needs a reasoning model and none is loaded.
+    ModelNotLoaded,
+    /// The prompt leaves no room for the response; see `native_runtime::fit_context`.
+    ContextOverflow { prompt_tokens: usize, max_tokens: usize, n_ctx: usize },
+    /// The prompt is over the `max_prompt_tokens` limit; see `native_runtime::check_prompt_tokens`.
+    PromptTooLarge { prompt_tokens: usize, limit: usize },
+    /// A model load was refused before reading the weights: the estimate
+    /// doesn't fit in free memory. See `native_runtime::check_load_memory`.
+    InsufficientMemory { required_mb: u64, available_mb: u64 },
+    /// The user cancelled the model load; see `native_runtime::LoadCancel`.
+    ModelLoadCancelled,
+    /// `operation` needs these model slots unloaded first; see
+    /// `NativeRuntime::require_no_model_loaded`.
+    ModelLoaded { operation: String, kinds: Vec<ModelKind> },
+    /// The sidecar sent something that isn't a valid response.
+    Protocol { detail: String },
+    /// The sidecar handled the request and reported an error.
+    Sidecar { message: String },
+    /// A command argument is invalid; `field` names it for the form to highlight.
+    Validation { field: String, message: String },
+    /// Any other failure inside the desktop app (validation, I/O, settings).
+    Other { message: String },
+}
+
+impl SemblanceError {
+    /// The `kind` tag in the serialized form.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            SemblanceError::SidecarNotReady => "sidecar_not_ready",
+            SemblanceError::Timeout { .. } => "timeout",
+            SemblanceError::SidecarCrashed { .. } => "sidecar_crashed",
+            SemblanceError::ModelNotLoaded => "model_not_loaded",
+            SemblanceError::ContextOverflow { .. } => "context_overflow",
+            SemblanceError::PromptTooLarge { .. } => "prompt_too_large",
+            SemblanceError::InsufficientMemory { .. } => "insufficient_memory",
+            SemblanceError::ModelLoadCancelled => "model_load_cancelled",
+            SemblanceError::ModelLoaded { .. } => "model_loaded",
+            SemblanceError::Protocol { .. } => "protocol",
+            SemblanceError::Sidecar { .. } => "sidecar",
+            SemblanceError::Validation { .. } => "validation",
+            SemblanceError::Other { .. } => "other",
+        }
+    }
+
+    /// An error the sidecar reported. A native runtime error it passed
+    /// through comes with its serialized form as `detail` and keeps its kind.
+    pub fn from_sidecar(message: String, detail: Option<&Value>) -> Self {
+        detail
+            .and_then(Self::from_runtime_detail)
+            .unwrap_or(SemblanceError::Sidecar { message })
+    }
+
+    /// The runtime error kinds back from their serialized form.
+    fn from_runtime_detail(detail: &Value) -> Option<Self> {
+        let u64_field = |name: &str| detail.get(name).and_then(|v| v.as_u64());
+        match detail.get("kind")?.as_str()? {
+            "model_not_loaded" => Some(SemblanceError::ModelNotLoaded),
+            "context_overflow" => Some(SemblanceError::ContextOverflow {
+                prompt_tokens: u64_field("promptTokens")? as usize,
+                max_tokens: u64_field("maxTokens")? as usize,
+                n_ctx: u64_field("nCtx")? as usize,
+            }),
+            "prompt_too_large" => Some(SemblanceError::PromptTooLarge {
+                prompt_tokens: u64_field("promptTokens")? as usize,
+                limit: u64_field("limit")? as usize,
+            }),
+            "insufficient_memory" => Some(SemblanceError::InsufficientMemory {
+                required_mb: u64_field("requiredMb")?,
+                available_mb: u64_field("availableMb")?,
+            }),
+            "model_load_cancelled" => Some(SemblanceError::ModelLoadCancelled),
+            _ => None,
+        }
+    }
+}
+
+impl std::fmt::Display for SemblanceError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SemblanceError::SidecarNotReady => write!(f, "Sidecar is still starting"),
+            SemblanceError::Timeout { method, timeout } => {
+                write!(f, "Sidecar request {} timed out ({:?})", method, timeout)
+            }
+            SemblanceError::SidecarCrashed { restarting: true } => {
+                write!(f, "Sidecar restarted: the process exited unexpectedly")
+            }
+            SemblanceError::SidecarCrashed { restarting: false } => {
+                write!(f, "Sidecar process exited unexpectedly")
+            }
+            SemblanceError::ModelNotLoaded => write!(f, "{}", NO_REASONING_MODEL),
+            SemblanceError::ContextOverflow { prompt_tokens, max_tokens, n_ctx } => write!(
+                f,
+                "{}",
+                ContextOverflow { prompt_tokens: *prompt_tokens, max_tokens: *max_tokens, n_ctx: *n_ctx }
+            ),
+            SemblanceError::PromptTooLarge { prompt_tokens, limit } => {
+                write!(f, "{}", PromptTooLarge { prompt_tokens: *prompt_tokens, limit: *limit })
+            }
+            SemblanceError::InsufficientMemory { required_mb, available_mb } => write!(
+                f,
+                "{}",
+                InsufficientMemory { required_mb: *required_mb, available_mb: *available_mb }
+            ),
+            SemblanceError::ModelLoadCancelled => write!(f, "{}", RuntimeError::LoadCancelled),
+            SemblanceError::ModelLoaded { operation, kinds } => write!(
+                f,
+                "{}",
+                RuntimeError::ModelLoaded { operation: operation.clone(), kinds: kinds.clone() }
+            ),
+            SemblanceError::Protocol { detail } => write!(f, "{}", detail),
+            SemblanceError::Sidecar { message }
+            | SemblanceError::Validation { message, .. }
+            | SemblanceError::Other { message } => write!(f, "{}", message),
+        }
+    }
+}
+
+impl std::error::Error for SemblanceError {}
+
+impl Serialize for SemblanceError {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(None)?;
+        map.serialize_entry("kind", self.kind())?;
+        match self {
+            SemblanceError::Timeout { method, timeout } => {
+                map.serialize_entry("method", method)?;
+                map.serialize_entry("timeoutMs", &(timeout.as_millis() as u64))?;
+            }
+            SemblanceError::SidecarCrashed { restarting } => map.serialize_entry("restarting", restarting)?,
+            SemblanceError::ContextOverflow { prompt_tokens, max_tokens, n_ctx } => {
+                map.serialize_entry("promptTokens", prompt_tokens)?;
+                map.serialize_entry("maxTokens", max_tokens)?;
+                map.serialize_entry("nCtx", n_ctx)?;
+            }
+            SemblanceError::PromptTooLarge { prompt_tokens, limit } => {
+                map.serialize_entry("promptTokens", prompt_tokens)?;
+                map.serialize_entry("limit", limit)?;
+            }
+            SemblanceError::InsufficientMemory { required_mb, available_mb } => {
+                map.serialize_entry("requiredMb", required_mb)?;
+                map.serialize_entry("availableMb", available_mb)?;
+            }
+            SemblanceError::ModelLoaded { operation, kinds } => {
+                map.serialize_entry("operation", operation)?;
+                map.serialize_entry("kinds", kinds)?;
+            }
+            SemblanceError::Protocol { detail } => map.serialize_entry("detail", detail)?,
+            SemblanceError::Validation { field, .. } => map.serialize_entry("field", field)?,
+            SemblanceError::SidecarNotReady
+            | SemblanceError::ModelNotLoaded
+            | SemblanceError::ModelLoadCancelled
+            | SemblanceError::Sidecar { .. }
+            | SemblanceError::Other { .. } => {}
+        }
+        map.serialize_entry("message", &self.to_string())?;
+        map.end()
+    }
+}
+
+impl From<ContextOverflow> for SemblanceError {
+    fn from(overflow: ContextOverflow) -> Self {
+        SemblanceError::ContextOverflow {
+            prompt_tokens: overflow.prompt_tokens,
+            max_tokens: overflow.max_tokens,
+            n_ctx: overflow.n_ctx,
+        }
+    }
+}
+
+impl From<PromptTooLarge> for SemblanceError {
+    fn from(too_large: PromptTooLarge) -> Self {
+        SemblanceError::PromptTooLarge { prompt_tokens: too_large.prompt_tokens, limit: too_large.limit }
+    }
+}
+
+impl From<InsufficientMemory> for SemblanceError {
+    fn from(shortfall: InsufficientMemory) -> Self {
+        SemblanceError::InsufficientMemory { required_mb: shortfall.required_mb, available_mb: shortfall.available_mb }
+    }
+}
+
+impl From<RuntimeError> for SemblanceError {
+    fn from(error: RuntimeError) -> Self {
+        match error {
+            RuntimeError::NoReasoningModel => SemblanceError::ModelNotLoaded,
+            RuntimeError::ContextOverflow(overflow) => overflow.into(),
+            RuntimeError::PromptTooLarge(too_large) => too_large.into(),
+            RuntimeError::InsufficientMemory(shortfall) => shortfall.into(),
+            RuntimeError::LoadCancelled => SemblanceError::ModelLoadCancelled,
+            RuntimeError::ModelLoaded { operation, kinds } => SemblanceError::ModelLoaded { operation, kinds },
+            RuntimeError::Failed(message) => SemblanceError::Other { message },
+        }
+    }
+}
+
+impl From<CredentialFieldError> for SemblanceError {
+    fn from(error: CredentialFieldError) -> Self {
+        SemblanceError::Validation { field: error.field.to_string(), message: error.message }
+    }
+}
+
+impl From<String> for SemblanceError {
+    fn from(message: String) -> Self {
+        SemblanceError::Other { message }
+    }
+}
+
+impl From<&str> for SemblanceError {
+    fn from(message: &str) -> Self {
+        message.to_string().into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_display_keeps_legacy_strings() {
+        let timeout = SemblanceError::Timeout { method: "initialize".to_string(), timeout: Duration::from_secs(30) };
+        assert_eq!(timeout.to_string(), "Sidecar request initialize timed out (30s)");
+        assert_eq!(SemblanceError::SidecarNotReady.to_string(), "Sidecar is still starting");
+        assert_eq!(
+            SemblanceError::SidecarCrashed { restarting: false }.to_string(),
+            "Sidecar process exited unexpectedly"
+        );
+        assert_eq!(SemblanceError::ModelNotLoaded.to_string(), NO_REASONING_MODEL);
+    }
+
+    #[test]
+    fn test_serializes_to_tagged_object() {
+        let timeout = SemblanceError::Timeout { method: "ping".to_string(), timeout: Duration::from_millis(2500) };
+        assert_eq!(
+            serde_json::to_value(&timeout).unwrap(),
+            json!({"kind": "timeout", "method": "ping", "timeoutMs": 2500, "message": "Sidecar request ping timed out (2.5s)"})
+        );
+        assert_eq!(
+            serde_json::to_value(SemblanceError::Sidecar { message: "Core not initialized".to_string() }).unwrap(),
+            json!({"kind": "sidecar", "message": "Core not initialized"})
+        );
+    }
+
+    #[test]
+    fn test_runtime_errors_keep_their_kind() {
+        assert_eq!(SemblanceError::from(RuntimeError::NoReasoningModel), SemblanceError::ModelNotLoaded);
+        let overflow = ContextOverflow { prompt_tokens: 3600, max_tokens: 512, n_ctx: 4096 };
+        let error = SemblanceError::from(RuntimeError::from(overflow.clone()));
+        assert_eq!(error, SemblanceError::ContextOverflow { prompt_tokens: 3600, max_tokens: 512, n_ctx: 4096 });
+        assert_eq!(error.to_string(), overflow.to_string());
+        let shortfall = InsufficientMemory { required_mb: 14208, available_mb: 8192 };
+        let error = SemblanceError::from(RuntimeError::from(shortfall.clone()));
+        assert_eq!(error, SemblanceError::InsufficientMemory { required_mb: 14208, available_mb: 8192 });
+        assert_eq!(
+            serde_json::to_value(&error).unwrap(),
+            json!({"kind": "insufficient_memory", "requiredMb": 14208, "availableMb": 8192, "message": shortfall.to_string()})
+        );
+        assert_eq!(SemblanceError::from(RuntimeError::LoadCancelled).to_string(), "Model load cancelled");
+        let loaded = SemblanceError::from(RuntimeError::ModelLoaded {
+            operation: "change the model storage directory".to_string(),
+            kinds: vec![ModelKind::Reasoning, ModelKind::Embedding],
+        });
+        assert_eq!(
+            serde_json::to_value(&loaded).unwrap(),
+            json!({
+                "kind": "model_loaded",
+                "operation": "change the model storage directory",
+                "kinds": ["reasoning", "embedding"],
+                "message": "Cannot change the model storage directory while a model is loaded (reasoning, embedding) — unload the model first",
+            })
+        );
+        // Only the type carries the kind: the same text as a String is `Other`.
+        assert_eq!(
+            SemblanceError::from(NO_REASONING_MODEL),
+            SemblanceError::Other { message: NO_REASONING_MODEL.to_string() }
+        );
+    }
+
+    #[test]
+    fn test_from_sidecar_reads_runtime_detail() {
+        for error in [
+            SemblanceError::ModelNotLoaded,
+            SemblanceError::ContextOverflow { prompt_tokens: 3600, max_tokens: 512, n_ctx: 4096 },
+            SemblanceError::PromptTooLarge { prompt_tokens: 9000, limit: 8192 },
+            SemblanceError::InsufficientMemory { required_mb: 14208, available_mb: 8192 },
+            SemblanceError::ModelLoadCancelled,
+        ] {
+            let detail = serde_json::to_value(&error).unwrap();
+            assert_eq!(SemblanceError::from_sidecar(error.to_string(), Some(&detail)), error);
+        }
+        let other = serde_json::to_value(SemblanceError::from("Missing text parameter")).unwrap();
+        assert_eq!(
+            SemblanceError::from_sidecar("Missing text parameter".to_string(), Some(&other)),
+            SemblanceError::Sidecar { message: "Missing text parameter".to_string() }
+        );
+        assert_eq!(
+            SemblanceError::from_sidecar("Core not initialized".to_string(), None),
+            SemblanceError::Sidecar { message: "Core not initialized".to_string() }
+        );
+    }
+}
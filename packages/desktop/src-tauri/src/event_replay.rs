@@ -0,0 +1,136 @@
+// Event Replay — recent sidecar events kept for windows that subscribe late.
+//
+// Sidecar events are forwarded to the webview as fire-and-forget Tauri events, so
+// a window opened after a status or progress update shows a blank state until
+// the next one arrives. Every forwarded event goes through `emit` here, which
+// keeps the last few events of each state-carrying channel so a new window can
+// catch up via `replay_recent_events`. Streaming channels (chat tokens) are
+// transient and never kept.
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+use tauri::Emitter;
+
+/// Channels whose latest events describe current state and are worth replaying.
+/// Progress channels keep several entries since multiple downloads/imports can
+/// be in flight at once.
+pub const REPLAYABLE_CHANNELS: &[&str] = &[
+    "status-update",
+    "sidecar-ready",
+    "sidecar-failed",
+    "indexing-progress",
+    "indexing-complete",
+    "indexing-state-changed",
+    "model-download-progress",
+    "native-model-loaded",
+    "import:progress",
+    "canvas:update",
+];
+
+/// Most recent events kept per channel.
+pub const EVENTS_PER_CHANNEL: usize = 8;
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct RecordedEvent {
+    /// Global emission order across channels.
+    pub seq: u64,
+    /// Full Tauri event name, e.g. `semblance://status-update`.
+    pub event: String,
+    pub data: Value,
+    pub emitted_at_ms: u64,
+}
+
+#[derive(Default)]
+pub struct EventReplayBuffer {
+    next_seq: u64,
+    channels: HashMap<String, VecDeque<RecordedEvent>>,
+}
+
+pub type SharedEventReplay = Arc<Mutex<EventReplayBuffer>>;
+
+impl EventReplayBuffer {
+    /// Keep `data` if `channel` is replayable. Returns whether it was kept.
+    pub fn record(&mut self, channel: &str, data: &Value, emitted_at_ms: u64) -> bool {
+        if !REPLAYABLE_CHANNELS.contains(&channel) {
+            return false;
+        }
+        self.next_seq += 1;
+        let queue = self.channels.entry(channel.to_string()).or_default();
+        if queue.len() == EVENTS_PER_CHANNEL {
+            queue.pop_front();
+        }
+        queue.push_back(RecordedEvent {
+            seq: self.next_seq,
+            event: format!("semblance://{}", channel),
+            data: data.clone(),
+            emitted_at_ms,
+        });
+        true
+    }
+
+    /// Kept events for `channels` (all replayable channels if `None`), oldest first.
+    pub fn recent(&self, channels: Option<&[String]>) -> Vec<RecordedEvent> {
+        let mut events: Vec<RecordedEvent> = self
+            .channels
+            .iter()
+            .filter(|(name, _)| channels.is_none_or(|wanted| wanted.iter().any(|w| w == *name)))
+            .flat_map(|(_, queue)| queue.iter().cloned())
+            .collect();
+        events.sort_by_key(|e| e.seq);
+        events
+    }
+}
+
+pub fn create_buffer() -> SharedEventReplay {
+    Arc::new(Mutex::new(EventReplayBuffer::default()))
+}
+
+/// Emit `semblance://<channel>` to the webview, recording it for replay first.
+pub fn emit(app: &tauri::AppHandle, replay: &SharedEventReplay, channel: &str, data: &Value) {
+    let now_ms = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0);
+    replay
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .record(channel, data, now_ms);
+    let _ = app.emit(&format!("semblance://{}", channel), data);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_only_replayable_channels_are_kept() {
+        let mut buffer = EventReplayBuffer::default();
+        assert!(buffer.record("status-update", &json!({"ollamaStatus": "connected"}), 1));
+        assert!(!buffer.record("chat-token", &json!("hello"), 2));
+        let events = buffer.recent(None);
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].event, "semblance://status-update");
+    }
+
+    #[test]
+    fn test_recent_is_bounded_and_ordered() {
+        let mut buffer = EventReplayBuffer::default();
+        for i in 0..(EVENTS_PER_CHANNEL as u64 + 3) {
+            buffer.record("indexing-progress", &json!({ "filesScanned": i }), i);
+            if i == 4 {
+                buffer.record("status-update", &json!({}), i);
+            }
+        }
+        let events = buffer.recent(None);
+        assert_eq!(events.len(), EVENTS_PER_CHANNEL + 1);
+        assert!(events.windows(2).all(|w| w[0].seq < w[1].seq));
+
+        let progress = buffer.recent(Some(&["indexing-progress".to_string()]));
+        assert_eq!(progress.len(), EVENTS_PER_CHANNEL);
+        assert_eq!(progress[0].data, json!({ "filesScanned": 3 }));
+        assert_eq!(progress.last().unwrap().data, json!({ "filesScanned": EVENTS_PER_CHANNEL as u64 + 2 }));
+    }
+}
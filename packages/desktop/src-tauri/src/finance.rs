@@ -0,0 +1,418 @@
+// Finance Helpers — Rust-side analysis of data returned by the sidecar finance path.
+// Statement parsing and recurring-charge detection live in the sidecar
+// (RecurringDetector); this module only post-processes what it returns, and
+// decodes statement files before the sidecar sees them: its parser assumes
+// UTF-8, and bank CSVs are often Latin-1, whose rows with accented merchant
+// names it dropped. The format is detected here too, so a PDF statement is
+// refused by name instead of failing to parse.
+// CRITICAL: No network calls. Pure local computation.
+
+use serde::{Deserialize, Serialize};
+
+/// Subset of the sidecar's `RecurringCharge` needed for duplicate detection.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct SubscriptionCharge {
+    pub id: String,
+    pub merchant_name: String,
+    pub amount: f64,
+    pub frequency: String,
+    #[serde(default)]
+    pub charge_count: u32,
+    #[serde(default)]
+    pub status: String,
+}
+
+/// A group of charges that look like one subscription split by merchant-name
+/// variations. Suggestion only — merging requires explicit user confirmation.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct SubscriptionMergeCandidate {
+    /// Charge IDs in the group. The first is the suggested primary (most charges).
+    pub charge_ids: Vec<String>,
+    pub merchant_names: Vec<String>,
+    pub suggested_merchant: String,
+    pub frequency: String,
+    pub amount_min: f64,
+    pub amount_max: f64,
+    /// Weakest merchant-name similarity among the matches that formed the group (0.0–1.0).
+    pub similarity: f32,
+}
+
+/// Merchant-name similarity at or above which two charges are considered the same merchant.
+const MERCHANT_SIMILARITY_THRESHOLD: f32 = 0.8;
+/// Maximum relative amount difference for two charges to be considered the same subscription.
+const AMOUNT_TOLERANCE: f64 = 0.10;
+
+/// Tokens that carry no merchant identity ("NETFLIX.COM" → "netflix").
+const MERCHANT_NOISE_TOKENS: &[&str] = &[
+    "com", "net", "org", "www", "inc", "llc", "ltd", "co", "corp", "the", "pmt", "payment",
+];
+
+/// Normalize a merchant name for fuzzy comparison: lowercase, split on
+/// non-alphanumerics, drop noise tokens and reference codes containing digits.
+pub fn normalize_merchant(name: &str) -> String {
+    name.to_lowercase()
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|t| !t.is_empty())
+        .filter(|t| !MERCHANT_NOISE_TOKENS.contains(t))
+        .filter(|t| !t.chars().any(|c| c.is_ascii_digit()))
+        .collect::<Vec<_>>()
+        .join("")
+}
+
+/// Similarity of two normalized merchant names in [0, 1].
+/// Exact match → 1.0, containment (≥4 chars) → 0.9, otherwise 1 − edit distance / max length.
+pub fn merchant_similarity(a: &str, b: &str) -> f32 {
+    if a.is_empty() || b.is_empty() {
+        return 0.0;
+    }
+    if a == b {
+        return 1.0;
+    }
+    let (short, long) = if a.len() <= b.len() { (a, b) } else { (b, a) };
+    if short.len() >= 4 && long.contains(short) {
+        return 0.9;
+    }
+    let distance = levenshtein(a, b);
+    let max_len = a.chars().count().max(b.chars().count());
+    1.0 - distance as f32 / max_len as f32
+}
+
+fn levenshtein(a: &str, b: &str) -> usize {
+    let b_chars: Vec<char> = b.chars().collect();
+    let mut prev: Vec<usize> = (0..=b_chars.len()).collect();
+    let mut curr = vec![0usize; b_chars.len() + 1];
+    for (i, ca) in a.chars().enumerate() {
+        curr[0] = i + 1;
+        for (j, &cb) in b_chars.iter().enumerate() {
+            let cost = if ca == cb { 0 } else { 1 };
+            curr[j + 1] = (prev[j + 1] + 1).min(curr[j] + 1).min(prev[j] + cost);
+        }
+        std::mem::swap(&mut prev, &mut curr);
+    }
+    prev[b_chars.len()]
+}
+
+fn amounts_match(a: f64, b: f64) -> bool {
+    let (a, b) = (a.abs(), b.abs());
+    let max = a.max(b);
+    max == 0.0 || (a - b).abs() / max <= AMOUNT_TOLERANCE
+}
+
+/// Group charges that share a cadence, have similar amounts, and fuzzy-match on
+/// merchant name. Cancelled and already merged charges are ignored. Only groups of two or
+/// more are returned.
+pub fn find_merge_candidates(charges: &[SubscriptionCharge]) -> Vec<SubscriptionMergeCandidate> {
+    let active: Vec<&SubscriptionCharge> =
+        charges.iter().filter(|c| c.status != "cancelled" && c.status != "merged").collect();
+    let normalized: Vec<String> = active.iter().map(|c| normalize_merchant(&c.merchant_name)).collect();
+
+    // Union-find over matching pairs; track the weakest link per group.
+    let mut parent: Vec<usize> = (0..active.len()).collect();
+    let mut weakest: Vec<f32> = vec![1.0; active.len()];
+    fn find(parent: &mut [usize], i: usize) -> usize {
+        let mut root = i;
+        while parent[root] != root {
+            root = parent[root];
+        }
+        parent[i] = root;
+        root
+    }
+
+    for i in 0..active.len() {
+        for j in (i + 1)..active.len() {
+            if active[i].frequency != active[j].frequency
+                || !amounts_match(active[i].amount, active[j].amount)
+            {
+                continue;
+            }
+            let similarity = merchant_similarity(&normalized[i], &normalized[j]);
+            if similarity < MERCHANT_SIMILARITY_THRESHOLD {
+                continue;
+            }
+            let (ri, rj) = (find(&mut parent, i), find(&mut parent, j));
+            let link = weakest[ri].min(weakest[rj]).min(similarity);
+            if ri != rj {
+                parent[rj] = ri;
+            }
+            weakest[ri] = link;
+        }
+    }
+
+    let mut groups: Vec<(usize, Vec<usize>)> = Vec::new();
+    for i in 0..active.len() {
+        let root = find(&mut parent, i);
+        match groups.iter_mut().find(|(r, _)| *r == root) {
+            Some((_, members)) => members.push(i),
+            None => groups.push((root, vec![i])),
+        }
+    }
+
+    groups
+        .into_iter()
+        .filter(|(_, members)| members.len() > 1)
+        .map(|(root, mut members)| {
+            // Stable sort: most charges first, original order breaks ties.
+            members.sort_by(|&a, &b| active[b].charge_count.cmp(&active[a].charge_count));
+            let amounts: Vec<f64> = members.iter().map(|&m| active[m].amount.abs()).collect();
+            SubscriptionMergeCandidate {
+                charge_ids: members.iter().map(|&m| active[m].id.clone()).collect(),
+                merchant_names: members.iter().map(|&m| active[m].merchant_name.clone()).collect(),
+                suggested_merchant: active[members[0]].merchant_name.clone(),
+                frequency: active[members[0]].frequency.clone(),
+                amount_min: amounts.iter().cloned().fold(f64::INFINITY, f64::min),
+                amount_max: amounts.iter().cloned().fold(0.0, f64::max),
+                similarity: weakest[root],
+            }
+        })
+        .collect()
+}
+
+/// A `semblance://statement-import-progress` event, sent as the sidecar works
+/// through the rows of a statement.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct StatementImportProgress {
+    /// The `filePath` passed to `import_statement`.
+    pub file_path: String,
+    /// Rows handled so far, unparseable ones included.
+    pub rows_parsed: u64,
+    pub rows_total: u64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+pub struct StatementDateRange {
+    pub start: String,
+    pub end: String,
+}
+
+/// What `import_statement` returns.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct StatementImportSummary {
+    pub transactions_imported: u64,
+    /// Transactions an earlier import already stored (same FITID for OFX; same
+    /// date, amount and description for CSV), as overlapping exports repeat.
+    pub duplicates_skipped: u64,
+    /// Rows the parser found no transaction in.
+    pub unparseable_rows: u64,
+    pub detected_subscriptions: u64,
+    /// Detected subscriptions flagged as likely forgotten.
+    pub forgotten_subscriptions: u64,
+    pub merchant_count: u64,
+    /// Dates of the first and last imported transaction; empty if none were.
+    pub date_range: StatementDateRange,
+    /// Yearly cost of all forgotten subscriptions, not only this import's.
+    pub potential_savings: f64,
+    /// What the file was read as: `utf-8`, `utf-16le`, `utf-16be` or `windows-1252`.
+    #[serde(default)]
+    pub encoding: String,
+    /// What the file was parsed as; see `detect_statement_format`.
+    #[serde(default)]
+    pub format: Option<StatementFormat>,
+}
+
+/// Statement formats the sidecar's parser reads.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum StatementFormat {
+    Csv,
+    /// OFX 1.x: `OFXHEADER:` header lines, then an SGML body.
+    Ofx,
+    /// OFX 2.x: an XML document with an `<?OFX ...?>` header.
+    OfxXml,
+    /// Quicken's OFX (either syntax), recognized by its Intuit `<INTU.BID>` tag.
+    Qfx,
+}
+
+/// File signatures of formats people pick by mistake, with what to call them.
+const NON_STATEMENT_SIGNATURES: &[(&[u8], &str)] = &[
+    (b"%PDF", "a PDF document"),
+    (b"PK\x03\x04", "a ZIP archive (an Excel .xlsx workbook or similar)"),
+    (b"\xD0\xCF\x11\xE0", "a legacy Office document (an Excel .xls workbook or similar)"),
+    (b"\x89PNG", "a PNG image"),
+    (b"\xFF\xD8\xFF", "a JPEG image"),
+    (b"\x1F\x8B", "a gzip archive"),
+];
+
+/// Characters of the decoded text checked for OFX headers and Intuit tags.
+const STATEMENT_SNIFF_CHARS: usize = 4096;
+
+/// Identify a statement from its raw `bytes` (file signatures) and decoded
+/// `text` (OFX headers, a CSV header row). A file that isn't one of the
+/// `StatementFormat`s errors with what it looks like instead.
+pub fn detect_statement_format(bytes: &[u8], text: &str) -> Result<StatementFormat, String> {
+    if let Some((_, name)) = NON_STATEMENT_SIGNATURES.iter().find(|(magic, _)| bytes.starts_with(magic)) {
+        return Err(name.to_string());
+    }
+    let text = text.trim_start();
+    if text.is_empty() {
+        return Err("an empty file".to_string());
+    }
+    if text.contains('\0') {
+        return Err("binary data".to_string());
+    }
+    let head: String = text.chars().take(STATEMENT_SNIFF_CHARS).collect::<String>().to_ascii_uppercase();
+    let ofx = if head.starts_with("OFXHEADER:") {
+        Some(StatementFormat::Ofx)
+    } else if head.contains("<?OFX") || (head.starts_with("<?XML") && head.contains("<OFX>")) {
+        Some(StatementFormat::OfxXml)
+    } else if head.starts_with("<OFX>") {
+        Some(StatementFormat::Ofx)
+    } else {
+        None
+    };
+    if let Some(ofx) = ofx {
+        return Ok(if head.contains("<INTU.BID>") { StatementFormat::Qfx } else { ofx });
+    }
+    if head.starts_with("!TYPE:") || head.starts_with("!ACCOUNT") {
+        return Err("a QIF file (export as OFX, QFX or CSV instead)".to_string());
+    }
+    if head.starts_with("<!DOCTYPE HTML") || head.starts_with("<HTML") {
+        return Err("a web page".to_string());
+    }
+    let header_row = text.lines().next().unwrap_or_default();
+    if header_row.contains([',', ';', '\t']) {
+        return Ok(StatementFormat::Csv);
+    }
+    Err("text without a CSV header row or OFX header".to_string())
+}
+
+/// Windows-1252 in 0x80–0x9F, where Latin-1 has C1 controls. Banks that say
+/// Latin-1 mean this superset in practice (€, smart quotes and dashes). The
+/// five bytes it leaves undefined keep their Latin-1 meaning.
+const WINDOWS_1252_HIGH: [char; 32] = [
+    '€', '\u{81}', '‚', 'ƒ', '„', '…', '†', '‡', 'ˆ', '‰', 'Š', '‹', 'Œ', '\u{8D}', 'Ž', '\u{8F}',
+    '\u{90}', '‘', '’', '“', '”', '•', '–', '—', '˜', '™', 'š', '›', 'œ', '\u{9D}', 'ž', 'Ÿ',
+];
+
+fn decode_utf16(bytes: &[u8], unit: fn([u8; 2]) -> u16) -> String {
+    let units = bytes.chunks_exact(2).map(|pair| unit([pair[0], pair[1]]));
+    char::decode_utf16(units).map(|c| c.unwrap_or(char::REPLACEMENT_CHARACTER)).collect()
+}
+
+/// Decode a statement file to text, with the name of the encoding it was read
+/// as. A byte order mark decides; otherwise the file is UTF-8 if it is valid
+/// UTF-8, and Windows-1252 if not. No row is dropped: every byte sequence
+/// decodes to something.
+pub fn decode_statement(bytes: &[u8]) -> (String, &'static str) {
+    if let Some(rest) = bytes.strip_prefix(b"\xEF\xBB\xBF") {
+        return (String::from_utf8_lossy(rest).into_owned(), "utf-8");
+    }
+    if let Some(rest) = bytes.strip_prefix(b"\xFF\xFE") {
+        return (decode_utf16(rest, u16::from_le_bytes), "utf-16le");
+    }
+    if let Some(rest) = bytes.strip_prefix(b"\xFE\xFF") {
+        return (decode_utf16(rest, u16::from_be_bytes), "utf-16be");
+    }
+    match std::str::from_utf8(bytes) {
+        Ok(text) => (text.to_string(), "utf-8"),
+        Err(_) => {
+            let text = bytes
+                .iter()
+                .map(|&b| match b {
+                    0x80..=0x9F => WINDOWS_1252_HIGH[(b - 0x80) as usize],
+                    _ => b as char,
+                })
+                .collect();
+            (text, "windows-1252")
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn charge(id: &str, merchant: &str, amount: f64, frequency: &str, count: u32) -> SubscriptionCharge {
+        SubscriptionCharge {
+            id: id.to_string(),
+            merchant_name: merchant.to_string(),
+            amount,
+            frequency: frequency.to_string(),
+            charge_count: count,
+            status: "active".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_normalize_merchant() {
+        assert_eq!(normalize_merchant("NETFLIX.COM"), "netflix");
+        assert_eq!(normalize_merchant("Netflix"), "netflix");
+        assert_eq!(normalize_merchant("SPOTIFY P1234ABC"), "spotify");
+        assert_eq!(normalize_merchant("The New York Times"), "newyorktimes");
+    }
+
+    #[test]
+    fn test_merchant_similarity() {
+        assert_eq!(merchant_similarity("netflix", "netflix"), 1.0);
+        assert_eq!(merchant_similarity("spotify", "spotifyusa"), 0.9);
+        assert!(merchant_similarity("netflix", "netflx") >= MERCHANT_SIMILARITY_THRESHOLD);
+        assert!(merchant_similarity("netflix", "hulu") < MERCHANT_SIMILARITY_THRESHOLD);
+        assert_eq!(merchant_similarity("", "hulu"), 0.0);
+    }
+
+    #[test]
+    fn test_find_merge_candidates_groups_variants() {
+        let charges = vec![
+            charge("a", "NETFLIX.COM", -15.49, "monthly", 3),
+            charge("b", "Netflix", -15.49, "monthly", 9),
+            charge("c", "Hulu", -7.99, "monthly", 6),
+        ];
+        let candidates = find_merge_candidates(&charges);
+        assert_eq!(candidates.len(), 1);
+        assert_eq!(candidates[0].charge_ids, vec!["b", "a"]);
+        assert_eq!(candidates[0].suggested_merchant, "Netflix");
+        assert_eq!(candidates[0].similarity, 1.0);
+    }
+
+    #[test]
+    fn test_find_merge_candidates_respects_amount_and_cadence() {
+        let charges = vec![
+            charge("a", "Netflix", 15.49, "monthly", 3),
+            charge("b", "Netflix", 22.99, "monthly", 3),
+            charge("c", "Netflix", 15.49, "annual", 1),
+        ];
+        assert!(find_merge_candidates(&charges).is_empty());
+    }
+
+    #[test]
+    fn test_decode_statement_transcodes_latin1() {
+        // "Café Müller;-4,50 €" as a Windows-1252 export.
+        let latin1 = b"Caf\xE9 M\xFCller;-4,50 \x80\n";
+        assert_eq!(decode_statement(latin1), ("Café Müller;-4,50 €\n".to_string(), "windows-1252"));
+        assert_eq!(decode_statement("Café".as_bytes()), ("Café".to_string(), "utf-8"));
+        assert_eq!(decode_statement(b"\xEF\xBB\xBFDate,Amount"), ("Date,Amount".to_string(), "utf-8"));
+        assert_eq!(decode_statement(b"\xFF\xFEC\x00\xE9\x00"), ("Cé".to_string(), "utf-16le"));
+    }
+
+    #[test]
+    fn test_detect_statement_format() {
+        let detect = |text: &str| detect_statement_format(text.as_bytes(), text);
+        assert_eq!(detect("Date,Description,Amount\n2024-01-02,Coffee,-3.50\n"), Ok(StatementFormat::Csv));
+        assert_eq!(detect("Datum;Betrag\n"), Ok(StatementFormat::Csv));
+        assert_eq!(detect("OFXHEADER:100\nDATA:OFXSGML\n\n<OFX><BANKMSGSRSV1>"), Ok(StatementFormat::Ofx));
+        let xml = "<?xml version=\"1.0\"?>\n<?OFX OFXHEADER=\"200\" VERSION=\"220\"?>\n<OFX>";
+        assert_eq!(detect(xml), Ok(StatementFormat::OfxXml));
+        let qfx = "OFXHEADER:100\n\n<OFX><SIGNONMSGSRSV1><SONRS><INTU.BID>3000</SONRS>";
+        assert_eq!(detect(qfx), Ok(StatementFormat::Qfx));
+
+        let pdf = b"%PDF-1.7\n%\xE2\xE3\xCF\xD3";
+        assert_eq!(detect_statement_format(pdf, &decode_statement(pdf).0), Err("a PDF document".to_string()));
+        assert!(detect("!Type:Bank\nD01/02/2024\n").unwrap_err().starts_with("a QIF file"));
+        assert_eq!(detect("  \n"), Err("an empty file".to_string()));
+        assert!(detect("just some notes").is_err());
+    }
+
+    #[test]
+    fn test_find_merge_candidates_skips_cancelled() {
+        let mut cancelled = charge("b", "Netflix", 15.49, "monthly", 2);
+        cancelled.status = "cancelled".to_string();
+        let charges = vec![charge("a", "NETFLIX.COM", 15.49, "monthly", 3), cancelled];
+        assert!(find_merge_candidates(&charges).is_empty());
+        let mut merged = charge("c", "Netflix", 15.49, "monthly", 2);
+        merged.status = "merged".to_string();
+        assert!(find_merge_candidates(&[charge("a", "NETFLIX.COM", 15.49, "monthly", 3), merged]).is_empty());
+    }
+}
@@ -0,0 +1,415 @@
+// GGUF Inspection — reads a model file's header without loading it.
+//
+// llama.cpp fails with an opaque error when a tensor uses a quantization type
+// the linked build doesn't know (newer upstream quants) or didn't compile
+// kernels for (BitNet TL1/TL2 — see build.rs). Reading the tensor types from
+// the header lets us reject such files up front, naming the offending types.
+// Only the header is read; tensor data is never touched, so it is cheap enough
+// to run on a file the user just picked.
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{BufReader, Read, Seek};
+use std::path::Path;
+
+const GGUF_MAGIC: &[u8; 4] = b"GGUF";
+/// ggml's GGML_MAX_DIMS.
+const MAX_TENSOR_DIMS: u32 = 4;
+/// Bounds for header-declared lengths, so a corrupt file can't make us allocate gigabytes.
+const MAX_STRING_LEN: u64 = 1 << 24;
+const MAX_COUNT: u64 = 1 << 28;
+
+/// ggml tensor types by id, as numbered in the ggml.h of the linked BitNet.cpp
+/// build, and whether this build can run them.
+const GGML_TYPES: &[(u32, &str, bool)] = &[
+    (0, "F32", true),
+    (1, "F16", true),
+    (2, "Q4_0", true),
+    (3, "Q4_1", true),
+    (4, "Q4_2", false), // removed from ggml
+    (5, "Q4_3", false), // removed from ggml
+    (6, "Q5_0", true),
+    (7, "Q5_1", true),
+    (8, "Q8_0", true),
+    (9, "Q8_1", true),
+    (10, "Q2_K", true),
+    (11, "Q3_K", true),
+    (12, "Q4_K", true),
+    (13, "Q5_K", true),
+    (14, "Q6_K", true),
+    (15, "Q8_K", true),
+    (16, "IQ2_XXS", true),
+    (17, "IQ2_XS", true),
+    (18, "IQ3_XXS", true),
+    (19, "IQ1_S", true),
+    (20, "IQ4_NL", true),
+    (21, "IQ3_S", true),
+    (22, "IQ2_S", true),
+    (23, "IQ4_XS", true),
+    (24, "I8", true),
+    (25, "I16", true),
+    (26, "I32", true),
+    (27, "I64", true),
+    (28, "F64", true),
+    (29, "IQ1_M", true),
+    (30, "BF16", true),
+    (31, "Q4_0_4_4", true),
+    (32, "Q4_0_4_8", true),
+    (33, "Q4_0_8_8", true),
+    (34, "TQ1_0", true),
+    (35, "TQ2_0", true),
+    (36, "I2_S", true),
+    (37, "TL1", false), // LUT kernels disabled in build.rs
+    (38, "TL2", false), // LUT kernels disabled in build.rs
+];
+
+/// Tensor count of one ggml type in a model.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct TensorTypeCount {
+    pub tensor_type: String,
+    pub tensors: u64,
+    pub supported: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct GgufInspection {
+    pub version: u32,
+    pub architecture: Option<String>,
+    pub model_name: Option<String>,
+    /// `<architecture>.context_length`, the context the model was trained with.
+    pub context_length: Option<u32>,
+    /// `general.file_type` (llama.cpp's `llama_ftype`), the dominant quantization.
+    pub file_type: Option<u32>,
+    pub tensor_count: u64,
+    /// Tensor types used by the model, most common first.
+    pub tensor_types: Vec<TensorTypeCount>,
+    /// Names of the types this build can't run. Empty means the model should load.
+    pub unsupported_types: Vec<String>,
+}
+
+impl GgufInspection {
+    /// Human-readable reason the model can't be loaded, if any.
+    pub fn unsupported_reason(&self) -> Option<String> {
+        if self.unsupported_types.is_empty() {
+            return None;
+        }
+        let plural = if self.unsupported_types.len() == 1 { "" } else { "s" };
+        Some(format!(
+            "Quantization{} {} not supported by this build. Use a different quant of this model (e.g. Q4_K_M or Q8_0; i2_s for BitNet).",
+            plural,
+            self.unsupported_types.join(", ")
+        ))
+    }
+}
+
+fn type_name(id: u32) -> (String, bool) {
+    match GGML_TYPES.iter().find(|(t, _, _)| *t == id) {
+        Some((_, name, supported)) => (name.to_string(), *supported),
+        None => (format!("unknown type {}", id), false),
+    }
+}
+
+/// Read the header of the GGUF at `path`.
+pub fn inspect(path: &Path) -> Result<GgufInspection, String> {
+    let file = File::open(path).map_err(|e| format!("Cannot open {:?}: {}", path, e))?;
+    let mut reader = HeaderReader { inner: BufReader::new(file) };
+    parse_header(&mut reader).map_err(|e| format!("Invalid GGUF {:?}: {}", path, e))
+}
+
+/// Whether the file at `path` starts with the GGUF magic. A file shorter than
+/// the magic isn't a GGUF.
+pub fn has_magic(path: &Path) -> Result<bool, String> {
+    let mut file = File::open(path).map_err(|e| format!("Cannot open {:?}: {}", path, e))?;
+    let mut magic = [0u8; 4];
+    match file.read_exact(&mut magic) {
+        Ok(()) => Ok(&magic == GGUF_MAGIC),
+        Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => Ok(false),
+        Err(e) => Err(format!("Cannot read {:?}: {}", path, e)),
+    }
+}
+
+/// Fail with a clear message if the GGUF at `path` uses tensor types this build
+/// can't run. A header this parser can't read is not an error here — llama.cpp
+/// has the final say on those.
+pub fn check_supported(path: &Path) -> Result<(), String> {
+    match inspect(path) {
+        Ok(inspection) => match inspection.unsupported_reason() {
+            Some(reason) => Err(reason),
+            None => Ok(()),
+        },
+        Err(e) => {
+            eprintln!("[gguf] Skipping tensor type check: {}", e);
+            Ok(())
+        }
+    }
+}
+
+struct HeaderReader<R> {
+    inner: R,
+}
+
+impl<R: Read + Seek> HeaderReader<R> {
+    fn bytes<const N: usize>(&mut self) -> Result<[u8; N], String> {
+        let mut buf = [0u8; N];
+        self.inner.read_exact(&mut buf).map_err(|e| format!("truncated header ({})", e))?;
+        Ok(buf)
+    }
+
+    fn u32(&mut self) -> Result<u32, String> {
+        Ok(u32::from_le_bytes(self.bytes()?))
+    }
+
+    fn u64(&mut self) -> Result<u64, String> {
+        Ok(u64::from_le_bytes(self.bytes()?))
+    }
+
+    fn count(&mut self, what: &str) -> Result<u64, String> {
+        let n = self.u64()?;
+        if n > MAX_COUNT {
+            return Err(format!("implausible {} count {}", what, n));
+        }
+        Ok(n)
+    }
+
+    fn skip(&mut self, n: u64) -> Result<(), String> {
+        self.inner
+            .seek_relative(n as i64)
+            .map_err(|e| format!("truncated header ({})", e))
+    }
+
+    fn string(&mut self) -> Result<String, String> {
+        let len = self.u64()?;
+        if len > MAX_STRING_LEN {
+            return Err(format!("implausible string length {}", len));
+        }
+        let mut buf = vec![0u8; len as usize];
+        self.inner.read_exact(&mut buf).map_err(|e| format!("truncated header ({})", e))?;
+        Ok(String::from_utf8_lossy(&buf).into_owned())
+    }
+
+    /// Read a metadata value of type `ty` as an unsigned integer. Other types
+    /// are skipped and read as `None`.
+    fn uint(&mut self, ty: u32) -> Result<Option<u64>, String> {
+        match ty {
+            4 => Ok(Some(self.u32()? as u64)),
+            5 => Ok(u64::try_from(self.u32()? as i32).ok()),
+            10 => Ok(Some(self.u64()?)),
+            11 => Ok(u64::try_from(self.u64()? as i64).ok()),
+            _ => self.skip_value(ty).map(|_| None),
+        }
+    }
+
+    fn skip_string(&mut self) -> Result<(), String> {
+        let len = self.u64()?;
+        if len > MAX_STRING_LEN {
+            return Err(format!("implausible string length {}", len));
+        }
+        self.skip(len)
+    }
+
+    /// Skip one metadata value of GGUF value type `ty`.
+    fn skip_value(&mut self, ty: u32) -> Result<(), String> {
+        match ty {
+            // array: element type, count, elements
+            9 => {
+                let elem = self.u32()?;
+                let n = self.count("array element")?;
+                match scalar_size(elem) {
+                    Some(size) => self.skip(size * n),
+                    None => (0..n).try_for_each(|_| self.skip_value(elem)),
+                }
+            }
+            8 => self.skip_string(),
+            _ => match scalar_size(ty) {
+                Some(size) => self.skip(size),
+                None => Err(format!("unknown metadata value type {}", ty)),
+            },
+        }
+    }
+}
+
+/// Byte size of a fixed-size GGUF value type.
+fn scalar_size(ty: u32) -> Option<u64> {
+    match ty {
+        0 | 1 | 7 => Some(1),    // u8, i8, bool
+        2 | 3 => Some(2),        // u16, i16
+        4..=6 => Some(4),        // u32, i32, f32
+        10..=12 => Some(8),      // u64, i64, f64
+        _ => None,
+    }
+}
+
+fn parse_header<R: Read + Seek>(r: &mut HeaderReader<R>) -> Result<GgufInspection, String> {
+    if &r.bytes::<4>()? != GGUF_MAGIC {
+        return Err("not a GGUF file".to_string());
+    }
+    let version = r.u32()?;
+    if !(2..=3).contains(&version) {
+        return Err(format!("unsupported GGUF version {}", version));
+    }
+    let tensor_count = r.count("tensor")?;
+    let kv_count = r.count("metadata")?;
+
+    let mut architecture = None;
+    let mut model_name = None;
+    let mut file_type = None;
+    // By architecture: the key is only known once `general.architecture` is
+    // read, which writers put first but the format doesn't require.
+    let mut context_lengths: Vec<(String, u64)> = Vec::new();
+    for _ in 0..kv_count {
+        let key = r.string()?;
+        let ty = r.u32()?;
+        match (key.as_str(), ty) {
+            ("general.architecture", 8) => architecture = Some(r.string()?),
+            ("general.name", 8) => model_name = Some(r.string()?),
+            ("general.file_type", _) => file_type = r.uint(ty)?.and_then(|t| u32::try_from(t).ok()),
+            _ => match key.strip_suffix(".context_length") {
+                Some(arch) => {
+                    if let Some(length) = r.uint(ty)? {
+                        context_lengths.push((arch.to_string(), length));
+                    }
+                }
+                None => r.skip_value(ty)?,
+            },
+        }
+    }
+    let context_length = context_lengths
+        .into_iter()
+        .find(|(arch, _)| Some(arch) == architecture.as_ref())
+        .and_then(|(_, length)| u32::try_from(length).ok());
+
+    let mut counts: BTreeMap<u32, u64> = BTreeMap::new();
+    for _ in 0..tensor_count {
+        r.skip_string()?; // name
+        let n_dims = r.u32()?;
+        if n_dims > MAX_TENSOR_DIMS {
+            return Err(format!("tensor with {} dimensions", n_dims));
+        }
+        r.skip(8 * n_dims as u64)?; // dims
+        let ty = r.u32()?;
+        r.skip(8)?; // data offset
+        *counts.entry(ty).or_default() += 1;
+    }
+
+    let mut tensor_types: Vec<TensorTypeCount> = counts
+        .into_iter()
+        .map(|(id, tensors)| {
+            let (tensor_type, supported) = type_name(id);
+            TensorTypeCount { tensor_type, tensors, supported }
+        })
+        .collect();
+    tensor_types.sort_by_key(|t| std::cmp::Reverse(t.tensors));
+    let unsupported_types = tensor_types
+        .iter()
+        .filter(|t| !t.supported)
+        .map(|t| t.tensor_type.clone())
+        .collect();
+
+    Ok(GgufInspection {
+        version,
+        architecture,
+        model_name,
+        context_length,
+        file_type,
+        tensor_count,
+        tensor_types,
+        unsupported_types,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    fn push_string(buf: &mut Vec<u8>, s: &str) {
+        buf.extend_from_slice(&(s.len() as u64).to_le_bytes());
+        buf.extend_from_slice(s.as_bytes());
+    }
+
+    /// Minimal GGUF v3 header with the given tensor types.
+    fn header(tensor_types: &[u32]) -> Vec<u8> {
+        let mut buf = Vec::new();
+        buf.extend_from_slice(GGUF_MAGIC);
+        buf.extend_from_slice(&3u32.to_le_bytes());
+        buf.extend_from_slice(&(tensor_types.len() as u64).to_le_bytes());
+        buf.extend_from_slice(&4u64.to_le_bytes());
+        // general.architecture = "llama"
+        push_string(&mut buf, "general.architecture");
+        buf.extend_from_slice(&8u32.to_le_bytes());
+        push_string(&mut buf, "llama");
+        // llama.context_length = 4096 (u32)
+        push_string(&mut buf, "llama.context_length");
+        buf.extend_from_slice(&4u32.to_le_bytes());
+        buf.extend_from_slice(&4096u32.to_le_bytes());
+        // general.file_type = 15 (Q4_K_M, u32)
+        push_string(&mut buf, "general.file_type");
+        buf.extend_from_slice(&4u32.to_le_bytes());
+        buf.extend_from_slice(&15u32.to_le_bytes());
+        // tokenizer.ggml.tokens = ["a", "bc"]
+        push_string(&mut buf, "tokenizer.ggml.tokens");
+        buf.extend_from_slice(&9u32.to_le_bytes());
+        buf.extend_from_slice(&8u32.to_le_bytes());
+        buf.extend_from_slice(&2u64.to_le_bytes());
+        push_string(&mut buf, "a");
+        push_string(&mut buf, "bc");
+        for (i, ty) in tensor_types.iter().enumerate() {
+            push_string(&mut buf, &format!("blk.{}.weight", i));
+            buf.extend_from_slice(&2u32.to_le_bytes());
+            buf.extend_from_slice(&64u64.to_le_bytes());
+            buf.extend_from_slice(&64u64.to_le_bytes());
+            buf.extend_from_slice(&ty.to_le_bytes());
+            buf.extend_from_slice(&0u64.to_le_bytes());
+        }
+        buf
+    }
+
+    fn parse(bytes: Vec<u8>) -> Result<GgufInspection, String> {
+        parse_header(&mut HeaderReader { inner: Cursor::new(bytes) })
+    }
+
+    #[test]
+    fn test_parse_header_counts_tensor_types() {
+        let inspection = parse(header(&[12, 12, 14, 0])).unwrap();
+        assert_eq!(inspection.architecture.as_deref(), Some("llama"));
+        assert_eq!(inspection.context_length, Some(4096));
+        assert_eq!(inspection.file_type, Some(15));
+        assert_eq!(inspection.tensor_count, 4);
+        assert_eq!(inspection.tensor_types[0], TensorTypeCount { tensor_type: "Q4_K".to_string(), tensors: 2, supported: true });
+        assert!(inspection.unsupported_types.is_empty());
+        assert!(inspection.unsupported_reason().is_none());
+    }
+
+    #[test]
+    fn test_unsupported_types_are_listed() {
+        let inspection = parse(header(&[36, 38, 39])).unwrap();
+        assert_eq!(inspection.unsupported_types, vec!["TL2".to_string(), "unknown type 39".to_string()]);
+        let reason = inspection.unsupported_reason().unwrap();
+        assert!(reason.contains("TL2, unknown type 39 not supported by this build"), "{}", reason);
+    }
+
+    #[test]
+    fn test_rejects_non_gguf_and_truncated() {
+        assert!(parse(b"GGML\x03\0\0\0".to_vec()).unwrap_err().contains("not a GGUF"));
+        let mut truncated = header(&[12]);
+        truncated.truncate(truncated.len() - 12);
+        assert!(parse(truncated).unwrap_err().contains("truncated"));
+    }
+
+    #[test]
+    fn test_has_magic() {
+        let dir = std::env::temp_dir().join(format!("semblance-gguf-magic-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let file = |name: &str, bytes: &[u8]| {
+            let path = dir.join(name);
+            std::fs::write(&path, bytes).unwrap();
+            has_magic(&path)
+        };
+        assert_eq!(file("model.gguf", &header(&[12])), Ok(true));
+        assert_eq!(file("statement.pdf", b"%PDF-1.7"), Ok(false));
+        assert_eq!(file("short.gguf", b"GG"), Ok(false));
+        assert!(has_magic(&dir.join("missing.gguf")).unwrap_err().contains("Cannot open"));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,338 @@
+// Grammar — GBNF checks and JSON Schema → GBNF conversion for grammar-constrained generation.
+//
+// llama.cpp's grammar sampler only reports "failed to parse" (and its log is
+// silenced), so grammars are checked here first to give callers a specific
+// error at request time: unterminated literals, unbalanced groups, a missing
+// root rule, or references to undefined rules. This is a structural check, not
+// a full parser; llama.cpp still has the final say.
+
+use serde_json::Value;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Write;
+
+/// Rule every grammar starts from.
+pub const ROOT_RULE: &str = "root";
+
+#[derive(Debug, PartialEq)]
+enum Token {
+    Ident(String),
+    Define,
+    Other(char),
+}
+
+fn is_ident_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '-' || c == '_'
+}
+
+/// Split `src` into identifiers, `::=`, and punctuation, skipping comments,
+/// string literals and character classes. Errors carry a 1-based line number.
+fn tokenize(src: &str) -> Result<Vec<(Token, usize)>, String> {
+    let mut tokens = Vec::new();
+    let mut chars = src.chars().peekable();
+    let mut line = 1;
+    while let Some(c) = chars.next() {
+        match c {
+            '\n' => line += 1,
+            c if c.is_whitespace() => {}
+            '#' => {
+                while chars.peek().is_some_and(|&c| c != '\n') {
+                    chars.next();
+                }
+            }
+            '"' | '[' => {
+                let close = if c == '"' { '"' } else { ']' };
+                let start = line;
+                loop {
+                    match chars.next() {
+                        Some('\\') => {
+                            chars.next();
+                        }
+                        Some(c) if c == close => break,
+                        Some('\n') if close == '"' => {
+                            return Err(format!("line {}: unterminated string literal", start));
+                        }
+                        Some('\n') => line += 1,
+                        Some(_) => {}
+                        None => {
+                            let what = if close == '"' { "string literal" } else { "character class" };
+                            return Err(format!("line {}: unterminated {}", start, what));
+                        }
+                    }
+                }
+            }
+            ':' => {
+                if chars.next() != Some(':') || chars.next() != Some('=') {
+                    return Err(format!("line {}: expected '::='", line));
+                }
+                tokens.push((Token::Define, line));
+            }
+            c if is_ident_char(c) => {
+                let mut ident = c.to_string();
+                while let Some(&c) = chars.peek().filter(|c| is_ident_char(**c)) {
+                    ident.push(c);
+                    chars.next();
+                }
+                tokens.push((Token::Ident(ident), line));
+            }
+            '(' | ')' | '|' | '*' | '+' | '?' | '.' | '{' | '}' | ',' => tokens.push((Token::Other(c), line)),
+            other => return Err(format!("line {}: unexpected character '{}'", line, other)),
+        }
+    }
+    Ok(tokens)
+}
+
+/// Structural check of a GBNF grammar that must define `root`.
+pub fn validate_gbnf(src: &str, root: &str) -> Result<(), String> {
+    let tokens = tokenize(src)?;
+    let mut defined = BTreeSet::new();
+    let mut referenced: BTreeMap<String, usize> = BTreeMap::new();
+
+    let mut i = 0;
+    while i < tokens.len() {
+        let name = match (&tokens[i], tokens.get(i + 1)) {
+            ((Token::Ident(name), _), Some((Token::Define, _))) => name.clone(),
+            ((_, line), _) => return Err(format!("line {}: expected a rule definition 'name ::= ...'", line)),
+        };
+        if !defined.insert(name.clone()) {
+            return Err(format!("line {}: rule '{}' is defined twice", tokens[i].1, name));
+        }
+        i += 2;
+
+        let mut depth = 0usize;
+        let mut in_repeat = false;
+        while i < tokens.len() && !matches!(tokens.get(i + 1), Some((Token::Define, _))) {
+            let (token, line) = &tokens[i];
+            match token {
+                Token::Ident(r) if in_repeat => {
+                    if !r.chars().all(|c| c.is_ascii_digit()) {
+                        return Err(format!("line {}: bad repetition count '{}'", line, r));
+                    }
+                }
+                Token::Ident(r) => {
+                    referenced.entry(r.clone()).or_insert(*line);
+                }
+                Token::Other('(') => depth += 1,
+                Token::Other(')') => {
+                    depth = depth
+                        .checked_sub(1)
+                        .ok_or_else(|| format!("line {}: unmatched ')' in rule '{}'", line, name))?;
+                }
+                Token::Other('{') => in_repeat = true,
+                Token::Other('}') => in_repeat = false,
+                Token::Define => return Err(format!("line {}: unexpected '::='", line)),
+                Token::Other(_) => {}
+            }
+            i += 1;
+        }
+        if depth > 0 {
+            return Err(format!("rule '{}' has an unclosed '('", name));
+        }
+    }
+
+    if !defined.contains(root) {
+        return Err(format!("grammar has no '{}' rule", root));
+    }
+    if let Some((name, line)) = referenced.iter().find(|(name, _)| !defined.contains(*name)) {
+        return Err(format!("line {}: rule '{}' is not defined", line, name));
+    }
+    Ok(())
+}
+
+// ─── JSON Schema → GBNF ──────────────────────────────────────────────────────
+
+const JSON_BASE_RULES: &str = r#"ws ::= [ \t\n]*
+value ::= object | array | string | number | boolean | null
+object ::= "{" ws ( string ":" ws value ( "," ws string ":" ws value )* )? "}" ws
+array ::= "[" ws ( value ( "," ws value )* )? "]" ws
+string ::= "\"" ( [^"\\\x7F\x00-\x1F] | "\\" ( ["\\/bfnrt] | "u" [0-9a-fA-F] [0-9a-fA-F] [0-9a-fA-F] [0-9a-fA-F] ) )* "\"" ws
+number ::= "-"? ( [0-9] | [1-9] [0-9]* ) ( "." [0-9]+ )? ( [eE] [-+]? [0-9]+ )? ws
+integer ::= "-"? ( [0-9] | [1-9] [0-9]* ) ws
+boolean ::= ( "true" | "false" ) ws
+null ::= "null" ws
+"#;
+
+/// `s` as a GBNF string literal.
+fn gbnf_literal(s: &str) -> String {
+    let mut out = String::from("\"");
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+struct SchemaRules {
+    rules: Vec<(String, String)>,
+}
+
+impl SchemaRules {
+    fn add(&mut self, hint: &str, body: String) -> String {
+        let base: String = hint
+            .chars()
+            .map(|c| if c.is_ascii_alphanumeric() { c.to_ascii_lowercase() } else { '-' })
+            .collect();
+        let mut name = base.clone();
+        let mut n = 1;
+        while self.rules.iter().any(|(r, _)| *r == name) || JSON_BASE_RULES.contains(&format!("\n{} ::=", name)) || name == "ws" {
+            n += 1;
+            name = format!("{}-{}", base, n);
+        }
+        self.rules.push((name.clone(), body));
+        name
+    }
+
+    /// Rule (or base rule) matching `schema`, named after `hint`.
+    fn visit(&mut self, schema: &Value, hint: &str) -> String {
+        if let Some(values) = schema.get("enum").and_then(|v| v.as_array()) {
+            let alts: Vec<String> = values.iter().map(|v| gbnf_literal(&v.to_string())).collect();
+            return self.add(hint, format!("( {} ) ws", alts.join(" | ")));
+        }
+        if let Some(value) = schema.get("const") {
+            return self.add(hint, format!("{} ws", gbnf_literal(&value.to_string())));
+        }
+        if let Some(options) = schema.get("anyOf").or_else(|| schema.get("oneOf")).and_then(|v| v.as_array()) {
+            let alts: Vec<String> = options
+                .iter()
+                .enumerate()
+                .map(|(i, s)| self.visit(s, &format!("{}-{}", hint, i)))
+                .collect();
+            return self.add(hint, alts.join(" | "));
+        }
+        match schema.get("type") {
+            Some(Value::Array(types)) => {
+                let alts: Vec<String> = types
+                    .iter()
+                    .map(|t| {
+                        let mut single = schema.clone();
+                        single["type"] = t.clone();
+                        self.visit(&single, &format!("{}-{}", hint, t.as_str().unwrap_or("value")))
+                    })
+                    .collect();
+                self.add(hint, alts.join(" | "))
+            }
+            Some(Value::String(t)) => match t.as_str() {
+                "object" => self.visit_object(schema, hint),
+                "array" => {
+                    let item = match schema.get("items") {
+                        Some(items) => self.visit(items, &format!("{}-item", hint)),
+                        None => "value".to_string(),
+                    };
+                    let min_items = schema.get("minItems").and_then(|v| v.as_u64()).unwrap_or(0);
+                    let list = format!("{} ( \",\" ws {} )*", item, item);
+                    let body = if min_items > 0 { list } else { format!("( {} )?", list) };
+                    self.add(hint, format!("\"[\" ws {} \"]\" ws", body))
+                }
+                "string" | "number" | "integer" | "boolean" | "null" => t.clone(),
+                _ => "value".to_string(),
+            },
+            _ => "value".to_string(),
+        }
+    }
+
+    fn visit_object(&mut self, schema: &Value, hint: &str) -> String {
+        let Some(properties) = schema.get("properties").and_then(|v| v.as_object()).filter(|p| !p.is_empty()) else {
+            return "object".to_string();
+        };
+        let required: BTreeSet<&str> = schema
+            .get("required")
+            .and_then(|v| v.as_array())
+            .map(|r| r.iter().filter_map(|v| v.as_str()).collect())
+            .unwrap_or_default();
+
+        let mut required_kvs = Vec::new();
+        let mut optional_kvs = Vec::new();
+        for (key, prop) in properties {
+            let value = self.visit(prop, &format!("{}-{}", hint, key));
+            let kv = format!("{} \":\" ws {}", gbnf_literal(&format!("\"{}\"", key)), value);
+            if required.contains(key.as_str()) {
+                required_kvs.push(kv);
+            } else {
+                optional_kvs.push(kv);
+            }
+        }
+
+        let mut body = String::from("\"{\" ws ");
+        if required_kvs.is_empty() {
+            // Each optional property may appear only if the ones before it do.
+            let mut nested = String::new();
+            for kv in optional_kvs.iter().rev() {
+                nested = if nested.is_empty() {
+                    kv.clone()
+                } else {
+                    format!("{} ( \",\" ws {} )?", kv, nested)
+                };
+            }
+            let _ = write!(body, "( {} )? ", nested);
+        } else {
+            body.push_str(&required_kvs.join(" \",\" ws "));
+            body.push(' ');
+            for kv in &optional_kvs {
+                let _ = write!(body, "( \",\" ws {} )? ", kv);
+            }
+        }
+        body.push_str("\"}\" ws");
+        self.add(hint, body)
+    }
+}
+
+/// GBNF grammar accepting JSON that matches `schema` (leading whitespace
+/// excluded). Supports `type` (including type lists), `properties`/`required`,
+/// `items`/`minItems`, `enum`, `const`, and `anyOf`/`oneOf`; anything else
+/// (`$ref`, string formats, numeric ranges) falls back to any JSON value of
+/// the type. Properties are emitted in key order, required ones first.
+pub fn json_schema_to_gbnf(schema: &Value) -> String {
+    let mut rules = SchemaRules { rules: Vec::new() };
+    let top = rules.visit(schema, "root-value");
+    let mut out = format!("{} ::= {}\n", ROOT_RULE, top);
+    for (name, body) in &rules.rules {
+        let _ = writeln!(out, "{} ::= {}", name, body);
+    }
+    out.push_str(JSON_BASE_RULES);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate_gbnf_errors() {
+        assert!(validate_gbnf("root ::= \"yes\" | \"no\" # answer\n", ROOT_RULE).is_ok());
+        assert!(validate_gbnf("root ::= item{2,3}\nitem ::= [a-z]", ROOT_RULE).is_ok());
+        assert!(validate_gbnf("answer ::= \"yes\"", ROOT_RULE).unwrap_err().contains("no 'root' rule"));
+        assert!(validate_gbnf("root ::= greeting", ROOT_RULE).unwrap_err().contains("'greeting' is not defined"));
+        assert!(validate_gbnf("root ::= \"yes", ROOT_RULE).unwrap_err().contains("unterminated string"));
+        assert!(validate_gbnf("root ::= ( \"a\" ", ROOT_RULE).unwrap_err().contains("unclosed '('"));
+        assert!(validate_gbnf("root ::= [a-z\n", ROOT_RULE).unwrap_err().contains("unterminated character class"));
+    }
+
+    #[test]
+    fn test_json_schema_to_gbnf() {
+        let schema = serde_json::json!({
+            "type": "object",
+            "properties": {
+                "to": { "type": "string" },
+                "priority": { "enum": ["low", "high"] },
+                "tags": { "type": "array", "items": { "type": "string" } },
+                "cc": { "type": ["string", "null"] }
+            },
+            "required": ["to", "priority"]
+        });
+        let grammar = json_schema_to_gbnf(&schema);
+        assert!(validate_gbnf(&grammar, ROOT_RULE).is_ok(), "{}", grammar);
+        assert!(grammar.starts_with("root ::= root-value\n"));
+        assert!(grammar.contains(r#"root-value-priority ::= ( "\"low\"" | "\"high\"" ) ws"#));
+        assert!(grammar.contains(r#""\"priority\"" ":" ws root-value-priority "," ws "\"to\"" ":" ws string"#));
+        assert!(grammar.contains(r#"( "," ws "\"cc\"" ":" ws root-value-cc )?"#));
+
+        assert!(json_schema_to_gbnf(&serde_json::json!({})).starts_with("root ::= value\n"));
+        assert!(validate_gbnf(&json_schema_to_gbnf(&serde_json::json!({ "type": "object", "properties": { "a": { "type": "integer" } } })), ROOT_RULE).is_ok());
+    }
+}
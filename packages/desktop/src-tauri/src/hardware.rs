@@ -3,7 +3,8 @@
 // CRITICAL: No network calls. Local hardware inspection only.
 
 use serde::{Deserialize, Serialize};
-use sysinfo::{Components, Disks, System};
+use std::path::Path;
+use sysinfo::{Components, CpuRefreshKind, Disks, MemoryRefreshKind, RefreshKind, System};
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 #[serde(rename_all = "camelCase")]
@@ -20,19 +21,38 @@ pub struct HardwareProfile {
     pub tier: String,
     pub cpu_cores: usize,
     pub cpu_arch: String,
+    /// SIMD extensions llama.cpp's CPU kernels use, as detected at runtime:
+    /// `avx2`, `avx512f`, `f16c`, `fma` on x86; `neon`, `dotprod` on ARM.
+    #[serde(default)]
+    pub cpu_features: Vec<String>,
     pub total_ram_mb: u64,
     pub available_ram_mb: u64,
     pub os: String,
+    /// The GPU a model would be offloaded to (see `primary_gpu`), i.e. the
+    /// first of `gpus`.
     pub gpu: Option<GpuInfo>,
+    /// Every GPU detected, primary first.
+    #[serde(default)]
+    pub gpus: Vec<GpuInfo>,
     /// Whether this device can run Whisper.cpp for local STT (8GB+ RAM, non-constrained)
     pub voice_capable: bool,
+    /// Free space on the volume holding the model directory, so downloads that
+    /// won't fit can be refused up front. 0 if the volume wasn't found.
+    #[serde(default)]
+    pub disk_free_mb: u64,
+    /// Size of that volume.
+    #[serde(default)]
+    pub disk_total_mb: u64,
 }
 
-/// Detect the hardware profile of this machine.
+/// Detect the hardware profile of this machine, with disk space measured for
+/// the volume holding `model_dir`.
 /// All detection is local — no network calls.
-pub fn detect_hardware() -> HardwareProfile {
-    let mut sys = System::new_all();
-    sys.refresh_all();
+pub fn detect_hardware(model_dir: &Path) -> HardwareProfile {
+    // Only the CPU list and memory are read; a full scan also walks every process.
+    let sys = System::new_with_specifics(
+        RefreshKind::new().with_cpu(CpuRefreshKind::new()).with_memory(MemoryRefreshKind::new().with_ram()),
+    );
 
     let total_ram_mb = sys.total_memory() / (1024 * 1024);
     let available_ram_mb = sys.available_memory() / (1024 * 1024);
@@ -57,31 +77,100 @@ pub fn detect_hardware() -> HardwareProfile {
     };
 
     // GPU detection — platform-specific heuristics.
-    // Detects Apple Silicon (Metal) on macOS, NVIDIA/AMD/Intel via WMIC on Windows.
-    let gpu = detect_gpu();
+    // Detects Apple Silicon (Metal) on macOS; NVIDIA via nvidia-smi and other
+    // vendors via DXGI/WMIC on Windows or the DRM sysfs on Linux.
+    let mut gpus = detect_gpus(total_ram_mb);
+    // Same order as `primary_gpu`: compute-capable first, then by VRAM.
+    gpus.sort_by_key(|g| std::cmp::Reverse((g.compute_capable, g.vram_mb)));
+    let gpu = gpus.first().cloned();
 
-    let tier = classify_tier(total_ram_mb, &gpu);
+    let tier = classify_tier(total_ram_mb, &gpus);
     let voice_capable = is_voice_capable(total_ram_mb, &tier);
 
+    let disks = Disks::new_with_refreshed_list();
+    let (disk_free_mb, disk_total_mb) = volume_space(
+        disks.iter().map(|d| (d.mount_point(), d.available_space(), d.total_space())),
+        model_dir,
+    );
+
     HardwareProfile {
         tier,
         cpu_cores,
         cpu_arch,
+        cpu_features: detect_cpu_features(),
         total_ram_mb,
         available_ram_mb,
         os,
         gpu,
+        gpus,
         voice_capable,
+        disk_free_mb,
+        disk_total_mb,
     }
 }
 
-/// Classify hardware tier based on RAM and GPU.
-fn classify_tier(total_ram_mb: u64, gpu: &Option<GpuInfo>) -> String {
+/// CPU SIMD features relevant to quantized inference, checked with the CPU
+/// itself (CPUID on x86), so a build for a baseline target still sees AVX2 etc.
+fn detect_cpu_features() -> Vec<String> {
+    #[allow(unused_mut)]
+    let mut features: Vec<&str> = Vec::new();
+    #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+    {
+        if std::arch::is_x86_feature_detected!("avx2") {
+            features.push("avx2");
+        }
+        if std::arch::is_x86_feature_detected!("avx512f") {
+            features.push("avx512f");
+        }
+        if std::arch::is_x86_feature_detected!("f16c") {
+            features.push("f16c");
+        }
+        if std::arch::is_x86_feature_detected!("fma") {
+            features.push("fma");
+        }
+    }
+    #[cfg(target_arch = "aarch64")]
+    {
+        if std::arch::is_aarch64_feature_detected!("neon") {
+            features.push("neon");
+        }
+        if std::arch::is_aarch64_feature_detected!("dotprod") {
+            features.push("dotprod");
+        }
+    }
+    features.into_iter().map(String::from).collect()
+}
+
+/// Free and total MB of the volume `path` lives on: of `disks` as
+/// `(mount point, available bytes, total bytes)`, the one with the longest
+/// mount point containing `path`. `path` needn't exist yet.
+fn volume_space<'a>(disks: impl Iterator<Item = (&'a Path, u64, u64)>, path: &Path) -> (u64, u64) {
+    disks
+        .filter(|(mount, _, _)| path.starts_with(mount))
+        .max_by_key(|(mount, _, _)| mount.components().count())
+        .map_or((0, 0), |(_, available, total)| (available / (1024 * 1024), total / (1024 * 1024)))
+}
+
+/// The GPU to offload to: the compute-capable one with the most VRAM, or, if
+/// none is compute-capable, the one with the most VRAM.
+fn primary_gpu(gpus: &[GpuInfo]) -> Option<&GpuInfo> {
+    gpus.iter()
+        .filter(|g| g.compute_capable)
+        .max_by_key(|g| g.vram_mb)
+        .or_else(|| gpus.iter().max_by_key(|g| g.vram_mb))
+}
+
+/// VRAM of the GPU models offload to, if it is compute-capable.
+pub fn offload_vram_mb() -> Option<u64> {
+    primary_gpu(&detect_gpus(total_memory_bytes() / (1024 * 1024))).filter(|g| g.compute_capable).map(|g| g.vram_mb)
+}
+
+/// Classify hardware tier based on RAM and the primary GPU.
+fn classify_tier(total_ram_mb: u64, gpus: &[GpuInfo]) -> String {
     let ram_gb = total_ram_mb / 1024;
 
     if ram_gb >= 32
-        || gpu
-            .as_ref()
+        || primary_gpu(gpus)
             .map(|g| g.compute_capable && g.vram_mb >= 8192)
             .unwrap_or(false)
     {
@@ -101,29 +190,55 @@ fn is_voice_capable(total_ram_mb: u64, tier: &str) -> bool {
     total_ram_mb >= 8192 && tier != "constrained"
 }
 
-/// GPU detection. Returns GPU info if a compute-capable GPU is detected.
+/// GPU detection. Returns every GPU found, unordered.
 ///
 /// On macOS with Apple Silicon, the GPU is integrated and always compute-capable (Metal).
-/// On Windows, uses WMIC to query the video controller for name and VRAM.
-/// On Linux, returns None (future: parse lspci or sysfs).
-fn detect_gpu() -> Option<GpuInfo> {
+/// On Windows and Linux, NVIDIA GPUs are queried through `nvidia-smi` (the local
+/// NVML tool shipped with the driver) for the real VRAM.
+/// Other GPUs (AMD, Intel, NVIDIA without the tool) are enumerated
+/// vendor-neutrally: DXGI on Windows (WMIC as a last resort), the DRM sysfs on
+/// Linux. See `primary_gpu` for which one models use.
+///
+/// `total_ram_mb` sizes the unified memory of Apple Silicon; callers already
+/// have it, so no second system scan happens here.
+#[cfg_attr(not(target_os = "macos"), allow(unused_variables))]
+fn detect_gpus(total_ram_mb: u64) -> Vec<GpuInfo> {
     #[cfg(target_os = "macos")]
     {
         if cfg!(target_arch = "aarch64") {
             // Apple Silicon — unified memory, Metal compute
-            let sys = System::new_all();
-            let total_ram_gb = sys.total_memory() / (1024 * 1024 * 1024);
+            let total_ram_gb = total_ram_mb / 1024;
             // Apple Silicon shares RAM with GPU. Estimate ~75% usable for GPU.
             let estimated_vram_mb = (total_ram_gb * 1024 * 3) / 4;
-            return Some(GpuInfo {
+            return vec![GpuInfo {
                 name: "Apple Silicon (Metal)".to_string(),
                 vendor: "apple".to_string(),
                 vram_mb: estimated_vram_mb,
                 compute_capable: true,
-            });
+            }];
         }
     }
 
+    #[cfg(any(target_os = "windows", target_os = "linux"))]
+    {
+        #[cfg(target_os = "windows")]
+        let adapters = dxgi_adapters();
+        #[cfg(target_os = "linux")]
+        let adapters = drm_adapters(std::path::Path::new("/sys/class/drm"));
+
+        let mut gpus = detect_nvidia_gpus();
+        // nvidia-smi has the better names and VRAM for NVIDIA cards, so only
+        // take them from enumeration when it found none.
+        let have_nvidia = !gpus.is_empty();
+        gpus.extend(adapter_gpus(adapters).into_iter().filter(|g| !(have_nvidia && g.vendor == "nvidia")));
+        if !gpus.is_empty() {
+            return gpus;
+        }
+    }
+
+    #[allow(unused_mut)]
+    let mut gpus = Vec::new();
+
     #[cfg(target_os = "windows")]
     {
         if let Ok(output) = std::process::Command::new("wmic")
@@ -170,7 +285,7 @@ fn detect_gpu() -> Option<GpuInfo> {
 
                         let compute_capable = vendor == "nvidia" || vendor == "amd";
 
-                        return Some(GpuInfo {
+                        gpus.push(GpuInfo {
                             name,
                             vendor,
                             vram_mb,
@@ -182,7 +297,271 @@ fn detect_gpu() -> Option<GpuInfo> {
         }
     }
 
-    None
+    gpus
+}
+
+/// Longest `nvidia-smi` may run. A wedged driver can hang it indefinitely;
+/// detection then reports no NVIDIA GPU rather than blocking startup.
+#[cfg(any(target_os = "windows", target_os = "linux"))]
+const NVIDIA_SMI_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(5);
+
+/// NVIDIA GPUs via `nvidia-smi`, which reads NVML locally (no network). Empty if
+/// the driver isn't installed, reports no devices, or doesn't answer within
+/// `NVIDIA_SMI_TIMEOUT`.
+#[cfg(any(target_os = "windows", target_os = "linux"))]
+fn detect_nvidia_gpus() -> Vec<GpuInfo> {
+    let mut cmd = std::process::Command::new("nvidia-smi");
+    cmd.args(["--query-gpu=name,memory.total", "--format=csv,noheader,nounits"]);
+    #[cfg(target_os = "windows")]
+    {
+        use std::os::windows::process::CommandExt;
+        cmd.creation_flags(0x08000000); // CREATE_NO_WINDOW
+    }
+    match output_within(cmd, NVIDIA_SMI_TIMEOUT) {
+        Some(stdout) => parse_nvidia_smi(&stdout),
+        None => Vec::new(),
+    }
+}
+
+/// Stdout of `cmd` if it exits successfully within `timeout`; otherwise the
+/// process is killed and `None` returned. Meant for short, small-output queries
+/// (the output is read after exit, so it must fit in the pipe buffer).
+#[cfg(any(target_os = "windows", target_os = "linux"))]
+fn output_within(mut cmd: std::process::Command, timeout: std::time::Duration) -> Option<String> {
+    use std::io::Read;
+    let mut child = cmd
+        .stdin(std::process::Stdio::null())
+        .stdout(std::process::Stdio::piped())
+        .stderr(std::process::Stdio::null())
+        .spawn()
+        .ok()?;
+    let deadline = std::time::Instant::now() + timeout;
+    loop {
+        match child.try_wait() {
+            Ok(Some(status)) if status.success() => break,
+            Ok(None) if std::time::Instant::now() < deadline => std::thread::sleep(std::time::Duration::from_millis(20)),
+            Ok(None) => {
+                let _ = child.kill();
+                let _ = child.wait();
+                return None;
+            }
+            _ => return None,
+        }
+    }
+    let mut stdout = String::new();
+    child.stdout.take()?.read_to_string(&mut stdout).ok()?;
+    Some(stdout)
+}
+
+/// Parse `nvidia-smi --query-gpu=name,memory.total --format=csv,noheader,nounits`
+/// output (one `name, MiB` line per device), in device order.
+fn parse_nvidia_smi(stdout: &str) -> Vec<GpuInfo> {
+    stdout
+        .lines()
+        .filter_map(|line| {
+            let (name, vram) = line.rsplit_once(',')?;
+            let vram_mb = vram.trim().parse::<u64>().ok()?;
+            Some((name.trim().to_string(), vram_mb))
+        })
+        .filter(|(name, _)| !name.is_empty())
+        .map(|(name, vram_mb)| GpuInfo {
+            name,
+            vendor: "nvidia".to_string(),
+            vram_mb,
+            compute_capable: true,
+        })
+        .collect()
+}
+
+/// A display adapter found by vendor-neutral enumeration.
+#[cfg(any(target_os = "windows", target_os = "linux", test))]
+#[derive(Debug, Clone, PartialEq)]
+struct GpuAdapter {
+    name: String,
+    /// PCI vendor id.
+    vendor_id: u32,
+    /// Dedicated VRAM; 0 when unknown or shared with system RAM.
+    vram_mb: u64,
+}
+
+/// Dedicated VRAM above which an adapter counts as a discrete, compute-capable
+/// GPU. Integrated GPUs report little or none.
+#[cfg(any(target_os = "windows", target_os = "linux", test))]
+const DISCRETE_GPU_MIN_VRAM_MB: u64 = 2048;
+
+#[cfg(any(target_os = "windows", target_os = "linux", test))]
+fn gpu_vendor(vendor_id: u32) -> &'static str {
+    match vendor_id {
+        0x10de => "nvidia",
+        0x1002 => "amd",
+        0x8086 => "intel",
+        _ => "unknown",
+    }
+}
+
+/// Enumerated adapters as `GpuInfo`, with a best-effort `compute_capable`: a
+/// discrete GPU (over `DISCRETE_GPU_MIN_VRAM_MB`).
+#[cfg(any(target_os = "windows", target_os = "linux", test))]
+fn adapter_gpus(adapters: Vec<GpuAdapter>) -> Vec<GpuInfo> {
+    adapters
+        .into_iter()
+        .map(|a| GpuInfo {
+            name: a.name,
+            vendor: gpu_vendor(a.vendor_id).to_string(),
+            vram_mb: a.vram_mb,
+            compute_capable: a.vram_mb > DISCRETE_GPU_MIN_VRAM_MB,
+        })
+        .collect()
+}
+
+/// Hardware adapters from DXGI, skipping software renderers (WARP).
+#[cfg(target_os = "windows")]
+fn dxgi_adapters() -> Vec<GpuAdapter> {
+    use windows::Win32::Graphics::Dxgi::{CreateDXGIFactory1, IDXGIFactory1, DXGI_ADAPTER_FLAG_SOFTWARE};
+
+    let Ok(factory) = (unsafe { CreateDXGIFactory1::<IDXGIFactory1>() }) else {
+        return Vec::new();
+    };
+    let mut adapters = Vec::new();
+    let mut index = 0;
+    while let Ok(adapter) = unsafe { factory.EnumAdapters1(index) } {
+        index += 1;
+        let Ok(desc) = (unsafe { adapter.GetDesc1() }) else {
+            continue;
+        };
+        if desc.Flags & DXGI_ADAPTER_FLAG_SOFTWARE.0 as u32 != 0 {
+            continue;
+        }
+        let len = desc.Description.iter().position(|&c| c == 0).unwrap_or(desc.Description.len());
+        adapters.push(GpuAdapter {
+            name: String::from_utf16_lossy(&desc.Description[..len]),
+            vendor_id: desc.VendorId,
+            vram_mb: desc.DedicatedVideoMemory as u64 / (1024 * 1024),
+        });
+    }
+    adapters
+}
+
+/// GPUs under `drm_root` (`/sys/class/drm`): each `cardN/device` has PCI
+/// `vendor`/`device` ids, and amdgpu (and Intel discrete) drivers expose
+/// `mem_info_vram_total` in bytes. Sysfs has no marketing name, so the name is
+/// the vendor and PCI ids.
+#[cfg(any(target_os = "linux", test))]
+fn drm_adapters(drm_root: &std::path::Path) -> Vec<GpuAdapter> {
+    let read_hex = |path: std::path::PathBuf| {
+        let raw = std::fs::read_to_string(path).ok()?;
+        u32::from_str_radix(raw.trim().trim_start_matches("0x"), 16).ok()
+    };
+    let Ok(entries) = std::fs::read_dir(drm_root) else {
+        return Vec::new();
+    };
+    let mut adapters: Vec<(u32, GpuAdapter)> = entries
+        .filter_map(|entry| {
+            let name = entry.ok()?.file_name().into_string().ok()?;
+            // `card0`, not connectors like `card0-HDMI-A-1`.
+            let card: u32 = name.strip_prefix("card")?.parse().ok()?;
+            let device = drm_root.join(&name).join("device");
+            let vendor_id = read_hex(device.join("vendor"))?;
+            let device_id = read_hex(device.join("device")).unwrap_or(0);
+            let vram_mb = std::fs::read_to_string(device.join("mem_info_vram_total"))
+                .ok()
+                .and_then(|raw| raw.trim().parse::<u64>().ok())
+                .map_or(0, |bytes| bytes / (1024 * 1024));
+            let vendor = match gpu_vendor(vendor_id) {
+                "nvidia" => "NVIDIA",
+                "amd" => "AMD",
+                "intel" => "Intel",
+                _ => "Unknown",
+            };
+            Some((card, GpuAdapter {
+                name: format!("{} GPU [{:04x}:{:04x}]", vendor, vendor_id, device_id),
+                vendor_id,
+                vram_mb,
+            }))
+        })
+        .collect();
+    adapters.sort_by_key(|(card, _)| *card);
+    adapters.into_iter().map(|(_, adapter)| adapter).collect()
+}
+
+// ─── Model Recommendation ─────────────────────────────────────────────────────
+// Turns a detected profile into concrete model sizes. Purely local arithmetic;
+// the sidecar's catalog (`recommend_models` command) picks the actual downloads.
+
+/// A model size and quantization this machine can run.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ModelRecommendation {
+    pub name: String,
+    pub quant: String,
+    /// Weights plus KV cache and runtime overhead at the default context.
+    pub approx_ram_mb: u64,
+    /// Whether every layer can be offloaded to the primary GPU.
+    pub fits_in_vram: bool,
+    pub reason: String,
+}
+
+/// Candidates, largest first: (name, billions of parameters, quant, bits per weight).
+const MODEL_CANDIDATES: &[(&str, f64, &str, f64)] = &[
+    ("Qwen2.5 32B Instruct", 32.0, "Q4_K_M", 4.85),
+    ("Qwen2.5 14B Instruct", 14.0, "Q5_K_M", 5.69),
+    ("Qwen2.5 14B Instruct", 14.0, "Q4_K_M", 4.85),
+    ("Qwen2.5 7B Instruct", 7.0, "Q5_K_M", 5.69),
+    ("Qwen2.5 7B Instruct", 7.0, "Q4_K_M", 4.85),
+    ("Qwen2.5 3B Instruct", 3.0, "Q4_K_M", 4.85),
+    ("Qwen2.5 1.5B Instruct", 1.5, "Q4_K_M", 4.85),
+];
+
+/// KV cache and compute buffers on top of the weights.
+const MODEL_OVERHEAD_MB: u64 = 512;
+
+/// Share of system RAM a CPU-resident model may take; the rest stays with the
+/// OS, the app, and the embedding model.
+const MODEL_RAM_FRACTION: f64 = 0.6;
+
+/// Largest model (in billions of parameters) worth running on an x86 CPU
+/// without AVX2, where llama.cpp falls back to much slower kernels.
+const NO_AVX2_MAX_PARAMS_B: f64 = 3.0;
+
+/// Model sizes `profile` can run, best first: those that fully offload to the
+/// primary GPU (largest first), then those that fit the RAM budget on the CPU.
+/// Models whose file wouldn't fit the model volume's free space are left out.
+/// Empty if nothing fits.
+pub fn recommend_models(profile: &HardwareProfile) -> Vec<ModelRecommendation> {
+    let vram_mb = profile.gpu.as_ref().filter(|g| g.compute_capable).map_or(0, |g| g.vram_mb);
+    let ram_budget_mb = (profile.total_ram_mb as f64 * MODEL_RAM_FRACTION) as u64;
+    let slow_cpu = profile.cpu_arch == "x64" && !profile.cpu_features.iter().any(|f| f == "avx2");
+
+    let mut gpu_fits = Vec::new();
+    let mut cpu_fits = Vec::new();
+    for &(name, params_b, quant, bits_per_weight) in MODEL_CANDIDATES {
+        let file_mb = (params_b * 1e9 * bits_per_weight / 8.0 / (1024.0 * 1024.0)) as u64;
+        // A volume that wasn't found reports 0 total; don't filter on it.
+        if profile.disk_total_mb > 0 && file_mb > profile.disk_free_mb {
+            continue;
+        }
+        let approx_ram_mb = file_mb + MODEL_OVERHEAD_MB;
+        let recommendation = |fits_in_vram, reason| ModelRecommendation {
+            name: name.to_string(),
+            quant: quant.to_string(),
+            approx_ram_mb,
+            fits_in_vram,
+            reason,
+        };
+        if approx_ram_mb <= vram_mb {
+            let gpu = profile.gpu.as_ref().map_or("the GPU", |g| g.name.as_str());
+            gpu_fits.push(recommendation(true, format!("Fully offloads to {} ({} MB VRAM)", gpu, vram_mb)));
+        } else if approx_ram_mb <= ram_budget_mb && !(slow_cpu && params_b > NO_AVX2_MAX_PARAMS_B) {
+            let reason = if slow_cpu {
+                format!("Runs on CPU within {} MB of RAM; larger models are too slow without AVX2", ram_budget_mb)
+            } else {
+                format!("Runs on CPU within {} MB of RAM", ram_budget_mb)
+            };
+            cpu_fits.push(recommendation(false, reason));
+        }
+    }
+    gpu_fits.extend(cpu_fits);
+    gpu_fits
 }
 
 // ─── Live Hardware Stats (Sprint F) ────────────────────────────────────────────
@@ -212,11 +591,110 @@ pub struct LiveHardwareStats {
     pub sampled_at: String,
 }
 
+/// Currently available system memory in bytes. Refreshes memory only, so it's cheap.
+pub fn available_memory_bytes() -> u64 {
+    let mut sys = System::new();
+    sys.refresh_memory();
+    sys.available_memory()
+}
+
+/// Total system memory in bytes. Refreshes memory only.
+pub fn total_memory_bytes() -> u64 {
+    let mut sys = System::new();
+    sys.refresh_memory();
+    sys.total_memory()
+}
+
+// ─── Profile Cache ────────────────────────────────────────────────────────────
+// Backs `get_hardware_profile`. Apart from available RAM the hardware doesn't
+// change while the app runs, and a full detection (CPU and memory scan, GPU
+// probes that may spawn nvidia-smi) is too slow to repeat for every caller.
+
+/// The last `detect_hardware` result and the model directory it measured disk
+/// space for.
+#[derive(Default)]
+pub struct HardwareCache {
+    profile: std::sync::Mutex<Option<(std::path::PathBuf, HardwareProfile)>>,
+}
+
+impl HardwareCache {
+    /// The profile for `model_dir`, detected on first use or when the model
+    /// directory changed, with available RAM re-read on every call. Blocking
+    /// when it has to detect.
+    pub fn profile(&self, model_dir: &Path) -> HardwareProfile {
+        self.profile_with(model_dir, detect_hardware)
+    }
+
+    fn profile_with(&self, model_dir: &Path, detect: impl FnOnce(&Path) -> HardwareProfile) -> HardwareProfile {
+        let mut cached = self.profile.lock().unwrap_or_else(|e| e.into_inner());
+        match cached.as_mut() {
+            Some((dir, profile)) if dir == model_dir => {
+                profile.available_ram_mb = available_memory_bytes() / (1024 * 1024);
+                profile.clone()
+            }
+            _ => {
+                let profile = detect(model_dir);
+                *cached = Some((model_dir.to_path_buf(), profile.clone()));
+                profile
+            }
+        }
+    }
+
+    /// Re-read available RAM, in MB, updating the cached profile. Refreshes
+    /// memory only, so it's cheap enough for a live gauge.
+    pub fn refresh_available_memory(&self) -> u64 {
+        let available_ram_mb = available_memory_bytes() / (1024 * 1024);
+        if let Some((_, profile)) = self.profile.lock().unwrap_or_else(|e| e.into_inner()).as_mut() {
+            profile.available_ram_mb = available_ram_mb;
+        }
+        available_ram_mb
+    }
+}
+
+// ─── Low Memory Monitor ───────────────────────────────────────────────────────
+// Polled by a background task in setup; decides when to emit `semblance://low-memory`.
+
+/// Minimum time between repeated alerts while memory stays low.
+pub const LOW_MEMORY_REALERT: std::time::Duration = std::time::Duration::from_secs(300);
+/// Memory must recover this far above the threshold (percent) before a new
+/// crossing counts, so hovering around the threshold doesn't alert repeatedly.
+const LOW_MEMORY_HYSTERESIS_PERCENT: u64 = 10;
+
+/// Debounced threshold-crossing detector for available RAM.
+#[derive(Default)]
+pub struct LowMemoryMonitor {
+    low: bool,
+    last_alert: Option<std::time::Instant>,
+}
+
+impl LowMemoryMonitor {
+    /// Record a sample. Returns true if the UI should be alerted: on the first
+    /// sample below `threshold_mb`, and again every `LOW_MEMORY_REALERT` while it stays low.
+    pub fn observe(&mut self, available_mb: u64, threshold_mb: u64, now: std::time::Instant) -> bool {
+        if available_mb >= threshold_mb + threshold_mb * LOW_MEMORY_HYSTERESIS_PERCENT / 100 {
+            self.low = false;
+            return false;
+        }
+        if available_mb >= threshold_mb {
+            return false;
+        }
+        let due = !self.low || self.last_alert.is_none_or(|t| now.duration_since(t) >= LOW_MEMORY_REALERT);
+        self.low = true;
+        if due {
+            self.last_alert = Some(now);
+        }
+        due
+    }
+}
+
 /// Get live hardware stats — CPU usage, memory, disk, temperature.
 /// All detection is local — no network calls, no shell commands.
 pub fn get_live_stats() -> LiveHardwareStats {
-    let mut sys = System::new_all();
-    sys.refresh_all();
+    let mut sys = System::new_with_specifics(
+        RefreshKind::new()
+            .with_cpu(CpuRefreshKind::new().with_cpu_usage())
+            .with_memory(MemoryRefreshKind::new().with_ram()),
+    );
 
     // CPU usage (global average)
     // Need a short sleep for accurate CPU measurement
@@ -325,44 +803,249 @@ fn chrono_iso_now() -> String {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_hardware_cache_detects_once_per_model_dir() {
+        let cache = HardwareCache::default();
+        let detections = std::cell::Cell::new(0);
+        let detect = |_: &Path| {
+            detections.set(detections.get() + 1);
+            HardwareProfile {
+                tier: "standard".to_string(),
+                cpu_cores: 8,
+                cpu_arch: "x64".to_string(),
+                cpu_features: Vec::new(),
+                total_ram_mb: 16384,
+                available_ram_mb: 0,
+                os: "linux".to_string(),
+                gpu: None,
+                gpus: Vec::new(),
+                voice_capable: true,
+                disk_free_mb: 1000,
+                disk_total_mb: 2000,
+            }
+        };
+        let models = Path::new("/data/models");
+        assert_eq!(cache.profile_with(models, detect).tier, "standard");
+        let again = cache.profile_with(models, detect);
+        assert_eq!(detections.get(), 1);
+        // Only available RAM is re-read.
+        assert!(again.available_ram_mb > 0);
+        assert_eq!(again.disk_free_mb, 1000);
+
+        cache.profile_with(Path::new("/mnt/models"), detect);
+        assert_eq!(detections.get(), 2);
+        assert!(cache.refresh_available_memory() > 0);
+    }
+
+    #[test]
+    fn test_parse_nvidia_smi_lists_every_device() {
+        let gpus = parse_nvidia_smi("NVIDIA GeForce RTX 4070, 12282\n");
+        assert_eq!(gpus.len(), 1);
+        let gpu = &gpus[0];
+        assert_eq!(gpu.name, "NVIDIA GeForce RTX 4070");
+        assert_eq!(gpu.vendor, "nvidia");
+        assert_eq!(gpu.vram_mb, 12282);
+        assert!(gpu.compute_capable);
+        assert_eq!(classify_tier(16384, &gpus), "workstation");
+
+        let multi = parse_nvidia_smi("NVIDIA T400, 2048\r\nNVIDIA GeForce RTX 3090, 24576\nNVIDIA GeForce GTX 1660, 6144\n");
+        assert_eq!(multi.len(), 3);
+        assert_eq!(multi[0].name, "NVIDIA T400");
+        assert_eq!(primary_gpu(&multi).unwrap().name, "NVIDIA GeForce RTX 3090");
+
+        assert!(parse_nvidia_smi("").is_empty());
+        assert!(parse_nvidia_smi("No devices were found\n").is_empty());
+        assert!(parse_nvidia_smi("NVIDIA GeForce RTX 4070, [N/A]\n").is_empty());
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_output_within_kills_a_command_past_its_timeout() {
+        let command = |script: &str| {
+            let mut cmd = std::process::Command::new("sh");
+            cmd.args(["-c", script]);
+            cmd
+        };
+        let timeout = std::time::Duration::from_millis(200);
+        assert_eq!(output_within(command("echo 'NVIDIA T400, 2048'"), timeout).as_deref(), Some("NVIDIA T400, 2048\n"));
+        assert_eq!(output_within(command("exit 1"), timeout), None);
+
+        let start = std::time::Instant::now();
+        assert_eq!(output_within(command("sleep 10"), timeout), None);
+        assert!(start.elapsed() < std::time::Duration::from_secs(5));
+        assert_eq!(output_within(command("definitely-not-a-command-xyz"), timeout), None);
+    }
+
+    #[test]
+    fn test_primary_gpu_prefers_discrete_adapter() {
+        let adapter = |name: &str, vendor_id, vram_mb| GpuAdapter { name: name.to_string(), vendor_id, vram_mb };
+        let gpus = adapter_gpus(vec![
+            adapter("Intel(R) UHD Graphics 770", 0x8086, 128),
+            adapter("AMD Radeon RX 7800 XT", 0x1002, 16368),
+        ]);
+        assert_eq!(gpus.len(), 2);
+        let gpu = primary_gpu(&gpus).unwrap();
+        assert_eq!((gpu.name.as_str(), gpu.vendor.as_str(), gpu.vram_mb), ("AMD Radeon RX 7800 XT", "amd", 16368));
+        assert!(gpu.compute_capable);
+
+        let integrated = adapter_gpus(vec![adapter("Intel(R) Iris(R) Xe Graphics", 0x8086, 128)]);
+        assert_eq!(integrated[0].vendor, "intel");
+        assert!(!integrated[0].compute_capable);
+        assert_eq!(primary_gpu(&integrated).unwrap().name, "Intel(R) Iris(R) Xe Graphics");
+        assert!(primary_gpu(&[]).is_none());
+    }
+
+    #[test]
+    fn test_drm_adapters_reads_sysfs_layout() {
+        let root = std::env::temp_dir().join(format!("semblance-drm-{}", std::process::id()));
+        let card = |name: &str, vendor: &str, device: &str, vram: Option<&str>| {
+            let dir = root.join(name).join("device");
+            std::fs::create_dir_all(&dir).unwrap();
+            std::fs::write(dir.join("vendor"), vendor).unwrap();
+            std::fs::write(dir.join("device"), device).unwrap();
+            if let Some(vram) = vram {
+                std::fs::write(dir.join("mem_info_vram_total"), vram).unwrap();
+            }
+        };
+        card("card0", "0x8086\n", "0x4680\n", None);
+        card("card1", "0x1002\n", "0x747e\n", Some("17163091968\n"));
+        std::fs::create_dir_all(root.join("card1-DP-1")).unwrap();
+
+        let adapters = drm_adapters(&root);
+        std::fs::remove_dir_all(&root).unwrap();
+        assert_eq!(adapters.len(), 2);
+        assert_eq!(adapters[1], GpuAdapter { name: "AMD GPU [1002:747e]".to_string(), vendor_id: 0x1002, vram_mb: 16368 });
+        let gpus = adapter_gpus(adapters);
+        let gpu = primary_gpu(&gpus).unwrap();
+        assert_eq!(gpu.vendor, "amd");
+        assert!(gpu.compute_capable);
+        assert!(drm_adapters(std::path::Path::new("/nonexistent/drm")).is_empty());
+    }
+
+    fn profile(total_ram_mb: u64, gpu: Option<GpuInfo>) -> HardwareProfile {
+        HardwareProfile {
+            tier: classify_tier(total_ram_mb, gpu.as_slice()),
+            cpu_cores: 8,
+            cpu_arch: "x64".to_string(),
+            cpu_features: vec!["avx2".to_string(), "fma".to_string()],
+            total_ram_mb,
+            available_ram_mb: total_ram_mb / 2,
+            os: "linux".to_string(),
+            gpus: gpu.iter().cloned().collect(),
+            gpu,
+            voice_capable: true,
+            disk_free_mb: 500 * 1024,
+            disk_total_mb: 1000 * 1024,
+        }
+    }
+
+    #[test]
+    fn test_recommend_models_constrained_gets_3b_q4() {
+        let p = profile(4096, None);
+        assert_eq!(p.tier, "constrained");
+        let recs = recommend_models(&p);
+        assert_eq!((recs[0].name.as_str(), recs[0].quant.as_str()), ("Qwen2.5 3B Instruct", "Q4_K_M"));
+        assert!(!recs[0].fits_in_vram);
+        assert!(recs.iter().all(|r| r.approx_ram_mb <= 4096));
+    }
+
+    #[test]
+    fn test_recommend_models_offloads_to_vram() {
+        let gpu = GpuInfo { name: "RTX 4070".to_string(), vendor: "nvidia".to_string(), vram_mb: 12288, compute_capable: true };
+        let p = profile(16384, Some(gpu));
+        assert_eq!(p.tier, "workstation");
+        let recs = recommend_models(&p);
+        assert_eq!((recs[0].name.as_str(), recs[0].quant.as_str()), ("Qwen2.5 14B Instruct", "Q5_K_M"));
+        assert!(recs[0].fits_in_vram);
+        assert!(recs[0].approx_ram_mb <= 12288);
+        assert!(recs[0].reason.contains("RTX 4070"));
+        // No 32B: it fits neither VRAM nor the RAM budget.
+        assert!(recs.iter().all(|r| !r.name.contains("32B")));
+
+        // VRAM on a GPU that can't run compute isn't offloaded to.
+        let mut p = profile(16384, None);
+        p.gpu = Some(GpuInfo { name: "Basic adapter".to_string(), vendor: "unknown".to_string(), vram_mb: 12288, compute_capable: false });
+        assert!(recommend_models(&p).iter().all(|r| !r.fits_in_vram));
+    }
+
+    #[test]
+    fn test_recommend_models_respects_disk_and_avx2() {
+        let mut p = profile(32768, None);
+        p.disk_free_mb = 3000;
+        let recs = recommend_models(&p);
+        assert_eq!(recs[0].name, "Qwen2.5 3B Instruct");
+        p.disk_free_mb = 500;
+        assert!(recommend_models(&p).is_empty());
+        // An unknown volume doesn't block anything.
+        p.disk_total_mb = 0;
+        assert_eq!(recommend_models(&p)[0].name, "Qwen2.5 32B Instruct");
+
+        let mut p = profile(32768, None);
+        p.cpu_features.clear();
+        let recs = recommend_models(&p);
+        assert_eq!(recs[0].name, "Qwen2.5 3B Instruct");
+        assert!(recs[0].reason.contains("AVX2"));
+    }
+
     #[test]
     fn test_classify_tier_constrained() {
-        assert_eq!(classify_tier(4096, &None), "constrained");
-        assert_eq!(classify_tier(6144, &None), "constrained");
+        assert_eq!(classify_tier(4096, &[]), "constrained");
+        assert_eq!(classify_tier(6144, &[]), "constrained");
     }
 
     #[test]
     fn test_classify_tier_standard() {
-        assert_eq!(classify_tier(8192, &None), "standard");
-        assert_eq!(classify_tier(12288, &None), "standard");
+        assert_eq!(classify_tier(8192, &[]), "standard");
+        assert_eq!(classify_tier(12288, &[]), "standard");
     }
 
     #[test]
     fn test_classify_tier_performance() {
-        assert_eq!(classify_tier(16384, &None), "performance");
-        assert_eq!(classify_tier(24576, &None), "performance");
+        assert_eq!(classify_tier(16384, &[]), "performance");
+        assert_eq!(classify_tier(24576, &[]), "performance");
     }
 
     #[test]
     fn test_classify_tier_workstation() {
-        assert_eq!(classify_tier(32768, &None), "workstation");
-        assert_eq!(classify_tier(65536, &None), "workstation");
+        assert_eq!(classify_tier(32768, &[]), "workstation");
+        assert_eq!(classify_tier(65536, &[]), "workstation");
     }
 
     #[test]
     fn test_classify_tier_gpu_promotion() {
-        let gpu = Some(GpuInfo {
+        let gpu = GpuInfo {
             name: "RTX 4070".to_string(),
             vendor: "nvidia".to_string(),
             vram_mb: 12288,
             compute_capable: true,
-        });
-        assert_eq!(classify_tier(16384, &gpu), "workstation");
+        };
+        assert_eq!(classify_tier(16384, &[gpu]), "workstation");
+    }
+
+    #[test]
+    fn test_classify_tier_multi_gpu() {
+        let gpu = |name: &str, vram_mb, compute_capable| GpuInfo {
+            name: name.to_string(),
+            vendor: "unknown".to_string(),
+            vram_mb,
+            compute_capable,
+        };
+        // The discrete card promotes, whichever order the GPUs were found in.
+        let mixed = [gpu("Intel UHD 770", 128, false), gpu("RTX 4070", 12288, true)];
+        assert_eq!(classify_tier(16384, &mixed), "workstation");
+        // Several compute-capable GPUs: the largest decides.
+        let two = [gpu("RTX 3090", 24576, true), gpu("T400", 2048, true)];
+        assert_eq!(primary_gpu(&two).unwrap().name, "RTX 3090");
+        assert_eq!(classify_tier(8192, &two), "workstation");
+        // Lots of VRAM on a GPU that can't run compute doesn't count.
+        let no_compute = [gpu("Display adapter", 16384, false), gpu("T400", 2048, true)];
+        assert_eq!(primary_gpu(&no_compute).unwrap().name, "T400");
+        assert_eq!(classify_tier(16384, &no_compute), "performance");
     }
 
     #[test]
     fn test_detect_hardware_returns_valid_profile() {
-        let profile = detect_hardware();
+        let profile = detect_hardware(&std::env::temp_dir());
         assert!(profile.cpu_cores > 0);
         assert!(profile.total_ram_mb > 0);
         assert!(!profile.os.is_empty());
@@ -371,6 +1054,24 @@ mod tests {
             .contains(&profile.tier.as_str()));
     }
 
+    #[test]
+    fn test_low_memory_monitor_debounces() {
+        let start = std::time::Instant::now();
+        let mut monitor = LowMemoryMonitor::default();
+        assert!(!monitor.observe(4000, 1000, start));
+        assert!(monitor.observe(900, 1000, start));
+        // Still low: no repeat until the re-alert interval passes.
+        assert!(!monitor.observe(800, 1000, start + std::time::Duration::from_secs(30)));
+        assert!(monitor.observe(800, 1000, start + LOW_MEMORY_REALERT));
+        // Hovering just above the threshold doesn't re-arm.
+        let later = start + LOW_MEMORY_REALERT + std::time::Duration::from_secs(10);
+        assert!(!monitor.observe(1050, 1000, later));
+        assert!(!monitor.observe(950, 1000, later));
+        // Recovering past the hysteresis band re-arms.
+        assert!(!monitor.observe(1200, 1000, later));
+        assert!(monitor.observe(950, 1000, later));
+    }
+
     #[test]
     fn test_voice_capable_standard_8gb() {
         assert!(is_voice_capable(8192, "standard"));
@@ -392,6 +1093,43 @@ mod tests {
         assert!(!is_voice_capable(6144, "standard"));
     }
 
+    #[test]
+    fn test_cpu_features_match_target() {
+        let features = detect_cpu_features();
+        let expected: &[&str] = if cfg!(any(target_arch = "x86", target_arch = "x86_64")) {
+            &["avx2", "avx512f", "f16c", "fma"]
+        } else if cfg!(target_arch = "aarch64") {
+            &["neon", "dotprod"]
+        } else {
+            &[]
+        };
+        assert!(features.iter().all(|f| expected.contains(&f.as_str())), "{:?}", features);
+        if cfg!(target_arch = "aarch64") {
+            // NEON is mandatory on AArch64.
+            assert!(features.contains(&"neon".to_string()));
+        }
+        // Whatever the compiler was told to assume must also be detected.
+        if cfg!(target_feature = "avx2") {
+            assert!(features.contains(&"avx2".to_string()));
+        }
+        assert_eq!(detect_hardware(&std::env::temp_dir()).cpu_features, features);
+    }
+
+    #[test]
+    fn test_volume_space_picks_deepest_mount() {
+        const GB: u64 = 1024 * 1024 * 1024;
+        let disks = [
+            (Path::new("/"), 5 * GB, 100 * GB),
+            (Path::new("/mnt/models"), 400 * GB, 1000 * GB),
+            (Path::new("/mnt/models-old"), GB, 10 * GB),
+        ];
+        let space = |path: &str| volume_space(disks.iter().copied(), Path::new(path));
+        assert_eq!(space("/home/me/.semblance/data"), (5 * 1024, 100 * 1024));
+        assert_eq!(space("/mnt/models/gguf/not-created-yet"), (400 * 1024, 1000 * 1024));
+        assert_eq!(space("/mnt/models"), (400 * 1024, 1000 * 1024));
+        assert_eq!(volume_space(std::iter::empty(), Path::new("/home")), (0, 0));
+    }
+
     #[test]
     fn test_voice_not_capable_constrained_even_8gb() {
         assert!(!is_voice_capable(8192, "constrained"));
@@ -399,7 +1137,7 @@ mod tests {
 
     #[test]
     fn test_detect_hardware_includes_voice_capable() {
-        let profile = detect_hardware();
+        let profile = detect_hardware(&std::env::temp_dir());
         // voice_capable should be consistent with RAM and tier
         let expected = is_voice_capable(profile.total_ram_mb, &profile.tier);
         assert_eq!(profile.voice_capable, expected);
@@ -0,0 +1,283 @@
+// Hardware Report — machine profile, live telemetry, inference backend, and
+// model recommendations in one Markdown or JSON document.
+// Meant for onboarding ("what should I download?") and attaching to bug reports.
+// CRITICAL: No network calls. The report is only written to a local path.
+
+use crate::hardware::{HardwareProfile, LiveHardwareStats};
+use crate::native_runtime::BackendInfo;
+use serde::{Deserialize, Serialize};
+use std::fmt::Write;
+
+/// One catalog model recommended for this machine's tier.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct RecommendedModel {
+    pub id: String,
+    pub display_name: String,
+    pub parameter_count: String,
+    pub quantization: String,
+    pub file_size_bytes: u64,
+    pub ram_required_mb: u64,
+    pub is_embedding: bool,
+}
+
+/// The sidecar's model recommendations for a hardware tier.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ModelRecommendations {
+    pub tier: String,
+    /// Reasoning + embedding models for the tier.
+    pub models: Vec<RecommendedModel>,
+    /// 1-bit alternative for the reasoning model.
+    pub bitnet_model: Option<RecommendedModel>,
+    pub total_download_bytes: u64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct HardwareReport {
+    pub app_version: String,
+    pub hardware: HardwareProfile,
+    pub live: LiveHardwareStats,
+    pub backend: BackendInfo,
+    pub recommendations: Option<ModelRecommendations>,
+    /// Why `recommendations` is missing, e.g. the sidecar isn't running yet.
+    pub recommendations_error: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReportFormat {
+    Markdown,
+    Json,
+}
+
+impl ReportFormat {
+    /// "markdown"/"md" or "json"; `None` defaults to Markdown.
+    pub fn parse(format: Option<&str>) -> Result<Self, String> {
+        match format.map(|f| f.trim().to_lowercase()).as_deref() {
+            None | Some("markdown") | Some("md") => Ok(ReportFormat::Markdown),
+            Some("json") => Ok(ReportFormat::Json),
+            Some(other) => Err(format!("Unknown report format '{}' (expected markdown or json)", other)),
+        }
+    }
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            ReportFormat::Markdown => "md",
+            ReportFormat::Json => "json",
+        }
+    }
+}
+
+pub fn render(report: &HardwareReport, format: ReportFormat) -> Result<String, String> {
+    match format {
+        ReportFormat::Json => serde_json::to_string_pretty(report)
+            .map_err(|e| format!("Failed to serialize hardware report: {}", e)),
+        ReportFormat::Markdown => Ok(render_markdown(report)),
+    }
+}
+
+fn gb_from_mb(mb: u64) -> String {
+    format!("{:.1} GB", mb as f64 / 1024.0)
+}
+
+fn gb_from_bytes(bytes: u64) -> String {
+    format!("{:.1} GB", bytes as f64 / (1024.0 * 1024.0 * 1024.0))
+}
+
+fn or_unavailable(value: Option<String>) -> String {
+    value.unwrap_or_else(|| "unavailable".to_string())
+}
+
+fn render_markdown(report: &HardwareReport) -> String {
+    let hw = &report.hardware;
+    let live = &report.live;
+    let mut md = String::new();
+
+    let _ = writeln!(md, "# Semblance Hardware Report\n");
+    let _ = writeln!(md, "Generated {} by Semblance {}.\n", live.sampled_at, report.app_version);
+
+    let _ = writeln!(md, "## Machine\n");
+    let _ = writeln!(md, "| | |\n|---|---|");
+    let _ = writeln!(md, "| Tier | {} |", hw.tier);
+    let _ = writeln!(md, "| OS | {} ({}) |", hw.os, hw.cpu_arch);
+    let _ = writeln!(md, "| CPU cores | {} |", hw.cpu_cores);
+    let _ = writeln!(md, "| CPU features | {} |", if hw.cpu_features.is_empty() { "None detected".to_string() } else { hw.cpu_features.join(", ") });
+    let _ = writeln!(md, "| RAM | {} total, {} available |", gb_from_mb(hw.total_ram_mb), gb_from_mb(hw.available_ram_mb));
+    let gpu = match &hw.gpu {
+        Some(g) => format!(
+            "{} ({}, {} VRAM{})",
+            g.name,
+            g.vendor,
+            gb_from_mb(g.vram_mb),
+            if g.compute_capable { ", compute capable" } else { "" }
+        ),
+        None => "None detected".to_string(),
+    };
+    let _ = writeln!(md, "| GPU | {} |", gpu);
+    let _ = writeln!(md, "| Model storage | {} free of {} |", gb_from_mb(hw.disk_free_mb), gb_from_mb(hw.disk_total_mb));
+    let _ = writeln!(md, "| Local voice (Whisper) | {} |\n", if hw.voice_capable { "Supported" } else { "Not supported" });
+
+    let _ = writeln!(md, "## Live Telemetry\n");
+    let _ = writeln!(md, "- CPU usage: {:.1}%", live.cpu_usage_percent);
+    let _ = writeln!(md, "- Memory: {} used of {}", gb_from_mb(live.memory_used_mb), gb_from_mb(live.memory_total_mb));
+    let _ = writeln!(md, "- CPU temperature: {}", or_unavailable(live.cpu_temp_celsius.map(|t| format!("{:.0} °C", t))));
+    let _ = writeln!(md, "- GPU temperature: {}", or_unavailable(live.gpu_temp_celsius.map(|t| format!("{:.0} °C", t))));
+    let _ = writeln!(md, "- GPU usage: {}", or_unavailable(live.gpu_usage_percent.map(|u| format!("{:.1}%", u))));
+    for disk in &live.disk_stats {
+        let _ = writeln!(
+            md,
+            "- Disk {}: {:.1} GB free of {:.1} GB ({:.1}% used)",
+            disk.mount_point, disk.available_gb, disk.total_gb, disk.used_percent
+        );
+    }
+    md.push('\n');
+
+    let backend = &report.backend;
+    let fa = &backend.flash_attention;
+    let _ = writeln!(md, "## Inference Backend\n");
+    let _ = writeln!(md, "- Engine: {}", backend.engine);
+    if let Some(error) = &backend.backend_error {
+        let _ = writeln!(md, "- Backend error: {}", error);
+    }
+    let _ = writeln!(md, "- GPU offload: {}", if backend.gpu_offload_supported { "supported" } else { "not supported (CPU only)" });
+    let _ = writeln!(
+        md,
+        "- Running on: {} ({} layers offloaded{})",
+        backend.backend,
+        backend.gpu_layers_offloaded,
+        backend.gpu_device_name.as_ref().map(|name| format!(", {}", name)).unwrap_or_default()
+    );
+    let _ = writeln!(
+        md,
+        "- Flash attention: {} on {}, {}\n",
+        if fa.supported { "supported" } else { "not supported" },
+        fa.backend,
+        match fa.setting {
+            Some(true) => "enabled",
+            Some(false) => "disabled",
+            None => "default (off)",
+        }
+    );
+
+    match &report.recommendations {
+        Some(rec) => {
+            let _ = writeln!(md, "## Recommended Models ({} tier)\n", rec.tier);
+            let _ = writeln!(md, "| Model | Role | Parameters | Download | RAM needed |\n|---|---|---|---|---|");
+            let row = |md: &mut String, m: &RecommendedModel, role: &str| {
+                let _ = writeln!(
+                    md,
+                    "| {} ({}) | {} | {} | {} | {} |",
+                    m.display_name,
+                    m.quantization,
+                    role,
+                    m.parameter_count,
+                    gb_from_bytes(m.file_size_bytes),
+                    gb_from_mb(m.ram_required_mb)
+                );
+            };
+            for m in &rec.models {
+                row(&mut md, m, if m.is_embedding { "Embedding" } else { "Reasoning" });
+            }
+            if let Some(m) = &rec.bitnet_model {
+                row(&mut md, m, "Reasoning (1-bit alternative)");
+            }
+            let _ = writeln!(md, "\nTotal download for the recommended set: {}", gb_from_bytes(rec.total_download_bytes));
+        }
+        None => {
+            let _ = writeln!(md, "## Recommended Models\n");
+            let _ = writeln!(
+                md,
+                "Unavailable: {}",
+                report.recommendations_error.as_deref().unwrap_or("unknown error")
+            );
+        }
+    }
+    md
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hardware::{DiskStat, GpuInfo};
+    use crate::native_runtime::FlashAttentionInfo;
+
+    fn report(recommendations: Option<ModelRecommendations>) -> HardwareReport {
+        HardwareReport {
+            app_version: "0.1.0".to_string(),
+            hardware: HardwareProfile {
+                tier: "performance".to_string(),
+                cpu_cores: 8,
+                cpu_arch: "x64".to_string(),
+                cpu_features: vec!["avx2".to_string(), "fma".to_string()],
+                total_ram_mb: 16384,
+                available_ram_mb: 8192,
+                os: "linux".to_string(),
+                gpu: Some(GpuInfo { name: "RTX 4070".to_string(), vendor: "nvidia".to_string(), vram_mb: 12288, compute_capable: true }),
+                gpus: vec![GpuInfo { name: "RTX 4070".to_string(), vendor: "nvidia".to_string(), vram_mb: 12288, compute_capable: true }],
+                voice_capable: true,
+                disk_free_mb: 102400,
+                disk_total_mb: 524288,
+            },
+            live: LiveHardwareStats {
+                cpu_usage_percent: 12.5,
+                memory_used_mb: 8192,
+                memory_total_mb: 16384,
+                memory_available_mb: 8192,
+                disk_stats: vec![DiskStat { mount_point: "/".to_string(), total_gb: 512.0, available_gb: 100.0, used_percent: 80.5 }],
+                cpu_temp_celsius: Some(55.0),
+                gpu_temp_celsius: None,
+                gpu_usage_percent: None,
+                sampled_at: "2026-01-01T00:00:00Z".to_string(),
+            },
+            backend: BackendInfo {
+                engine: "bitnet.cpp".to_string(),
+                backend_error: None,
+                gpu_offload_supported: false,
+                backend: "cpu".to_string(),
+                gpu_layers_offloaded: 0,
+                gpu_device_name: None,
+                flash_attention: FlashAttentionInfo { setting: None, supported: true, backend: "cpu".to_string(), last_context: None },
+            },
+            recommendations_error: recommendations.is_none().then(|| "Sidecar not running".to_string()),
+            recommendations,
+        }
+    }
+
+    #[test]
+    fn test_render_markdown_sections() {
+        let model = RecommendedModel {
+            id: "qwen".to_string(),
+            display_name: "Qwen 2.5 7B".to_string(),
+            parameter_count: "7B".to_string(),
+            quantization: "Q4_K_M".to_string(),
+            file_size_bytes: 4 * 1024 * 1024 * 1024,
+            ram_required_mb: 6144,
+            is_embedding: false,
+        };
+        let md = render_markdown(&report(Some(ModelRecommendations {
+            tier: "performance".to_string(),
+            models: vec![model],
+            bitnet_model: None,
+            total_download_bytes: 4 * 1024 * 1024 * 1024,
+        })));
+        assert!(md.contains("| RAM | 16.0 GB total, 8.0 GB available |"));
+        assert!(md.contains("RTX 4070 (nvidia, 12.0 GB VRAM, compute capable)"));
+        assert!(md.contains("| Model storage | 100.0 GB free of 512.0 GB |"));
+        assert!(md.contains("| CPU features | avx2, fma |"));
+        assert!(md.contains("- GPU temperature: unavailable"));
+        assert!(md.contains("| Qwen 2.5 7B (Q4_K_M) | Reasoning | 7B | 4.0 GB | 6.0 GB |"));
+
+        let md = render_markdown(&report(None));
+        assert!(md.contains("Unavailable: Sidecar not running"));
+    }
+
+    #[test]
+    fn test_report_format_parse() {
+        assert_eq!(ReportFormat::parse(None).unwrap(), ReportFormat::Markdown);
+        assert_eq!(ReportFormat::parse(Some("JSON")).unwrap().extension(), "json");
+        assert!(ReportFormat::parse(Some("pdf")).is_err());
+        let json = render(&report(None), ReportFormat::Json).unwrap();
+        assert!(json.contains("\"recommendationsError\": \"Sidecar not running\""));
+    }
+}
@@ -16,6 +16,7 @@ use serde_json::Value;
 use std::collections::HashMap;
 use std::path::PathBuf;
 use std::sync::Arc;
+use error::SemblanceError;
 use tauri::Listener;
 use tauri::{Emitter, Manager};
 use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
@@ -25,8 +26,24 @@ use tokio::sync::{oneshot, Mutex};
 #[cfg(target_os = "windows")]
 use std::os::windows::process::CommandExt;
 
+mod autonomy;
+mod bridge_metrics;
+mod chat_stream;
+mod credentials;
+mod error;
+mod event_replay;
+mod finance;
+mod gguf;
+mod grammar;
 mod hardware;
+mod hardware_report;
+mod model_verify;
 mod native_runtime;
+mod scheduler;
+mod settings;
+mod storage;
+mod summarize;
+mod supervisor;
 use native_runtime::RuntimeStatus;
 
 // ─── Data Types ────────────────────────────────────────────────────────────
@@ -41,6 +58,7 @@ pub struct OllamaStatus {
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct IndexingStatus {
+    /// `idle`, `indexing`, `paused` or `cancelled`.
     pub state: String,
     pub files_scanned: u32,
     pub files_total: u32,
@@ -84,6 +102,92 @@ pub struct AutonomyConfig {
     pub domains: std::collections::HashMap<String, String>,
 }
 
+/// An action `preview_action` can preview, with the arguments of the command
+/// that performs it.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(tag = "type", rename_all = "snake_case", rename_all_fields = "camelCase")]
+pub enum PreviewableAction {
+    /// `send_email_action`.
+    SendEmail {
+        to: Vec<String>,
+        subject: String,
+        body: String,
+        #[serde(default)]
+        reply_to_message_id: Option<String>,
+    },
+    /// `archive_emails`.
+    ArchiveEmails { message_ids: Vec<String> },
+}
+
+impl PreviewableAction {
+    /// The IPC action type the command executes.
+    fn action_type(&self) -> &'static str {
+        match self {
+            PreviewableAction::SendEmail { .. } => "email.send",
+            PreviewableAction::ArchiveEmails { .. } => "email.archive",
+        }
+    }
+}
+
+/// An indexed email an action refers to.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct MessagePreview {
+    pub message_id: String,
+    pub from: String,
+    pub subject: String,
+    pub received_at: String,
+    pub folder: String,
+}
+
+/// The email `send_email_action` would send, as the recipient gets it.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct EmailPreview {
+    /// The SMTP account it goes out from; None when it is sent through a
+    /// connected Google account.
+    pub from: Option<String>,
+    pub to: Vec<String>,
+    pub subject: String,
+    pub body: String,
+    /// The message replied to, if `replyToMessageId` is indexed.
+    pub in_reply_to: Option<MessagePreview>,
+}
+
+/// The messages `archive_emails` would move out of the inbox.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ArchivePreview {
+    pub messages: Vec<MessagePreview>,
+    /// Requested ids that aren't in the email index; archiving one still hits the server.
+    pub unknown_message_ids: Vec<String>,
+}
+
+/// What executing an action would do. Built without sending, archiving or
+/// queueing anything, whatever the autonomy tier.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ActionPreview {
+    /// The IPC action type (`email.send`, `email.archive`).
+    pub action: String,
+    /// The autonomy tier the command records the action under; None for
+    /// `archive_emails`, which runs without going through the action log.
+    pub tier: Option<String>,
+    /// Whether the command queues the action for approval instead of running it.
+    pub requires_approval: bool,
+    /// Whether `undo_action` can reverse the action once it runs. Neither can:
+    /// a sent email can't be recalled, and an archive leaves no action log
+    /// entry to undo.
+    pub undoable: bool,
+    /// How long after it runs the action can still be undone; None when it
+    /// can't be, or there is no deadline.
+    pub undo_window_ms: Option<u64>,
+    /// Set for `send_email`.
+    pub email: Option<EmailPreview>,
+    /// Set for `archive_emails`.
+    pub archive: Option<ArchivePreview>,
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct ChatMessage {
     pub id: String,
@@ -111,6 +215,10 @@ pub struct ConnectionTestResult {
     pub success: bool,
     pub error: Option<String>,
     pub calendars: Option<Vec<CalendarInfo>>,
+    /// On failure, the phase that failed or was still running at the timeout
+    /// ("dns", "tls", "auth", "list_calendars").
+    #[serde(default)]
+    pub phase: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -138,176 +246,865 @@ pub struct AccountStatus {
 
 // ─── Sidecar Bridge ───────────────────────────────────────────────────────────
 
+/// How long a single write to the sidecar's stdin may take. A sidecar that
+/// stops reading fills the pipe buffer, and without this the writer task would
+/// block on it forever while the queue behind it grows.
+const SIDECAR_WRITE_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(5);
+
+/// How long `SidecarBridge::call` waits for a response.
+const SIDECAR_CALL_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(120);
+
+/// How long `SidecarBridge::call_fire` waits; its methods answer at once and
+/// report progress through events.
+const SIDECAR_FIRE_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(10);
+
+/// Default wait for `test_credential`, well short of `SIDECAR_CALL_TIMEOUT`:
+/// a mistyped server should fail the "Test" button quickly.
+const CREDENTIAL_TEST_TIMEOUT_SECS: u64 = 15;
+
+/// For status polls, which should fail fast while the sidecar is stuck rather
+/// than hold up the UI.
+const SIDECAR_STATUS_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(2);
+
+/// For methods that run the reasoning model over a lot of data (digests,
+/// reports, statement imports), which can take several minutes on constrained hardware.
+const SIDECAR_GENERATION_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(600);
+
+/// How often the heartbeat pings the sidecar.
+const SIDECAR_PING_INTERVAL: std::time::Duration = std::time::Duration::from_secs(15);
+
+/// A ping not answered within this long counts as missed.
+const SIDECAR_PING_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(5);
+
+/// Missed pings in a row after which the sidecar is considered hung.
+const SIDECAR_PING_MAX_MISSES: u32 = 3;
+
+/// Sidecar stderr lines kept in memory for `get_sidecar_logs`.
+const SIDECAR_STDERR_LINES: usize = 500;
+
+/// Stderr lines appended to an `initialize` failure.
+const SIDECAR_INIT_ERROR_LINES: usize = 20;
+
+/// Longest NDJSON line accepted from the sidecar. Longer ones are discarded
+/// (with a logged error) instead of buffered, so a runaway or unterminated
+/// line can't grow memory without bound.
+const SIDECAR_MAX_LINE_BYTES: usize = 16 * 1024 * 1024;
+
+/// Messages queued for the sidecar's writer task. When a stalled sidecar lets
+/// it fill, new requests fail at once instead of queueing without bound.
+const SIDECAR_WRITE_QUEUE: usize = 1024;
+
+/// Request ids are `epoch << REQUEST_ID_EPOCH_SHIFT | sequence`, with a new
+/// epoch for every spawned sidecar, so a late response from a previous sidecar
+/// can never match a request made to the current one. Epochs stay far below
+/// 2^21, keeping ids exact as JavaScript numbers (< 2^53).
+const REQUEST_ID_EPOCH_SHIFT: u32 = 32;
+
+/// Epoch of the most recently spawned sidecar.
+static SIDECAR_EPOCH: std::sync::atomic::AtomicU64 = std::sync::atomic::AtomicU64::new(0);
+
+fn request_id_epoch(id: u64) -> u64 {
+    id >> REQUEST_ID_EPOCH_SHIFT
+}
+
+/// The sidecar's stdin. Messages are newline-delimited JSON.
+struct SidecarStdin {
+    inner: tokio::process::ChildStdin,
+    /// A write was abandoned mid-line (timed out), so the pipe may end in a partial line.
+    torn: bool,
+}
+
+impl SidecarStdin {
+    async fn write_line(&mut self, line: &str) -> std::io::Result<()> {
+        if self.torn {
+            // End the partial line so the sidecar drops it as malformed JSON
+            // rather than merging it with this message.
+            self.inner.write_all(b"\n").await?;
+            self.torn = false;
+        }
+        self.torn = true;
+        self.inner.write_all(line.as_bytes()).await?;
+        self.inner.flush().await?;
+        self.torn = false;
+        Ok(())
+    }
+}
+
+/// Stdin writes that timed out, for `get_sidecar_health`.
+#[derive(Default)]
+struct InputStalls {
+    count: std::sync::atomic::AtomicU64,
+    last_at_ms: std::sync::atomic::AtomicU64,
+}
+
+/// Heartbeat results, for `get_sidecar_health`.
+#[derive(Default)]
+struct Heartbeat {
+    /// Round trip of the last answered ping.
+    last_latency_ms: Mutex<Option<u64>>,
+    consecutive_misses: std::sync::atomic::AtomicU32,
+}
+
+/// The sidecar's last `SIDECAR_STDERR_LINES` stderr lines, across restarts.
+#[derive(Default)]
+struct StderrTail {
+    lines: std::sync::Mutex<std::collections::VecDeque<String>>,
+}
+
+impl StderrTail {
+    fn push(&self, line: String) {
+        let mut lines = self.lines.lock().unwrap_or_else(|e| e.into_inner());
+        if lines.len() == SIDECAR_STDERR_LINES {
+            lines.pop_front();
+        }
+        lines.push_back(line);
+    }
+
+    /// The last `limit` lines, oldest first.
+    fn last(&self, limit: usize) -> Vec<String> {
+        let lines = self.lines.lock().unwrap_or_else(|e| e.into_inner());
+        lines.iter().skip(lines.len().saturating_sub(limit)).cloned().collect()
+    }
+}
+
+/// Write one NDJSON line to the sidecar, failing fast with a distinct error if it
+/// isn't accepting input.
+async fn write_to_sidecar(stdin: &mut SidecarStdin, stalls: &InputStalls, line: &str) -> Result<(), String> {
+    match tokio::time::timeout(SIDECAR_WRITE_TIMEOUT, stdin.write_line(line)).await {
+        Ok(Ok(())) => Ok(()),
+        Ok(Err(e)) => Err(format!("Failed to write to sidecar stdin: {}", e)),
+        Err(_) => {
+            use std::sync::atomic::Ordering;
+            stalls.count.fetch_add(1, Ordering::Relaxed);
+            let now_ms = std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .map(|d| d.as_millis() as u64)
+                .unwrap_or(0);
+            stalls.last_at_ms.store(now_ms, Ordering::Relaxed);
+            Err(format!(
+                "Sidecar not accepting input (stdin write blocked for {}s)",
+                SIDECAR_WRITE_TIMEOUT.as_secs()
+            ))
+        }
+    }
+}
+
+/// Response channels of requests written to the sidecar, by request id.
+type PendingRequests = Arc<Mutex<HashMap<u64, oneshot::Sender<Result<Value, SemblanceError>>>>>;
+
+/// One framed line waiting for `run_sidecar_writer`.
+struct WriteJob {
+    line: String,
+    /// Id and method of the request the line carries, if it is one.
+    request: Option<(u64, String)>,
+    queued_at: std::time::Instant,
+}
+
+/// The front of the sidecar's write queue. Everything sent to the sidecar goes
+/// through `enqueue`, so framing and the length cap live in one place, and a
+/// single `run_sidecar_writer` task owns stdin: callers never wait on each
+/// other's writes, only on the queue. Request ids are handed out under the
+/// same lock as the enqueue, so requests reach stdin in id order.
+struct SidecarWriter {
+    next_id: u64,
+    queue: tokio::sync::mpsc::Sender<WriteJob>,
+}
+
+impl SidecarWriter {
+    /// A writer for the sidecar of `epoch`, and the receiving end of its queue
+    /// to hand to `run_sidecar_writer`.
+    fn new(epoch: u64) -> (Self, tokio::sync::mpsc::Receiver<WriteJob>) {
+        let (queue, jobs) = tokio::sync::mpsc::channel(SIDECAR_WRITE_QUEUE);
+        (SidecarWriter { next_id: (epoch << REQUEST_ID_EPOCH_SHIFT) | 1, queue }, jobs)
+    }
+
+    /// Frame `message` as an NDJSON line and queue it.
+    fn enqueue(&self, message: &Value, request: Option<(u64, String)>) -> Result<(), String> {
+        let mut line = serde_json::to_string(message).map_err(|e| format!("Failed to encode sidecar message: {}", e))?;
+        if line.len() > SIDECAR_MAX_LINE_BYTES {
+            return Err(format!("Sidecar message is {} bytes (limit {} bytes)", line.len(), SIDECAR_MAX_LINE_BYTES));
+        }
+        line.push('\n');
+        let job = WriteJob { line, request, queued_at: std::time::Instant::now() };
+        self.queue.try_send(job).map_err(|e| match e {
+            tokio::sync::mpsc::error::TrySendError::Full(_) => {
+                format!("Sidecar not accepting input ({} writes queued)", SIDECAR_WRITE_QUEUE)
+            }
+            tokio::sync::mpsc::error::TrySendError::Closed(_) => "Sidecar stdin is closed".to_string(),
+        })
+    }
+
+    /// Queue a request to `method` under the next request id, and return the id.
+    fn request(&mut self, method: &str, params: Value) -> Result<u64, String> {
+        let id = self.next_id;
+        let request = serde_json::json!({
+            "id": id,
+            "method": method,
+            "params": params,
+        });
+        self.enqueue(&request, Some((id, method.to_string())))?;
+        self.next_id += 1;
+        Ok(id)
+    }
+}
+
+/// Drain `jobs` onto the sidecar's stdin, in order, until the `SidecarWriter`
+/// is replaced or dropped. A failed request write fails its pending entry;
+/// other failed writes are logged. Write times, queueing included, go to
+/// `metrics`.
+async fn run_sidecar_writer(
+    mut stdin: SidecarStdin,
+    mut jobs: tokio::sync::mpsc::Receiver<WriteJob>,
+    pending: PendingRequests,
+    stalls: Arc<InputStalls>,
+    metrics: Arc<std::sync::Mutex<bridge_metrics::BridgeMetrics>>,
+) {
+    while let Some(job) = jobs.recv().await {
+        let written = write_to_sidecar(&mut stdin, &stalls, &job.line).await;
+        let Some((id, method)) = job.request else {
+            if let Err(e) = written {
+                eprintln!("[tauri] Dropped sidecar message: {}", e);
+            }
+            continue;
+        };
+        metrics.lock().unwrap_or_else(|e| e.into_inner()).record_write(&method, job.queued_at.elapsed());
+        if let Err(e) = written {
+            eprintln!("[tauri] Failed to send sidecar request {} ({}): {}", id, method, e);
+            if let Some(sender) = pending.lock().await.remove(&id) {
+                let _ = sender.send(Err(e.into()));
+            }
+        }
+    }
+}
+
+/// Queue a request to `method` through `writer` and wait up to `timeout` for
+/// its response. The pending entry is registered before the request can be
+/// written, under the lock the stdout reader takes to resolve it; `on_queued`
+/// runs there too, with the request id. Returns the request id (0 if it was
+/// never queued) with the result.
+async fn request_sidecar(
+    writer: &std::sync::Mutex<SidecarWriter>,
+    pending: &PendingRequests,
+    method: &str,
+    params: Value,
+    timeout: std::time::Duration,
+    on_queued: impl FnOnce(u64),
+) -> (u64, Result<Value, SemblanceError>) {
+    let (tx, rx) = oneshot::channel();
+    let queued = {
+        let mut pending_map = pending.lock().await;
+        let queued = writer.lock().unwrap_or_else(|e| e.into_inner()).request(method, params);
+        if let Ok(id) = queued {
+            on_queued(id);
+            pending_map.insert(id, tx);
+        }
+        queued
+    };
+    let id = match queued {
+        Ok(id) => id,
+        Err(e) => return (0, Err(e.into())),
+    };
+
+    // Wait for the response (with timeout)
+    let result = match tokio::time::timeout(timeout, rx).await {
+        Ok(Ok(result)) => result,
+        Ok(Err(_)) => Err(SemblanceError::Protocol { detail: format!("Sidecar response channel closed ({})", method) }),
+        Err(_) => {
+            // Remove pending entry on timeout
+            pending.lock().await.remove(&id);
+            Err(SemblanceError::Timeout { method: method.to_string(), timeout })
+        }
+    };
+    (id, result)
+}
+
+/// Bytes of an oversized line kept, enough for the `{"id":N` a response starts with.
+const OVERSIZED_HEAD_BYTES: usize = 64;
+
+/// One line read by `read_capped_line`.
+#[derive(Debug, PartialEq)]
+enum SidecarLine {
+    Line(String),
+    /// Longer than the cap; all but its first `OVERSIZED_HEAD_BYTES` were discarded.
+    Oversized { len: usize, head: String },
+}
+
+/// Read the next line, like `lines()` but holding at most `max_bytes` of it:
+/// the rest of a longer line is skipped and reported as `Oversized`. A last
+/// line without a newline is returned at EOF; invalid UTF-8 is replaced.
+async fn read_capped_line<R: tokio::io::AsyncBufRead + Unpin>(
+    reader: &mut R,
+    max_bytes: usize,
+) -> std::io::Result<Option<SidecarLine>> {
+    let mut line = Vec::new();
+    let mut len = 0usize;
+    loop {
+        let available = reader.fill_buf().await?;
+        if available.is_empty() && len == 0 {
+            return Ok(None);
+        }
+        let newline = available.iter().position(|&b| b == b'\n');
+        let chunk = &available[..newline.unwrap_or(available.len())];
+        if len + chunk.len() <= max_bytes {
+            line.extend_from_slice(chunk);
+        } else {
+            // Over the cap: keep only the head.
+            let room = OVERSIZED_HEAD_BYTES.saturating_sub(line.len());
+            line.extend_from_slice(&chunk[..chunk.len().min(room)]);
+        }
+        len += chunk.len();
+        let at_end = available.is_empty();
+        let used = newline.map_or(available.len(), |i| i + 1);
+        reader.consume(used);
+        if newline.is_some() || at_end {
+            if len > max_bytes {
+                line.truncate(OVERSIZED_HEAD_BYTES);
+                return Ok(Some(SidecarLine::Oversized { len, head: String::from_utf8_lossy(&line).into_owned() }));
+            }
+            if line.last() == Some(&b'\r') {
+                line.pop();
+            }
+            return Ok(Some(SidecarLine::Line(String::from_utf8_lossy(&line).into_owned())));
+        }
+    }
+}
+
+/// Id of the response an oversized line `head` starts, if it is one. The
+/// sidecar writes responses as `{"id":N,...}`, id first; callbacks and events
+/// lead with other keys or a string id.
+fn oversized_response_id(head: &str) -> Option<u64> {
+    let rest = head.trim_start().strip_prefix('{')?.trim_start().strip_prefix("\"id\"")?;
+    let rest = rest.trim_start().strip_prefix(':')?.trim_start();
+    let digits = rest.find(|c: char| !c.is_ascii_digit()).unwrap_or(rest.len());
+    // All digits to the end of the head means the number may be cut off.
+    if digits == 0 || digits == rest.len() {
+        return None;
+    }
+    rest[..digits].parse().ok()
+}
+
+/// Read NDJSON lines from the sidecar's stdout until it closes. Responses to
+/// requests of `epoch` resolve their entry in `pending`; everything else
+/// (callbacks, events) goes to `on_message`. A response over `max_line_bytes`
+/// fails its request with a protocol error; other oversized lines and lines
+/// that aren't JSON are logged and skipped.
+async fn read_sidecar_stdout<R: tokio::io::AsyncRead + Unpin>(
+    stdout: R,
+    pending: &PendingRequests,
+    epoch: u64,
+    max_line_bytes: usize,
+    mut on_message: impl FnMut(Value),
+) {
+    let mut reader = BufReader::new(stdout);
+    loop {
+        let line = match read_capped_line(&mut reader, max_line_bytes).await {
+            Ok(Some(SidecarLine::Line(line))) => line,
+            Ok(Some(SidecarLine::Oversized { len, head })) => {
+                eprintln!("[tauri] Dropped {}-byte sidecar message (limit {} bytes)", len, max_line_bytes);
+                // Fail the request now rather than let it wait out its timeout.
+                let sender = match oversized_response_id(&head) {
+                    Some(id) if request_id_epoch(id) == epoch => pending.lock().await.remove(&id),
+                    _ => None,
+                };
+                if let Some(sender) = sender {
+                    let detail = format!("Sidecar response was {} bytes, over the {}-byte limit", len, max_line_bytes);
+                    let _ = sender.send(Err(SemblanceError::Protocol { detail }));
+                }
+                continue;
+            }
+            Ok(None) => break,
+            Err(e) => {
+                eprintln!("[tauri] Failed to read sidecar stdout: {}", e);
+                break;
+            }
+        };
+        if line.trim().is_empty() {
+            continue;
+        }
+        let msg = match serde_json::from_str::<Value>(&line) {
+            Ok(msg) => msg,
+            Err(e) => {
+                let preview: String = line.chars().take(200).collect();
+                eprintln!("[tauri] Unparseable sidecar output ({}): {}", e, preview);
+                continue;
+            }
+        };
+        let response_id = match msg.get("type").and_then(|v| v.as_str()) {
+            Some("callback") => None,
+            _ if msg.get("event").is_some() => None,
+            _ => msg.get("id").and_then(|v| v.as_u64()),
+        };
+        let Some(id) = response_id else {
+            on_message(msg);
+            continue;
+        };
+        // Response to a pending request
+        if request_id_epoch(id) != epoch {
+            eprintln!("[tauri] Ignoring response {} from sidecar epoch {} (current {})", id, request_id_epoch(id), epoch);
+            continue;
+        }
+        let mut pending_map = pending.lock().await;
+        if let Some(sender) = pending_map.remove(&id) {
+            if let Some(error) = msg.get("error").and_then(|v| v.as_str()) {
+                let _ = sender.send(Err(SemblanceError::from_sidecar(error.to_string(), msg.get("detail"))));
+            } else {
+                let result =
+                    msg.get("result").cloned().unwrap_or(Value::Null);
+                let _ = sender.send(Ok(result));
+            }
+        }
+    }
+}
+
+/// The stdout task of the sidecar of `epoch`: `read_sidecar_stdout` until the
+/// sidecar closes it, then fail every request still pending with the error
+/// `on_exit` returns. `on_exit` runs first, so the exit can be announced
+/// before callers hear of it; its other result is passed back.
+async fn serve_sidecar_stdout<R, T, F>(
+    stdout: R,
+    pending: &PendingRequests,
+    epoch: u64,
+    on_message: impl FnMut(Value),
+    on_exit: impl FnOnce() -> F,
+) -> T
+where
+    R: tokio::io::AsyncRead + Unpin,
+    F: std::future::Future<Output = (SemblanceError, T)>,
+{
+    read_sidecar_stdout(stdout, pending, epoch, SIDECAR_MAX_LINE_BYTES, on_message).await;
+    let (error, exit) = on_exit().await;
+    fail_pending(pending, error).await;
+    exit
+}
+
+/// Fail every request still waiting on the sidecar with `error`, so callers
+/// learn it exited now rather than at their timeout.
+async fn fail_pending(pending: &PendingRequests, error: SemblanceError) {
+    for (_, sender) in pending.lock().await.drain() {
+        let _ = sender.send(Err(error.clone()));
+    }
+}
+
+/// What `SidecarBridge` needs to (re)spawn the sidecar and serve its callbacks.
+struct SidecarLaunch {
+    project_root: PathBuf,
+    app_handle: tauri::AppHandle,
+    runtime: native_runtime::SharedNativeRuntime,
+    scheduler: Arc<scheduler::GenerationScheduler>,
+    replay: event_replay::SharedEventReplay,
+}
+
+/// A freshly spawned sidecar process and its stdio pipes.
+struct SidecarProcess {
+    child: Child,
+    stdin: tokio::process::ChildStdin,
+    stdout: tokio::process::ChildStdout,
+    stderr: tokio::process::ChildStderr,
+}
+
+/// Start the sidecar process.
+fn spawn_sidecar_process(project_root: &std::path::Path) -> Result<SidecarProcess, String> {
+    // Production: use bundled bridge.cjs with system node
+    // Development: use tsx to run bridge.ts from source
+    // Use the exe's parent directory — resources are placed alongside the exe
+    let exe_dir = std::env::current_exe()
+        .ok()
+        .and_then(|p| p.parent().map(|p| p.to_path_buf()))
+        .unwrap_or_else(|| project_root.to_path_buf());
+    let bundled_bridge = exe_dir.join("sidecar").join("bridge.cjs");
+    eprintln!("[tauri] Looking for bundled bridge at: {:?} exists={}", bundled_bridge, bundled_bridge.exists());
+
+    let (node_path, script_path, working_dir) = if bundled_bridge.exists() {
+        // Production mode: bundled bridge.cjs, use system node
+        let node = which_node().ok_or("Node.js not found. Install Node.js 20+ to run Semblance.")?;
+        eprintln!("[tauri] Production mode: node={:?} script={:?}", node, bundled_bridge);
+        (node, bundled_bridge, exe_dir.join("sidecar"))
+    } else {
+        // Development mode: tsx from node_modules
+        #[cfg(windows)]
+        let tsx_path = project_root.join("node_modules").join(".bin").join("tsx.cmd");
+        #[cfg(not(windows))]
+        let tsx_path = project_root.join("node_modules").join(".bin").join("tsx");
+
+        let sidecar_script = project_root
+            .join("packages")
+            .join("desktop")
+            .join("src-tauri")
+            .join("sidecar")
+            .join("bridge.ts");
+
+        if !tsx_path.exists() {
+            return Err(format!(
+                "tsx not found at {:?}. Run `pnpm add -Dw tsx` in the project root.",
+                tsx_path
+            ));
+        }
+
+        if !sidecar_script.exists() {
+            return Err(format!("Sidecar script not found at {:?}", sidecar_script));
+        }
+
+        (tsx_path, sidecar_script, project_root.to_path_buf())
+    };
+
+    let mut cmd = Command::new(&node_path);
+    cmd.arg("--max-old-space-size=4096")
+        .arg("--expose-gc")
+        .arg(&script_path)
+        .current_dir(&working_dir)
+        .stdin(std::process::Stdio::piped())
+        .stdout(std::process::Stdio::piped())
+        .stderr(std::process::Stdio::piped())
+        .kill_on_drop(true);
+
+    #[cfg(target_os = "windows")]
+    cmd.creation_flags(0x08000000); // CREATE_NO_WINDOW
+
+    let mut child = cmd.spawn()
+        .map_err(|e| format!("Failed to spawn sidecar: {}", e))?;
+
+    let stdin = child
+        .stdin
+        .take()
+        .ok_or("Failed to take sidecar stdin")?;
+    let stdout = child
+        .stdout
+        .take()
+        .ok_or("Failed to take sidecar stdout")?;
+    let stderr = child
+        .stderr
+        .take()
+        .ok_or("Failed to take sidecar stderr")?;
+
+    Ok(SidecarProcess { child, stdin, stdout, stderr })
+}
+
 /// Manages communication with the Node.js sidecar process that hosts
-/// SemblanceCore and Gateway.
+/// SemblanceCore and Gateway. Clones share the same sidecar.
+#[derive(Clone)]
 struct SidecarBridge {
-    stdin: Arc<Mutex<tokio::process::ChildStdin>>,
-    pending: Arc<Mutex<HashMap<u64, oneshot::Sender<Result<Value, String>>>>>,
-    next_id: Arc<Mutex<u64>>,
+    writer: Arc<std::sync::Mutex<SidecarWriter>>,
+    pending: PendingRequests,
+    /// The running sidecar's request id epoch; see `REQUEST_ID_EPOCH_SHIFT`.
+    epoch: Arc<std::sync::atomic::AtomicU64>,
     child: Arc<Mutex<Child>>,
+    input_stalls: Arc<InputStalls>,
+    heartbeat: Arc<Heartbeat>,
+    stderr_tail: Arc<StderrTail>,
+    launch: Arc<SidecarLaunch>,
+    restart_policy: Arc<Mutex<supervisor::RestartPolicy>>,
+    /// Set by `shutdown`, so the sidecar's exit isn't treated as a crash.
+    shutting_down: Arc<std::sync::atomic::AtomicBool>,
+    /// Latest epoch whose `sidecar-ready` or `sidecar-failed` went out; see `announce`.
+    announced_epoch: Arc<std::sync::atomic::AtomicU64>,
+    chat_streams: Arc<std::sync::Mutex<chat_stream::ChatStreams>>,
+    /// In-progress `call_cancellable` work.
+    cancellable: Arc<std::sync::Mutex<CancellableRequests>>,
+    /// Cancel flags of running native callbacks by sidecar epoch and callback
+    /// id. Set by the sidecar's `callback_cancel`, or when it exits.
+    native_callbacks: Arc<std::sync::Mutex<HashMap<(u64, String), Arc<std::sync::atomic::AtomicBool>>>>,
+    /// Phase of each running `test_credential` by credential id, from its
+    /// `credential-test-progress` events.
+    credential_tests: Arc<std::sync::Mutex<HashMap<String, String>>>,
+    metrics: Arc<std::sync::Mutex<bridge_metrics::BridgeMetrics>>,
+}
+
+/// A `call_cancellable` request, from before it is sent until the event
+/// announcing the end of its background work.
+struct CancellableRequest {
+    method: String,
+    /// The `responseId` it answered with; None until the response arrives.
+    response_id: Option<String>,
+}
+
+/// Most end events kept for requests whose response hasn't been matched yet.
+const MAX_EARLY_FINISHES: usize = 32;
+
+/// `call_cancellable` work by request id. A request is registered before it
+/// is sent, so an end event that arrives before its caller has seen the
+/// response (the reader handles both before the caller wakes) isn't lost.
+#[derive(Default)]
+struct CancellableRequests {
+    requests: HashMap<u64, CancellableRequest>,
+    /// `responseId`s whose end event came while some request was still
+    /// waiting for its response, oldest first.
+    finished_early: std::collections::VecDeque<String>,
+}
+
+impl CancellableRequests {
+    fn register(&mut self, request_id: u64, method: &str) {
+        self.requests.insert(request_id, CancellableRequest { method: method.to_string(), response_id: None });
+    }
+
+    /// Record the response of `request_id`. Returns whether its work is still
+    /// running, i.e. it answered with a `responseId` whose end hasn't arrived.
+    fn answered(&mut self, request_id: u64, response_id: Option<&str>) -> bool {
+        let (Some(mut request), Some(response_id)) = (self.requests.remove(&request_id), response_id) else {
+            return false;
+        };
+        if let Some(i) = self.finished_early.iter().position(|f| f == response_id) {
+            self.finished_early.remove(i);
+            return false;
+        }
+        request.response_id = Some(response_id.to_string());
+        self.requests.insert(request_id, request);
+        true
+    }
+
+    /// The work behind `response_id` ended.
+    fn finish(&mut self, response_id: &str) {
+        let request_id = self.requests.iter().find(|(_, r)| r.response_id.as_deref() == Some(response_id)).map(|(id, _)| *id);
+        match request_id {
+            Some(id) => {
+                self.requests.remove(&id);
+            }
+            None if self.requests.values().any(|r| r.response_id.is_none()) => {
+                if self.finished_early.len() == MAX_EARLY_FINISHES {
+                    self.finished_early.pop_front();
+                }
+                self.finished_early.push_back(response_id.to_string());
+            }
+            None => {}
+        }
+    }
+
+    /// Remove the running work behind `response_id`, with its request id.
+    fn take(&mut self, response_id: &str) -> Option<(u64, CancellableRequest)> {
+        let request_id = *self.requests.iter().find(|(_, r)| r.response_id.as_deref() == Some(response_id))?.0;
+        self.requests.remove(&request_id).map(|request| (request_id, request))
+    }
+
+    fn clear(&mut self) {
+        self.requests.clear();
+        self.finished_early.clear();
+    }
 }
 
 impl SidecarBridge {
     /// Spawn the sidecar process and start reading its stdout.
-    /// Events from the sidecar are forwarded as Tauri events to the frontend.
-    async fn spawn(project_root: PathBuf, app_handle: tauri::AppHandle, runtime: native_runtime::SharedNativeRuntime) -> Result<Self, String> {
-        // Production: use bundled bridge.cjs with system node
-        // Development: use tsx to run bridge.ts from source
-        // Use the exe's parent directory — resources are placed alongside the exe
-        let exe_dir = std::env::current_exe()
-            .ok()
-            .and_then(|p| p.parent().map(|p| p.to_path_buf()))
-            .unwrap_or_else(|| project_root.clone());
-        let bundled_bridge = exe_dir.join("sidecar").join("bridge.cjs");
-        eprintln!("[tauri] Looking for bundled bridge at: {:?} exists={}", bundled_bridge, bundled_bridge.exists());
-
-        let (node_path, script_path, working_dir) = if bundled_bridge.exists() {
-            // Production mode: bundled bridge.cjs, use system node
-            let node = which_node().ok_or("Node.js not found. Install Node.js 20+ to run Semblance.")?;
-            eprintln!("[tauri] Production mode: node={:?} script={:?}", node, bundled_bridge);
-            (node, bundled_bridge, exe_dir.join("sidecar"))
-        } else {
-            // Development mode: tsx from node_modules
-            #[cfg(windows)]
-            let tsx_path = project_root.join("node_modules").join(".bin").join("tsx.cmd");
-            #[cfg(not(windows))]
-            let tsx_path = project_root.join("node_modules").join(".bin").join("tsx");
+    /// Events from the sidecar are forwarded as Tauri events to the frontend
+    /// (recorded in `replay` for late-opening windows). If the sidecar later
+    /// exits unexpectedly it is respawned; see `supervisor`.
+    async fn spawn(project_root: PathBuf, app_handle: tauri::AppHandle, runtime: native_runtime::SharedNativeRuntime, scheduler: Arc<scheduler::GenerationScheduler>, replay: event_replay::SharedEventReplay) -> Result<Self, String> {
+        let spawned_at = std::time::Instant::now();
+        let process = spawn_sidecar_process(&project_root)?;
+
+        let epoch = SIDECAR_EPOCH.fetch_add(1, std::sync::atomic::Ordering::SeqCst) + 1;
+        let (writer, jobs) = SidecarWriter::new(epoch);
+        let bridge = SidecarBridge {
+            writer: Arc::new(std::sync::Mutex::new(writer)),
+            pending: Arc::new(Mutex::new(HashMap::new())),
+            epoch: Arc::new(std::sync::atomic::AtomicU64::new(epoch)),
+            child: Arc::new(Mutex::new(process.child)),
+            input_stalls: Arc::new(InputStalls::default()),
+            heartbeat: Arc::new(Heartbeat::default()),
+            stderr_tail: Arc::new(StderrTail::default()),
+            launch: Arc::new(SidecarLaunch { project_root, app_handle, runtime, scheduler, replay }),
+            restart_policy: Arc::new(Mutex::new(supervisor::RestartPolicy::new(spawned_at))),
+            shutting_down: Arc::new(std::sync::atomic::AtomicBool::new(false)),
+            announced_epoch: Arc::new(std::sync::atomic::AtomicU64::new(0)),
+            chat_streams: Arc::new(std::sync::Mutex::new(chat_stream::ChatStreams::default())),
+            cancellable: Arc::new(std::sync::Mutex::new(CancellableRequests::default())),
+            native_callbacks: Arc::new(std::sync::Mutex::new(HashMap::new())),
+            credential_tests: Arc::new(std::sync::Mutex::new(HashMap::new())),
+            metrics: Arc::new(std::sync::Mutex::new(bridge_metrics::BridgeMetrics::default())),
+        };
+        bridge.start_writer(process.stdin, jobs);
+        bridge.attach(process.stdout, process.stderr, epoch);
+        bridge.start_heartbeat();
 
-            let sidecar_script = project_root
-                .join("packages")
-                .join("desktop")
-                .join("src-tauri")
-                .join("sidecar")
-                .join("bridge.ts");
-
-            if !tsx_path.exists() {
-                return Err(format!(
-                    "tsx not found at {:?}. Run `pnpm add -Dw tsx` in the project root.",
-                    tsx_path
-                ));
-            }
+        Ok(bridge)
+    }
 
-            if !sidecar_script.exists() {
-                return Err(format!("Sidecar script not found at {:?}", sidecar_script));
+    /// Start the task that writes queued messages to `stdin`.
+    fn start_writer(&self, stdin: tokio::process::ChildStdin, jobs: tokio::sync::mpsc::Receiver<WriteJob>) {
+        tauri::async_runtime::spawn(run_sidecar_writer(
+            SidecarStdin { inner: stdin, torn: false },
+            jobs,
+            self.pending.clone(),
+            self.input_stalls.clone(),
+            self.metrics.clone(),
+        ));
+    }
+
+    /// Validate a `chat-token` event against `ChatToken` and drop tokens and
+    /// completions of cancelled or finished streams, and drop malformed
+    /// `statement-import-progress` events. Other events pass through.
+    fn check_event(&self, event_name: &str, data: Value) -> Option<Value> {
+        let mut streams = self.chat_streams.lock().unwrap_or_else(|e| e.into_inner());
+        match event_name {
+            "chat-token" => {
+                let token = match serde_json::from_value::<chat_stream::ChatToken>(data) {
+                    Ok(token) => token,
+                    Err(e) => {
+                        eprintln!("[tauri] Dropped malformed chat-token event: {}", e);
+                        return None;
+                    }
+                };
+                if !streams.accept(&token) {
+                    return None;
+                }
+                serde_json::to_value(token).ok()
+            }
+            "chat-complete" => {
+                let response_id = data.get("id").and_then(|v| v.as_str()).unwrap_or("");
+                streams.accept_complete(response_id).then_some(data)
             }
+            "statement-import-progress" => match serde_json::from_value::<finance::StatementImportProgress>(data) {
+                Ok(progress) => serde_json::to_value(progress).ok(),
+                Err(e) => {
+                    eprintln!("[tauri] Dropped malformed statement-import-progress event: {}", e);
+                    None
+                }
+            },
+            _ => Some(data),
+        }
+    }
 
-            (tsx_path, sidecar_script, project_root.clone())
+    /// Forget a cancellable request once the event announcing its end arrives.
+    fn finish_cancellable(&self, event_name: &str, data: &Value) {
+        let key = match event_name {
+            "chat-complete" => "id",
+            "email-index-complete" => "responseId",
+            _ => return,
         };
+        if let Some(response_id) = data.get(key).and_then(|v| v.as_str()) {
+            self.cancellable.lock().unwrap_or_else(|e| e.into_inner()).finish(response_id);
+        }
+    }
 
-        let mut cmd = Command::new(&node_path);
-        cmd.arg("--max-old-space-size=4096")
-            .arg("--expose-gc")
-            .arg(&script_path)
-            .current_dir(&working_dir)
-            .stdin(std::process::Stdio::piped())
-            .stdout(std::process::Stdio::piped())
-            .stderr(std::process::Stdio::piped())
-            .kill_on_drop(true);
-
-        #[cfg(target_os = "windows")]
-        cmd.creation_flags(0x08000000); // CREATE_NO_WINDOW
-
-        let mut child = cmd.spawn()
-            .map_err(|e| format!("Failed to spawn sidecar: {}", e))?;
-
-        let stdin = child
-            .stdin
-            .take()
-            .ok_or("Failed to take sidecar stdin")?;
-        let stdout = child
-            .stdout
-            .take()
-            .ok_or("Failed to take sidecar stdout")?;
-        let stderr = child
-            .stderr
-            .take()
-            .ok_or("Failed to take sidecar stderr")?;
-
-        let pending: Arc<Mutex<HashMap<u64, oneshot::Sender<Result<Value, String>>>>> =
-            Arc::new(Mutex::new(HashMap::new()));
-
-        let bridge = SidecarBridge {
-            stdin: Arc::new(Mutex::new(stdin)),
-            pending: pending.clone(),
-            next_id: Arc::new(Mutex::new(1)),
-            child: Arc::new(Mutex::new(child)),
+    /// Record the phase a running credential test reports.
+    fn track_credential_test(&self, event_name: &str, data: &Value) {
+        if event_name != "credential-test-progress" {
+            return;
+        }
+        let (Some(id), Some(phase)) = (data.get("id").and_then(|v| v.as_str()), data.get("phase").and_then(|v| v.as_str())) else {
+            return;
         };
+        if let Some(current) = self.credential_tests.lock().unwrap_or_else(|e| e.into_inner()).get_mut(id) {
+            *current = phase.to_string();
+        }
+    }
 
+    /// Start the stdout and stderr readers for the sidecar process of `epoch`.
+    fn attach(&self, stdout: tokio::process::ChildStdout, stderr: tokio::process::ChildStderr, epoch: u64) {
         // Background task: read stdout lines from sidecar, dispatch events, responses, and callbacks
-        let pending_for_stdout = pending.clone();
-        let app_for_stdout = app_handle.clone();
-        let stdin_for_callbacks = bridge.stdin.clone();
-        let runtime_for_callbacks = runtime.clone();
+        let bridge = self.clone();
         tauri::async_runtime::spawn(async move {
-            let reader = BufReader::new(stdout);
-            let mut lines = reader.lines();
-            while let Ok(Some(line)) = lines.next_line().await {
-                if let Ok(msg) = serde_json::from_str::<Value>(&line) {
-                    // Step 9: NDJSON callback requests from sidecar → Rust NativeRuntime
-                    if msg.get("type").and_then(|v| v.as_str()) == Some("callback") {
-                        let callback_id = msg.get("id").and_then(|v| v.as_str()).unwrap_or("").to_string();
-                        let method = msg.get("method").and_then(|v| v.as_str()).unwrap_or("").to_string();
-                        let params = msg.get("params").cloned().unwrap_or(Value::Null);
-
-                        // Dispatch callback to NativeRuntime in background
-                        let stdin_ref = stdin_for_callbacks.clone();
-                        let runtime_ref = runtime_for_callbacks.clone();
-                        tauri::async_runtime::spawn(async move {
-                            let response = dispatch_native_callback(runtime_ref, &method, params).await;
-                            let response_msg = match response {
-                                Ok(result) => serde_json::json!({
-                                    "type": "callback_response",
-                                    "id": callback_id,
-                                    "result": result,
-                                }),
-                                Err(error) => serde_json::json!({
-                                    "type": "callback_response",
-                                    "id": callback_id,
-                                    "error": error,
-                                }),
-                            };
-
-                            let line = format!("{}\n", serde_json::to_string(&response_msg).unwrap());
-                            let mut stdin = stdin_ref.lock().await;
-                            let _ = stdin.write_all(line.as_bytes()).await;
-                            let _ = stdin.flush().await;
-                        });
-                    } else if let Some(event_name) = msg.get("event").and_then(|v| v.as_str()) {
-                        // Forward sidecar event as Tauri event
-                        let data = msg.get("data").cloned().unwrap_or(Value::Null);
-                        let full_event = format!("semblance://{}", event_name);
-                        let _ = app_for_stdout.emit(&full_event, &data);
-                    } else if let Some(id) = msg.get("id").and_then(|v| v.as_u64()) {
-                        // Response to a pending request
-                        let mut pending_map = pending_for_stdout.lock().await;
-                        if let Some(sender) = pending_map.remove(&id) {
-                            if let Some(error) = msg.get("error").and_then(|v| v.as_str()) {
-                                let _ = sender.send(Err(error.to_string()));
-                            } else {
-                                let result =
-                                    msg.get("result").cloned().unwrap_or(Value::Null);
-                                let _ = sender.send(Ok(result));
-                            }
+            let launch = bridge.launch.clone();
+            let (bridge_ref, launch_ref) = (&bridge, &launch);
+            // stdout closed — sidecar died. Fail in-flight requests now rather
+            // than leaving them to time out, then respawn it unless we're
+            // shutting down or the restart policy has given up.
+            let on_exit = || async move {
+                let crashed = !bridge_ref.shutting_down.load(std::sync::atomic::Ordering::SeqCst)
+                    && bridge_ref.epoch.load(std::sync::atomic::Ordering::SeqCst) == epoch;
+                let restart_in = if crashed {
+                    bridge_ref.restart_policy.lock().await.on_exit(std::time::Instant::now())
+                } else {
+                    None
+                };
+                // Before failing `initialize`: a sidecar that dies starting up failed by exiting.
+                if crashed && bridge_ref.announce(epoch) {
+                    emit_sidecar_failed(
+                        &launch_ref.app_handle,
+                        &launch_ref.replay,
+                        &SidecarFailed {
+                            epoch: Some(epoch),
+                            stage: SidecarFailureStage::Exited,
+                            reason: "Sidecar process exited unexpectedly".to_string(),
+                            restarting: restart_in.is_some(),
+                        },
+                    );
+                }
+                (SemblanceError::SidecarCrashed { restarting: restart_in.is_some() }, (crashed, restart_in))
+            };
+            let (crashed, restart_in) = serve_sidecar_stdout(stdout, &bridge.pending, epoch, |msg| {
+                // Step 9: NDJSON callback requests from sidecar → Rust NativeRuntime
+                if msg.get("type").and_then(|v| v.as_str()) == Some("callback") {
+                    let callback_id = msg.get("id").and_then(|v| v.as_str()).unwrap_or("").to_string();
+                    let method = msg.get("method").and_then(|v| v.as_str()).unwrap_or("").to_string();
+                    let params = msg.get("params").cloned().unwrap_or(Value::Null);
+                    let cancel = Arc::new(std::sync::atomic::AtomicBool::new(false));
+                    let key = (epoch, callback_id.clone());
+                    bridge.native_callbacks.lock().unwrap_or_else(|e| e.into_inner()).insert(key.clone(), cancel.clone());
+
+                    // Dispatch callback to NativeRuntime in background
+                    let bridge_ref = bridge.clone();
+                    tauri::async_runtime::spawn(async move {
+                        let launch = &bridge_ref.launch;
+                        let response = dispatch_native_callback(&launch.app_handle, launch.runtime.clone(), &launch.scheduler, &method, params, cancel).await;
+                        bridge_ref.native_callbacks.lock().unwrap_or_else(|e| e.into_inner()).remove(&key);
+                        let response_msg = match response {
+                            Ok(result) => serde_json::json!({
+                                "type": "callback_response",
+                                "id": callback_id,
+                                "result": result,
+                            }),
+                            // `detail` lets the sidecar hand a runtime error's kind back to `from_sidecar`.
+                            Err(error) => serde_json::json!({
+                                "type": "callback_response",
+                                "id": callback_id,
+                                "error": error.to_string(),
+                                "detail": error,
+                            }),
+                        };
+
+                        // The sidecar that asked may have been replaced since.
+                        if bridge_ref.epoch.load(std::sync::atomic::Ordering::SeqCst) != epoch {
+                            eprintln!("[tauri] Dropped callback response {}: sidecar epoch {} restarted", callback_id, epoch);
+                            return;
+                        }
+                        let queued = bridge_ref.writer.lock().unwrap_or_else(|e| e.into_inner()).enqueue(&response_msg, None);
+                        if let Err(e) = queued {
+                            eprintln!("[tauri] Dropped callback response {}: {}", callback_id, e);
                         }
+                    });
+                } else if msg.get("type").and_then(|v| v.as_str()) == Some("callback_cancel") {
+                    // The sidecar stopped waiting for a callback (it timed out);
+                    // a generation behind it stops at its next token.
+                    let callback_id = msg.get("id").and_then(|v| v.as_str()).unwrap_or("").to_string();
+                    if let Some(cancel) = bridge.native_callbacks.lock().unwrap_or_else(|e| e.into_inner()).get(&(epoch, callback_id)) {
+                        cancel.store(true, std::sync::atomic::Ordering::Relaxed);
+                    }
+                } else if let Some(event_name) = msg.get("event").and_then(|v| v.as_str()) {
+                    // Forward sidecar event as Tauri event
+                    let data = msg.get("data").cloned().unwrap_or(Value::Null);
+                    bridge.finish_cancellable(event_name, &data);
+                    bridge.track_credential_test(event_name, &data);
+                    if let Some(data) = bridge.check_event(event_name, data) {
+                        event_replay::emit(&launch.app_handle, &launch.replay, event_name, &data);
                     }
                 }
-            }
-            // stdout closed — sidecar died
-            let _ = app_for_stdout.emit(
-                "semblance://status-update",
-                serde_json::json!({"ollamaStatus": "disconnected", "gatewayStatus": "disconnected", "error": "Sidecar process exited unexpectedly"}),
+            }, on_exit)
+            .await;
+            // Background work died with the sidecar; nothing left to cancel.
+            bridge.cancellable.lock().unwrap_or_else(|e| e.into_inner()).clear();
+            // Nobody is waiting for its callbacks any more either.
+            bridge.native_callbacks.lock().unwrap_or_else(|e| e.into_inner()).retain(|(callback_epoch, _), cancel| {
+                if *callback_epoch == epoch {
+                    cancel.store(true, std::sync::atomic::Ordering::Relaxed);
+                }
+                *callback_epoch != epoch
+            });
+            event_replay::emit(
+                &launch.app_handle,
+                &launch.replay,
+                "status-update",
+                &serde_json::json!({"ollamaStatus": "disconnected", "gatewayStatus": "disconnected", "error": "Sidecar process exited unexpectedly"}),
             );
+            match restart_in {
+                Some(delay) => bridge.restart(delay).await,
+                None if crashed => bridge.emit_restart_abandoned(),
+                None => {}
+            }
         });
 
         // Background task: read stderr from sidecar (logging + file)
-        let log_dir = {
-            let home = std::env::var("USERPROFILE")
-                .or_else(|_| std::env::var("HOME"))
-                .unwrap_or_else(|_| ".".to_string());
-            PathBuf::from(home).join(".semblance").join("data")
-        };
+        let log_dir = storage::data_dir();
         let _ = std::fs::create_dir_all(&log_dir);
         let log_path = log_dir.join("sidecar.log");
+        let tail = self.stderr_tail.clone();
 
         tauri::async_runtime::spawn(async move {
             use std::io::Write;
@@ -324,9 +1121,10 @@ impl SidecarBridge {
                     .duration_since(std::time::UNIX_EPOCH)
                     .map(|d| d.as_secs())
                     .unwrap_or(0);
-                let _ = writeln!(f, "\n=== SESSION START unix={} ===", ts);
+                let _ = writeln!(f, "\n=== SESSION START unix={} epoch={} ===", ts, epoch);
                 let _ = f.flush();
             }
+            tail.push(format!("=== SESSION START epoch={} ===", epoch));
 
             let reader = BufReader::new(stderr);
             let mut lines = reader.lines();
@@ -336,112 +1134,245 @@ impl SidecarBridge {
                     let _ = writeln!(f, "{}", line);
                     let _ = f.flush();
                 }
+                tail.push(line);
             }
         });
+    }
 
-        Ok(bridge)
+    /// Ping the sidecar every `SIDECAR_PING_INTERVAL` for the bridge's lifetime.
+    /// A hung sidecar (event loop blocked) never closes stdout, so only this
+    /// notices it: after `SIDECAR_PING_MAX_MISSES` misses in a row, emit
+    /// `semblance://sidecar-unresponsive` and, with the
+    /// `restart_unresponsive_sidecar` setting, kill it so it is respawned.
+    fn start_heartbeat(&self) {
+        let bridge = self.clone();
+        tauri::async_runtime::spawn(async move {
+            use std::sync::atomic::Ordering;
+            let mut interval = tokio::time::interval_at(
+                tokio::time::Instant::now() + SIDECAR_PING_INTERVAL,
+                SIDECAR_PING_INTERVAL,
+            );
+            interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+            let mut last_epoch = bridge.epoch.load(Ordering::SeqCst);
+            loop {
+                interval.tick().await;
+                if bridge.shutting_down.load(Ordering::SeqCst) {
+                    return;
+                }
+                // Misses are counted per sidecar: a respawned one starts from zero.
+                let epoch = bridge.epoch.load(Ordering::SeqCst);
+                if epoch != last_epoch {
+                    bridge.heartbeat.consecutive_misses.store(0, Ordering::SeqCst);
+                    last_epoch = epoch;
+                }
+                let sent = std::time::Instant::now();
+                match bridge.call_with_timeout("ping", Value::Null, SIDECAR_PING_TIMEOUT).await {
+                    Ok(_) => {
+                        *bridge.heartbeat.last_latency_ms.lock().await = Some(sent.elapsed().as_millis() as u64);
+                        bridge.heartbeat.consecutive_misses.store(0, Ordering::SeqCst);
+                    }
+                    // Sent to a sidecar that has since been replaced; not the new one's miss.
+                    Err(_) if bridge.epoch.load(Ordering::SeqCst) != epoch => {}
+                    Err(e) => {
+                        let misses = bridge.heartbeat.consecutive_misses.fetch_add(1, Ordering::SeqCst) + 1;
+                        eprintln!("[tauri] Sidecar missed heartbeat {} in a row: {}", misses, e);
+                        if misses != SIDECAR_PING_MAX_MISSES {
+                            continue;
+                        }
+                        let launch = &bridge.launch;
+                        let restart = launch.app_handle.state::<settings::SettingsState>().get().await.restart_unresponsive_sidecar;
+                        let _ = launch.app_handle.emit(
+                            "semblance://sidecar-unresponsive",
+                            serde_json::json!({
+                                "epoch": bridge.epoch.load(Ordering::SeqCst),
+                                "missed_pings": misses,
+                                "restarting": restart,
+                            }),
+                        );
+                        if restart {
+                            // Closes its stdout, so the crash path respawns it.
+                            eprintln!("[tauri] Killing unresponsive sidecar");
+                            let _ = bridge.child.lock().await.start_kill();
+                        }
+                    }
+                }
+            }
+        });
     }
 
-    /// Send a JSON-RPC request to the sidecar and wait for the response.
-    async fn call(&self, method: &str, params: Value) -> Result<Value, String> {
-        let id = {
-            let mut next = self.next_id.lock().await;
-            let id = *next;
-            *next += 1;
-            id
+    /// Respawn the sidecar after it crashed: wait `delay`, spawn, and retry
+    /// with backoff while spawning fails. Once one is running, re-run
+    /// `initialize` and emit `semblance://sidecar-restarted`.
+    async fn restart(&self, mut delay: std::time::Duration) {
+        use std::sync::atomic::Ordering;
+        let launch = &self.launch;
+        let process = loop {
+            eprintln!("[tauri] Sidecar exited unexpectedly; restarting in {}ms", delay.as_millis());
+            tokio::time::sleep(delay).await;
+            if self.shutting_down.load(Ordering::SeqCst) {
+                return;
+            }
+            self.restart_policy.lock().await.on_spawn(std::time::Instant::now());
+            match spawn_sidecar_process(&launch.project_root) {
+                Ok(process) => {
+                    self.restart_policy.lock().await.on_spawned();
+                    break process;
+                }
+                Err(e) => {
+                    eprintln!("[tauri] Sidecar restart failed: {}", e);
+                    let next = self.restart_policy.lock().await.on_exit(std::time::Instant::now());
+                    emit_sidecar_failed(
+                        &launch.app_handle,
+                        &launch.replay,
+                        &SidecarFailed {
+                            epoch: None,
+                            stage: SidecarFailureStage::Spawn,
+                            reason: e,
+                            restarting: next.is_some(),
+                        },
+                    );
+                    match next {
+                        Some(next) => delay = next,
+                        None => return self.emit_restart_abandoned(),
+                    }
+                }
+            }
         };
 
-        // Register a response channel
-        let (tx, rx) = oneshot::channel();
-        {
-            let mut pending = self.pending.lock().await;
-            pending.insert(id, tx);
-        }
+        // New epoch first, so requests made from here on get ids the new
+        // sidecar's reader accepts.
+        let epoch = SIDECAR_EPOCH.fetch_add(1, Ordering::SeqCst) + 1;
+        // Dropping the old writer ends its task once the queue drains.
+        let (writer, jobs) = SidecarWriter::new(epoch);
+        *self.writer.lock().unwrap_or_else(|e| e.into_inner()) = writer;
+        self.epoch.store(epoch, Ordering::SeqCst);
+        self.start_writer(process.stdin, jobs);
+        // Replacing the old child drops (and kills) it.
+        *self.child.lock().await = process.child;
+        self.heartbeat.consecutive_misses.store(0, Ordering::SeqCst);
+        self.attach(process.stdout, process.stderr, epoch);
+
+        let attempt = self.restart_policy.lock().await.attempts();
+        eprintln!("[tauri] Sidecar respawned (epoch {}, attempt {})", epoch, attempt);
+        let initialized = initialize_sidecar(&launch.app_handle, self, &launch.replay).await;
+        let _ = launch.app_handle.emit(
+            "semblance://sidecar-restarted",
+            serde_json::json!({ "epoch": epoch, "attempt": attempt, "initialized": initialized }),
+        );
+    }
 
-        // Write the request to stdin
-        let request = serde_json::json!({
-            "id": id,
-            "method": method,
-            "params": params,
-        });
+    /// Claim the one `sidecar-ready` / `sidecar-failed` announcement of the
+    /// sidecar of `epoch`. False if it was already made.
+    fn announce(&self, epoch: u64) -> bool {
+        self.announced_epoch.fetch_max(epoch, std::sync::atomic::Ordering::SeqCst) < epoch
+    }
 
-        {
-            let mut stdin = self.stdin.lock().await;
-            let line = format!("{}\n", serde_json::to_string(&request).unwrap());
-            stdin
-                .write_all(line.as_bytes())
-                .await
-                .map_err(|e| format!("Failed to write to sidecar stdin: {}", e))?;
-            stdin
-                .flush()
-                .await
-                .map_err(|e| format!("Failed to flush sidecar stdin: {}", e))?;
-        }
+    /// Tell the UI the sidecar is down for good, after `supervisor::MAX_RESTARTS` crashes.
+    fn emit_restart_abandoned(&self) {
+        eprintln!("[tauri] Sidecar crashed {} times in a row; not restarting", supervisor::MAX_RESTARTS);
+        event_replay::emit(
+            &self.launch.app_handle,
+            &self.launch.replay,
+            "status-update",
+            &serde_json::json!({
+                "ollamaStatus": "disconnected",
+                "gatewayStatus": "disconnected",
+                "error": format!("Sidecar crashed {} times in a row and was not restarted. Check sidecar.log.", supervisor::MAX_RESTARTS)
+            }),
+        );
+    }
 
-        // Wait for the response (with timeout)
-        match tokio::time::timeout(std::time::Duration::from_secs(120), rx).await {
-            Ok(Ok(result)) => result,
-            Ok(Err(_)) => Err("Sidecar response channel closed".to_string()),
-            Err(_) => {
-                // Remove pending entry on timeout
-                let mut pending = self.pending.lock().await;
-                pending.remove(&id);
-                Err("Sidecar request timed out (120s)".to_string())
-            }
-        }
+    /// Send a JSON-RPC request to the sidecar and wait for the response, for up
+    /// to `SIDECAR_CALL_TIMEOUT`.
+    async fn call(&self, method: &str, params: Value) -> Result<Value, SemblanceError> {
+        self.call_with_timeout(method, params, SIDECAR_CALL_TIMEOUT).await
     }
 
     /// Send a fire-and-forget request that also registers for a response.
     /// Used for send_message and start_indexing which respond immediately
     /// and then emit events asynchronously.
-    async fn call_fire(&self, method: &str, params: Value) -> Result<Value, String> {
-        // Same as call() but with a shorter timeout since these return quickly
-        let id = {
-            let mut next = self.next_id.lock().await;
-            let id = *next;
-            *next += 1;
-            id
+    async fn call_fire(&self, method: &str, params: Value) -> Result<Value, SemblanceError> {
+        self.call_with_timeout(method, params, SIDECAR_FIRE_TIMEOUT).await
+    }
+
+    /// Like `call_fire`, for requests whose background work can be aborted
+    /// with `cancel_request`. The response must carry a `responseId`, and the
+    /// work must end with an event `finish_cancellable` recognizes.
+    async fn call_cancellable(&self, method: &str, params: Value) -> Result<Value, SemblanceError> {
+        let register = |request_id| self.cancellable.lock().unwrap_or_else(|e| e.into_inner()).register(request_id, method);
+        let (request_id, result) = self.send_request_with(method, params, SIDECAR_FIRE_TIMEOUT, register).await;
+        let response_id = result.as_ref().ok().and_then(|r| r.get("responseId")).and_then(|v| v.as_str());
+        self.cancellable.lock().unwrap_or_else(|e| e.into_inner()).answered(request_id, response_id);
+        result
+    }
+
+    /// Abort the work behind `response_id`: send the sidecar a `cancel` frame
+    /// for the original request, drop any further chat tokens, and emit `semblance://request-cancelled`. Returns false (and
+    /// does nothing) if the id is unknown or its work already finished.
+    async fn cancel_request(&self, response_id: &str) -> Result<bool, SemblanceError> {
+        let Some((request_id, request)) = self.cancellable.lock().unwrap_or_else(|e| e.into_inner()).take(response_id) else {
+            return Ok(false);
         };
+        self.chat_streams.lock().unwrap_or_else(|e| e.into_inner()).cancel(response_id);
+        let frame = serde_json::json!({ "type": "cancel", "id": request_id });
+        self.writer.lock().unwrap_or_else(|e| e.into_inner()).enqueue(&frame, None)?;
+        let _ = self.launch.app_handle.emit(
+            "semblance://request-cancelled",
+            serde_json::json!({ "responseId": response_id, "method": request.method }),
+        );
+        Ok(true)
+    }
 
-        let (tx, rx) = oneshot::channel();
-        {
-            let mut pending = self.pending.lock().await;
-            pending.insert(id, tx);
-        }
+    /// Send a JSON-RPC request to the sidecar and wait up to `timeout` for the
+    /// response.
+    async fn call_with_timeout(&self, method: &str, params: Value, timeout: std::time::Duration) -> Result<Value, SemblanceError> {
+        self.send_request(method, params, timeout).await.1
+    }
 
-        let request = serde_json::json!({
-            "id": id,
-            "method": method,
-            "params": params,
-        });
+    /// Send a request and wait for its response, recording the latency in
+    /// `metrics` from before it is queued. Returns the request id with the
+    /// result; see `request_sidecar`.
+    async fn send_request(&self, method: &str, params: Value, timeout: std::time::Duration) -> (u64, Result<Value, SemblanceError>) {
+        self.send_request_with(method, params, timeout, |_| {}).await
+    }
 
-        {
-            let mut stdin = self.stdin.lock().await;
-            let line = format!("{}\n", serde_json::to_string(&request).unwrap());
-            stdin
-                .write_all(line.as_bytes())
-                .await
-                .map_err(|e| format!("Failed to write to sidecar stdin: {}", e))?;
-            stdin
-                .flush()
-                .await
-                .map_err(|e| format!("Failed to flush sidecar stdin: {}", e))?;
-        }
+    /// `send_request`, calling `on_queued` with the request id before the
+    /// request can be written.
+    async fn send_request_with(
+        &self,
+        method: &str,
+        params: Value,
+        timeout: std::time::Duration,
+        on_queued: impl FnOnce(u64),
+    ) -> (u64, Result<Value, SemblanceError>) {
+        let started = std::time::Instant::now();
+        let (id, result) = request_sidecar(&self.writer, &self.pending, method, params, timeout, on_queued).await;
+        self.metrics
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .record(method, started.elapsed(), result.is_ok());
+        (id, result)
+    }
 
-        // Short timeout for the initial response
-        match tokio::time::timeout(std::time::Duration::from_secs(10), rx).await {
-            Ok(Ok(result)) => result,
-            Ok(Err(_)) => Err("Sidecar response channel closed".to_string()),
-            Err(_) => {
-                let mut pending = self.pending.lock().await;
-                pending.remove(&id);
-                Err("Sidecar initial response timed out".to_string())
-            }
+    async fn health(&self) -> SidecarHealth {
+        use std::sync::atomic::Ordering;
+        let last = self.input_stalls.last_at_ms.load(Ordering::Relaxed);
+        let next_request_id = self.writer.lock().unwrap_or_else(|e| e.into_inner()).next_id;
+        SidecarHealth {
+            epoch: self.epoch.load(Ordering::SeqCst),
+            restarts: self.restart_policy.lock().await.total_restarts(),
+            next_request_id,
+            pending_requests: self.pending.lock().await.len(),
+            input_stalls: self.input_stalls.count.load(Ordering::Relaxed),
+            last_input_stall_at_ms: (last > 0).then_some(last),
+            last_ping_latency_ms: *self.heartbeat.last_latency_ms.lock().await,
+            consecutive_ping_misses: self.heartbeat.consecutive_misses.load(Ordering::SeqCst),
         }
     }
 
     /// Shut down the sidecar process gracefully.
     async fn shutdown(&self) {
+        self.shutting_down.store(true, std::sync::atomic::Ordering::SeqCst);
         // Try graceful shutdown
         let _ = tokio::time::timeout(
             std::time::Duration::from_secs(5),
@@ -455,13 +1386,172 @@ impl SidecarBridge {
     }
 }
 
-/// Wrapper struct for Tauri managed state.
+/// Where the sidecar is in starting up, for `get_bridge_state`.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum BridgeState {
+    /// The sidecar process is being spawned; commands fail with "still starting".
+    Spawning,
+    /// The sidecar is running and accepting requests (`initialize` may still be in progress).
+    Ready,
+    /// The sidecar couldn't be spawned; commands fail with this reason.
+    Failed { error: String },
+}
+
+/// Payload of `semblance://sidecar-ready`, emitted once per sidecar (the
+/// first and every respawn) when `initialize` succeeds.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct SidecarReady {
+    pub epoch: u64,
+    /// What `initialize` returned, as also sent in `status-update`.
+    pub init_result: Value,
+    /// From starting the process until `initialize` returned.
+    pub spawn_duration_ms: u64,
+}
+
+/// Where starting a sidecar failed.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum SidecarFailureStage {
+    /// The process couldn't be started.
+    Spawn,
+    /// `initialize` returned an error or timed out.
+    Initialize,
+    /// The process exited before `initialize` finished.
+    Exited,
+}
+
+/// Payload of `semblance://sidecar-failed`, emitted instead of `sidecar-ready`
+/// when a sidecar doesn't come up. Each spawn attempt gets one or the other.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct SidecarFailed {
+    /// None when the process never started.
+    pub epoch: Option<u64>,
+    pub stage: SidecarFailureStage,
+    pub reason: String,
+    /// Another sidecar is being spawned and will announce its own outcome.
+    pub restarting: bool,
+}
+
+fn emit_sidecar_failed(app: &tauri::AppHandle, replay: &event_replay::SharedEventReplay, failed: &SidecarFailed) {
+    if let Ok(data) = serde_json::to_value(failed) {
+        event_replay::emit(app, replay, "sidecar-failed", &data);
+    }
+}
+
+/// Wrapper struct for Tauri managed state. Managed before the sidecar is
+/// spawned so commands can always extract it; `bridge()` reports a sidecar
+/// that isn't up yet (or failed to start) as a normal command error.
+#[derive(Default)]
 struct AppBridge {
-    bridge: SidecarBridge,
+    bridge: std::sync::OnceLock<SidecarBridge>,
+    spawn_error: std::sync::OnceLock<String>,
+}
+
+impl AppBridge {
+    fn bridge(&self) -> Result<&SidecarBridge, SemblanceError> {
+        match self.state() {
+            BridgeState::Ready => self.bridge.get().ok_or(SemblanceError::SidecarNotReady),
+            BridgeState::Spawning => Err(SemblanceError::SidecarNotReady),
+            BridgeState::Failed { error } => Err(SemblanceError::Sidecar { message: format!("Sidecar failed to start: {}", error) }),
+        }
+    }
+
+    fn state(&self) -> BridgeState {
+        if self.bridge.get().is_some() {
+            BridgeState::Ready
+        } else if let Some(error) = self.spawn_error.get() {
+            BridgeState::Failed { error: error.clone() }
+        } else {
+            BridgeState::Spawning
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SidecarHealth {
+    /// Request id epoch of the running sidecar (1 for the first spawn).
+    pub epoch: u64,
+    /// Times the sidecar was respawned after exiting unexpectedly.
+    pub restarts: u64,
+    pub next_request_id: u64,
+    /// Requests written to the sidecar and still awaiting a response.
+    pub pending_requests: usize,
+    /// Writes to the sidecar's stdin that timed out because it wasn't reading.
+    pub input_stalls: u64,
+    pub last_input_stall_at_ms: Option<u64>,
+    /// Round trip of the last answered heartbeat ping; `None` before the first.
+    pub last_ping_latency_ms: Option<u64>,
+    /// Heartbeat pings missed in a row; 0 while the sidecar is responsive.
+    pub consecutive_ping_misses: u32,
 }
 
 // ─── Tauri Commands ────────────────────────────────────────────────────────
 
+/// Whether the sidecar is still spawning, ready, or failed to start (with the
+/// reason). Poll during startup: until `ready`, sidecar-backed commands fail
+/// with the same reason.
+#[tauri::command]
+async fn get_bridge_state(state: tauri::State<'_, AppBridge>) -> Result<BridgeState, SemblanceError> {
+    Ok(state.state())
+}
+
+/// Whether the sidecar is keeping up: in-flight requests, and how often (and
+/// when last) it stopped accepting input. Requests made while it isn't reading
+/// fail after 5s with "Sidecar not accepting input". Also reports the request
+/// id epoch, which changes on every spawn so stale responses are ignored, and
+/// how often the sidecar was restarted after crashing. The heartbeat fields
+/// back a connection indicator: ping latency, and misses that point at a hung
+/// sidecar well before requests start timing out.
+#[tauri::command]
+async fn get_sidecar_health(state: tauri::State<'_, AppBridge>) -> Result<SidecarHealth, SemblanceError> {
+    Ok(state.bridge()?.health().await)
+}
+
+/// Round-trip latency of sidecar requests by method (count, errors, p50, p95,
+/// max; percentiles over the last 256 requests), measured from before the
+/// request waits for stdin so serialized writes count too. `write_p95_ms` is
+/// the stdin part alone. Local only; resets when the app restarts.
+#[tauri::command]
+async fn get_bridge_metrics(
+    state: tauri::State<'_, AppBridge>,
+) -> Result<std::collections::BTreeMap<String, bridge_metrics::MethodLatency>, SemblanceError> {
+    let bridge = state.bridge()?;
+    let metrics = bridge.metrics.lock().unwrap_or_else(|e| e.into_inner());
+    Ok(metrics.snapshot())
+}
+
+/// The sidecar's last `limit` stderr lines (up to 500, oldest first), kept
+/// across restarts. Also written to `sidecar.log` in the data dir.
+#[tauri::command]
+async fn get_sidecar_logs(state: tauri::State<'_, AppBridge>, limit: u32) -> Result<Vec<String>, SemblanceError> {
+    Ok(state.bridge()?.stderr_tail.last(limit as usize))
+}
+
+/// Kill and respawn the sidecar when it stops answering heartbeats, instead of
+/// only emitting `semblance://sidecar-unresponsive`. Persisted; returns the new
+/// setting.
+#[tauri::command]
+async fn set_restart_unresponsive_sidecar(
+    settings: tauri::State<'_, settings::SettingsState>,
+    enabled: bool,
+) -> Result<bool, SemblanceError> {
+    let updated = settings
+        .update(|s| s.restart_unresponsive_sidecar = enabled)
+        .await?;
+    Ok(updated.restart_unresponsive_sidecar)
+}
+
+/// Whether an unresponsive sidecar is restarted; see `set_restart_unresponsive_sidecar`.
+#[tauri::command]
+async fn get_restart_unresponsive_sidecar(
+    settings: tauri::State<'_, settings::SettingsState>,
+) -> Result<bool, SemblanceError> {
+    Ok(settings.get().await.restart_unresponsive_sidecar)
+}
+
 /// Send a message to the Orchestrator. Streams tokens back via events.
 #[tauri::command]
 async fn send_message(
@@ -469,10 +1559,10 @@ async fn send_message(
     message: String,
     conversation_id: Option<String>,
     attachments: Option<Value>,
-) -> Result<Value, String> {
+) -> Result<Value, SemblanceError> {
     state
-        .bridge
-        .call_fire("send_message", serde_json::json!({
+        .bridge()?
+        .call_cancellable("send_message", serde_json::json!({
             "message": message,
             "conversation_id": conversation_id,
             "attachments": attachments,
@@ -480,10 +1570,57 @@ async fn send_message(
         .await
 }
 
+/// Stop streaming the reply to a `send_message` call: later `chat-token`
+/// events and the `chat-complete` for `response_id` are dropped. The sidecar
+/// still finishes the generation. Returns false if the stream had already
+/// finished or been cancelled.
+#[tauri::command]
+async fn cancel_message(state: tauri::State<'_, AppBridge>, response_id: String) -> Result<bool, SemblanceError> {
+    let bridge = state.bridge()?;
+    let mut streams = bridge.chat_streams.lock().unwrap_or_else(|e| e.into_inner());
+    Ok(streams.cancel(&response_id))
+}
+
+/// Abort an in-progress `send_message` or `start_email_index` by the
+/// `responseId` it returned. Unknown or already finished ids are a no-op
+/// returning false.
+#[tauri::command]
+async fn cancel_request(state: tauri::State<'_, AppBridge>, response_id: String) -> Result<bool, SemblanceError> {
+    state.bridge()?.cancel_request(&response_id).await
+}
+
+/// Token streaming stats for the latest message in a conversation
+/// (`session_id` is the `conversationId` returned by `send_message`): events
+/// emitted, tokens coalesced or dropped from the live display while the UI
+/// lagged, and the length of the final text. Dropped tokens are never missing
+/// from the final message. Null if no message has streamed for the session.
+#[tauri::command]
+async fn get_stream_stats(state: tauri::State<'_, AppBridge>, session_id: String) -> Result<Value, SemblanceError> {
+    state
+        .bridge()?
+        .call("chat:getStreamStats", serde_json::json!({ "session_id": session_id }))
+        .await
+}
+
+/// Which inference backend is serving generations (native runtime vs. Ollama),
+/// the model each has loaded, and why the other is unavailable — e.g.
+/// "native backend failed to init: ..., using Ollama". Also returns the most
+/// recent provider transition and its reason.
+#[tauri::command]
+async fn get_active_inference_backend(state: tauri::State<'_, AppBridge>) -> Result<Value, SemblanceError> {
+    state
+        .bridge()?
+        .call("get_active_inference_backend", Value::Null)
+        .await
+}
+
 /// Check Ollama connection status and list available models.
 #[tauri::command]
-async fn get_ollama_status(state: tauri::State<'_, AppBridge>) -> Result<OllamaStatus, String> {
-    let result = state.bridge.call("get_ollama_status", Value::Null).await?;
+async fn get_ollama_status(state: tauri::State<'_, AppBridge>) -> Result<OllamaStatus, SemblanceError> {
+    let result = state
+        .bridge()?
+        .call_with_timeout("get_ollama_status", Value::Null, SIDECAR_STATUS_TIMEOUT)
+        .await?;
 
     Ok(OllamaStatus {
         status: result
@@ -512,46 +1649,168 @@ async fn get_ollama_status(state: tauri::State<'_, AppBridge>) -> Result<OllamaS
     })
 }
 
-/// Switch the active LLM model.
+/// Switch the active LLM model. `skip_memory_check` loads it even if the
+/// memory check refuses it (`insufficient_memory`), for this load only; see
+/// `set_skip_memory_check` to turn the check off for good.
 #[tauri::command]
 async fn select_model(
     state: tauri::State<'_, AppBridge>,
     model_id: String,
-) -> Result<(), String> {
+    skip_memory_check: Option<bool>,
+) -> Result<(), SemblanceError> {
     state
-        .bridge
-        .call("select_model", serde_json::json!({"model_id": model_id}))
+        .bridge()?
+        .call(
+            "select_model",
+            serde_json::json!({"model_id": model_id, "skip_memory_check": skip_memory_check.unwrap_or(false)}),
+        )
         .await?;
     Ok(())
 }
 
-/// Start indexing the given directories.
+/// Start indexing the given directories. Persisted exclusion patterns are applied.
 #[tauri::command]
 async fn start_indexing(
     state: tauri::State<'_, AppBridge>,
+    settings: tauri::State<'_, settings::SettingsState>,
     directories: Vec<String>,
-) -> Result<(), String> {
+) -> Result<(), SemblanceError> {
+    let exclusions = settings.get().await.indexing_exclusions;
     state
-        .bridge
+        .bridge()?
         .call_fire(
             "start_indexing",
-            serde_json::json!({"directories": directories}),
+            serde_json::json!({"directories": directories, "exclusions": exclusions}),
         )
         .await?;
     Ok(())
 }
 
-/// Get current indexing state.
+/// Replace the indexing exclusion list (glob patterns, e.g. `node_modules`, `*.log`,
+/// `private/**`). Persisted by the desktop app and applied to every index run.
+#[tauri::command]
+async fn set_indexing_exclusions(
+    settings: tauri::State<'_, settings::SettingsState>,
+    patterns: Vec<String>,
+) -> Result<Vec<String>, SemblanceError> {
+    let patterns = settings::normalize_exclusions(patterns)?;
+    let updated = settings
+        .update(|s| s.indexing_exclusions = patterns)
+        .await?;
+    Ok(updated.indexing_exclusions)
+}
+
+/// Get the persisted indexing exclusion patterns.
+#[tauri::command]
+async fn get_indexing_exclusions(
+    settings: tauri::State<'_, settings::SettingsState>,
+) -> Result<Vec<String>, SemblanceError> {
+    Ok(settings.get().await.indexing_exclusions)
+}
+
+/// Unload the reasoning model after `seconds` without generations to free RAM
+/// (0 disables). It is reloaded from the same file on the next generation
+/// request. Persisted; returns the new setting.
+#[tauri::command]
+async fn set_model_idle_timeout(
+    settings: tauri::State<'_, settings::SettingsState>,
+    seconds: u64,
+) -> Result<Option<u64>, SemblanceError> {
+    if seconds != 0 && seconds < settings::MIN_MODEL_IDLE_TIMEOUT_SECS {
+        return Err(format!(
+            "Idle timeout must be 0 (disabled) or at least {} seconds",
+            settings::MIN_MODEL_IDLE_TIMEOUT_SECS
+        ).into());
+    }
+    let timeout = if seconds == 0 { None } else { Some(seconds) };
+    let updated = settings
+        .update(|s| s.model_idle_timeout_secs = timeout)
+        .await?;
+    Ok(updated.model_idle_timeout_secs)
+}
+
+/// Get the reasoning model idle timeout in seconds (`null` = never unload).
+#[tauri::command]
+async fn get_model_idle_timeout(
+    settings: tauri::State<'_, settings::SettingsState>,
+) -> Result<Option<u64>, SemblanceError> {
+    Ok(settings.get().await.model_idle_timeout_secs)
+}
+
+/// Emit `semblance://low-memory` when available RAM drops below `mb` megabytes
+/// (0 disables). Alerts repeat at most every 5 minutes while memory stays low.
+/// Persisted; returns the new setting.
+#[tauri::command]
+async fn set_low_memory_threshold(
+    settings: tauri::State<'_, settings::SettingsState>,
+    mb: u64,
+) -> Result<Option<u64>, SemblanceError> {
+    let total_mb = hardware::total_memory_bytes() / (1024 * 1024);
+    if mb >= total_mb {
+        return Err(format!("Threshold must be below total RAM ({} MB)", total_mb).into());
+    }
+    let threshold = if mb == 0 { None } else { Some(mb) };
+    let updated = settings
+        .update(|s| s.low_memory_threshold_mb = threshold)
+        .await?;
+    Ok(updated.low_memory_threshold_mb)
+}
+
+/// Get the low-memory alert threshold in MB (`null` = disabled).
+#[tauri::command]
+async fn get_low_memory_threshold(
+    settings: tauri::State<'_, settings::SettingsState>,
+) -> Result<Option<u64>, SemblanceError> {
+    Ok(settings.get().await.low_memory_threshold_mb)
+}
+
+/// Get current indexing state.
+#[tauri::command]
+async fn get_indexing_status(
+    state: tauri::State<'_, AppBridge>,
+) -> Result<IndexingStatus, SemblanceError> {
+    let result = state
+        .bridge()?
+        .call_with_timeout("get_indexing_status", Value::Null, SIDECAR_STATUS_TIMEOUT)
+        .await?;
+
+    Ok(serde_json::from_value(result).map_err(|e| SemblanceError::Protocol { detail: format!("Failed to parse indexing status: {}", e) })?)
+}
+
+/// Forward an indexing control (`pause_indexing`, `resume_indexing`,
+/// `cancel_indexing`) and return the status after the transition.
+async fn control_indexing(state: &AppBridge, method: &str) -> Result<IndexingStatus, SemblanceError> {
+    let result = state
+        .bridge()?
+        .call_with_timeout(method, Value::Null, SIDECAR_STATUS_TIMEOUT)
+        .await?;
+    serde_json::from_value(result)
+        .map_err(|e| SemblanceError::Protocol { detail: format!("Failed to parse indexing status: {}", e) })
+}
+
+/// Pause the running index job after the file in progress. Fails unless indexing.
+#[tauri::command]
+async fn pause_indexing(
+    state: tauri::State<'_, AppBridge>,
+) -> Result<IndexingStatus, SemblanceError> {
+    control_indexing(&state, "pause_indexing").await
+}
+
+/// Resume a paused index job. Fails unless paused.
+#[tauri::command]
+async fn resume_indexing(
+    state: tauri::State<'_, AppBridge>,
+) -> Result<IndexingStatus, SemblanceError> {
+    control_indexing(&state, "resume_indexing").await
+}
+
+/// Stop a running or paused index job. Files already indexed are kept; the
+/// state stays `cancelled` until the next `start_indexing`.
 #[tauri::command]
-async fn get_indexing_status(
+async fn cancel_indexing(
     state: tauri::State<'_, AppBridge>,
-) -> Result<IndexingStatus, String> {
-    let result = state
-        .bridge
-        .call("get_indexing_status", Value::Null)
-        .await?;
-
-    Ok(serde_json::from_value(result).map_err(|e| format!("Failed to parse indexing status: {}", e))?)
+) -> Result<IndexingStatus, SemblanceError> {
+    control_indexing(&state, "cancel_indexing").await
 }
 
 /// Query the audit trail for paginated action log entries.
@@ -560,23 +1819,23 @@ async fn get_action_log(
     state: tauri::State<'_, AppBridge>,
     limit: u32,
     offset: u32,
-) -> Result<Vec<ActionLogEntry>, String> {
+) -> Result<Vec<ActionLogEntry>, SemblanceError> {
     let result = state
-        .bridge
+        .bridge()?
         .call(
             "get_action_log",
             serde_json::json!({"limit": limit, "offset": offset}),
         )
         .await?;
 
-    serde_json::from_value(result).map_err(|e| format!("Failed to parse action log: {}", e))
+    serde_json::from_value(result).map_err(|e| SemblanceError::Protocol { detail: format!("Failed to parse action log: {}", e) })
 }
 
 /// Get privacy status from the Gateway.
 #[tauri::command]
-async fn get_privacy_status(state: tauri::State<'_, AppBridge>) -> Result<PrivacyStatus, String> {
+async fn get_privacy_status(state: tauri::State<'_, AppBridge>) -> Result<PrivacyStatus, SemblanceError> {
     let result = state
-        .bridge
+        .bridge()?
         .call("get_privacy_status", Value::Null)
         .await?;
 
@@ -602,9 +1861,9 @@ async fn get_privacy_status(state: tauri::State<'_, AppBridge>) -> Result<Privac
 
 /// Persist the user's chosen name for their Semblance.
 #[tauri::command]
-async fn set_user_name(state: tauri::State<'_, AppBridge>, name: String) -> Result<(), String> {
+async fn set_user_name(state: tauri::State<'_, AppBridge>, name: String) -> Result<(), SemblanceError> {
     state
-        .bridge
+        .bridge()?
         .call("set_user_name", serde_json::json!({"name": name}))
         .await?;
     Ok(())
@@ -612,28 +1871,35 @@ async fn set_user_name(state: tauri::State<'_, AppBridge>, name: String) -> Resu
 
 /// Retrieve the user's chosen name.
 #[tauri::command]
-async fn get_user_name(state: tauri::State<'_, AppBridge>) -> Result<Option<String>, String> {
-    let result = state.bridge.call("get_user_name", Value::Null).await?;
+async fn get_user_name(state: tauri::State<'_, AppBridge>) -> Result<Option<String>, SemblanceError> {
+    let result = state.bridge()?.call("get_user_name", Value::Null).await?;
     Ok(result
         .get("name")
         .and_then(|v| v.as_str())
         .map(|s| s.to_string()))
 }
 
-/// Update autonomy tier for a domain.
+/// Update autonomy tier for a domain. Persisted in desktop settings once the
+/// sidecar accepts it, so it is restored if the sidecar's copy is lost.
 #[tauri::command]
 async fn set_autonomy_tier(
     state: tauri::State<'_, AppBridge>,
+    settings: tauri::State<'_, settings::SettingsState>,
     domain: String,
     tier: String,
-) -> Result<(), String> {
+) -> Result<(), SemblanceError> {
     state
-        .bridge
+        .bridge()?
         .call(
             "set_autonomy_tier",
             serde_json::json!({"domain": domain, "tier": tier}),
         )
         .await?;
+    settings
+        .update(|s| {
+            s.autonomy_tiers.insert(domain, tier);
+        })
+        .await?;
     Ok(())
 }
 
@@ -641,9 +1907,9 @@ async fn set_autonomy_tier(
 #[tauri::command]
 async fn get_autonomy_config(
     state: tauri::State<'_, AppBridge>,
-) -> Result<AutonomyConfig, String> {
+) -> Result<AutonomyConfig, SemblanceError> {
     let result = state
-        .bridge
+        .bridge()?
         .call("get_autonomy_config", Value::Null)
         .await?;
 
@@ -655,32 +1921,163 @@ async fn get_autonomy_config(
     Ok(AutonomyConfig { domains })
 }
 
+/// Initialize Core and Gateway in a newly spawned sidecar, then reconcile
+/// autonomy tiers. Emits the resulting status either way, plus
+/// `sidecar-ready` or `sidecar-failed` unless the sidecar's exit already
+/// announced it; returns whether `initialize` succeeded.
+async fn initialize_sidecar(
+    app: &tauri::AppHandle,
+    bridge: &SidecarBridge,
+    replay: &event_replay::SharedEventReplay,
+) -> bool {
+    let epoch = bridge.epoch.load(std::sync::atomic::Ordering::SeqCst);
+    match bridge.call("initialize", Value::Null).await {
+        Ok(init_result) => {
+            event_replay::emit(app, replay, "status-update", &init_result);
+            eprintln!(
+                "[tauri] Sidecar initialized: {}",
+                serde_json::to_string(&init_result).unwrap_or_default()
+            );
+            if bridge.announce(epoch) {
+                let ready = SidecarReady {
+                    epoch,
+                    spawn_duration_ms: bridge.restart_policy.lock().await.spawned_at().elapsed().as_millis() as u64,
+                    init_result,
+                };
+                if let Ok(data) = serde_json::to_value(&ready) {
+                    event_replay::emit(app, replay, "sidecar-ready", &data);
+                }
+            }
+
+            let settings_state = app.state::<settings::SettingsState>();
+            match reconcile_autonomy(bridge, &settings_state).await {
+                Ok(summary) => {
+                    eprintln!(
+                        "[tauri] Autonomy reconciled: {} restored, {} adopted, {} failed",
+                        summary.restored.len(),
+                        summary.adopted.len(),
+                        summary.failed.len()
+                    );
+                    let _ = app.emit("semblance://autonomy-reconciled", &summary);
+                }
+                Err(e) => eprintln!("[tauri] Autonomy reconciliation failed: {}", e),
+            }
+            true
+        }
+        Err(e) => {
+            eprintln!("[tauri] Sidecar initialization failed: {}", e);
+            let mut error = format!("Initialization failed: {}", e);
+            let stderr = bridge.stderr_tail.last(SIDECAR_INIT_ERROR_LINES);
+            if !stderr.is_empty() {
+                error.push_str("\n\nLast sidecar output:\n");
+                error.push_str(&stderr.join("\n"));
+            }
+            event_replay::emit(
+                app,
+                replay,
+                "status-update",
+                &serde_json::json!({
+                    "ollamaStatus": "disconnected",
+                    "error": error
+                }),
+            );
+            if bridge.announce(epoch) {
+                emit_sidecar_failed(
+                    app,
+                    replay,
+                    &SidecarFailed {
+                        epoch: Some(epoch),
+                        stage: SidecarFailureStage::Initialize,
+                        reason: error,
+                        restarting: matches!(e, SemblanceError::SidecarCrashed { restarting: true }),
+                    },
+                );
+            }
+            false
+        }
+    }
+}
+
+/// Compare the sidecar's autonomy tiers with the persisted ones after
+/// `initialize`: push persisted tiers back where they differ, and adopt (and
+/// persist) the sidecar's tier for domains settings doesn't know yet.
+async fn reconcile_autonomy(
+    bridge: &SidecarBridge,
+    settings: &settings::SettingsState,
+) -> Result<autonomy::AutonomyReconciliation, String> {
+    let reported = bridge.call("get_autonomy_config", Value::Null).await.map_err(|e| e.to_string())?;
+    let reported: HashMap<String, String> = reported
+        .get("domains")
+        .and_then(|v| serde_json::from_value(v.clone()).ok())
+        .ok_or("Sidecar returned no autonomy config")?;
+    let mut summary = autonomy::plan(&settings.get().await.autonomy_tiers, &reported);
+
+    let mut restored = Vec::with_capacity(summary.restored.len());
+    for change in std::mem::take(&mut summary.restored) {
+        match bridge
+            .call("set_autonomy_tier", serde_json::json!({"domain": change.domain, "tier": change.tier}))
+            .await
+        {
+            Ok(_) => restored.push(change),
+            Err(e) => summary.failed.push((change.domain, e.to_string())),
+        }
+    }
+    summary.restored = restored;
+
+    if !summary.adopted.is_empty() {
+        let adopted = summary.adopted.clone();
+        settings
+            .update(|s| {
+                for change in adopted {
+                    s.autonomy_tiers.insert(change.domain, change.tier);
+                }
+            })
+            .await?;
+    }
+    Ok(summary)
+}
+
 /// Get list of currently indexed directories.
 #[tauri::command]
 async fn get_indexed_directories(
     state: tauri::State<'_, AppBridge>,
-) -> Result<Vec<String>, String> {
+) -> Result<Vec<String>, SemblanceError> {
     let result = state
-        .bridge
+        .bridge()?
         .call("get_indexed_directories", Value::Null)
         .await?;
 
     serde_json::from_value(result)
-        .map_err(|e| format!("Failed to parse indexed directories: {}", e))
+        .map_err(|e| SemblanceError::Protocol { detail: format!("Failed to parse indexed directories: {}", e) })
 }
 
 /// Get knowledge graph statistics.
 #[tauri::command]
 async fn get_knowledge_stats(
     state: tauri::State<'_, AppBridge>,
-) -> Result<KnowledgeStats, String> {
+) -> Result<KnowledgeStats, SemblanceError> {
     let result = state
-        .bridge
+        .bridge()?
         .call("get_knowledge_stats", Value::Null)
         .await?;
 
     Ok(serde_json::from_value(result)
-        .map_err(|e| format!("Failed to parse knowledge stats: {}", e))?)
+        .map_err(|e| SemblanceError::Protocol { detail: format!("Failed to parse knowledge stats: {}", e) })?)
+}
+
+/// Stable fingerprint of the current index, for deciding whether a cached result
+/// or backup still matches. The digest covers, and only covers: the sorted set of
+/// indexed directories, the document count, the chunk count, the embedding model
+/// id, and a hash over every document's content hash. Returns
+/// `{fingerprint, inputs}` so callers can see which input changed.
+#[tauri::command]
+async fn get_index_fingerprint(
+    state: tauri::State<'_, AppBridge>,
+) -> Result<Value, SemblanceError> {
+    state
+        .bridge()?
+        .call("get_index_fingerprint", Value::Null)
+        .await
 }
 
 /// Get chat history (paginated).
@@ -689,23 +2086,23 @@ async fn get_chat_history(
     state: tauri::State<'_, AppBridge>,
     limit: u32,
     offset: u32,
-) -> Result<Vec<ChatMessage>, String> {
+) -> Result<Vec<ChatMessage>, SemblanceError> {
     let result = state
-        .bridge
+        .bridge()?
         .call(
             "get_chat_history",
             serde_json::json!({"limit": limit, "offset": offset}),
         )
         .await?;
 
-    serde_json::from_value(result).map_err(|e| format!("Failed to parse chat history: {}", e))
+    serde_json::from_value(result).map_err(|e| SemblanceError::Protocol { detail: format!("Failed to parse chat history: {}", e) })
 }
 
 /// Set onboarding complete flag.
 #[tauri::command]
-async fn set_onboarding_complete(state: tauri::State<'_, AppBridge>) -> Result<(), String> {
+async fn set_onboarding_complete(state: tauri::State<'_, AppBridge>) -> Result<(), SemblanceError> {
     state
-        .bridge
+        .bridge()?
         .call("set_onboarding_complete", Value::Null)
         .await?;
     Ok(())
@@ -713,9 +2110,9 @@ async fn set_onboarding_complete(state: tauri::State<'_, AppBridge>) -> Result<(
 
 /// Check if onboarding has been completed.
 #[tauri::command]
-async fn get_onboarding_complete(state: tauri::State<'_, AppBridge>) -> Result<bool, String> {
+async fn get_onboarding_complete(state: tauri::State<'_, AppBridge>) -> Result<bool, SemblanceError> {
     let result = state
-        .bridge
+        .bridge()?
         .call("get_onboarding_complete", Value::Null)
         .await?;
     Ok(result
@@ -726,7 +2123,9 @@ async fn get_onboarding_complete(state: tauri::State<'_, AppBridge>) -> Result<b
 
 // ─── Credential Management Commands ─────────────────────────────────────────
 
-/// Add a new service credential (email or calendar).
+/// Add a new service credential (email or calendar). The server settings are
+/// validated first (see `credentials::validate`); a bad one fails with a
+/// `validation` error naming the field.
 #[tauri::command]
 async fn add_credential(
     state: tauri::State<'_, AppBridge>,
@@ -738,26 +2137,27 @@ async fn add_credential(
     password: String,
     use_tls: bool,
     display_name: String,
-) -> Result<ServiceCredentialInfo, String> {
+) -> Result<ServiceCredentialInfo, SemblanceError> {
+    let settings = credentials::validate(&service_type, &protocol, &host, port, &username, use_tls, &display_name)?;
     let result = state
-        .bridge
+        .bridge()?
         .call(
             "add_credential",
             serde_json::json!({
-                "serviceType": service_type,
-                "protocol": protocol,
-                "host": host,
-                "port": port,
-                "username": username,
+                "serviceType": settings.service_type,
+                "protocol": settings.protocol,
+                "host": settings.host,
+                "port": settings.port,
+                "username": settings.username,
                 "password": password,
-                "useTLS": use_tls,
-                "displayName": display_name,
+                "useTLS": settings.use_tls,
+                "displayName": settings.display_name,
             }),
         )
         .await?;
 
     serde_json::from_value(result)
-        .map_err(|e| format!("Failed to parse credential response: {}", e))
+        .map_err(|e| SemblanceError::Protocol { detail: format!("Failed to parse credential response: {}", e) })
 }
 
 /// List credentials by service type ("email", "calendar", or "all").
@@ -765,9 +2165,9 @@ async fn add_credential(
 async fn list_credentials(
     state: tauri::State<'_, AppBridge>,
     service_type: String,
-) -> Result<Vec<ServiceCredentialInfo>, String> {
+) -> Result<Vec<ServiceCredentialInfo>, SemblanceError> {
     let result = state
-        .bridge
+        .bridge()?
         .call(
             "list_credentials",
             serde_json::json!({"service_type": service_type}),
@@ -775,7 +2175,7 @@ async fn list_credentials(
         .await?;
 
     serde_json::from_value(result)
-        .map_err(|e| format!("Failed to parse credentials list: {}", e))
+        .map_err(|e| SemblanceError::Protocol { detail: format!("Failed to parse credentials list: {}", e) })
 }
 
 /// Remove a credential by ID.
@@ -783,27 +2183,49 @@ async fn list_credentials(
 async fn remove_credential(
     state: tauri::State<'_, AppBridge>,
     id: String,
-) -> Result<(), String> {
+) -> Result<(), SemblanceError> {
     state
-        .bridge
+        .bridge()?
         .call("remove_credential", serde_json::json!({"id": id}))
         .await?;
     Ok(())
 }
 
-/// Test a credential's connection (IMAP, SMTP, or CalDAV).
+/// Test a credential's connection (IMAP, SMTP, or CalDAV), waiting up to
+/// `timeout_secs` (default 15, at most 120). Each phase (dns, tls, auth,
+/// list_calendars) is announced with `semblance://credential-test-progress`
+/// `{id, phase}`. A timeout is a failed result naming the phase that hung.
 #[tauri::command]
 async fn test_credential(
     state: tauri::State<'_, AppBridge>,
     id: String,
-) -> Result<ConnectionTestResult, String> {
-    let result = state
-        .bridge
-        .call("test_credential", serde_json::json!({"id": id}))
-        .await?;
-
-    serde_json::from_value(result)
-        .map_err(|e| format!("Failed to parse connection test result: {}", e))
+    timeout_secs: Option<u64>,
+) -> Result<ConnectionTestResult, SemblanceError> {
+    let secs = timeout_secs.unwrap_or(CREDENTIAL_TEST_TIMEOUT_SECS);
+    if secs == 0 || secs > SIDECAR_CALL_TIMEOUT.as_secs() {
+        return Err(format!("timeout_secs must be between 1 and {}", SIDECAR_CALL_TIMEOUT.as_secs()).into());
+    }
+    let bridge = state.bridge()?;
+    bridge.credential_tests.lock().unwrap_or_else(|e| e.into_inner()).insert(id.clone(), "dns".to_string());
+    let result = bridge
+        .call_with_timeout("test_credential", serde_json::json!({"id": id}), std::time::Duration::from_secs(secs))
+        .await;
+    let phase = bridge.credential_tests.lock().unwrap_or_else(|e| e.into_inner()).remove(&id);
+
+    match result {
+        Ok(result) => serde_json::from_value(result)
+            .map_err(|e| SemblanceError::Protocol { detail: format!("Failed to parse connection test result: {}", e) }),
+        Err(SemblanceError::Timeout { .. }) => {
+            let phase = phase.unwrap_or_else(|| "dns".to_string());
+            Ok(ConnectionTestResult {
+                success: false,
+                error: Some(format!("Connection test timed out after {}s during {}", secs, phase)),
+                calendars: None,
+                phase: Some(phase),
+            })
+        }
+        Err(e) => Err(e),
+    }
 }
 
 /// Discover available calendars for a CalDAV credential.
@@ -811,9 +2233,9 @@ async fn test_credential(
 async fn discover_calendars(
     state: tauri::State<'_, AppBridge>,
     credential_id: String,
-) -> Result<Vec<CalendarInfo>, String> {
+) -> Result<Vec<CalendarInfo>, SemblanceError> {
     let result = state
-        .bridge
+        .bridge()?
         .call(
             "discover_calendars",
             serde_json::json!({"credential_id": credential_id}),
@@ -821,30 +2243,30 @@ async fn discover_calendars(
         .await?;
 
     serde_json::from_value(result)
-        .map_err(|e| format!("Failed to parse calendars: {}", e))
+        .map_err(|e| SemblanceError::Protocol { detail: format!("Failed to parse calendars: {}", e) })
 }
 
 /// Get status of all configured accounts.
 #[tauri::command]
 async fn get_accounts_status(
     state: tauri::State<'_, AppBridge>,
-) -> Result<Vec<AccountStatus>, String> {
+) -> Result<Vec<AccountStatus>, SemblanceError> {
     let result = state
-        .bridge
+        .bridge()?
         .call("get_accounts_status", Value::Null)
         .await?;
 
     serde_json::from_value(result)
-        .map_err(|e| format!("Failed to parse accounts status: {}", e))
+        .map_err(|e| SemblanceError::Protocol { detail: format!("Failed to parse accounts status: {}", e) })
 }
 
 /// Get provider presets for email/calendar configuration.
 #[tauri::command]
 async fn get_provider_presets(
     state: tauri::State<'_, AppBridge>,
-) -> Result<Value, String> {
+) -> Result<Value, SemblanceError> {
     state
-        .bridge
+        .bridge()?
         .call("get_provider_presets", Value::Null)
         .await
 }
@@ -857,9 +2279,9 @@ async fn get_inbox_items(
     state: tauri::State<'_, AppBridge>,
     limit: u32,
     offset: u32,
-) -> Result<Value, String> {
+) -> Result<Value, SemblanceError> {
     state
-        .bridge
+        .bridge()?
         .call(
             "inbox:getItems",
             serde_json::json!({"limit": limit, "offset": offset}),
@@ -869,27 +2291,27 @@ async fn get_inbox_items(
 
 /// Get proactive insights (meeting preps, follow-ups, deadlines).
 #[tauri::command]
-async fn get_proactive_insights(state: tauri::State<'_, AppBridge>) -> Result<Value, String> {
+async fn get_proactive_insights(state: tauri::State<'_, AppBridge>) -> Result<Value, SemblanceError> {
     state
-        .bridge
+        .bridge()?
         .call("inbox:getProactiveInsights", Value::Null)
         .await
 }
 
 /// Get today's calendar events.
 #[tauri::command]
-async fn get_today_events(state: tauri::State<'_, AppBridge>) -> Result<Value, String> {
+async fn get_today_events(state: tauri::State<'_, AppBridge>) -> Result<Value, SemblanceError> {
     state
-        .bridge
+        .bridge()?
         .call("inbox:getTodayEvents", Value::Null)
         .await
 }
 
 /// Get actions summary (count, time saved, recent actions).
 #[tauri::command]
-async fn get_actions_summary(state: tauri::State<'_, AppBridge>) -> Result<Value, String> {
+async fn get_actions_summary(state: tauri::State<'_, AppBridge>) -> Result<Value, SemblanceError> {
     state
-        .bridge
+        .bridge()?
         .call("inbox:getActionsSummary", Value::Null)
         .await
 }
@@ -899,9 +2321,9 @@ async fn get_actions_summary(state: tauri::State<'_, AppBridge>) -> Result<Value
 async fn archive_emails(
     state: tauri::State<'_, AppBridge>,
     message_ids: Vec<String>,
-) -> Result<Value, String> {
+) -> Result<Value, SemblanceError> {
     state
-        .bridge
+        .bridge()?
         .call(
             "email:archive",
             serde_json::json!({"message_ids": message_ids}),
@@ -917,9 +2339,9 @@ async fn send_email_action(
     subject: String,
     body: String,
     reply_to_message_id: Option<String>,
-) -> Result<Value, String> {
+) -> Result<Value, SemblanceError> {
     state
-        .bridge
+        .bridge()?
         .call(
             "email:sendAction",
             serde_json::json!({
@@ -940,9 +2362,9 @@ async fn draft_email_action(
     subject: String,
     body: String,
     reply_to_message_id: Option<String>,
-) -> Result<Value, String> {
+) -> Result<Value, SemblanceError> {
     state
-        .bridge
+        .bridge()?
         .call(
             "email:draftAction",
             serde_json::json!({
@@ -955,14 +2377,34 @@ async fn draft_email_action(
         .await
 }
 
+/// Preview what `send_email_action` or `archive_emails` would do with these
+/// arguments — the rendered email, the affected messages, whether it can be
+/// undone — without doing it. Works for every autonomy tier, including those
+/// that would run the action without asking.
+#[tauri::command]
+async fn preview_action(
+    state: tauri::State<'_, AppBridge>,
+    action: PreviewableAction,
+) -> Result<ActionPreview, SemblanceError> {
+    let result = state
+        .bridge()?
+        .call(
+            "action:preview",
+            serde_json::json!({"action": action.action_type(), "payload": action}),
+        )
+        .await?;
+    serde_json::from_value(result)
+        .map_err(|e| SemblanceError::Protocol { detail: format!("Invalid action preview response: {}", e) })
+}
+
 /// Undo a previously executed action.
 #[tauri::command]
 async fn undo_action(
     state: tauri::State<'_, AppBridge>,
     action_id: String,
-) -> Result<Value, String> {
+) -> Result<Value, SemblanceError> {
     state
-        .bridge
+        .bridge()?
         .call("action:undo", serde_json::json!({"action_id": action_id}))
         .await
 }
@@ -972,9 +2414,9 @@ async fn undo_action(
 async fn dismiss_insight(
     state: tauri::State<'_, AppBridge>,
     insight_id: String,
-) -> Result<Value, String> {
+) -> Result<Value, SemblanceError> {
     state
-        .bridge
+        .bridge()?
         .call(
             "insight:dismiss",
             serde_json::json!({"insight_id": insight_id}),
@@ -982,10 +2424,26 @@ async fn dismiss_insight(
         .await
 }
 
+/// Get the evidence behind a proactive insight — source items (email ids, event ids,
+/// file paths) and a short rationale. Returns null for insights stored without evidence.
+#[tauri::command]
+async fn get_insight_evidence(
+    state: tauri::State<'_, AppBridge>,
+    insight_id: String,
+) -> Result<Value, SemblanceError> {
+    state
+        .bridge()?
+        .call(
+            "insight:getEvidence",
+            serde_json::json!({"insight_id": insight_id}),
+        )
+        .await
+}
+
 /// Get pending actions awaiting user approval.
 #[tauri::command]
-async fn get_pending_actions(state: tauri::State<'_, AppBridge>) -> Result<Value, String> {
-    state.bridge.call("action:getPending", Value::Null).await
+async fn get_pending_actions(state: tauri::State<'_, AppBridge>) -> Result<Value, SemblanceError> {
+    state.bridge()?.call("action:getPending", Value::Null).await
 }
 
 /// Approve a pending action for execution.
@@ -993,9 +2451,9 @@ async fn get_pending_actions(state: tauri::State<'_, AppBridge>) -> Result<Value
 async fn approve_action(
     state: tauri::State<'_, AppBridge>,
     action_id: String,
-) -> Result<Value, String> {
+) -> Result<Value, SemblanceError> {
     state
-        .bridge
+        .bridge()?
         .call(
             "action:approve",
             serde_json::json!({"action_id": action_id}),
@@ -1008,9 +2466,9 @@ async fn approve_action(
 async fn reject_action(
     state: tauri::State<'_, AppBridge>,
     action_id: String,
-) -> Result<Value, String> {
+) -> Result<Value, SemblanceError> {
     state
-        .bridge
+        .bridge()?
         .call(
             "action:reject",
             serde_json::json!({"action_id": action_id}),
@@ -1024,9 +2482,9 @@ async fn get_approval_count(
     state: tauri::State<'_, AppBridge>,
     action_type: String,
     payload: Value,
-) -> Result<Value, String> {
+) -> Result<Value, SemblanceError> {
     state
-        .bridge
+        .bridge()?
         .call(
             "action:getApprovalCount",
             serde_json::json!({"action_type": action_type, "payload": payload}),
@@ -1040,9 +2498,9 @@ async fn get_approval_threshold(
     state: tauri::State<'_, AppBridge>,
     action_type: String,
     payload: Value,
-) -> Result<Value, String> {
+) -> Result<Value, SemblanceError> {
     state
-        .bridge
+        .bridge()?
         .call(
             "action:getApprovalThreshold",
             serde_json::json!({"action_type": action_type, "payload": payload}),
@@ -1055,10 +2513,10 @@ async fn get_approval_threshold(
 async fn start_email_index(
     state: tauri::State<'_, AppBridge>,
     account_id: String,
-) -> Result<Value, String> {
+) -> Result<Value, SemblanceError> {
     state
-        .bridge
-        .call_fire(
+        .bridge()?
+        .call_cancellable(
             "email:startIndex",
             serde_json::json!({"account_id": account_id}),
         )
@@ -1070,9 +2528,9 @@ async fn start_email_index(
 async fn start_calendar_index(
     state: tauri::State<'_, AppBridge>,
     account_id: String,
-) -> Result<Value, String> {
+) -> Result<Value, SemblanceError> {
     state
-        .bridge
+        .bridge()?
         .call_fire(
             "calendar:startIndex",
             serde_json::json!({"account_id": account_id}),
@@ -1082,25 +2540,43 @@ async fn start_calendar_index(
 
 /// Run the proactive context engine manually.
 #[tauri::command]
-async fn run_proactive_engine(state: tauri::State<'_, AppBridge>) -> Result<Value, String> {
-    state.bridge.call("proactive:run", Value::Null).await
+async fn run_proactive_engine(state: tauri::State<'_, AppBridge>) -> Result<Value, SemblanceError> {
+    state.bridge()?.call_with_timeout("proactive:run", Value::Null, SIDECAR_GENERATION_TIMEOUT).await
 }
 
 // ─── Step 7: Subscription Detection ─────────────────────────────────────────
 
-/// Import a bank statement (CSV/OFX) from local filesystem.
+/// Import a bank statement (CSV, OFX 1.x/2.x or QFX) from local filesystem.
+/// The file is read, decoded and identified here, so a Latin-1 CSV reaches the
+/// sidecar's parser as UTF-8 and a PDF is refused as a `validation` error
+/// naming what it is; `semblance://statement-import-progress` events report
+/// rows parsed as it works through them, which for a multi-year export takes a while.
 #[tauri::command]
 async fn import_statement(
     state: tauri::State<'_, AppBridge>,
     file_path: String,
-) -> Result<Value, String> {
-    state
-        .bridge
-        .call(
+) -> Result<finance::StatementImportSummary, SemblanceError> {
+    let bytes = tokio::fs::read(&file_path)
+        .await
+        .map_err(|e| format!("Failed to read statement {}: {}", file_path, e))?;
+    let (content, encoding) = finance::decode_statement(&bytes);
+    let format = finance::detect_statement_format(&bytes, &content).map_err(|detected| SemblanceError::Validation {
+        field: "filePath".to_string(),
+        message: format!("Unsupported statement format: the file is {}. Export a CSV, OFX or QFX statement from your bank instead.", detected),
+    })?;
+    let result = state
+        .bridge()?
+        .call_with_timeout(
             "finance:importStatement",
-            serde_json::json!({"file_path": file_path}),
+            serde_json::json!({"file_path": file_path, "content": content, "format": format}),
+            SIDECAR_GENERATION_TIMEOUT,
         )
-        .await
+        .await?;
+    let mut summary: finance::StatementImportSummary = serde_json::from_value(result)
+        .map_err(|e| SemblanceError::Protocol { detail: format!("Invalid statement import response: {}", e) })?;
+    summary.encoding = encoding.to_string();
+    summary.format = Some(format);
+    Ok(summary)
 }
 
 /// Get stored subscription/recurring charges.
@@ -1108,9 +2584,9 @@ async fn import_statement(
 async fn get_subscriptions(
     state: tauri::State<'_, AppBridge>,
     status: Option<String>,
-) -> Result<Value, String> {
+) -> Result<Value, SemblanceError> {
     state
-        .bridge
+        .bridge()?
         .call(
             "finance:getSubscriptions",
             serde_json::json!({"status": status}),
@@ -1124,9 +2600,9 @@ async fn update_subscription_status(
     state: tauri::State<'_, AppBridge>,
     charge_id: String,
     status: String,
-) -> Result<Value, String> {
+) -> Result<Value, SemblanceError> {
     state
-        .bridge
+        .bridge()?
         .call(
             "finance:updateSubscriptionStatus",
             serde_json::json!({"charge_id": charge_id, "status": status}),
@@ -1136,16 +2612,58 @@ async fn update_subscription_status(
 
 /// Get subscription summary (totals, forgotten count, savings).
 #[tauri::command]
-async fn get_subscription_summary(state: tauri::State<'_, AppBridge>) -> Result<Value, String> {
-    state.bridge.call("finance:getSummary", Value::Null).await
+async fn get_subscription_summary(state: tauri::State<'_, AppBridge>) -> Result<Value, SemblanceError> {
+    state.bridge()?.call("finance:getSummary", Value::Null).await
+}
+
+/// Suggest groups of subscriptions that look like one charge split by merchant-name
+/// variations (e.g. "NETFLIX.COM" vs "Netflix") with similar amount and cadence.
+/// Suggestions only — nothing is merged until `confirm_subscription_merge`.
+#[tauri::command]
+async fn merge_subscription_candidates(
+    state: tauri::State<'_, AppBridge>,
+) -> Result<Vec<finance::SubscriptionMergeCandidate>, SemblanceError> {
+    let result = state
+        .bridge()?
+        .call("finance:getSubscriptions", serde_json::json!({"status": null}))
+        .await?;
+    let charges: Vec<finance::SubscriptionCharge> = serde_json::from_value(result)
+        .map_err(|e| SemblanceError::Protocol { detail: format!("Invalid subscriptions response: {}", e) })?;
+    Ok(finance::find_merge_candidates(&charges))
+}
+
+/// Merge user-confirmed duplicate subscriptions. The one with the most charges is kept
+/// as the primary, as `merge_subscription_candidates` suggests; the rest are folded into
+/// it and hidden with status `merged`.
+#[tauri::command]
+async fn confirm_subscription_merge(
+    state: tauri::State<'_, AppBridge>,
+    ids: Vec<String>,
+) -> Result<Value, SemblanceError> {
+    let mut charge_ids: Vec<String> = Vec::with_capacity(ids.len());
+    for id in ids {
+        if !charge_ids.contains(&id) {
+            charge_ids.push(id);
+        }
+    }
+    if charge_ids.len() < 2 {
+        return Err("At least two distinct subscription IDs are required to merge".into());
+    }
+    state
+        .bridge()?
+        .call(
+            "finance:mergeSubscriptions",
+            serde_json::json!({"charge_ids": charge_ids}),
+        )
+        .await
 }
 
 // ─── Step 7: Autonomy Escalation ────────────────────────────────────────────
 
 /// Check for available autonomy escalation prompts.
 #[tauri::command]
-async fn check_escalations(state: tauri::State<'_, AppBridge>) -> Result<Value, String> {
-    state.bridge.call("escalation:check", Value::Null).await
+async fn check_escalations(state: tauri::State<'_, AppBridge>) -> Result<Value, SemblanceError> {
+    state.bridge()?.call("escalation:check", Value::Null).await
 }
 
 /// Respond to an escalation prompt (accept or dismiss).
@@ -1154,9 +2672,9 @@ async fn respond_to_escalation(
     state: tauri::State<'_, AppBridge>,
     prompt_id: String,
     accepted: bool,
-) -> Result<Value, String> {
+) -> Result<Value, SemblanceError> {
     state
-        .bridge
+        .bridge()?
         .call(
             "escalation:respond",
             serde_json::json!({"prompt_id": prompt_id, "accepted": accepted}),
@@ -1166,18 +2684,18 @@ async fn respond_to_escalation(
 
 /// Get active (pending) escalation prompts.
 #[tauri::command]
-async fn get_active_escalations(state: tauri::State<'_, AppBridge>) -> Result<Value, String> {
-    state.bridge.call("escalation:getActive", Value::Null).await
+async fn get_active_escalations(state: tauri::State<'_, AppBridge>) -> Result<Value, SemblanceError> {
+    state.bridge()?.call("escalation:getActive", Value::Null).await
 }
 
 // ─── Step 7: Knowledge Moment ───────────────────────────────────────────────
 
 /// Generate a cross-source knowledge moment.
 #[tauri::command]
-async fn generate_knowledge_moment(state: tauri::State<'_, AppBridge>) -> Result<Value, String> {
+async fn generate_knowledge_moment(state: tauri::State<'_, AppBridge>) -> Result<Value, SemblanceError> {
     state
-        .bridge
-        .call("knowledge:generateMoment", Value::Null)
+        .bridge()?
+        .call_with_timeout("knowledge:generateMoment", Value::Null, SIDECAR_GENERATION_TIMEOUT)
         .await
 }
 
@@ -1189,58 +2707,110 @@ async fn generate_digest(
     state: tauri::State<'_, AppBridge>,
     week_start: String,
     week_end: String,
-) -> Result<Value, String> {
+) -> Result<Value, SemblanceError> {
     state
-        .bridge
-        .call(
+        .bridge()?
+        .call_with_timeout(
             "digest:generate",
             serde_json::json!({"week_start": week_start, "week_end": week_end}),
+            SIDECAR_GENERATION_TIMEOUT,
         )
         .await
 }
 
 /// Get the most recent weekly digest.
 #[tauri::command]
-async fn get_latest_digest(state: tauri::State<'_, AppBridge>) -> Result<Value, String> {
-    state.bridge.call("digest:getLatest", Value::Null).await
+async fn get_latest_digest(state: tauri::State<'_, AppBridge>) -> Result<Value, SemblanceError> {
+    state.bridge()?.call("digest:getLatest", Value::Null).await
 }
 
 /// List all generated digests (summaries).
 #[tauri::command]
-async fn list_digests(state: tauri::State<'_, AppBridge>) -> Result<Value, String> {
-    state.bridge.call("digest:list", Value::Null).await
+async fn list_digests(state: tauri::State<'_, AppBridge>) -> Result<Value, SemblanceError> {
+    state.bridge()?.call("digest:list", Value::Null).await
 }
 
 // ─── Network Monitor (Step 8) ──────────────────────────────────────────────
 
 #[tauri::command]
-async fn get_active_connections(state: tauri::State<'_, AppBridge>) -> Result<Value, String> {
-    state.bridge.call("network:getActiveConnections", Value::Null).await
+async fn get_active_connections(state: tauri::State<'_, AppBridge>) -> Result<Value, SemblanceError> {
+    state.bridge()?.call("network:getActiveConnections", Value::Null).await
 }
 
 #[tauri::command]
 async fn get_network_statistics(
     state: tauri::State<'_, AppBridge>,
     period: String,
-) -> Result<Value, String> {
+) -> Result<Value, SemblanceError> {
     state
-        .bridge
+        .bridge()?
         .call("network:getStatistics", serde_json::json!({ "period": period }))
         .await
 }
 
 #[tauri::command]
-async fn get_network_allowlist(state: tauri::State<'_, AppBridge>) -> Result<Value, String> {
-    state.bridge.call("network:getAllowlist", Value::Null).await
+async fn get_network_allowlist(state: tauri::State<'_, AppBridge>) -> Result<Value, SemblanceError> {
+    state.bridge()?.call("network:getAllowlist", Value::Null).await
+}
+
+/// A domain the gateway refuses to contact, even if it is allowlisted.
+/// Blocking a domain also blocks its subdomains.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+struct BlocklistEntry {
+    id: String,
+    domain: String,
+    reason: String,
+    added_at: String,
+    added_by: String,
+}
+
+/// List blocklisted domains, oldest first.
+#[tauri::command]
+async fn get_blocklist(state: tauri::State<'_, AppBridge>) -> Result<Vec<BlocklistEntry>, SemblanceError> {
+    let value = state.bridge()?.call("network:getBlocklist", Value::Null).await?;
+    serde_json::from_value(value).map_err(|e| SemblanceError::Protocol { detail: format!("Invalid blocklist response: {}", e) })
+}
+
+/// Block a domain (and its subdomains). Requests to it are refused by the
+/// gateway and logged as unauthorized attempts. Re-adding updates the reason.
+#[tauri::command]
+async fn add_blocklist_entry(
+    state: tauri::State<'_, AppBridge>,
+    domain: String,
+    reason: Option<String>,
+) -> Result<BlocklistEntry, SemblanceError> {
+    let domain = domain.trim().to_string();
+    if domain.is_empty() {
+        return Err("Domain must not be empty".into());
+    }
+    let value = state
+        .bridge()?
+        .call(
+            "network:addBlocklistEntry",
+            serde_json::json!({ "domain": domain, "reason": reason }),
+        )
+        .await?;
+    serde_json::from_value(value).map_err(|e| SemblanceError::Protocol { detail: format!("Invalid blocklist response: {}", e) })
+}
+
+/// Unblock a domain by entry id or domain name. Returns whether anything was removed.
+#[tauri::command]
+async fn remove_blocklist_entry(state: tauri::State<'_, AppBridge>, id: String) -> Result<bool, SemblanceError> {
+    let value = state
+        .bridge()?
+        .call("network:removeBlocklistEntry", serde_json::json!({ "id": id }))
+        .await?;
+    Ok(value.get("removed").and_then(|v| v.as_bool()).unwrap_or(false))
 }
 
 #[tauri::command]
 async fn get_unauthorized_attempts(
     state: tauri::State<'_, AppBridge>,
     period: Option<String>,
-) -> Result<Value, String> {
+) -> Result<Value, SemblanceError> {
     state
-        .bridge
+        .bridge()?
         .call(
             "network:getUnauthorizedAttempts",
             serde_json::json!({ "period": period }),
@@ -1253,9 +2823,9 @@ async fn get_connection_timeline(
     state: tauri::State<'_, AppBridge>,
     period: String,
     granularity: String,
-) -> Result<Value, String> {
+) -> Result<Value, SemblanceError> {
     state
-        .bridge
+        .bridge()?
         .call(
             "network:getTimeline",
             serde_json::json!({ "period": period, "granularity": granularity }),
@@ -1267,9 +2837,9 @@ async fn get_connection_timeline(
 async fn get_connection_history(
     state: tauri::State<'_, AppBridge>,
     limit: Option<u32>,
-) -> Result<Value, String> {
+) -> Result<Value, SemblanceError> {
     state
-        .bridge
+        .bridge()?
         .call("network:getHistory", serde_json::json!({ "limit": limit }))
         .await
 }
@@ -1280,9 +2850,9 @@ async fn generate_privacy_report(
     start_date: String,
     end_date: String,
     format: String,
-) -> Result<Value, String> {
+) -> Result<Value, SemblanceError> {
     state
-        .bridge
+        .bridge()?
         .call(
             "network:generateReport",
             serde_json::json!({
@@ -1295,9 +2865,9 @@ async fn generate_privacy_report(
 }
 
 #[tauri::command]
-async fn get_network_trust_status(state: tauri::State<'_, AppBridge>) -> Result<Value, String> {
+async fn get_network_trust_status(state: tauri::State<'_, AppBridge>) -> Result<Value, SemblanceError> {
     state
-        .bridge
+        .bridge()?
         .call("network:getTrustStatus", Value::Null)
         .await
 }
@@ -1305,17 +2875,17 @@ async fn get_network_trust_status(state: tauri::State<'_, AppBridge>) -> Result<
 // ─── Task Routing (Step 8) ─────────────────────────────────────────────────
 
 #[tauri::command]
-async fn get_routing_devices(state: tauri::State<'_, AppBridge>) -> Result<Value, String> {
-    state.bridge.call("routing:getDevices", Value::Null).await
+async fn get_routing_devices(state: tauri::State<'_, AppBridge>) -> Result<Value, SemblanceError> {
+    state.bridge()?.call("routing:getDevices", Value::Null).await
 }
 
 #[tauri::command]
 async fn route_task(
     state: tauri::State<'_, AppBridge>,
     task: Value,
-) -> Result<Value, String> {
+) -> Result<Value, SemblanceError> {
     state
-        .bridge
+        .bridge()?
         .call("routing:routeTask", serde_json::json!({ "task": task }))
         .await
 }
@@ -1324,24 +2894,81 @@ async fn route_task(
 async fn assess_task(
     state: tauri::State<'_, AppBridge>,
     task: Value,
-) -> Result<Value, String> {
+) -> Result<Value, SemblanceError> {
     state
-        .bridge
+        .bridge()?
         .call("routing:assessTask", serde_json::json!({ "task": task }))
         .await
 }
 
 // ─── NDJSON Callback Dispatch (Step 9) ────────────────────────────────────────
 
+/// Emit `semblance://first-token` the moment a generation samples its first
+/// token, so the UI can show time-to-first-token while the rest is decoded.
+fn emit_first_token(app: &tauri::AppHandle, kind: &str, session_id: Option<&str>, first_token_ms: u64) {
+    let _ = app.emit("semblance://first-token", serde_json::json!({
+        "kind": kind,
+        "session_id": session_id,
+        "first_token_ms": first_token_ms,
+    }));
+}
+
+/// Emit `semblance://model-load-cancelled` if `e` is a cancelled load of the
+/// `kind` model at `path`; returns `e` for `map_err`.
+fn emit_if_load_cancelled(
+    app: &tauri::AppHandle,
+    kind: &str,
+    path: &str,
+    e: native_runtime::RuntimeError,
+) -> native_runtime::RuntimeError {
+    if e == native_runtime::RuntimeError::LoadCancelled {
+        let _ = app.emit("semblance://model-load-cancelled", serde_json::json!({ "kind": kind, "path": path }));
+    }
+    e
+}
+
+/// Emit `semblance://model-load-cancelled` if `result` failed because its
+/// idle reload of the reasoning model was cancelled. A failed reload keeps the
+/// model's path as `idle_unloaded_model`, so it is read back for the payload.
+async fn report_idle_reload_cancelled<T>(
+    app: &tauri::AppHandle,
+    runtime: &native_runtime::SharedNativeRuntime,
+    result: Result<T, native_runtime::RuntimeError>,
+) -> Result<T, native_runtime::RuntimeError> {
+    match result {
+        Err(e @ native_runtime::RuntimeError::LoadCancelled) => {
+            let path = runtime.read().await.idle_unloaded_model().map(|p| p.display().to_string()).unwrap_or_default();
+            Err(emit_if_load_cancelled(app, "reasoning", &path, e))
+        }
+        result => result,
+    }
+}
+
+/// `native_runtime::ensure_reasoning_resident` for commands and callbacks,
+/// reporting a cancelled reload like an explicit load.
+async fn ensure_reasoning_resident(
+    app: &tauri::AppHandle,
+    runtime: &native_runtime::SharedNativeRuntime,
+) -> Result<(), native_runtime::RuntimeError> {
+    let result = native_runtime::ensure_reasoning_resident(runtime).await;
+    report_idle_reload_cancelled(app, runtime, result).await
+}
+
 /// Dispatch a callback request from the Node.js sidecar to NativeRuntime.
 /// Called when the stdout reader detects a {"type":"callback",...} message.
 ///
 /// LOCKED DECISION: Uses NDJSON callbacks, not Tauri invoke from sidecar.
+///
+/// Generations run on the blocking pool and stop at their next token once
+/// `cancel` is set.
 async fn dispatch_native_callback(
+    app: &tauri::AppHandle,
     runtime: native_runtime::SharedNativeRuntime,
+    scheduler: &Arc<scheduler::GenerationScheduler>,
     method: &str,
     params: Value,
-) -> Result<Value, String> {
+    cancel: Arc<std::sync::atomic::AtomicBool>,
+) -> Result<Value, SemblanceError> {
     // File-based logging — eprintln goes nowhere on Windows GUI apps
     fn log_to_file(msg: &str) {
         use std::io::Write;
@@ -1359,6 +2986,8 @@ async fn dispatch_native_callback(
     match method {
         "native_generate" => {
             log_to_file(&format!("native_generate: parsing request..."));
+            // Keep the context resident between turns of this session_id.
+            let resident_session = params.get("resident_session").and_then(|v| v.as_bool()).unwrap_or(false);
             let request: native_runtime::GenerateRequest =
                 serde_json::from_value(params).map_err(|e| format!("Invalid generate params: {}", e))?;
 
@@ -1367,26 +2996,50 @@ async fn dispatch_native_callback(
             let max_tok = request.max_tokens.unwrap_or(512);
             log_to_file(&format!("native_generate: sys={}chars prompt={}chars max_tokens={}", sys_len, prompt_len, max_tok));
 
-            log_to_file("native_generate: acquiring runtime lock...");
-            let rt = runtime.lock().await;
-            log_to_file("native_generate: lock acquired, calling generate with catch_unwind...");
-
-            let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
-                rt.generate(request)
-            }));
+            log_to_file("native_generate: waiting for generation slot...");
+            let slot = scheduler.acquire_owned("reasoning", sys_len + prompt_len, request.priority).await;
+            ensure_reasoning_resident(app, &runtime).await?;
+            native_runtime::ensure_reasoning_routable(&runtime, &request.model_path)
+                .await
+                .map_err(|e| emit_if_load_cancelled(app, "reasoning", &request.model_path, e))?;
+            log_to_file("native_generate: calling generate on the blocking pool with catch_unwind...");
+
+            let app = app.clone();
+            let result = native_runtime::run_blocking(&runtime, move |rt| {
+                let session_id = request.session_id.clone();
+                std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+                    let hooks = native_runtime::GenerationHooks {
+                        yield_point: &|| {
+                            if slot.yield_if_preempted() {
+                                log_to_file("native_generate: resumed after yielding to a higher-priority request");
+                            }
+                        },
+                        on_first_token: &|ms| emit_first_token(&app, "reasoning", session_id.as_deref(), ms),
+                        cancel: Some(&cancel),
+                    };
+                    match session_id.as_deref() {
+                        Some(id) if resident_session => rt.generate_in_session_with_hooks(id, request, &hooks),
+                        _ => rt.generate_with_hooks(request, &hooks),
+                    }
+                }))
+            })
+            .await?;
 
             match result {
                 Ok(Ok(response)) => {
                     log_to_file(&format!("native_generate: SUCCESS, {} tokens in {}ms", response.tokens_generated, response.duration_ms));
-                    serde_json::to_value(response).map_err(|e| format!("Serialization error: {}", e))
+                    serde_json::to_value(response).map_err(|e| format!("Serialization error: {}", e).into())
                 }
                 Ok(Err(e)) => {
                     log_to_file(&format!("native_generate: ERROR: {}", e));
-                    Err(format!("Generate error: {}", e))
+                    Err(match e {
+                        native_runtime::RuntimeError::Failed(message) => format!("Generate error: {}", message).into(),
+                        typed => typed.into(),
+                    })
                 }
                 Err(_) => {
                     log_to_file("native_generate: PANIC caught by catch_unwind");
-                    Err("Native runtime panicked during generation — the prompt may be too large or malformed".to_string())
+                    Err("Native runtime panicked during generation — the prompt may be too large or malformed".into())
                 }
             }
         }
@@ -1396,7 +3049,7 @@ async fn dispatch_native_callback(
                 serde_json::from_value(params).map_err(|e| format!("Invalid embed params: {}", e))?;
 
             log_to_file(&format!("native_embed: {} inputs", request.input.len()));
-            let rt = runtime.lock().await;
+            let rt = runtime.read().await;
             log_to_file("native_embed: lock acquired, calling embed with catch_unwind...");
 
             let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
@@ -1405,15 +3058,15 @@ async fn dispatch_native_callback(
             match result {
                 Ok(Ok(response)) => {
                     log_to_file(&format!("native_embed: SUCCESS, {} embeddings in {}ms", response.embeddings.len(), response.duration_ms));
-                    serde_json::to_value(response).map_err(|e| format!("Serialization error: {}", e))
+                    serde_json::to_value(response).map_err(|e| format!("Serialization error: {}", e).into())
                 }
                 Ok(Err(e)) => {
                     log_to_file(&format!("native_embed: ERROR: {}", e));
-                    Err(format!("Embed error: {}", e))
+                    Err(format!("Embed error: {}", e).into())
                 }
                 Err(_) => {
                     log_to_file("native_embed: PANIC caught by catch_unwind");
-                    Err("Native runtime panicked during embedding — input text may be too large".to_string())
+                    Err("Native runtime panicked during embedding — input text may be too large".into())
                 }
             }
         }
@@ -1428,9 +3081,10 @@ async fn dispatch_native_callback(
                 .unwrap_or("reasoning");
 
             let path = PathBuf::from(model_path);
-            let mut rt = runtime.lock().await;
+            let mut rt = runtime.write().await;
+            let cancelled = |e| emit_if_load_cancelled(app, model_type, model_path, e);
             if model_type == "embedding" {
-                rt.load_embedding_model(path)?;
+                rt.load_embedding_model(path).map_err(cancelled)?;
             } else if model_type == "fast" {
                 rt.load_fast_model(path)?;
             } else if model_type == "vision" {
@@ -1440,41 +3094,93 @@ async fn dispatch_native_callback(
                     .ok_or("Missing mmproj_path for vision model")?;
                 rt.load_vision_model(path, PathBuf::from(mmproj_path))?;
             } else {
-                rt.load_reasoning_model(path)?;
+                // A layer count, or "auto" to fit the GPU's VRAM. Absent: CPU only.
+                let gpu_layers = match params.get("n_gpu_layers") {
+                    None | Some(serde_json::Value::Null) => None,
+                    Some(v) if v.as_str() == Some("auto") => Some(native_runtime::GpuLayers::Auto),
+                    Some(v) => Some(native_runtime::GpuLayers::Count(
+                        v.as_i64()
+                            .and_then(|n| i32::try_from(n).ok())
+                            .ok_or("n_gpu_layers must be a layer count or \"auto\"")?,
+                    )),
+                };
+                // Context the memory check sizes the KV cache for; absent: GENERATION_N_CTX.
+                let n_ctx = match params.get("n_ctx") {
+                    None | Some(serde_json::Value::Null) => None,
+                    Some(v) => Some(
+                        v.as_u64()
+                            .and_then(|n| u32::try_from(n).ok())
+                            .ok_or("n_ctx must be a token count")?,
+                    ),
+                };
+                // "Load anyway" for this load, without turning the check off for later ones.
+                let skip_memory_check = params.get("skip_memory_check").and_then(|v| v.as_bool()).unwrap_or(false);
+                rt.load_reasoning_model_with_gpu_layers(path, gpu_layers, n_ctx, skip_memory_check).map_err(cancelled)?;
+                return Ok(serde_json::json!({ "status": "loaded", "warmup": rt.last_warmup() }));
             }
             Ok(serde_json::json!({ "status": "loaded" }))
         }
+        "native_tokenize" => {
+            let text = params.get("text").and_then(|v| v.as_str()).ok_or("Missing text parameter")?;
+            let add_bos = params.get("add_bos").and_then(|v| v.as_bool()).unwrap_or(false);
+            ensure_reasoning_resident(app, &runtime).await?;
+            let tokens = runtime.read().await.tokenize(text, add_bos)?;
+            Ok(serde_json::json!({ "tokens": tokens }))
+        }
         "native_status" => {
-            let rt = runtime.lock().await;
+            let rt = runtime.read().await;
             let status_str = match rt.status() {
                 RuntimeStatus::Ready => "ready",
+                RuntimeStatus::EmbeddingOnly => "embedding_only",
                 RuntimeStatus::Loading => "loading",
                 RuntimeStatus::Uninitialized => "uninitialized",
                 RuntimeStatus::Error(_) => "error",
             };
             Ok(serde_json::json!({
                 "status": status_str,
-                "reasoning_model": rt.reasoning_model_path().map(|p| p.display().to_string()),
+                // An idle-unloaded model still serves requests (reloaded on demand).
+                "reasoning_model": rt.reasoning_model_path().or(rt.idle_unloaded_model()).map(|p| p.display().to_string()),
+                "reasoning_idle_unloaded": rt.idle_unloaded_model().is_some(),
+                "can_generate": rt.can_generate(),
+                "can_embed": rt.can_embed(),
                 "embedding_model": rt.embedding_model_path().map(|p| p.display().to_string()),
                 "fast_model": rt.fast_model_path().map(|p| p.display().to_string()),
                 "vision_model": rt.vision_model_path().map(|p| p.display().to_string()),
+                "draft_model": rt.draft_model_path().map(|p| p.display().to_string()),
+                "error": match rt.status() {
+                    RuntimeStatus::Error(e) => Some(e.clone()),
+                    _ => None,
+                },
+                "backend_error": rt.backend_error(),
             }))
         }
         "native_generate_fast" => {
             log_to_file("native_generate_fast: parsing request...");
             let request: native_runtime::GenerateRequest =
                 serde_json::from_value(params).map_err(|e| format!("Invalid generate params: {}", e))?;
-            let rt = runtime.lock().await;
-            let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
-                rt.generate_fast(request)
-            }));
+            let slot = scheduler.acquire_owned("fast", request.prompt.len(), request.priority).await;
+            let app = app.clone();
+            let result = native_runtime::run_blocking(&runtime, move |rt| {
+                let session_id = request.session_id.clone();
+                std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+                    rt.generate_fast_with_hooks(request, &native_runtime::GenerationHooks {
+                        yield_point: &|| {
+                            slot.yield_if_preempted();
+                        },
+                        on_first_token: &|ms| emit_first_token(&app, "fast", session_id.as_deref(), ms),
+                        cancel: Some(&cancel),
+                    })
+                }))
+            })
+            .await?;
             match result {
                 Ok(Ok(response)) => {
                     log_to_file(&format!("native_generate_fast: {} tokens in {}ms", response.tokens_generated, response.duration_ms));
-                    serde_json::to_value(response).map_err(|e| format!("Serialization error: {}", e))
+                    serde_json::to_value(response).map_err(|e| format!("Serialization error: {}", e).into())
                 }
-                Ok(Err(e)) => Err(format!("Fast generate error: {}", e)),
-                Err(_) => Err("Fast runtime panicked".to_string()),
+                Ok(Err(native_runtime::RuntimeError::Failed(message))) => Err(format!("Fast generate error: {}", message).into()),
+                Ok(Err(typed)) => Err(typed.into()),
+                Err(_) => Err("Fast runtime panicked".into()),
             }
         }
         "native_generate_vision" => {
@@ -1485,36 +3191,871 @@ async fn dispatch_native_callback(
                 .ok_or("Missing image_path")?.to_string();
             let max_tokens = params.get("max_tokens").and_then(|v| v.as_u64()).unwrap_or(512) as u32;
 
-            let rt = runtime.lock().await;
-            let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
-                rt.generate_vision(prompt, image_path, max_tokens)
-            }));
-            match result {
-                Ok(Ok(response)) => {
-                    log_to_file(&format!("native_generate_vision: {} tokens in {}ms", response.tokens_generated, response.duration_ms));
-                    serde_json::to_value(response).map_err(|e| format!("Serialization error: {}", e))
-                }
-                Ok(Err(e)) => Err(format!("Vision generate error: {}", e)),
-                Err(_) => Err("Vision runtime panicked".to_string()),
+            let _slot = scheduler.acquire("vision", prompt.len()).await;
+            // Vision needs the write lock — the CLIP context can't be shared.
+            let mut rt = runtime.write().await;
+            let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+                rt.generate_vision(prompt, image_path, max_tokens)
+            }));
+            match result {
+                Ok(Ok(response)) => {
+                    log_to_file(&format!("native_generate_vision: {} tokens in {}ms", response.tokens_generated, response.duration_ms));
+                    serde_json::to_value(response).map_err(|e| format!("Serialization error: {}", e).into())
+                }
+                Ok(Err(e)) => Err(format!("Vision generate error: {}", e).into()),
+                Err(_) => Err("Vision runtime panicked".into()),
+            }
+        }
+        _ => Err(format!("Unknown native callback method: {}", method).into()),
+    }
+}
+
+// ─── Hardware Detection & Runtime Management (Step 9) ───────────────────────
+
+/// Detect hardware profile for model selection, including free space where
+/// models are stored. All detection is local.
+#[tauri::command]
+async fn detect_hardware(
+    settings: tauri::State<'_, settings::SettingsState>,
+) -> Result<hardware::HardwareProfile, SemblanceError> {
+    let model_dir = model_storage_dir(&settings).await;
+    Ok(hardware::detect_hardware(&model_dir))
+}
+
+/// The hardware profile cached since first use: tier, CPU, GPUs, and disk
+/// space where models are stored, with available RAM current. Detects again
+/// only if the model storage directory changed; `detect_hardware` always does.
+#[tauri::command]
+async fn get_hardware_profile(
+    cache: tauri::State<'_, Arc<hardware::HardwareCache>>,
+    settings: tauri::State<'_, settings::SettingsState>,
+) -> Result<hardware::HardwareProfile, SemblanceError> {
+    let model_dir = model_storage_dir(&settings).await;
+    let cache = cache.inner().clone();
+    Ok(tokio::task::spawn_blocking(move || cache.profile(&model_dir))
+        .await
+        .map_err(|e| format!("Hardware detection failed: {}", e))?)
+}
+
+/// Available RAM in MB, re-read on its own (and updated in the cached
+/// profile). Cheap enough to poll for a live memory gauge.
+#[tauri::command]
+async fn refresh_available_memory(
+    cache: tauri::State<'_, Arc<hardware::HardwareCache>>,
+) -> Result<u64, SemblanceError> {
+    Ok(cache.refresh_available_memory())
+}
+
+/// Where models are downloaded: the `model_storage_dir` setting, else the app data dir.
+async fn model_storage_dir(settings: &settings::SettingsState) -> PathBuf {
+    settings.get().await.model_storage_dir.map(PathBuf::from).unwrap_or_else(storage::data_dir)
+}
+
+/// Set the directory whose volume `detect_hardware` reports disk space for,
+/// when models live somewhere other than the app data dir (`null` resets it).
+/// Must be absolute, and no model may be loaded from the old one. Persisted;
+/// returns the directory now in effect.
+#[tauri::command]
+async fn set_model_storage_dir(
+    runtime: tauri::State<'_, native_runtime::SharedNativeRuntime>,
+    settings: tauri::State<'_, settings::SettingsState>,
+    path: Option<String>,
+) -> Result<String, SemblanceError> {
+    if let Some(p) = &path {
+        if !std::path::Path::new(p).is_absolute() {
+            return Err(format!("Model storage directory must be an absolute path: {}", p).into());
+        }
+    }
+    runtime
+        .read()
+        .await
+        .require_no_model_loaded(&native_runtime::ModelKind::ALL, "change the model storage directory")?;
+    settings.update(|s| s.model_storage_dir = path).await?;
+    Ok(model_storage_dir(&settings).await.to_string_lossy().to_string())
+}
+
+/// The directory models are stored in; see `set_model_storage_dir`.
+#[tauri::command]
+async fn get_model_storage_dir(
+    settings: tauri::State<'_, settings::SettingsState>,
+) -> Result<String, SemblanceError> {
+    Ok(model_storage_dir(&settings).await.to_string_lossy().to_string())
+}
+
+/// Get live hardware stats — CPU usage, memory, disk, temperature.
+/// Uses native OS APIs via sysinfo crate. No shell commands.
+#[tauri::command]
+async fn get_live_hardware_stats() -> Result<hardware::LiveHardwareStats, SemblanceError> {
+    Ok(hardware::get_live_stats())
+}
+
+/// Catalog models recommended for `tier` (this machine's detected tier if
+/// omitted), with download sizes and RAM requirements.
+#[tauri::command]
+async fn recommend_models(
+    state: tauri::State<'_, AppBridge>,
+    settings: tauri::State<'_, settings::SettingsState>,
+    tier: Option<String>,
+) -> Result<hardware_report::ModelRecommendations, SemblanceError> {
+    let tier = match tier {
+        Some(tier) => tier,
+        None => hardware::detect_hardware(&model_storage_dir(&settings).await).tier,
+    };
+    fetch_model_recommendations(state.bridge()?, tier).await
+}
+
+/// Model sizes and quantizations this machine can run, best first, from its
+/// RAM, GPU VRAM, CPU features, and free space where models are stored.
+/// Computed locally; no catalog lookup.
+#[tauri::command]
+async fn recommend_local_models(
+    settings: tauri::State<'_, settings::SettingsState>,
+) -> Result<Vec<hardware::ModelRecommendation>, SemblanceError> {
+    let model_dir = model_storage_dir(&settings).await;
+    let profile = tokio::task::spawn_blocking(move || hardware::detect_hardware(&model_dir))
+        .await
+        .map_err(|e| format!("Hardware detection failed: {}", e))?;
+    Ok(hardware::recommend_models(&profile))
+}
+
+async fn fetch_model_recommendations(
+    bridge: &SidecarBridge,
+    tier: String,
+) -> Result<hardware_report::ModelRecommendations, SemblanceError> {
+    let result = bridge
+        .call("hardware:recommendModels", serde_json::json!({ "tier": tier }))
+        .await?;
+    serde_json::from_value(result)
+        .map_err(|e| SemblanceError::Protocol { detail: format!("Invalid model recommendations: {}", e) })
+}
+
+/// Write a hardware report — machine profile, live telemetry (including GPU),
+/// inference backend, and recommended models — to `output_path` as Markdown
+/// (default) or JSON, for onboarding and bug reports. Prompts for a path when
+/// omitted. Returns the path written, or None if the dialog was cancelled. The
+/// report is only written locally; recommendations are left out with a note if
+/// the sidecar isn't running.
+#[tauri::command]
+async fn export_hardware_report(
+    app: tauri::AppHandle,
+    runtime: tauri::State<'_, native_runtime::SharedNativeRuntime>,
+    output_path: Option<String>,
+    format: Option<String>,
+) -> Result<Option<String>, SemblanceError> {
+    let format = hardware_report::ReportFormat::parse(format.as_deref())?;
+    let path = match output_path {
+        Some(p) => PathBuf::from(p),
+        None => {
+            use tauri_plugin_dialog::DialogExt;
+            let picked = app
+                .dialog()
+                .file()
+                .add_filter(if format == hardware_report::ReportFormat::Json { "JSON" } else { "Markdown" }, &[format.extension()])
+                .set_file_name(format!("semblance-hardware-report.{}", format.extension()))
+                .blocking_save_file();
+            match picked {
+                Some(p) => p.into_path().map_err(|e| format!("Invalid save location: {}", e))?,
+                None => return Ok(None),
+            }
+        }
+    };
+
+    let model_dir = model_storage_dir(&app.state::<settings::SettingsState>()).await;
+    let (hardware, live) = tokio::task::spawn_blocking(move || (hardware::detect_hardware(&model_dir), hardware::get_live_stats()))
+        .await
+        .map_err(|e| format!("Hardware detection failed: {}", e))?;
+    let backend = runtime.read().await.backend_info();
+    let bridge = app.state::<AppBridge>();
+    let (recommendations, recommendations_error) = match bridge.bridge() {
+        Ok(bridge) => match fetch_model_recommendations(bridge, hardware.tier.clone()).await {
+            Ok(r) => (Some(r), None),
+            Err(e) => (None, Some(e.to_string())),
+        },
+        Err(e) => (None, Some(e.to_string())),
+    };
+
+    let report = hardware_report::HardwareReport {
+        app_version: env!("CARGO_PKG_VERSION").to_string(),
+        hardware,
+        live,
+        backend,
+        recommendations,
+        recommendations_error,
+    };
+    let contents = hardware_report::render(&report, format)?;
+    if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+        std::fs::create_dir_all(parent).map_err(|e| format!("Failed to create report dir: {}", e))?;
+    }
+    std::fs::write(&path, contents).map_err(|e| format!("Failed to write hardware report: {}", e))?;
+    Ok(Some(path.display().to_string()))
+}
+
+// ─── Storage ────────────────────────────────────────────────────────────────
+
+/// Report size, file count, and last-modified time of each persisted artifact
+/// category under the app data dir. Read-only — clearing uses each category's purge command.
+#[tauri::command]
+async fn get_storage_breakdown() -> Result<storage::StorageBreakdown, SemblanceError> {
+    tauri::async_runtime::spawn_blocking(|| storage::storage_breakdown(&storage::data_dir()))
+        .await
+        .map_err(|e| SemblanceError::Other { message: format!("Storage scan failed: {}", e) })
+}
+
+// ─── Native Runtime Commands ────────────────────────────────────────────────
+
+/// Time the embedding model on `batch_size` synthetic inputs of `text_len`
+/// characters: embeddings/sec, per-vector latency, and the sub-batch size used.
+/// Use it to estimate indexing time for a corpus.
+#[tauri::command]
+async fn benchmark_embedding(
+    runtime: tauri::State<'_, native_runtime::SharedNativeRuntime>,
+    batch_size: u32,
+    text_len: u32,
+) -> Result<native_runtime::EmbeddingBenchmark, SemblanceError> {
+    Ok(native_runtime::run_blocking(&runtime, move |rt| rt.benchmark_embedding(batch_size, text_len)).await??)
+}
+
+/// Zero-shot classify a text against candidate labels using the embedding model.
+/// Returns labels ranked by cosine similarity. No reasoning model required.
+#[tauri::command]
+async fn classify_text(
+    runtime: tauri::State<'_, native_runtime::SharedNativeRuntime>,
+    text: String,
+    labels: Vec<String>,
+) -> Result<Vec<native_runtime::LabelScore>, SemblanceError> {
+    let rt = runtime.read().await;
+    rt.classify_text(&text, &labels).map_err(SemblanceError::from)
+}
+
+/// Pick one of `choices` as the reasoning model's answer to `prompt` by scoring
+/// each choice's log-probability — no sampling, so the same input always gives
+/// the same ranking. For routing/triage steps with a fixed set of outcomes.
+#[tauri::command]
+async fn classify_choice(
+    app: tauri::AppHandle,
+    runtime: tauri::State<'_, native_runtime::SharedNativeRuntime>,
+    scheduler: tauri::State<'_, Arc<scheduler::GenerationScheduler>>,
+    prompt: String,
+    choices: Vec<String>,
+) -> Result<native_runtime::ChoiceClassification, SemblanceError> {
+    let _slot = scheduler.acquire("reasoning", prompt.len()).await;
+    ensure_reasoning_resident(&app, &runtime).await?;
+    Ok(native_runtime::run_blocking(&runtime, move |rt| {
+        std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| rt.classify_choice(&prompt, &choices)))
+            .unwrap_or_else(|_| Err("Native runtime panicked during classification".into()))
+    })
+    .await??)
+}
+
+/// Generate with the reasoning model in a resident session: the context is
+/// kept between calls, so a follow-up turn that extends the previous prompt
+/// only prefills the new tokens. Each live session pins a KV cache sized to
+/// its request's `n_ctx` in memory until `reset_session`; at most four are
+/// kept (least recently used are evicted).
+#[tauri::command]
+async fn generate_in_session(
+    app: tauri::AppHandle,
+    runtime: tauri::State<'_, native_runtime::SharedNativeRuntime>,
+    scheduler: tauri::State<'_, Arc<scheduler::GenerationScheduler>>,
+    session_id: String,
+    request: native_runtime::GenerateRequest,
+) -> Result<native_runtime::GenerateResponse, SemblanceError> {
+    let prompt_chars = request.prompt.len() + request.system_prompt.as_ref().map_or(0, |s| s.len());
+    let _slot = scheduler.acquire_with_priority("reasoning", prompt_chars, request.priority).await;
+    ensure_reasoning_resident(&app, &runtime).await?;
+    let rt = runtime.read().await;
+    Ok(std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| rt.generate_in_session(&session_id, request)))
+        .unwrap_or_else(|_| Err("Native runtime panicked during generation".into()))?)
+}
+
+/// Drop a resident session and free its context. Returns false if there was none.
+#[tauri::command]
+async fn reset_session(
+    runtime: tauri::State<'_, native_runtime::SharedNativeRuntime>,
+    session_id: String,
+) -> Result<bool, SemblanceError> {
+    Ok(runtime.read().await.reset_session(&session_id))
+}
+
+/// Convert a JSON Schema to a GBNF grammar for a request's `grammar` field, so
+/// the output is JSON matching the schema.
+#[tauri::command]
+fn json_schema_to_gbnf(schema: serde_json::Value) -> Result<String, SemblanceError> {
+    if !schema.is_object() {
+        return Err("JSON Schema must be an object".into());
+    }
+    Ok(grammar::json_schema_to_gbnf(&schema))
+}
+
+/// One-shot "is inference working" check of the loaded native models.
+/// Returns pass/fail/skipped per component (reasoning, embedding).
+#[tauri::command]
+async fn self_test_runtime(
+    runtime: tauri::State<'_, native_runtime::SharedNativeRuntime>,
+    scheduler: tauri::State<'_, Arc<scheduler::GenerationScheduler>>,
+) -> Result<native_runtime::SelfTestReport, SemblanceError> {
+    let _slot = scheduler.acquire("self_test", 0).await;
+    let rt = runtime.read().await;
+    Ok(rt.self_test())
+}
+
+/// Decode a few throwaway tokens on the loaded reasoning model so GPU kernels
+/// are compiled before the first real message. Returns how long it took, the
+/// cold-start cost of this machine.
+#[tauri::command]
+async fn warmup_runtime(
+    runtime: tauri::State<'_, native_runtime::SharedNativeRuntime>,
+    scheduler: tauri::State<'_, Arc<scheduler::GenerationScheduler>>,
+) -> Result<native_runtime::ModelWarmup, SemblanceError> {
+    let _slot = scheduler.acquire("warmup", 0).await;
+    let rt = runtime.read().await;
+    Ok(rt.warmup()?)
+}
+
+/// Run the warmup automatically after every reasoning model load (default
+/// off). The result is in `native_load_model`'s response. Persisted.
+#[tauri::command]
+async fn set_warmup_after_load(
+    runtime: tauri::State<'_, native_runtime::SharedNativeRuntime>,
+    settings: tauri::State<'_, settings::SettingsState>,
+    enabled: bool,
+) -> Result<bool, SemblanceError> {
+    settings.update(|s| s.warmup_after_load = enabled).await?;
+    runtime.write().await.set_warmup_after_load(enabled);
+    Ok(enabled)
+}
+
+/// Load reasoning models even when their estimated weights and KV cache
+/// exceed free memory (default off: such loads fail with
+/// `insufficient_memory`). For users who opt out permanently; a single
+/// "load anyway" is `select_model`'s `skip_memory_check`. Persisted.
+#[tauri::command]
+async fn set_skip_memory_check(
+    runtime: tauri::State<'_, native_runtime::SharedNativeRuntime>,
+    settings: tauri::State<'_, settings::SettingsState>,
+    enabled: bool,
+) -> Result<bool, SemblanceError> {
+    settings.update(|s| s.skip_memory_check = enabled).await?;
+    runtime.write().await.set_skip_memory_check(enabled);
+    Ok(enabled)
+}
+
+/// Render the loaded reasoning model's chat template with `messages` (or
+/// sample turns) to catch broken templates before they fail mid-conversation.
+/// Returns the rendered prompt, or `ok: false` with the template error.
+#[tauri::command]
+async fn test_chat_template(
+    runtime: tauri::State<'_, native_runtime::SharedNativeRuntime>,
+    messages: Option<Vec<native_runtime::ChatMessage>>,
+) -> Result<native_runtime::ChatTemplateCheck, SemblanceError> {
+    let rt = runtime.read().await;
+    rt.test_chat_template(messages).map_err(SemblanceError::from)
+}
+
+/// Summarize a document longer than the context window: chunk it by token count,
+/// summarize each chunk with the reasoning model, then summarize the summaries.
+/// Emits `semblance://summarize-progress` after each step. `target_tokens`
+/// defaults to 512.
+#[tauri::command]
+async fn summarize_document(
+    app: tauri::AppHandle,
+    runtime: tauri::State<'_, native_runtime::SharedNativeRuntime>,
+    scheduler: tauri::State<'_, Arc<scheduler::GenerationScheduler>>,
+    text: String,
+    target_tokens: Option<u32>,
+) -> Result<summarize::DocumentSummary, SemblanceError> {
+    let result = summarize::summarize_document(&runtime, &scheduler, &text, target_tokens.unwrap_or(512), |progress| {
+        let _ = app.emit("semblance://summarize-progress", &progress);
+    })
+    .await;
+    Ok(report_idle_reload_cancelled(&app, &runtime, result).await?)
+}
+
+/// Where the loaded reasoning model's layers landed (GPU vs CPU) — for tuning
+/// partial offload. Errors if no reasoning model is loaded.
+#[tauri::command]
+async fn get_layer_placement(
+    runtime: tauri::State<'_, native_runtime::SharedNativeRuntime>,
+) -> Result<native_runtime::LayerPlacement, SemblanceError> {
+    let rt = runtime.read().await;
+    rt.layer_placement().map_err(SemblanceError::from)
+}
+
+/// Name, quantization, and hyperparameters (trained context, vocab, layers) of
+/// the loaded reasoning model, or null if none is loaded.
+#[tauri::command]
+async fn get_model_info(
+    runtime: tauri::State<'_, native_runtime::SharedNativeRuntime>,
+) -> Result<Option<native_runtime::ModelInfo>, SemblanceError> {
+    Ok(runtime.read().await.model_info())
+}
+
+/// Native backend capabilities: GPU offload, what the reasoning model actually
+/// runs on (backend, layers offloaded and GPU name, as llama.cpp reported
+/// them), and flash attention support, its default, and whether the last
+/// generation context actually used it (with the reason if it fell back). See
+/// `native_runtime::FlashAttentionInfo` for which backends support it.
+#[tauri::command]
+async fn get_backend_info(
+    runtime: tauri::State<'_, native_runtime::SharedNativeRuntime>,
+) -> Result<native_runtime::BackendInfo, SemblanceError> {
+    Ok(runtime.read().await.backend_info())
+}
+
+/// Set the flash attention default for native generation (`null` = llama.cpp's
+/// default, off). Requests can override it with `flash_attention`. Persisted;
+/// returns the backend info with the new setting.
+#[tauri::command]
+async fn set_flash_attention(
+    runtime: tauri::State<'_, native_runtime::SharedNativeRuntime>,
+    settings: tauri::State<'_, settings::SettingsState>,
+    enabled: Option<bool>,
+) -> Result<native_runtime::BackendInfo, SemblanceError> {
+    settings.update(|s| s.flash_attention = enabled).await?;
+    let mut rt = runtime.write().await;
+    rt.set_flash_attention(enabled);
+    Ok(rt.backend_info())
+}
+
+/// Cap the prompt size native generation accepts, in tokens (`null` = the
+/// reasoning model's training context). Larger prompts fail with the actual
+/// and allowed counts unless the request sets `truncate_prompt`. Persisted.
+#[tauri::command]
+async fn set_max_prompt_tokens(
+    runtime: tauri::State<'_, native_runtime::SharedNativeRuntime>,
+    settings: tauri::State<'_, settings::SettingsState>,
+    n: Option<u32>,
+) -> Result<native_runtime::PromptTokenLimit, SemblanceError> {
+    if n == Some(0) {
+        return Err("max_prompt_tokens must be at least 1".into());
+    }
+    settings.update(|s| s.max_prompt_tokens = n).await?;
+    let mut rt = runtime.write().await;
+    rt.set_max_prompt_tokens(n);
+    Ok(rt.prompt_token_limit())
+}
+
+/// The prompt size limit native generation enforces right now.
+#[tauri::command]
+async fn get_max_prompt_tokens(
+    runtime: tauri::State<'_, native_runtime::SharedNativeRuntime>,
+) -> Result<native_runtime::PromptTokenLimit, SemblanceError> {
+    Ok(runtime.read().await.prompt_token_limit())
+}
+
+/// Keep up to `max_resident` reasoning models loaded (1–4), evicting the least
+/// recently used past that count or past `budget_mb` of weights. Generation
+/// requests then run on the model their `model_path` names, loading it on
+/// demand. 1 keeps only the active model. Persisted.
+#[tauri::command]
+async fn set_resident_models(
+    runtime: tauri::State<'_, native_runtime::SharedNativeRuntime>,
+    settings: tauri::State<'_, settings::SettingsState>,
+    max_resident: u32,
+    budget_mb: Option<u64>,
+) -> Result<native_runtime::ResidentModels, SemblanceError> {
+    if max_resident == 0 || max_resident > native_runtime::MAX_RESIDENT_REASONING {
+        return Err(format!("max_resident must be between 1 and {}", native_runtime::MAX_RESIDENT_REASONING).into());
+    }
+    if budget_mb == Some(0) {
+        return Err("budget_mb must be at least 1".into());
+    }
+    settings.update(|s| {
+        s.max_resident_models = Some(max_resident);
+        s.resident_models_budget_mb = budget_mb;
+    }).await?;
+    Ok(runtime.write().await.set_resident_models(max_resident, budget_mb))
+}
+
+/// Reasoning models currently loaded, active first, and the resident limits.
+#[tauri::command]
+async fn get_resident_models(
+    runtime: tauri::State<'_, native_runtime::SharedNativeRuntime>,
+) -> Result<native_runtime::ResidentModels, SemblanceError> {
+    Ok(runtime.read().await.resident_models())
+}
+
+/// Largest context length the model at `model_path` can afford in free RAM,
+/// from its KV-cache cost per token, with the memory math. Models run CPU-only,
+/// so VRAM isn't counted. Errors with the shortfall if even 512 tokens won't fit.
+#[tauri::command]
+async fn recommend_context_length(
+    runtime: tauri::State<'_, native_runtime::SharedNativeRuntime>,
+    model_path: String,
+) -> Result<native_runtime::ContextRecommendation, SemblanceError> {
+    let path = PathBuf::from(&model_path);
+    let rt = runtime.read().await;
+    rt.recommend_context_length(&path, hardware::available_memory_bytes()).map_err(SemblanceError::from)
+}
+
+/// How many sequences the loaded models can run in parallel per context with
+/// the RAM free right now, and the memory each sequence costs — for sizing
+/// batch generation and embedding. `embed` clamps its sub-batches to the
+/// embedding limit and reports `sequences_clamped` when it did.
+#[tauri::command]
+async fn get_parallel_limits(
+    runtime: tauri::State<'_, native_runtime::SharedNativeRuntime>,
+) -> Result<native_runtime::ParallelLimits, SemblanceError> {
+    let rt = runtime.read().await;
+    Ok(rt.parallel_limits(hardware::available_memory_bytes()))
+}
+
+/// Architecture and tensor quantization types of the GGUF at `model_path`, read
+/// from its header without loading it. `unsupported_types` lists the types this
+/// build can't run.
+#[tauri::command]
+async fn inspect_model(model_path: String) -> Result<gguf::GgufInspection, SemblanceError> {
+    tokio::task::spawn_blocking(move || gguf::inspect(&PathBuf::from(model_path)))
+        .await
+        .map_err(|e| format!("Inspection task failed: {}", e))?
+        .map_err(SemblanceError::from)
+}
+
+/// Whether the file the user picked at `model_path` is a GGUF, and its
+/// architecture, training context, quantization and size, from the header
+/// alone. Call on file pick to reject a wrong file before any load.
+#[tauri::command]
+async fn probe_model_file(model_path: String) -> Result<native_runtime::GgufProbe, SemblanceError> {
+    tokio::task::spawn_blocking(move || native_runtime::NativeRuntime::probe_gguf(&PathBuf::from(model_path)))
+        .await
+        .map_err(|e| format!("Probe task failed: {}", e))?
+        .map_err(SemblanceError::from)
+}
+
+/// Check that `model_path` is a GGUF this build can load, before any load
+/// attempt. Errors name the unsupported quantization types found.
+#[tauri::command]
+async fn validate_model_file(model_path: String) -> Result<gguf::GgufInspection, SemblanceError> {
+    let inspection = inspect_model(model_path).await?;
+    match inspection.unsupported_reason() {
+        Some(reason) => Err(reason.into()),
+        None => Ok(inspection),
+    }
+}
+
+/// Re-hash downloaded model files (all of them, or just `model_ids`) against
+/// the catalog's SHA-256. Emits `semblance://manifest-verify-progress` per file
+/// while hashing; resolves with a per-file verified/failed summary. Only one
+/// verification runs at a time; `cancel_manifest_verification` stops it.
+#[tauri::command]
+async fn verify_model_manifest(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, AppBridge>,
+    control: tauri::State<'_, model_verify::VerifyControl>,
+    model_ids: Option<Vec<String>>,
+) -> Result<model_verify::ManifestVerification, SemblanceError> {
+    let run = control.begin()?;
+    let value = state
+        .bridge()?
+        .call("model:getManifest", serde_json::json!({ "model_ids": model_ids }))
+        .await?;
+    let entries: Vec<model_verify::ManifestEntry> =
+        serde_json::from_value(value).map_err(|e| SemblanceError::Protocol { detail: format!("Invalid manifest response: {}", e) })?;
+
+    tokio::task::spawn_blocking(move || {
+        // A progress event per 4MB chunk would flood the webview on multi-GB files.
+        let mut last_emit: Option<(usize, std::time::Instant)> = None;
+        model_verify::verify_manifest(&entries, run.cancel_flag(), |progress| {
+            let file_done = progress.bytes_hashed == progress.total_bytes;
+            let due = match last_emit {
+                Some((index, at)) => index != progress.file_index || at.elapsed() >= std::time::Duration::from_millis(250),
+                None => true,
+            };
+            if due || file_done {
+                last_emit = Some((progress.file_index, std::time::Instant::now()));
+                let _ = app.emit("semblance://manifest-verify-progress", progress);
             }
-        }
-        _ => Err(format!("Unknown native callback method: {}", method)),
-    }
+        })
+    })
+    .await
+    .map_err(|e| SemblanceError::Other { message: format!("Verification task failed: {}", e) })
 }
 
-// ─── Hardware Detection & Runtime Management (Step 9) ───────────────────────
+/// Abort the reasoning or embedding model load in progress. The runtime keeps
+/// the models it had before, the load fails with "Model load cancelled", and
+/// `semblance://model-load-cancelled` is emitted, also for loads a request
+/// triggered (an idle reload or a routed warm model). Returns false if no load
+/// is in progress.
+#[tauri::command]
+async fn cancel_model_load(load_cancel: tauri::State<'_, native_runtime::LoadCancel>) -> Result<bool, SemblanceError> {
+    Ok(load_cancel.cancel())
+}
 
-/// Detect hardware profile for model selection. All detection is local.
+/// Stop the running manifest verification after its current chunk. Returns
+/// false if none is running.
 #[tauri::command]
-async fn detect_hardware() -> Result<hardware::HardwareProfile, String> {
-    Ok(hardware::detect_hardware())
+async fn cancel_manifest_verification(control: tauri::State<'_, model_verify::VerifyControl>) -> Result<bool, SemblanceError> {
+    Ok(control.cancel())
 }
 
-/// Get live hardware stats — CPU usage, memory, disk, temperature.
-/// Uses native OS APIs via sysinfo crate. No shell commands.
+/// Write a self-contained JSON bundle for reproducing a session's most recent
+/// native generation: resolved prompt, sampler config and seed, model id and
+/// catalog SHA-256, backend info, and the produced output. Saved under
+/// `<data dir>/repros/`; returns `{path, bundle}`. Generations sent without a
+/// session id are recorded under "default".
 #[tauri::command]
-async fn get_live_hardware_stats() -> Result<hardware::LiveHardwareStats, String> {
-    Ok(hardware::get_live_stats())
+async fn capture_generation_repro(
+    state: tauri::State<'_, AppBridge>,
+    runtime: tauri::State<'_, native_runtime::SharedNativeRuntime>,
+    session_id: String,
+) -> Result<Value, SemblanceError> {
+    let (record, native_backend) = {
+        let rt = runtime.read().await;
+        let record = rt.generation_record(&session_id)?;
+        let native_backend = serde_json::json!({
+            "engine": "bitnet.cpp",
+            "backend_error": rt.backend_error(),
+            "layer_placement": rt.layer_placement().ok(),
+            "draft_model": rt.draft_model_path().map(|p| p.display().to_string()),
+        });
+        (record, native_backend)
+    };
+
+    // Model files are stored as `<model id>.gguf`.
+    let model_id = record
+        .model_path
+        .as_deref()
+        .and_then(|p| std::path::Path::new(p).file_stem())
+        .map(|s| s.to_string_lossy().into_owned());
+    let catalog_entry = match &model_id {
+        Some(id) => state
+            .bridge()?
+            .call("model:lookup", serde_json::json!({ "model_id": id }))
+            .await
+            .unwrap_or(Value::Null),
+        None => Value::Null,
+    };
+    let inference_backend = state
+        .bridge()?
+        .call("get_active_inference_backend", Value::Null)
+        .await
+        .unwrap_or_else(|e| serde_json::json!({ "error": e }));
+
+    let captured_at_ms = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0);
+    let bundle = serde_json::json!({
+        "format": "semblance-generation-repro",
+        "version": 1,
+        "captured_at_ms": captured_at_ms,
+        "app_version": env!("CARGO_PKG_VERSION"),
+        "session_id": session_id,
+        "model": {
+            "id": model_id,
+            "path": record.model_path,
+            "sha256": catalog_entry.get("sha256").cloned().unwrap_or(Value::Null),
+        },
+        "backend": {
+            "native": native_backend,
+            "inference": inference_backend,
+        },
+        "generation": record,
+    });
+
+    let file_stem: String = session_id
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '-' || c == '_' { c } else { '_' })
+        .collect();
+    let dir = storage::data_dir().join("repros");
+    std::fs::create_dir_all(&dir).map_err(|e| format!("Failed to create repro dir: {}", e))?;
+    let path = dir.join(format!("repro-{}-{}.json", file_stem, captured_at_ms));
+    let json = serde_json::to_string_pretty(&bundle)
+        .map_err(|e| format!("Failed to serialize repro bundle: {}", e))?;
+    std::fs::write(&path, json).map_err(|e| format!("Failed to write repro bundle: {}", e))?;
+
+    Ok(serde_json::json!({ "path": path.display().to_string(), "bundle": bundle }))
+}
+
+/// Where a session's sampler RNG stream is, so a paused agent run can be resumed
+/// with the same random sequence. See `native_runtime::SamplerState` for the
+/// format; only generations sent with an explicit `session_id` keep one.
+#[tauri::command]
+async fn get_sampler_state(
+    runtime: tauri::State<'_, native_runtime::SharedNativeRuntime>,
+    session_id: String,
+) -> Result<native_runtime::SamplerState, SemblanceError> {
+    runtime.read().await.sampler_state(&session_id).map_err(SemblanceError::from)
+}
+
+/// Restore a state from `get_sampler_state`; the session's next generation
+/// continues from it. Outputs match the original run only with the same sampler
+/// configuration and prompts.
+#[tauri::command]
+async fn set_sampler_state(
+    runtime: tauri::State<'_, native_runtime::SharedNativeRuntime>,
+    session_id: String,
+    state: native_runtime::SamplerState,
+) -> Result<(), SemblanceError> {
+    runtime.read().await.set_sampler_state(&session_id, state).map_err(SemblanceError::from)
+}
+
+/// Free context in a resident session without discarding it: removes the oldest
+/// tokens beyond `keep_last_tokens`, keeping the system prefix. Returns the new
+/// KV cache occupancy. Errors for unknown sessions.
+#[tauri::command]
+async fn trim_session(
+    runtime: tauri::State<'_, native_runtime::SharedNativeRuntime>,
+    session_id: String,
+    keep_last_tokens: u32,
+) -> Result<native_runtime::SessionOccupancy, SemblanceError> {
+    let rt = runtime.read().await;
+    rt.trim_session(&session_id, keep_last_tokens as usize).map_err(SemblanceError::from)
+}
+
+/// Load a small draft model for speculative decoding. While loaded, reasoning
+/// generations propose tokens with the draft and verify them with the reasoning
+/// model; responses then carry a `speculative` block with timing against the last
+/// plain run. Errors if no reasoning model is loaded or the vocabularies differ.
+#[tauri::command]
+async fn load_draft_model(
+    runtime: tauri::State<'_, native_runtime::SharedNativeRuntime>,
+    path: String,
+) -> Result<(), SemblanceError> {
+    let mut rt = runtime.write().await;
+    rt.load_draft_model(PathBuf::from(path)).map_err(SemblanceError::from)
+}
+
+/// Unload the draft model; reasoning generations go back to plain decoding.
+#[tauri::command]
+async fn unload_draft_model(
+    runtime: tauri::State<'_, native_runtime::SharedNativeRuntime>,
+) -> Result<(), SemblanceError> {
+    let mut rt = runtime.write().await;
+    rt.unload_draft_model();
+    Ok(())
+}
+
+/// Whether a model is resident in the `kind` slot ("reasoning", "embedding",
+/// "fast", "vision" or "draft"), so the UI can disable controls that need the
+/// model unloaded.
+#[tauri::command]
+async fn is_model_loaded(
+    runtime: tauri::State<'_, native_runtime::SharedNativeRuntime>,
+    kind: String,
+) -> Result<bool, SemblanceError> {
+    let kind = native_runtime::ModelKind::parse(&kind)?;
+    Ok(runtime.read().await.is_model_loaded(kind))
+}
+
+/// Precondition check before an operation that can't run with a model loaded.
+/// Errors with "unload the model first" if any of `kinds` (default: every
+/// slot) is loaded. `operation` names the operation in the error.
+#[tauri::command]
+async fn require_no_model_loaded(
+    runtime: tauri::State<'_, native_runtime::SharedNativeRuntime>,
+    kinds: Option<Vec<String>>,
+    operation: Option<String>,
+) -> Result<(), SemblanceError> {
+    let kinds = match kinds {
+        Some(kinds) => kinds
+            .iter()
+            .map(|k| native_runtime::ModelKind::parse(k))
+            .collect::<Result<Vec<_>, _>>()?,
+        None => native_runtime::ModelKind::ALL.to_vec(),
+    };
+    let operation = operation.unwrap_or_else(|| "do this".to_string());
+    runtime.read().await.require_no_model_loaded(&kinds, &operation).map_err(SemblanceError::from)
+}
+
+/// Load just the tokenizer of the GGUF at `path` (vocab-only, no weights —
+/// a few MB to a few tens of MB) so `tokenizer_count` works before the full
+/// model is loaded, or on machines too small to run it. Doesn't enable
+/// generation. Replaces any previously loaded tokenizer.
+#[tauri::command]
+async fn load_tokenizer(
+    runtime: tauri::State<'_, native_runtime::SharedNativeRuntime>,
+    path: String,
+) -> Result<native_runtime::TokenizerInfo, SemblanceError> {
+    let mut rt = runtime.write().await;
+    rt.load_tokenizer(PathBuf::from(path)).map_err(SemblanceError::from)
+}
+
+/// Token count of `text` with the tokenizer loaded by `load_tokenizer`.
+#[tauri::command]
+async fn tokenizer_count(
+    runtime: tauri::State<'_, native_runtime::SharedNativeRuntime>,
+    text: String,
+) -> Result<usize, SemblanceError> {
+    let rt = runtime.read().await;
+    rt.tokenizer_count(&text).map_err(SemblanceError::from)
+}
+
+/// Token count of `text` with the reasoning model's tokenizer (no BOS, no chat
+/// template), to check a document fits the context before generating.
+#[tauri::command]
+async fn count_tokens(
+    app: tauri::AppHandle,
+    runtime: tauri::State<'_, native_runtime::SharedNativeRuntime>,
+    text: String,
+) -> Result<usize, SemblanceError> {
+    ensure_reasoning_resident(&app, &runtime).await?;
+    let rt = runtime.read().await;
+    rt.count_tokens(&text).map_err(SemblanceError::from)
+}
+
+/// Token ids of `text` with the reasoning model's tokenizer; `add_bos` (default
+/// false) prepends BOS as generation does. For debugging prompt formatting.
+#[tauri::command]
+async fn tokenize(
+    app: tauri::AppHandle,
+    runtime: tauri::State<'_, native_runtime::SharedNativeRuntime>,
+    text: String,
+    add_bos: Option<bool>,
+) -> Result<Vec<i32>, SemblanceError> {
+    ensure_reasoning_resident(&app, &runtime).await?;
+    let rt = runtime.read().await;
+    rt.tokenize(&text, add_bos.unwrap_or(false)).map_err(SemblanceError::from)
+}
+
+/// Text of `tokens` with the reasoning model's vocabulary; the inverse of `tokenize`.
+#[tauri::command]
+async fn detokenize(
+    app: tauri::AppHandle,
+    runtime: tauri::State<'_, native_runtime::SharedNativeRuntime>,
+    tokens: Vec<i32>,
+) -> Result<String, SemblanceError> {
+    ensure_reasoning_resident(&app, &runtime).await?;
+    let rt = runtime.read().await;
+    rt.detokenize(&tokens).map_err(SemblanceError::from)
+}
+
+/// Set how many generations may run on the native runtime at once (1–8).
+/// Each extra slot costs one more context's KV cache; single-model hardware should stay at 1.
+#[tauri::command]
+async fn set_max_concurrent_generations(
+    scheduler: tauri::State<'_, Arc<scheduler::GenerationScheduler>>,
+    n: usize,
+) -> Result<usize, SemblanceError> {
+    scheduler.set_max_concurrent(n).map_err(SemblanceError::from)
+}
+
+/// Set the priority ("low", "normal" or "high") of generation requests that
+/// don't set their own. The sidecar marks chat turns "high", so lowering the
+/// default lets chat preempt background work between tokens.
+#[tauri::command]
+async fn set_default_generation_priority(
+    scheduler: tauri::State<'_, Arc<scheduler::GenerationScheduler>>,
+    level: String,
+) -> Result<scheduler::GenerationPriority, SemblanceError> {
+    let priority = scheduler::GenerationPriority::parse(&level)?;
+    scheduler.set_default_priority(priority);
+    Ok(priority)
+}
+
+/// Show running vs. waiting generation requests (priority, then FIFO order).
+#[tauri::command]
+async fn get_generation_queue(
+    scheduler: tauri::State<'_, Arc<scheduler::GenerationScheduler>>,
+) -> Result<scheduler::GenerationQueueSnapshot, SemblanceError> {
+    Ok(scheduler.snapshot())
+}
+
+/// Sampler modes the native backend supports, with each parameter's range and
+/// default, so the settings UI doesn't hardcode them.
+#[tauri::command]
+async fn get_sampler_modes() -> Result<Vec<native_runtime::SamplerModeSpec>, SemblanceError> {
+    Ok(native_runtime::sampler_modes())
 }
 
 // ─── Founding Member Activation (Deep Link) ─────────────────────────────────
@@ -1525,9 +4066,9 @@ async fn get_live_hardware_stats() -> Result<hardware::LiveHardwareStats, String
 async fn activate_founding_token(
     state: tauri::State<'_, AppBridge>,
     token: String,
-) -> Result<Value, String> {
+) -> Result<Value, SemblanceError> {
     state
-        .bridge
+        .bridge()?
         .call("license:activate_founding", serde_json::json!({ "token": token }))
         .await
 }
@@ -1538,18 +4079,18 @@ async fn activate_founding_token(
 async fn activate_license_key(
     state: tauri::State<'_, AppBridge>,
     key: String,
-) -> Result<Value, String> {
+) -> Result<Value, SemblanceError> {
     state
-        .bridge
+        .bridge()?
         .call("license:activate_key", serde_json::json!({ "key": key }))
         .await
 }
 
 /// Get current license status from the sidecar bridge.
 #[tauri::command]
-async fn get_license_status(state: tauri::State<'_, AppBridge>) -> Result<Value, String> {
+async fn get_license_status(state: tauri::State<'_, AppBridge>) -> Result<Value, SemblanceError> {
     state
-        .bridge
+        .bridge()?
         .call("license:status", serde_json::json!({}))
         .await
 }
@@ -1563,8 +4104,8 @@ async fn list_conversations(
     offset: Option<u32>,
     pinned_only: Option<bool>,
     search: Option<String>,
-) -> Result<Value, String> {
-    state.bridge.call("list_conversations", serde_json::json!({
+) -> Result<Value, SemblanceError> {
+    state.bridge()?.call("list_conversations", serde_json::json!({
         "limit": limit, "offset": offset, "pinnedOnly": pinned_only, "search": search,
     })).await
 }
@@ -1573,24 +4114,24 @@ async fn list_conversations(
 async fn get_conversation(
     state: tauri::State<'_, AppBridge>,
     id: String,
-) -> Result<Value, String> {
-    state.bridge.call("get_conversation", serde_json::json!({ "id": id })).await
+) -> Result<Value, SemblanceError> {
+    state.bridge()?.call("get_conversation", serde_json::json!({ "id": id })).await
 }
 
 #[tauri::command]
 async fn create_conversation(
     state: tauri::State<'_, AppBridge>,
     first_message: Option<String>,
-) -> Result<Value, String> {
-    state.bridge.call("create_conversation", serde_json::json!({ "first_message": first_message })).await
+) -> Result<Value, SemblanceError> {
+    state.bridge()?.call("create_conversation", serde_json::json!({ "first_message": first_message })).await
 }
 
 #[tauri::command]
 async fn delete_conversation(
     state: tauri::State<'_, AppBridge>,
     id: String,
-) -> Result<Value, String> {
-    state.bridge.call("delete_conversation", serde_json::json!({ "id": id })).await
+) -> Result<Value, SemblanceError> {
+    state.bridge()?.call("delete_conversation", serde_json::json!({ "id": id })).await
 }
 
 #[tauri::command]
@@ -1598,24 +4139,24 @@ async fn rename_conversation(
     state: tauri::State<'_, AppBridge>,
     id: String,
     title: String,
-) -> Result<Value, String> {
-    state.bridge.call("rename_conversation", serde_json::json!({ "id": id, "title": title })).await
+) -> Result<Value, SemblanceError> {
+    state.bridge()?.call("rename_conversation", serde_json::json!({ "id": id, "title": title })).await
 }
 
 #[tauri::command]
 async fn pin_conversation(
     state: tauri::State<'_, AppBridge>,
     id: String,
-) -> Result<Value, String> {
-    state.bridge.call("pin_conversation", serde_json::json!({ "id": id })).await
+) -> Result<Value, SemblanceError> {
+    state.bridge()?.call("pin_conversation", serde_json::json!({ "id": id })).await
 }
 
 #[tauri::command]
 async fn unpin_conversation(
     state: tauri::State<'_, AppBridge>,
     id: String,
-) -> Result<Value, String> {
-    state.bridge.call("unpin_conversation", serde_json::json!({ "id": id })).await
+) -> Result<Value, SemblanceError> {
+    state.bridge()?.call("unpin_conversation", serde_json::json!({ "id": id })).await
 }
 
 #[tauri::command]
@@ -1623,8 +4164,8 @@ async fn switch_conversation(
     state: tauri::State<'_, AppBridge>,
     id: String,
     limit: Option<u32>,
-) -> Result<Value, String> {
-    state.bridge.call("switch_conversation", serde_json::json!({ "id": id, "limit": limit })).await
+) -> Result<Value, SemblanceError> {
+    state.bridge()?.call("switch_conversation", serde_json::json!({ "id": id, "limit": limit })).await
 }
 
 #[tauri::command]
@@ -1632,36 +4173,36 @@ async fn search_conversations(
     state: tauri::State<'_, AppBridge>,
     query: String,
     limit: Option<u32>,
-) -> Result<Value, String> {
-    state.bridge.call("search_conversations", serde_json::json!({ "query": query, "limit": limit })).await
+) -> Result<Value, SemblanceError> {
+    state.bridge()?.call("search_conversations", serde_json::json!({ "query": query, "limit": limit })).await
 }
 
 #[tauri::command]
 async fn clear_all_conversations(
     state: tauri::State<'_, AppBridge>,
     preserve_pinned: Option<bool>,
-) -> Result<Value, String> {
-    state.bridge.call("clear_all_conversations", serde_json::json!({ "preserve_pinned": preserve_pinned })).await
+) -> Result<Value, SemblanceError> {
+    state.bridge()?.call("clear_all_conversations", serde_json::json!({ "preserve_pinned": preserve_pinned })).await
 }
 
 #[tauri::command]
 async fn set_conversation_auto_expiry(
     state: tauri::State<'_, AppBridge>,
     days: Option<u32>,
-) -> Result<Value, String> {
-    state.bridge.call("set_conversation_auto_expiry", serde_json::json!({ "days": days })).await
+) -> Result<Value, SemblanceError> {
+    state.bridge()?.call("set_conversation_auto_expiry", serde_json::json!({ "days": days })).await
 }
 
 // ─── Intent Layer ──────────────────────────────────────────────────────────
 
 #[tauri::command]
-async fn get_intent(state: tauri::State<'_, AppBridge>) -> Result<Value, String> {
-    state.bridge.call("get_intent", Value::Null).await
+async fn get_intent(state: tauri::State<'_, AppBridge>) -> Result<Value, SemblanceError> {
+    state.bridge()?.call("get_intent", Value::Null).await
 }
 
 #[tauri::command]
-async fn set_primary_goal(state: tauri::State<'_, AppBridge>, text: String) -> Result<Value, String> {
-    state.bridge.call("set_primary_goal", serde_json::json!({ "text": text })).await
+async fn set_primary_goal(state: tauri::State<'_, AppBridge>, text: String) -> Result<Value, SemblanceError> {
+    state.bridge()?.call("set_primary_goal", serde_json::json!({ "text": text })).await
 }
 
 #[tauri::command]
@@ -1669,13 +4210,13 @@ async fn add_hard_limit(
     state: tauri::State<'_, AppBridge>,
     raw_text: String,
     source: String,
-) -> Result<Value, String> {
-    state.bridge.call("add_hard_limit", serde_json::json!({ "rawText": raw_text, "source": source })).await
+) -> Result<Value, SemblanceError> {
+    state.bridge()?.call("add_hard_limit", serde_json::json!({ "rawText": raw_text, "source": source })).await
 }
 
 #[tauri::command]
-async fn remove_hard_limit(state: tauri::State<'_, AppBridge>, id: String) -> Result<Value, String> {
-    state.bridge.call("remove_hard_limit", serde_json::json!({ "id": id })).await
+async fn remove_hard_limit(state: tauri::State<'_, AppBridge>, id: String) -> Result<Value, SemblanceError> {
+    state.bridge()?.call("remove_hard_limit", serde_json::json!({ "id": id })).await
 }
 
 #[tauri::command]
@@ -1683,8 +4224,8 @@ async fn toggle_hard_limit(
     state: tauri::State<'_, AppBridge>,
     id: String,
     active: bool,
-) -> Result<Value, String> {
-    state.bridge.call("toggle_hard_limit", serde_json::json!({ "id": id, "active": active })).await
+) -> Result<Value, SemblanceError> {
+    state.bridge()?.call("toggle_hard_limit", serde_json::json!({ "id": id, "active": active })).await
 }
 
 #[tauri::command]
@@ -1692,21 +4233,21 @@ async fn add_personal_value(
     state: tauri::State<'_, AppBridge>,
     raw_text: String,
     source: String,
-) -> Result<Value, String> {
-    state.bridge.call("add_personal_value", serde_json::json!({ "rawText": raw_text, "source": source })).await
+) -> Result<Value, SemblanceError> {
+    state.bridge()?.call("add_personal_value", serde_json::json!({ "rawText": raw_text, "source": source })).await
 }
 
 #[tauri::command]
-async fn remove_personal_value(state: tauri::State<'_, AppBridge>, id: String) -> Result<Value, String> {
-    state.bridge.call("remove_personal_value", serde_json::json!({ "id": id })).await
+async fn remove_personal_value(state: tauri::State<'_, AppBridge>, id: String) -> Result<Value, SemblanceError> {
+    state.bridge()?.call("remove_personal_value", serde_json::json!({ "id": id })).await
 }
 
 #[tauri::command]
 async fn get_intent_observations(
     state: tauri::State<'_, AppBridge>,
     channel: Option<String>,
-) -> Result<Value, String> {
-    state.bridge.call("get_intent_observations", serde_json::json!({ "channel": channel })).await
+) -> Result<Value, SemblanceError> {
+    state.bridge()?.call("get_intent_observations", serde_json::json!({ "channel": channel })).await
 }
 
 #[tauri::command]
@@ -1714,8 +4255,8 @@ async fn dismiss_observation(
     state: tauri::State<'_, AppBridge>,
     id: String,
     user_response: Option<String>,
-) -> Result<Value, String> {
-    state.bridge.call("dismiss_observation", serde_json::json!({ "id": id, "userResponse": user_response })).await
+) -> Result<Value, SemblanceError> {
+    state.bridge()?.call("dismiss_observation", serde_json::json!({ "id": id, "userResponse": user_response })).await
 }
 
 #[tauri::command]
@@ -1723,8 +4264,8 @@ async fn check_action_intent(
     state: tauri::State<'_, AppBridge>,
     action: String,
     context: Value,
-) -> Result<Value, String> {
-    state.bridge.call("check_action_intent", serde_json::json!({ "action": action, "context": context })).await
+) -> Result<Value, SemblanceError> {
+    state.bridge()?.call("check_action_intent", serde_json::json!({ "action": action, "context": context })).await
 }
 
 #[tauri::command]
@@ -1733,8 +4274,8 @@ async fn set_intent_onboarding(
     primary_goal: Option<String>,
     hard_limit: Option<String>,
     personal_value: Option<String>,
-) -> Result<Value, String> {
-    state.bridge.call("set_intent_onboarding", serde_json::json!({
+) -> Result<Value, SemblanceError> {
+    state.bridge()?.call("set_intent_onboarding", serde_json::json!({
         "primaryGoal": primary_goal,
         "hardLimit": hard_limit,
         "personalValue": personal_value,
@@ -1744,40 +4285,40 @@ async fn set_intent_onboarding(
 // ─── Alter Ego Guardrails ──────────────────────────────────────────────────
 
 #[tauri::command]
-async fn alter_ego_get_settings(state: tauri::State<'_, AppBridge>) -> Result<Value, String> {
-    state.bridge.call("alterEgo:getSettings", Value::Null).await
+async fn alter_ego_get_settings(state: tauri::State<'_, AppBridge>) -> Result<Value, SemblanceError> {
+    state.bridge()?.call("alterEgo:getSettings", Value::Null).await
 }
 
 #[tauri::command]
 async fn alter_ego_update_settings(
     state: tauri::State<'_, AppBridge>,
     settings: Value,
-) -> Result<Value, String> {
-    state.bridge.call("alterEgo:updateSettings", settings).await
+) -> Result<Value, SemblanceError> {
+    state.bridge()?.call("alterEgo:updateSettings", settings).await
 }
 
 #[tauri::command]
 async fn alter_ego_get_receipts(
     state: tauri::State<'_, AppBridge>,
     week_group: Option<String>,
-) -> Result<Value, String> {
-    state.bridge.call("alterEgo:getReceipts", serde_json::json!({ "weekGroup": week_group })).await
+) -> Result<Value, SemblanceError> {
+    state.bridge()?.call("alterEgo:getReceipts", serde_json::json!({ "weekGroup": week_group })).await
 }
 
 #[tauri::command]
 async fn alter_ego_approve_batch(
     state: tauri::State<'_, AppBridge>,
     ids: Vec<String>,
-) -> Result<Value, String> {
-    state.bridge.call("alterEgo:approveBatch", serde_json::json!({ "ids": ids })).await
+) -> Result<Value, SemblanceError> {
+    state.bridge()?.call("alterEgo:approveBatch", serde_json::json!({ "ids": ids })).await
 }
 
 #[tauri::command]
 async fn alter_ego_reject_batch(
     state: tauri::State<'_, AppBridge>,
     ids: Vec<String>,
-) -> Result<Value, String> {
-    state.bridge.call("alterEgo:rejectBatch", serde_json::json!({ "ids": ids })).await
+) -> Result<Value, SemblanceError> {
+    state.bridge()?.call("alterEgo:rejectBatch", serde_json::json!({ "ids": ids })).await
 }
 
 #[tauri::command]
@@ -1786,8 +4327,8 @@ async fn alter_ego_send_draft(
     action_id: String,
     email: String,
     action: String,
-) -> Result<Value, String> {
-    state.bridge.call("alterEgo:sendDraft", serde_json::json!({
+) -> Result<Value, SemblanceError> {
+    state.bridge()?.call("alterEgo:sendDraft", serde_json::json!({
         "actionId": action_id,
         "email": email,
         "action": action,
@@ -1798,74 +4339,74 @@ async fn alter_ego_send_draft(
 async fn alter_ego_undo_receipt(
     state: tauri::State<'_, AppBridge>,
     receipt_id: String,
-) -> Result<Value, String> {
-    state.bridge.call("alterEgo:undoReceipt", serde_json::json!({ "receiptId": receipt_id })).await
+) -> Result<Value, SemblanceError> {
+    state.bridge()?.call("alterEgo:undoReceipt", serde_json::json!({ "receiptId": receipt_id })).await
 }
 
 // ─── Sound Settings ──────────────────────────────────────────────────────────
 
 #[tauri::command]
-async fn get_sound_settings(state: tauri::State<'_, AppBridge>) -> Result<Value, String> {
-    state.bridge.call("sound:getSettings", Value::Null).await
+async fn get_sound_settings(state: tauri::State<'_, AppBridge>) -> Result<Value, SemblanceError> {
+    state.bridge()?.call("sound:getSettings", Value::Null).await
 }
 
 #[tauri::command]
 async fn save_sound_settings(
     state: tauri::State<'_, AppBridge>,
     settings: Value,
-) -> Result<Value, String> {
-    state.bridge.call("sound:saveSettings", settings).await
+) -> Result<Value, SemblanceError> {
+    state.bridge()?.call("sound:saveSettings", settings).await
 }
 
 // ─── Notification Settings ────────────────────────────────────────────────
 
 #[tauri::command]
-async fn get_notification_settings(state: tauri::State<'_, AppBridge>) -> Result<Value, String> {
-    state.bridge.call("notification:getSettings", Value::Null).await
+async fn get_notification_settings(state: tauri::State<'_, AppBridge>) -> Result<Value, SemblanceError> {
+    state.bridge()?.call("notification:getSettings", Value::Null).await
 }
 
 #[tauri::command]
 async fn save_notification_settings(
     state: tauri::State<'_, AppBridge>,
     settings: Value,
-) -> Result<Value, String> {
-    state.bridge.call("notification:saveSettings", settings).await
+) -> Result<Value, SemblanceError> {
+    state.bridge()?.call("notification:saveSettings", settings).await
 }
 
 // ─── Location Settings ───────────────────────────────────────────────────
 
 #[tauri::command]
-async fn get_location_settings(state: tauri::State<'_, AppBridge>) -> Result<Value, String> {
-    state.bridge.call("location:getSettings", Value::Null).await
+async fn get_location_settings(state: tauri::State<'_, AppBridge>) -> Result<Value, SemblanceError> {
+    state.bridge()?.call("location:getSettings", Value::Null).await
 }
 
 #[tauri::command]
 async fn save_location_settings(
     state: tauri::State<'_, AppBridge>,
     settings: Value,
-) -> Result<Value, String> {
-    state.bridge.call("location:saveSettings", settings).await
+) -> Result<Value, SemblanceError> {
+    state.bridge()?.call("location:saveSettings", settings).await
 }
 
 #[tauri::command]
-async fn clear_location_history(state: tauri::State<'_, AppBridge>) -> Result<Value, String> {
-    state.bridge.call("location:clearHistory", Value::Null).await
+async fn clear_location_history(state: tauri::State<'_, AppBridge>) -> Result<Value, SemblanceError> {
+    state.bridge()?.call("location:clearHistory", Value::Null).await
 }
 
 // ─── Language Preference ──────────────────────────────────────────────────
 
 #[tauri::command]
-async fn get_language_preference(state: tauri::State<'_, AppBridge>) -> Result<Value, String> {
-    state.bridge.call("language:get", Value::Null).await
+async fn get_language_preference(state: tauri::State<'_, AppBridge>) -> Result<Value, SemblanceError> {
+    state.bridge()?.call("language:get", Value::Null).await
 }
 
 #[tauri::command]
 async fn set_language_preference(
     state: tauri::State<'_, AppBridge>,
     code: String,
-) -> Result<Value, String> {
+) -> Result<Value, SemblanceError> {
     state
-        .bridge
+        .bridge()?
         .call("language:set", serde_json::json!({ "code": code }))
         .await
 }
@@ -1879,8 +4420,8 @@ async fn list_knowledge_by_category(
     limit: u32,
     offset: u32,
     search_query: Option<String>,
-) -> Result<Value, String> {
-    state.bridge.call("knowledge:listByCategory", serde_json::json!({
+) -> Result<Value, SemblanceError> {
+    state.bridge()?.call("knowledge:listByCategory", serde_json::json!({
         "category": category,
         "limit": limit,
         "offset": offset,
@@ -1892,8 +4433,8 @@ async fn list_knowledge_by_category(
 async fn remove_knowledge_item(
     state: tauri::State<'_, AppBridge>,
     chunk_id: String,
-) -> Result<Value, String> {
-    state.bridge.call("knowledge:remove", serde_json::json!({
+) -> Result<Value, SemblanceError> {
+    state.bridge()?.call("knowledge:remove", serde_json::json!({
         "chunkId": chunk_id,
     })).await
 }
@@ -1902,8 +4443,8 @@ async fn remove_knowledge_item(
 async fn delete_knowledge_item(
     state: tauri::State<'_, AppBridge>,
     chunk_id: String,
-) -> Result<Value, String> {
-    state.bridge.call("knowledge:delete", serde_json::json!({
+) -> Result<Value, SemblanceError> {
+    state.bridge()?.call("knowledge:delete", serde_json::json!({
         "chunkId": chunk_id,
     })).await
 }
@@ -1913,8 +4454,8 @@ async fn recategorize_knowledge_item(
     state: tauri::State<'_, AppBridge>,
     chunk_id: String,
     new_category: String,
-) -> Result<Value, String> {
-    state.bridge.call("knowledge:recategorize", serde_json::json!({
+) -> Result<Value, SemblanceError> {
+    state.bridge()?.call("knowledge:recategorize", serde_json::json!({
         "chunkId": chunk_id,
         "newCategory": new_category,
     })).await
@@ -1924,8 +4465,8 @@ async fn recategorize_knowledge_item(
 async fn reindex_knowledge_item(
     state: tauri::State<'_, AppBridge>,
     chunk_id: String,
-) -> Result<Value, String> {
-    state.bridge.call("knowledge:reindex", serde_json::json!({
+) -> Result<Value, SemblanceError> {
+    state.bridge()?.call("knowledge:reindex", serde_json::json!({
         "chunkId": chunk_id,
     })).await
 }
@@ -1934,8 +4475,8 @@ async fn reindex_knowledge_item(
 async fn suggest_knowledge_categories(
     state: tauri::State<'_, AppBridge>,
     chunk_id: String,
-) -> Result<Value, String> {
-    state.bridge.call("knowledge:suggestCategories", serde_json::json!({
+) -> Result<Value, SemblanceError> {
+    state.bridge()?.call("knowledge:suggestCategories", serde_json::json!({
         "chunkId": chunk_id,
     })).await
 }
@@ -1943,8 +4484,8 @@ async fn suggest_knowledge_categories(
 #[tauri::command]
 async fn list_knowledge_categories(
     state: tauri::State<'_, AppBridge>,
-) -> Result<Value, String> {
-    state.bridge.call("knowledge:listCategories", Value::Null).await
+) -> Result<Value, SemblanceError> {
+    state.bridge()?.call("knowledge:listCategories", Value::Null).await
 }
 
 // ─── Merkle Chain / Audit Integrity ─────────────────────────────────────────
@@ -1954,8 +4495,8 @@ async fn audit_verify_chain(
     state: tauri::State<'_, AppBridge>,
     start_date: Option<String>,
     end_date: Option<String>,
-) -> Result<Value, String> {
-    state.bridge.call("audit_verify_chain", serde_json::json!({
+) -> Result<Value, SemblanceError> {
+    state.bridge()?.call("audit_verify_chain", serde_json::json!({
         "startDate": start_date,
         "endDate": end_date,
     })).await
@@ -1965,8 +4506,8 @@ async fn audit_verify_chain(
 async fn audit_generate_receipt(
     state: tauri::State<'_, AppBridge>,
     date: String,
-) -> Result<Value, String> {
-    state.bridge.call("audit_generate_receipt", serde_json::json!({
+) -> Result<Value, SemblanceError> {
+    state.bridge()?.call("audit_generate_receipt", serde_json::json!({
         "date": date,
     })).await
 }
@@ -1974,8 +4515,8 @@ async fn audit_generate_receipt(
 #[tauri::command]
 async fn audit_get_chain_status(
     state: tauri::State<'_, AppBridge>,
-) -> Result<Value, String> {
-    state.bridge.call("audit_get_chain_status", Value::Null).await
+) -> Result<Value, SemblanceError> {
+    state.bridge()?.call("audit_get_chain_status", Value::Null).await
 }
 
 // ─── Hardware-Bound Key Commands ──────────────────────────────────────────
@@ -1984,8 +4525,8 @@ async fn audit_get_chain_status(
 async fn hw_key_get_info(
     state: tauri::State<'_, AppBridge>,
     key_id: Option<String>,
-) -> Result<Value, String> {
-    state.bridge.call("hw_key_get_info", serde_json::json!({
+) -> Result<Value, SemblanceError> {
+    state.bridge()?.call("hw_key_get_info", serde_json::json!({
         "keyId": key_id,
     })).await
 }
@@ -1995,8 +4536,8 @@ async fn hw_key_sign(
     state: tauri::State<'_, AppBridge>,
     payload: String,
     key_id: Option<String>,
-) -> Result<Value, String> {
-    state.bridge.call("hw_key_sign", serde_json::json!({
+) -> Result<Value, SemblanceError> {
+    state.bridge()?.call("hw_key_sign", serde_json::json!({
         "payload": payload,
         "keyId": key_id,
     })).await
@@ -2008,8 +4549,8 @@ async fn hw_key_verify(
     payload: String,
     signature_hex: String,
     key_id: Option<String>,
-) -> Result<Value, String> {
-    state.bridge.call("hw_key_verify", serde_json::json!({
+) -> Result<Value, SemblanceError> {
+    state.bridge()?.call("hw_key_verify", serde_json::json!({
         "payload": payload,
         "signatureHex": signature_hex,
         "keyId": key_id,
@@ -2019,8 +4560,8 @@ async fn hw_key_verify(
 #[tauri::command]
 async fn hw_key_get_backend(
     state: tauri::State<'_, AppBridge>,
-) -> Result<Value, String> {
-    state.bridge.call("hw_key_get_backend", Value::Null).await
+) -> Result<Value, SemblanceError> {
+    state.bridge()?.call("hw_key_get_backend", Value::Null).await
 }
 
 // ─── Sovereignty Report Commands ──────────────────────────────────────────
@@ -2030,19 +4571,19 @@ async fn report_generate_sovereignty(
     state: tauri::State<'_, AppBridge>,
     period_start: String,
     period_end: String,
-) -> Result<Value, String> {
-    state.bridge.call("report_generate_sovereignty", serde_json::json!({
+) -> Result<Value, SemblanceError> {
+    state.bridge()?.call_with_timeout("report_generate_sovereignty", serde_json::json!({
         "periodStart": period_start,
         "periodEnd": period_end,
-    })).await
+    }), SIDECAR_GENERATION_TIMEOUT).await
 }
 
 #[tauri::command]
 async fn report_render_pdf(
     state: tauri::State<'_, AppBridge>,
     report_json: String,
-) -> Result<Value, String> {
-    state.bridge.call("report_render_pdf", serde_json::json!({
+) -> Result<Value, SemblanceError> {
+    state.bridge()?.call("report_render_pdf", serde_json::json!({
         "reportJson": report_json,
     })).await
 }
@@ -2051,8 +4592,8 @@ async fn report_render_pdf(
 async fn report_verify_sovereignty(
     state: tauri::State<'_, AppBridge>,
     report_json: String,
-) -> Result<Value, String> {
-    state.bridge.call("report_verify_sovereignty", serde_json::json!({
+) -> Result<Value, SemblanceError> {
+    state.bridge()?.call("report_verify_sovereignty", serde_json::json!({
         "reportJson": report_json,
     })).await
 }
@@ -2060,14 +4601,14 @@ async fn report_verify_sovereignty(
 // ─── Document / File Picker Commands ────────────────────────────────────────
 
 #[tauri::command]
-async fn document_pick_file(app: tauri::AppHandle) -> Result<Option<String>, String> {
+async fn document_pick_file(app: tauri::AppHandle) -> Result<Option<String>, SemblanceError> {
     use tauri_plugin_dialog::DialogExt;
     let file = app.dialog().file().blocking_pick_file();
     Ok(file.map(|f| f.to_string()))
 }
 
 #[tauri::command]
-async fn document_pick_files(app: tauri::AppHandle) -> Result<Vec<String>, String> {
+async fn document_pick_files(app: tauri::AppHandle) -> Result<Vec<String>, SemblanceError> {
     use tauri_plugin_dialog::DialogExt;
     let files = app.dialog().file().blocking_pick_files();
     Ok(files.unwrap_or_default().iter().map(|f| f.to_string()).collect())
@@ -2077,39 +4618,39 @@ async fn document_pick_files(app: tauri::AppHandle) -> Result<Vec<String>, Strin
 async fn document_set_context(
     state: tauri::State<'_, AppBridge>,
     file_path: String,
-) -> Result<Value, String> {
-    state.bridge.call("document_set_context", serde_json::json!({ "filePath": file_path })).await
+) -> Result<Value, SemblanceError> {
+    state.bridge()?.call("document_set_context", serde_json::json!({ "filePath": file_path })).await
 }
 
 #[tauri::command]
 async fn document_clear_context(
     state: tauri::State<'_, AppBridge>,
-) -> Result<Value, String> {
-    state.bridge.call("document_clear_context", Value::Null).await
+) -> Result<Value, SemblanceError> {
+    state.bridge()?.call("document_clear_context", Value::Null).await
 }
 
 #[tauri::command]
 async fn document_add_file(
     state: tauri::State<'_, AppBridge>,
     file_path: String,
-) -> Result<Value, String> {
-    state.bridge.call("document_add_file", serde_json::json!({ "filePath": file_path })).await
+) -> Result<Value, SemblanceError> {
+    state.bridge()?.call("document_add_file", serde_json::json!({ "filePath": file_path })).await
 }
 
 #[tauri::command]
 async fn document_remove_file(
     state: tauri::State<'_, AppBridge>,
     document_id: String,
-) -> Result<Value, String> {
-    state.bridge.call("document_remove_file", serde_json::json!({ "documentId": document_id })).await
+) -> Result<Value, SemblanceError> {
+    state.bridge()?.call("document_remove_file", serde_json::json!({ "documentId": document_id })).await
 }
 
 #[tauri::command]
 async fn add_attachment_to_knowledge(
     state: tauri::State<'_, AppBridge>,
     document_id: String,
-) -> Result<Value, String> {
-    state.bridge.call("add_attachment_to_knowledge", serde_json::json!({ "documentId": document_id })).await
+) -> Result<Value, SemblanceError> {
+    state.bridge()?.call("add_attachment_to_knowledge", serde_json::json!({ "documentId": document_id })).await
 }
 
 // ─── Morning Brief Commands ──────────────────────────────────────────────
@@ -2117,59 +4658,59 @@ async fn add_attachment_to_knowledge(
 #[tauri::command]
 async fn brief_get_morning(
     state: tauri::State<'_, AppBridge>,
-) -> Result<Value, String> {
-    state.bridge.call("brief_get_morning", Value::Null).await
+) -> Result<Value, SemblanceError> {
+    state.bridge()?.call("brief_get_morning", Value::Null).await
 }
 
 #[tauri::command]
 async fn brief_dismiss(
     state: tauri::State<'_, AppBridge>,
     id: String,
-) -> Result<Value, String> {
-    state.bridge.call("brief_dismiss", serde_json::json!({ "id": id })).await
+) -> Result<Value, SemblanceError> {
+    state.bridge()?.call("brief_dismiss", serde_json::json!({ "id": id })).await
 }
 
 #[tauri::command]
 async fn weather_get_current(
     state: tauri::State<'_, AppBridge>,
-) -> Result<Value, String> {
-    state.bridge.call("weather_get_current", Value::Null).await
+) -> Result<Value, SemblanceError> {
+    state.bridge()?.call("weather_get_current", Value::Null).await
 }
 
 #[tauri::command]
 async fn commute_get_today(
     state: tauri::State<'_, AppBridge>,
-) -> Result<Value, String> {
-    state.bridge.call("commute_get_today", Value::Null).await
+) -> Result<Value, SemblanceError> {
+    state.bridge()?.call("commute_get_today", Value::Null).await
 }
 
 #[tauri::command]
 async fn knowledge_get_moment(
     state: tauri::State<'_, AppBridge>,
-) -> Result<Value, String> {
-    state.bridge.call("knowledge_get_moment", Value::Null).await
+) -> Result<Value, SemblanceError> {
+    state.bridge()?.call("knowledge_get_moment", Value::Null).await
 }
 
 #[tauri::command]
 async fn alter_ego_get_activation_prompt(
     state: tauri::State<'_, AppBridge>,
-) -> Result<Value, String> {
-    state.bridge.call("alter_ego_get_activation_prompt", Value::Null).await
+) -> Result<Value, SemblanceError> {
+    state.bridge()?.call("alter_ego_get_activation_prompt", Value::Null).await
 }
 
 #[tauri::command]
 async fn digest_get_daily(
     state: tauri::State<'_, AppBridge>,
-) -> Result<Value, String> {
-    state.bridge.call("digest_get_daily", Value::Null).await
+) -> Result<Value, SemblanceError> {
+    state.bridge()?.call("digest_get_daily", Value::Null).await
 }
 
 #[tauri::command]
 async fn digest_dismiss_daily(
     state: tauri::State<'_, AppBridge>,
     id: String,
-) -> Result<Value, String> {
-    state.bridge.call("digest_dismiss_daily", serde_json::json!({ "id": id })).await
+) -> Result<Value, SemblanceError> {
+    state.bridge()?.call("digest_dismiss_daily", serde_json::json!({ "id": id })).await
 }
 
 // ─── Knowledge Graph Commands ────────────────────────────────────────────
@@ -2177,23 +4718,23 @@ async fn digest_dismiss_daily(
 #[tauri::command]
 async fn knowledge_get_graph(
     state: tauri::State<'_, AppBridge>,
-) -> Result<Value, String> {
-    state.bridge.call("knowledge_get_graph", Value::Null).await
+) -> Result<Value, SemblanceError> {
+    state.bridge()?.call("knowledge_get_graph", Value::Null).await
 }
 
 #[tauri::command]
 async fn knowledge_get_node_context(
     state: tauri::State<'_, AppBridge>,
     node_id: String,
-) -> Result<Value, String> {
-    state.bridge.call("knowledge_get_node_context", serde_json::json!({ "nodeId": node_id })).await
+) -> Result<Value, SemblanceError> {
+    state.bridge()?.call("knowledge_get_node_context", serde_json::json!({ "nodeId": node_id })).await
 }
 
 #[tauri::command]
 async fn knowledge_export_graph(
     state: tauri::State<'_, AppBridge>,
-) -> Result<Value, String> {
-    state.bridge.call("knowledge_export_graph", Value::Null).await
+) -> Result<Value, SemblanceError> {
+    state.bridge()?.call("knowledge_export_graph", Value::Null).await
 }
 
 // ─── Escalation Commands ─────────────────────────────────────────────────
@@ -2201,8 +4742,8 @@ async fn knowledge_export_graph(
 #[tauri::command]
 async fn escalation_get_prompts(
     state: tauri::State<'_, AppBridge>,
-) -> Result<Value, String> {
-    state.bridge.call("escalation_get_prompts", Value::Null).await
+) -> Result<Value, SemblanceError> {
+    state.bridge()?.call("escalation_get_prompts", Value::Null).await
 }
 
 // ─── Clipboard Insight Commands ──────────────────────────────────────────
@@ -2210,24 +4751,24 @@ async fn escalation_get_prompts(
 #[tauri::command]
 async fn clipboard_get_insights(
     state: tauri::State<'_, AppBridge>,
-) -> Result<Value, String> {
-    state.bridge.call("clipboard_get_insights", Value::Null).await
+) -> Result<Value, SemblanceError> {
+    state.bridge()?.call("clipboard_get_insights", Value::Null).await
 }
 
 #[tauri::command]
 async fn clipboard_execute_action(
     state: tauri::State<'_, AppBridge>,
     action_id: String,
-) -> Result<Value, String> {
-    state.bridge.call("clipboard_execute_action", serde_json::json!({ "actionId": action_id })).await
+) -> Result<Value, SemblanceError> {
+    state.bridge()?.call("clipboard_execute_action", serde_json::json!({ "actionId": action_id })).await
 }
 
 #[tauri::command]
 async fn clipboard_dismiss_insight(
     state: tauri::State<'_, AppBridge>,
     action_id: String,
-) -> Result<Value, String> {
-    state.bridge.call("clipboard_dismiss_insight", serde_json::json!({ "actionId": action_id })).await
+) -> Result<Value, SemblanceError> {
+    state.bridge()?.call("clipboard_dismiss_insight", serde_json::json!({ "actionId": action_id })).await
 }
 
 // ─── Reminder Commands ───────────────────────────────────────────────────
@@ -2235,8 +4776,8 @@ async fn clipboard_dismiss_insight(
 #[tauri::command]
 async fn reminder_list(
     state: tauri::State<'_, AppBridge>,
-) -> Result<Value, String> {
-    state.bridge.call("reminder_list", Value::Null).await
+) -> Result<Value, SemblanceError> {
+    state.bridge()?.call("reminder_list", Value::Null).await
 }
 
 #[tauri::command]
@@ -2244,16 +4785,16 @@ async fn reminder_snooze(
     state: tauri::State<'_, AppBridge>,
     id: String,
     duration: String,
-) -> Result<Value, String> {
-    state.bridge.call("reminder_snooze", serde_json::json!({ "id": id, "duration": duration })).await
+) -> Result<Value, SemblanceError> {
+    state.bridge()?.call("reminder_snooze", serde_json::json!({ "id": id, "duration": duration })).await
 }
 
 #[tauri::command]
 async fn reminder_dismiss(
     state: tauri::State<'_, AppBridge>,
     id: String,
-) -> Result<Value, String> {
-    state.bridge.call("reminder_dismiss", serde_json::json!({ "id": id })).await
+) -> Result<Value, SemblanceError> {
+    state.bridge()?.call("reminder_dismiss", serde_json::json!({ "id": id })).await
 }
 
 // ─── Quick Capture Command ───────────────────────────────────────────────
@@ -2262,8 +4803,8 @@ async fn reminder_dismiss(
 async fn quick_capture(
     state: tauri::State<'_, AppBridge>,
     text: String,
-) -> Result<Value, String> {
-    state.bridge.call("quick_capture", serde_json::json!({ "text": text })).await
+) -> Result<Value, SemblanceError> {
+    state.bridge()?.call("quick_capture", serde_json::json!({ "text": text })).await
 }
 
 // ─── Style Profile Commands ──────────────────────────────────────────────
@@ -2271,22 +4812,22 @@ async fn quick_capture(
 #[tauri::command]
 async fn style_get_profile(
     state: tauri::State<'_, AppBridge>,
-) -> Result<Value, String> {
-    state.bridge.call("style_get_profile", Value::Null).await
+) -> Result<Value, SemblanceError> {
+    state.bridge()?.call("style_get_profile", Value::Null).await
 }
 
 #[tauri::command]
 async fn style_reanalyze(
     state: tauri::State<'_, AppBridge>,
-) -> Result<Value, String> {
-    state.bridge.call("style_reanalyze", Value::Null).await
+) -> Result<Value, SemblanceError> {
+    state.bridge()?.call_with_timeout("style_reanalyze", Value::Null, SIDECAR_GENERATION_TIMEOUT).await
 }
 
 #[tauri::command]
 async fn style_reset(
     state: tauri::State<'_, AppBridge>,
-) -> Result<Value, String> {
-    state.bridge.call("style_reset", Value::Null).await
+) -> Result<Value, SemblanceError> {
+    state.bridge()?.call("style_reset", Value::Null).await
 }
 
 // ─── Dark Pattern Detection Commands ─────────────────────────────────────
@@ -2294,16 +4835,16 @@ async fn style_reset(
 #[tauri::command]
 async fn dark_pattern_get_flags(
     state: tauri::State<'_, AppBridge>,
-) -> Result<Value, String> {
-    state.bridge.call("dark_pattern_get_flags", Value::Null).await
+) -> Result<Value, SemblanceError> {
+    state.bridge()?.call("dark_pattern_get_flags", Value::Null).await
 }
 
 #[tauri::command]
 async fn dark_pattern_dismiss(
     state: tauri::State<'_, AppBridge>,
     content_id: String,
-) -> Result<Value, String> {
-    state.bridge.call("dark_pattern_dismiss", serde_json::json!({ "contentId": content_id })).await
+) -> Result<Value, SemblanceError> {
+    state.bridge()?.call("dark_pattern_dismiss", serde_json::json!({ "contentId": content_id })).await
 }
 
 // ─── Voice Model Commands ────────────────────────────────────────────────
@@ -2311,16 +4852,16 @@ async fn dark_pattern_dismiss(
 #[tauri::command]
 async fn voice_get_model_status(
     state: tauri::State<'_, AppBridge>,
-) -> Result<Value, String> {
-    state.bridge.call("voice_get_model_status", Value::Null).await
+) -> Result<Value, SemblanceError> {
+    state.bridge()?.call_with_timeout("voice_get_model_status", Value::Null, SIDECAR_STATUS_TIMEOUT).await
 }
 
 #[tauri::command]
 async fn voice_download_model(
     state: tauri::State<'_, AppBridge>,
     model: String,
-) -> Result<Value, String> {
-    state.bridge.call("voice_download_model", serde_json::json!({ "model": model })).await
+) -> Result<Value, SemblanceError> {
+    state.bridge()?.call("voice_download_model", serde_json::json!({ "model": model })).await
 }
 
 // ─── Import Digital Life Commands ────────────────────────────────────────
@@ -2328,16 +4869,16 @@ async fn voice_download_model(
 #[tauri::command]
 async fn import_get_history(
     state: tauri::State<'_, AppBridge>,
-) -> Result<Value, String> {
-    state.bridge.call("import_get_history", Value::Null).await
+) -> Result<Value, SemblanceError> {
+    state.bridge()?.call("import_get_history", Value::Null).await
 }
 
 #[tauri::command]
 async fn import_start(
     state: tauri::State<'_, AppBridge>,
     source_id: String,
-) -> Result<Value, String> {
-    state.bridge.call("import_start", serde_json::json!({ "sourceId": source_id })).await
+) -> Result<Value, SemblanceError> {
+    state.bridge()?.call("import_start", serde_json::json!({ "sourceId": source_id })).await
 }
 
 // ─── Model Download Commands ─────────────────────────────────────────────
@@ -2346,23 +4887,23 @@ async fn import_start(
 async fn start_model_downloads(
     state: tauri::State<'_, AppBridge>,
     tier: String,
-) -> Result<Value, String> {
-    state.bridge.call("start_model_downloads", serde_json::json!({ "tier": tier })).await
+) -> Result<Value, SemblanceError> {
+    state.bridge()?.call("start_model_downloads", serde_json::json!({ "tier": tier })).await
 }
 
 #[tauri::command]
 async fn model_get_download_status(
     state: tauri::State<'_, AppBridge>,
-) -> Result<Value, String> {
-    state.bridge.call("model_get_download_status", Value::Null).await
+) -> Result<Value, SemblanceError> {
+    state.bridge()?.call_with_timeout("model_get_download_status", Value::Null, SIDECAR_STATUS_TIMEOUT).await
 }
 
 #[tauri::command]
 async fn model_retry_download(
     state: tauri::State<'_, AppBridge>,
     model_name: String,
-) -> Result<Value, String> {
-    state.bridge.call("model_retry_download", serde_json::json!({ "modelName": model_name })).await
+) -> Result<Value, SemblanceError> {
+    state.bridge()?.call("model_retry_download", serde_json::json!({ "modelName": model_name })).await
 }
 
 // ─── BitNet Model Management Commands ────────────────────────────────────
@@ -2371,31 +4912,31 @@ async fn model_retry_download(
 async fn bitnet_get_available_models(
     state: tauri::State<'_, AppBridge>,
     tier: Option<String>,
-) -> Result<Value, String> {
-    state.bridge.call("bitnet_get_models", serde_json::json!({ "tier": tier.unwrap_or_default() })).await
+) -> Result<Value, SemblanceError> {
+    state.bridge()?.call("bitnet_get_models", serde_json::json!({ "tier": tier.unwrap_or_default() })).await
 }
 
 #[tauri::command]
 async fn bitnet_download_model(
     state: tauri::State<'_, AppBridge>,
     model_id: String,
-) -> Result<Value, String> {
-    state.bridge.call("bitnet_download_model", serde_json::json!({ "modelId": model_id })).await
+) -> Result<Value, SemblanceError> {
+    state.bridge()?.call("bitnet_download_model", serde_json::json!({ "modelId": model_id })).await
 }
 
 #[tauri::command]
 async fn bitnet_set_active_model(
     state: tauri::State<'_, AppBridge>,
     model_id: String,
-) -> Result<Value, String> {
-    state.bridge.call("bitnet_set_active", serde_json::json!({ "modelId": model_id })).await
+) -> Result<Value, SemblanceError> {
+    state.bridge()?.call("bitnet_set_active", serde_json::json!({ "modelId": model_id })).await
 }
 
 #[tauri::command]
 async fn bitnet_get_status(
     state: tauri::State<'_, AppBridge>,
-) -> Result<Value, String> {
-    state.bridge.call("bitnet_get_status", Value::Null).await
+) -> Result<Value, SemblanceError> {
+    state.bridge()?.call_with_timeout("bitnet_get_status", Value::Null, SIDECAR_STATUS_TIMEOUT).await
 }
 
 // ─── Standard Model Management Commands ─────────────────────────────────
@@ -2403,24 +4944,24 @@ async fn bitnet_get_status(
 #[tauri::command]
 async fn standard_get_models(
     state: tauri::State<'_, AppBridge>,
-) -> Result<Value, String> {
-    state.bridge.call("standard_get_models", Value::Null).await
+) -> Result<Value, SemblanceError> {
+    state.bridge()?.call("standard_get_models", Value::Null).await
 }
 
 #[tauri::command]
 async fn standard_download_model(
     state: tauri::State<'_, AppBridge>,
     model_id: String,
-) -> Result<Value, String> {
-    state.bridge.call("standard_download_model", serde_json::json!({ "modelId": model_id })).await
+) -> Result<Value, SemblanceError> {
+    state.bridge()?.call("standard_download_model", serde_json::json!({ "modelId": model_id })).await
 }
 
 #[tauri::command]
 async fn standard_set_active(
     state: tauri::State<'_, AppBridge>,
     model_id: String,
-) -> Result<Value, String> {
-    state.bridge.call("standard_set_active", serde_json::json!({ "modelId": model_id })).await
+) -> Result<Value, SemblanceError> {
+    state.bridge()?.call("standard_set_active", serde_json::json!({ "modelId": model_id })).await
 }
 
 // ─── Alter Ego Week Commands ─────────────────────────────────────────────
@@ -2428,23 +4969,23 @@ async fn standard_set_active(
 #[tauri::command]
 async fn alter_ego_get_week_progress(
     state: tauri::State<'_, AppBridge>,
-) -> Result<Value, String> {
-    state.bridge.call("alter_ego_get_week_progress", Value::Null).await
+) -> Result<Value, SemblanceError> {
+    state.bridge()?.call("alter_ego_get_week_progress", Value::Null).await
 }
 
 #[tauri::command]
 async fn alter_ego_complete_day(
     state: tauri::State<'_, AppBridge>,
     day: u32,
-) -> Result<Value, String> {
-    state.bridge.call("alter_ego_complete_day", serde_json::json!({ "day": day })).await
+) -> Result<Value, SemblanceError> {
+    state.bridge()?.call("alter_ego_complete_day", serde_json::json!({ "day": day })).await
 }
 
 #[tauri::command]
 async fn alter_ego_skip_day(
     state: tauri::State<'_, AppBridge>,
-) -> Result<Value, String> {
-    state.bridge.call("alter_ego_skip_day", Value::Null).await
+) -> Result<Value, SemblanceError> {
+    state.bridge()?.call("alter_ego_skip_day", Value::Null).await
 }
 
 // ─── Financial Dashboard Commands ────────────────────────────────────────
@@ -2455,8 +4996,8 @@ async fn get_financial_dashboard(
     period: String,
     custom_start: Option<String>,
     custom_end: Option<String>,
-) -> Result<Value, String> {
-    state.bridge.call("get_financial_dashboard", serde_json::json!({
+) -> Result<Value, SemblanceError> {
+    state.bridge()?.call("get_financial_dashboard", serde_json::json!({
         "period": period,
         "customStart": custom_start,
         "customEnd": custom_end,
@@ -2467,8 +5008,8 @@ async fn get_financial_dashboard(
 async fn dismiss_anomaly(
     state: tauri::State<'_, AppBridge>,
     anomaly_id: String,
-) -> Result<Value, String> {
-    state.bridge.call("dismiss_anomaly", serde_json::json!({ "anomalyId": anomaly_id })).await
+) -> Result<Value, SemblanceError> {
+    state.bridge()?.call("dismiss_anomaly", serde_json::json!({ "anomalyId": anomaly_id })).await
 }
 
 // ─── Health Dashboard Commands ───────────────────────────────────────────
@@ -2477,16 +5018,16 @@ async fn dismiss_anomaly(
 async fn get_health_dashboard(
     state: tauri::State<'_, AppBridge>,
     trend_days: u32,
-) -> Result<Value, String> {
-    state.bridge.call("get_health_dashboard", serde_json::json!({ "trendDays": trend_days })).await
+) -> Result<Value, SemblanceError> {
+    state.bridge()?.call("get_health_dashboard", serde_json::json!({ "trendDays": trend_days })).await
 }
 
 #[tauri::command]
 async fn save_health_entry(
     state: tauri::State<'_, AppBridge>,
     entry: Value,
-) -> Result<Value, String> {
-    state.bridge.call("save_health_entry", serde_json::json!({ "entry": entry })).await
+) -> Result<Value, SemblanceError> {
+    state.bridge()?.call("save_health_entry", serde_json::json!({ "entry": entry })).await
 }
 
 // ─── Cloud Storage Commands ──────────────────────────────────────────────
@@ -2495,39 +5036,39 @@ async fn save_health_entry(
 async fn cloud_storage_connect(
     state: tauri::State<'_, AppBridge>,
     provider: String,
-) -> Result<Value, String> {
-    state.bridge.call("cloud_storage_connect", serde_json::json!({ "provider": provider })).await
+) -> Result<Value, SemblanceError> {
+    state.bridge()?.call("cloud_storage_connect", serde_json::json!({ "provider": provider })).await
 }
 
 #[tauri::command]
 async fn cloud_storage_disconnect(
     state: tauri::State<'_, AppBridge>,
     provider: String,
-) -> Result<Value, String> {
-    state.bridge.call("cloud_storage_disconnect", serde_json::json!({ "provider": provider })).await
+) -> Result<Value, SemblanceError> {
+    state.bridge()?.call("cloud_storage_disconnect", serde_json::json!({ "provider": provider })).await
 }
 
 #[tauri::command]
 async fn cloud_storage_sync_now(
     state: tauri::State<'_, AppBridge>,
-) -> Result<Value, String> {
-    state.bridge.call("cloud_storage_sync_now", Value::Null).await
+) -> Result<Value, SemblanceError> {
+    state.bridge()?.call("cloud_storage_sync_now", Value::Null).await
 }
 
 #[tauri::command]
 async fn cloud_storage_set_interval(
     state: tauri::State<'_, AppBridge>,
     minutes: u32,
-) -> Result<Value, String> {
-    state.bridge.call("cloud_storage_set_interval", serde_json::json!({ "minutes": minutes })).await
+) -> Result<Value, SemblanceError> {
+    state.bridge()?.call("cloud_storage_set_interval", serde_json::json!({ "minutes": minutes })).await
 }
 
 #[tauri::command]
 async fn cloud_storage_set_max_file_size(
     state: tauri::State<'_, AppBridge>,
     mb: u32,
-) -> Result<Value, String> {
-    state.bridge.call("cloud_storage_set_max_file_size", serde_json::json!({ "mb": mb })).await
+) -> Result<Value, SemblanceError> {
+    state.bridge()?.call("cloud_storage_set_max_file_size", serde_json::json!({ "mb": mb })).await
 }
 
 #[tauri::command]
@@ -2535,8 +5076,8 @@ async fn cloud_storage_browse_folders(
     state: tauri::State<'_, AppBridge>,
     provider: String,
     parent_folder_id: String,
-) -> Result<Value, String> {
-    state.bridge.call("cloud_storage_browse_folders", serde_json::json!({
+) -> Result<Value, SemblanceError> {
+    state.bridge()?.call("cloud_storage_browse_folders", serde_json::json!({
         "provider": provider,
         "parentFolderId": parent_folder_id,
     })).await
@@ -2547,8 +5088,8 @@ async fn cloud_storage_browse_folders(
 #[tauri::command]
 async fn get_search_settings(
     state: tauri::State<'_, AppBridge>,
-) -> Result<Value, String> {
-    state.bridge.call("get_search_settings", Value::Null).await
+) -> Result<Value, SemblanceError> {
+    state.bridge()?.call("get_search_settings", Value::Null).await
 }
 
 #[tauri::command]
@@ -2559,8 +5100,8 @@ async fn save_search_settings(
     api_key: Option<String>,
     safe_search: Option<bool>,
     max_results: Option<u32>,
-) -> Result<Value, String> {
-    state.bridge.call("save_search_settings", serde_json::json!({
+) -> Result<Value, SemblanceError> {
+    state.bridge()?.call("save_search_settings", serde_json::json!({
         "enabled": enabled,
         "provider": provider,
         "apiKey": api_key,
@@ -2573,20 +5114,144 @@ async fn save_search_settings(
 async fn test_brave_api_key(
     state: tauri::State<'_, AppBridge>,
     api_key: String,
-) -> Result<Value, String> {
-    state.bridge.call("test_brave_api_key", serde_json::json!({ "apiKey": api_key })).await
+) -> Result<Value, SemblanceError> {
+    state.bridge()?.call("test_brave_api_key", serde_json::json!({ "apiKey": api_key })).await
 }
 
 // ─── Sidecar / IPC Bridge Commands ──────────────────────────────────────
 
+/// Recent state-carrying sidecar events (status, indexing and download progress)
+/// for a window that opened after they were emitted, oldest first. `channels`
+/// filters by event name without the `semblance://` prefix. Token streams are
+/// never replayed.
+#[tauri::command]
+async fn replay_recent_events(
+    replay: tauri::State<'_, event_replay::SharedEventReplay>,
+    channels: Option<Vec<String>>,
+) -> Result<Vec<event_replay::RecordedEvent>, SemblanceError> {
+    let buffer = replay.lock().unwrap_or_else(|e| e.into_inner());
+    Ok(buffer.recent(channels.as_deref()))
+}
+
+/// Method prefixes `invoke_sidecar` may call: read-only endpoints, each a
+/// namespace plus a read verb, so `network:get` allows `network:getTimeline`
+/// but not `network:addBlocklistEntry`. Anything that changes state, sends or
+/// deletes gets a command of its own.
+const SIDECAR_PASSTHROUGH_PREFIXES: &[&str] = &[
+    "calendar:detect",
+    "calendar:get",
+    "contacts:get",
+    "contacts:list",
+    "contacts:search",
+    "digest:get",
+    "digest:list",
+    "finance:get",
+    "inbox:get",
+    "insight:get",
+    "knowledge:list",
+    "network:get",
+    "routing:get",
+];
+
+/// Whether `invoke_sidecar` may call `method`. The prefix must end a word of
+/// the camelCase method name: `inbox:get` doesn't allow `inbox:getaway`.
+fn passthrough_allowed(method: &str) -> bool {
+    SIDECAR_PASSTHROUGH_PREFIXES.iter().any(|prefix| {
+        method
+            .strip_prefix(prefix)
+            .is_some_and(|rest| rest.is_empty() || rest.starts_with(|c: char| c.is_ascii_uppercase()))
+    })
+}
+
+/// Methods the frontend calls through `sidecar_request` and `ipc_send`, which
+/// predate `invoke_sidecar`, beyond the read-only passthrough prefixes. Each is
+/// one a screen uses today; anything else needs a command of its own.
+const SIDECAR_REQUEST_METHODS: &[&str] = &[
+    "audit_get_chain_status",
+    "binary_allowlist_list",
+    "binary_allowlist_remove",
+    "biometric:check",
+    "biometric:test",
+    "channel_list",
+    "clear_all_data",
+    "clear_knowledge_data",
+    "connector.auth",
+    "connector.disconnect",
+    "connector.sync",
+    "dark_pattern_get_flags",
+    "get_connected_services",
+    "get_knowledge_stats",
+    "get_model_status",
+    "hw_key_get_info",
+    "import.run",
+    "network_generate_connect_code",
+    "network_peer_connect",
+    "network_peer_disconnect",
+    "network_peer_sharing_config",
+    "network_peers_list",
+    "pref_clear_session",
+    "pref_delete",
+    "pref_get",
+    "pref_reset_all",
+    "pref_set",
+    "preference_confirm",
+    "preference_deny",
+    "preference_get_high_confidence",
+    "preference_list",
+    "session_delete",
+    "session_list",
+    "set_ai_name",
+    "skill_disable",
+    "skill_enable",
+    "skill_list",
+    "skill_uninstall",
+    "sync:trigger",
+    "tunnel_generate_pairing_code",
+    "tunnel_list_paired_devices",
+];
+
+/// Whether `sidecar_request` or `ipc_send` may call `method`.
+fn sidecar_request_allowed(method: &str) -> bool {
+    passthrough_allowed(method) || SIDECAR_REQUEST_METHODS.contains(&method)
+}
+
+fn check_sidecar_request(command: &str, method: &str) -> Result<(), SemblanceError> {
+    if sidecar_request_allowed(method) {
+        return Ok(());
+    }
+    Err(SemblanceError::Validation {
+        field: "method".to_string(),
+        message: format!("Sidecar method {} can't be called through {}", method, command),
+    })
+}
+
+/// Call a read-only sidecar method that has no command of its own, so new
+/// endpoints don't need a Rust wrapper. Only methods under
+/// `SIDECAR_PASSTHROUGH_PREFIXES` are allowed.
+#[tauri::command]
+async fn invoke_sidecar(
+    state: tauri::State<'_, AppBridge>,
+    method: String,
+    params: Option<Value>,
+) -> Result<Value, SemblanceError> {
+    if !passthrough_allowed(&method) {
+        return Err(SemblanceError::Validation {
+            field: "method".to_string(),
+            message: format!("Sidecar method {} can't be called through invoke_sidecar", method),
+        });
+    }
+    state.bridge()?.call(&method, params.unwrap_or(Value::Null)).await
+}
+
 #[tauri::command]
 async fn sidecar_request(
     state: tauri::State<'_, AppBridge>,
     request: Value,
-) -> Result<Value, String> {
+) -> Result<Value, SemblanceError> {
     let method = request.get("method").and_then(|v| v.as_str()).unwrap_or("unknown").to_string();
+    check_sidecar_request("sidecar_request", &method)?;
     let params = request.get("params").cloned().unwrap_or(Value::Null);
-    state.bridge.call(&method, params).await
+    state.bridge()?.call(&method, params).await
 }
 
 #[tauri::command]
@@ -2594,234 +5259,54 @@ async fn ipc_send(
     state: tauri::State<'_, AppBridge>,
     action: Option<String>,
     method: Option<String>,
-    params: Option<Value>,
-) -> Result<Value, String> {
-    let method_str = method.or(action).unwrap_or_else(|| "unknown".to_string());
-    state.bridge.call(&method_str, params.unwrap_or(Value::Null)).await
-}
-
-// ─── Upgrade Email Capture ───────────────────────────────────────────────
-
-#[tauri::command]
-async fn upgrade_submit_email(
-    state: tauri::State<'_, AppBridge>,
-    email: String,
-) -> Result<Value, String> {
-    state.bridge.call("upgrade_submit_email", serde_json::json!({ "email": email })).await
-}
-
-// ─── Application Entry Point ───────────────────────────────────────────────
-
-#[cfg_attr(mobile, tauri::mobile_entry_point)]
-pub fn run() {
-    tauri::Builder::default()
-        .plugin(tauri_plugin_dialog::init())
-        .plugin(tauri_plugin_shell::init())
-        .plugin(tauri_plugin_updater::Builder::new().build())
-        .plugin(tauri_plugin_deep_link::init())
-        .plugin(tauri_plugin_notification::init())
-        .setup(|app| {
-            let app_handle = app.handle().clone();
-
-            // Enable devtools in release builds for debugging
-            #[cfg(debug_assertions)]
-            {
-                if let Some(window) = app.get_webview_window("main") {
-                    window.open_devtools();
-                }
-            }
-            // Also open devtools in release if SEMBLANCE_DEBUG env is set
-            if std::env::var("SEMBLANCE_DEBUG").is_ok() {
-                if let Some(window) = app.get_webview_window("main") {
-                    window.open_devtools();
-                }
-            }
-
-            // Kill stale node.exe sidecar processes from previous sessions
-            // This prevents named pipe conflicts and locked SQLite databases
-            #[cfg(target_os = "windows")]
-            {
-                use std::process::Command as StdCommand;
-                // Use wmic to find node.exe processes running bridge.cjs from a previous Semblance session
-                if let Ok(output) = StdCommand::new("wmic")
-                    .args(["process", "where", "CommandLine like '%semblance%bridge.cjs%'", "get", "ProcessId", "/value"])
-                    .creation_flags(0x08000000) // CREATE_NO_WINDOW
-                    .output()
-                {
-                    let stdout = String::from_utf8_lossy(&output.stdout);
-                    for line in stdout.lines() {
-                        if let Some(pid_str) = line.strip_prefix("ProcessId=") {
-                            if let Ok(pid) = pid_str.trim().parse::<u32>() {
-                                eprintln!("[tauri] Killing stale sidecar process PID={}", pid);
-                                let _ = StdCommand::new("taskkill")
-                                    .args(["/F", "/PID", &pid.to_string()])
-                                    .creation_flags(0x08000000)
-                                    .output();
-                            }
-                        }
-                    }
-                }
-            }
-
-            // System tray setup with right-click menu
-            let tray_menu = tauri::menu::MenuBuilder::new(app)
-                .text("show", "Show Semblance")
-                .separator()
-                .text("quit", "Quit")
-                .build()?;
-
-            let _tray = tauri::tray::TrayIconBuilder::new()
-                .tooltip("Semblance — Local Only")
-                .menu(&tray_menu)
-                .show_menu_on_left_click(false)
-                .on_menu_event(|app, event| {
-                    match event.id().as_ref() {
-                        "show" => {
-                            if let Some(window) = app.get_webview_window("main") {
-                                let _ = window.show();
-                                let _ = window.set_focus();
-                            }
-                        }
-                        "quit" => {
-                            // Graceful shutdown: tell sidecar to clean up, then exit
-                            let app_clone = app.clone();
-                            tauri::async_runtime::spawn(async move {
-                                if let Some(bridge) = app_clone.try_state::<AppBridge>() {
-                                    bridge.bridge.shutdown().await;
-                                    eprintln!("[tauri] Sidecar shut down cleanly");
-                                }
-                                app_clone.exit(0);
-                            });
-                        }
-                        _ => {}
-                    }
-                })
-                .on_tray_icon_event(|tray, event| {
-                    if let tauri::tray::TrayIconEvent::Click {
-                        button: tauri::tray::MouseButton::Left,
-                        ..
-                    } = event
-                    {
-                        let app = tray.app_handle();
-                        if let Some(window) = app.get_webview_window("main") {
-                            let _ = window.show();
-                            let _ = window.set_focus();
-                        }
-                    }
-                })
-                .build(app)?;
-
-            // Deep link handler: listen for semblance:// URLs and forward to frontend
-            let app_for_deeplink = app_handle.clone();
-            app.listen("deep-link://new-url", move |event: tauri::Event| {
-                let payload_str = event.payload();
-                // The payload is a JSON string containing the URL(s)
-                if let Ok(urls) = serde_json::from_str::<Vec<String>>(payload_str) {
-                    for url in urls {
-                        // Parse semblance://activate?token=xxx or semblance://activate?key=sem_xxx
-                        if url.starts_with("semblance://activate") {
-                            if let Ok(parsed) = url::Url::parse(&url.replace("semblance://", "https://")) {
-                                // License key activation (sem_ format)
-                                if let Some(key) = parsed.query_pairs().find(|(k, _)| k == "key").map(|(_, v)| v.to_string()) {
-                                    eprintln!("[tauri] Deep link received: license key activation");
-                                    let _ = app_for_deeplink.emit("license-activate", serde_json::json!({ "key": key }));
-                                }
-                                // Founding token activation (JWT format)
-                                else if let Some(token) = parsed.query_pairs().find(|(k, _)| k == "token").map(|(_, v)| v.to_string()) {
-                                    eprintln!("[tauri] Deep link received: founding activation");
-                                    let _ = app_for_deeplink.emit("founding-activate", serde_json::json!({ "token": token }));
-                                }
-                            }
-                        }
-                    }
-                }
-            });
-
-            // AUTONOMOUS DECISION: Locate project root by walking up from the
-            // Tauri resource directory. In development, the Tauri app runs from
-            // packages/desktop/src-tauri/, so the project root is 3 levels up.
-            // In production, the sidecar is bundled alongside the binary.
-            let project_root = std::env::current_dir()
-                .unwrap_or_else(|_| PathBuf::from("."))
-                .join("packages")
-                .join("desktop")
-                .join("src-tauri");
-
-            // Walk up to find the project root (directory containing package.json with workspaces)
-            let project_root = find_project_root(&project_root).unwrap_or_else(|| {
-                // Fallback: assume we're running from project root
-                std::env::current_dir().unwrap_or_else(|_| PathBuf::from("."))
-            });
-
-            // Create NativeRuntime for direct llama.cpp inference
-            let native_runtime = native_runtime::create_runtime();
-
-            // Spawn the sidecar asynchronously
-            let app_handle_clone = app_handle.clone();
-            tauri::async_runtime::spawn(async move {
-                match SidecarBridge::spawn(project_root, app_handle_clone.clone(), native_runtime).await {
-                    Ok(bridge) => {
-                        // CRITICAL: Manage AppBridge IMMEDIATELY after spawn, BEFORE init.
-                        // This allows IPC commands (model downloads, hardware detection) to work
-                        // while init (LanceDB, Ollama checks) is still in progress.
-                        // The sidecar's NDJSON stdin/stdout loop is already running.
-                        app_handle_clone.manage(AppBridge { bridge });
-                        eprintln!("[tauri] AppBridge managed — IPC commands available");
-
-                        // Now initialize Core and Gateway asynchronously
-                        let app_for_init = app_handle_clone.clone();
-                        let bridge_state = app_handle_clone.state::<AppBridge>();
-                        match bridge_state.bridge.call("initialize", Value::Null).await {
-                            Ok(init_result) => {
-                                let _ = app_for_init.emit(
-                                    "semblance://status-update",
-                                    &init_result,
-                                );
-                                eprintln!(
-                                    "[tauri] Sidecar initialized: {}",
-                                    serde_json::to_string(&init_result).unwrap_or_default()
-                                );
-                            }
-                            Err(e) => {
-                                eprintln!("[tauri] Sidecar initialization failed: {}", e);
-                                let _ = app_for_init.emit(
-                                    "semblance://status-update",
-                                    serde_json::json!({
-                                        "ollamaStatus": "disconnected",
-                                        "error": format!("Initialization failed: {}", e)
-                                    }),
-                                );
-                            }
-                        }
-                    }
-                    Err(e) => {
-                        eprintln!("[tauri] Failed to spawn sidecar: {}", e);
-                        let _ = app_handle_clone.emit(
-                            "semblance://status-update",
-                            serde_json::json!({
-                                "ollamaStatus": "disconnected",
-                                "error": format!("Sidecar spawn failed: {}", e)
-                            }),
-                        );
-                    }
-                }
-            });
+    params: Option<Value>,
+) -> Result<Value, SemblanceError> {
+    let method_str = method.or(action).unwrap_or_else(|| "unknown".to_string());
+    check_sidecar_request("ipc_send", &method_str)?;
+    state.bridge()?.call(&method_str, params.unwrap_or(Value::Null)).await
+}
 
-            Ok(())
-        })
-        .on_window_event(|window, event| {
-            if let tauri::WindowEvent::CloseRequested { api, .. } = event {
-                // Minimize to tray instead of quitting
-                api.prevent_close();
-                let _ = window.hide();
-            }
-        })
-        .invoke_handler(tauri::generate_handler![
+// ─── Upgrade Email Capture ───────────────────────────────────────────────
+
+#[tauri::command]
+async fn upgrade_submit_email(
+    state: tauri::State<'_, AppBridge>,
+    email: String,
+) -> Result<Value, SemblanceError> {
+    state.bridge()?.call("upgrade_submit_email", serde_json::json!({ "email": email })).await
+}
+
+// ─── Command Registry ───────────────────────────────────────────────────────
+
+/// The one list of registered commands. `with_commands!(path::to::macro)` expands
+/// to `macro![...]`, so `generate_handler!` and `list_commands` can't drift apart.
+macro_rules! with_commands {
+    ($($m:ident)::+) => {
+        $($m)::+![
             send_message,
+            cancel_message,
+            cancel_request,
+            get_stream_stats,
+            get_bridge_state,
+            get_sidecar_health,
+            get_bridge_metrics,
+            get_sidecar_logs,
+            set_restart_unresponsive_sidecar,
+            get_restart_unresponsive_sidecar,
             get_ollama_status,
+            get_active_inference_backend,
             select_model,
             start_indexing,
+            set_indexing_exclusions,
+            get_indexing_exclusions,
+            set_model_idle_timeout,
+            get_model_idle_timeout,
+            set_low_memory_threshold,
+            get_low_memory_threshold,
             get_indexing_status,
+            pause_indexing,
+            resume_indexing,
+            cancel_indexing,
             get_action_log,
             get_privacy_status,
             set_user_name,
@@ -2830,6 +5315,7 @@ pub fn run() {
             get_autonomy_config,
             get_indexed_directories,
             get_knowledge_stats,
+            get_index_fingerprint,
             get_chat_history,
             set_onboarding_complete,
             get_onboarding_complete,
@@ -2848,8 +5334,10 @@ pub fn run() {
             archive_emails,
             send_email_action,
             draft_email_action,
+            preview_action,
             undo_action,
             dismiss_insight,
+            get_insight_evidence,
             get_pending_actions,
             approve_action,
             reject_action,
@@ -2863,6 +5351,8 @@ pub fn run() {
             get_subscriptions,
             update_subscription_status,
             get_subscription_summary,
+            merge_subscription_candidates,
+            confirm_subscription_merge,
             // Autonomy Escalation (Step 7)
             check_escalations,
             respond_to_escalation,
@@ -2877,6 +5367,9 @@ pub fn run() {
             get_active_connections,
             get_network_statistics,
             get_network_allowlist,
+            get_blocklist,
+            add_blocklist_entry,
+            remove_blocklist_entry,
             get_unauthorized_attempts,
             get_connection_timeline,
             get_connection_history,
@@ -2888,7 +5381,62 @@ pub fn run() {
             assess_task,
             // Hardware & Runtime (Step 9)
             detect_hardware,
+            get_hardware_profile,
+            refresh_available_memory,
+            set_model_storage_dir,
+            get_model_storage_dir,
             get_live_hardware_stats,
+            recommend_models,
+            recommend_local_models,
+            export_hardware_report,
+            // Storage
+            get_storage_breakdown,
+            // Native Runtime
+            classify_text,
+            classify_choice,
+            generate_in_session,
+            reset_session,
+            json_schema_to_gbnf,
+            benchmark_embedding,
+            self_test_runtime,
+            warmup_runtime,
+            set_warmup_after_load,
+            set_skip_memory_check,
+            test_chat_template,
+            summarize_document,
+            get_layer_placement,
+            get_model_info,
+            get_backend_info,
+            set_flash_attention,
+            set_max_prompt_tokens,
+            get_max_prompt_tokens,
+            set_resident_models,
+            get_resident_models,
+            recommend_context_length,
+            get_parallel_limits,
+            inspect_model,
+            probe_model_file,
+            validate_model_file,
+            verify_model_manifest,
+            cancel_manifest_verification,
+            cancel_model_load,
+            load_draft_model,
+            unload_draft_model,
+            is_model_loaded,
+            require_no_model_loaded,
+            load_tokenizer,
+            tokenizer_count,
+            count_tokens,
+            tokenize,
+            detokenize,
+            trim_session,
+            capture_generation_repro,
+            get_sampler_state,
+            set_sampler_state,
+            set_max_concurrent_generations,
+            set_default_generation_priority,
+            get_generation_queue,
+            get_sampler_modes,
             // Founding Member Activation
             activate_founding_token,
             activate_license_key,
@@ -3040,10 +5588,311 @@ pub fn run() {
             test_brave_api_key,
             // Sidecar / IPC Bridge
             sidecar_request,
+            invoke_sidecar,
             ipc_send,
+            list_commands,
+            replay_recent_events,
             // Upgrade Email
             upgrade_submit_email,
-        ])
+        ]
+    };
+}
+
+macro_rules! command_names {
+    ($($cmd:ident),* $(,)?) => {
+        &[$(stringify!($cmd)),*]
+    };
+}
+
+const COMMAND_NAMES: &[&str] = with_commands!(command_names);
+
+/// Names of every registered command, so the frontend can feature-detect before
+/// invoking. Parameter names aren't recoverable from the registration, so only
+/// names are listed.
+#[tauri::command]
+async fn list_commands() -> Result<Vec<String>, SemblanceError> {
+    Ok(COMMAND_NAMES.iter().map(|name| name.to_string()).collect())
+}
+
+// ─── Application Entry Point ───────────────────────────────────────────────
+
+#[cfg_attr(mobile, tauri::mobile_entry_point)]
+pub fn run() {
+    tauri::Builder::default()
+        .plugin(tauri_plugin_dialog::init())
+        .plugin(tauri_plugin_shell::init())
+        .plugin(tauri_plugin_updater::Builder::new().build())
+        .plugin(tauri_plugin_deep_link::init())
+        .plugin(tauri_plugin_notification::init())
+        .setup(|app| {
+            let app_handle = app.handle().clone();
+
+            // Enable devtools in release builds for debugging
+            #[cfg(debug_assertions)]
+            {
+                if let Some(window) = app.get_webview_window("main") {
+                    window.open_devtools();
+                }
+            }
+            // Also open devtools in release if SEMBLANCE_DEBUG env is set
+            if std::env::var("SEMBLANCE_DEBUG").is_ok() {
+                if let Some(window) = app.get_webview_window("main") {
+                    window.open_devtools();
+                }
+            }
+
+            // Kill stale node.exe sidecar processes from previous sessions
+            // This prevents named pipe conflicts and locked SQLite databases
+            #[cfg(target_os = "windows")]
+            {
+                use std::process::Command as StdCommand;
+                // Use wmic to find node.exe processes running bridge.cjs from a previous Semblance session
+                if let Ok(output) = StdCommand::new("wmic")
+                    .args(["process", "where", "CommandLine like '%semblance%bridge.cjs%'", "get", "ProcessId", "/value"])
+                    .creation_flags(0x08000000) // CREATE_NO_WINDOW
+                    .output()
+                {
+                    let stdout = String::from_utf8_lossy(&output.stdout);
+                    for line in stdout.lines() {
+                        if let Some(pid_str) = line.strip_prefix("ProcessId=") {
+                            if let Ok(pid) = pid_str.trim().parse::<u32>() {
+                                eprintln!("[tauri] Killing stale sidecar process PID={}", pid);
+                                let _ = StdCommand::new("taskkill")
+                                    .args(["/F", "/PID", &pid.to_string()])
+                                    .creation_flags(0x08000000)
+                                    .output();
+                            }
+                        }
+                    }
+                }
+            }
+
+            // System tray setup with right-click menu
+            let tray_menu = tauri::menu::MenuBuilder::new(app)
+                .text("show", "Show Semblance")
+                .separator()
+                .text("quit", "Quit")
+                .build()?;
+
+            let _tray = tauri::tray::TrayIconBuilder::new()
+                .tooltip("Semblance — Local Only")
+                .menu(&tray_menu)
+                .show_menu_on_left_click(false)
+                .on_menu_event(|app, event| {
+                    match event.id().as_ref() {
+                        "show" => {
+                            if let Some(window) = app.get_webview_window("main") {
+                                let _ = window.show();
+                                let _ = window.set_focus();
+                            }
+                        }
+                        "quit" => {
+                            // Graceful shutdown: tell sidecar to clean up, then exit
+                            let app_clone = app.clone();
+                            tauri::async_runtime::spawn(async move {
+                                if let Ok(bridge) = app_clone.state::<AppBridge>().bridge() {
+                                    bridge.shutdown().await;
+                                    eprintln!("[tauri] Sidecar shut down cleanly");
+                                }
+                                app_clone.exit(0);
+                            });
+                        }
+                        _ => {}
+                    }
+                })
+                .on_tray_icon_event(|tray, event| {
+                    if let tauri::tray::TrayIconEvent::Click {
+                        button: tauri::tray::MouseButton::Left,
+                        ..
+                    } = event
+                    {
+                        let app = tray.app_handle();
+                        if let Some(window) = app.get_webview_window("main") {
+                            let _ = window.show();
+                            let _ = window.set_focus();
+                        }
+                    }
+                })
+                .build(app)?;
+
+            // Deep link handler: listen for semblance:// URLs and forward to frontend
+            let app_for_deeplink = app_handle.clone();
+            app.listen("deep-link://new-url", move |event: tauri::Event| {
+                let payload_str = event.payload();
+                // The payload is a JSON string containing the URL(s)
+                if let Ok(urls) = serde_json::from_str::<Vec<String>>(payload_str) {
+                    for url in urls {
+                        // Parse semblance://activate?token=xxx or semblance://activate?key=sem_xxx
+                        if url.starts_with("semblance://activate") {
+                            if let Ok(parsed) = url::Url::parse(&url.replace("semblance://", "https://")) {
+                                // License key activation (sem_ format)
+                                if let Some(key) = parsed.query_pairs().find(|(k, _)| k == "key").map(|(_, v)| v.to_string()) {
+                                    eprintln!("[tauri] Deep link received: license key activation");
+                                    let _ = app_for_deeplink.emit("license-activate", serde_json::json!({ "key": key }));
+                                }
+                                // Founding token activation (JWT format)
+                                else if let Some(token) = parsed.query_pairs().find(|(k, _)| k == "token").map(|(_, v)| v.to_string()) {
+                                    eprintln!("[tauri] Deep link received: founding activation");
+                                    let _ = app_for_deeplink.emit("founding-activate", serde_json::json!({ "token": token }));
+                                }
+                            }
+                        }
+                    }
+                }
+            });
+
+            // AUTONOMOUS DECISION: Locate project root by walking up from the
+            // Tauri resource directory. In development, the Tauri app runs from
+            // packages/desktop/src-tauri/, so the project root is 3 levels up.
+            // In production, the sidecar is bundled alongside the binary.
+            let project_root = std::env::current_dir()
+                .unwrap_or_else(|_| PathBuf::from("."))
+                .join("packages")
+                .join("desktop")
+                .join("src-tauri");
+
+            // Walk up to find the project root (directory containing package.json with workspaces)
+            let project_root = find_project_root(&project_root).unwrap_or_else(|| {
+                // Fallback: assume we're running from project root
+                std::env::current_dir().unwrap_or_else(|_| PathBuf::from("."))
+            });
+
+            // Create NativeRuntime for direct llama.cpp inference
+            let native_runtime = native_runtime::create_runtime();
+            // Managed separately from AppBridge so runtime commands work even
+            // before (or without) a healthy sidecar.
+            app.manage(native_runtime.clone());
+            let desktop_settings = settings::SettingsState::load(&storage::data_dir());
+            let persisted = tauri::async_runtime::block_on(desktop_settings.get());
+            if let Ok(mut rt) = native_runtime.try_write() {
+                let progress_app = app_handle.clone();
+                rt.set_load_progress_callback(Some(Arc::new(move |kind: &str, path: &std::path::Path, progress: f32| {
+                    let _ = progress_app.emit(
+                        "semblance://model-load-progress",
+                        serde_json::json!({ "kind": kind, "path": path.to_string_lossy(), "progress": progress }),
+                    );
+                })));
+                rt.set_flash_attention(persisted.flash_attention);
+                rt.set_max_prompt_tokens(persisted.max_prompt_tokens);
+                rt.set_warmup_after_load(persisted.warmup_after_load);
+                rt.set_skip_memory_check(persisted.skip_memory_check);
+                rt.set_resident_models(persisted.max_resident_models.unwrap_or(1), persisted.resident_models_budget_mb);
+                // Outside the runtime's lock, which a load holds.
+                app.manage(rt.load_cancel());
+            }
+            let generation_scheduler = Arc::new(scheduler::GenerationScheduler::new());
+            app.manage(generation_scheduler.clone());
+            app.manage(desktop_settings);
+            let event_replay = event_replay::create_buffer();
+            app.manage(event_replay.clone());
+            app.manage(model_verify::VerifyControl::default());
+            app.manage(Arc::new(hardware::HardwareCache::default()));
+
+            // Unload the reasoning model after the configured idle period. Skipped
+            // while any generation is running or queued.
+            let idle_app = app_handle.clone();
+            let idle_runtime = native_runtime.clone();
+            let idle_scheduler = generation_scheduler.clone();
+            tauri::async_runtime::spawn(async move {
+                let mut interval = tokio::time::interval(std::time::Duration::from_secs(15));
+                loop {
+                    interval.tick().await;
+                    let Some(secs) = idle_app.state::<settings::SettingsState>().get().await.model_idle_timeout_secs else {
+                        continue;
+                    };
+                    let queue = idle_scheduler.snapshot();
+                    if !queue.running.is_empty() || !queue.waiting.is_empty() {
+                        continue;
+                    }
+                    let timeout = std::time::Duration::from_secs(secs);
+                    if idle_runtime.read().await.reasoning_idle_for() < timeout {
+                        continue;
+                    }
+                    let unloaded = idle_runtime.write().await.unload_reasoning_if_idle(timeout);
+                    if let Some(path) = unloaded {
+                        eprintln!("[tauri] Unloaded idle reasoning model after {}s: {:?}", secs, path);
+                        let _ = idle_app.emit(
+                            "semblance://model-unloaded-idle",
+                            serde_json::json!({ "model_path": path.display().to_string(), "idle_timeout_secs": secs }),
+                        );
+                    }
+                }
+            });
+
+            // Alert the UI when available RAM drops below the configured threshold,
+            // so it can suggest unloading a model or pausing indexing.
+            let memory_app = app_handle.clone();
+            tauri::async_runtime::spawn(async move {
+                let mut monitor = hardware::LowMemoryMonitor::default();
+                let mut interval = tokio::time::interval(std::time::Duration::from_secs(10));
+                loop {
+                    interval.tick().await;
+                    let Some(threshold_mb) = memory_app.state::<settings::SettingsState>().get().await.low_memory_threshold_mb else {
+                        continue;
+                    };
+                    let available_mb = hardware::available_memory_bytes() / (1024 * 1024);
+                    if monitor.observe(available_mb, threshold_mb, std::time::Instant::now()) {
+                        eprintln!("[tauri] Low memory: {} MB available (threshold {} MB)", available_mb, threshold_mb);
+                        let _ = memory_app.emit(
+                            "semblance://low-memory",
+                            serde_json::json!({ "available_ram_mb": available_mb, "threshold_mb": threshold_mb }),
+                        );
+                    }
+                }
+            });
+
+            // Managed before the spawn so sidecar-backed commands report
+            // "still starting" (see `get_bridge_state`) instead of failing to
+            // extract their state.
+            app_handle.manage(AppBridge::default());
+
+            // Spawn the sidecar asynchronously
+            let app_handle_clone = app_handle.clone();
+            tauri::async_runtime::spawn(async move {
+                let bridge_state = app_handle_clone.state::<AppBridge>();
+                match SidecarBridge::spawn(project_root, app_handle_clone.clone(), native_runtime, generation_scheduler, event_replay.clone()).await {
+                    Ok(bridge) => {
+                        // CRITICAL: Publish the bridge IMMEDIATELY after spawn, BEFORE init.
+                        // This allows IPC commands (model downloads, hardware detection) to work
+                        // while init (LanceDB, Ollama checks) is still in progress.
+                        // The sidecar's NDJSON stdin/stdout loop is already running.
+                        let bridge = bridge_state.bridge.get_or_init(|| bridge);
+                        eprintln!("[tauri] AppBridge ready — IPC commands available");
+
+                        // Now initialize Core and Gateway asynchronously
+                        initialize_sidecar(&app_handle_clone, bridge, &event_replay).await;
+                    }
+                    Err(e) => {
+                        eprintln!("[tauri] Failed to spawn sidecar: {}", e);
+                        let _ = bridge_state.spawn_error.set(e.clone());
+                        event_replay::emit(
+                            &app_handle_clone,
+                            &event_replay,
+                            "status-update",
+                            &serde_json::json!({
+                                "ollamaStatus": "disconnected",
+                                "error": format!("Sidecar spawn failed: {}", e)
+                            }),
+                        );
+                        emit_sidecar_failed(
+                            &app_handle_clone,
+                            &event_replay,
+                            &SidecarFailed { epoch: None, stage: SidecarFailureStage::Spawn, reason: e, restarting: false },
+                        );
+                    }
+                }
+            });
+
+            Ok(())
+        })
+        .on_window_event(|window, event| {
+            if let tauri::WindowEvent::CloseRequested { api, .. } = event {
+                // Minimize to tray instead of quitting
+                api.prevent_close();
+                let _ = window.hide();
+            }
+        })
+        .invoke_handler(with_commands!(tauri::generate_handler))
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
 }
@@ -3091,3 +5940,256 @@ fn find_project_root(start: &std::path::Path) -> Option<PathBuf> {
     }
     None
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::{Duration, Instant};
+
+    fn pending_with(id: u64) -> (PendingRequests, oneshot::Receiver<Result<Value, SemblanceError>>) {
+        let (tx, rx) = oneshot::channel();
+        (Arc::new(Mutex::new(HashMap::from([(id, tx)]))), rx)
+    }
+
+    #[tokio::test]
+    async fn test_read_sidecar_stdout_routes_responses_by_epoch() {
+        let id: u64 = (3 << REQUEST_ID_EPOCH_SHIFT) | 1;
+        let stale: u64 = (2 << REQUEST_ID_EPOCH_SHIFT) | 1;
+        let (pending, rx) = pending_with(id);
+        let output = format!(
+            "{{\"id\":{stale},\"result\":\"stale\"}}\nnot json\n{{\"event\":\"status-update\",\"data\":{{}}}}\n{{\"type\":\"callback\",\"id\":\"cb-1\",\"method\":\"native_status\"}}\n{{\"id\":{id},\"result\":\"ok\"}}\n"
+        );
+        let mut others = Vec::new();
+        read_sidecar_stdout(output.as_bytes(), &pending, 3, SIDECAR_MAX_LINE_BYTES, |msg| others.push(msg)).await;
+
+        assert_eq!(rx.await.unwrap(), Ok(Value::from("ok")));
+        assert_eq!(others.len(), 2);
+        assert_eq!(others[0]["event"], "status-update");
+        assert_eq!(others[1]["id"], "cb-1");
+    }
+
+    #[tokio::test]
+    async fn test_read_capped_line_skips_oversized_lines() {
+        let input = format!("{{\"a\":1}}\r\n{}\n{{\"b\":2}}\nunterminated", "x".repeat(100));
+        // A tiny buffer so lines span several fill_buf calls.
+        let mut reader = BufReader::with_capacity(8, input.as_bytes());
+        let mut frames = Vec::new();
+        while let Some(frame) = read_capped_line(&mut reader, 32).await.unwrap() {
+            frames.push(frame);
+        }
+        assert_eq!(
+            frames,
+            vec![
+                SidecarLine::Line("{\"a\":1}".to_string()),
+                SidecarLine::Oversized { len: 100, head: "x".repeat(OVERSIZED_HEAD_BYTES) },
+                SidecarLine::Line("{\"b\":2}".to_string()),
+                SidecarLine::Line("unterminated".to_string()),
+            ]
+        );
+
+        // An oversized response fails its request at once instead of at the timeout.
+        let id: u64 = (1 << REQUEST_ID_EPOCH_SHIFT) | 1;
+        let (pending, rx) = pending_with(id);
+        let output = format!("{{\"id\":{id},\"result\":\"{}\"}}\n", "x".repeat(64));
+        read_sidecar_stdout(output.as_bytes(), &pending, 1, 64, |_| {}).await;
+        let detail = format!("Sidecar response was {} bytes, over the 64-byte limit", output.len() - 1);
+        assert_eq!(rx.await.unwrap(), Err(SemblanceError::Protocol { detail }));
+        assert!(pending.lock().await.is_empty());
+
+        assert_eq!(oversized_response_id(&format!("{{\"id\":{id},\"result\":")), Some(id));
+        assert_eq!(oversized_response_id("{\"type\":\"callback\",\"id\":\"cb-1\""), None);
+        assert_eq!(oversized_response_id("{\"event\":\"chat-token\",\"data\":{\"id\":7,"), None);
+        assert_eq!(oversized_response_id("{\"id\":1234"), None);
+    }
+
+    #[test]
+    fn test_passthrough_allows_only_listed_read_methods() {
+        assert!(passthrough_allowed("inbox:getItems"));
+        assert!(passthrough_allowed("network:getTimeline"));
+        assert!(passthrough_allowed("contacts:search"));
+        assert!(!passthrough_allowed("network:addBlocklistEntry"));
+        assert!(!passthrough_allowed("finance:updateSubscriptionStatus"));
+        assert!(!passthrough_allowed("email:archive"));
+        assert!(!passthrough_allowed("inbox:getaway"));
+        assert!(!passthrough_allowed("xinbox:getItems"));
+    }
+
+    #[test]
+    fn test_sidecar_request_allows_only_listed_methods() {
+        assert!(sidecar_request_allowed("pref_get"));
+        assert!(sidecar_request_allowed("connector.sync"));
+        assert!(sidecar_request_allowed("contacts:getStats"));
+        assert!(!sidecar_request_allowed("knowledge:delete"));
+        assert!(!sidecar_request_allowed("network:addBlocklistEntry"));
+        assert!(!sidecar_request_allowed("finance:mergeSubscriptions"));
+        assert!(!sidecar_request_allowed("unknown"));
+        let error = check_sidecar_request("ipc_send", "email:send").unwrap_err();
+        assert_eq!(error.to_string(), "Sidecar method email:send can't be called through ipc_send");
+    }
+
+    #[test]
+    fn test_sidecar_lifecycle_payloads() {
+        let failed = SidecarFailed {
+            epoch: Some(2),
+            stage: SidecarFailureStage::Exited,
+            reason: "Sidecar process exited unexpectedly".to_string(),
+            restarting: true,
+        };
+        assert_eq!(
+            serde_json::to_value(&failed).unwrap(),
+            serde_json::json!({"epoch": 2, "stage": "exited", "reason": "Sidecar process exited unexpectedly", "restarting": true})
+        );
+        let ready = SidecarReady { epoch: 1, init_result: serde_json::json!({"ollamaStatus": "connected"}), spawn_duration_ms: 850 };
+        assert_eq!(
+            serde_json::to_value(&ready).unwrap(),
+            serde_json::json!({"epoch": 1, "initResult": {"ollamaStatus": "connected"}, "spawnDurationMs": 850})
+        );
+    }
+
+    #[test]
+    fn test_stderr_tail_keeps_last_lines() {
+        let tail = StderrTail::default();
+        for i in 0..SIDECAR_STDERR_LINES + 3 {
+            tail.push(format!("line {}", i));
+        }
+        let all = tail.last(usize::MAX);
+        assert_eq!(all.len(), SIDECAR_STDERR_LINES);
+        assert_eq!(all[0], "line 3");
+        assert_eq!(tail.last(2), vec![format!("line {}", SIDECAR_STDERR_LINES + 1), format!("line {}", SIDECAR_STDERR_LINES + 2)]);
+        assert!(tail.last(0).is_empty());
+    }
+
+    #[test]
+    fn test_cancellable_request_finished_before_its_response_is_seen() {
+        let mut requests = CancellableRequests::default();
+
+        // Answered, still running: cancellable until its end event.
+        requests.register(1, "send_message");
+        assert!(requests.answered(1, Some("r1")));
+        assert_eq!(requests.take("r1").map(|(id, r)| (id, r.method)), Some((1, "send_message".to_string())));
+        assert!(requests.take("r1").is_none());
+
+        // The end event is read before the caller sees the response.
+        requests.register(2, "send_message");
+        requests.finish("r2");
+        assert!(!requests.answered(2, Some("r2")));
+        assert!(requests.take("r2").is_none());
+        assert!(requests.requests.is_empty() && requests.finished_early.is_empty());
+
+        // Finished normally, then cancelled.
+        requests.register(3, "start_email_index");
+        assert!(requests.answered(3, Some("r3")));
+        requests.finish("r3");
+        assert!(requests.take("r3").is_none());
+
+        // A failed request, and end events nobody is waiting for, leave nothing behind.
+        requests.register(4, "send_message");
+        assert!(!requests.answered(4, None));
+        requests.finish("unknown");
+        assert!(requests.requests.is_empty() && requests.finished_early.is_empty());
+    }
+
+    /// A fake sidecar that reads one request and exits without answering it.
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_pending_request_fails_when_sidecar_exits_mid_request() {
+        let mut child = Command::new("sh")
+            .args(["-c", "read -r request; exit 1"])
+            .stdin(std::process::Stdio::piped())
+            .stdout(std::process::Stdio::piped())
+            .kill_on_drop(true)
+            .spawn()
+            .unwrap();
+        let stdin = child.stdin.take().unwrap();
+        let stdout = child.stdout.take().unwrap();
+
+        let (writer, jobs) = SidecarWriter::new(1);
+        let writer = std::sync::Mutex::new(writer);
+        let pending: PendingRequests = Arc::new(Mutex::new(HashMap::new()));
+        tokio::spawn(run_sidecar_writer(
+            SidecarStdin { inner: stdin, torn: false },
+            jobs,
+            pending.clone(),
+            Arc::new(InputStalls::default()),
+            Arc::new(std::sync::Mutex::new(bridge_metrics::BridgeMetrics::default())),
+        ));
+        let reader_pending = pending.clone();
+        let reader = tokio::spawn(async move {
+            serve_sidecar_stdout(stdout, &reader_pending, 1, |_| {}, || async {
+                (SemblanceError::SidecarCrashed { restarting: false }, "exited")
+            })
+            .await
+        });
+
+        let started = Instant::now();
+        // Well under `call_fire`'s 10s timeout, let alone `call`'s 120s.
+        let (_, result) = request_sidecar(&writer, &pending, "initialize", Value::Null, SIDECAR_FIRE_TIMEOUT, |_| {}).await;
+        assert_eq!(result, Err(SemblanceError::SidecarCrashed { restarting: false }));
+        assert!(started.elapsed() < Duration::from_secs(5), "request was left waiting after the sidecar exited");
+        assert_eq!(reader.await.unwrap(), "exited");
+        assert!(pending.lock().await.is_empty());
+    }
+
+    /// A fake sidecar that answers each request with the order it arrived in
+    /// and the method it named.
+    #[cfg(unix)]
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn test_concurrent_requests_are_written_in_id_order() {
+        let script = r#"n=0
+while IFS= read -r line; do
+  n=$((n + 1))
+  id=${line#*'"id":'}; id=${id%%,*}
+  method=${line#*'"method":'}; method=${method%%,*}
+  printf '{"id":%s,"result":[%s,%s]}\n' "$id" "$n" "$method"
+done"#;
+        let mut child = Command::new("sh")
+            .args(["-c", script])
+            .stdin(std::process::Stdio::piped())
+            .stdout(std::process::Stdio::piped())
+            .kill_on_drop(true)
+            .spawn()
+            .unwrap();
+        let stdin = child.stdin.take().unwrap();
+        let stdout = child.stdout.take().unwrap();
+
+        let (writer, jobs) = SidecarWriter::new(1);
+        let writer = Arc::new(std::sync::Mutex::new(writer));
+        let pending: PendingRequests = Arc::new(Mutex::new(HashMap::new()));
+        let metrics = Arc::new(std::sync::Mutex::new(bridge_metrics::BridgeMetrics::default()));
+        tokio::spawn(run_sidecar_writer(
+            SidecarStdin { inner: stdin, torn: false },
+            jobs,
+            pending.clone(),
+            Arc::new(InputStalls::default()),
+            metrics.clone(),
+        ));
+        let reader_pending = pending.clone();
+        tokio::spawn(async move {
+            read_sidecar_stdout(stdout, &reader_pending, 1, SIDECAR_MAX_LINE_BYTES, |_| {}).await;
+        });
+
+        let calls: Vec<_> = (0..100)
+            .map(|i| {
+                let (writer, pending) = (writer.clone(), pending.clone());
+                tokio::spawn(async move {
+                    let method = format!("method_{}", i);
+                    let (id, result) = request_sidecar(&writer, &pending, &method, Value::Null, Duration::from_secs(10), |_| {}).await;
+                    (id, method, result)
+                })
+            })
+            .collect();
+
+        let first_id: u64 = (1 << REQUEST_ID_EPOCH_SHIFT) | 1;
+        let mut ids = Vec::new();
+        for call in calls {
+            let (id, method, result) = call.await.unwrap();
+            let result = result.expect("request was lost");
+            // The sidecar saw requests in id order, and each got its own answer.
+            assert_eq!(result, serde_json::json!([id - first_id + 1, method]));
+            ids.push(id);
+        }
+        ids.sort_unstable();
+        assert_eq!(ids, (first_id..first_id + 100).collect::<Vec<_>>());
+        assert!(pending.lock().await.is_empty());
+    }
+}
@@ -0,0 +1,272 @@
+// Model Verification — streaming SHA-256 of downloaded model files against the catalog.
+//
+// Model files are several GB, so hashing a whole manifest takes minutes. Files
+// are hashed in fixed-size chunks with a progress callback after each chunk and
+// a cancellation flag checked between chunks, so the UI can show per-file
+// progress and stop a long run. Hashing is blocking; run it off the async runtime.
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::io::Read;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Instant;
+
+const HASH_CHUNK_BYTES: usize = 4 * 1024 * 1024;
+
+/// One downloaded model file and the checksum the catalog expects for it.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ManifestEntry {
+    pub model_id: String,
+    pub file_name: String,
+    pub path: String,
+    /// Lowercase hex. `None` when the catalog has no checksum for the model yet.
+    pub sha256: Option<String>,
+}
+
+/// Emitted as `semblance://manifest-verify-progress` while a file is hashed.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ManifestVerifyProgress {
+    pub model_id: String,
+    pub file_name: String,
+    pub bytes_hashed: u64,
+    pub total_bytes: u64,
+    /// 1-based position of this file in the manifest.
+    pub file_index: usize,
+    pub file_count: usize,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct FileVerification {
+    pub model_id: String,
+    pub file_name: String,
+    /// "verified", "mismatch", "missing", "unchecked" (no catalog checksum),
+    /// "error", or "cancelled".
+    pub status: String,
+    pub expected_sha256: Option<String>,
+    pub actual_sha256: Option<String>,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ManifestVerification {
+    pub files: Vec<FileVerification>,
+    pub verified: usize,
+    /// Files that were checked and did not match, or couldn't be read.
+    pub failed: usize,
+    pub cancelled: bool,
+    pub duration_ms: u64,
+}
+
+/// Cancellation flag for the running verification, managed as Tauri state.
+#[derive(Clone, Default)]
+pub struct VerifyControl {
+    running: Arc<AtomicBool>,
+    cancel: Arc<AtomicBool>,
+}
+
+impl VerifyControl {
+    /// Mark a verification as started. Errors if one is already running.
+    pub fn begin(&self) -> Result<VerifyRun, String> {
+        if self.running.swap(true, Ordering::SeqCst) {
+            return Err("A manifest verification is already running".to_string());
+        }
+        self.cancel.store(false, Ordering::SeqCst);
+        Ok(VerifyRun { control: self.clone() })
+    }
+
+    /// Ask the running verification to stop after the current chunk. Returns
+    /// false if nothing is running.
+    pub fn cancel(&self) -> bool {
+        if !self.running.load(Ordering::SeqCst) {
+            return false;
+        }
+        self.cancel.store(true, Ordering::SeqCst);
+        true
+    }
+}
+
+/// Held for the duration of a verification; clears the running flag on drop.
+pub struct VerifyRun {
+    control: VerifyControl,
+}
+
+impl VerifyRun {
+    pub fn cancel_flag(&self) -> &AtomicBool {
+        &self.control.cancel
+    }
+}
+
+impl Drop for VerifyRun {
+    fn drop(&mut self) {
+        self.control.running.store(false, Ordering::SeqCst);
+    }
+}
+
+/// SHA-256 of `reader`, reporting bytes hashed after each chunk. Returns `None`
+/// if `cancel` was set before the end.
+pub fn sha256_streaming(
+    mut reader: impl Read,
+    cancel: &AtomicBool,
+    mut on_progress: impl FnMut(u64),
+) -> std::io::Result<Option<String>> {
+    let mut hasher = Sha256::new();
+    let mut buf = vec![0u8; HASH_CHUNK_BYTES];
+    let mut hashed = 0u64;
+    loop {
+        if cancel.load(Ordering::Relaxed) {
+            return Ok(None);
+        }
+        let n = reader.read(&mut buf)?;
+        if n == 0 {
+            break;
+        }
+        hasher.update(&buf[..n]);
+        hashed += n as u64;
+        on_progress(hashed);
+    }
+    let digest = hasher.finalize();
+    Ok(Some(digest.iter().map(|b| format!("{:02x}", b)).collect()))
+}
+
+/// Hash every manifest entry in order. Entries without a catalog checksum are
+/// still hashed (and reported "unchecked") so the user learns the value.
+/// After cancellation the remaining entries are reported "cancelled".
+pub fn verify_manifest(
+    entries: &[ManifestEntry],
+    cancel: &AtomicBool,
+    mut on_progress: impl FnMut(&ManifestVerifyProgress),
+) -> ManifestVerification {
+    let start = Instant::now();
+    let mut files = Vec::with_capacity(entries.len());
+    let mut cancelled = false;
+
+    for (i, entry) in entries.iter().enumerate() {
+        let expected = entry.sha256.as_ref().map(|s| s.trim().to_lowercase()).filter(|s| !s.is_empty());
+        let result = |status: &str, actual: Option<String>, error: Option<String>| FileVerification {
+            model_id: entry.model_id.clone(),
+            file_name: entry.file_name.clone(),
+            status: status.to_string(),
+            expected_sha256: expected.clone(),
+            actual_sha256: actual,
+            error,
+        };
+        if cancelled {
+            files.push(result("cancelled", None, None));
+            continue;
+        }
+
+        let path = Path::new(&entry.path);
+        let file = match std::fs::File::open(path) {
+            Ok(f) => f,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                files.push(result("missing", None, None));
+                continue;
+            }
+            Err(e) => {
+                files.push(result("error", None, Some(e.to_string())));
+                continue;
+            }
+        };
+        let total_bytes = file.metadata().map(|m| m.len()).unwrap_or(0);
+        let mut progress = ManifestVerifyProgress {
+            model_id: entry.model_id.clone(),
+            file_name: entry.file_name.clone(),
+            bytes_hashed: 0,
+            total_bytes,
+            file_index: i + 1,
+            file_count: entries.len(),
+        };
+        on_progress(&progress);
+
+        let hashed = sha256_streaming(file, cancel, |bytes| {
+            progress.bytes_hashed = bytes;
+            on_progress(&progress);
+        });
+        files.push(match hashed {
+            Ok(Some(actual)) => {
+                let status = match &expected {
+                    Some(e) if *e == actual => "verified",
+                    Some(_) => "mismatch",
+                    None => "unchecked",
+                };
+                result(status, Some(actual), None)
+            }
+            Ok(None) => {
+                cancelled = true;
+                result("cancelled", None, None)
+            }
+            Err(e) => result("error", None, Some(e.to_string())),
+        });
+    }
+
+    ManifestVerification {
+        verified: files.iter().filter(|f| f.status == "verified").count(),
+        failed: files.iter().filter(|f| f.status == "mismatch" || f.status == "error").count(),
+        files,
+        cancelled,
+        duration_ms: start.elapsed().as_millis() as u64,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ABC_SHA256: &str = "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad";
+
+    #[test]
+    fn test_sha256_streaming_reports_progress() {
+        let data = vec![7u8; HASH_CHUNK_BYTES + 10];
+        let mut reports = Vec::new();
+        let hash = sha256_streaming(&data[..], &AtomicBool::new(false), |n| reports.push(n)).unwrap();
+        assert!(hash.is_some());
+        assert_eq!(reports.last(), Some(&(data.len() as u64)));
+        assert_eq!(sha256_streaming(&b"abc"[..], &AtomicBool::new(false), |_| {}).unwrap().as_deref(), Some(ABC_SHA256));
+        assert_eq!(sha256_streaming(&b"abc"[..], &AtomicBool::new(true), |_| {}).unwrap(), None);
+    }
+
+    #[test]
+    fn test_verify_manifest_statuses() {
+        let dir = std::env::temp_dir().join(format!("semblance_verify_test_{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("a.gguf"), b"abc").unwrap();
+        let entry = |id: &str, file: &str, sha: Option<&str>| ManifestEntry {
+            model_id: id.to_string(),
+            file_name: file.to_string(),
+            path: dir.join(file).to_string_lossy().to_string(),
+            sha256: sha.map(str::to_string),
+        };
+        let entries = vec![
+            entry("good", "a.gguf", Some(&ABC_SHA256.to_uppercase())),
+            entry("bad", "a.gguf", Some("00")),
+            entry("new", "a.gguf", None),
+            entry("gone", "missing.gguf", Some("00")),
+        ];
+
+        let mut progress_files = Vec::new();
+        let summary = verify_manifest(&entries, &AtomicBool::new(false), |p| progress_files.push(p.file_index));
+        let _ = std::fs::remove_dir_all(&dir);
+
+        let statuses: Vec<&str> = summary.files.iter().map(|f| f.status.as_str()).collect();
+        assert_eq!(statuses, vec!["verified", "mismatch", "unchecked", "missing"]);
+        assert_eq!((summary.verified, summary.failed, summary.cancelled), (1, 1, false));
+        assert_eq!(summary.files[2].actual_sha256.as_deref(), Some(ABC_SHA256));
+        assert!(progress_files.contains(&1) && progress_files.contains(&3) && !progress_files.contains(&4));
+    }
+
+    #[test]
+    fn test_verify_control_allows_one_run() {
+        let control = VerifyControl::default();
+        assert!(!control.cancel());
+        let run = control.begin().unwrap();
+        assert!(control.begin().is_err());
+        assert!(control.cancel());
+        assert!(run.cancel_flag().load(Ordering::SeqCst));
+        drop(run);
+        let run = control.begin().unwrap();
+        assert!(!run.cancel_flag().load(Ordering::SeqCst));
+    }
+}
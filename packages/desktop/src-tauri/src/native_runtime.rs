@@ -9,1003 +9,6360 @@
 // Replaces the previous `llama-cpp-2` crate (TODO-05 Step 1).
 //
 // Architecture:
-// - Only one reasoning model loaded at a time (Arc<Mutex<>> guarded)
+// - One active reasoning model (Arc<RwLock<>> guarded), plus up to
+//   MAX_RESIDENT_REASONING - 1 previously active ones kept warm and evicted by
+//   LRU when resident models are enabled; requests pick one by `model_path`
 // - Embedding model stays resident separately (small, ~275MB)
 // - GPU backend auto-selected: CUDA (Windows/Linux) > Metal (macOS) > CPU fallback
 // - Methods are synchronous (CPU-bound llama.cpp FFI calls) — callers use the async
-//   Mutex wrapper and tokio tasks for concurrency.
+//   RwLock wrapper and tokio tasks for concurrency. Inference takes a read lock
+//   (each call builds its own llama_context), loading/unloading takes a write lock.
+//   Awaiting a generation directly stalls the tokio worker for its whole run;
+//   `generate_async` / `run_blocking` move it onto the blocking pool instead.
 
 use bitnet_sys::{
-    AddBos, LlamaBackend, LlamaBatch, LlamaContextParams, LlamaModel,
-    LlamaModelParams, LlamaSampler,
+    AddBos, LlamaBackend, LlamaBatch, LlamaContext, LlamaContextParams, LlamaModel,
+    LlamaModelParams, LlamaSampler, LlamaToken, PoolingType,
 };
+use crate::scheduler::GenerationPriority;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::num::NonZeroU32;
-use std::path::PathBuf;
-use std::sync::Arc;
-use tokio::sync::Mutex;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::RwLock;
 
 // ─── Types ───────────────────────────────────────────────────────────────────
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
 pub struct GenerateRequest {
     #[serde(default)]
     pub model_path: String,
+    /// Shorthand for a single user turn. Ignored when `messages` is set.
+    #[serde(default)]
     pub prompt: String,
     pub system_prompt: Option<String>,
     pub max_tokens: Option<u32>,
     pub temperature: Option<f32>,
     pub stop: Option<Vec<String>>,
+    /// Stop once the decoded output reaches this many characters (Unicode scalar
+    /// values, not bytes or tokens). Output is cut at a codepoint boundary.
+    #[serde(default)]
+    pub max_chars: Option<usize>,
+    /// Groups generations for `generation_record`; unset requests are recorded
+    /// under `DEFAULT_SESSION_ID`.
+    #[serde(default)]
+    pub session_id: Option<String>,
+    /// Bias added to the logits of these tokens before sampling (e.g. -100 to
+    /// forbid a token, +5 to favour "yes"). Keys are token strings and must each
+    /// tokenize to exactly one token; others are listed in `unmapped_logit_bias`.
+    #[serde(default)]
+    pub logit_bias: Option<HashMap<String, f32>>,
+    /// Like `logit_bias`, keyed by token id (see the `tokenize` command), for
+    /// tokens that have no standalone string. Wins over `logit_bias` for the
+    /// same token. Ids outside the vocabulary fail the request.
+    #[serde(default)]
+    pub token_bias: Option<HashMap<LlamaToken, f32>>,
+    /// Seed for the final draw. The same seed, prompt, and settings reproduce
+    /// a generation; `None` picks a random one, so repeated generations vary.
+    /// The seed used is in the generation record's `sampler`. Set, it also
+    /// restarts a session's RNG stream instead of continuing it.
+    #[serde(default)]
+    pub seed: Option<u32>,
+    /// Top-p for `SamplingMode::TopPMinP`. `None` uses 0.95 (fast model: 0.9).
+    #[serde(default)]
+    pub top_p: Option<f32>,
+    /// Min-p for `SamplingMode::TopPMinP`. `None` uses 0.05.
+    #[serde(default)]
+    pub min_p: Option<f32>,
+    /// How the token is picked after temperature. `None` is top-p/min-p;
+    /// Mirostat modes fail the request if `top_p` or `min_p` is also set.
+    #[serde(default)]
+    pub sampling_mode: Option<SamplingMode>,
+    /// Use flash attention for this generation's context. `None` follows the
+    /// runtime default (`set_flash_attention`). Falls back to regular attention
+    /// where it can't be used; see `FlashAttentionUse`.
+    #[serde(default)]
+    pub flash_attention: Option<bool>,
+    /// Record the interval before each generated token in `token_intervals_ms`.
+    #[serde(default)]
+    pub profile_timing: bool,
+    /// Scheduler admission priority. `None` uses the scheduler default
+    /// (`set_default_generation_priority`).
+    #[serde(default)]
+    pub priority: Option<GenerationPriority>,
+    /// Truncate a prompt over `max_prompt_tokens` (or one that leaves less
+    /// than `max_tokens` of `n_ctx`) to fit instead of failing the generation.
+    /// The middle is cut: the system prompt and the most recent tokens stay.
+    #[serde(default)]
+    pub truncate_prompt: bool,
+    /// Context window for this generation. `None` uses `GENERATION_N_CTX`;
+    /// larger values are clamped to the model's training context.
+    #[serde(default)]
+    pub n_ctx: Option<u32>,
+    /// GBNF grammar (rooted at `root`) the output must match, e.g. from
+    /// `json_schema_to_gbnf`. An invalid grammar fails the request before any
+    /// context is created.
+    #[serde(default)]
+    pub grammar: Option<String>,
+    /// Divide the logits of tokens among the last `repeat_last_n` generated
+    /// tokens by this (>1 discourages repeats). `None` or 1.0 is no penalty.
+    #[serde(default)]
+    pub repeat_penalty: Option<f32>,
+    /// Window of generated tokens the penalties look at. `None` uses
+    /// `DEFAULT_REPEAT_LAST_N`; -1 is the whole context, 0 turns penalties off.
+    #[serde(default)]
+    pub repeat_last_n: Option<i32>,
+    /// Subtracted from a token's logit once per occurrence in the window.
+    #[serde(default)]
+    pub frequency_penalty: Option<f32>,
+    /// Subtracted from a token's logit if it occurs in the window at all.
+    #[serde(default)]
+    pub presence_penalty: Option<f32>,
+    /// Format the prompt with the chat template embedded in the model's GGUF
+    /// instead of the built-in format (ChatML, or Falcon3 by model path). Models
+    /// without an embedded template keep the built-in format.
+    #[serde(default)]
+    pub apply_chat_template: bool,
+    /// The whole conversation, oldest turn first, rendered after `system_prompt`
+    /// in place of `prompt`. Always formatted with the model's chat template
+    /// (the built-in format, turn by turn, if it has none).
+    #[serde(default)]
+    pub messages: Option<Vec<ChatMessage>>,
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
-pub struct GenerateResponse {
-    pub text: String,
-    pub tokens_generated: u32,
-    pub duration_ms: u64,
-}
-
-#[derive(Debug, Serialize, Deserialize, Clone)]
-pub struct EmbedRequest {
+/// Session id that generations without an explicit `session_id` are recorded under.
+pub const DEFAULT_SESSION_ID: &str = "default";
+
+/// Error from operations that need the reasoning model when none is loaded.
+pub const NO_REASONING_MODEL: &str = "No reasoning model loaded";
+
+/// Sampler chain settings (grammar → logit bias → penalties → top-p → min-p →
+/// temperature → seeded draw).
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct SamplerConfig {
+    pub top_p: f32,
+    pub min_p: f32,
+    pub temperature: f32,
+    pub seed: u32,
+    pub max_tokens: u32,
+    /// Resolved `(token id, bias)` pairs from the request's `logit_bias`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub logit_bias: Vec<(LlamaToken, f32)>,
+    /// RNG position the draw continued from, for generations in a session with
+    /// a sampler state. `None` starts fresh from `seed`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rng_state: Option<SamplerRng>,
+    /// Repetition penalties from the request. `None` leaves them out of the chain.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub penalties: Option<SamplerPenalties>,
+    /// `top_p` and `min_p` only apply to `SamplingMode::TopPMinP`.
     #[serde(default)]
-    pub model_path: String,
-    pub input: Vec<String>,
+    pub mode: SamplingMode,
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
-pub struct EmbedResponse {
-    pub embeddings: Vec<Vec<f32>>,
-    pub dimensions: u32,
-    pub duration_ms: u64,
+/// How tokens are narrowed down before the seeded draw. The chain runs grammar
+/// → logit bias → penalties → top-p → min-p → temperature for `TopPMinP`, and
+/// grammar → logit bias → penalties → temperature → Mirostat for the Mirostat
+/// modes: Mirostat steers the surprise of the distribution actually drawn
+/// from, so it has to come after temperature, and it replaces top-p/min-p.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Default)]
+#[serde(tag = "mode", rename_all = "snake_case")]
+pub enum SamplingMode {
+    /// Nucleus sampling with a min-p floor. The default.
+    #[default]
+    #[serde(rename = "top_p")]
+    TopPMinP,
+    /// Mirostat 1.0: keeps the top k tokens, k estimated from a Zipf fit of
+    /// the distribution, steering the surprise towards `tau` bits per token
+    /// with learning rate `eta`.
+    Mirostat { tau: f32, eta: f32 },
+    /// Mirostat 2.0: drops tokens more surprising than the running target,
+    /// which moves towards `tau` with learning rate `eta`.
+    MirostatV2 { tau: f32, eta: f32 },
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub enum RuntimeStatus {
-    Uninitialized,
-    Loading,
-    Ready,
-    Error(String),
+impl SamplingMode {
+    fn check(&self) -> Result<(), String> {
+        match *self {
+            SamplingMode::TopPMinP => Ok(()),
+            SamplingMode::Mirostat { tau, eta } | SamplingMode::MirostatV2 { tau, eta } => {
+                if !(tau.is_finite() && tau > 0.0) {
+                    return Err(format!("Mirostat tau must be a positive number, got {}", tau));
+                }
+                if !(eta > 0.0 && eta <= 1.0) {
+                    return Err(format!("Mirostat eta must be in (0, 1], got {}", eta));
+                }
+                Ok(())
+            }
+        }
+    }
 }
 
-// ─── NativeRuntime ───────────────────────────────────────────────────────────
-
-/// NativeRuntime manages BitNet.cpp / llama.cpp model instances for local inference.
-///
-/// Thread-safe via Arc<Mutex<>>. Only one reasoning model loaded at a time.
-/// Embedding model can be loaded concurrently (separate context).
-///
-/// All inference methods are synchronous (CPU-bound FFI calls).
-/// The caller wraps access in tokio::sync::Mutex for async compatibility.
-pub struct NativeRuntime {
-    status: RuntimeStatus,
-    backend: Option<LlamaBackend>,
-    reasoning_model: Option<LlamaModel>,
-    reasoning_model_path: Option<PathBuf>,
-    embedding_model: Option<LlamaModel>,
-    embedding_model_path: Option<PathBuf>,
-    fast_model: Option<LlamaModel>,
-    fast_model_path: Option<PathBuf>,
-    vision_model: Option<LlamaModel>,
-    vision_model_path: Option<PathBuf>,
-    vision_clip_ctx: Option<*mut bitnet_sys::clip_ctx>,
-    vision_mmproj_path: Option<PathBuf>,
+/// `request`'s sampling mode, top-p, and min-p, with `default_top_p` for an
+/// unset top-p. Mixing a Mirostat mode with top-p/min-p is an error.
+fn sampling_settings(request: &GenerateRequest, default_top_p: f32) -> Result<(SamplingMode, f32, f32), String> {
+    let mode = request.sampling_mode.unwrap_or_default();
+    mode.check()?;
+    if mode != SamplingMode::TopPMinP && (request.top_p.is_some() || request.min_p.is_some()) {
+        return Err("top_p and min_p don't apply to Mirostat sampling; leave them unset".to_string());
+    }
+    let top_p = request.top_p.unwrap_or(default_top_p);
+    let min_p = request.min_p.unwrap_or(0.05);
+    if !(0.0..=1.0).contains(&top_p) || !(0.0..=1.0).contains(&min_p) {
+        return Err(format!("top_p and min_p must be in [0, 1], got {} and {}", top_p, min_p));
+    }
+    Ok((mode, top_p, min_p))
 }
 
-// SAFETY: NativeRuntime is only accessed through a tokio::sync::Mutex, ensuring
-// exclusive access. The raw *mut clip_ctx pointer is only used while the lock is held.
-unsafe impl Send for NativeRuntime {}
+/// Window of generated tokens the repetition penalties look at by default
+/// (llama.cpp's default).
+pub const DEFAULT_REPEAT_LAST_N: i32 = 64;
+
+/// Repetition penalties over the last `last_n` generated tokens. Prompt tokens
+/// don't count, and the newline token is never penalized.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+pub struct SamplerPenalties {
+    pub last_n: i32,
+    pub repeat: f32,
+    pub frequency: f32,
+    pub presence: f32,
+    #[serde(skip)]
+    newline: LlamaToken,
+}
 
-#[allow(dead_code)] // Public API — callers wired in Step 2 (BitNetProvider)
-impl NativeRuntime {
-    pub fn new() -> Self {
-        let backend = match LlamaBackend::init() {
-            Ok(mut b) => {
-                b.void_logs();
-                Some(b)
-            }
-            Err(e) => {
-                eprintln!(
-                    "[NativeRuntime] Failed to initialize BitNet.cpp backend: {}",
-                    e
-                );
-                None
-            }
+impl SamplerPenalties {
+    /// Penalties requested by `request`, or `None` when every penalty is unset
+    /// or neutral. A `repeat_last_n` of -1 resolves to `n_ctx`.
+    fn from_request(request: &GenerateRequest, n_ctx: usize, newline: LlamaToken) -> Result<Option<Self>, String> {
+        let repeat = request.repeat_penalty.unwrap_or(1.0);
+        let frequency = request.frequency_penalty.unwrap_or(0.0);
+        let presence = request.presence_penalty.unwrap_or(0.0);
+        if !(repeat.is_finite() && repeat > 0.0) {
+            return Err(format!("repeat_penalty must be a positive number, got {}", repeat));
+        }
+        if !frequency.is_finite() || !presence.is_finite() {
+            return Err("frequency_penalty and presence_penalty must be finite".to_string());
+        }
+        let last_n = match request.repeat_last_n.unwrap_or(DEFAULT_REPEAT_LAST_N) {
+            -1 => n_ctx as i32,
+            n if n < -1 => return Err(format!("repeat_last_n must be -1 (whole context) or at least 0, got {}", n)),
+            n => n,
         };
-        NativeRuntime {
-            status: RuntimeStatus::Uninitialized,
-            backend,
-            reasoning_model: None,
-            reasoning_model_path: None,
-            embedding_model: None,
-            embedding_model_path: None,
-            fast_model: None,
-            fast_model_path: None,
-            vision_model: None,
-            vision_model_path: None,
-            vision_clip_ctx: None,
-            vision_mmproj_path: None,
+        if last_n == 0 || (repeat == 1.0 && frequency == 0.0 && presence == 0.0) {
+            return Ok(None);
         }
+        Ok(Some(SamplerPenalties { last_n, repeat, frequency, presence, newline }))
     }
+}
 
-    /// Load a reasoning model from a GGUF file.
-    /// Works with both standard GGUF (Q4_K_M, Q8_0) and BitNet i2_s GGUFs.
-    /// Blocking — model loading reads the full file from disk.
-    pub fn load_reasoning_model(&mut self, model_path: PathBuf) -> Result<(), String> {
-        if !model_path.exists() {
-            return Err(format!("Model file not found: {:?}", model_path));
+impl SamplerConfig {
+    /// `grammar` is a compiled grammar sampler (see `compile_grammar`) put at the
+    /// head of the chain.
+    fn build(&self, n_vocab: i32, grammar: Option<LlamaSampler>) -> TokenSampler {
+        let bias = (!self.logit_bias.is_empty()).then(|| LlamaSampler::logit_bias(n_vocab, &self.logit_bias));
+        let penalties = self.penalties.map(|p| {
+            LlamaSampler::penalties(n_vocab, p.newline, p.last_n, p.repeat, p.frequency, p.presence)
+        });
+        let nucleus = match self.mode {
+            SamplingMode::TopPMinP => vec![LlamaSampler::top_p(self.top_p, 1), LlamaSampler::min_p(self.min_p, 1)],
+            _ => Vec::new(),
+        };
+        TokenSampler {
+            filter: LlamaSampler::chain_simple(
+                grammar.into_iter().chain(bias).chain(penalties).chain(nucleus).chain([LlamaSampler::temp(self.temperature)]),
+            ),
+            rng: self.rng_state.unwrap_or_else(|| SamplerRng::seeded(self.seed)),
+            draws: 0,
+            n_vocab,
+            mirostat: Mirostat::new(self.mode, n_vocab),
         }
+    }
 
-        let backend = self
-            .backend
-            .as_ref()
-            .ok_or("BitNet.cpp backend not initialized")?;
+    /// Settings that decide which token each draw picks, recorded in `SamplerState`.
+    fn describe(&self) -> String {
+        let selection = match self.mode {
+            SamplingMode::TopPMinP => format!("top_p={} min_p={}", self.top_p, self.min_p),
+            SamplingMode::Mirostat { tau, eta } => format!("mirostat tau={} eta={}", tau, eta),
+            SamplingMode::MirostatV2 { tau, eta } => format!("mirostat_v2 tau={} eta={}", tau, eta),
+        };
+        let mut description = format!(
+            "{} temperature={} logit_bias={}",
+            selection,
+            self.temperature,
+            self.logit_bias.len()
+        );
+        if let Some(p) = &self.penalties {
+            description.push_str(&format!(
+                " repeat_penalty={} repeat_last_n={} frequency_penalty={} presence_penalty={}",
+                p.repeat, p.last_n, p.frequency, p.presence
+            ));
+        }
+        description
+    }
+}
 
-        self.status = RuntimeStatus::Loading;
+/// SplitMix64 generator for the final draw. The draw is done here rather than by
+/// llama.cpp's dist sampler, whose RNG can't be read or restored through its API.
+/// Serialized as 16 lowercase hex digits (a u64 doesn't survive JSON numbers in JS).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(into = "String", try_from = "String")]
+pub struct SamplerRng(u64);
 
-        // CPU-only inference (CUDA/Vulkan disabled in BitNet build for portability).
-        // n_gpu_layers=0 keeps everything on CPU — avoids crashes from missing GPU backend.
-        let model_params = LlamaModelParams::default().with_n_gpu_layers(0);
+impl SamplerRng {
+    pub fn seeded(seed: u32) -> Self {
+        SamplerRng(seed as u64)
+    }
 
-        match LlamaModel::load_from_file(backend, &model_path, &model_params) {
-            Ok(model) => {
-                eprintln!(
-                    "[NativeRuntime] Reasoning model loaded: {:?} ({} params, embd={})",
-                    model_path,
-                    model.n_params(),
-                    model.n_embd()
-                );
-                self.reasoning_model = Some(model);
-                self.reasoning_model_path = Some(model_path);
-                self.status = RuntimeStatus::Ready;
-                Ok(())
-            }
-            Err(e) => {
-                let err_msg = format!("Failed to load reasoning model: {}", e);
-                self.status = RuntimeStatus::Error(err_msg.clone());
-                Err(err_msg)
-            }
-        }
+    fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
     }
 
-    /// Load an embedding model from a GGUF file.
-    /// Blocking — model loading reads the full file from disk.
-    pub fn load_embedding_model(&mut self, model_path: PathBuf) -> Result<(), String> {
-        if !model_path.exists() {
-            return Err(format!(
-                "Embedding model file not found: {:?}",
-                model_path
-            ));
-        }
+    /// Uniform in [0, 1).
+    fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
 
-        let backend = self
-            .backend
-            .as_ref()
-            .ok_or("BitNet.cpp backend not initialized")?;
+/// Seed for a generation: the request's, else the one its session's RNG stream
+/// started from, else a random one.
+fn request_seed(request: &GenerateRequest, session: Option<&SamplerState>) -> u32 {
+    request.seed.or(session.map(|s| s.seed)).unwrap_or_else(random_seed)
+}
 
-        let model_params = LlamaModelParams::default().with_n_gpu_layers(0);
+/// A seed that differs between calls: `RandomState` keys are random per
+/// process and advance per instance.
+fn random_seed() -> u32 {
+    use std::hash::{BuildHasher, Hasher};
+    std::collections::hash_map::RandomState::new().build_hasher().finish() as u32
+}
 
-        match LlamaModel::load_from_file(backend, &model_path, &model_params) {
-            Ok(model) => {
-                eprintln!(
-                    "[NativeRuntime] Embedding model loaded: {:?} (dim={})",
-                    model_path,
-                    model.n_embd()
-                );
-                self.embedding_model = Some(model);
-                self.embedding_model_path = Some(model_path);
-                Ok(())
-            }
-            Err(e) => Err(format!("Failed to load embedding model: {}", e)),
-        }
+impl From<SamplerRng> for String {
+    fn from(rng: SamplerRng) -> String {
+        format!("{:016x}", rng.0)
     }
+}
 
-    /// Load a fast-tier model (SmolLM2) from a GGUF file.
-    /// Runs concurrently with reasoning model — does NOT gate overall readiness.
-    /// Blocking — model loading reads the full file from disk.
-    pub fn load_fast_model(&mut self, model_path: PathBuf) -> Result<(), String> {
-        if !model_path.exists() {
-            return Err(format!("Fast model file not found: {:?}", model_path));
+impl TryFrom<String> for SamplerRng {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, String> {
+        if s.len() != 16 {
+            return Err(format!("Sampler RNG state must be 16 hex digits, got {:?}", s));
         }
+        u64::from_str_radix(&s, 16)
+            .map(SamplerRng)
+            .map_err(|_| format!("Sampler RNG state must be 16 hex digits, got {:?}", s))
+    }
+}
 
-        let backend = self
-            .backend
-            .as_ref()
-            .ok_or("BitNet.cpp backend not initialized")?;
+/// A built sampler: the llama.cpp filter chain from `SamplerConfig`, then a
+/// softmax draw from `rng`. Every sample consumes exactly one draw, so the
+/// stream's position depends only on how many tokens were sampled.
+pub struct TokenSampler {
+    filter: LlamaSampler,
+    rng: SamplerRng,
+    /// Draws taken since this sampler was built.
+    draws: u64,
+    n_vocab: i32,
+    /// Set for the Mirostat modes; truncates after the filter chain.
+    mirostat: Option<Mirostat>,
+}
 
-        let model_params = LlamaModelParams::default().with_n_gpu_layers(0);
+impl TokenSampler {
+    /// Sample from the context's logits at batch output `idx` (-1 for the last).
+    pub fn sample(&mut self, ctx: &LlamaContext, idx: i32) -> Result<LlamaToken, String> {
+        let logits = ctx
+            .logits_ith(idx, self.n_vocab)
+            .ok_or_else(|| format!("No logits for batch output {}", idx))?;
+        self.select_from_logits(logits)
+            .ok_or_else(|| "Sampler left no candidate tokens".to_string())
+    }
 
-        match LlamaModel::load_from_file(backend, &model_path, &model_params) {
-            Ok(model) => {
-                eprintln!(
-                    "[NativeRuntime] Fast model loaded: {:?} ({} params, embd={})",
-                    model_path,
-                    model.n_params(),
-                    model.n_embd()
-                );
-                self.fast_model = Some(model);
-                self.fast_model_path = Some(model_path);
-                Ok(())
-            }
-            Err(e) => Err(format!("Failed to load fast model: {}", e)),
+    /// Sample from raw logits indexed by token id.
+    pub fn select_from_logits(&mut self, logits: &[f32]) -> Option<LlamaToken> {
+        let mut candidates = self.filter.filter_logits(logits);
+        // Tokens a grammar rules out come back at -inf; never draw one, since
+        // accepting it would put the grammar in an invalid state.
+        candidates.retain(|&(_, logit)| logit != f32::NEG_INFINITY);
+        if let Some(mirostat) = &self.mirostat {
+            mirostat.truncate(&mut candidates);
         }
+        self.draws += 1;
+        let token = draw_token(&candidates, self.rng.next_f64());
+        if let (Some(mirostat), Some(token)) = (&mut self.mirostat, token) {
+            mirostat.update(&candidates, token);
+        }
+        token
     }
 
-    pub fn has_fast_model(&self) -> bool {
-        self.fast_model.is_some()
+    pub fn accept(&mut self, token: LlamaToken) {
+        self.filter.accept(token);
     }
+}
 
-    pub fn fast_model_path(&self) -> Option<&PathBuf> {
-        self.fast_model_path.as_ref()
-    }
+/// Mirostat's feedback loop over one generation. The target surprise `mu`
+/// starts at `2 * tau` and isn't kept with a session's sampler state.
+#[derive(Debug, Clone, Copy)]
+struct Mirostat {
+    v2: bool,
+    tau: f32,
+    eta: f32,
+    mu: f32,
+    n_vocab: i32,
+}
 
-    pub fn unload_fast_model(&mut self) {
-        self.fast_model = None;
-        self.fast_model_path = None;
+/// Candidates Mirostat 1.0 fits its Zipf exponent on (as in the paper and llama.cpp).
+const MIROSTAT_M: usize = 100;
+
+impl Mirostat {
+    fn new(mode: SamplingMode, n_vocab: i32) -> Option<Self> {
+        let (v2, tau, eta) = match mode {
+            SamplingMode::TopPMinP => return None,
+            SamplingMode::Mirostat { tau, eta } => (false, tau, eta),
+            SamplingMode::MirostatV2 { tau, eta } => (true, tau, eta),
+        };
+        Some(Mirostat { v2, tau, eta, mu: 2.0 * tau, n_vocab })
     }
 
-    /// Load a vision model (Moondream2) and its multimodal projector.
-    /// Both the main GGUF and the mmproj GGUF must be provided.
-    pub fn load_vision_model(&mut self, model_path: PathBuf, mmproj_path: PathBuf) -> Result<(), String> {
-        if !model_path.exists() {
-            return Err(format!("Vision model file not found: {:?}", model_path));
-        }
-        if !mmproj_path.exists() {
-            return Err(format!("Vision mmproj file not found: {:?}", mmproj_path));
+    /// Cut `candidates` (temperature-scaled logits) to the ones to draw from,
+    /// most likely first. At least one survives.
+    fn truncate(&self, candidates: &mut Vec<(LlamaToken, f32)>) {
+        if candidates.is_empty() {
+            return;
         }
+        candidates.sort_by(|a, b| b.1.total_cmp(&a.1));
+        let probs = softmax(candidates);
+        let keep = if self.v2 {
+            probs.iter().take_while(|&&p| -p.log2() <= self.mu as f64).count()
+        } else {
+            let m = MIROSTAT_M.min(probs.len());
+            let (mut num, mut den) = (0.0, 0.0);
+            for i in 0..m.saturating_sub(1) {
+                let t = ((i + 2) as f64 / (i + 1) as f64).ln();
+                let b = (probs[i] / probs[i + 1]).ln();
+                num += t * b;
+                den += t * t;
+            }
+            let s_hat = num / den;
+            let epsilon = s_hat - 1.0;
+            let k = ((epsilon * 2f64.powf(self.mu as f64)) / (1.0 - (self.n_vocab as f64).powf(-epsilon))).powf(1.0 / s_hat);
+            if k.is_finite() { k as usize } else { probs.len() }
+        };
+        candidates.truncate(keep.clamp(1, probs.len()));
+    }
 
-        let backend = self.backend.as_ref().ok_or("Backend not initialized")?;
+    /// Move `mu` by the error between `token`'s surprise, drawn from the
+    /// truncated `candidates`, and `tau`.
+    fn update(&mut self, candidates: &[(LlamaToken, f32)], token: LlamaToken) {
+        let probs = softmax(candidates);
+        let Some(p) = candidates.iter().position(|&(t, _)| t == token).map(|i| probs[i]) else {
+            return;
+        };
+        let surprise = -p.log2() as f32;
+        self.mu -= self.eta * (surprise - self.tau);
+    }
+}
 
-        // Load the main vision model (same as any GGUF)
-        let model_params = LlamaModelParams::default().with_n_gpu_layers(0);
-        let model = LlamaModel::load_from_file(backend, &model_path, &model_params)
-            .map_err(|e| format!("Failed to load vision model: {}", e))?;
+/// Probabilities of `(token, logit)` candidates, in order.
+fn softmax(candidates: &[(LlamaToken, f32)]) -> Vec<f64> {
+    let max = candidates.iter().map(|&(_, logit)| logit).fold(f32::NEG_INFINITY, f32::max);
+    let weights: Vec<f64> = candidates.iter().map(|&(_, logit)| ((logit - max) as f64).exp()).collect();
+    let total: f64 = weights.iter().sum();
+    weights.into_iter().map(|w| w / total).collect()
+}
 
-        // Load the CLIP multimodal projector
-        let mmproj_cstr = std::ffi::CString::new(mmproj_path.to_string_lossy().as_bytes())
-            .map_err(|_| "Invalid mmproj path".to_string())?;
-        let clip_ctx = unsafe { bitnet_sys::clip_model_load(mmproj_cstr.as_ptr(), 0) };
-        if clip_ctx.is_null() {
-            return Err("Failed to load CLIP model from mmproj".to_string());
+/// Pick from `(token, logit)` candidates by softmax weight, with `u` in [0, 1).
+fn draw_token(candidates: &[(LlamaToken, f32)], u: f64) -> Option<LlamaToken> {
+    let max = candidates.iter().map(|&(_, logit)| logit).fold(f32::NEG_INFINITY, f32::max);
+    let weights: Vec<f64> = candidates.iter().map(|&(_, logit)| ((logit - max) as f64).exp()).collect();
+    let total: f64 = weights.iter().sum();
+    if total.is_nan() || total <= 0.0 {
+        return candidates.first().map(|&(token, _)| token);
+    }
+    let target = u * total;
+    let mut acc = 0.0;
+    for (&(token, _), weight) in candidates.iter().zip(&weights) {
+        acc += weight;
+        if target < acc {
+            return Some(token);
         }
-
-        eprintln!(
-            "[NativeRuntime] Vision model loaded: {:?} + mmproj: {:?} (clip_embd={})",
-            model_path, mmproj_path,
-            unsafe { bitnet_sys::clip_n_mmproj_embd(clip_ctx) }
-        );
-        self.vision_model = Some(model);
-        self.vision_model_path = Some(model_path);
-        self.vision_clip_ctx = Some(clip_ctx);
-        self.vision_mmproj_path = Some(mmproj_path);
-        Ok(())
     }
+    candidates.last().map(|&(token, _)| token)
+}
 
-    /// Generate text from an image + prompt using the vision model.
-    /// Image is processed through CLIP, embeddings injected into context,
-    /// then text generation continues with the Moondream2 chat template.
-    pub fn generate_vision(&self, prompt: String, image_path: String, max_tokens: u32) -> Result<GenerateResponse, String> {
-        let model = self.vision_model.as_ref().ok_or("No vision model loaded")?;
-        let clip_ctx = self.vision_clip_ctx.ok_or("No CLIP model loaded")?;
-        let backend = self.backend.as_ref().ok_or("Backend not initialized")?;
+/// Where a session's sampler RNG stream is, for pausing and resuming an agent
+/// run with `get_sampler_state` / `set_sampler_state`.
+///
+/// Generations with an explicit `session_id` continue their session's stream
+/// rather than reseeding, and store the state they ended at. Restoring a state
+/// resumes the exact random sequence; the tokens it picks match the original run
+/// only if the sampler configuration (`sampler`) and prompts are the same too.
+/// Generations without a `session_id` always start fresh from the seed.
+///
+/// Format (version 1): `{"version": 1, "algorithm": "splitmix64", "rng": "<16
+/// hex digits>", "seed": u32, "draws": u64, "sampler": "<settings>"}`.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct SamplerState {
+    pub version: u32,
+    pub algorithm: String,
+    pub rng: SamplerRng,
+    /// Seed the stream started from.
+    pub seed: u32,
+    /// Tokens drawn since the stream was seeded.
+    pub draws: u64,
+    /// `SamplerConfig` settings of the generation that produced this state.
+    pub sampler: String,
+    #[serde(skip)]
+    updated_at_ms: u64,
+}
 
-        let start = std::time::Instant::now();
+const SAMPLER_STATE_VERSION: u32 = 1;
+const SAMPLER_RNG_ALGORITHM: &str = "splitmix64";
 
-        // Encode image through CLIP
-        let image_cstr = std::ffi::CString::new(image_path.as_bytes())
-            .map_err(|_| "Invalid image path".to_string())?;
-        let n_threads = std::thread::available_parallelism().map(|n| n.get() as i32).unwrap_or(4);
+/// Explicit session ids carry a sampler RNG stream; the default session doesn't.
+fn stateful_session(session_id: Option<&str>) -> Option<&str> {
+    session_id.filter(|id| !id.is_empty() && *id != DEFAULT_SESSION_ID)
+}
 
-        let image_embed = unsafe {
-            bitnet_sys::llava_image_embed_make_with_filename(clip_ctx, n_threads, image_cstr.as_ptr())
-        };
-        if image_embed.is_null() {
-            return Err("Failed to encode image through CLIP".to_string());
+/// Map `logit_bias` token strings to token ids. Returns the `(id, bias)` pairs,
+/// sorted by id, and the strings that didn't tokenize to exactly one token.
+fn resolve_logit_bias(
+    bias: Option<&HashMap<String, f32>>,
+    mut tokenize: impl FnMut(&str) -> Result<Vec<LlamaToken>, String>,
+) -> (Vec<(LlamaToken, f32)>, Vec<String>) {
+    let (mut resolved, mut unmapped) = (Vec::new(), Vec::new());
+    for (text, &value) in bias.into_iter().flatten() {
+        match tokenize(text).as_deref() {
+            Ok(&[token]) => resolved.push((token, value)),
+            _ => unmapped.push(text.clone()),
         }
+    }
+    resolved.sort_by_key(|&(token, _)| token);
+    unmapped.sort();
+    (resolved, unmapped)
+}
 
-        Self::log(&format!("generate_vision: image encoded, n_image_pos={}", unsafe { (*image_embed).n_image_pos }));
-
-        // Create context
-        let ctx_params = LlamaContextParams::default().with_n_ctx(std::num::NonZeroU32::new(4096));
-        let mut ctx = model.new_context(backend, ctx_params)
-            .map_err(|e| format!("Failed to create vision context: {}", e))?;
+/// Add the request's `token_bias` to the pairs resolved from `logit_bias`,
+/// replacing any entry for the same id. Keeps the result sorted by id.
+fn apply_token_bias(
+    mut resolved: Vec<(LlamaToken, f32)>,
+    token_bias: Option<&HashMap<LlamaToken, f32>>,
+    n_vocab: i32,
+) -> Result<Vec<(LlamaToken, f32)>, String> {
+    for (&token, &value) in token_bias.into_iter().flatten() {
+        if token < 0 || token >= n_vocab {
+            return Err(format!("token_bias id {} is outside the vocabulary (0–{})", token, n_vocab - 1));
+        }
+        resolved.retain(|&(t, _)| t != token);
+        resolved.push((token, value));
+    }
+    resolved.sort_by_key(|&(token, _)| token);
+    Ok(resolved)
+}
 
-        // Inject image embeddings into context at position 0
-        let mut n_past: i32 = 0;
-        let success = unsafe {
-            bitnet_sys::llava_eval_image_embed(
-                ctx.as_mut_ptr(),
-                image_embed,
-                512,
-                &mut n_past,
-            )
-        };
+/// One parameter of a sampler mode, for the settings UI.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SamplerParamSpec {
+    pub name: String,
+    pub description: String,
+    pub min: f64,
+    pub max: f64,
+    /// Default for the reasoning model.
+    pub default: f64,
+    pub integer: bool,
+    /// `GenerateRequest` field that overrides it per request; `None` if fixed.
+    pub request_field: Option<String>,
+}
 
-        // Free image embed (no longer needed after injection)
-        unsafe { bitnet_sys::llava_image_embed_free(image_embed); }
+/// A sampling strategy the native backend can run.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SamplerModeSpec {
+    pub name: String,
+    pub description: String,
+    /// Whether generation requests use this mode.
+    pub used_for_generation: bool,
+    pub params: Vec<SamplerParamSpec>,
+}
 
-        if !success {
-            return Err("Failed to inject image embeddings into context".to_string());
+/// Sampler modes backed by the samplers bound in bitnet-sys. Keep in sync with
+/// `SamplerConfig::build` and the defaults in `generate` / `generate_fast`.
+pub fn sampler_modes() -> Vec<SamplerModeSpec> {
+    let param = |name: &str, description: &str, min: f64, max: f64, default: f64, integer: bool, request_field: Option<&str>| {
+        SamplerParamSpec {
+            name: name.to_string(),
+            description: description.to_string(),
+            min,
+            max,
+            default,
+            integer,
+            request_field: request_field.map(|f| f.to_string()),
         }
+    };
+    vec![
+        SamplerModeSpec {
+            name: "top_p".to_string(),
+            description: "Nucleus sampling: repetition penalties, top-p, then min-p, then temperature, then a seeded draw.".to_string(),
+            used_for_generation: true,
+            params: vec![
+                param("top_p", "Keep the smallest token set whose probability sums to at least p (fast model: 0.9).", 0.0, 1.0, 0.95, false, Some("top_p")),
+                param("min_p", "Drop tokens less likely than min_p times the top token.", 0.0, 1.0, 0.05, false, Some("min_p")),
+                param("temperature", "Higher is more random; 0 is effectively greedy (fast model: 0.3).", 0.0, 2.0, 0.7, false, Some("temperature")),
+                param("seed", "Seed for the final draw; random per generation unless the request sets one.", 0.0, u32::MAX as f64, 0.0, true, Some("seed")),
+                param("repeat_penalty", "Divide logits of recently generated tokens by this; 1 is off.", 0.0, 2.0, 1.0, false, Some("repeat_penalty")),
+                param("repeat_last_n", "Generated tokens the penalties look at; -1 is the whole context, 0 is off.", -1.0, 4096.0, DEFAULT_REPEAT_LAST_N as f64, true, Some("repeat_last_n")),
+                param("frequency_penalty", "Subtract this per earlier occurrence of a token.", -2.0, 2.0, 0.0, false, Some("frequency_penalty")),
+                param("presence_penalty", "Subtract this once if a token already occurred.", -2.0, 2.0, 0.0, false, Some("presence_penalty")),
+            ],
+        },
+        SamplerModeSpec {
+            name: "mirostat".to_string(),
+            description: "Mirostat 1.0: repetition penalties, temperature, then the top k tokens with k adapted to hold the surprise near tau, then a seeded draw.".to_string(),
+            used_for_generation: true,
+            params: vec![
+                param("tau", "Target surprise in bits per token; lower is more focused.", 0.0, 10.0, 5.0, false, Some("sampling_mode.tau")),
+                param("eta", "How fast the truncation adapts to the observed surprise.", 0.0, 1.0, 0.1, false, Some("sampling_mode.eta")),
+            ],
+        },
+        SamplerModeSpec {
+            name: "mirostat_v2".to_string(),
+            description: "Mirostat 2.0: repetition penalties, temperature, then drop tokens more surprising than a target that moves towards tau, then a seeded draw.".to_string(),
+            used_for_generation: true,
+            params: vec![
+                param("tau", "Target surprise in bits per token; lower is more focused.", 0.0, 10.0, 5.0, false, Some("sampling_mode.tau")),
+                param("eta", "How fast the target adapts to the observed surprise.", 0.0, 1.0, 0.1, false, Some("sampling_mode.eta")),
+            ],
+        },
+        SamplerModeSpec {
+            name: "greedy".to_string(),
+            description: "Always pick the most likely token. Used by the draft model in speculative decoding.".to_string(),
+            used_for_generation: false,
+            params: Vec::new(),
+        },
+    ]
+}
 
-        Self::log(&format!("generate_vision: image embeddings injected, n_past={}", n_past));
+/// Everything about one completed generation needed to replay it.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct GenerationRecord {
+    pub session_id: String,
+    /// "reasoning" or "fast".
+    pub kind: String,
+    pub model_path: Option<String>,
+    /// Set when the reasoning model decoded speculatively with a draft model.
+    pub draft_model_path: Option<String>,
+    pub request: GenerateRequest,
+    /// Prompt after the chat template was applied, exactly as tokenized.
+    pub resolved_prompt: String,
+    /// Prompt tokens actually decoded (after truncation to the context window).
+    pub prompt_tokens: u32,
+    pub sampler: SamplerConfig,
+    pub output: String,
+    pub tokens_generated: u32,
+    pub duration_ms: u64,
+    pub recorded_at_ms: u64,
+}
 
-        // Tokenize the text prompt (comes after image in the context)
-        // Moondream2 uses a simple prompt format: <image>\nQuestion: {prompt}\n\nAnswer:
-        let text_prompt = format!("\nQuestion: {}\n\nAnswer:", prompt);
-        let tokens = model.str_to_token(&text_prompt, AddBos::Always)
-            .map_err(|e| format!("Vision tokenization failed: {}", e))?;
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct GenerateResponse {
+    pub text: String,
+    pub tokens_generated: u32,
+    pub duration_ms: u64,
+    /// Present when the reasoning model generated with a draft model loaded.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub speculative: Option<SpeculativeStats>,
+    /// `logit_bias` keys that don't map to a single token and were not applied.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub unmapped_logit_bias: Vec<String>,
+    /// With `profile_timing`: milliseconds before each generated token, the
+    /// first measured from the end of prompt prefill. Uniform values mean
+    /// steady decoding; isolated spikes point at pauses (GC, thermal throttling,
+    /// another process). The sum is the decode time behind `tokens_generated`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub token_intervals_ms: Option<Vec<u32>>,
+    /// Time to first token: milliseconds from the start of the call, through
+    /// prompt prefill, to the first sampled token. `None` if nothing was sampled.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub first_token_ms: Option<u64>,
+    /// Tokens in the formatted prompt, including any already cached by a resident
+    /// session (and the image positions for vision).
+    #[serde(default)]
+    pub prompt_tokens: u32,
+    /// Time spent decoding the prompt batch (with the draft model's, when
+    /// speculative). Slow prefill means long prompts are the bottleneck.
+    #[serde(default)]
+    pub prefill_ms: u64,
+    /// Time spent in the generation loop, after prefill.
+    #[serde(default)]
+    pub decode_ms: u64,
+    /// `tokens_generated` per second of `decode_ms`; the figure to compare
+    /// against a hardware tier's usable decode speed.
+    #[serde(default)]
+    pub tokens_per_second: f32,
+}
 
-        if tokens.is_empty() {
-            return Err("Empty prompt after tokenization".to_string());
+/// Callbacks into a running generation.
+pub struct GenerationHooks<'a> {
+    /// Called between tokens (between rounds when speculative). The scheduler
+    /// pauses the generation here while a higher-priority one runs; see
+    /// `OwnedGenerationPermit::yield_if_preempted`.
+    pub yield_point: &'a dyn Fn(),
+    /// Called once, as soon as the first token is sampled, with `first_token_ms`.
+    pub on_first_token: &'a dyn Fn(u64),
+    /// Checked at every yield point; once set, the generation stops with a
+    /// "Generation cancelled" error.
+    pub cancel: Option<&'a AtomicBool>,
+}
+
+impl GenerationHooks<'_> {
+    pub const NONE: GenerationHooks<'static> =
+        GenerationHooks { yield_point: &|| {}, on_first_token: &|_| {}, cancel: None };
+
+    /// Run `yield_point`, then fail if the generation was cancelled.
+    fn checkpoint(&self) -> Result<(), String> {
+        (self.yield_point)();
+        match self.cancel {
+            Some(flag) if flag.load(Ordering::Relaxed) => Err("Generation cancelled".to_string()),
+            _ => Ok(()),
         }
+    }
+}
 
-        // Decode text tokens after image embeddings (chunked prefill)
-        let chunk_size: usize = 512;
-        let total_prompt_tokens = tokens.len();
-        let mut pos = n_past;
-        for (chunk_idx, chunk) in tokens.chunks(chunk_size).enumerate() {
-            let is_last_chunk = (chunk_idx + 1) * chunk_size >= total_prompt_tokens;
-            let mut batch = LlamaBatch::new(chunk.len().max(512), 1);
-            for (i, token) in chunk.iter().enumerate() {
-                let is_last_token = is_last_chunk && i == chunk.len() - 1;
-                batch.add(*token, pos, &[0], is_last_token)
-                    .map_err(|e| format!("Vision batch add failed: {}", e))?;
-                pos += 1;
-            }
-            ctx.decode(&mut batch)
-                .map_err(|e| format!("Vision prefill chunk {} failed: {}", chunk_idx, e))?;
+/// Speculative decoding statistics for one `generate()` call.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SpeculativeStats {
+    pub draft_model: String,
+    pub draft_tokens_proposed: u32,
+    pub draft_tokens_accepted: u32,
+    /// accepted / proposed (0.0 if nothing was proposed).
+    pub acceptance_rate: f32,
+    /// Time spent running the draft model.
+    pub draft_ms: u64,
+    /// Time spent verifying drafts with the reasoning model.
+    pub verify_ms: u64,
+    /// Generated tokens per second, excluding prompt prefill.
+    pub tokens_per_sec: f32,
+    /// Tokens per second of the most recent plain (no draft) generation with the
+    /// current reasoning model, if there has been one.
+    pub baseline_tokens_per_sec: Option<f32>,
+    /// tokens_per_sec / baseline_tokens_per_sec.
+    pub speedup: Option<f32>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct EmbedRequest {
+    #[serde(default)]
+    pub model_path: String,
+    pub input: Vec<String>,
+    /// Token window per input. `None` uses `EMBED_MAX_TOKENS_PER_INPUT`; larger
+    /// values are clamped to the model's training context. Longer inputs are
+    /// truncated to the window.
+    #[serde(default)]
+    pub n_ctx: Option<u32>,
+    /// How token embeddings are combined into each input's vector. `None` uses
+    /// the model's own pooling type from its GGUF metadata. BGE-style models
+    /// expect CLS, E5-style mean; the wrong one degrades retrieval quietly.
+    #[serde(default)]
+    pub pooling: Option<PoolingMode>,
+}
+
+/// Embedding pooling override for `EmbedRequest::pooling`.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum PoolingMode {
+    Mean,
+    Cls,
+    Last,
+    /// No pooling. `embed` returns one vector per input, so it rejects this;
+    /// it exists so a model's own "none" can be named.
+    None,
+}
+
+impl PoolingMode {
+    fn pooling_type(self) -> PoolingType {
+        match self {
+            PoolingMode::Mean => PoolingType::Mean,
+            PoolingMode::Cls => PoolingType::Cls,
+            PoolingMode::Last => PoolingType::Last,
+            PoolingMode::None => PoolingType::None,
         }
+    }
+}
 
-        Self::log("generate_vision: text prefill complete, starting generation...");
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct EmbedResponse {
+    pub embeddings: Vec<Vec<f32>>,
+    pub dimensions: u32,
+    pub duration_ms: u64,
+    /// Inputs per sub-batch actually used (after any shrinking on allocation failure).
+    pub sub_batch_size: u32,
+    /// Whether the inputs exceeded the per-context sequence limit and were split
+    /// into sub-batches of `sub_batch_size`.
+    #[serde(default)]
+    pub sequences_clamped: bool,
+    /// Indices of inputs whose embedding contained NaN or infinity. Their
+    /// entry in `embeddings` is empty so the poisoned values never reach the
+    /// index; callers skip empty vectors.
+    #[serde(default)]
+    pub failed_inputs: Vec<usize>,
+    #[serde(default)]
+    pub failed_count: u32,
+}
 
-        // Sampler chain (same as generate_fast — deterministic for vision)
-        let mut sampler = LlamaSampler::chain_simple([
-            LlamaSampler::top_p(0.9, 1),
-            LlamaSampler::min_p(0.05, 1),
-            LlamaSampler::temp(0.3),
-            LlamaSampler::dist(42),
-        ]);
+/// Result of `benchmark_embedding`.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct EmbeddingBenchmark {
+    pub batch_size: u32,
+    pub text_len_chars: u32,
+    /// Tokens per synthetic input after tokenization (and truncation).
+    pub tokens_per_input: u32,
+    pub dimensions: u32,
+    /// Untimed single-input warm-up.
+    pub warmup_ms: u64,
+    pub duration_ms: u64,
+    pub embeddings_per_sec: f32,
+    pub ms_per_embedding: f32,
+    /// Inputs per sub-batch `embed()` actually used.
+    pub sub_batch_size: u32,
+}
 
-        // Generation loop (identical to generate/generate_fast)
-        let mut output_bytes: Vec<u8> = Vec::new();
-        let mut n_cur = pos;
-        let mut tokens_generated = 0u32;
-        let mut gen_batch = LlamaBatch::new(1, 1);
+/// Largest batch `benchmark_embedding` accepts.
+pub const MAX_EMBED_BENCHMARK_BATCH: u32 = 512;
+/// Longest synthetic input `benchmark_embedding` accepts, in characters.
+pub const MAX_EMBED_BENCHMARK_TEXT_LEN: u32 = 16_384;
 
-        for _ in 0..max_tokens {
-            let token = sampler.sample(&ctx, -1);
-            sampler.accept(token);
+/// One candidate label scored against an input text (zero-shot classification).
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct LabelScore {
+    pub label: String,
+    pub score: f32,
+}
 
-            if model.is_eog_token(token) {
-                break;
-            }
+/// One option scored by `classify_choice`.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ChoiceScore {
+    pub choice: String,
+    /// Position in the request's `choices`.
+    pub index: usize,
+    /// Total log-probability of the choice's tokens as the reply.
+    pub logprob: f32,
+    /// `logprob` divided by `tokens`.
+    pub mean_logprob: f32,
+    /// Share of probability among the given choices (softmax of `logprob`).
+    pub probability: f32,
+    pub tokens: usize,
+}
 
-            let piece = model.token_to_bytes(token);
-            output_bytes.extend_from_slice(&piece);
-            tokens_generated += 1;
+/// Result of `classify_choice`: `ranked` is best first and `label` is its top choice.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ChoiceClassification {
+    pub label: String,
+    pub ranked: Vec<ChoiceScore>,
+    pub prompt_tokens: usize,
+    pub duration_ms: u64,
+}
 
-            // Stop sequence check
-            let output_so_far = String::from_utf8_lossy(&output_bytes);
-            let stops = ["<|endoftext|>", "\nQuestion:"];
-            if let Some(stop) = stops.iter().find(|s| output_so_far.ends_with(*s)) {
-                let stop_len = stop.len();
-                let output_str_len = output_so_far.len();
-                output_bytes.truncate(output_str_len - stop_len);
-                break;
-            }
+/// Outcome of one self-test check. `Skipped` means the component has no model loaded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SelfTestStatus {
+    Pass,
+    Fail,
+    Skipped,
+}
 
-            gen_batch.clear();
-            gen_batch.add(token, n_cur, &[0], true)
-                .map_err(|e| format!("Vision decode failed: {}", e))?;
-            ctx.decode(&mut gen_batch)
-                .map_err(|e| format!("Vision decode failed: {}", e))?;
-            n_cur += 1;
-        }
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ComponentSelfTest {
+    pub component: String,
+    pub status: SelfTestStatus,
+    pub detail: String,
+    pub duration_ms: u64,
+}
 
-        let duration_ms = start.elapsed().as_millis() as u64;
-        let output = String::from_utf8_lossy(&output_bytes).into_owned();
+/// Flash attention as used by the most recent generation context.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct FlashAttentionUse {
+    pub requested: bool,
+    pub enabled: bool,
+    /// Why a requested flash attention context fell back to regular attention.
+    pub fallback_reason: Option<String>,
+}
 
-        Self::log(&format!("generate_vision: {} tokens in {}ms", tokens_generated, duration_ms));
+/// Flash attention support and state, for `get_backend_info`.
+///
+/// ggml implements flash attention on CPU and on Metal, the only GPU backend
+/// this build compiles (CUDA also supports it; Vulkan doesn't yet). On Metal,
+/// head sizes without a kernel run that op on the CPU instead. llama.cpp can't
+/// use it for Grok models or models whose key and value head sizes differ.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct FlashAttentionInfo {
+    /// Runtime default; `None` uses llama.cpp's default (off).
+    pub setting: Option<bool>,
+    pub supported: bool,
+    /// Backend that runs attention: "metal" or "cpu".
+    pub backend: String,
+    pub last_context: Option<FlashAttentionUse>,
+}
 
-        Ok(GenerateResponse {
-            text: output,
-            tokens_generated,
-            duration_ms,
-        })
-    }
+/// Native backend capabilities and tuning state.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct BackendInfo {
+    pub engine: String,
+    /// Why BitNet.cpp failed to initialize, if it did.
+    pub backend_error: Option<String>,
+    pub gpu_offload_supported: bool,
+    /// What the loaded reasoning model runs on: "cuda", "metal" or "cpu"
+    /// ("cpu" when none is loaded).
+    pub backend: String,
+    /// Reasoning-model layers on the GPU, as llama.cpp reported at load.
+    pub gpu_layers_offloaded: i32,
+    /// The GPU llama.cpp initialized, once it has initialized one.
+    pub gpu_device_name: Option<String>,
+    pub flash_attention: FlashAttentionInfo,
+}
 
-    pub fn has_vision_model(&self) -> bool { self.vision_model.is_some() }
-    pub fn vision_model_path(&self) -> Option<&PathBuf> { self.vision_model_path.as_ref() }
+/// A GPU device llama.cpp reported initializing.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct GpuDevice {
+    /// "cuda" or "metal".
+    backend: &'static str,
+    name: String,
+}
 
-    pub fn unload_vision_model(&mut self) {
-        self.vision_model = None;
-        self.vision_model_path = None;
-        if let Some(ctx) = self.vision_clip_ctx.take() {
-            unsafe { bitnet_sys::clip_free(ctx); }
-        }
-        self.vision_mmproj_path = None;
+/// The GPU device a llama.cpp log line reports, if it reports one. Older
+/// builds log it as their backend initializes, newer ones per model load:
+///
+///   ggml_metal_init: found device: Apple M2 Pro
+///     Device 0: NVIDIA GeForce RTX 4070, compute capability 8.9, VMM: yes
+///   llama_load_model_from_file: using device CUDA0 (NVIDIA GeForce RTX 4070) - 11094 MiB free
+fn parse_gpu_device(line: &str) -> Option<GpuDevice> {
+    let line = line.trim();
+    if let Some((_, name)) = line.split_once("ggml_metal_init: found device: ") {
+        return Some(GpuDevice { backend: "metal", name: name.trim().to_string() });
     }
-
-    // File-based logging — eprintln goes nowhere on Windows GUI apps
-    fn log(msg: &str) {
-        use std::io::Write;
-        let log_path = if cfg!(target_os = "windows") {
-            let appdata = std::env::var("LOCALAPPDATA").unwrap_or_else(|_| ".".to_string());
-            std::path::PathBuf::from(appdata)
-                .join("Semblance")
-                .join("native_runtime.log")
+    if let Some((_, rest)) = line.split_once("using device ") {
+        let backend = if rest.starts_with("Metal") {
+            "metal"
+        } else if rest.starts_with("CUDA") {
+            "cuda"
         } else {
-            std::path::PathBuf::from("/tmp/semblance_native_runtime.log")
+            return None;
         };
-        if let Ok(mut f) = std::fs::OpenOptions::new()
-            .create(true)
-            .append(true)
-            .open(&log_path)
-        {
-            let _ = writeln!(f, "[NativeRuntime] {}", msg);
-        }
+        let (_, name) = rest.split_once('(')?;
+        let (name, _) = name.rsplit_once(')')?;
+        return Some(GpuDevice { backend, name: name.to_string() });
     }
+    let (_, rest) = line.strip_prefix("Device ")?.split_once(": ")?;
+    let (name, _) = rest.split_once(", compute capability")?;
+    Some(GpuDevice { backend: "cuda", name: name.to_string() })
+}
 
-    /// Generate text from a prompt using the loaded reasoning model.
-    /// Blocking — runs the full inference loop synchronously.
-    pub fn generate(&self, request: GenerateRequest) -> Result<GenerateResponse, String> {
-        Self::log("generate() entered");
+/// Layers a model load put on the GPU, from llama.cpp's
+/// "llm_load_tensors: offloaded 23/33 layers to GPU".
+fn parse_offloaded_layers(line: &str) -> Option<i32> {
+    let (_, rest) = line.split_once("offloaded ")?;
+    let (count, rest) = rest.split_once('/')?;
+    if !rest.contains("layers to GPU") {
+        return None;
+    }
+    count.trim().parse().ok()
+}
 
-        if !matches!(self.status, RuntimeStatus::Ready) {
-            return Err("Runtime not ready — no model loaded".to_string());
-        }
+/// Why llama.cpp can't use flash attention for a model, from its GGUF metadata.
+fn flash_attention_blocker(arch: Option<&str>, key_length: Option<&str>, value_length: Option<&str>) -> Option<String> {
+    if arch == Some("grok") {
+        return Some("Flash attention is not supported for Grok models".to_string());
+    }
+    match (key_length, value_length) {
+        (Some(k), Some(v)) if k != v => Some(format!(
+            "Flash attention needs equal key and value head sizes (model has {} and {})",
+            k, v
+        )),
+        _ => None,
+    }
+}
 
-        let backend = self
-            .backend
-            .as_ref()
-            .ok_or("BitNet.cpp backend not initialized")?;
-        let model = self
-            .reasoning_model
-            .as_ref()
-            .ok_or("No reasoning model loaded")?;
+/// Result of `self_test()`. `passed` is false if any loaded component failed.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SelfTestReport {
+    pub passed: bool,
+    pub components: Vec<ComponentSelfTest>,
+}
 
-        let start = std::time::Instant::now();
-        let max_tokens = request.max_tokens.unwrap_or(512);
-        let temperature = request.temperature.unwrap_or(0.7);
+/// Tokens decoded by `warmup()`: enough to run every decode kernel once.
+const WARMUP_TOKENS: u32 = 4;
 
-        // Detect prompt template from model path.
-        // Qwen/ChatML: <|im_start|>system\n...<|im_end|>\n<|im_start|>user\n...<|im_end|>\n<|im_start|>assistant\n
-        // Falcon3: <|system|>\n...\n<|user|>\n...\n<|assistant|>\n
-        // Default to ChatML since Qwen is the primary reasoning model.
-        let model_path_lower = self.reasoning_model_path
-            .as_ref()
-            .map(|p| p.to_string_lossy().to_lowercase())
-            .unwrap_or_default();
-
-        let use_falcon = model_path_lower.contains("falcon");
-
-        let full_prompt = if use_falcon {
-            // Falcon3 Instruct template
-            match &request.system_prompt {
-                Some(sys) if !sys.is_empty() => format!(
-                    "<|system|>\n{}\n<|user|>\n{}\n<|assistant|>\n",
-                    sys, request.prompt
-                ),
-                _ => format!(
-                    "<|user|>\n{}\n<|assistant|>\n",
-                    request.prompt
-                ),
-            }
-        } else {
-            // ChatML template (Qwen — default reasoning model)
-            match &request.system_prompt {
-                Some(sys) if !sys.is_empty() => format!(
-                    "<|im_start|>system\n{}<|im_end|>\n<|im_start|>user\n{}<|im_end|>\n<|im_start|>assistant\n",
-                    sys, request.prompt
-                ),
-                _ => format!(
-                    "<|im_start|>user\n{}<|im_end|>\n<|im_start|>assistant\n",
-                    request.prompt
-                ),
-            }
-        };
+/// Result of `warmup()`: what the first decode on a freshly loaded model cost.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ModelWarmup {
+    pub duration_ms: u64,
+    pub tokens_generated: u32,
+    /// Layers offloaded to the GPU, whose kernels the warmup compiled.
+    pub gpu_layers: i32,
+}
 
-        Self::log(&format!(
-            "generate: prompt_len={} chars, max_tokens={}, temp={}",
-            full_prompt.len(),
-            max_tokens,
-            temperature
-        ));
+/// Where the reasoning model's layers landed after load.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct LayerPlacement {
+    pub total_layers: u32,
+    pub gpu_layers: u32,
+    pub cpu_layers: u32,
+    /// `n_gpu_layers` requested at load time.
+    pub requested_gpu_layers: i32,
+    /// Whether this build has a GPU backend. If false, all layers run on CPU.
+    pub gpu_offload_supported: bool,
+}
 
-        // 4096 context — sufficient for conversational turns with Qwen Q4_K_M models.
-        // Smaller BitNet models use less KV cache so this is safe for both.
-        Self::log("generate: creating context with n_ctx=4096...");
-        let ctx_params = LlamaContextParams::default().with_n_ctx(NonZeroU32::new(4096));
-        let mut ctx = model
-            .new_context(backend, ctx_params)
-            .map_err(|e| format!("Failed to create context: {}", e))?;
-        Self::log("generate: context created OK");
+/// How many reasoning-model layers to offload to the GPU at load.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum GpuLayers {
+    /// As many as fit in the GPU's VRAM; see `auto_gpu_layers`.
+    Auto,
+    /// Exactly this many (negative = all).
+    Count(i32),
+}
 
-        Self::log("generate: tokenizing...");
-        let tokens = model
-            .str_to_token(&full_prompt, AddBos::Always)
-            .map_err(|e| format!("Tokenization failed: {}", e))?;
+/// One chat turn, for `test_chat_template` or a request's `messages`.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ChatMessage {
+    pub role: String,
+    pub content: String,
+}
 
-        Self::log(&format!("generate: tokenized {} tokens", tokens.len()));
+/// Result of rendering the reasoning model's chat template.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ChatTemplateCheck {
+    pub ok: bool,
+    /// Template embedded in the GGUF. `None` means llama.cpp fell back to ChatML.
+    pub template: Option<String>,
+    pub rendered: Option<String>,
+    pub error: Option<String>,
+}
 
-        if tokens.is_empty() {
-            return Err("Empty prompt after tokenization".to_string());
-        }
+/// The vocab-only model in the tokenizer slot.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct TokenizerInfo {
+    pub path: String,
+    pub n_vocab: i32,
+    /// llama.cpp vocab type (1 = SentencePiece, 2 = BPE, 3 = WordPiece, ...).
+    pub vocab_type: i32,
+}
 
-        // Safety: if prompt exceeds context window, truncate to leave room for response.
-        let n_ctx: usize = 4096;
-        let max_prompt_tokens = n_ctx.saturating_sub(max_tokens as usize);
-        let tokens = if tokens.len() > max_prompt_tokens {
-            Self::log(&format!(
-                "generate: TRUNCATING {} tokens -> {} to fit context",
-                tokens.len(),
-                max_prompt_tokens
-            ));
-            tokens[..max_prompt_tokens].to_vec()
-        } else {
-            Self::log(&format!(
-                "generate: tokens fit ({} <= {})",
-                tokens.len(),
-                max_prompt_tokens
-            ));
-            tokens
-        };
+/// What the loaded reasoning model is, from its GGUF metadata.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct ModelInfo {
+    /// `general.name`, e.g. "Llama 3.1 8B Instruct", when the file records one.
+    pub name: Option<String>,
+    /// `general.architecture`, e.g. "llama" or "qwen2".
+    pub architecture: String,
+    /// Dominant quantization from `general.file_type`, e.g. "Q4_K_M".
+    /// `None` when unrecorded or a type this build doesn't name.
+    pub quantization: Option<String>,
+    pub n_params: u64,
+    pub n_layer: u32,
+    pub n_embd: u32,
+    /// Context length the model was trained with (0 if not recorded).
+    pub n_ctx_train: u32,
+    pub n_vocab: i32,
+}
 
-        // Chunked prefill: decode prompt in batches.
-        let chunk_size: usize = 512;
-        let total_prompt_tokens = tokens.len();
-        Self::log(&format!(
-            "generate: chunked prefill, {} tokens in chunks of {}",
-            total_prompt_tokens, chunk_size
-        ));
+/// What a model file is, from `NativeRuntime::probe_gguf`: read from its
+/// header, without loading it.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct GgufProbe {
+    /// Whether the file starts with the GGUF magic. The other fields are only
+    /// read when it does.
+    pub is_gguf: bool,
+    /// `general.architecture`, e.g. "llama" or "qwen2".
+    pub architecture: Option<String>,
+    /// Context length the model was trained with.
+    pub n_ctx_train: Option<u32>,
+    /// Dominant quantization, e.g. "Q4_K_M": `general.file_type` if this build
+    /// names it, else the most common tensor type after F32.
+    pub quantization: Option<String>,
+    /// Size of the file, roughly the memory its weights take.
+    pub approx_size_mb: u64,
+}
 
-        let mut pos: i32 = 0;
-        for (chunk_idx, chunk) in tokens.chunks(chunk_size).enumerate() {
-            let is_last_chunk = (chunk_idx + 1) * chunk_size >= total_prompt_tokens;
-            let mut batch = LlamaBatch::new(chunk.len().max(512), 1);
+/// A reasoning model held in memory, from `resident_models`.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct ResidentModel {
+    pub path: String,
+    /// Size of the GGUF file, roughly the memory its weights take.
+    pub size_mb: u64,
+    /// Whether this is the active model (the one requests without `model_path` use).
+    pub active: bool,
+}
 
-            for (i, token) in chunk.iter().enumerate() {
-                let is_last_token = is_last_chunk && i == chunk.len() - 1;
-                batch
-                    .add(*token, pos, &[0], is_last_token)
-                    .map_err(|e| format!("Batch add failed: {}", e))?;
-                pos += 1;
-            }
+/// Resident reasoning models and the limits that evict them.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct ResidentModels {
+    /// Most reasoning models kept loaded at once, the active one included.
+    pub max_resident: u32,
+    /// Most memory their weights may take together. `None` for no limit.
+    pub budget_mb: Option<u64>,
+    /// Active model first, then the warm ones, most recently used first.
+    pub models: Vec<ResidentModel>,
+}
 
-            Self::log(&format!(
-                "generate: decoding chunk {} ({} tokens, pos={})",
-                chunk_idx,
-                chunk.len(),
-                pos
-            ));
-            // Flush log before decode — if we crash here, at least we'll see which chunk
-            Self::log("generate: calling ctx.decode()...");
-            ctx.decode(&mut batch)
-                .map_err(|e| format!("Prompt decode chunk {} failed: {}", chunk_idx, e))?;
-            Self::log(&format!("generate: chunk {} decoded OK", chunk_idx));
+/// Largest prompt `generate()` accepts, from `set_max_prompt_tokens`.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct PromptTokenLimit {
+    /// Configured limit. `None` defaults to the model's training context.
+    pub setting: Option<u32>,
+    /// Training context of the loaded reasoning model, if known.
+    pub n_ctx_train: Option<u32>,
+    /// The limit that applies now. `None` when unset and no model is loaded.
+    pub effective: Option<u32>,
+}
+
+/// A model slot in the runtime.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ModelKind {
+    Reasoning,
+    Embedding,
+    Fast,
+    Vision,
+    Draft,
+}
+
+impl ModelKind {
+    pub const ALL: [ModelKind; 5] =
+        [ModelKind::Reasoning, ModelKind::Embedding, ModelKind::Fast, ModelKind::Vision, ModelKind::Draft];
+
+    pub fn parse(kind: &str) -> Result<Self, String> {
+        match kind.trim().to_lowercase().as_str() {
+            "reasoning" => Ok(ModelKind::Reasoning),
+            "embedding" => Ok(ModelKind::Embedding),
+            "fast" => Ok(ModelKind::Fast),
+            "vision" => Ok(ModelKind::Vision),
+            "draft" => Ok(ModelKind::Draft),
+            other => Err(format!(
+                "Unknown model kind '{}' (expected reasoning, embedding, fast, vision or draft)",
+                other
+            )),
         }
+    }
 
-        Self::log("generate: prefill decode OK, starting generation loop...");
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ModelKind::Reasoning => "reasoning",
+            ModelKind::Embedding => "embedding",
+            ModelKind::Fast => "fast",
+            ModelKind::Vision => "vision",
+            ModelKind::Draft => "draft",
+        }
+    }
+}
 
-        // Create sampler chain: top-p + min-p + temperature + random sampling
-        let mut sampler = LlamaSampler::chain_simple([
-            LlamaSampler::top_p(0.95, 1),
-            LlamaSampler::min_p(0.05, 1),
-            LlamaSampler::temp(temperature),
-            LlamaSampler::dist(42),
-        ]);
+/// Attention shape of a model, as read from its GGUF hyperparameters.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct ModelShape {
+    pub n_layer: u32,
+    pub n_embd: u32,
+    pub n_head: u32,
+    pub n_head_kv: u32,
+    /// Context length the model was trained with (0 if not recorded).
+    pub n_ctx_train: u32,
+}
 
-        // Generation loop — accumulate raw bytes then decode to UTF-8 at the end
-        let mut output_bytes: Vec<u8> = Vec::new();
-        let mut n_cur = pos;
-        let mut tokens_generated = 0u32;
+/// Result of `recommend_context_length`, with the memory math behind it.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct ContextRecommendation {
+    pub n_ctx: u32,
+    pub shape: ModelShape,
+    /// f16 K + V across all layers for one token.
+    pub kv_bytes_per_token: u64,
+    pub kv_cache_bytes: u64,
+    pub available_bytes: u64,
+    /// Weights still to be loaded; 0 when the model is already resident.
+    pub model_bytes: u64,
+    /// Held back for the OS, other apps, and llama.cpp compute buffers.
+    pub reserved_bytes: u64,
+    pub usable_bytes: u64,
+}
 
-        // Single-token batch for auto-regressive generation
-        let mut gen_batch = LlamaBatch::new(1, 1);
+/// Parallel sequences one context supports, and what each costs.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct SequenceLimit {
+    /// Most sequences to put in one context with the memory available. At least 1.
+    pub max_sequences: u32,
+    /// Tokens each sequence is sized for.
+    pub tokens_per_sequence: u32,
+    /// Memory each additional sequence costs at that length.
+    pub bytes_per_sequence: u64,
+}
 
-        for _ in 0..max_tokens {
-            let token = sampler.sample(&ctx, -1);
-            sampler.accept(token);
+/// Result of `parallel_limits`. A field is `None` when that model isn't loaded
+/// (or its GGUF lacks the hyperparameters to size it).
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct ParallelLimits {
+    /// Reasoning model, one `GENERATION_N_CTX` window per sequence.
+    pub generation: Option<SequenceLimit>,
+    /// Embedding model, with full-length inputs. `embed` splits larger batches
+    /// into sub-batches of at most this many inputs.
+    pub embedding: Option<SequenceLimit>,
+    pub available_bytes: u64,
+}
 
-            // End-of-generation check
-            if model.is_eog_token(token) {
-                break;
-            }
+/// Context window of a generation sequence.
+pub const GENERATION_N_CTX: u32 = 4096;
+/// Most sequences one generation context is sized for. llama.cpp's KV cache has
+/// no hard cap, but every sequence adds a full window of cache.
+pub const MAX_PARALLEL_SEQUENCES: u32 = 32;
+
+/// Smallest context worth recommending.
+pub const MIN_CONTEXT_LENGTH: u32 = 512;
+/// Fraction of available memory left for other use.
+const CONTEXT_MEMORY_HEADROOM: f64 = 0.25;
+/// llama.cpp compute (scratch) buffers, roughly independent of n_ctx at these sizes.
+const COMPUTE_BUFFER_BYTES: u64 = 256 * 1024 * 1024;
+
+/// KV cache occupancy of a resident session, after any trim.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct SessionOccupancy {
+    pub session_id: String,
+    /// Tokens currently held in the session's KV cache.
+    pub tokens_in_cache: u32,
+    /// Leading system-prompt tokens that trimming never removes.
+    pub prefix_tokens: u32,
+    pub n_ctx: u32,
+    /// Tokens removed by this call.
+    pub tokens_removed: u32,
+}
 
-            // Decode token to bytes
-            let piece = model.token_to_bytes(token);
-            output_bytes.extend_from_slice(&piece);
-            tokens_generated += 1;
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum RuntimeStatus {
+    Uninitialized,
+    Loading,
+    /// A reasoning model is loaded (or idle-unloaded, and reloaded on demand),
+    /// so `generate` works.
+    Ready,
+    /// Only an embedding model is loaded: `embed` works, `generate` is refused.
+    /// The lightweight mode for indexing on constrained machines.
+    EmbeddingOnly,
+    Error(String),
+}
 
-            // Check stop sequences (on the accumulated UTF-8 string so far)
-            let output_so_far = String::from_utf8_lossy(&output_bytes);
-            if let Some(ref stops) = request.stop {
-                if let Some(stop) = stops.iter().find(|s| output_so_far.ends_with(s.as_str())) {
-                    let stop_len = stop.len();
-                    // Remove the stop sequence bytes from the end
-                    let output_str_len = output_so_far.len();
-                    output_bytes.truncate(output_str_len - stop_len);
-                    break;
-                }
-            }
+// ─── NativeRuntime ───────────────────────────────────────────────────────────
 
-            // Prepare next batch with just the new token
-            gen_batch.clear();
-            gen_batch
-                .add(token, n_cur, &[0], true)
-                .map_err(|e| format!("Batch add failed: {}", e))?;
-            ctx.decode(&mut gen_batch)
-                .map_err(|e| format!("Decode failed: {}", e))?;
-            n_cur += 1;
+/// NativeRuntime manages BitNet.cpp / llama.cpp model instances for local inference.
+///
+/// Thread-safe via Arc<RwLock<>>. One reasoning model is active; with
+/// `set_resident_models` above 1, outgoing ones stay warm (least recently used
+/// evicted first) and `generate` routes to them by `model_path`.
+/// Embedding model can be loaded concurrently (separate context).
+///
+/// All inference methods are synchronous (CPU-bound FFI calls).
+/// The caller wraps access in tokio::sync::RwLock for async compatibility.
+/// `&self` inference methods may run concurrently; how many generations actually
+/// do is governed by the generation scheduler.
+pub struct NativeRuntime {
+    /// Resident reasoning contexts keyed by session id. Declared first so the
+    /// contexts drop before the model they were created from.
+    sessions: Mutex<HashMap<String, NativeSession>>,
+    /// The last session-less generation's context, for system-prompt reuse.
+    prompt_cache: Mutex<Option<PromptCache>>,
+    status: RuntimeStatus,
+    backend: Option<LlamaBackend>,
+    /// Why `backend` is None, if BitNet.cpp failed to initialize.
+    backend_error: Option<String>,
+    /// Told how far reasoning and embedding model loads are; see `set_load_progress_callback`.
+    load_progress: Option<LoadProgressCallback>,
+    /// Aborts those loads; see `load_cancel`.
+    load_cancel: LoadCancel,
+    reasoning_model: Option<LlamaModel>,
+    reasoning_model_path: Option<PathBuf>,
+    /// GGUF size of the active reasoning model, for `reasoning_budget_bytes`.
+    reasoning_model_bytes: u64,
+    /// `n_gpu_layers` the reasoning model was loaded with.
+    reasoning_gpu_layers: i32,
+    /// Layers llama.cpp reported putting on the GPU when it loaded the
+    /// reasoning model; None if its log didn't say.
+    reasoning_offloaded_layers: Option<i32>,
+    /// The GPU device llama.cpp's log reported, kept up to date by `absorb_backend_logs`.
+    gpu_device: Mutex<Option<GpuDevice>>,
+    /// Previously active reasoning models kept loaded; empty unless
+    /// `max_resident_reasoning` > 1.
+    warm_reasoning: HashMap<PathBuf, WarmModel>,
+    /// Most reasoning models resident at once, the active one included.
+    max_resident_reasoning: usize,
+    /// Most GGUF bytes the resident reasoning models may add up to.
+    reasoning_budget_bytes: Option<u64>,
+    embedding_model: Option<LlamaModel>,
+    embedding_model_path: Option<PathBuf>,
+    fast_model: Option<LlamaModel>,
+    fast_model_path: Option<PathBuf>,
+    vision_model: Option<LlamaModel>,
+    vision_model_path: Option<PathBuf>,
+    vision_clip_ctx: Option<*mut bitnet_sys::clip_ctx>,
+    vision_mmproj_path: Option<PathBuf>,
+    /// Small model that proposes tokens for the reasoning model to verify.
+    draft_model: Option<LlamaModel>,
+    draft_model_path: Option<PathBuf>,
+    /// Vocab-only model for token counting without a loaded inference model.
+    tokenizer_model: Option<LlamaModel>,
+    tokenizer_path: Option<PathBuf>,
+    /// Most recent generation per session id, for repro capture.
+    recent_generations: Mutex<HashMap<String, GenerationRecord>>,
+    /// Sampler RNG stream position per explicit session id.
+    sampler_states: Mutex<HashMap<String, SamplerState>>,
+    /// Flash attention default for generation contexts.
+    flash_attention: Option<bool>,
+    /// Largest accepted prompt in tokens; `None` = the model's training context.
+    max_prompt_tokens: Option<u32>,
+    last_flash_attention: Mutex<Option<FlashAttentionUse>>,
+    /// Decode speed (f32 bits, 0 = unknown) of the last plain reasoning generation —
+    /// the baseline speculative runs are compared against.
+    plain_decode_tps: AtomicU32,
+    /// When the reasoning model was loaded or last started/finished a generation.
+    last_reasoning_use: Mutex<Instant>,
+    /// Reasoning model unloaded for idleness, reloaded on the next generation.
+    idle_unloaded_path: Option<PathBuf>,
+    /// Run `warmup()` after every reasoning model load.
+    warmup_after_load: bool,
+    /// Load reasoning models even when `check_load_memory` says they won't fit.
+    skip_memory_check: bool,
+    /// The automatic warmup of the current reasoning model, if it ran.
+    last_warmup: Option<ModelWarmup>,
+}
+
+/// Marks the reasoning model as used when created and again when dropped, so a
+/// long generation doesn't count as idle time.
+struct ReasoningActivity<'a>(&'a Mutex<Instant>);
+
+impl<'a> ReasoningActivity<'a> {
+    fn begin(last_use: &'a Mutex<Instant>) -> Self {
+        *last_use.lock().unwrap_or_else(|e| e.into_inner()) = Instant::now();
+        ReasoningActivity(last_use)
+    }
+}
+
+impl Drop for ReasoningActivity<'_> {
+    fn drop(&mut self) {
+        *self.0.lock().unwrap_or_else(|e| e.into_inner()) = Instant::now();
+    }
+}
+
+/// Resident sessions kept at once; each pins a generation context of its request's `n_ctx`.
+const MAX_RESIDENT_SESSIONS: usize = 4;
+
+/// A reasoning context kept alive across generations for one session.
+struct NativeSession {
+    ctx: LlamaContext,
+    /// Tokens in the KV cache (sequence 0), in position order.
+    tokens: Vec<LlamaToken>,
+    /// Leading tokens (the system prompt) that trimming never removes.
+    prefix_len: usize,
+    last_used: Instant,
+}
+
+impl NativeSession {
+    /// Cut the KV cache back to the longest prefix it shares with `prompt`,
+    /// leaving at least the last prompt token to decode (sampling needs its
+    /// logits). Returns how many prompt tokens are already cached.
+    fn keep_prefix_of(&mut self, prompt: &[LlamaToken]) -> usize {
+        let mut keep = shared_prefix_len(&self.tokens, prompt).min(prompt.len().saturating_sub(1));
+        if keep < self.tokens.len() && !self.ctx.kv_cache_seq_rm(0, keep as i32, -1) {
+            // Partial removal isn't supported by every cache type; start over.
+            self.ctx.kv_cache_seq_rm(0, 0, -1);
+            keep = 0;
         }
+        self.tokens.truncate(keep);
+        keep
+    }
+}
 
-        let duration_ms = start.elapsed().as_millis() as u64;
-        let output = String::from_utf8_lossy(&output_bytes).into_owned();
+/// Receives model load progress: which model (`"reasoning"` or `"embedding"`),
+/// its path, and the fraction loaded, 0.0 to 1.0.
+pub type LoadProgressCallback = Arc<dyn Fn(&str, &Path, f32) + Send + Sync>;
+
+/// Cancels the reasoning or embedding model load in progress. Obtained from
+/// `NativeRuntime::load_cancel` and used without the runtime's lock, which
+/// the load holds throughout.
+#[derive(Clone, Default)]
+pub struct LoadCancel {
+    loading: Arc<AtomicBool>,
+    requested: Arc<AtomicBool>,
+}
 
-        Ok(GenerateResponse {
-            text: output,
-            tokens_generated,
-            duration_ms,
-        })
+impl LoadCancel {
+    /// Ask the load in progress to stop at its next progress report. Returns
+    /// false if no load is in progress.
+    pub fn cancel(&self) -> bool {
+        if !self.loading.load(Ordering::SeqCst) {
+            return false;
+        }
+        self.requested.store(true, Ordering::SeqCst);
+        true
     }
 
-    /// Generate text from a prompt using the loaded fast-tier model (SmolLM2).
-    /// Does NOT check self.status — fast model availability is independent of primary.
-    /// SmolLM2 uses ChatML template (hardcoded).
-    pub fn generate_fast(&self, request: GenerateRequest) -> Result<GenerateResponse, String> {
-        Self::log("generate_fast() entered");
+    /// Mark a load as started; it is in progress until the run is dropped.
+    fn begin(&self) -> LoadRun {
+        self.requested.store(false, Ordering::SeqCst);
+        self.loading.store(true, Ordering::SeqCst);
+        LoadRun { cancel: self.clone() }
+    }
+
+    /// Whether the last load was asked to stop.
+    fn requested(&self) -> bool {
+        self.requested.load(Ordering::SeqCst)
+    }
+}
+
+struct LoadRun {
+    cancel: LoadCancel,
+}
+
+impl Drop for LoadRun {
+    fn drop(&mut self) {
+        self.cancel.loading.store(false, Ordering::SeqCst);
+    }
+}
+
+/// Whether `progress` has moved a whole percent past the last report in
+/// `reported` (percent + 1, 0 before the first), recording it if so. llama.cpp
+/// calls back per tensor, far more often than a progress bar needs.
+fn progress_step(reported: &AtomicU32, progress: f32) -> bool {
+    let percent = (progress.clamp(0.0, 1.0) * 100.0) as u32 + 1;
+    reported.fetch_max(percent, Ordering::Relaxed) < percent
+}
+
+/// Upper bound for `set_resident_models`; each resident model holds its full weights.
+pub const MAX_RESIDENT_REASONING: u32 = 4;
+
+/// A reasoning model kept loaded besides the active one, so a request naming
+/// it by `model_path` (or loading it again) doesn't re-read it from disk.
+struct WarmModel {
+    model: LlamaModel,
+    bytes: u64,
+    gpu_layers: i32,
+    /// Layers llama.cpp reported offloading; see `NativeRuntime::reasoning_offloaded_layers`.
+    offloaded_layers: Option<i32>,
+    last_used: Mutex<Instant>,
+}
+
+/// Warm models to evict, least recently used first, until the active model
+/// plus what's left are at most `max_resident` models and, with a budget, at
+/// most `budget_bytes` together. The active model itself is never evicted.
+fn plan_warm_evictions<K: Clone>(
+    warm: &[(K, u64, Instant)],
+    active_bytes: u64,
+    max_resident: usize,
+    budget_bytes: Option<u64>,
+) -> Vec<K> {
+    let mut by_age: Vec<&(K, u64, Instant)> = warm.iter().collect();
+    by_age.sort_by_key(|(_, _, last_used)| *last_used);
+    let mut count = 1 + warm.len();
+    let mut total = active_bytes + warm.iter().map(|(_, bytes, _)| bytes).sum::<u64>();
+    let mut evict = Vec::new();
+    for (key, bytes, _) in by_age {
+        if count <= max_resident && budget_bytes.is_none_or(|b| total <= b) {
+            break;
+        }
+        evict.push(key.clone());
+        count -= 1;
+        total -= bytes;
+    }
+    evict
+}
+
+/// Shortest system-prompt prefix worth pinning a context for in `PromptCache`.
+const MIN_CACHED_PREFIX_TOKENS: usize = 64;
+
+/// What a cached system-prompt prefix was decoded with. A request can reuse the
+/// cache only if all of it matches.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct PrefixKey {
+    /// Hash of the prefix tokens (the templated system prompt).
+    hash: u64,
+    len: usize,
+    n_ctx: usize,
+    flash_attention: Option<bool>,
+}
+
+impl PrefixKey {
+    fn new(prefix: &[LlamaToken], n_ctx: usize, flash_attention: Option<bool>) -> Self {
+        use std::hash::{Hash, Hasher};
+        let mut hasher = std::collections::hash_map::DefaultHasher::new();
+        prefix.hash(&mut hasher);
+        PrefixKey { hash: hasher.finish(), len: prefix.len(), n_ctx, flash_attention }
+    }
+}
+
+/// A reasoning context whose KV cache starts with a decoded system prompt, kept
+/// between session-less generations so the next one with the same system
+/// prompt only prefills its user turn.
+struct PromptCache {
+    ctx: LlamaContext,
+    key: PrefixKey,
+}
+
+/// Number of leading tokens `a` and `b` have in common.
+fn shared_prefix_len(a: &[LlamaToken], b: &[LlamaToken]) -> usize {
+    a.iter().zip(b).take_while(|(x, y)| x == y).count()
+}
+
+// SAFETY: NativeRuntime is only accessed through a tokio::sync::RwLock. Loaded
+// llama_model handles are read-only after load, and every `&self` inference call
+// creates its own llama_context and sampler, which llama.cpp supports concurrently
+// on one model. The raw *mut clip_ctx is not safe to share, so the only method
+// that uses it (generate_vision) takes `&mut self` and needs the write lock.
+unsafe impl Send for NativeRuntime {}
+unsafe impl Sync for NativeRuntime {}
+
+#[allow(dead_code)] // Public API — callers wired in Step 2 (BitNetProvider)
+impl NativeRuntime {
+    pub fn new() -> Self {
+        let (backend, backend_error) = match LlamaBackend::init() {
+            Ok(mut b) => {
+                b.capture_logs();
+                (Some(b), None)
+            }
+            Err(e) => {
+                eprintln!(
+                    "[NativeRuntime] Failed to initialize BitNet.cpp backend: {}",
+                    e
+                );
+                (None, Some(e))
+            }
+        };
+        NativeRuntime {
+            sessions: Mutex::new(HashMap::new()),
+            prompt_cache: Mutex::new(None),
+            status: RuntimeStatus::Uninitialized,
+            backend,
+            backend_error,
+            load_progress: None,
+            load_cancel: LoadCancel::default(),
+            reasoning_model: None,
+            reasoning_model_path: None,
+            reasoning_model_bytes: 0,
+            reasoning_gpu_layers: 0,
+            reasoning_offloaded_layers: None,
+            gpu_device: Mutex::new(None),
+            warm_reasoning: HashMap::new(),
+            max_resident_reasoning: 1,
+            reasoning_budget_bytes: None,
+            embedding_model: None,
+            embedding_model_path: None,
+            fast_model: None,
+            fast_model_path: None,
+            vision_model: None,
+            vision_model_path: None,
+            vision_clip_ctx: None,
+            vision_mmproj_path: None,
+            draft_model: None,
+            draft_model_path: None,
+            tokenizer_model: None,
+            tokenizer_path: None,
+            recent_generations: Mutex::new(HashMap::new()),
+            sampler_states: Mutex::new(HashMap::new()),
+            flash_attention: None,
+            max_prompt_tokens: None,
+            last_flash_attention: Mutex::new(None),
+            plain_decode_tps: AtomicU32::new(0),
+            last_reasoning_use: Mutex::new(Instant::now()),
+            idle_unloaded_path: None,
+            warmup_after_load: false,
+            skip_memory_check: false,
+            last_warmup: None,
+        }
+    }
+
+    /// Load a reasoning model from a GGUF file.
+    /// Works with both standard GGUF (Q4_K_M, Q8_0) and BitNet i2_s GGUFs.
+    /// Blocking — model loading reads the full file from disk.
+    pub fn load_reasoning_model(&mut self, model_path: PathBuf) -> Result<(), RuntimeError> {
+        self.load_reasoning_model_with_gpu_layers(model_path, None, None, false)
+    }
+
+    /// `load_reasoning_model`, offloading `gpu_layers` to the GPU. `None` keeps
+    /// everything on CPU. If the load fails with layers on the GPU (usually
+    /// out of VRAM), the error says so and the runtime keeps whatever model it
+    /// had, so the caller can retry with fewer layers.
+    ///
+    /// Before reading the weights, the model's footprint with an `n_ctx` KV
+    /// cache (default `GENERATION_N_CTX`) is checked against free RAM and
+    /// VRAM; see `check_reasoning_memory`. `skip_memory_check` skips it for
+    /// this load only ("load anyway").
+    pub fn load_reasoning_model_with_gpu_layers(
+        &mut self,
+        model_path: PathBuf,
+        gpu_layers: Option<GpuLayers>,
+        n_ctx: Option<u32>,
+        skip_memory_check: bool,
+    ) -> Result<(), RuntimeError> {
+        if !model_path.exists() {
+            return Err(format!("Model file not found: {:?}", model_path).into());
+        }
+        crate::gguf::check_supported(&model_path)?;
 
         let backend = self
             .backend
             .as_ref()
             .ok_or("BitNet.cpp backend not initialized")?;
-        let model = self
-            .fast_model
+
+        // A warm model is already in memory: no offload to plan, nothing to check.
+        let warm = self.warm_reasoning.contains_key(&model_path);
+        let shape = if warm { None } else { self.model_shape(&model_path).ok().map(|(shape, _)| shape) };
+        let model_bytes = std::fs::metadata(&model_path).map_or(0, |m| m.len());
+
+        // CPU-only by default (CUDA/Vulkan disabled in BitNet build for portability).
+        // n_gpu_layers=0 keeps everything on CPU — avoids crashes from missing GPU backend.
+        let n_gpu_layers = match gpu_layers {
+            None => 0,
+            Some(GpuLayers::Count(n)) => n,
+            Some(GpuLayers::Auto) if !backend.supports_gpu_offload() => 0,
+            Some(GpuLayers::Auto) => {
+                let n_layer = shape.as_ref().map_or(0, |s| s.n_layer);
+                let vram_mb = crate::hardware::offload_vram_mb().unwrap_or(0);
+                let n = auto_gpu_layers(n_layer, model_bytes, vram_mb);
+                eprintln!("[NativeRuntime] Auto GPU offload: {} of {} layers ({} MB VRAM)", n, n_layer, vram_mb);
+                n
+            }
+        };
+        if !skip_memory_check {
+            self.check_reasoning_memory(shape.as_ref(), model_bytes, n_gpu_layers, n_ctx)?;
+        }
+
+        self.status = RuntimeStatus::Loading;
+
+        let model_params = self.load_params("reasoning", &model_path, n_gpu_layers);
+
+        // A warm model becomes active without touching the disk.
+        let loaded = match self.warm_reasoning.remove(&model_path) {
+            Some(warm) => Ok((warm.model, warm.bytes, warm.gpu_layers, warm.offloaded_layers)),
+            None => {
+                // Only this load's lines may report the offload.
+                self.absorb_backend_logs();
+                LlamaModel::load_from_file(backend, &model_path, &model_params)
+                    .map(|model| (model, model_bytes, n_gpu_layers, self.absorb_backend_logs()))
+            }
+        };
+        // The load is over; from here (warmup included) there is nothing to cancel.
+        drop(model_params);
+        match loaded {
+            Ok((model, bytes, n_gpu_layers, offloaded_layers)) => {
+                // Session contexts and the warmup belong to the outgoing model.
+                // A failed or cancelled load keeps both.
+                self.last_warmup = None;
+                self.clear_sessions();
+                eprintln!(
+                    "[NativeRuntime] Reasoning model loaded: {:?} ({} params, embd={})",
+                    model_path,
+                    model.n_params(),
+                    model.n_embd()
+                );
+                if let Some(draft) = self.draft_model.as_ref() {
+                    if let Err(e) = check_draft_compatibility(&model, draft) {
+                        eprintln!("[NativeRuntime] Unloading draft model, incompatible with new reasoning model: {}", e);
+                        self.unload_draft_model();
+                    }
+                }
+                let template_check = render_chat_template(&model, &sample_chat_turns());
+                if !template_check.ok {
+                    eprintln!(
+                        "[NativeRuntime] Warning: chat template of {:?} failed to render: {}",
+                        model_path,
+                        template_check.error.as_deref().unwrap_or("unknown error")
+                    );
+                }
+                let outgoing = self.reasoning_model.replace(model);
+                let outgoing_path = self.reasoning_model_path.replace(model_path.clone());
+                if let (Some(old), Some(old_path)) = (outgoing, outgoing_path) {
+                    if self.max_resident_reasoning > 1 && old_path != model_path {
+                        let warm = WarmModel {
+                            model: old,
+                            bytes: self.reasoning_model_bytes,
+                            gpu_layers: self.reasoning_gpu_layers,
+                            offloaded_layers: self.reasoning_offloaded_layers,
+                            last_used: Mutex::new(Instant::now()),
+                        };
+                        self.warm_reasoning.insert(old_path, warm);
+                    }
+                }
+                self.reasoning_model_bytes = bytes;
+                self.evict_warm_reasoning();
+                self.reasoning_gpu_layers = n_gpu_layers;
+                self.reasoning_offloaded_layers = offloaded_layers;
+                self.plain_decode_tps.store(0, Ordering::Relaxed);
+                self.idle_unloaded_path = None;
+                *self.last_reasoning_use.get_mut().unwrap_or_else(|e| e.into_inner()) = Instant::now();
+                self.status = RuntimeStatus::Ready;
+                if self.warmup_after_load {
+                    // Best effort: a failed warmup leaves a usable model.
+                    match self.warmup() {
+                        Ok(warmup) => {
+                            eprintln!(
+                                "[NativeRuntime] Warmup of {:?}: {} ms ({} GPU layers)",
+                                model_path, warmup.duration_ms, warmup.gpu_layers
+                            );
+                            self.last_warmup = Some(warmup);
+                        }
+                        Err(e) => eprintln!("[NativeRuntime] Warmup of {:?} failed: {}", model_path, e),
+                    }
+                }
+                Ok(())
+            }
+            Err(_) if self.load_cancel.requested() => {
+                // Nothing was replaced, so whatever was loaded still serves.
+                eprintln!("[NativeRuntime] Reasoning model load cancelled: {:?}", model_path);
+                self.status = self.resident_status();
+                Err(RuntimeError::LoadCancelled)
+            }
+            Err(e) if n_gpu_layers != 0 => {
+                // Recoverable: nothing was replaced, so whatever was loaded still serves.
+                self.status = self.resident_status();
+                Err(format!(
+                    "Failed to load reasoning model with {} GPU layers: {} — the GPU is likely out of memory; retry with fewer n_gpu_layers, or \"auto\"",
+                    n_gpu_layers, e
+                ).into())
+            }
+            Err(e) => {
+                let err_msg = format!("Failed to load reasoning model: {}", e);
+                self.status = RuntimeStatus::Error(err_msg.clone());
+                Err(err_msg.into())
+            }
+        }
+    }
+
+    /// Load an embedding model from a GGUF file.
+    /// Blocking — model loading reads the full file from disk.
+    pub fn load_embedding_model(&mut self, model_path: PathBuf) -> Result<(), RuntimeError> {
+        if !model_path.exists() {
+            return Err(format!(
+                "Embedding model file not found: {:?}",
+                model_path
+            ).into());
+        }
+        crate::gguf::check_supported(&model_path)?;
+
+        let backend = self
+            .backend
             .as_ref()
-            .ok_or("No fast model loaded")?;
+            .ok_or("BitNet.cpp backend not initialized")?;
+
+        let model_params = self.load_params("embedding", &model_path, 0);
+
+        match LlamaModel::load_from_file(backend, &model_path, &model_params) {
+            Ok(model) => {
+                eprintln!(
+                    "[NativeRuntime] Embedding model loaded: {:?} (dim={})",
+                    model_path,
+                    model.n_embd()
+                );
+                self.embedding_model = Some(model);
+                self.embedding_model_path = Some(model_path);
+                if matches!(self.status, RuntimeStatus::Uninitialized) {
+                    self.status = RuntimeStatus::EmbeddingOnly;
+                }
+                Ok(())
+            }
+            Err(_) if self.load_cancel.requested() => Err(RuntimeError::LoadCancelled),
+            Err(e) => Err(format!("Failed to load embedding model: {}", e).into()),
+        }
+    }
+
+    /// Load a fast-tier model (SmolLM2) from a GGUF file.
+    /// Runs concurrently with reasoning model — does NOT gate overall readiness.
+    /// Blocking — model loading reads the full file from disk.
+    pub fn load_fast_model(&mut self, model_path: PathBuf) -> Result<(), String> {
+        if !model_path.exists() {
+            return Err(format!("Fast model file not found: {:?}", model_path));
+        }
+        crate::gguf::check_supported(&model_path)?;
+
+        let backend = self
+            .backend
+            .as_ref()
+            .ok_or("BitNet.cpp backend not initialized")?;
+
+        let model_params = LlamaModelParams::default().with_n_gpu_layers(0);
+
+        match LlamaModel::load_from_file(backend, &model_path, &model_params) {
+            Ok(model) => {
+                eprintln!(
+                    "[NativeRuntime] Fast model loaded: {:?} ({} params, embd={})",
+                    model_path,
+                    model.n_params(),
+                    model.n_embd()
+                );
+                self.fast_model = Some(model);
+                self.fast_model_path = Some(model_path);
+                Ok(())
+            }
+            Err(e) => Err(format!("Failed to load fast model: {}", e)),
+        }
+    }
+
+    /// Load a draft model for speculative decoding with the current reasoning model.
+    /// The draft must share the reasoning model's tokenizer; otherwise this errors
+    /// and any previously loaded draft is kept.
+    /// Blocking — model loading reads the full file from disk.
+    pub fn load_draft_model(&mut self, model_path: PathBuf) -> Result<(), String> {
+        if !model_path.exists() {
+            return Err(format!("Draft model file not found: {:?}", model_path));
+        }
+        crate::gguf::check_supported(&model_path)?;
+
+        let backend = self
+            .backend
+            .as_ref()
+            .ok_or("BitNet.cpp backend not initialized")?;
+        let main = self
+            .reasoning_model
+            .as_ref()
+            .ok_or("Load a reasoning model before loading a draft model")?;
+
+        let model_params = LlamaModelParams::default().with_n_gpu_layers(0);
+        let model = LlamaModel::load_from_file(backend, &model_path, &model_params)
+            .map_err(|e| format!("Failed to load draft model: {}", e))?;
+        check_draft_compatibility(main, &model)
+            .map_err(|e| format!("Draft model is incompatible with the reasoning model: {}", e))?;
+
+        eprintln!(
+            "[NativeRuntime] Draft model loaded: {:?} ({} params)",
+            model_path,
+            model.n_params()
+        );
+        self.draft_model = Some(model);
+        self.draft_model_path = Some(model_path);
+        Ok(())
+    }
+
+    pub fn has_draft_model(&self) -> bool {
+        self.draft_model.is_some()
+    }
+
+    pub fn draft_model_path(&self) -> Option<&PathBuf> {
+        self.draft_model_path.as_ref()
+    }
+
+    /// Drop the draft model; `generate()` goes back to plain decoding.
+    pub fn unload_draft_model(&mut self) {
+        self.draft_model = None;
+        self.draft_model_path = None;
+    }
+
+    pub fn has_fast_model(&self) -> bool {
+        self.fast_model.is_some()
+    }
+
+    pub fn fast_model_path(&self) -> Option<&PathBuf> {
+        self.fast_model_path.as_ref()
+    }
+
+    pub fn unload_fast_model(&mut self) {
+        self.fast_model = None;
+        self.fast_model_path = None;
+    }
+
+    /// Load a vision model (Moondream2) and its multimodal projector.
+    /// Both the main GGUF and the mmproj GGUF must be provided.
+    pub fn load_vision_model(&mut self, model_path: PathBuf, mmproj_path: PathBuf) -> Result<(), String> {
+        if !model_path.exists() {
+            return Err(format!("Vision model file not found: {:?}", model_path));
+        }
+        crate::gguf::check_supported(&model_path)?;
+        if !mmproj_path.exists() {
+            return Err(format!("Vision mmproj file not found: {:?}", mmproj_path));
+        }
+
+        let backend = self.backend.as_ref().ok_or("Backend not initialized")?;
+
+        // Load the main vision model (same as any GGUF)
+        let model_params = LlamaModelParams::default().with_n_gpu_layers(0);
+        let model = LlamaModel::load_from_file(backend, &model_path, &model_params)
+            .map_err(|e| format!("Failed to load vision model: {}", e))?;
+
+        // Load the CLIP multimodal projector
+        let mmproj_cstr = std::ffi::CString::new(mmproj_path.to_string_lossy().as_bytes())
+            .map_err(|_| "Invalid mmproj path".to_string())?;
+        let clip_ctx = unsafe { bitnet_sys::clip_model_load(mmproj_cstr.as_ptr(), 0) };
+        if clip_ctx.is_null() {
+            return Err("Failed to load CLIP model from mmproj".to_string());
+        }
+
+        eprintln!(
+            "[NativeRuntime] Vision model loaded: {:?} + mmproj: {:?} (clip_embd={})",
+            model_path, mmproj_path,
+            unsafe { bitnet_sys::clip_n_mmproj_embd(clip_ctx) }
+        );
+        self.vision_model = Some(model);
+        self.vision_model_path = Some(model_path);
+        self.vision_clip_ctx = Some(clip_ctx);
+        self.vision_mmproj_path = Some(mmproj_path);
+        Ok(())
+    }
+
+    /// Generate text from an image + prompt using the vision model.
+    /// Image is processed through CLIP, embeddings injected into context,
+    /// then text generation continues with the Moondream2 chat template.
+    pub fn generate_vision(&mut self, prompt: String, image_path: String, max_tokens: u32) -> Result<GenerateResponse, String> {
+        let model = self.vision_model.as_ref().ok_or("No vision model loaded")?;
+        let clip_ctx = self.vision_clip_ctx.ok_or("No CLIP model loaded")?;
+        let backend = self.backend.as_ref().ok_or("Backend not initialized")?;
+
+        let start = std::time::Instant::now();
+
+        // Encode image through CLIP
+        let image_cstr = std::ffi::CString::new(image_path.as_bytes())
+            .map_err(|_| "Invalid image path".to_string())?;
+        let n_threads = std::thread::available_parallelism().map(|n| n.get() as i32).unwrap_or(4);
+
+        let image_embed = unsafe {
+            bitnet_sys::llava_image_embed_make_with_filename(clip_ctx, n_threads, image_cstr.as_ptr())
+        };
+        if image_embed.is_null() {
+            return Err("Failed to encode image through CLIP".to_string());
+        }
+
+        Self::log(&format!("generate_vision: image encoded, n_image_pos={}", unsafe { (*image_embed).n_image_pos }));
+
+        // Create context
+        let ctx_params = LlamaContextParams::default().with_n_ctx(std::num::NonZeroU32::new(4096));
+        let mut ctx = model.new_context(backend, ctx_params)
+            .map_err(|e| format!("Failed to create vision context: {}", e))?;
+
+        // Inject image embeddings into context at position 0
+        let prefill_start = Instant::now();
+        let mut n_past: i32 = 0;
+        let success = unsafe {
+            bitnet_sys::llava_eval_image_embed(
+                ctx.as_mut_ptr(),
+                image_embed,
+                512,
+                &mut n_past,
+            )
+        };
+
+        // Free image embed (no longer needed after injection)
+        unsafe { bitnet_sys::llava_image_embed_free(image_embed); }
+
+        if !success {
+            return Err("Failed to inject image embeddings into context".to_string());
+        }
+
+        Self::log(&format!("generate_vision: image embeddings injected, n_past={}", n_past));
+
+        // Tokenize the text prompt (comes after image in the context)
+        // Moondream2 uses a simple prompt format: <image>\nQuestion: {prompt}\n\nAnswer:
+        let text_prompt = format!("\nQuestion: {}\n\nAnswer:", prompt);
+        let tokens = model.str_to_token(&text_prompt, AddBos::Always)
+            .map_err(|e| format!("Vision tokenization failed: {}", e))?;
+
+        if tokens.is_empty() {
+            return Err("Empty prompt after tokenization".to_string());
+        }
+
+        // Decode text tokens after image embeddings (chunked prefill)
+        let chunk_size: usize = 512;
+        let total_prompt_tokens = tokens.len();
+        let mut pos = n_past;
+        for (chunk_idx, chunk) in tokens.chunks(chunk_size).enumerate() {
+            let is_last_chunk = (chunk_idx + 1) * chunk_size >= total_prompt_tokens;
+            let mut batch = LlamaBatch::new(chunk.len().max(512), 1);
+            for (i, token) in chunk.iter().enumerate() {
+                let is_last_token = is_last_chunk && i == chunk.len() - 1;
+                batch.add(*token, pos, &[0], is_last_token)
+                    .map_err(|e| format!("Vision batch add failed: {}", e))?;
+                pos += 1;
+            }
+            ctx.decode(&mut batch)
+                .map_err(|e| format!("Vision prefill chunk {} failed: {}", chunk_idx, e))?;
+        }
+
+        let prefill_ms = prefill_start.elapsed().as_millis() as u64;
+        Self::log("generate_vision: text prefill complete, starting generation...");
+
+        // Sampler chain (same as generate_fast — deterministic for vision)
+        let mut sampler = LlamaSampler::chain_simple([
+            LlamaSampler::top_p(0.9, 1),
+            LlamaSampler::min_p(0.05, 1),
+            LlamaSampler::temp(0.3),
+            LlamaSampler::dist(42),
+        ]);
+
+        // Generation loop (identical to generate/generate_fast)
+        let mut output = StopBuffer::new(&["<|endoftext|>", "\nQuestion:"]);
+        let mut n_cur = pos;
+        let mut tokens_generated = 0u32;
+        let mut gen_batch = LlamaBatch::new(1, 1);
+        let mut first_token_ms = None;
+        let decode_start = Instant::now();
+
+        for _ in 0..max_tokens {
+            let token = sampler.sample(&ctx, -1);
+            sampler.accept(token);
+            first_token_ms.get_or_insert_with(|| start.elapsed().as_millis() as u64);
+
+            if model.is_eog_token(token) {
+                break;
+            }
+
+            let piece = model.token_to_bytes(token);
+            tokens_generated += 1;
+
+            if output.push(&piece) {
+                break;
+            }
+
+            gen_batch.clear();
+            gen_batch.add(token, n_cur, &[0], true)
+                .map_err(|e| format!("Vision decode failed: {}", e))?;
+            ctx.decode(&mut gen_batch)
+                .map_err(|e| format!("Vision decode failed: {}", e))?;
+            n_cur += 1;
+        }
+
+        let decode_ms = decode_start.elapsed().as_millis() as u64;
+        let duration_ms = start.elapsed().as_millis() as u64;
+        let output = String::from_utf8_lossy(&output.bytes).into_owned();
+
+        Self::log(&format!("generate_vision: {} tokens in {}ms", tokens_generated, duration_ms));
+
+        Ok(GenerateResponse {
+            text: output,
+            tokens_generated,
+            duration_ms,
+            speculative: None,
+            unmapped_logit_bias: Vec::new(),
+            token_intervals_ms: None,
+            first_token_ms,
+            prompt_tokens: pos as u32,
+            prefill_ms,
+            decode_ms,
+            tokens_per_second: tokens_per_second(decode_ms, tokens_generated),
+        })
+    }
+
+    pub fn has_vision_model(&self) -> bool { self.vision_model.is_some() }
+    pub fn vision_model_path(&self) -> Option<&PathBuf> { self.vision_model_path.as_ref() }
+
+    pub fn unload_vision_model(&mut self) {
+        self.vision_model = None;
+        self.vision_model_path = None;
+        if let Some(ctx) = self.vision_clip_ctx.take() {
+            unsafe { bitnet_sys::clip_free(ctx); }
+        }
+        self.vision_mmproj_path = None;
+    }
+
+    // File-based logging — eprintln goes nowhere on Windows GUI apps
+    fn log(msg: &str) {
+        use std::io::Write;
+        let log_path = if cfg!(target_os = "windows") {
+            let appdata = std::env::var("LOCALAPPDATA").unwrap_or_else(|_| ".".to_string());
+            std::path::PathBuf::from(appdata)
+                .join("Semblance")
+                .join("native_runtime.log")
+        } else {
+            std::path::PathBuf::from("/tmp/semblance_native_runtime.log")
+        };
+        if let Ok(mut f) = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&log_path)
+        {
+            let _ = writeln!(f, "[NativeRuntime] {}", msg);
+        }
+    }
+
+    /// Generate text from a prompt using the loaded reasoning model.
+    /// Blocking — runs the full inference loop synchronously.
+    pub fn generate(&self, request: GenerateRequest) -> Result<GenerateResponse, RuntimeError> {
+        self.generate_with_hooks(request, &GenerationHooks::NONE)
+    }
+
+    /// `generate`, calling into `hooks` as it runs.
+    ///
+    /// Without a draft model, the context of the last generation is kept with
+    /// its decoded system prompt (when that is at least
+    /// `MIN_CACHED_PREFIX_TOKENS` long). A request whose system prompt (and
+    /// context size and flash attention choice) matches skips prefilling it;
+    /// any change just prefills in full and replaces the cache. This pins one
+    /// extra context's KV cache while a reasoning model is loaded.
+    pub fn generate_with_hooks(
+        &self,
+        request: GenerateRequest,
+        hooks: &GenerationHooks,
+    ) -> Result<GenerateResponse, RuntimeError> {
+        self.generate_inner(request, hooks, None)
+    }
+
+    /// `generate` in a resident session: the session's context stays alive
+    /// between calls, so a follow-up turn whose prompt extends the previous one
+    /// only prefills the appended tokens. If the prompt diverges from what the
+    /// cache holds, the KV cache is cut back to the shared prefix (possibly
+    /// nothing, i.e. a full prefill). Records and sampler state are kept under
+    /// `session_id`. Always decodes plainly, even with a draft model loaded.
+    ///
+    /// Memory: each live session pins a KV cache for its request's `n_ctx`
+    /// (`GENERATION_N_CTX` unless set; tens to hundreds of MB depending on the
+    /// model and window) until `reset_session`, eviction
+    /// (at most `MAX_RESIDENT_SESSIONS`, least recently used first), or a
+    /// model unload. Concurrent calls for one session don't share the cache:
+    /// the second starts a fresh context and the last to finish is kept.
+    pub fn generate_in_session(&self, session_id: &str, request: GenerateRequest) -> Result<GenerateResponse, RuntimeError> {
+        self.generate_in_session_with_hooks(session_id, request, &GenerationHooks::NONE)
+    }
+
+    /// `generate_in_session`, calling into `hooks` as it runs.
+    pub fn generate_in_session_with_hooks(
+        &self,
+        session_id: &str,
+        mut request: GenerateRequest,
+        hooks: &GenerationHooks,
+    ) -> Result<GenerateResponse, RuntimeError> {
+        request.session_id = Some(session_id.to_string());
+        self.generate_inner(request, hooks, Some(session_id))
+    }
+
+    /// Drop a resident session and its context. Returns false if there was none.
+    pub fn reset_session(&self, session_id: &str) -> bool {
+        self.sessions().remove(session_id).is_some()
+    }
+
+    fn generate_inner(
+        &self,
+        request: GenerateRequest,
+        hooks: &GenerationHooks,
+        resident: Option<&str>,
+    ) -> Result<GenerateResponse, RuntimeError> {
+        Self::log("generate() entered");
+
+        if !self.can_generate() {
+            return Err(self.generation_refused());
+        }
+
+        let backend = self
+            .backend
+            .as_ref()
+            .ok_or("BitNet.cpp backend not initialized")?;
+        let (model, model_path) = self.route_reasoning(&request.model_path)?;
+        // Sessions, the prompt cache, and speculative decoding belong to the active model.
+        let routed = model_path != self.reasoning_model_path.as_ref();
+        if routed && resident.is_some() {
+            return Err(format!(
+                "Resident sessions run on the active reasoning model; load {} first",
+                request.model_path
+            ).into());
+        }
+        let chat_format = chat_format_for(model_path);
+        let _activity = ReasoningActivity::begin(&self.last_reasoning_use);
+
+        let start = std::time::Instant::now();
+        let max_tokens = request.max_tokens.unwrap_or(512);
+        let temperature = request.temperature.unwrap_or(0.7);
+
+        let full_prompt = chat_prompt(model, &request, chat_format);
+
+        Self::log(&format!(
+            "generate: prompt_len={} chars, max_tokens={}, temp={}",
+            full_prompt.len(),
+            max_tokens,
+            temperature
+        ));
+
+        Self::log("generate: tokenizing...");
+        let mut tokens = model
+            .str_to_token(&full_prompt, AddBos::Always)
+            .map_err(|e| format!("Tokenization failed: {}", e))?;
+
+        Self::log(&format!("generate: tokenized {} tokens", tokens.len()));
+
+        if tokens.is_empty() {
+            return Err("Empty prompt after tokenization".into());
+        }
+
+        // Refuse (or cut) a giant prompt before allocating a context for it.
+        let keep_head = if request.truncate_prompt {
+            system_prefix_len(model, &request, &tokens, chat_format)
+        } else {
+            0
+        };
+        if let Some(limit) = self.prompt_token_limit().effective {
+            check_prompt_tokens(&mut tokens, limit as usize, keep_head, request.truncate_prompt)?;
+        }
+        let grammar = request.grammar.as_deref().map(|g| compile_grammar(model, g)).transpose()?;
+
+        let n_ctx = self.request_n_ctx(model, request.n_ctx, GENERATION_N_CTX) as usize;
+        let prompt_tokens = tokens.len();
+        fit_context(&mut tokens, max_tokens as usize, n_ctx, keep_head, request.truncate_prompt)?;
+        if tokens.len() < prompt_tokens {
+            Self::log(&format!(
+                "generate: TRUNCATED {} tokens -> {} to fit context",
+                prompt_tokens,
+                tokens.len()
+            ));
+        }
+        let penalties = SamplerPenalties::from_request(&request, n_ctx, model.token_nl())?;
+
+        // A session context of another size can't be reused.
+        let cached_session = resident
+            .and_then(|id| self.sessions().remove(id))
+            .filter(|s| s.ctx.n_ctx() as usize == n_ctx);
+        // Session-less plain decoding can reuse the system prompt's KV cache.
+        let prefix_key = (resident.is_none() && self.draft_model.is_none() && !routed)
+            .then(|| system_prefix_len(model, &request, &tokens, chat_format))
+            .filter(|&len| len >= MIN_CACHED_PREFIX_TOKENS && len < tokens.len())
+            .map(|len| PrefixKey::new(&tokens[..len], n_ctx, request.flash_attention));
+        let cached_prefix = prefix_key.and_then(|key| {
+            let mut cache = self.prompt_cache.lock().unwrap_or_else(|e| e.into_inner()).take()?;
+            // Drop the previous user turn and output, keeping the prefix.
+            (cache.key == key && cache.ctx.kv_cache_seq_rm(0, key.len as i32, -1)).then_some(cache.ctx)
+        });
+        let (mut ctx, reused) = match (cached_session, cached_prefix) {
+            (Some(mut session), _) => {
+                let reused = session.keep_prefix_of(&tokens);
+                Self::log(&format!(
+                    "generate: session {:?} reuses {} of {} prompt tokens",
+                    resident, reused, tokens.len()
+                ));
+                (session.ctx, reused)
+            }
+            (None, Some(ctx)) => {
+                let reused = prefix_key.map_or(0, |key| key.len);
+                Self::log(&format!("generate: reusing cached system prompt ({} of {} prompt tokens)", reused, tokens.len()));
+                (ctx, reused)
+            }
+            (None, None) => {
+                // 4096 context by default — sufficient for conversational turns with
+                // Qwen Q4_K_M models. Smaller BitNet models use less KV cache so this is safe for both.
+                Self::log(&format!("generate: creating context with n_ctx={}...", n_ctx));
+                let ctx_params = LlamaContextParams::default().with_n_ctx(NonZeroU32::new(n_ctx as u32));
+                let ctx = self
+                    .new_generation_context(model, backend, ctx_params, request.flash_attention)
+                    .map_err(|e| format!("Failed to create context: {}", e))?;
+                Self::log("generate: context created OK");
+                (ctx, 0)
+            }
+        };
+
+        let (logit_bias, unmapped_logit_bias) =
+            resolve_logit_bias(request.logit_bias.as_ref(), |s| model.str_to_token(s, AddBos::Never));
+        let logit_bias = apply_token_bias(logit_bias, request.token_bias.as_ref(), model.n_vocab())?;
+        if !unmapped_logit_bias.is_empty() {
+            Self::log(&format!("generate: logit_bias keys not a single token: {:?}", unmapped_logit_bias));
+        }
+        let rng_start = self.session_rng_start(&request);
+        let (mode, top_p, min_p) = sampling_settings(&request, 0.95)?;
+        let sampler_config = SamplerConfig {
+            top_p,
+            min_p,
+            temperature,
+            seed: request_seed(&request, rng_start.as_ref()),
+            max_tokens,
+            logit_bias,
+            rng_state: rng_start.as_ref().map(|s| s.rng),
+            penalties,
+            mode,
+        };
+        let mut sampler = sampler_config.build(model.n_vocab(), grammar);
+
+        if self.draft_model.is_some() && resident.is_none() && !routed {
+            let mut response =
+                self.generate_speculative(ctx, &tokens, &request, &mut sampler, max_tokens, start, hooks)?;
+            response.unmapped_logit_bias = unmapped_logit_bias;
+            self.store_sampler_state(&request, &sampler_config, rng_start, &sampler);
+            self.record_generation(generation_record(
+                "reasoning",
+                model_path,
+                self.draft_model_path.as_ref(),
+                &request,
+                (full_prompt, tokens.len()),
+                sampler_config,
+                &response,
+            ));
+            return Ok(response);
+        }
+
+        // Chunked prefill: decode prompt in batches (only what the session cache lacks).
+        let prefill_start = Instant::now();
+        let pos = Self::prefill(&mut ctx, &tokens[reused..], reused as i32, true)?;
+        let prefill_ms = prefill_start.elapsed().as_millis() as u64;
+
+        Self::log("generate: prefill decode OK, starting generation loop...");
+
+        // Generation loop — accumulate raw bytes then decode to UTF-8 at the end
+        let mut output = StopBuffer::new(request.stop.as_deref().unwrap_or_default());
+        let mut n_cur = pos;
+        let mut tokens_generated = 0u32;
+        let mut char_budget = request.max_chars.map(CharBudget::new);
+        let decode_start = Instant::now();
+        let mut timer = TokenTimer::new(request.profile_timing, max_tokens, start);
+
+        // Single-token batch for auto-regressive generation
+        let mut gen_batch = LlamaBatch::new(1, 1);
+        // Generated tokens now in the KV cache, for the resident session.
+        let mut decoded: Vec<LlamaToken> = Vec::new();
+
+        for _ in 0..max_tokens {
+            hooks.checkpoint()?;
+            let token = sampler.sample(&ctx, -1)?;
+            sampler.accept(token);
+            if let Some(ms) = timer.sampled() {
+                (hooks.on_first_token)(ms);
+            }
+
+            // End-of-generation check
+            if model.is_eog_token(token) {
+                break;
+            }
+
+            // Decode token to bytes
+            let piece = model.token_to_bytes(token);
+            tokens_generated += 1;
+            timer.tick();
+
+            if output.push(&piece) {
+                break;
+            }
+            if char_budget.as_mut().is_some_and(|b| b.exhausted(&mut output.bytes)) {
+                break;
+            }
+
+            // Prepare next batch with just the new token
+            gen_batch.clear();
+            gen_batch
+                .add(token, n_cur, &[0], true)
+                .map_err(|e| format!("Batch add failed: {}", e))?;
+            ctx.decode(&mut gen_batch)
+                .map_err(|e| format!("Decode failed: {}", e))?;
+            n_cur += 1;
+            if resident.is_some() {
+                decoded.push(token);
+            }
+        }
+
+        let decode_ms = decode_start.elapsed().as_millis() as u64;
+        let tps = tokens_per_second(decode_ms, tokens_generated);
+        if tps > 0.0 && !routed {
+            self.plain_decode_tps.store(tps.to_bits(), Ordering::Relaxed);
+        }
+
+        if let Some(session_id) = resident {
+            let prefix_len = system_prefix_len(model, &request, &tokens, chat_format);
+            let mut cached = tokens.clone();
+            cached.extend_from_slice(&decoded);
+            self.put_session(session_id, NativeSession { ctx, tokens: cached, prefix_len, last_used: Instant::now() });
+        } else if let Some(key) = prefix_key {
+            *self.prompt_cache.lock().unwrap_or_else(|e| e.into_inner()) = Some(PromptCache { ctx, key });
+        }
+
+        let duration_ms = start.elapsed().as_millis() as u64;
+        let output = String::from_utf8_lossy(&output.bytes).into_owned();
+
+        let response = GenerateResponse {
+            text: output,
+            tokens_generated,
+            duration_ms,
+            speculative: None,
+            unmapped_logit_bias,
+            first_token_ms: timer.first_token_ms,
+            token_intervals_ms: timer.finish(),
+            prompt_tokens: tokens.len() as u32,
+            prefill_ms,
+            decode_ms,
+            tokens_per_second: tps,
+        };
+        self.store_sampler_state(&request, &sampler_config, rng_start, &sampler);
+        self.record_generation(generation_record(
+            "reasoning",
+            model_path,
+            None,
+            &request,
+            (full_prompt, tokens.len()),
+            sampler_config,
+            &response,
+        ));
+        Ok(response)
+    }
+
+    /// The reasoning model `model_path` names: the active one or a warm one,
+    /// which is marked as used. An empty path, or any path with routing off,
+    /// gets the active model. With routing on, a path that isn't resident
+    /// (e.g. evicted since `ensure_reasoning_routable`) is an error rather
+    /// than output from a different model.
+    fn route_reasoning(&self, model_path: &str) -> Result<(&LlamaModel, Option<&PathBuf>), RuntimeError> {
+        let path = Path::new(model_path);
+        if !model_path.is_empty() && self.max_resident_reasoning > 1 && self.reasoning_model_path.as_deref() != Some(path) {
+            let Some((path, warm)) = self.warm_reasoning.get_key_value(path) else {
+                return Err(format!("Reasoning model {:?} is not loaded; it may have been evicted, retry to reload it", path).into());
+            };
+            *warm.last_used.lock().unwrap_or_else(|e| e.into_inner()) = Instant::now();
+            return Ok((&warm.model, Some(path)));
+        }
+        let model = self
+            .reasoning_model
+            .as_ref()
+            .ok_or(RuntimeError::NoReasoningModel)?;
+        Ok((model, self.reasoning_model_path.as_ref()))
+    }
+
+    /// Built-in chat format of the active reasoning model; see `chat_format_for`.
+    fn reasoning_chat_format(&self) -> ChatFormat {
+        chat_format_for(self.reasoning_model_path.as_ref())
+    }
+
+    /// Wrap a user prompt in the reasoning model's built-in chat format.
+    fn reasoning_prompt(&self, system_prompt: Option<&str>, prompt: &str) -> String {
+        let mut turns = Vec::with_capacity(2);
+        if let Some(sys) = system_prompt.filter(|s| !s.is_empty()) {
+            turns.push(("system", sys));
+        }
+        turns.push(("user", prompt));
+        self.reasoning_chat_format().render(&turns)
+    }
+
+    /// Decode `tokens` into sequence 0 starting at `start_pos`, in 512-token chunks.
+    /// Logits are kept for the final token only if `logits_last`. Returns the next position.
+    fn prefill(ctx: &mut LlamaContext, tokens: &[LlamaToken], start_pos: i32, logits_last: bool) -> Result<i32, String> {
+        let chunk_size: usize = 512;
+        let total_prompt_tokens = tokens.len();
+        Self::log(&format!(
+            "generate: chunked prefill, {} tokens in chunks of {}",
+            total_prompt_tokens, chunk_size
+        ));
+
+        let mut pos: i32 = start_pos;
+        for (chunk_idx, chunk) in tokens.chunks(chunk_size).enumerate() {
+            let is_last_chunk = (chunk_idx + 1) * chunk_size >= total_prompt_tokens;
+            let mut batch = LlamaBatch::new(chunk.len().max(512), 1);
+
+            for (i, token) in chunk.iter().enumerate() {
+                let is_last_token = logits_last && is_last_chunk && i == chunk.len() - 1;
+                batch
+                    .add(*token, pos, &[0], is_last_token)
+                    .map_err(|e| format!("Batch add failed: {}", e))?;
+                pos += 1;
+            }
+
+            Self::log(&format!(
+                "generate: decoding chunk {} ({} tokens, pos={})",
+                chunk_idx,
+                chunk.len(),
+                pos
+            ));
+            // Flush log before decode — if we crash here, at least we'll see which chunk
+            Self::log("generate: calling ctx.decode()...");
+            ctx.decode(&mut batch)
+                .map_err(|e| format!("Prompt decode chunk {} failed: {}", chunk_idx, e))?;
+            Self::log(&format!("generate: chunk {} decoded OK", chunk_idx));
+        }
+        Ok(pos)
+    }
+
+    /// Speculative decoding: each round the draft model greedily proposes up to
+    /// `SPECULATIVE_MAX_DRAFT` tokens, the reasoning model scores them in one batch,
+    /// and tokens are kept while the reasoning model's own sample agrees with the
+    /// draft. The first disagreement is replaced by the reasoning model's token, so
+    /// every round yields at least one token. Every emitted token is drawn from the
+    /// reasoning model's sampler, so output follows the same distribution as plain decoding.
+    #[allow(clippy::too_many_arguments)]
+    fn generate_speculative(
+        &self,
+        mut ctx: LlamaContext,
+        tokens: &[LlamaToken],
+        request: &GenerateRequest,
+        sampler: &mut TokenSampler,
+        max_tokens: u32,
+        start: Instant,
+        hooks: &GenerationHooks,
+    ) -> Result<GenerateResponse, RuntimeError> {
+        let backend = self
+            .backend
+            .as_ref()
+            .ok_or("BitNet.cpp backend not initialized")?;
+        let model = self
+            .reasoning_model
+            .as_ref()
+            .ok_or(RuntimeError::NoReasoningModel)?;
+        let draft = self.draft_model.as_ref().ok_or("No draft model loaded")?;
+        // Same window as the reasoning context created by generate().
+        let n_ctx = ctx.n_ctx() as i32;
+
+        Self::log(&format!("generate: speculative, {} prompt tokens", tokens.len()));
+
+        let mut draft_ctx = draft
+            .new_context(backend, LlamaContextParams::default().with_n_ctx(NonZeroU32::new(n_ctx as u32)))
+            .map_err(|e| format!("Failed to create draft context: {}", e))?;
+
+        // Both KV caches hold every committed token except the newest one
+        // (`id_last`), which is fed at the start of each round. `history` mirrors
+        // the reasoning model's cache so the draft can catch up when it lags.
+        let (prompt_head, prompt_last) = tokens.split_at(tokens.len() - 1);
+        let mut id_last = prompt_last[0];
+        let mut history: Vec<LlamaToken> = prompt_head.to_vec();
+        let prefill_start = Instant::now();
+        let mut n_past = Self::prefill(&mut ctx, prompt_head, 0, false)?;
+        let mut draft_n_past = Self::prefill(&mut draft_ctx, prompt_head, 0, false)?;
+        let prefill_ms = prefill_start.elapsed().as_millis() as u64;
+
+        let mut draft_sampler = LlamaSampler::greedy();
+        let mut batch = LlamaBatch::new(SPECULATIVE_MAX_DRAFT + 1, 1);
+        let mut draft_batch = LlamaBatch::new(SPECULATIVE_MAX_DRAFT + 2, 1);
+
+        let mut output = StopBuffer::new(request.stop.as_deref().unwrap_or_default());
+        let mut tokens_generated = 0u32;
+        let mut char_budget = request.max_chars.map(CharBudget::new);
+        let (mut proposed, mut accepted) = (0u32, 0u32);
+        let (mut draft_time, mut verify_time) = (std::time::Duration::ZERO, std::time::Duration::ZERO);
+        let decode_start = Instant::now();
+        // Tokens accepted in one round are ticked together, so intervals within
+        // a round are ~0 and the round's cost lands on its first token.
+        let mut timer = TokenTimer::new(request.profile_timing, max_tokens, start);
+
+        'rounds: while tokens_generated < max_tokens && n_past < n_ctx {
+            hooks.checkpoint()?;
+            let remaining = (max_tokens - tokens_generated) as usize;
+            let n_draft = SPECULATIVE_MAX_DRAFT
+                .min(remaining - 1)
+                .min((n_ctx - n_past - 1) as usize);
+
+            // Draft phase: catch the draft cache up, then propose greedily.
+            let draft_started = Instant::now();
+            let mut drafts: Vec<LlamaToken> = Vec::with_capacity(n_draft);
+            if n_draft > 0 {
+                let mut feed: Vec<LlamaToken> = history[draft_n_past as usize..].to_vec();
+                feed.push(id_last);
+                draft_batch.clear();
+                for (i, tok) in feed.iter().enumerate() {
+                    draft_batch
+                        .add(*tok, draft_n_past + i as i32, &[0], i == feed.len() - 1)
+                        .map_err(|e| format!("Draft batch add failed: {}", e))?;
+                }
+                draft_ctx.decode(&mut draft_batch)
+                    .map_err(|e| format!("Draft decode failed: {}", e))?;
+                draft_n_past += feed.len() as i32;
+
+                loop {
+                    let tok = draft_sampler.sample(&draft_ctx, -1);
+                    draft_sampler.accept(tok);
+                    if draft.is_eog_token(tok) {
+                        break;
+                    }
+                    drafts.push(tok);
+                    if drafts.len() >= n_draft {
+                        break;
+                    }
+                    draft_batch.clear();
+                    draft_batch
+                        .add(tok, draft_n_past, &[0], true)
+                        .map_err(|e| format!("Draft batch add failed: {}", e))?;
+                    draft_ctx.decode(&mut draft_batch)
+                        .map_err(|e| format!("Draft decode failed: {}", e))?;
+                    draft_n_past += 1;
+                }
+            }
+            draft_time += draft_started.elapsed();
+
+            // Verify phase: score id_last + drafts in one batch.
+            let verify_started = Instant::now();
+            batch.clear();
+            batch
+                .add(id_last, n_past, &[0], true)
+                .map_err(|e| format!("Batch add failed: {}", e))?;
+            for (i, tok) in drafts.iter().enumerate() {
+                batch
+                    .add(*tok, n_past + 1 + i as i32, &[0], true)
+                    .map_err(|e| format!("Batch add failed: {}", e))?;
+            }
+            ctx.decode(&mut batch)
+                .map_err(|e| format!("Decode failed: {}", e))?;
+
+            let mut emitted: Vec<LlamaToken> = Vec::with_capacity(drafts.len() + 1);
+            for i in 0..=drafts.len() {
+                let tok = sampler.sample(&ctx, i as i32)?;
+                sampler.accept(tok);
+                if let Some(ms) = timer.sampled() {
+                    (hooks.on_first_token)(ms);
+                }
+                emitted.push(tok);
+                if i == drafts.len() || tok != drafts[i] {
+                    break;
+                }
+            }
+            verify_time += verify_started.elapsed();
+            proposed += drafts.len() as u32;
+            accepted += (emitted.len() - 1) as u32;
+
+            // Commit id_last + the accepted drafts; roll back the rest.
+            history.push(id_last);
+            history.extend_from_slice(&emitted[..emitted.len() - 1]);
+            n_past += emitted.len() as i32;
+            id_last = emitted[emitted.len() - 1];
+            ctx.kv_cache_seq_rm(0, n_past, -1);
+            if draft_n_past > n_past {
+                draft_ctx.kv_cache_seq_rm(0, n_past, -1);
+                draft_n_past = n_past;
+            }
+
+            for tok in emitted {
+                if model.is_eog_token(tok) {
+                    break 'rounds;
+                }
+                tokens_generated += 1;
+                timer.tick();
+                if output.push(&model.token_to_bytes(tok))
+                    || char_budget.as_mut().is_some_and(|b| b.exhausted(&mut output.bytes))
+                    || tokens_generated >= max_tokens
+                {
+                    break 'rounds;
+                }
+            }
+        }
+
+        let decode_ms = decode_start.elapsed().as_millis() as u64;
+        let tps = tokens_per_second(decode_ms, tokens_generated);
+        let baseline = match f32::from_bits(self.plain_decode_tps.load(Ordering::Relaxed)) {
+            b if b > 0.0 => Some(b),
+            _ => None,
+        };
+        let stats = SpeculativeStats {
+            draft_model: self
+                .draft_model_path
+                .as_ref()
+                .and_then(|p| p.file_name())
+                .map(|n| n.to_string_lossy().into_owned())
+                .unwrap_or_default(),
+            draft_tokens_proposed: proposed,
+            draft_tokens_accepted: accepted,
+            acceptance_rate: if proposed > 0 { accepted as f32 / proposed as f32 } else { 0.0 },
+            draft_ms: draft_time.as_millis() as u64,
+            verify_ms: verify_time.as_millis() as u64,
+            tokens_per_sec: tps,
+            baseline_tokens_per_sec: baseline,
+            speedup: baseline.map(|b| tps / b),
+        };
+        Self::log(&format!(
+            "generate: speculative done, {} tokens, {}/{} drafts accepted, {:.1} tok/s",
+            tokens_generated, accepted, proposed, tps
+        ));
+
+        Ok(GenerateResponse {
+            text: String::from_utf8_lossy(&output.bytes).into_owned(),
+            tokens_generated,
+            duration_ms: start.elapsed().as_millis() as u64,
+            speculative: Some(stats),
+            unmapped_logit_bias: Vec::new(),
+            first_token_ms: timer.first_token_ms,
+            token_intervals_ms: timer.finish(),
+            prompt_tokens: tokens.len() as u32,
+            prefill_ms,
+            decode_ms,
+            tokens_per_second: tps,
+        })
+    }
+
+    /// Generate text from a prompt using the loaded fast-tier model (SmolLM2).
+    /// Does NOT check self.status — fast model availability is independent of primary.
+    /// SmolLM2 uses ChatML template (hardcoded).
+    pub fn generate_fast(&self, request: GenerateRequest) -> Result<GenerateResponse, RuntimeError> {
+        self.generate_fast_with_hooks(request, &GenerationHooks::NONE)
+    }
+
+    /// `generate_fast`, calling into `hooks` as it runs.
+    pub fn generate_fast_with_hooks(
+        &self,
+        request: GenerateRequest,
+        hooks: &GenerationHooks,
+    ) -> Result<GenerateResponse, RuntimeError> {
+        Self::log("generate_fast() entered");
+
+        let backend = self
+            .backend
+            .as_ref()
+            .ok_or("BitNet.cpp backend not initialized")?;
+        let model = self
+            .fast_model
+            .as_ref()
+            .ok_or("No fast model loaded")?;
+
+        let start = std::time::Instant::now();
+        let max_tokens = request.max_tokens.unwrap_or(256);
+        let temperature = request.temperature.unwrap_or(0.3);
+
+        // SmolLM2 uses ChatML template
+        let full_prompt = chat_prompt(model, &request, ChatFormat::ChatMl);
+
+        let mut tokens = model
+            .str_to_token(&full_prompt, AddBos::Always)
+            .map_err(|e| format!("Fast tokenization failed: {}", e))?;
+
+        if tokens.is_empty() {
+            return Err("Empty prompt after tokenization".into());
+        }
+        let grammar = request.grammar.as_deref().map(|g| compile_grammar(model, g)).transpose()?;
+
+        let n_ctx = self.request_n_ctx(model, request.n_ctx, GENERATION_N_CTX) as usize;
+        let keep_head = if request.truncate_prompt {
+            system_prefix_len(model, &request, &tokens, ChatFormat::ChatMl)
+        } else {
+            0
+        };
+        fit_context(&mut tokens, max_tokens as usize, n_ctx, keep_head, request.truncate_prompt)?;
+        let penalties = SamplerPenalties::from_request(&request, n_ctx, model.token_nl())?;
+        let ctx_params = LlamaContextParams::default().with_n_ctx(NonZeroU32::new(n_ctx as u32));
+        let mut ctx = self
+            .new_generation_context(model, backend, ctx_params, request.flash_attention)
+            .map_err(|e| format!("Failed to create fast context: {}", e))?;
+
+        // Chunked prefill
+        let prefill_start = Instant::now();
+        let chunk_size: usize = 512;
+        let total_prompt_tokens = tokens.len();
+        let mut pos: i32 = 0;
+        for (chunk_idx, chunk) in tokens.chunks(chunk_size).enumerate() {
+            let is_last_chunk = (chunk_idx + 1) * chunk_size >= total_prompt_tokens;
+            let mut batch = LlamaBatch::new(chunk.len().max(512), 1);
+            for (i, token) in chunk.iter().enumerate() {
+                let is_last_token = is_last_chunk && i == chunk.len() - 1;
+                batch
+                    .add(*token, pos, &[0], is_last_token)
+                    .map_err(|e| format!("Fast batch add failed: {}", e))?;
+                pos += 1;
+            }
+            ctx.decode(&mut batch)
+                .map_err(|e| format!("Fast prefill chunk {} failed: {}", chunk_idx, e))?;
+        }
+        let prefill_ms = prefill_start.elapsed().as_millis() as u64;
+
+        // Sampler: more deterministic for fast tier (lower temp, tighter top-p)
+        let (logit_bias, unmapped_logit_bias) =
+            resolve_logit_bias(request.logit_bias.as_ref(), |s| model.str_to_token(s, AddBos::Never));
+        let logit_bias = apply_token_bias(logit_bias, request.token_bias.as_ref(), model.n_vocab())?;
+        let rng_start = self.session_rng_start(&request);
+        let (mode, top_p, min_p) = sampling_settings(&request, 0.9)?;
+        let sampler_config = SamplerConfig {
+            top_p,
+            min_p,
+            temperature,
+            seed: request_seed(&request, rng_start.as_ref()),
+            max_tokens,
+            logit_bias,
+            rng_state: rng_start.as_ref().map(|s| s.rng),
+            penalties,
+            mode,
+        };
+        let mut sampler = sampler_config.build(model.n_vocab(), grammar);
+
+        let mut output = StopBuffer::new(request.stop.as_deref().unwrap_or_default());
+        let mut n_cur = pos;
+        let mut tokens_generated = 0u32;
+        let mut char_budget = request.max_chars.map(CharBudget::new);
+        let mut gen_batch = LlamaBatch::new(1, 1);
+        let mut timer = TokenTimer::new(request.profile_timing, max_tokens, start);
+        let decode_start = Instant::now();
+
+        for _ in 0..max_tokens {
+            hooks.checkpoint()?;
+            let token = sampler.sample(&ctx, -1)?;
+            sampler.accept(token);
+            if let Some(ms) = timer.sampled() {
+                (hooks.on_first_token)(ms);
+            }
+
+            if model.is_eog_token(token) {
+                break;
+            }
+
+            let piece = model.token_to_bytes(token);
+            tokens_generated += 1;
+            timer.tick();
+
+            if output.push(&piece) {
+                break;
+            }
+            if char_budget.as_mut().is_some_and(|b| b.exhausted(&mut output.bytes)) {
+                break;
+            }
+
+            gen_batch.clear();
+            gen_batch
+                .add(token, n_cur, &[0], true)
+                .map_err(|e| format!("Fast decode failed: {}", e))?;
+            ctx.decode(&mut gen_batch)
+                .map_err(|e| format!("Fast decode failed: {}", e))?;
+            n_cur += 1;
+        }
+
+        let decode_ms = decode_start.elapsed().as_millis() as u64;
+        let duration_ms = start.elapsed().as_millis() as u64;
+        let output = String::from_utf8_lossy(&output.bytes).into_owned();
+
+        Self::log(&format!("generate_fast: {} tokens in {}ms", tokens_generated, duration_ms));
+
+        let response = GenerateResponse {
+            text: output,
+            tokens_generated,
+            duration_ms,
+            speculative: None,
+            unmapped_logit_bias,
+            first_token_ms: timer.first_token_ms,
+            token_intervals_ms: timer.finish(),
+            prompt_tokens: total_prompt_tokens as u32,
+            prefill_ms,
+            decode_ms,
+            tokens_per_second: tokens_per_second(decode_ms, tokens_generated),
+        };
+        self.store_sampler_state(&request, &sampler_config, rng_start, &sampler);
+        self.record_generation(generation_record(
+            "fast",
+            self.fast_model_path.as_ref(),
+            None,
+            &request,
+            (full_prompt, tokens.len()),
+            sampler_config,
+            &response,
+        ));
+        Ok(response)
+    }
+
+    /// Remember `record` as its session's most recent generation. Keeps at most
+    /// `MAX_RECORDED_SESSIONS` sessions, evicting the least recently recorded.
+    fn record_generation(&self, record: GenerationRecord) {
+        let mut recent = self.recent_generations.lock().unwrap_or_else(|e| e.into_inner());
+        recent.insert(record.session_id.clone(), record);
+        while recent.len() > MAX_RECORDED_SESSIONS {
+            let oldest = recent
+                .values()
+                .min_by_key(|r| r.recorded_at_ms)
+                .map(|r| r.session_id.clone());
+            match oldest {
+                Some(id) => recent.remove(&id),
+                None => break,
+            };
+        }
+    }
+
+    /// Sampler state a generation for `request` continues from, if its session
+    /// has one and the request doesn't set its own `seed`.
+    fn session_rng_start(&self, request: &GenerateRequest) -> Option<SamplerState> {
+        if request.seed.is_some() {
+            return None;
+        }
+        let session_id = stateful_session(request.session_id.as_deref())?;
+        self.sampler_states
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .get(session_id)
+            .cloned()
+    }
+
+    /// Store where `sampler` left its session's RNG stream. No-op for requests
+    /// without an explicit session id. Keeps at most `MAX_RECORDED_SESSIONS`.
+    fn store_sampler_state(
+        &self,
+        request: &GenerateRequest,
+        config: &SamplerConfig,
+        start: Option<SamplerState>,
+        sampler: &TokenSampler,
+    ) {
+        let Some(session_id) = stateful_session(request.session_id.as_deref()) else {
+            return;
+        };
+        let (seed, prior_draws) = start.map_or((config.seed, 0), |s| (s.seed, s.draws));
+        let state = SamplerState {
+            version: SAMPLER_STATE_VERSION,
+            algorithm: SAMPLER_RNG_ALGORITHM.to_string(),
+            rng: sampler.rng,
+            seed,
+            draws: prior_draws + sampler.draws,
+            sampler: config.describe(),
+            updated_at_ms: now_ms(),
+        };
+        self.put_sampler_state(session_id, state);
+    }
+
+    fn put_sampler_state(&self, session_id: &str, state: SamplerState) {
+        let mut states = self.sampler_states.lock().unwrap_or_else(|e| e.into_inner());
+        states.insert(session_id.to_string(), state);
+        while states.len() > MAX_RECORDED_SESSIONS {
+            let oldest = states
+                .iter()
+                .min_by_key(|(_, s)| s.updated_at_ms)
+                .map(|(id, _)| id.clone());
+            match oldest {
+                Some(id) => states.remove(&id),
+                None => break,
+            };
+        }
+    }
+
+    /// Where `session_id`'s sampler RNG stream currently is. Errors if the
+    /// session hasn't generated (or been given a state) yet.
+    pub fn sampler_state(&self, session_id: &str) -> Result<SamplerState, String> {
+        let id = stateful_session(Some(session_id))
+            .ok_or("Generations without a session_id don't keep sampler state")?;
+        self.sampler_states
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .get(id)
+            .cloned()
+            .ok_or_else(|| format!("No sampler state for session: {}", session_id))
+    }
+
+    /// Make `session_id`'s next generation continue from `state`.
+    pub fn set_sampler_state(&self, session_id: &str, mut state: SamplerState) -> Result<(), String> {
+        let id = stateful_session(Some(session_id))
+            .ok_or("Generations without a session_id don't keep sampler state")?;
+        if state.version != SAMPLER_STATE_VERSION {
+            return Err(format!("Unsupported sampler state version: {}", state.version));
+        }
+        if state.algorithm != SAMPLER_RNG_ALGORITHM {
+            return Err(format!("Unsupported sampler RNG algorithm: {}", state.algorithm));
+        }
+        state.updated_at_ms = now_ms();
+        self.put_sampler_state(id, state);
+        Ok(())
+    }
+
+    /// The most recent generation recorded for `session_id`.
+    pub fn generation_record(&self, session_id: &str) -> Result<GenerationRecord, String> {
+        self.recent_generations
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .get(session_id)
+            .cloned()
+            .ok_or_else(|| format!("No recorded generation for session: {}", session_id))
+    }
+
+    /// Generate embeddings for a batch of texts using the loaded embedding model.
+    /// Blocking — runs the forward passes synchronously.
+    ///
+    /// Inputs are embedded in sub-batches sized to fit available memory, one
+    /// sequence per input. One context, sized for the largest sub-batch, is
+    /// reused for all of them with the KV cache cleared in between. If a
+    /// sub-batch fails (context allocation or decode), it is halved and retried
+    /// in a fresh context rather than aborting the whole job. The final
+    /// sub-batch size is reported in the response.
+    pub fn embed(&self, request: EmbedRequest) -> Result<EmbedResponse, String> {
+        Self::log(&format!("embed() entered, {} inputs", request.input.len()));
+
+        let model = self
+            .embedding_model
+            .as_ref()
+            .ok_or("No embedding model loaded")?;
+        let backend = self
+            .backend
+            .as_ref()
+            .ok_or("BitNet.cpp backend not initialized")?;
+
+        if request.pooling == Some(PoolingMode::None) {
+            return Err("Pooling \"none\" gives per-token embeddings; embed needs one vector per input (use mean, cls or last)".to_string());
+        }
+        let pooling = request.pooling.map(PoolingMode::pooling_type);
+
+        let start = std::time::Instant::now();
+        let n_embd = model.n_embd() as u32;
+        let window = self.request_n_ctx(model, request.n_ctx, EMBED_MAX_TOKENS_PER_INPUT as u32) as usize;
+
+        // Tokenize everything up front so sub-batches can be sized by token count.
+        let mut tokenized = Vec::with_capacity(request.input.len());
+        for text in &request.input {
+            let tokens = model
+                .str_to_token(text, AddBos::Always)
+                .map_err(|e| format!("Tokenization failed: {}", e))?;
+            // Safety: truncate tokens to fit within the per-input embedding window.
+            let tokens = if tokens.len() > window {
+                Self::log(&format!(
+                    "embed: TRUNCATING {} tokens -> {}",
+                    tokens.len(),
+                    window
+                ));
+                tokens[..window].to_vec()
+            } else {
+                tokens
+            };
+            tokenized.push(tokens);
+        }
+
+        // The embedding model is loaded CPU-only (n_gpu_layers=0), so system RAM is
+        // the binding budget. Model weights are already resident, so available RAM
+        // already excludes the model's footprint.
+        let available_ram_mb = {
+            let mut sys = sysinfo::System::new();
+            sys.refresh_memory();
+            sys.available_memory() / (1024 * 1024)
+        };
+        let longest = tokenized.iter().map(|t| t.len()).max().unwrap_or(0);
+        let mut sub_batch_size = plan_embed_sub_batch(
+            available_ram_mb,
+            longest,
+            model.n_layer().max(1) as u64,
+            n_embd as u64,
+            tokenized.len(),
+        );
+        Self::log(&format!(
+            "embed: available_ram={}MB longest={} tokens -> sub_batch_size={}",
+            available_ram_mb, longest, sub_batch_size
+        ));
+
+        let mut all_embeddings = Vec::with_capacity(tokenized.len());
+        let mut ctx = None;
+        let mut capacity = embed_context_tokens(&tokenized, sub_batch_size);
+        let mut next = 0;
+        while next < tokenized.len() {
+            let end = (next + sub_batch_size).min(tokenized.len());
+            let shape = (capacity, sub_batch_size);
+            match Self::embed_sub_batch(model, backend, &mut ctx, shape, pooling, &tokenized[next..end], n_embd as usize) {
+                Ok(vectors) => {
+                    all_embeddings.extend(vectors);
+                    next = end;
+                }
+                Err(e) if sub_batch_size > 1 => {
+                    ctx = None;
+                    sub_batch_size /= 2;
+                    capacity = embed_context_tokens(&tokenized[next..], sub_batch_size);
+                    Self::log(&format!(
+                        "embed: sub-batch at input {} failed ({}), shrinking to {}",
+                        next, e, sub_batch_size
+                    ));
+                }
+                Err(e) => return Err(e),
+            }
+        }
+
+        let failed_inputs = reject_non_finite(&mut all_embeddings);
+        if !failed_inputs.is_empty() {
+            Self::log(&format!(
+                "embed: {} of {} embeddings had NaN/inf values (inputs {:?}), returned empty",
+                failed_inputs.len(),
+                all_embeddings.len(),
+                failed_inputs
+            ));
+        }
+
+        let duration_ms = start.elapsed().as_millis() as u64;
+        Self::log(&format!(
+            "embed: all {} inputs done in {}ms (sub_batch_size={})",
+            all_embeddings.len(),
+            duration_ms,
+            sub_batch_size
+        ));
+
+        Ok(EmbedResponse {
+            sequences_clamped: sub_batch_size < all_embeddings.len(),
+            embeddings: all_embeddings,
+            dimensions: n_embd,
+            duration_ms,
+            sub_batch_size: sub_batch_size as u32,
+            failed_count: failed_inputs.len() as u32,
+            failed_inputs,
+        })
+    }
+
+    /// Embed one sub-batch of pre-tokenized inputs, one sequence per input, in
+    /// `ctx`: reused with a cleared KV cache if set, otherwise created for
+    /// `(tokens, sequences)` and kept for the next sub-batch. `pooling` overrides
+    /// the model's pooling type. Empty inputs get a zero vector without a sequence.
+    fn embed_sub_batch(
+        model: &LlamaModel,
+        backend: &LlamaBackend,
+        ctx: &mut Option<LlamaContext>,
+        (max_tokens, max_seqs): (usize, usize),
+        pooling: Option<PoolingType>,
+        inputs: &[Vec<LlamaToken>],
+        n_embd: usize,
+    ) -> Result<Vec<Vec<f32>>, String> {
+        let total_tokens: usize = inputs.iter().map(|t| t.len()).sum();
+        let n_seqs = inputs.iter().filter(|t| !t.is_empty()).count();
+        if n_seqs == 0 {
+            return Ok(vec![vec![0.0f32; n_embd]; inputs.len()]);
+        }
+
+        if ctx.as_ref().is_some_and(|c| (c.n_ctx() as usize) < total_tokens) {
+            *ctx = None;
+        }
+        let ctx = match ctx {
+            Some(ctx) => {
+                ctx.clear_kv_cache();
+                ctx
+            }
+            None => {
+                // All of a sub-batch's tokens go in one decode call so non-causal
+                // (BERT-style) models see each sequence whole.
+                let max_tokens = max_tokens.max(total_tokens) as u32;
+                let mut ctx_params = LlamaContextParams::default()
+                    .with_embeddings(true)
+                    .with_n_ctx(NonZeroU32::new(max_tokens))
+                    .with_n_batch(max_tokens)
+                    .with_n_seq_max(max_seqs.max(n_seqs) as u32);
+                if let Some(pooling) = pooling {
+                    ctx_params = ctx_params.with_pooling_type(pooling);
+                }
+                let created = model
+                    .new_context(backend, ctx_params)
+                    .map_err(|e| format!("Failed to create embedding context: {}", e))?;
+                ctx.insert(created)
+            }
+        };
+
+        let mut batch = LlamaBatch::new(total_tokens, 1);
+        let mut seq_ids = Vec::with_capacity(inputs.len());
+        let mut seq: i32 = 0;
+        for tokens in inputs {
+            if tokens.is_empty() {
+                seq_ids.push(None);
+                continue;
+            }
+            for (pos, token) in tokens.iter().enumerate() {
+                batch
+                    .add(*token, pos as i32, &[seq], pos == tokens.len() - 1)
+                    .map_err(|e| format!("Embed batch add failed: {}", e))?;
+            }
+            seq_ids.push(Some(seq));
+            seq += 1;
+        }
+
+        Self::log(&format!(
+            "embed: decoding sub-batch ({} inputs, {} tokens)",
+            inputs.len(),
+            total_tokens
+        ));
+        ctx.decode(&mut batch)
+            .map_err(|e| format!("Embed decode failed: {}", e))?;
+
+        seq_ids
+            .into_iter()
+            .map(|seq_id| match seq_id {
+                None => Ok(vec![0.0f32; n_embd]),
+                Some(id) => ctx
+                    .embeddings_seq_ith(id)
+                    .map(l2_normalize)
+                    .map_err(|e| format!("Failed to get embeddings: {}", e)),
+            })
+            .collect()
+    }
+
+    /// Number of tokens `text` encodes to with the reasoning model's tokenizer
+    /// (no BOS, no chat template).
+    pub fn count_tokens(&self, text: &str) -> Result<usize, RuntimeError> {
+        let model = self
+            .reasoning_model
+            .as_ref()
+            .ok_or(RuntimeError::NoReasoningModel)?;
+        model
+            .str_to_token(text, AddBos::Never)
+            .map(|tokens| tokens.len())
+            .map_err(|e| format!("Tokenization failed: {}", e).into())
+    }
+
+    /// Token ids `text` encodes to with the reasoning model's tokenizer
+    /// (no chat template). `add_bos` adds the BOS and any other special tokens
+    /// the tokenizer prepends, as generation does.
+    pub fn tokenize(&self, text: &str, add_bos: bool) -> Result<Vec<LlamaToken>, RuntimeError> {
+        let model = self
+            .reasoning_model
+            .as_ref()
+            .ok_or(RuntimeError::NoReasoningModel)?;
+        model
+            .str_to_token(text, if add_bos { AddBos::Always } else { AddBos::Never })
+            .map_err(|e| format!("Tokenization failed: {}", e).into())
+    }
+
+    /// Text of `tokens` with the reasoning model's vocabulary, pieces joined.
+    /// Control tokens, which have no piece, show as their vocabulary text
+    /// (`<|im_start|>`) so prompt formatting stays visible.
+    pub fn detokenize(&self, tokens: &[LlamaToken]) -> Result<String, RuntimeError> {
+        let model = self
+            .reasoning_model
+            .as_ref()
+            .ok_or(RuntimeError::NoReasoningModel)?;
+        let n_vocab = model.n_vocab();
+        let mut bytes = Vec::new();
+        for &token in tokens {
+            if token < 0 || token >= n_vocab {
+                return Err(format!("Token id {} is outside the vocabulary (0–{})", token, n_vocab - 1).into());
+            }
+            let piece = model.token_to_bytes(token);
+            if piece.is_empty() {
+                bytes.extend_from_slice(model.token_text(token));
+            } else {
+                bytes.extend_from_slice(&piece);
+            }
+        }
+        Ok(String::from_utf8_lossy(&bytes).into_owned())
+    }
+
+    /// Load only the vocabulary of the GGUF at `path` into the tokenizer slot,
+    /// replacing whatever was there. No weights are read, so this costs a few
+    /// MB to a few tens of MB (vocab tables and metadata, growing with vocab
+    /// size) regardless of model size. The tokenizer slot is independent of the
+    /// inference models and can't generate.
+    pub fn load_tokenizer(&mut self, path: PathBuf) -> Result<TokenizerInfo, String> {
+        if !path.exists() {
+            return Err(format!("Model file not found: {:?}", path));
+        }
+        let backend = self
+            .backend
+            .as_ref()
+            .ok_or("BitNet.cpp backend not initialized")?;
+        let params = LlamaModelParams::default().with_n_gpu_layers(0).with_vocab_only(true);
+        let model = LlamaModel::load_from_file(backend, &path, &params)
+            .map_err(|e| format!("Failed to load tokenizer: {}", e))?;
+        self.tokenizer_model = Some(model);
+        self.tokenizer_path = Some(path);
+        self.tokenizer_info().ok_or_else(|| "Tokenizer failed to load".to_string())
+    }
+
+    pub fn tokenizer_info(&self) -> Option<TokenizerInfo> {
+        let model = self.tokenizer_model.as_ref()?;
+        Some(TokenizerInfo {
+            path: self.tokenizer_path.as_ref()?.to_string_lossy().to_string(),
+            n_vocab: model.n_vocab(),
+            vocab_type: model.vocab_type(),
+        })
+    }
+
+    /// Number of tokens `text` encodes to with the tokenizer slot's vocabulary
+    /// (no BOS, no chat template), counted the same way as `count_tokens`.
+    pub fn tokenizer_count(&self, text: &str) -> Result<usize, String> {
+        let model = self
+            .tokenizer_model
+            .as_ref()
+            .ok_or("No tokenizer loaded — call load_tokenizer first")?;
+        model
+            .str_to_token(text, AddBos::Never)
+            .map(|tokens| tokens.len())
+            .map_err(|e| format!("Tokenization failed: {}", e))
+    }
+
+    /// Time embedding `batch_size` synthetic inputs of `text_len` characters, after
+    /// a one-input warm-up. Blocking — callers should run it off the async runtime.
+    pub fn benchmark_embedding(&self, batch_size: u32, text_len: u32) -> Result<EmbeddingBenchmark, String> {
+        if batch_size == 0 || batch_size > MAX_EMBED_BENCHMARK_BATCH {
+            return Err(format!("batch_size must be between 1 and {}", MAX_EMBED_BENCHMARK_BATCH));
+        }
+        if text_len == 0 || text_len > MAX_EMBED_BENCHMARK_TEXT_LEN {
+            return Err(format!("text_len must be between 1 and {}", MAX_EMBED_BENCHMARK_TEXT_LEN));
+        }
+        let model = self
+            .embedding_model
+            .as_ref()
+            .ok_or("No embedding model loaded")?;
+
+        let input: Vec<String> = (0..batch_size as usize)
+            .map(|i| synthetic_text(i, text_len as usize))
+            .collect();
+        let tokens_per_input = model
+            .str_to_token(&input[0], AddBos::Always)
+            .map_err(|e| format!("Tokenization failed: {}", e))?
+            .len()
+            .min(EMBED_MAX_TOKENS_PER_INPUT) as u32;
+
+        let warmup = self.embed(EmbedRequest {
+            model_path: String::new(),
+            input: vec![input[0].clone()],
+            n_ctx: None,
+            pooling: None,
+        })?;
+
+        let start = Instant::now();
+        let response = self.embed(EmbedRequest {
+            model_path: String::new(),
+            input,
+            n_ctx: None,
+            pooling: None,
+        })?;
+        let secs = start.elapsed().as_secs_f32();
+
+        Ok(EmbeddingBenchmark {
+            batch_size,
+            text_len_chars: text_len,
+            tokens_per_input,
+            dimensions: response.dimensions,
+            warmup_ms: warmup.duration_ms,
+            duration_ms: (secs * 1000.0) as u64,
+            embeddings_per_sec: if secs > 0.0 { batch_size as f32 / secs } else { 0.0 },
+            ms_per_embedding: secs * 1000.0 / batch_size as f32,
+            sub_batch_size: response.sub_batch_size,
+        })
+    }
+
+    /// Zero-shot classification: embed the text and every label, rank labels by
+    /// cosine similarity to the text. Uses only the embedding model — no reasoning
+    /// model required. Scores are in [-1, 1], highest first.
+    pub fn classify_text(&self, text: &str, labels: &[String]) -> Result<Vec<LabelScore>, String> {
+        if labels.is_empty() {
+            return Err("At least one label is required".to_string());
+        }
+        if labels.iter().any(|l| l.trim().is_empty()) {
+            return Err("Labels must not be empty strings".to_string());
+        }
+
+        // Embed text + labels in one request so they share the same model state.
+        let mut input = Vec::with_capacity(labels.len() + 1);
+        input.push(text.to_string());
+        input.extend(labels.iter().cloned());
+        let response = self.embed(EmbedRequest {
+            model_path: String::new(),
+            input,
+            n_ctx: None,
+            pooling: None,
+        })?;
+
+        let (text_vec, label_vecs) = response
+            .embeddings
+            .split_first()
+            .ok_or("Embedding returned no vectors")?;
+
+        let mut scores: Vec<LabelScore> = labels
+            .iter()
+            .zip(label_vecs)
+            .map(|(label, vec)| LabelScore {
+                label: label.clone(),
+                score: cosine_similarity(text_vec, vec),
+            })
+            .collect();
+        scores.sort_by(|a, b| b.score.total_cmp(&a.score));
+
+        Self::log(&format!(
+            "classify_text: {} labels, top={:?}",
+            scores.len(),
+            scores.first().map(|s| &s.label)
+        ));
+        Ok(scores)
+    }
+
+    /// Pick one of a closed set of `choices` as the reasoning model's answer to
+    /// `prompt`. Instead of sampling, the prompt is prefilled once and each
+    /// choice is scored by the log-probability of its tokens as the reply (the
+    /// KV cache is rolled back between choices), so the result is deterministic.
+    /// Returns the choices ranked best first. Scores are total log-probabilities:
+    /// a choice that is a prefix of another always scores at least as high, so
+    /// prefer choices that don't share leading words.
+    pub fn classify_choice(&self, prompt: &str, choices: &[String]) -> Result<ChoiceClassification, RuntimeError> {
+        if choices.is_empty() {
+            return Err("At least one choice is required".into());
+        }
+        if choices.len() > MAX_CLASSIFY_CHOICES {
+            return Err(format!("At most {} choices are supported", MAX_CLASSIFY_CHOICES).into());
+        }
+        if !self.can_generate() {
+            return Err(self.generation_refused());
+        }
+        let backend = self
+            .backend
+            .as_ref()
+            .ok_or("BitNet.cpp backend not initialized")?;
+        let model = self
+            .reasoning_model
+            .as_ref()
+            .ok_or(RuntimeError::NoReasoningModel)?;
+        let _activity = ReasoningActivity::begin(&self.last_reasoning_use);
+        let start = Instant::now();
+
+        let choice_tokens = choices
+            .iter()
+            .map(|choice| {
+                if choice.trim().is_empty() {
+                    return Err("Choices must not be empty strings".to_string());
+                }
+                let tokens = model
+                    .str_to_token(choice, AddBos::Never)
+                    .map_err(|e| format!("Tokenization failed: {}", e))?;
+                if tokens.len() > MAX_CHOICE_TOKENS {
+                    return Err(format!("Choice {:?} is longer than {} tokens", choice, MAX_CHOICE_TOKENS));
+                }
+                Ok(tokens)
+            })
+            .collect::<Result<Vec<_>, String>>()?;
+
+        let full_prompt = self.reasoning_prompt(None, prompt);
+        let tokens = model
+            .str_to_token(&full_prompt, AddBos::Always)
+            .map_err(|e| format!("Tokenization failed: {}", e))?;
+        let longest = choice_tokens.iter().map(Vec::len).max().unwrap_or(0);
+        if tokens.len() + longest > GENERATION_N_CTX as usize {
+            return Err(format!(
+                "Prompt is {} tokens; at most {} fit with the longest choice",
+                tokens.len(),
+                GENERATION_N_CTX as usize - longest
+            ).into());
+        }
+
+        let ctx_params = LlamaContextParams::default().with_n_ctx(NonZeroU32::new(GENERATION_N_CTX));
+        let mut ctx = self
+            .new_generation_context(model, backend, ctx_params, None)
+            .map_err(|e| format!("Failed to create context: {}", e))?;
+        let n_past = Self::prefill(&mut ctx, &tokens, 0, true)?;
+        let n_vocab = model.n_vocab();
+        let prompt_logits = ctx
+            .logits_ith(-1, n_vocab)
+            .ok_or("Prefill produced no logits")?
+            .to_vec();
+
+        // Token i of a choice is scored from the logits after token i-1, so the
+        // first comes from the prompt and the rest from decoding all but the last.
+        let mut batch = LlamaBatch::new(MAX_CHOICE_TOKENS, 1);
+        let mut token_logprobs = Vec::with_capacity(choices.len());
+        for toks in &choice_tokens {
+            let mut logprobs = vec![token_logprob(&prompt_logits, toks[0])];
+            if toks.len() > 1 {
+                batch.clear();
+                for (i, tok) in toks[..toks.len() - 1].iter().enumerate() {
+                    batch
+                        .add(*tok, n_past + i as i32, &[0], true)
+                        .map_err(|e| format!("Batch add failed: {}", e))?;
+                }
+                ctx.decode(&mut batch)
+                    .map_err(|e| format!("Decode failed: {}", e))?;
+                for (i, tok) in toks[1..].iter().enumerate() {
+                    let logits = ctx.logits_ith(i as i32, n_vocab).ok_or("Decode produced no logits")?;
+                    logprobs.push(token_logprob(logits, *tok));
+                }
+                ctx.kv_cache_seq_rm(0, n_past, -1);
+            }
+            token_logprobs.push(logprobs);
+        }
+
+        let ranked = rank_choices(choices, &token_logprobs);
+        Self::log(&format!(
+            "classify_choice: {} choices, top={:?}",
+            ranked.len(),
+            ranked.first().map(|c| &c.choice)
+        ));
+        Ok(ChoiceClassification {
+            label: ranked[0].choice.clone(),
+            ranked,
+            prompt_tokens: tokens.len(),
+            duration_ms: start.elapsed().as_millis() as u64,
+        })
+    }
+
+    /// Decode a few throwaway tokens on the reasoning model so GPU kernels are
+    /// compiled and caches warm before the first real generation, which would
+    /// otherwise pay for both. Blocking. Recorded under the `warmup` session.
+    pub fn warmup(&self) -> Result<ModelWarmup, RuntimeError> {
+        if !self.has_reasoning_model() {
+            return Err(RuntimeError::NoReasoningModel);
+        }
+        let start = Instant::now();
+        let response = self.generate(GenerateRequest {
+            prompt: "Hello".to_string(),
+            max_tokens: Some(WARMUP_TOKENS),
+            temperature: Some(0.0),
+            // No session: warmup must not keep a context resident or evict a chat's.
+            session_id: None,
+            ..Default::default()
+        })?;
+        Ok(ModelWarmup {
+            duration_ms: start.elapsed().as_millis() as u64,
+            tokens_generated: response.tokens_generated,
+            gpu_layers: self.reasoning_gpu_layers,
+        })
+    }
+
+    /// Run `warmup()` automatically after each reasoning model load.
+    pub fn set_warmup_after_load(&mut self, enabled: bool) {
+        self.warmup_after_load = enabled;
+    }
+
+    /// Load reasoning models even when the memory check estimates they won't
+    /// fit, from now on. A single load overrides it with
+    /// `load_reasoning_model_with_gpu_layers`' `skip_memory_check`.
+    pub fn set_skip_memory_check(&mut self, skip: bool) {
+        self.skip_memory_check = skip;
+    }
+
+    /// Refuse a reasoning model load that `check_load_memory` estimates won't
+    /// fit in free memory, unless `skip_memory_check` is set. A model whose
+    /// hyperparameters couldn't be read (`shape` None) is let through.
+    fn check_reasoning_memory(
+        &self,
+        shape: Option<&ModelShape>,
+        model_bytes: u64,
+        n_gpu_layers: i32,
+        n_ctx: Option<u32>,
+    ) -> Result<(), RuntimeError> {
+        let Some(shape) = shape.filter(|_| !self.skip_memory_check) else {
+            return Ok(());
+        };
+        let n_ctx = clamp_n_ctx(n_ctx, GENERATION_N_CTX, shape.n_ctx_train).0;
+        let offload = self.backend.as_ref().is_some_and(|b| b.supports_gpu_offload());
+        // Apple Silicon memory is unified: offloaded layers still come out of RAM.
+        let vram_mb = if offload && !cfg!(target_os = "macos") {
+            crate::hardware::offload_vram_mb().unwrap_or(0)
+        } else {
+            0
+        };
+        let available_bytes = crate::hardware::available_memory_bytes();
+        check_load_memory(shape, model_bytes, n_ctx, n_gpu_layers, available_bytes, vram_mb).map_err(|e| {
+            eprintln!("[NativeRuntime] {}", e);
+            e.into()
+        })
+    }
+
+    /// The automatic warmup of the current reasoning model, if it ran and
+    /// succeeded.
+    pub fn last_warmup(&self) -> Option<&ModelWarmup> {
+        self.last_warmup.as_ref()
+    }
+
+    /// Quick "is inference working" check. Runs a tiny deterministic (temp 0)
+    /// completion on the reasoning model and embeds a short string on the embedding
+    /// model, validating the output shape. Components without a model are skipped.
+    pub fn self_test(&self) -> SelfTestReport {
+        let mut components = Vec::with_capacity(2);
+
+        let start = std::time::Instant::now();
+        let (status, detail) = if !self.has_reasoning_model() {
+            (SelfTestStatus::Skipped, NO_REASONING_MODEL.to_string())
+        } else {
+            let result = self
+                .generate(GenerateRequest {
+                    prompt: "Reply with the single word OK.".to_string(),
+                    max_tokens: Some(8),
+                    temperature: Some(0.0),
+                    session_id: Some("self_test".to_string()),
+                    ..Default::default()
+                })
+                .and_then(|r| check_generation_output(&r).map_err(RuntimeError::from));
+            match result {
+                Ok(detail) => (SelfTestStatus::Pass, detail),
+                Err(e) => (SelfTestStatus::Fail, e.to_string()),
+            }
+        };
+        components.push(ComponentSelfTest {
+            component: "reasoning".to_string(),
+            status,
+            detail,
+            duration_ms: start.elapsed().as_millis() as u64,
+        });
+
+        let start = std::time::Instant::now();
+        let (status, detail) = if !self.has_embedding_model() {
+            (SelfTestStatus::Skipped, "No embedding model loaded".to_string())
+        } else {
+            let result = self
+                .embed(EmbedRequest {
+                    model_path: String::new(),
+                    input: vec!["Semblance runtime self-test".to_string()],
+                    n_ctx: None,
+                    pooling: None,
+                })
+                .and_then(|r| check_embedding_output(&r));
+            match result {
+                Ok(detail) => (SelfTestStatus::Pass, detail),
+                Err(e) => (SelfTestStatus::Fail, e),
+            }
+        };
+        components.push(ComponentSelfTest {
+            component: "embedding".to_string(),
+            status,
+            detail,
+            duration_ms: start.elapsed().as_millis() as u64,
+        });
+
+        let passed = components.iter().all(|c| c.status != SelfTestStatus::Fail);
+        Self::log(&format!("self_test: passed={} {:?}", passed, components));
+        SelfTestReport { passed, components }
+    }
+
+    /// Render the reasoning model's chat template with `messages` (or sample
+    /// system/user/assistant turns). Template failures are reported in the
+    /// result; only a missing model is an `Err`.
+    pub fn test_chat_template(&self, messages: Option<Vec<ChatMessage>>) -> Result<ChatTemplateCheck, RuntimeError> {
+        let model = self
+            .reasoning_model
+            .as_ref()
+            .ok_or(RuntimeError::NoReasoningModel)?;
+        let messages = messages.unwrap_or_else(sample_chat_turns);
+        if messages.is_empty() {
+            return Err("messages must not be empty".into());
+        }
+        Ok(render_chat_template(model, &messages))
+    }
+
+    /// Report how many of the reasoning model's layers are on GPU vs CPU, derived
+    /// from the `n_gpu_layers` used at load and the backend's offload support.
+    pub fn layer_placement(&self) -> Result<LayerPlacement, RuntimeError> {
+        let model = self
+            .reasoning_model
+            .as_ref()
+            .ok_or(RuntimeError::NoReasoningModel)?;
+        let offload_supported = self
+            .backend
+            .as_ref()
+            .map(|b| b.supports_gpu_offload())
+            .unwrap_or(false);
+        Ok(compute_layer_placement(
+            model.n_layer().max(0) as u32,
+            self.reasoning_gpu_layers,
+            offload_supported,
+        ))
+    }
+
+    /// Name, size, and hyperparameters of the loaded reasoning model, or `None`
+    /// if none is loaded.
+    pub fn model_info(&self) -> Option<ModelInfo> {
+        let model = self.reasoning_model.as_ref()?;
+        let shape = read_model_shape(model);
+        Some(ModelInfo {
+            name: model.meta_val_str("general.name").filter(|n| !n.trim().is_empty()),
+            architecture: model.meta_val_str("general.architecture").unwrap_or_default(),
+            quantization: model
+                .meta_val_str("general.file_type")
+                .and_then(|v| v.trim().parse::<u32>().ok())
+                .and_then(file_type_name)
+                .map(String::from),
+            n_params: model.n_params(),
+            n_layer: shape.n_layer,
+            n_embd: shape.n_embd,
+            n_ctx_train: shape.n_ctx_train,
+            n_vocab: model.n_vocab(),
+        })
+    }
+
+    /// Hyperparameters of the GGUF at `path`. Uses the loaded reasoning model if
+    /// it is that file; otherwise loads the file vocab-only (no weights).
+    /// Returns the shape and whether the model is already resident.
+    pub fn model_shape(&self, path: &Path) -> Result<(ModelShape, bool), String> {
+        if let (Some(model), Some(loaded)) = (self.reasoning_model.as_ref(), self.reasoning_model_path.as_ref()) {
+            if loaded == path {
+                return Ok((read_model_shape(model), true));
+            }
+        }
+        if !path.exists() {
+            return Err(format!("Model file not found: {:?}", path));
+        }
+        let backend = self
+            .backend
+            .as_ref()
+            .ok_or("BitNet.cpp backend not initialized")?;
+        let params = LlamaModelParams::default().with_n_gpu_layers(0).with_vocab_only(true);
+        let model = LlamaModel::load_from_file(backend, path, &params)?;
+        Ok((read_model_shape(&model), false))
+    }
+
+    /// Check that `path` is a readable GGUF and summarize it from the header
+    /// alone, in milliseconds rather than a load's seconds. A file that isn't
+    /// a GGUF probes with `is_gguf` false; a GGUF whose header is truncated or
+    /// corrupt is an error.
+    pub fn probe_gguf(path: &Path) -> Result<GgufProbe, String> {
+        let bytes = std::fs::metadata(path)
+            .map_err(|e| format!("Failed to read model file {:?}: {}", path, e))?
+            .len();
+        let approx_size_mb = bytes / (1024 * 1024);
+        if !crate::gguf::has_magic(path)? {
+            return Ok(GgufProbe { is_gguf: false, architecture: None, n_ctx_train: None, quantization: None, approx_size_mb });
+        }
+        let inspection = crate::gguf::inspect(path)?;
+        let quantization = inspection
+            .file_type
+            .and_then(file_type_name)
+            .map(String::from)
+            .or_else(|| {
+                let types = &inspection.tensor_types;
+                types.iter().find(|t| t.tensor_type != "F32").or(types.first()).map(|t| t.tensor_type.clone())
+            });
+        Ok(GgufProbe {
+            is_gguf: true,
+            architecture: inspection.architecture,
+            n_ctx_train: inspection.context_length,
+            quantization,
+            approx_size_mb,
+        })
+    }
+
+    /// Recommend the largest `n_ctx` the model at `path` can afford given
+    /// `available_bytes` of free memory. See `plan_context_length`.
+    pub fn recommend_context_length(&self, path: &Path, available_bytes: u64) -> Result<ContextRecommendation, String> {
+        let (shape, resident) = self.model_shape(path)?;
+        let model_bytes = if resident {
+            0
+        } else {
+            std::fs::metadata(path)
+                .map(|m| m.len())
+                .map_err(|e| format!("Failed to read model file {:?}: {}", path, e))?
+        };
+        plan_context_length(&shape, available_bytes, model_bytes)
+    }
+
+    /// Parallel sequences the loaded reasoning and embedding models can run per
+    /// context with `available_bytes` free, and the memory each costs.
+    pub fn parallel_limits(&self, available_bytes: u64) -> ParallelLimits {
+        ParallelLimits {
+            generation: self
+                .reasoning_model
+                .as_ref()
+                .and_then(|m| plan_generation_parallelism(&read_model_shape(m), available_bytes)),
+            embedding: self.embedding_model.as_ref().map(|m| {
+                plan_embed_parallelism(available_bytes / (1024 * 1024), m.n_layer().max(1) as u64, m.n_embd().max(0) as u64)
+            }),
+            available_bytes,
+        }
+    }
+
+    /// Unload the reasoning model to free memory.
+    /// Warm reasoning models are unloaded too.
+    pub fn unload_reasoning_model(&mut self) {
+        self.clear_sessions();
+        self.warm_reasoning.clear();
+        self.reasoning_model = None;
+        self.reasoning_model_path = None;
+        self.idle_unloaded_path = None;
+        self.status = self.resident_status();
+    }
+
+    /// Time since the reasoning model was loaded or last generated.
+    pub fn reasoning_idle_for(&self) -> Duration {
+        self.last_reasoning_use
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .elapsed()
+    }
+
+    /// Unload the reasoning model if it has been idle for at least `timeout`,
+    /// remembering its path so the next generation reloads it. The runtime stays
+    /// `Ready` since generation still works. Returns the unloaded path.
+    pub fn unload_reasoning_if_idle(&mut self, timeout: Duration) -> Option<PathBuf> {
+        if self.reasoning_model.is_none() || self.reasoning_idle_for() < timeout {
+            return None;
+        }
+        let path = self.reasoning_model_path.clone()?;
+        self.unload_reasoning_model();
+        self.idle_unloaded_path = Some(path.clone());
+        self.status = RuntimeStatus::Ready;
+        Some(path)
+    }
+
+    /// Reasoning model unloaded for idleness and awaiting reload, if any.
+    pub fn idle_unloaded_model(&self) -> Option<&PathBuf> {
+        self.idle_unloaded_path.as_ref()
+    }
+
+    /// Reload the reasoning model unloaded by `unload_reasoning_if_idle`.
+    /// Returns true if a model was reloaded.
+    pub fn reload_idle_unloaded(&mut self) -> Result<bool, RuntimeError> {
+        let Some(path) = self.idle_unloaded_path.take() else {
+            return Ok(false);
+        };
+        if self.reasoning_model.is_some() {
+            return Ok(false);
+        }
+        eprintln!("[NativeRuntime] Reloading reasoning model after idle unload: {:?}", path);
+        // With the layer offload it had before the unload.
+        let gpu_layers = Some(GpuLayers::Count(self.reasoning_gpu_layers));
+        if let Err(e) = self.load_reasoning_model_with_gpu_layers(path.clone(), gpu_layers, None, false) {
+            self.idle_unloaded_path = Some(path);
+            return Err(e);
+        }
+        Ok(true)
+    }
+
+    fn sessions(&self) -> std::sync::MutexGuard<'_, HashMap<String, NativeSession>> {
+        self.sessions.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Drop every context created from the reasoning model: resident sessions
+    /// and the system-prompt cache.
+    fn clear_sessions(&self) {
+        self.sessions().clear();
+        *self.prompt_cache.lock().unwrap_or_else(|e| e.into_inner()) = None;
+    }
+
+    /// Store a resident session, evicting the least recently used one when
+    /// `MAX_RESIDENT_SESSIONS` are already live.
+    fn put_session(&self, session_id: &str, session: NativeSession) {
+        let mut sessions = self.sessions();
+        if !sessions.contains_key(session_id) && sessions.len() >= MAX_RESIDENT_SESSIONS {
+            if let Some(oldest) = sessions.iter().min_by_key(|(_, s)| s.last_used).map(|(id, _)| id.clone()) {
+                Self::log(&format!("generate: evicting resident session {}", oldest));
+                sessions.remove(&oldest);
+            }
+        }
+        sessions.insert(session_id.to_string(), session);
+    }
+
+    /// Drop the oldest tokens of a session's KV cache so at most `keep_last_tokens`
+    /// remain after the system prefix. The prefix is never removed; the kept tail is
+    /// shifted down to stay contiguous with it. Errors for unknown sessions.
+    pub fn trim_session(&self, session_id: &str, keep_last_tokens: usize) -> Result<SessionOccupancy, String> {
+        let mut sessions = self.sessions();
+        let session = sessions
+            .get_mut(session_id)
+            .ok_or_else(|| format!("Unknown session: {}", session_id))?;
+
+        let (start, end) = plan_session_trim(session.tokens.len(), session.prefix_len, keep_last_tokens);
+        if end > start {
+            if !session.ctx.kv_cache_seq_rm(0, start as i32, end as i32) {
+                return Err(format!("Failed to trim KV cache of session {}", session_id));
+            }
+            session.ctx.kv_cache_seq_add(0, end as i32, -1, -((end - start) as i32));
+            session.tokens.drain(start..end);
+        }
+
+        Ok(SessionOccupancy {
+            session_id: session_id.to_string(),
+            tokens_in_cache: session.tokens.len() as u32,
+            prefix_tokens: session.prefix_len.min(session.tokens.len()) as u32,
+            n_ctx: session.ctx.n_ctx(),
+            tokens_removed: (end - start) as u32,
+        })
+    }
+
+    /// Unload the embedding model to free memory.
+    pub fn unload_embedding_model(&mut self) {
+        self.embedding_model = None;
+        self.embedding_model_path = None;
+        if matches!(self.status, RuntimeStatus::EmbeddingOnly) {
+            self.status = RuntimeStatus::Uninitialized;
+        }
+    }
+
+    /// Why the BitNet.cpp backend failed to initialize, if it did.
+    pub fn backend_error(&self) -> Option<&str> {
+        self.backend_error.as_deref()
+    }
+
+    /// Report reasoning and embedding model load progress to `callback`, at
+    /// most once per percent. `None` (the default) loads silently.
+    pub fn set_load_progress_callback(&mut self, callback: Option<LoadProgressCallback>) {
+        self.load_progress = callback;
+    }
+
+    /// A handle that cancels the reasoning or embedding model load in progress.
+    pub fn load_cancel(&self) -> LoadCancel {
+        self.load_cancel.clone()
+    }
+
+    /// Model params for loading `path`, reporting progress as `kind` if a
+    /// progress callback is set and aborting once `load_cancel` is cancelled.
+    /// The load counts as in progress until the params are dropped.
+    fn load_params(&self, kind: &'static str, path: &Path, n_gpu_layers: i32) -> LlamaModelParams {
+        let run = self.load_cancel.begin();
+        let callback = self.load_progress.clone();
+        let path = path.to_path_buf();
+        let reported = AtomicU32::new(0);
+        LlamaModelParams::default().with_n_gpu_layers(n_gpu_layers).with_progress_callback(move |progress| {
+            if let Some(callback) = callback.as_ref() {
+                if progress_step(&reported, progress) {
+                    callback(kind, &path, progress);
+                }
+            }
+            !run.cancel.requested()
+        })
+    }
+
+    /// Set the flash attention default for generation contexts (`None` = llama.cpp's default).
+    pub fn set_flash_attention(&mut self, enabled: Option<bool>) {
+        self.flash_attention = enabled;
+    }
+
+    /// Context size for a request: `requested` or `default`, clamped to the
+    /// model's training context (logged when the caller asked for more).
+    fn request_n_ctx(&self, model: &LlamaModel, requested: Option<u32>, default: u32) -> u32 {
+        let n_ctx_train = read_model_shape(model).n_ctx_train;
+        let (n_ctx, clamped) = clamp_n_ctx(requested, default, n_ctx_train);
+        if clamped {
+            Self::log(&format!(
+                "WARNING: requested n_ctx={} exceeds the model's training context ({}), using {}",
+                requested.unwrap_or(default),
+                n_ctx_train,
+                n_ctx
+            ));
+        }
+        n_ctx
+    }
+
+    /// Cap the prompt size `generate()` accepts (`None` = the model's training context).
+    pub fn set_max_prompt_tokens(&mut self, max_tokens: Option<u32>) {
+        self.max_prompt_tokens = max_tokens;
+    }
+
+    /// Keep up to `max_resident` reasoning models loaded (clamped to
+    /// 1–`MAX_RESIDENT_REASONING`), with their weights within `budget_mb` if
+    /// set. Loading another model then keeps the outgoing one warm, and
+    /// `generate` routes requests by `model_path`. 1 (the default) keeps only
+    /// the active model, as before. Evicts immediately if over the new limits.
+    pub fn set_resident_models(&mut self, max_resident: u32, budget_mb: Option<u64>) -> ResidentModels {
+        self.max_resident_reasoning = max_resident.clamp(1, MAX_RESIDENT_REASONING) as usize;
+        self.reasoning_budget_bytes = budget_mb.map(|mb| mb * 1024 * 1024);
+        self.evict_warm_reasoning();
+        self.resident_models()
+    }
+
+    pub fn resident_models(&self) -> ResidentModels {
+        let mut warm: Vec<(&PathBuf, &WarmModel, Instant)> = self
+            .warm_reasoning
+            .iter()
+            .map(|(path, w)| (path, w, *w.last_used.lock().unwrap_or_else(|e| e.into_inner())))
+            .collect();
+        warm.sort_by_key(|(_, _, last_used)| std::cmp::Reverse(*last_used));
+        let active = self.reasoning_model_path.iter().map(|path| ResidentModel {
+            path: path.to_string_lossy().to_string(),
+            size_mb: self.reasoning_model_bytes / (1024 * 1024),
+            active: true,
+        });
+        ResidentModels {
+            max_resident: self.max_resident_reasoning as u32,
+            budget_mb: self.reasoning_budget_bytes.map(|b| b / (1024 * 1024)),
+            models: active
+                .chain(warm.into_iter().map(|(path, w, _)| ResidentModel {
+                    path: path.to_string_lossy().to_string(),
+                    size_mb: w.bytes / (1024 * 1024),
+                    active: false,
+                }))
+                .collect(),
+        }
+    }
+
+    /// Whether `generate` can serve `model_path` without loading: it is empty,
+    /// names the active or a warm model, or routing is off (`max_resident` 1)
+    /// so the active model serves it anyway.
+    pub fn can_route_reasoning(&self, model_path: &str) -> bool {
+        let path = Path::new(model_path);
+        model_path.is_empty()
+            || self.max_resident_reasoning <= 1
+            || self.reasoning_model_path.as_deref() == Some(path)
+            || self.warm_reasoning.contains_key(path)
+    }
+
+    /// Load `path` as a warm reasoning model, leaving the active one in place
+    /// (or as the active model if there is none), evicting by LRU to stay
+    /// within the resident limits.
+    pub fn load_warm_reasoning_model(&mut self, path: PathBuf) -> Result<(), RuntimeError> {
+        if self.reasoning_model.is_none() {
+            return self.load_reasoning_model(path);
+        }
+        if !path.exists() {
+            return Err(format!("Model file not found: {:?}", path).into());
+        }
+        crate::gguf::check_supported(&path)?;
+        let bytes = std::fs::metadata(&path).map_or(0, |m| m.len());
+        let shape = self.model_shape(&path).ok().map(|(shape, _)| shape);
+        self.check_reasoning_memory(shape.as_ref(), bytes, 0, None)?;
+        let backend = self
+            .backend
+            .as_ref()
+            .ok_or("BitNet.cpp backend not initialized")?;
+        let model = LlamaModel::load_from_file(backend, &path, &self.load_params("reasoning", &path, 0)).map_err(|e| {
+            if self.load_cancel.requested() {
+                RuntimeError::LoadCancelled
+            } else {
+                format!("Failed to load reasoning model: {}", e).into()
+            }
+        })?;
+        eprintln!("[NativeRuntime] Warm reasoning model loaded: {:?} ({} params)", path, model.n_params());
+        let warm = WarmModel { model, bytes, gpu_layers: 0, offloaded_layers: Some(0), last_used: Mutex::new(Instant::now()) };
+        self.warm_reasoning.insert(path, warm);
+        self.evict_warm_reasoning();
+        Ok(())
+    }
+
+    fn evict_warm_reasoning(&mut self) {
+        let warm: Vec<(PathBuf, u64, Instant)> = self
+            .warm_reasoning
+            .iter()
+            .map(|(path, w)| (path.clone(), w.bytes, *w.last_used.lock().unwrap_or_else(|e| e.into_inner())))
+            .collect();
+        let evict = plan_warm_evictions(&warm, self.reasoning_model_bytes, self.max_resident_reasoning, self.reasoning_budget_bytes);
+        for path in evict {
+            eprintln!("[NativeRuntime] Evicting warm reasoning model {:?}", path);
+            self.warm_reasoning.remove(&path);
+        }
+    }
+
+    pub fn prompt_token_limit(&self) -> PromptTokenLimit {
+        let n_ctx_train = self
+            .reasoning_model
+            .as_ref()
+            .map(|m| read_model_shape(m).n_ctx_train)
+            .filter(|n| *n > 0);
+        PromptTokenLimit {
+            setting: self.max_prompt_tokens,
+            n_ctx_train,
+            effective: self.max_prompt_tokens.or(n_ctx_train),
+        }
+    }
+
+    /// Read what llama.cpp logged since the last call, noting the GPU device
+    /// it reports. Returns the layer offload the lines report, if any.
+    fn absorb_backend_logs(&self) -> Option<i32> {
+        let mut offloaded = None;
+        let mut device = self.gpu_device.lock().unwrap_or_else(|e| e.into_inner());
+        for line in bitnet_sys::take_captured_logs() {
+            if let Some(found) = parse_gpu_device(&line) {
+                *device = Some(found);
+            }
+            offloaded = parse_offloaded_layers(&line).or(offloaded);
+        }
+        offloaded
+    }
+
+    pub fn backend_info(&self) -> BackendInfo {
+        let gpu_offload_supported = self.backend.as_ref().is_some_and(|b| b.supports_gpu_offload());
+        self.absorb_backend_logs();
+        let device = self.gpu_device.lock().unwrap_or_else(|e| e.into_inner()).clone();
+        // Without a reported count, fall back to the placement the requested
+        // count implies.
+        let gpu_layers_offloaded = match &self.reasoning_model {
+            None => 0,
+            Some(model) => self.reasoning_offloaded_layers.unwrap_or_else(|| {
+                compute_layer_placement(model.n_layer().max(0) as u32, self.reasoning_gpu_layers, gpu_offload_supported)
+                    .gpu_layers as i32
+            }),
+        };
+        // This build compiles Metal as its only GPU backend, so layers on an
+        // unnamed device are on Metal.
+        let backend = match (&device, gpu_layers_offloaded > 0) {
+            (_, false) => "cpu",
+            (Some(device), true) => device.backend,
+            (None, true) => "metal",
+        };
+        BackendInfo {
+            engine: "bitnet.cpp".to_string(),
+            backend_error: self.backend_error.clone(),
+            gpu_offload_supported,
+            backend: backend.to_string(),
+            gpu_layers_offloaded,
+            gpu_device_name: device.map(|d| d.name),
+            flash_attention: FlashAttentionInfo {
+                setting: self.flash_attention,
+                supported: self.backend.is_some(),
+                backend: if gpu_offload_supported { "metal" } else { "cpu" }.to_string(),
+                last_context: self.last_flash_attention.lock().unwrap_or_else(|e| e.into_inner()).clone(),
+            },
+        }
+    }
+
+    /// Create a generation context, with flash attention if `requested` (or the
+    /// runtime default) asks for it and `model` can use it. If the context can't
+    /// be created with flash attention, retry without it rather than failing.
+    /// Either way, what was used is recorded for `backend_info`.
+    fn new_generation_context(
+        &self,
+        model: &LlamaModel,
+        backend: &LlamaBackend,
+        params: LlamaContextParams,
+        requested: Option<bool>,
+    ) -> Result<LlamaContext, String> {
+        let requested = requested.or(self.flash_attention).unwrap_or(false);
+        let mut fallback_reason = None;
+        let mut ctx = None;
+        if requested {
+            let arch = model.meta_val_str("general.architecture");
+            let head_len = |kind: &str| {
+                arch.as_deref()
+                    .and_then(|a| model.meta_val_str(&format!("{}.attention.{}_length", a, kind)))
+            };
+            fallback_reason = flash_attention_blocker(arch.as_deref(), head_len("key").as_deref(), head_len("value").as_deref());
+            if fallback_reason.is_none() {
+                match model.new_context(backend, params.clone().with_flash_attn(true)) {
+                    Ok(c) => ctx = Some(c),
+                    Err(e) => fallback_reason = Some(format!("Context creation with flash attention failed: {}", e)),
+                }
+            }
+            if let Some(reason) = &fallback_reason {
+                Self::log(&format!("generate: flash attention unavailable, using regular attention: {}", reason));
+            }
+        }
+        let enabled = ctx.is_some();
+        let ctx = match ctx {
+            Some(c) => c,
+            None => model.new_context(backend, params.with_flash_attn(false))?,
+        };
+        *self.last_flash_attention.lock().unwrap_or_else(|e| e.into_inner()) = Some(FlashAttentionUse {
+            requested,
+            enabled,
+            fallback_reason,
+        });
+        Ok(ctx)
+    }
+
+    /// Get the current runtime status.
+    pub fn status(&self) -> &RuntimeStatus {
+        &self.status
+    }
+
+    /// The status the resident models call for, outside of a load.
+    fn resident_status(&self) -> RuntimeStatus {
+        if self.reasoning_model.is_some() || self.idle_unloaded_path.is_some() {
+            RuntimeStatus::Ready
+        } else if self.embedding_model.is_some() {
+            RuntimeStatus::EmbeddingOnly
+        } else {
+            RuntimeStatus::Uninitialized
+        }
+    }
+
+    /// Whether `generate` will run: the runtime is `Ready`.
+    pub fn can_generate(&self) -> bool {
+        matches!(self.status, RuntimeStatus::Ready)
+    }
+
+    /// Whether `embed` will run: an embedding model is loaded, whatever the
+    /// reasoning model is doing.
+    pub fn can_embed(&self) -> bool {
+        self.embedding_model.is_some()
+    }
+
+    /// The error for a generation `can_generate` refuses.
+    fn generation_refused(&self) -> RuntimeError {
+        match self.status {
+            RuntimeStatus::EmbeddingOnly => RuntimeError::NoReasoningModel,
+            _ => "Runtime not ready — no model loaded".into(),
+        }
+    }
+
+    /// Check if a reasoning model is loaded.
+    pub fn has_reasoning_model(&self) -> bool {
+        self.reasoning_model.is_some()
+    }
+
+    /// Check if an embedding model is loaded.
+    pub fn has_embedding_model(&self) -> bool {
+        self.embedding_model.is_some()
+    }
+
+    /// Get reasoning model path if loaded.
+    pub fn reasoning_model_path(&self) -> Option<&PathBuf> {
+        self.reasoning_model_path.as_ref()
+    }
+
+    /// Get embedding model path if loaded.
+    pub fn embedding_model_path(&self) -> Option<&PathBuf> {
+        self.embedding_model_path.as_ref()
+    }
+
+    /// Whether a model is resident in the `kind` slot. An idle-unloaded
+    /// reasoning model doesn't count: it is reloaded with the current settings.
+    pub fn is_model_loaded(&self, kind: ModelKind) -> bool {
+        match kind {
+            ModelKind::Reasoning => self.reasoning_model.is_some(),
+            ModelKind::Embedding => self.embedding_model.is_some(),
+            ModelKind::Fast => self.fast_model.is_some(),
+            ModelKind::Vision => self.vision_model.is_some(),
+            ModelKind::Draft => self.draft_model.is_some(),
+        }
+    }
+
+    /// Precondition for operations that can't safely run while a model is
+    /// loaded (e.g. changing how models are placed in memory). Errors naming
+    /// every loaded slot in `kinds` so the caller can tell the user what to unload.
+    pub fn require_no_model_loaded(&self, kinds: &[ModelKind], operation: &str) -> Result<(), RuntimeError> {
+        let loaded: Vec<ModelKind> = kinds.iter().copied().filter(|k| self.is_model_loaded(*k)).collect();
+        if loaded.is_empty() {
+            return Ok(());
+        }
+        Err(RuntimeError::ModelLoaded { operation: operation.to_string(), kinds: loaded })
+    }
+}
+
+/// Thread-safe wrapper for NativeRuntime.
+pub type SharedNativeRuntime = Arc<RwLock<NativeRuntime>>;
+
+/// Create a new shared NativeRuntime instance.
+pub fn create_runtime() -> SharedNativeRuntime {
+    Arc::new(RwLock::new(NativeRuntime::new()))
+}
+
+/// Reload an idle-unloaded reasoning model before a generation. Awaited by
+/// callers so the first request after an idle unload is slower, not an error.
+pub async fn ensure_reasoning_resident(runtime: &SharedNativeRuntime) -> Result<(), RuntimeError> {
+    if runtime.read().await.idle_unloaded_model().is_none() {
+        return Ok(());
+    }
+    runtime.write().await.reload_idle_unloaded().map(|_| ())
+}
+
+/// Run `f` against the runtime on tokio's blocking pool, holding the read lock
+/// for the duration, so a long FFI call doesn't stall the async worker that
+/// other Tauri commands run on. Writers (model loads) wait until it finishes.
+pub async fn run_blocking<T: Send + 'static>(
+    runtime: &SharedNativeRuntime,
+    f: impl FnOnce(&NativeRuntime) -> T + Send + 'static,
+) -> Result<T, String> {
+    let rt = runtime.clone().read_owned().await;
+    tokio::task::spawn_blocking(move || f(&rt))
+        .await
+        .map_err(|e| format!("Native runtime task failed: {}", e))
+}
+
+/// `generate` via `run_blocking`. The spawned task can't be aborted by
+/// dropping the returned future: it runs to completion and keeps the read
+/// lock. To stop it early, set `cancel`; it is checked between tokens and the
+/// generation then fails with "Generation cancelled".
+pub async fn generate_async(
+    runtime: &SharedNativeRuntime,
+    request: GenerateRequest,
+    cancel: Option<Arc<AtomicBool>>,
+) -> Result<GenerateResponse, RuntimeError> {
+    run_blocking(runtime, move |rt| {
+        let hooks = GenerationHooks { cancel: cancel.as_deref(), ..GenerationHooks::NONE };
+        std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| rt.generate_with_hooks(request, &hooks)))
+            .unwrap_or_else(|_| Err("Native runtime panicked during generation".into()))
+    })
+    .await?
+}
+
+/// Load the reasoning model a request names by `model_path` if routing is on
+/// and it isn't resident yet, so `generate` (which only takes the read lock)
+/// can use it.
+pub async fn ensure_reasoning_routable(runtime: &SharedNativeRuntime, model_path: &str) -> Result<(), RuntimeError> {
+    if runtime.read().await.can_route_reasoning(model_path) {
+        return Ok(());
+    }
+    let mut rt = runtime.write().await;
+    if rt.can_route_reasoning(model_path) {
+        return Ok(());
+    }
+    rt.load_warm_reasoning_model(PathBuf::from(model_path))
+}
+
+/// Cosine similarity between two vectors. Returns 0.0 for mismatched lengths
+/// or zero-magnitude inputs rather than NaN.
+pub fn cosine_similarity(a: &[f32], b: &[f32]) -> f32 {
+    if a.len() != b.len() || a.is_empty() {
+        return 0.0;
+    }
+    let (mut dot, mut norm_a, mut norm_b) = (0.0f32, 0.0f32, 0.0f32);
+    for (&x, &y) in a.iter().zip(b) {
+        dot = x.mul_add(y, dot);
+        norm_a = x.mul_add(x, norm_a);
+        norm_b = y.mul_add(y, norm_b);
+    }
+    let denom = norm_a.sqrt() * norm_b.sqrt();
+    if denom > 0.0 {
+        dot / denom
+    } else {
+        0.0
+    }
+}
+
+/// Most choices `classify_choice` accepts. Choices are scored one after another
+/// (one decode each, rolling the KV cache back to the prompt), so this bounds
+/// how long a classification can take.
+const MAX_CLASSIFY_CHOICES: usize = 64;
+/// Most tokens in one choice: each is decoded as a single batch of this size.
+const MAX_CHOICE_TOKENS: usize = 64;
+
+/// Natural-log probability of `token` under `logits` (log-softmax).
+fn token_logprob(logits: &[f32], token: LlamaToken) -> f32 {
+    let Some(&logit) = usize::try_from(token).ok().and_then(|i| logits.get(i)) else {
+        return f32::NEG_INFINITY;
+    };
+    let max = logits.iter().copied().fold(f32::NEG_INFINITY, f32::max);
+    let sum: f32 = logits.iter().map(|&l| (l - max).exp()).sum();
+    logit - max - sum.ln()
+}
+
+/// Score each choice from its per-token log-probabilities and sort best first
+/// (ties keep request order).
+fn rank_choices(choices: &[String], token_logprobs: &[Vec<f32>]) -> Vec<ChoiceScore> {
+    let totals: Vec<f32> = token_logprobs.iter().map(|lps| lps.iter().sum()).collect();
+    let max = totals.iter().copied().fold(f32::NEG_INFINITY, f32::max);
+    let norm: f32 = totals.iter().map(|&t| (t - max).exp()).sum();
+    let mut ranked: Vec<ChoiceScore> = choices
+        .iter()
+        .zip(token_logprobs)
+        .zip(&totals)
+        .enumerate()
+        .map(|(index, ((choice, lps), &logprob))| ChoiceScore {
+            choice: choice.clone(),
+            index,
+            logprob,
+            mean_logprob: logprob / lps.len().max(1) as f32,
+            probability: if norm > 0.0 { (logprob - max).exp() / norm } else { 0.0 },
+            tokens: lps.len(),
+        })
+        .collect();
+    ranked.sort_by(|a, b| b.logprob.total_cmp(&a.logprob));
+    ranked
+}
+
+/// Sessions whose latest generation is kept for repro capture.
+const MAX_RECORDED_SESSIONS: usize = 32;
+
+fn generation_record(
+    kind: &str,
+    model_path: Option<&PathBuf>,
+    draft_model_path: Option<&PathBuf>,
+    request: &GenerateRequest,
+    (resolved_prompt, prompt_tokens): (String, usize),
+    sampler: SamplerConfig,
+    response: &GenerateResponse,
+) -> GenerationRecord {
+    GenerationRecord {
+        session_id: request
+            .session_id
+            .clone()
+            .unwrap_or_else(|| DEFAULT_SESSION_ID.to_string()),
+        kind: kind.to_string(),
+        model_path: model_path.map(|p| p.display().to_string()),
+        draft_model_path: draft_model_path.map(|p| p.display().to_string()),
+        request: request.clone(),
+        resolved_prompt,
+        prompt_tokens: prompt_tokens as u32,
+        sampler,
+        output: response.text.clone(),
+        tokens_generated: response.tokens_generated,
+        duration_ms: response.duration_ms,
+        recorded_at_ms: now_ms(),
+    }
+}
+
+fn now_ms() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+/// Span [start, end) of cache positions to remove so that at most `keep_last`
+/// tokens follow the first `prefix_len` tokens. Empty when nothing needs trimming.
+fn plan_session_trim(n_tokens: usize, prefix_len: usize, keep_last: usize) -> (usize, usize) {
+    let start = prefix_len.min(n_tokens);
+    let end = n_tokens.saturating_sub(keep_last).max(start);
+    (start, end)
+}
+
+/// Most tokens the draft model proposes per speculative round.
+const SPECULATIVE_MAX_DRAFT: usize = 8;
+/// Largest vocabulary size difference tolerated between draft and reasoning model
+/// (models of one family often pad their vocabularies differently).
+const DRAFT_VOCAB_MAX_SIZE_DIFFERENCE: i32 = 128;
+/// Token texts are compared from this id up; the first few ids are special tokens
+/// whose text legitimately varies between models of one family.
+const DRAFT_VOCAB_CHECK_START_TOKEN_ID: i32 = 5;
+
+/// Tokenizer facts compared when pairing a draft model with the reasoning model.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct VocabSummary {
+    vocab_type: i32,
+    n_vocab: i32,
+    bos: LlamaToken,
+    eos: LlamaToken,
+}
+
+impl VocabSummary {
+    fn of(model: &LlamaModel) -> Self {
+        VocabSummary {
+            vocab_type: model.vocab_type(),
+            n_vocab: model.n_vocab(),
+            bos: model.token_bos(),
+            eos: model.token_eos(),
+        }
+    }
+}
+
+/// A draft can only propose tokens the reasoning model understands: same tokenizer
+/// type, near-identical vocabulary size, same BOS/EOS, and the same text for every
+/// shared token id.
+fn check_draft_compatibility(main: &LlamaModel, draft: &LlamaModel) -> Result<(), String> {
+    check_vocab_compatibility(&VocabSummary::of(main), &VocabSummary::of(draft), |t| {
+        main.token_text(t) == draft.token_text(t)
+    })
+}
+
+fn check_vocab_compatibility(
+    main: &VocabSummary,
+    draft: &VocabSummary,
+    mut same_text: impl FnMut(LlamaToken) -> bool,
+) -> Result<(), String> {
+    if main.vocab_type != draft.vocab_type {
+        return Err(format!(
+            "tokenizer types differ (reasoning {}, draft {})",
+            main.vocab_type, draft.vocab_type
+        ));
+    }
+    let size_difference = (main.n_vocab - draft.n_vocab).abs();
+    if size_difference > DRAFT_VOCAB_MAX_SIZE_DIFFERENCE {
+        return Err(format!(
+            "vocabulary sizes differ by {} (reasoning {}, draft {}; at most {} allowed)",
+            size_difference, main.n_vocab, draft.n_vocab, DRAFT_VOCAB_MAX_SIZE_DIFFERENCE
+        ));
+    }
+    if main.bos != draft.bos || main.eos != draft.eos {
+        return Err(format!(
+            "special tokens differ (reasoning bos={} eos={}, draft bos={} eos={})",
+            main.bos, main.eos, draft.bos, draft.eos
+        ));
+    }
+    for token in DRAFT_VOCAB_CHECK_START_TOKEN_ID..main.n_vocab.min(draft.n_vocab) {
+        if !same_text(token) {
+            return Err(format!("token {} has different text in the two vocabularies", token));
+        }
+    }
+    Ok(())
+}
+
+/// Check and compile a request's GBNF grammar for `model`'s vocabulary.
+fn compile_grammar(model: &LlamaModel, grammar: &str) -> Result<LlamaSampler, String> {
+    crate::grammar::validate_gbnf(grammar, crate::grammar::ROOT_RULE)
+        .and_then(|()| LlamaSampler::grammar(model, grammar, crate::grammar::ROOT_RULE))
+        .map_err(|e| format!("Invalid grammar: {}", e))
+}
+
+/// Enforce `max_prompt_tokens`: an over-limit prompt is cut to `limit` tokens
+/// by `truncate_middle` when `truncate` is set, otherwise rejected with the
+/// actual and allowed counts.
+fn check_prompt_tokens(tokens: &mut Vec<LlamaToken>, limit: usize, keep_head: usize, truncate: bool) -> Result<(), PromptTooLarge> {
+    if tokens.len() <= limit {
+        return Ok(());
+    }
+    if truncate {
+        truncate_middle(tokens, keep_head, limit);
+        return Ok(());
+    }
+    Err(PromptTooLarge { prompt_tokens: tokens.len(), limit })
+}
+
+/// `requested` (or `default`) context size, at least 1 and at most
+/// `n_ctx_train` when that's known (non-zero). Returns whether a requested size
+/// was clamped down; the default is capped silently.
+fn clamp_n_ctx(requested: Option<u32>, default: u32, n_ctx_train: u32) -> (u32, bool) {
+    let n_ctx = requested.unwrap_or(default).max(1);
+    if n_ctx_train > 0 && n_ctx > n_ctx_train {
+        return (n_ctx_train, requested.is_some());
+    }
+    (n_ctx, false)
+}
+
+/// A prompt that leaves less than `max_tokens` of the context for the response.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ContextOverflow {
+    pub prompt_tokens: usize,
+    pub max_tokens: usize,
+    pub n_ctx: usize,
+}
+
+impl std::fmt::Display for ContextOverflow {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Context overflow: {} prompt tokens + {} max_tokens exceed the context of {} (n_ctx). Lower max_tokens, raise n_ctx, shorten the prompt, or set truncate_prompt",
+            self.prompt_tokens, self.max_tokens, self.n_ctx
+        )
+    }
+}
+
+/// A prompt over `max_prompt_tokens`; see `check_prompt_tokens`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PromptTooLarge {
+    pub prompt_tokens: usize,
+    pub limit: usize,
+}
+
+impl std::fmt::Display for PromptTooLarge {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Prompt too large: {} tokens, limit is {} (max_prompt_tokens). Shorten the prompt or set truncate_prompt",
+            self.prompt_tokens, self.limit
+        )
+    }
+}
+
+/// A model load estimated to need more RAM than is free; see `check_load_memory`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InsufficientMemory {
+    pub required_mb: u64,
+    pub available_mb: u64,
+}
+
+impl std::fmt::Display for InsufficientMemory {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Insufficient memory: loading the model needs about {} MB but {} MB is available. Close other apps, pick a smaller model, or turn off the memory check to try anyway",
+            self.required_mb, self.available_mb
+        )
+    }
+}
+
+/// Error of a generation or reasoning model load. The cases a caller can act
+/// on are typed, so they reach the frontend as their `SemblanceError` kind;
+/// everything else is `Failed` with the message. `Display` is the text these
+/// operations returned as a `String`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RuntimeError {
+    /// The operation needs the reasoning model and none is loaded.
+    NoReasoningModel,
+    ContextOverflow(ContextOverflow),
+    PromptTooLarge(PromptTooLarge),
+    InsufficientMemory(InsufficientMemory),
+    /// Stopped by `LoadCancel::cancel`.
+    LoadCancelled,
+    /// `operation` can't run while these slots are loaded; see `require_no_model_loaded`.
+    ModelLoaded { operation: String, kinds: Vec<ModelKind> },
+    Failed(String),
+}
+
+impl std::fmt::Display for RuntimeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RuntimeError::NoReasoningModel => write!(f, "{}", NO_REASONING_MODEL),
+            RuntimeError::ContextOverflow(overflow) => write!(f, "{}", overflow),
+            RuntimeError::PromptTooLarge(too_large) => write!(f, "{}", too_large),
+            RuntimeError::InsufficientMemory(shortfall) => write!(f, "{}", shortfall),
+            RuntimeError::LoadCancelled => write!(f, "Model load cancelled"),
+            RuntimeError::ModelLoaded { operation, kinds } => write!(
+                f,
+                "Cannot {} while a model is loaded ({}) — unload the model first",
+                operation,
+                kinds.iter().map(|k| k.as_str()).collect::<Vec<_>>().join(", ")
+            ),
+            RuntimeError::Failed(message) => write!(f, "{}", message),
+        }
+    }
+}
+
+impl std::error::Error for RuntimeError {}
+
+impl From<ContextOverflow> for RuntimeError {
+    fn from(overflow: ContextOverflow) -> Self {
+        RuntimeError::ContextOverflow(overflow)
+    }
+}
+
+impl From<PromptTooLarge> for RuntimeError {
+    fn from(too_large: PromptTooLarge) -> Self {
+        RuntimeError::PromptTooLarge(too_large)
+    }
+}
+
+impl From<InsufficientMemory> for RuntimeError {
+    fn from(shortfall: InsufficientMemory) -> Self {
+        RuntimeError::InsufficientMemory(shortfall)
+    }
+}
+
+impl From<String> for RuntimeError {
+    fn from(message: String) -> Self {
+        RuntimeError::Failed(message)
+    }
+}
+
+impl From<&str> for RuntimeError {
+    fn from(message: &str) -> Self {
+        RuntimeError::Failed(message.to_string())
+    }
+}
+
+impl From<RuntimeError> for String {
+    fn from(error: RuntimeError) -> Self {
+        error.to_string()
+    }
+}
+
+/// Leave room for `max_tokens` of output in an `n_ctx` context. A prompt that
+/// doesn't is a `ContextOverflow`, unless `truncate` is set: then its middle is
+/// cut (see `truncate_middle`). A `max_tokens` filling the whole context
+/// overflows either way.
+fn fit_context(
+    tokens: &mut Vec<LlamaToken>,
+    max_tokens: usize,
+    n_ctx: usize,
+    keep_head: usize,
+    truncate: bool,
+) -> Result<(), ContextOverflow> {
+    let budget = n_ctx.saturating_sub(max_tokens);
+    if tokens.len() <= budget {
+        return Ok(());
+    }
+    if truncate && budget > 0 {
+        truncate_middle(tokens, keep_head, budget);
+        return Ok(());
+    }
+    Err(ContextOverflow { prompt_tokens: tokens.len(), max_tokens, n_ctx })
+}
+
+/// Cut `tokens` to `budget` by dropping from the middle: the first
+/// `keep_head` (the templated system prompt; at least the BOS token, at most
+/// half the budget) stay, and the most recent tokens fill the rest. The cut
+/// can land inside a turn, but the latest turn and the assistant header that
+/// ends the prompt survive.
+fn truncate_middle(tokens: &mut Vec<LlamaToken>, keep_head: usize, budget: usize) {
+    if tokens.len() <= budget {
+        return;
+    }
+    let head = keep_head.min(budget / 2).max(1).min(budget);
+    let tail_start = tokens.len() - (budget - head);
+    tokens.drain(head..tail_start);
+}
+
+/// Generated bytes with stop-sequence detection. Stop sequences are matched on
+/// raw bytes, so one split across token pieces (or across a codepoint) is still
+/// found, as is one that ends mid-piece. The tail that could still grow into a
+/// stop sequence is held back: only `bytes[..bytes.len() - held]` is final, so
+/// a streaming consumer never sees part of a stop marker.
+struct StopBuffer {
+    stops: Vec<Vec<u8>>,
+    /// Output so far, with any matched stop sequence (and what followed it) cut.
+    bytes: Vec<u8>,
+    /// Trailing bytes of `bytes` that are a prefix of some stop sequence.
+    held: usize,
+}
+
+impl StopBuffer {
+    /// Empty stop strings are ignored.
+    fn new<S: AsRef<str>>(stops: &[S]) -> Self {
+        StopBuffer {
+            stops: stops.iter().map(|s| s.as_ref().as_bytes().to_vec()).filter(|s| !s.is_empty()).collect(),
+            bytes: Vec::new(),
+            held: 0,
+        }
+    }
+
+    /// Append a token's bytes. Returns true if a stop sequence completed; the
+    /// output is cut where it starts.
+    fn push(&mut self, piece: &[u8]) -> bool {
+        // Released bytes can't start a match, so only the held tail and the new
+        // piece need searching.
+        let from = self.bytes.len().saturating_sub(self.held);
+        self.bytes.extend_from_slice(piece);
+        let tail = &self.bytes[from..];
+        let first_match = self
+            .stops
+            .iter()
+            .filter_map(|stop| tail.windows(stop.len()).position(|w| w == stop.as_slice()))
+            .min();
+        if let Some(at) = first_match {
+            self.bytes.truncate(from + at);
+            self.held = 0;
+            return true;
+        }
+        self.held = self
+            .stops
+            .iter()
+            .map(|stop| (1..stop.len().min(tail.len() + 1)).rev().find(|&k| tail.ends_with(&stop[..k])).unwrap_or(0))
+            .max()
+            .unwrap_or(0);
+        false
+    }
+}
+
+/// Built-in chat format of a reasoning model, detected from its path: Falcon3
+/// for Falcon models, otherwise ChatML since Qwen is the primary reasoning model.
+fn chat_format_for(model_path: Option<&PathBuf>) -> ChatFormat {
+    let model_path_lower = model_path
+        .map(|p| p.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+
+    if model_path_lower.contains("falcon") {
+        ChatFormat::Falcon3
+    } else {
+        ChatFormat::ChatMl
+    }
+}
+
+/// Leading prompt `tokens` that are the templated system prompt alone, i.e.
+/// the same for any user turn under it. 0 without a system prompt.
+fn system_prefix_len(model: &LlamaModel, request: &GenerateRequest, tokens: &[LlamaToken], format: ChatFormat) -> usize {
+    match request.system_prompt.as_deref() {
+        Some(sys) if !sys.is_empty() => {
+            let header = GenerateRequest {
+                system_prompt: Some(sys.to_string()),
+                prompt: String::new(),
+                messages: None,
+                ..request.clone()
+            };
+            model
+                .str_to_token(&chat_prompt(model, &header, format), AddBos::Always)
+                .ok()
+                .map(|header| shared_prefix_len(&header, tokens))
+                .unwrap_or(0)
+        }
+        _ => 0,
+    }
+}
+
+/// Chat formats built into the runtime, for models without an embedded template.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ChatFormat {
+    /// `<|im_start|>role\n...<|im_end|>\n` per turn, then `<|im_start|>assistant\n`.
+    ChatMl,
+    /// `<|role|>\n...\n` per turn, then `<|assistant|>\n`.
+    Falcon3,
+}
+
+impl ChatFormat {
+    /// Render `(role, content)` turns and open the assistant's reply.
+    fn render(self, turns: &[(&str, &str)]) -> String {
+        let mut out = String::new();
+        for (role, content) in turns {
+            out.push_str(&match self {
+                ChatFormat::ChatMl => format!("<|im_start|>{}\n{}<|im_end|>\n", role, content),
+                ChatFormat::Falcon3 => format!("<|{}|>\n{}\n", role, content),
+            });
+        }
+        out.push_str(match self {
+            ChatFormat::ChatMl => "<|im_start|>assistant\n",
+            ChatFormat::Falcon3 => "<|assistant|>\n",
+        });
+        out
+    }
+}
+
+/// `request` as chat turns: `system_prompt` (if not empty), then `messages`,
+/// or `prompt` as a single user turn without them.
+fn request_turns(request: &GenerateRequest) -> Vec<(&str, &str)> {
+    let mut turns = Vec::new();
+    if let Some(sys) = request.system_prompt.as_deref().filter(|s| !s.is_empty()) {
+        turns.push(("system", sys));
+    }
+    match &request.messages {
+        Some(messages) => turns.extend(messages.iter().map(|m| (m.role.as_str(), m.content.as_str()))),
+        None => turns.push(("user", request.prompt.as_str())),
+    }
+    turns
+}
+
+/// Prompt for `request`. With `messages` or `apply_chat_template`, rendered by
+/// `model`'s embedded chat template; otherwise, or when the model has none or
+/// llama.cpp can't render it, in the built-in `fallback` format.
+fn chat_prompt(model: &LlamaModel, request: &GenerateRequest, fallback: ChatFormat) -> String {
+    let turns = request_turns(request);
+    if (request.apply_chat_template || request.messages.is_some()) && model.chat_template().is_some() {
+        match model.apply_chat_template(None, &turns, true) {
+            Ok(rendered) => return rendered,
+            Err(e) => NativeRuntime::log(&format!("chat template failed ({}), using built-in format", e)),
+        }
+    }
+    fallback.render(&turns)
+}
+
+/// Turns used to exercise a chat template when the caller supplies none.
+fn sample_chat_turns() -> Vec<ChatMessage> {
+    [
+        ("system", "You are a helpful assistant."),
+        ("user", "Hello!"),
+        ("assistant", "Hi! How can I help?"),
+        ("user", "What is 2 + 2?"),
+    ]
+    .iter()
+    .map(|(role, content)| ChatMessage {
+        role: role.to_string(),
+        content: content.to_string(),
+    })
+    .collect()
+}
+
+fn render_chat_template(model: &LlamaModel, messages: &[ChatMessage]) -> ChatTemplateCheck {
+    let turns: Vec<(&str, &str)> = messages
+        .iter()
+        .map(|m| (m.role.as_str(), m.content.as_str()))
+        .collect();
+    let rendered = model.apply_chat_template(None, &turns, true);
+    check_rendered_template(model.chat_template(), messages, rendered)
+}
+
+/// A template that renders but drops a turn's content is as broken as one that
+/// fails outright.
+fn check_rendered_template(
+    template: Option<String>,
+    messages: &[ChatMessage],
+    rendered: Result<String, String>,
+) -> ChatTemplateCheck {
+    let error = match &rendered {
+        Err(e) => Some(e.clone()),
+        Ok(text) => messages
+            .iter()
+            .find(|m| !m.content.is_empty() && !text.contains(&m.content))
+            .map(|m| format!("Rendered template is missing the content of a '{}' turn", m.role)),
+    };
+    ChatTemplateCheck {
+        ok: error.is_none(),
+        template,
+        rendered: rendered.ok(),
+        error,
+    }
+}
+
+/// Inter-token intervals for `profile_timing` requests; does nothing when off.
+struct TokenTimer {
+    /// When the generation call started, for `first_token_ms`.
+    start: Instant,
+    first_token_ms: Option<u64>,
+    last: Instant,
+    intervals: Option<Vec<u32>>,
+}
+
+impl TokenTimer {
+    fn new(enabled: bool, max_tokens: u32, start: Instant) -> Self {
+        TokenTimer {
+            start,
+            first_token_ms: None,
+            last: Instant::now(),
+            intervals: enabled.then(|| Vec::with_capacity(max_tokens as usize)),
+        }
+    }
+
+    /// Call after each sample. Returns the time to first token the first time.
+    fn sampled(&mut self) -> Option<u64> {
+        if self.first_token_ms.is_some() {
+            return None;
+        }
+        let ms = self.start.elapsed().as_millis() as u64;
+        self.first_token_ms = Some(ms);
+        Some(ms)
+    }
+
+    /// Record the time since the previous token (or since creation).
+    fn tick(&mut self) {
+        if let Some(intervals) = self.intervals.as_mut() {
+            let now = Instant::now();
+            intervals.push(now.duration_since(self.last).as_millis().min(u32::MAX as u128) as u32);
+            self.last = now;
+        }
+    }
+
+    fn finish(self) -> Option<Vec<u32>> {
+        self.intervals
+    }
+}
+
+/// Incremental character counter for `max_chars`. Tokens can end mid-codepoint,
+/// so characters are counted from UTF-8 lead bytes as output grows.
+struct CharBudget {
+    max_chars: usize,
+    /// Bytes of output already scanned.
+    scanned: usize,
+    /// Characters started so far (the last may still be incomplete).
+    chars: usize,
+}
+
+impl CharBudget {
+    fn new(max_chars: usize) -> Self {
+        CharBudget { max_chars, scanned: 0, chars: 0 }
+    }
+
+    /// Scan newly appended bytes. Returns true once `max_chars` complete characters
+    /// are present, truncating anything after them.
+    fn exhausted(&mut self, output: &mut Vec<u8>) -> bool {
+        while self.scanned < output.len() {
+            if output[self.scanned] & 0xC0 != 0x80 {
+                if self.chars == self.max_chars {
+                    output.truncate(self.scanned);
+                    return true;
+                }
+                self.chars += 1;
+            }
+            self.scanned += 1;
+        }
+        self.chars == self.max_chars && last_char_complete(output)
+    }
+}
+
+/// Whether `bytes` ends on a codepoint boundary (no partial UTF-8 sequence at the end).
+fn last_char_complete(bytes: &[u8]) -> bool {
+    let Some(lead_idx) = bytes.iter().rposition(|b| b & 0xC0 != 0x80) else {
+        return true;
+    };
+    let lead = bytes[lead_idx];
+    let expected = match lead {
+        0x00..=0x7F => 1,
+        0xC0..=0xDF => 2,
+        0xE0..=0xEF => 3,
+        _ => 4,
+    };
+    bytes.len() - lead_idx >= expected
+}
+
+/// Decode throughput for a response's `tokens_per_second`, from the same
+/// `decode_ms` it reports. 0 when nothing was decoded or it took under 1ms.
+fn tokens_per_second(decode_ms: u64, tokens: u32) -> f32 {
+    if decode_ms == 0 {
+        return 0.0;
+    }
+    tokens as f32 * 1000.0 / decode_ms as f32
+}
+
+/// Deterministic prose-like filler of exactly `len` characters. `index` varies
+/// the opening so inputs in one batch differ.
+fn synthetic_text(index: usize, len: usize) -> String {
+    const WORDS: &[&str] = &[
+        "the", "quarterly", "report", "notes", "that", "meeting", "schedules", "should", "move", "to",
+        "thursday", "after", "review", "of", "budget", "and", "travel", "plans", "for", "march",
+    ];
+    let mut text = String::with_capacity(len + 16);
+    let mut i = index;
+    while text.len() < len {
+        if !text.is_empty() {
+            text.push(' ');
+        }
+        text.push_str(WORDS[i % WORDS.len()]);
+        i += 7;
+    }
+    text.truncate(len);
+    text
+}
+
+/// Per-input token cap for embeddings (inputs beyond this are truncated).
+const EMBED_MAX_TOKENS_PER_INPUT: usize = 2048;
+/// Upper bound on inputs per embedding sub-batch.
+const MAX_EMBED_SUB_BATCH: usize = 64;
+
+/// L2-normalize an embedding vector. Zero vectors are returned unchanged.
+fn l2_normalize(embedding: &[f32]) -> Vec<f32> {
+    let magnitude = embedding
+        .iter()
+        .fold(0.0f32, |acc, &v| v.mul_add(v, acc))
+        .sqrt();
+    if magnitude > 0.0 {
+        embedding.iter().map(|&v| v / magnitude).collect()
+    } else {
+        embedding.to_vec()
+    }
+}
+
+/// Empty every embedding containing a non-finite value. Returns their indices.
+fn reject_non_finite(embeddings: &mut [Vec<f32>]) -> Vec<usize> {
+    let mut failed = Vec::new();
+    for (i, embedding) in embeddings.iter_mut().enumerate() {
+        if embedding.iter().any(|v| !v.is_finite()) {
+            embedding.clear();
+            failed.push(i);
+        }
+    }
+    failed
+}
+
+/// Rough bytes needed to embed `tokens` tokens in one context: f16 KV cache,
+/// per-token activations, and the attention score matrix (quadratic in tokens).
+fn estimate_embed_context_bytes(tokens: u64, n_layer: u64, n_embd: u64) -> u64 {
+    let kv_cache = 2 * n_layer * tokens * n_embd * 2;
+    let activations = tokens * n_embd * 4 * 8;
+    let n_head = (n_embd / 64).max(1);
+    let attention_scores = n_head * tokens * tokens * 4;
+    kv_cache + activations + attention_scores
+}
+
+/// Pick how many inputs to embed per context so a worst-case sub-batch (every
+/// input as long as the longest) fits in half of available RAM. Always at least 1.
+fn plan_embed_sub_batch(available_ram_mb: u64, longest_tokens: usize, n_layer: u64, n_embd: u64, n_inputs: usize) -> usize {
+    let budget = available_ram_mb * 1024 * 1024 / 2;
+    let per_input = longest_tokens.max(1) as u64;
+    let mut size = n_inputs.clamp(1, MAX_EMBED_SUB_BATCH);
+    while size > 1 && estimate_embed_context_bytes(size as u64 * per_input, n_layer, n_embd) > budget {
+        size /= 2;
+    }
+    size
+}
+
+/// Tokens the shared embedding context needs: the largest total over the
+/// sub-batches `tokenized` splits into at `sub_batch_size`.
+fn embed_context_tokens(tokenized: &[Vec<LlamaToken>], sub_batch_size: usize) -> usize {
+    tokenized
+        .chunks(sub_batch_size.max(1))
+        .map(|chunk| chunk.iter().map(|t| t.len()).sum::<usize>())
+        .max()
+        .unwrap_or(0)
+}
+
+/// Embedding sequences per context for full-length inputs; matches how `embed`
+/// sizes its sub-batches.
+fn plan_embed_parallelism(available_ram_mb: u64, n_layer: u64, n_embd: u64) -> SequenceLimit {
+    let tokens = EMBED_MAX_TOKENS_PER_INPUT;
+    SequenceLimit {
+        max_sequences: plan_embed_sub_batch(available_ram_mb, tokens, n_layer, n_embd, MAX_EMBED_SUB_BATCH) as u32,
+        tokens_per_sequence: tokens as u32,
+        bytes_per_sequence: estimate_embed_context_bytes(tokens as u64, n_layer, n_embd),
+    }
+}
+
+/// Generation sequences whose KV caches fit in `available_bytes` after compute
+/// buffers and headroom, capped at `MAX_PARALLEL_SEQUENCES`. Assumes the model
+/// weights are already resident. `None` if the shape lacks hyperparameters
+/// (including a zero KV head count, which would make a sequence free).
+fn plan_generation_parallelism(shape: &ModelShape, available_bytes: u64) -> Option<SequenceLimit> {
+    if shape.n_layer == 0 || shape.n_head == 0 || shape.n_embd == 0 {
+        return None;
+    }
+    let bytes_per_sequence = kv_bytes_per_token(shape) * GENERATION_N_CTX as u64;
+    if bytes_per_sequence == 0 {
+        return None;
+    }
+    let headroom = (available_bytes as f64 * CONTEXT_MEMORY_HEADROOM) as u64;
+    let usable = available_bytes.saturating_sub(headroom + COMPUTE_BUFFER_BYTES);
+    Some(SequenceLimit {
+        max_sequences: (usable / bytes_per_sequence).clamp(1, MAX_PARALLEL_SEQUENCES as u64) as u32,
+        tokens_per_sequence: GENERATION_N_CTX,
+        bytes_per_sequence,
+    })
+}
+
+/// f16 K + V across all layers for one token.
+fn kv_bytes_per_token(shape: &ModelShape) -> u64 {
+    let head_dim = (shape.n_embd / shape.n_head) as u64;
+    // K and V, f16 (2 bytes), per layer.
+    2 * shape.n_layer as u64 * head_dim * shape.n_head_kv as u64 * 2
+}
+
+fn read_model_shape(model: &LlamaModel) -> ModelShape {
+    let arch = model.meta_val_str("general.architecture").unwrap_or_default();
+    let meta_u32 = |key: &str| {
+        model
+            .meta_val_str(&format!("{}.{}", arch, key))
+            .and_then(|v| v.trim().parse::<u32>().ok())
+    };
+    let n_head = meta_u32("attention.head_count").unwrap_or(0);
+    ModelShape {
+        n_layer: model.n_layer().max(0) as u32,
+        n_embd: model.n_embd().max(0) as u32,
+        n_head,
+        n_head_kv: meta_u32("attention.head_count_kv").unwrap_or(n_head),
+        n_ctx_train: meta_u32("context_length").unwrap_or(0),
+    }
+}
+
+/// Name of a GGUF `general.file_type` (llama.cpp's `llama_ftype`), the
+/// quantization most tensors use. Ids from 33 up follow BitNet.cpp's fork,
+/// which adds its ternary types after the upstream ones.
+fn file_type_name(file_type: u32) -> Option<&'static str> {
+    Some(match file_type {
+        0 => "F32",
+        1 => "F16",
+        2 => "Q4_0",
+        3 => "Q4_1",
+        7 => "Q8_0",
+        8 => "Q5_0",
+        9 => "Q5_1",
+        10 => "Q2_K",
+        11 => "Q3_K_S",
+        12 => "Q3_K_M",
+        13 => "Q3_K_L",
+        14 => "Q4_K_S",
+        15 => "Q4_K_M",
+        16 => "Q5_K_S",
+        17 => "Q5_K_M",
+        18 => "Q6_K",
+        19 => "IQ2_XXS",
+        20 => "IQ2_XS",
+        21 => "Q2_K_S",
+        22 => "IQ3_XS",
+        23 => "IQ3_XXS",
+        24 => "IQ1_S",
+        25 => "IQ4_NL",
+        26 => "IQ3_S",
+        27 => "IQ3_M",
+        28 => "IQ2_S",
+        29 => "IQ2_M",
+        30 => "IQ4_XS",
+        31 => "IQ1_M",
+        32 => "BF16",
+        33 => "Q4_0_4_4",
+        34 => "Q4_0_4_8",
+        35 => "Q4_0_8_8",
+        36 => "TQ1_0",
+        37 => "TQ2_0",
+        38 => "TL1",
+        39 => "TL2",
+        40 => "I2_S",
+        _ => return None,
+    })
+}
+
+/// Largest context whose KV cache fits in `available_bytes` after the weights
+/// (`model_bytes`, 0 if already resident), compute buffers, and headroom.
+/// Rounded down to a multiple of `MIN_CONTEXT_LENGTH` and capped at the
+/// training context. Errors with the shortfall if even the minimum doesn't fit.
+fn plan_context_length(shape: &ModelShape, available_bytes: u64, model_bytes: u64) -> Result<ContextRecommendation, String> {
+    if shape.n_layer == 0 || shape.n_head == 0 || shape.n_embd == 0 {
+        return Err("Model is missing layer/head hyperparameters".to_string());
+    }
+    let kv_bytes_per_token = kv_bytes_per_token(shape);
+
+    let headroom = (available_bytes as f64 * CONTEXT_MEMORY_HEADROOM) as u64;
+    let reserved_bytes = headroom + COMPUTE_BUFFER_BYTES;
+    let usable_bytes = available_bytes.saturating_sub(reserved_bytes + model_bytes);
+
+    let min_kv = MIN_CONTEXT_LENGTH as u64 * kv_bytes_per_token;
+    if usable_bytes < min_kv {
+        let shortfall = min_kv - usable_bytes;
+        return Err(format!(
+            "Not enough memory for a {}-token context: short by {} MB ({} MB available, {} MB for weights, {} MB reserved, {} MB KV cache needed)",
+            MIN_CONTEXT_LENGTH,
+            shortfall.div_ceil(1024 * 1024),
+            available_bytes / (1024 * 1024),
+            model_bytes / (1024 * 1024),
+            reserved_bytes / (1024 * 1024),
+            min_kv.div_ceil(1024 * 1024)
+        ));
+    }
+
+    let mut n_ctx = (usable_bytes / kv_bytes_per_token).min(u32::MAX as u64) as u32;
+    if shape.n_ctx_train > 0 {
+        n_ctx = n_ctx.min(shape.n_ctx_train);
+    }
+    let n_ctx = (n_ctx / MIN_CONTEXT_LENGTH * MIN_CONTEXT_LENGTH).max(MIN_CONTEXT_LENGTH);
+
+    Ok(ContextRecommendation {
+        n_ctx,
+        shape: shape.clone(),
+        kv_bytes_per_token,
+        kv_cache_bytes: n_ctx as u64 * kv_bytes_per_token,
+        available_bytes,
+        model_bytes,
+        reserved_bytes,
+        usable_bytes,
+    })
+}
+
+/// Check that a model with `model_bytes` of weights fits in memory with an
+/// `n_ctx` KV cache and compute buffers. The share of the `n_gpu_layers`
+/// offloaded layers (weights split as in `auto_gpu_layers`, KV cache per
+/// layer) that fits in `vram_mb` is left out; the rest must fit in
+/// `available_bytes` of RAM. `vram_mb` is 0 without a discrete GPU.
+fn check_load_memory(
+    shape: &ModelShape,
+    model_bytes: u64,
+    n_ctx: u32,
+    n_gpu_layers: i32,
+    available_bytes: u64,
+    vram_mb: u64,
+) -> Result<(), InsufficientMemory> {
+    let kv_bytes = if shape.n_head == 0 { 0 } else { n_ctx as u64 * kv_bytes_per_token(shape) };
+    let total = model_bytes + kv_bytes + COMPUTE_BUFFER_BYTES;
+    let gpu_layers = compute_layer_placement(shape.n_layer, n_gpu_layers, vram_mb > 0).gpu_layers as u64;
+    let offloaded = if gpu_layers == 0 {
+        0
+    } else {
+        model_bytes / (shape.n_layer as u64 + 1) * gpu_layers + kv_bytes / shape.n_layer as u64 * gpu_layers
+    };
+    let required = total - offloaded.min(vram_mb * 1024 * 1024);
+    if required > available_bytes {
+        return Err(InsufficientMemory {
+            required_mb: required.div_ceil(1024 * 1024),
+            available_mb: available_bytes / (1024 * 1024),
+        });
+    }
+    Ok(())
+}
+
+/// VRAM `GpuLayers::Auto` leaves free for the KV cache and compute buffers.
+const GPU_OFFLOAD_HEADROOM_MB: u64 = 1024;
+
+/// Layers of a model with `n_layer` repeating layers and `model_bytes` of
+/// weights that fit in `vram_mb`, less `GPU_OFFLOAD_HEADROOM_MB`. The weights
+/// are taken as split evenly over the layers plus one more for the token
+/// embeddings and output head.
+fn auto_gpu_layers(n_layer: u32, model_bytes: u64, vram_mb: u64) -> i32 {
+    if n_layer == 0 || model_bytes == 0 {
+        return 0;
+    }
+    let usable = vram_mb.saturating_sub(GPU_OFFLOAD_HEADROOM_MB) * 1024 * 1024;
+    let per_layer = model_bytes.div_ceil(n_layer as u64 + 1);
+    (usable / per_layer).min(n_layer as u64) as i32
+}
+
+/// Split `total_layers` between GPU and CPU the way llama.cpp offloads: the last
+/// `n_gpu_layers` repeating layers go to GPU (negative = all), capped at the total.
+fn compute_layer_placement(total_layers: u32, n_gpu_layers: i32, offload_supported: bool) -> LayerPlacement {
+    let gpu_layers = if !offload_supported || n_gpu_layers == 0 {
+        0
+    } else if n_gpu_layers < 0 {
+        total_layers
+    } else {
+        (n_gpu_layers as u32).min(total_layers)
+    };
+    LayerPlacement {
+        total_layers,
+        gpu_layers,
+        cpu_layers: total_layers - gpu_layers,
+        requested_gpu_layers: n_gpu_layers,
+        gpu_offload_supported: offload_supported,
+    }
+}
+
+/// Validate a self-test completion: non-empty text, at least one token, and no
+/// U+FFFD replacement characters (a sign of invalid UTF-8 from a broken vocab).
+fn check_generation_output(response: &GenerateResponse) -> Result<String, String> {
+    if response.tokens_generated == 0 || response.text.trim().is_empty() {
+        return Err("Generation produced no output".to_string());
+    }
+    if response.text.contains('\u{FFFD}') {
+        return Err(format!("Generation produced malformed UTF-8: {:?}", response.text));
+    }
+    Ok(format!("{} tokens: {:?}", response.tokens_generated, response.text.trim()))
+}
+
+/// Validate a self-test embedding: one vector of the reported dimension, all
+/// values finite, and not all zero.
+fn check_embedding_output(response: &EmbedResponse) -> Result<String, String> {
+    if response.failed_count > 0 {
+        return Err("Embedding contains NaN or infinite values".to_string());
+    }
+    let vector = match response.embeddings.as_slice() {
+        [v] => v,
+        other => return Err(format!("Expected 1 embedding, got {}", other.len())),
+    };
+    if response.dimensions == 0 || vector.len() != response.dimensions as usize {
+        return Err(format!(
+            "Embedding dimension mismatch: vector has {}, model reports {}",
+            vector.len(),
+            response.dimensions
+        ));
+    }
+    if vector.iter().any(|v| !v.is_finite()) {
+        return Err("Embedding contains NaN or infinite values".to_string());
+    }
+    if vector.iter().all(|&v| v == 0.0) {
+        return Err("Embedding is all zeros".to_string());
+    }
+    Ok(format!("dim={}", vector.len()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_new_runtime_is_uninitialized() {
+        let runtime = NativeRuntime::new();
+        assert!(matches!(runtime.status(), RuntimeStatus::Uninitialized));
+        assert!(!runtime.has_reasoning_model());
+        assert!(!runtime.has_embedding_model());
+        assert!(!runtime.can_generate());
+        assert!(!runtime.can_embed());
+    }
+
+    #[test]
+    fn test_embedding_only_refuses_generation() {
+        let mut runtime = NativeRuntime::new();
+        runtime.status = RuntimeStatus::EmbeddingOnly;
+        assert!(!runtime.can_generate());
+        assert_eq!(runtime.generation_refused(), RuntimeError::NoReasoningModel);
+
+        // Unloading the embedding model leaves nothing resident.
+        runtime.unload_embedding_model();
+        assert!(matches!(runtime.status(), RuntimeStatus::Uninitialized));
+        // Nor does unloading a reasoning model that was never there make it ready.
+        runtime.unload_reasoning_model();
+        assert!(matches!(runtime.status(), RuntimeStatus::Uninitialized));
+
+        // An idle-unloaded model still counts as ready to generate.
+        runtime.idle_unloaded_path = Some(PathBuf::from("/nonexistent/model.gguf"));
+        assert!(matches!(runtime.resident_status(), RuntimeStatus::Ready));
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+    async fn test_run_blocking_keeps_executor_responsive() {
+        let runtime = create_runtime();
+        let ticks = Arc::new(AtomicU32::new(0));
+        let ticker = tokio::spawn({
+            let ticks = ticks.clone();
+            async move {
+                loop {
+                    tokio::time::sleep(Duration::from_millis(10)).await;
+                    ticks.fetch_add(1, Ordering::Relaxed);
+                }
+            }
+        });
+        // Stands in for a long generation; on the only worker it would starve the ticker.
+        let result = run_blocking(&runtime, |_| std::thread::sleep(Duration::from_millis(300))).await;
+        assert!(result.is_ok());
+        assert!(ticks.load(Ordering::Relaxed) >= 5, "ticker stalled during run_blocking");
+        ticker.abort();
+    }
+
+    #[tokio::test]
+    async fn test_generate_async_without_model_fails() {
+        let runtime = create_runtime();
+        let request: GenerateRequest =
+            serde_json::from_value(serde_json::json!({ "model_path": "", "prompt": "test" })).unwrap();
+        assert!(generate_async(&runtime, request, None).await.unwrap_err().to_string().contains("not ready"));
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+    async fn test_cancel_flag_stops_blocking_generation() {
+        let runtime = create_runtime();
+        let cancel = Arc::new(AtomicBool::new(false));
+        let tokens = Arc::new(AtomicU32::new(0));
+        // Stands in for the token loop: a checkpoint before every token, as `generate` does.
+        let run = tokio::spawn({
+            let (runtime, cancel, tokens) = (runtime.clone(), cancel.clone(), tokens.clone());
+            async move {
+                run_blocking(&runtime, move |_| {
+                    let hooks = GenerationHooks { cancel: Some(&cancel), ..GenerationHooks::NONE };
+                    for _ in 0..10_000 {
+                        hooks.checkpoint()?;
+                        tokens.fetch_add(1, Ordering::Relaxed);
+                        std::thread::sleep(Duration::from_millis(1));
+                    }
+                    Ok::<_, String>(())
+                })
+                .await
+            }
+        });
+        while tokens.load(Ordering::Relaxed) < 5 {
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+
+        cancel.store(true, Ordering::Relaxed);
+        let result = tokio::time::timeout(Duration::from_secs(5), run).await.expect("cancelled run kept going");
+        assert_eq!(result.unwrap().unwrap().unwrap_err(), "Generation cancelled");
+        assert!(tokens.load(Ordering::Relaxed) < 10_000);
+        // The read lock is released, so a model load can proceed.
+        assert!(runtime.try_write().is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_load_nonexistent_model_fails() {
+        let mut runtime = NativeRuntime::new();
+        let result = runtime.load_reasoning_model(PathBuf::from("/nonexistent/model.gguf"));
+        assert!(result.is_err());
+        assert!(result.unwrap_err().to_string().contains("not found"));
+    }
+
+    #[tokio::test]
+    async fn test_generate_without_model_fails() {
+        let runtime = NativeRuntime::new();
+        let result = runtime.generate(GenerateRequest {
+            prompt: "test".to_string(),
+            ..Default::default()
+        });
+        assert!(result.is_err());
+        assert!(result.unwrap_err().to_string().contains("not ready"));
+    }
+
+    #[tokio::test]
+    async fn test_embed_without_model_fails() {
+        let runtime = NativeRuntime::new();
+        let result = runtime.embed(EmbedRequest {
+            model_path: String::new(),
+            input: vec!["test".to_string()],
+            n_ctx: None,
+            pooling: None,
+        });
+        assert!(result.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_classify_text_requires_labels() {
+        let runtime = NativeRuntime::new();
+        let result = runtime.classify_text("invoice attached", &[]);
+        assert!(result.unwrap_err().contains("label"));
+        let result = runtime.classify_text("invoice attached", &["  ".to_string()]);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_cosine_similarity() {
+        assert!((cosine_similarity(&[1.0, 0.0], &[1.0, 0.0]) - 1.0).abs() < 1e-6);
+        assert!(cosine_similarity(&[1.0, 0.0], &[0.0, 1.0]).abs() < 1e-6);
+        assert!((cosine_similarity(&[1.0, 0.0], &[-2.0, 0.0]) + 1.0).abs() < 1e-6);
+        assert_eq!(cosine_similarity(&[0.0, 0.0], &[1.0, 0.0]), 0.0);
+        assert_eq!(cosine_similarity(&[1.0], &[1.0, 0.0]), 0.0);
+    }
+
+    #[test]
+    fn test_layer_placement_without_model_fails() {
+        let runtime = NativeRuntime::new();
+        assert_eq!(runtime.layer_placement().unwrap_err(), RuntimeError::NoReasoningModel);
+    }
+
+    #[test]
+    fn test_model_info() {
+        assert!(NativeRuntime::new().model_info().is_none());
+        assert_eq!(file_type_name(15), Some("Q4_K_M"));
+        assert_eq!(file_type_name(17), Some("Q5_K_M"));
+        assert_eq!(file_type_name(7), Some("Q8_0"));
+        assert_eq!(file_type_name(1), Some("F16"));
+        // BitNet models.
+        assert_eq!(file_type_name(40), Some("I2_S"));
+        assert_eq!(file_type_name(38), Some("TL1"));
+        assert_eq!(file_type_name(36), Some("TQ1_0"));
+        // 4–6 were removed from llama.cpp.
+        assert_eq!(file_type_name(4), None);
+        assert_eq!(file_type_name(1024), None);
+    }
+
+    #[test]
+    fn test_tokenize_without_model_fails() {
+        let runtime = NativeRuntime::new();
+        assert_eq!(runtime.count_tokens("hello").unwrap_err(), RuntimeError::NoReasoningModel);
+        assert_eq!(runtime.tokenize("hello", true).unwrap_err(), RuntimeError::NoReasoningModel);
+        assert_eq!(runtime.detokenize(&[1, 2]).unwrap_err(), RuntimeError::NoReasoningModel);
+    }
+
+    #[test]
+    fn test_warmup_without_model_fails() {
+        let mut runtime = NativeRuntime::new();
+        runtime.set_warmup_after_load(true);
+        assert_eq!(runtime.warmup().unwrap_err(), RuntimeError::NoReasoningModel);
+        assert!(runtime.last_warmup().is_none());
+    }
+
+    #[test]
+    fn test_idle_unload_requires_loaded_model() {
+        let mut runtime = NativeRuntime::new();
+        assert!(runtime.unload_reasoning_if_idle(Duration::ZERO).is_none());
+        assert!(runtime.idle_unloaded_model().is_none());
+        assert!(!runtime.reload_idle_unloaded().unwrap());
+
+        // A failed reload keeps the path for the next attempt.
+        runtime.idle_unloaded_path = Some(PathBuf::from("/nonexistent/model.gguf"));
+        assert!(runtime.reload_idle_unloaded().is_err());
+        assert!(runtime.idle_unloaded_model().is_some());
+        runtime.unload_reasoning_model();
+        assert!(runtime.idle_unloaded_model().is_none());
+    }
+
+    #[test]
+    fn test_plan_parallelism() {
+        const GIB: u64 = 1024 * 1024 * 1024;
+        let shape = ModelShape { n_layer: 28, n_embd: 1536, n_head: 12, n_head_kv: 2, n_ctx_train: 32768 };
+        let limit = plan_generation_parallelism(&shape, 8 * GIB).unwrap();
+        assert_eq!(limit.bytes_per_sequence, 4096 * 28 * 1024);
+        assert_eq!(limit.max_sequences, MAX_PARALLEL_SEQUENCES);
+        // 1 GiB: 768 MiB after headroom, 512 MiB after compute buffers → 4 × 112 MiB.
+        assert_eq!(plan_generation_parallelism(&shape, GIB).unwrap().max_sequences, 4);
+        assert_eq!(plan_generation_parallelism(&shape, 0).unwrap().max_sequences, 1);
+        assert!(plan_generation_parallelism(&ModelShape { n_head: 0, ..shape }, GIB).is_none());
+        assert!(plan_generation_parallelism(&ModelShape { n_head_kv: 0, ..shape }, GIB).is_none());
+
+        let embedding = plan_embed_parallelism(64 * 1024, 12, 768);
+        assert_eq!(embedding.tokens_per_sequence, EMBED_MAX_TOKENS_PER_INPUT as u32);
+        assert!(embedding.max_sequences >= 1 && embedding.max_sequences <= MAX_EMBED_SUB_BATCH as u32);
+        assert!(plan_embed_parallelism(256, 12, 768).max_sequences < embedding.max_sequences);
+    }
+
+    #[test]
+    fn test_tokens_per_second() {
+        assert_eq!(tokens_per_second(2000, 50), 25.0);
+        assert_eq!(tokens_per_second(250, 1), 4.0);
+        assert_eq!(tokens_per_second(1000, 0), 0.0);
+        // Under a millisecond reports 0 rather than infinity.
+        assert_eq!(tokens_per_second(0, 12), 0.0);
+        assert_eq!(tokens_per_second(0, 0), 0.0);
+    }
+
+    #[test]
+    fn test_token_timer_records_intervals_and_first_token() {
+        // The call started 30ms before the timer was created (prompt prefill).
+        let start = Instant::now() - Duration::from_millis(30);
+        let mut timer = TokenTimer::new(true, 3, start);
+        std::thread::sleep(Duration::from_millis(20));
+        let first = timer.sampled().unwrap();
+        assert!(first >= 50, "first token at {}ms", first);
+        assert_eq!(timer.sampled(), None);
+        timer.tick();
+        std::thread::sleep(Duration::from_millis(10));
+        timer.tick();
+        timer.tick();
+        let intervals = timer.finish().unwrap();
+        assert_eq!(intervals.len(), 3);
+        // The first interval runs from the timer's creation, not the call's start.
+        assert!(intervals[0] >= 20 && (intervals[0] as u64) < first, "{:?}", intervals);
+        assert!(intervals[1] >= 10, "{:?}", intervals);
+
+        let mut disabled = TokenTimer::new(false, 3, Instant::now());
+        assert!(disabled.sampled().is_some());
+        disabled.tick();
+        assert_eq!(disabled.finish(), None);
+    }
+
+    #[test]
+    fn test_rank_choices_by_logprob() {
+        let logits = [0.0f32, 0.0, 0.0, 0.0];
+        assert!((token_logprob(&logits, 2) - (0.25f32).ln()).abs() < 1e-6);
+        assert_eq!(token_logprob(&logits, 9), f32::NEG_INFINITY);
+
+        let choices = vec!["billing".to_string(), "bug".to_string(), "other".to_string()];
+        let ranked = rank_choices(&choices, &[vec![-2.0, -0.5], vec![-1.0], vec![-5.0]]);
+        let order: Vec<usize> = ranked.iter().map(|c| c.index).collect();
+        assert_eq!(order, vec![1, 0, 2]);
+        assert_eq!(ranked[1].tokens, 2);
+        assert!((ranked[1].mean_logprob + 1.25).abs() < 1e-6);
+        let total: f32 = ranked.iter().map(|c| c.probability).sum();
+        assert!((total - 1.0).abs() < 1e-5);
+        assert!(ranked[0].probability > ranked[1].probability);
+    }
+
+    #[test]
+    fn test_plan_context_length() {
+        const GIB: u64 = 1024 * 1024 * 1024;
+        // Qwen2.5-1.5B-like: 28 layers, 1536 embd, 12 heads, 2 KV heads → 28 KiB/token.
+        let shape = ModelShape { n_layer: 28, n_embd: 1536, n_head: 12, n_head_kv: 2, n_ctx_train: 32768 };
+        let plan = plan_context_length(&shape, 8 * GIB, GIB).unwrap();
+        assert_eq!(plan.kv_bytes_per_token, 28 * 1024);
+        assert_eq!(plan.n_ctx, 32768); // capped at the training context
+        assert_eq!(plan.kv_cache_bytes, 32768 * 28 * 1024);
+
+        let untrained = ModelShape { n_ctx_train: 0, ..shape.clone() };
+        let plan = plan_context_length(&untrained, 2 * GIB, 0).unwrap();
+        assert_eq!(plan.n_ctx % MIN_CONTEXT_LENGTH, 0);
+        assert!(plan.kv_cache_bytes <= plan.usable_bytes);
+
+        let err = plan_context_length(&shape, GIB, GIB).unwrap_err();
+        assert!(err.contains("short by"), "{}", err);
+        assert!(plan_context_length(&ModelShape { n_head: 0, ..shape }, 8 * GIB, 0).is_err());
+    }
+
+    #[test]
+    fn test_compute_layer_placement() {
+        let partial = compute_layer_placement(33, 23, true);
+        assert_eq!((partial.gpu_layers, partial.cpu_layers), (23, 10));
+        let capped = compute_layer_placement(33, 99, true);
+        assert_eq!((capped.gpu_layers, capped.cpu_layers), (33, 0));
+        let all = compute_layer_placement(33, -1, true);
+        assert_eq!(all.gpu_layers, 33);
+        let no_gpu = compute_layer_placement(33, 23, false);
+        assert_eq!((no_gpu.gpu_layers, no_gpu.cpu_layers), (0, 33));
+    }
+
+    #[test]
+    fn test_auto_gpu_layers() {
+        const GIB: u64 = 1024 * 1024 * 1024;
+        // 10 GB over 40 + 1 layers on an 8 GB card: 7 GB usable fits 28.
+        assert_eq!(auto_gpu_layers(40, 10 * GIB, 8192), 28);
+        assert_eq!(auto_gpu_layers(40, 10 * GIB, 24576), 40);
+        assert_eq!(auto_gpu_layers(40, 10 * GIB, 512), 0);
+        assert_eq!(auto_gpu_layers(40, 10 * GIB, 0), 0);
+        assert_eq!(auto_gpu_layers(0, 10 * GIB, 8192), 0);
+    }
+
+    #[test]
+    fn test_load_cancel_only_while_loading() {
+        let cancel = LoadCancel::default();
+        assert!(!cancel.cancel());
+        let run = cancel.begin();
+        assert!(cancel.cancel());
+        assert!(run.cancel.requested());
+        drop(run);
+        assert!(!cancel.cancel());
+        // The next load starts uncancelled.
+        let _run = cancel.begin();
+        assert!(!cancel.requested());
+    }
 
-        let start = std::time::Instant::now();
-        let max_tokens = request.max_tokens.unwrap_or(256);
-        let temperature = request.temperature.unwrap_or(0.3);
+    #[test]
+    fn test_check_load_memory() {
+        const GIB: u64 = 1024 * 1024 * 1024;
+        // 40 layers, 8 KV heads of 128 → 160 KiB/token, 640 MiB at 4096.
+        let shape = ModelShape { n_layer: 40, n_embd: 5120, n_head: 40, n_head_kv: 8, n_ctx_train: 4096 };
+        // 13 GB of weights + 640 MB KV cache + 256 MB compute buffers.
+        let err = check_load_memory(&shape, 13 * GIB, 4096, 0, 8 * GIB, 0).unwrap_err();
+        assert_eq!(err, InsufficientMemory { required_mb: 14208, available_mb: 8192 });
+        assert!(check_load_memory(&shape, 13 * GIB, 4096, 0, 16 * GIB, 0).is_ok());
+        assert!(check_load_memory(&shape, 13 * GIB, 512, 0, 14 * GIB, 0).is_ok());
+        // Fully offloaded to a 24 GB card, only the embeddings and buffers stay in RAM.
+        assert!(check_load_memory(&shape, 13 * GIB, 4096, -1, GIB, 24576).is_ok());
+        // What doesn't fit in VRAM counts against RAM.
+        assert!(check_load_memory(&shape, 13 * GIB, 4096, -1, GIB, 4096).is_err());
+        // Layers requested without a discrete GPU stay in RAM.
+        assert!(check_load_memory(&shape, 13 * GIB, 4096, -1, 8 * GIB, 0).is_err());
+    }
 
-        // SmolLM2 uses ChatML template (always)
-        let full_prompt = match &request.system_prompt {
-            Some(sys) if !sys.is_empty() => format!(
-                "<|im_start|>system\n{}<|im_end|>\n<|im_start|>user\n{}<|im_end|>\n<|im_start|>assistant\n",
-                sys, request.prompt
-            ),
-            _ => format!(
-                "<|im_start|>user\n{}<|im_end|>\n<|im_start|>assistant\n",
-                request.prompt
-            ),
-        };
+    #[test]
+    fn test_check_vocab_compatibility() {
+        let main = VocabSummary { vocab_type: 2, n_vocab: 151_936, bos: 1, eos: 2 };
+        assert!(check_vocab_compatibility(&main, &main, |_| true).is_ok());
 
-        let ctx_params = LlamaContextParams::default().with_n_ctx(NonZeroU32::new(4096));
-        let mut ctx = model
-            .new_context(backend, ctx_params)
-            .map_err(|e| format!("Failed to create fast context: {}", e))?;
+        let padded = VocabSummary { n_vocab: 151_665, ..main };
+        let err = check_vocab_compatibility(&main, &padded, |_| true).unwrap_err();
+        assert!(err.contains("vocabulary sizes differ by 271"));
 
-        let tokens = model
-            .str_to_token(&full_prompt, AddBos::Always)
-            .map_err(|e| format!("Fast tokenization failed: {}", e))?;
+        let spm = VocabSummary { vocab_type: 1, ..main };
+        assert!(check_vocab_compatibility(&main, &spm, |_| true).unwrap_err().contains("tokenizer types"));
 
-        if tokens.is_empty() {
-            return Err("Empty prompt after tokenization".to_string());
-        }
+        let other_eos = VocabSummary { eos: 3, ..main };
+        assert!(check_vocab_compatibility(&main, &other_eos, |_| true).unwrap_err().contains("special tokens"));
 
-        let n_ctx: usize = 4096;
-        let max_prompt_tokens = n_ctx.saturating_sub(max_tokens as usize);
-        let tokens = if tokens.len() > max_prompt_tokens {
-            tokens[..max_prompt_tokens].to_vec()
-        } else {
-            tokens
-        };
+        let err = check_vocab_compatibility(&main, &main, |t| t != 42).unwrap_err();
+        assert!(err.contains("token 42"));
+        // Ids below the check start are never compared.
+        assert!(check_vocab_compatibility(&main, &main, |t| t >= DRAFT_VOCAB_CHECK_START_TOKEN_ID).is_ok());
+    }
 
-        // Chunked prefill
-        let chunk_size: usize = 512;
-        let total_prompt_tokens = tokens.len();
-        let mut pos: i32 = 0;
-        for (chunk_idx, chunk) in tokens.chunks(chunk_size).enumerate() {
-            let is_last_chunk = (chunk_idx + 1) * chunk_size >= total_prompt_tokens;
-            let mut batch = LlamaBatch::new(chunk.len().max(512), 1);
-            for (i, token) in chunk.iter().enumerate() {
-                let is_last_token = is_last_chunk && i == chunk.len() - 1;
-                batch
-                    .add(*token, pos, &[0], is_last_token)
-                    .map_err(|e| format!("Fast batch add failed: {}", e))?;
-                pos += 1;
-            }
-            ctx.decode(&mut batch)
-                .map_err(|e| format!("Fast prefill chunk {} failed: {}", chunk_idx, e))?;
-        }
+    #[test]
+    fn test_plan_session_trim() {
+        // 100 cached tokens, 20-token system prefix, keep the last 30.
+        assert_eq!(plan_session_trim(100, 20, 30), (20, 70));
+        // Already within budget: nothing removed.
+        assert_eq!(plan_session_trim(40, 20, 30), (20, 20));
+        // keep 0 drops everything after the prefix.
+        assert_eq!(plan_session_trim(100, 20, 0), (20, 100));
+        // Prefix longer than the cache (never trims into it).
+        assert_eq!(plan_session_trim(10, 20, 0), (10, 10));
+    }
 
-        // Sampler: more deterministic for fast tier (lower temp, tighter top-p)
-        let mut sampler = LlamaSampler::chain_simple([
-            LlamaSampler::top_p(0.9, 1),
-            LlamaSampler::min_p(0.05, 1),
-            LlamaSampler::temp(temperature),
-            LlamaSampler::dist(42),
-        ]);
+    #[test]
+    fn test_generation_record_keyed_by_session() {
+        let runtime = NativeRuntime::new();
+        assert!(runtime.generation_record(DEFAULT_SESSION_ID).is_err());
 
-        let mut output_bytes: Vec<u8> = Vec::new();
-        let mut n_cur = pos;
-        let mut tokens_generated = 0u32;
-        let mut gen_batch = LlamaBatch::new(1, 1);
+        let request = GenerateRequest {
+            prompt: "hi".to_string(),
+            max_tokens: Some(4),
+            temperature: Some(0.5),
+            ..Default::default()
+        };
+        let sampler = SamplerConfig { top_p: 0.95, min_p: 0.05, temperature: 0.5, seed: 42, max_tokens: 4, logit_bias: Vec::new(), rng_state: None, penalties: None, mode: SamplingMode::TopPMinP };
+        let response = GenerateResponse { text: "hello".to_string(), tokens_generated: 1, duration_ms: 3, speculative: None, unmapped_logit_bias: Vec::new(), token_intervals_ms: None, first_token_ms: None, prompt_tokens: 0, prefill_ms: 0, decode_ms: 0, tokens_per_second: 0.0 };
+        runtime.record_generation(generation_record(
+            "reasoning",
+            None,
+            None,
+            &request,
+            ("<|im_start|>user\nhi".to_string(), 5),
+            sampler.clone(),
+            &response,
+        ));
 
-        for _ in 0..max_tokens {
-            let token = sampler.sample(&ctx, -1);
-            sampler.accept(token);
+        let record = runtime.generation_record(DEFAULT_SESSION_ID).unwrap();
+        assert_eq!(record.output, "hello");
+        assert_eq!(record.prompt_tokens, 5);
+        assert_eq!(record.sampler, sampler);
+        assert!(runtime.generation_record("other").is_err());
+    }
 
-            if model.is_eog_token(token) {
-                break;
-            }
+    #[test]
+    fn test_trim_unknown_session_fails() {
+        let runtime = NativeRuntime::new();
+        assert!(runtime.trim_session("missing", 100).unwrap_err().contains("Unknown session"));
+    }
 
-            let piece = model.token_to_bytes(token);
-            output_bytes.extend_from_slice(&piece);
-            tokens_generated += 1;
+    #[test]
+    fn test_check_rendered_template() {
+        let turns = sample_chat_turns();
+        let full: String = turns.iter().map(|m| format!("<|{}|>{}\n", m.role, m.content)).collect();
+        let check = check_rendered_template(None, &turns, Ok(full));
+        assert!(check.ok);
+        assert!(check.error.is_none());
+
+        let dropped = check_rendered_template(None, &turns, Ok("<|user|>Hello!\n".to_string()));
+        assert!(!dropped.ok);
+        assert!(dropped.rendered.is_some());
+        assert!(dropped.error.unwrap().contains("'system'"));
+
+        let failed = check_rendered_template(Some("{{ bad".to_string()), &turns, Err("unsupported".to_string()));
+        assert!(!failed.ok);
+        assert!(failed.rendered.is_none());
+        assert_eq!(failed.template.as_deref(), Some("{{ bad"));
+    }
 
-            let output_so_far = String::from_utf8_lossy(&output_bytes);
-            if let Some(ref stops) = request.stop {
-                if let Some(stop) = stops.iter().find(|s| output_so_far.ends_with(s.as_str())) {
-                    let stop_len = stop.len();
-                    let output_str_len = output_so_far.len();
-                    output_bytes.truncate(output_str_len - stop_len);
-                    break;
-                }
+    #[test]
+    fn test_chat_template_requires_reasoning_model() {
+        let rt = NativeRuntime::new();
+        assert!(rt.test_chat_template(None).is_err());
+    }
+
+    #[test]
+    fn test_sampler_modes_defaults_in_range() {
+        let modes = sampler_modes();
+        assert!(modes.iter().any(|m| m.name == "top_p" && m.used_for_generation));
+        for mode in &modes {
+            for p in &mode.params {
+                assert!(p.min <= p.default && p.default <= p.max, "{}.{}", mode.name, p.name);
             }
+        }
+        let top_p = &modes[0].params;
+        let default = |name: &str| top_p.iter().find(|p| p.name == name).unwrap().default as f32;
+        let built = SamplerConfig {
+            top_p: default("top_p"),
+            min_p: default("min_p"),
+            temperature: default("temperature"),
+            seed: default("seed") as u32,
+            max_tokens: 0,
+            logit_bias: Vec::new(),
+            rng_state: None,
+            penalties: None,
+            mode: SamplingMode::default(),
+        };
+        assert_eq!(built, SamplerConfig { top_p: 0.95, min_p: 0.05, temperature: 0.7, seed: 0, max_tokens: 0, logit_bias: Vec::new(), rng_state: None, penalties: None, mode: SamplingMode::TopPMinP });
+    }
 
-            gen_batch.clear();
-            gen_batch
-                .add(token, n_cur, &[0], true)
-                .map_err(|e| format!("Fast decode failed: {}", e))?;
-            ctx.decode(&mut gen_batch)
-                .map_err(|e| format!("Fast decode failed: {}", e))?;
-            n_cur += 1;
+    #[test]
+    fn test_sampling_settings_reject_mixed_modes() {
+        let mirostat = Some(SamplingMode::MirostatV2 { tau: 5.0, eta: 0.1 });
+        let request = GenerateRequest { sampling_mode: mirostat, ..penalty_request("", None) };
+        assert_eq!(sampling_settings(&request, 0.95).unwrap(), (mirostat.unwrap(), 0.95, 0.05));
+        let mixed = GenerateRequest { top_p: Some(0.8), ..request.clone() };
+        assert!(sampling_settings(&mixed, 0.95).unwrap_err().contains("don't apply to Mirostat"));
+        let bad_eta = GenerateRequest { sampling_mode: Some(SamplingMode::Mirostat { tau: 5.0, eta: 0.0 }), ..request };
+        assert!(sampling_settings(&bad_eta, 0.95).is_err());
+        let nucleus = GenerateRequest { top_p: Some(0.8), min_p: Some(0.1), ..penalty_request("", None) };
+        assert_eq!(sampling_settings(&nucleus, 0.95).unwrap(), (SamplingMode::TopPMinP, 0.8, 0.1));
+        assert!(sampling_settings(&GenerateRequest { top_p: Some(1.5), ..nucleus }, 0.95).is_err());
+
+        let parsed: SamplingMode = serde_json::from_value(serde_json::json!({ "mode": "mirostat", "tau": 3.0, "eta": 0.2 })).unwrap();
+        assert_eq!(parsed, SamplingMode::Mirostat { tau: 3.0, eta: 0.2 });
+        let parsed: SamplingMode = serde_json::from_value(serde_json::json!({ "mode": "top_p" })).unwrap();
+        assert_eq!(parsed, SamplingMode::TopPMinP);
+    }
+
+    #[test]
+    fn test_mirostat_truncates_and_adapts() {
+        // Zipf-like logits over 200 tokens: p_i ∝ 1 / (i + 1)^1.2.
+        let logits: Vec<f32> = (0..200).map(|i| -1.2 * ((i + 1) as f32).ln()).collect();
+        let candidates: Vec<(LlamaToken, f32)> = logits.iter().enumerate().map(|(i, &l)| (i as LlamaToken, l)).collect();
+        for mode in [SamplingMode::Mirostat { tau: 3.0, eta: 0.1 }, SamplingMode::MirostatV2 { tau: 3.0, eta: 0.1 }] {
+            let mut mirostat = Mirostat::new(mode, 200).unwrap();
+            let mut kept = candidates.clone();
+            mirostat.truncate(&mut kept);
+            assert!(!kept.is_empty() && kept.len() < 200, "{:?} kept {}", mode, kept.len());
+            assert_eq!(kept[0].0, 0);
+            // A surprising (unlikely) pick lowers mu, narrowing the next cut.
+            let mu = mirostat.mu;
+            mirostat.update(&kept, kept.last().unwrap().0);
+            assert!(mirostat.mu < mu);
+            let mut narrower = candidates.clone();
+            mirostat.truncate(&mut narrower);
+            assert!(narrower.len() <= kept.len());
         }
+        assert!(Mirostat::new(SamplingMode::TopPMinP, 200).is_none());
+
+        // The sampler draws only from what Mirostat keeps, reproducibly per seed.
+        let config = SamplerConfig {
+            top_p: 1.0,
+            min_p: 0.0,
+            temperature: 1.0,
+            seed: 7,
+            max_tokens: 0,
+            logit_bias: Vec::new(),
+            rng_state: None,
+            penalties: None,
+            mode: SamplingMode::MirostatV2 { tau: 1.0, eta: 0.1 },
+        };
+        let draws = |config: &SamplerConfig| {
+            let mut sampler = config.build(logits.len() as i32, None);
+            (0..20).map(|_| sampler.select_from_logits(&logits).unwrap()).collect::<Vec<_>>()
+        };
+        let picked = draws(&config);
+        assert!(picked.iter().all(|&t| t < 10), "{:?}", picked);
+        assert_eq!(draws(&config), picked);
+    }
 
-        let duration_ms = start.elapsed().as_millis() as u64;
-        let output = String::from_utf8_lossy(&output_bytes).into_owned();
+    #[test]
+    fn test_resolve_logit_bias_reports_multi_token_keys() {
+        let bias: HashMap<String, f32> = [("yes", 5.0), ("no", -5.0), ("maybe", 1.0), ("", 1.0)]
+            .into_iter()
+            .map(|(k, v)| (k.to_string(), v))
+            .collect();
+        // Fake tokenizer: one token per character.
+        let (resolved, unmapped) = resolve_logit_bias(Some(&bias), |s| Ok(s.chars().map(|c| c as LlamaToken).collect()));
+        assert!(resolved.is_empty());
+        assert_eq!(unmapped, vec!["".to_string(), "maybe".to_string(), "no".to_string(), "yes".to_string()]);
+
+        let (resolved, unmapped) = resolve_logit_bias(Some(&bias), |s| match s {
+            "yes" => Ok(vec![7]),
+            "no" => Ok(vec![3]),
+            "maybe" => Err("tokenizer error".to_string()),
+            _ => Ok(vec![]),
+        });
+        assert_eq!(resolved, vec![(3, -5.0), (7, 5.0)]);
+        assert_eq!(unmapped, vec!["".to_string(), "maybe".to_string()]);
+        assert_eq!(resolve_logit_bias(None, |_| Ok(vec![1])), (Vec::new(), Vec::new()));
+    }
 
-        Self::log(&format!("generate_fast: {} tokens in {}ms", tokens_generated, duration_ms));
+    #[test]
+    fn test_apply_token_bias_validates_ids() {
+        let ids: HashMap<LlamaToken, f32> = [(7, -100.0), (0, 2.0)].into_iter().collect();
+        let merged = apply_token_bias(vec![(3, -5.0), (7, 5.0)], Some(&ids), 8).unwrap();
+        // The id entry replaces the string-resolved one for token 7.
+        assert_eq!(merged, vec![(0, 2.0), (3, -5.0), (7, -100.0)]);
+        assert_eq!(apply_token_bias(vec![(3, 1.0)], None, 8).unwrap(), vec![(3, 1.0)]);
+
+        let out_of_range: HashMap<LlamaToken, f32> = [(8, 1.0)].into_iter().collect();
+        assert!(apply_token_bias(Vec::new(), Some(&out_of_range), 8).unwrap_err().contains("id 8 is outside the vocabulary (0–7)"));
+        let negative: HashMap<LlamaToken, f32> = [(-1, 1.0)].into_iter().collect();
+        assert!(apply_token_bias(Vec::new(), Some(&negative), 8).is_err());
+
+        // JSON object keys are strings; serde parses them as ids.
+        let request: GenerateRequest = serde_json::from_str(r#"{"prompt": "hi", "token_bias": {"42": -100}}"#).unwrap();
+        assert_eq!(request.token_bias.unwrap()[&42], -100.0);
+    }
 
-        Ok(GenerateResponse {
-            text: output,
-            tokens_generated,
-            duration_ms,
-        })
+    #[test]
+    fn test_negative_logit_bias_suppresses_token() {
+        let logits = [0.0, 10.0, 0.0, 0.0];
+        let mut config = SamplerConfig { top_p: 0.95, min_p: 0.05, temperature: 0.7, seed: 42, max_tokens: 1, logit_bias: Vec::new(), rng_state: None, penalties: None, mode: SamplingMode::TopPMinP };
+        assert_eq!(config.build(logits.len() as i32, None).select_from_logits(&logits), Some(1));
+
+        config.logit_bias = vec![(1, -100.0)];
+        for seed in 0..20 {
+            config.seed = seed;
+            let picked = config.build(logits.len() as i32, None).select_from_logits(&logits);
+            assert!(matches!(picked, Some(0 | 2 | 3)), "picked {:?}", picked);
+        }
     }
 
-    /// Generate embeddings for a batch of texts using the loaded embedding model.
-    /// Blocking — runs forward pass for each input text synchronously.
-    pub fn embed(&self, request: EmbedRequest) -> Result<EmbedResponse, String> {
-        Self::log(&format!("embed() entered, {} inputs", request.input.len()));
+    #[test]
+    fn test_sampler_rng_serializes_as_hex() {
+        let mut rng = SamplerRng::seeded(42);
+        rng.next_u64();
+        let json = serde_json::to_string(&rng).unwrap();
+        assert_eq!(json.len(), 18);
+        assert_eq!(serde_json::from_str::<SamplerRng>(&json).unwrap(), rng);
+        assert!(serde_json::from_str::<SamplerRng>("\"12\"").is_err());
+        assert!(serde_json::from_str::<SamplerRng>("\"zzzzzzzzzzzzzzzz\"").is_err());
+    }
 
-        let model = self
-            .embedding_model
-            .as_ref()
-            .ok_or("No embedding model loaded")?;
-        let backend = self
-            .backend
-            .as_ref()
-            .ok_or("BitNet.cpp backend not initialized")?;
+    #[test]
+    fn test_draw_token_follows_weights() {
+        let even = [(5, 0.0), (9, 0.0)];
+        assert_eq!(draw_token(&even, 0.25), Some(5));
+        assert_eq!(draw_token(&even, 0.75), Some(9));
+        assert_eq!(draw_token(&[(5, f32::NEG_INFINITY), (9, 0.0)], 0.0), Some(9));
+        assert_eq!(draw_token(&[], 0.5), None);
+    }
 
-        let start = std::time::Instant::now();
-        let n_embd = model.n_embd() as u32;
-        let mut all_embeddings = Vec::with_capacity(request.input.len());
+    #[test]
+    fn test_parse_backend_log_lines() {
+        let metal = parse_gpu_device("ggml_metal_init: found device: Apple M2 Pro").unwrap();
+        assert_eq!((metal.backend, metal.name.as_str()), ("metal", "Apple M2 Pro"));
+        let cuda = parse_gpu_device("  Device 0: NVIDIA GeForce RTX 4070, compute capability 8.9, VMM: yes").unwrap();
+        assert_eq!((cuda.backend, cuda.name.as_str()), ("cuda", "NVIDIA GeForce RTX 4070"));
+        let newer = parse_gpu_device("llama_load_model_from_file: using device CUDA0 (NVIDIA GeForce RTX 4070) - 11094 MiB free");
+        assert_eq!(newer, Some(cuda));
+        assert_eq!(parse_gpu_device("llm_load_tensors: CPU_Mapped model buffer size = 1252.41 MiB"), None);
+
+        assert_eq!(parse_offloaded_layers("llm_load_tensors: offloaded 23/33 layers to GPU"), Some(23));
+        assert_eq!(parse_offloaded_layers("load_tensors: offloaded 0/29 layers to GPU"), Some(0));
+        assert_eq!(parse_offloaded_layers("llm_load_tensors: offloading 23 repeating layers to GPU"), None);
+    }
 
-        for (text_idx, text) in request.input.iter().enumerate() {
-            Self::log(&format!(
-                "embed: processing input {}/{} ({} chars)",
-                text_idx + 1,
-                request.input.len(),
-                text.len()
-            ));
+    #[test]
+    fn test_flash_attention_blocker() {
+        assert_eq!(flash_attention_blocker(Some("llama"), Some("128"), Some("128")), None);
+        assert_eq!(flash_attention_blocker(Some("qwen2"), None, None), None);
+        assert!(flash_attention_blocker(Some("grok"), None, None).unwrap().contains("Grok"));
+        assert!(flash_attention_blocker(Some("deepseek2"), Some("192"), Some("128")).unwrap().contains("192 and 128"));
+    }
 
-            // Create embedding context per input (with mean pooling for sentence embeddings)
-            let ctx_params = LlamaContextParams::default()
-                .with_embeddings(true)
-                .with_n_ctx(NonZeroU32::new(2048));
-            let mut ctx = model
-                .new_context(backend, ctx_params)
-                .map_err(|e| format!("Failed to create embedding context: {}", e))?;
+    #[test]
+    fn test_restored_sampler_state_continues_sequence() {
+        let logits = [1.0, 1.2, 0.8, 1.1, 0.9];
+        let config = SamplerConfig { top_p: 1.0, min_p: 0.0, temperature: 1.0, seed: 7, max_tokens: 0, logit_bias: Vec::new(), rng_state: None, penalties: None, mode: SamplingMode::TopPMinP };
+        let mut uninterrupted = config.build(logits.len() as i32, None);
+        let expected: Vec<_> = (0..40).map(|_| uninterrupted.select_from_logits(&logits)).collect();
+
+        let mut first = config.build(logits.len() as i32, None);
+        let mut resumed: Vec<_> = (0..15).map(|_| first.select_from_logits(&logits)).collect();
+        let saved = serde_json::to_value(first.rng).unwrap();
+        let restored = SamplerConfig { rng_state: Some(serde_json::from_value(saved).unwrap()), ..config };
+        let mut second = restored.build(logits.len() as i32, None);
+        resumed.extend((15..40).map(|_| second.select_from_logits(&logits)));
+        assert_eq!(resumed, expected);
+    }
 
-            let tokens = model
-                .str_to_token(text, AddBos::Always)
-                .map_err(|e| format!("Tokenization failed: {}", e))?;
+    #[test]
+    fn test_sampler_state_requires_explicit_session() {
+        let runtime = NativeRuntime::new();
+        assert!(runtime.sampler_state("agent-1").unwrap_err().contains("No sampler state"));
+        assert!(runtime.sampler_state(DEFAULT_SESSION_ID).is_err());
+
+        let state = SamplerState {
+            version: SAMPLER_STATE_VERSION,
+            algorithm: SAMPLER_RNG_ALGORITHM.to_string(),
+            rng: SamplerRng::seeded(3),
+            seed: 3,
+            draws: 12,
+            sampler: "top_p=0.95".to_string(),
+            updated_at_ms: 0,
+        };
+        assert!(runtime.set_sampler_state(DEFAULT_SESSION_ID, state.clone()).is_err());
+        assert!(runtime.set_sampler_state("agent-1", SamplerState { version: 2, ..state.clone() }).is_err());
+        runtime.set_sampler_state("agent-1", state.clone()).unwrap();
+        let stored = runtime.sampler_state("agent-1").unwrap();
+        assert_eq!((stored.rng, stored.draws), (state.rng, 12));
+    }
 
-            Self::log(&format!("embed: tokenized {} tokens", tokens.len()));
+    #[test]
+    fn test_char_budget_cuts_at_codepoint_boundary() {
+        // "héllo" fed one byte at a time, as tokens can split codepoints.
+        let mut budget = CharBudget::new(2);
+        let mut out = Vec::new();
+        let bytes = "héllo".as_bytes();
+        out.push(bytes[0]);
+        assert!(!budget.exhausted(&mut out));
+        out.push(bytes[1]); // first byte of 'é'
+        assert!(!budget.exhausted(&mut out));
+        out.push(bytes[2]); // 'é' complete: 2 chars
+        assert!(budget.exhausted(&mut out));
+        assert_eq!(out, "hé".as_bytes());
+
+        // A multi-char token overshooting the budget is truncated.
+        let mut budget = CharBudget::new(3);
+        let mut out = "ab日本語".as_bytes().to_vec();
+        assert!(budget.exhausted(&mut out));
+        assert_eq!(String::from_utf8(out).unwrap(), "ab日");
+
+        let mut out = b"anything".to_vec();
+        assert!(CharBudget::new(0).exhausted(&mut out));
+        assert!(out.is_empty());
+    }
 
-            if tokens.is_empty() {
-                all_embeddings.push(vec![0.0f32; n_embd as usize]);
-                continue;
-            }
+    #[test]
+    fn test_stop_buffer() {
+        let stops = vec!["</s>".to_string(), "\nUser:".to_string()];
+        let mut out = StopBuffer::new(&stops);
+        assert!(out.push(b"Hello</s>"));
+        assert_eq!(out.bytes, b"Hello");
+
+        let mut out = StopBuffer::new(&stops);
+        assert!(!out.push(b"Hello"));
+        assert_eq!((out.bytes.as_slice(), out.held), (&b"Hello"[..], 0));
+        let mut out = StopBuffer::new::<String>(&[]);
+        assert!(!out.push(b"Hello</s>"));
+        assert_eq!(out.bytes, b"Hello</s>");
+
+        // A stop sequence ending mid-piece cuts everything after it too.
+        let mut out = StopBuffer::new(&stops);
+        assert!(!out.push(b"Hi</"));
+        assert!(out.push(b"s> and more"));
+        assert_eq!(out.bytes, b"Hi");
+    }
 
-            // Safety: truncate tokens to fit within the embedding context window (2048).
-            let embed_ctx_size: usize = 2048;
-            let tokens = if tokens.len() > embed_ctx_size {
-                Self::log(&format!(
-                    "embed: TRUNCATING {} tokens -> {}",
-                    tokens.len(),
-                    embed_ctx_size
-                ));
-                tokens[..embed_ctx_size].to_vec()
-            } else {
-                tokens
-            };
+    #[test]
+    fn test_stop_buffer_holds_back_split_stop_sequence() {
+        let mut out = StopBuffer::new(&["\n\n"]);
+        assert!(!out.push(b"Hello\n"));
+        // The newline might start "\n\n", so it isn't final yet.
+        assert_eq!(out.held, 1);
+        assert!(out.push(b"\nWorld"));
+        assert_eq!(out.bytes, b"Hello");
+        assert_eq!(out.held, 0);
+
+        // A lone newline that isn't followed by another is released.
+        let mut out = StopBuffer::new(&["\n\n"]);
+        assert!(!out.push(b"a\n"));
+        assert!(!out.push(b"b"));
+        assert_eq!((out.bytes.as_slice(), out.held), (&b"a\nb"[..], 0));
+    }
 
-            // Chunked prefill: decode tokens in batches of CHUNK_SIZE
-            let chunk_size: usize = 512;
-            let total_tokens = tokens.len();
-            Self::log(&format!(
-                "embed: chunked prefill, {} tokens in chunks of {}",
-                total_tokens, chunk_size
-            ));
+    #[test]
+    fn test_draft_model_requires_reasoning_model() {
+        let mut runtime = NativeRuntime::new();
+        assert!(runtime.load_draft_model(PathBuf::from("/nonexistent/draft.gguf")).unwrap_err().contains("not found"));
+        assert!(!runtime.has_draft_model());
+    }
 
-            ctx.clear_kv_cache();
+    #[test]
+    fn test_synthetic_text() {
+        for len in [1, 17, 500] {
+            assert_eq!(synthetic_text(3, len).len(), len);
+        }
+        assert_ne!(synthetic_text(0, 40), synthetic_text(1, 40));
+        assert_eq!(synthetic_text(2, 40), synthetic_text(2, 40));
+    }
 
-            let mut pos: i32 = 0;
-            for (chunk_idx, chunk) in tokens.chunks(chunk_size).enumerate() {
-                let is_last_chunk = (chunk_idx + 1) * chunk_size >= total_tokens;
-                let mut batch = LlamaBatch::new(chunk.len().max(512), 1);
+    #[test]
+    fn test_benchmark_embedding_validates_input() {
+        let runtime = NativeRuntime::new();
+        assert!(runtime.benchmark_embedding(0, 100).unwrap_err().contains("batch_size"));
+        assert!(runtime.benchmark_embedding(8, 0).unwrap_err().contains("text_len"));
+        assert!(runtime.benchmark_embedding(8, 100).unwrap_err().contains("No embedding model"));
+    }
 
-                for (i, token) in chunk.iter().enumerate() {
-                    let is_last_token = is_last_chunk && i == chunk.len() - 1;
-                    batch
-                        .add(*token, pos, &[0], is_last_token)
-                        .map_err(|e| format!("Embed batch add failed: {}", e))?;
-                    pos += 1;
-                }
+    #[test]
+    fn test_plan_embed_sub_batch() {
+        // Plenty of RAM: bounded by input count, then by the hard cap.
+        assert_eq!(plan_embed_sub_batch(64 * 1024, 64, 12, 768, 10), 10);
+        assert_eq!(plan_embed_sub_batch(64 * 1024, 64, 12, 768, 1000), MAX_EMBED_SUB_BATCH);
+        // Constrained RAM with long inputs shrinks the sub-batch, never below 1.
+        let constrained = plan_embed_sub_batch(512, 2048, 12, 768, 1000);
+        assert!(constrained >= 1 && constrained < MAX_EMBED_SUB_BATCH);
+        assert_eq!(plan_embed_sub_batch(0, 2048, 12, 768, 1000), 1);
+        assert_eq!(plan_embed_sub_batch(1024, 0, 12, 768, 0), 1);
+    }
 
-                Self::log(&format!(
-                    "embed: decoding chunk {} ({} tokens, pos={})",
-                    chunk_idx,
-                    chunk.len(),
-                    pos
-                ));
-                ctx.decode(&mut batch)
-                    .map_err(|e| format!("Embed decode chunk {} failed: {}", chunk_idx, e))?;
-            }
+    #[test]
+    fn test_embed_context_tokens() {
+        let tokenized: Vec<Vec<LlamaToken>> = vec![vec![1; 3], vec![1; 5], vec![], vec![1; 4], vec![1; 1]];
+        assert_eq!(embed_context_tokens(&tokenized, 2), 5);
+        assert_eq!(embed_context_tokens(&tokenized, 3), 8);
+        assert_eq!(embed_context_tokens(&tokenized, 0), 5);
+        assert_eq!(embed_context_tokens(&[], 4), 0);
+    }
 
-            Self::log("embed: decode OK, extracting embeddings...");
+    /// Needs a real model: set SEMBLANCE_TEST_EMBED_GGUF to a small embedding
+    /// GGUF. Embedding 100 short strings in one request (one shared context)
+    /// should beat one request per string (a context each), and agree with it.
+    #[test]
+    fn test_batched_embed_is_faster_than_one_context_per_input() {
+        let Ok(path) = std::env::var("SEMBLANCE_TEST_EMBED_GGUF") else {
+            return;
+        };
+        let mut runtime = NativeRuntime::new();
+        runtime.load_embedding_model(PathBuf::from(path)).unwrap();
+        let input: Vec<String> = (0..100).map(|i| format!("note {}: buy milk and eggs", i)).collect();
+        let request = |input: Vec<String>| EmbedRequest { model_path: String::new(), input, n_ctx: None, pooling: None };
+        runtime.embed(request(vec![input[0].clone()])).unwrap();
+
+        let start = Instant::now();
+        let one_by_one: Vec<Vec<f32>> = input
+            .iter()
+            .map(|text| runtime.embed(request(vec![text.clone()])).unwrap().embeddings.remove(0))
+            .collect();
+        let unbatched = start.elapsed();
+        let start = Instant::now();
+        let batched = runtime.embed(request(input)).unwrap();
+        let batched_time = start.elapsed();
+
+        assert!(
+            batched_time < unbatched,
+            "embed 100 inputs: batched {:?}, one context per input {:?}",
+            batched_time,
+            unbatched
+        );
+        for (a, b) in batched.embeddings.iter().zip(&one_by_one) {
+            let cosine: f32 = a.iter().zip(b).map(|(x, y)| x * y).sum();
+            assert!(cosine > 0.99, "cosine {}", cosine);
+        }
+    }
 
-            let embedding = ctx
-                .embeddings_seq_ith(0)
-                .map_err(|e| format!("Failed to get embeddings: {}", e))?;
+    #[test]
+    fn test_pooling_mode_parse() {
+        let request: EmbedRequest = serde_json::from_value(serde_json::json!({ "input": ["a"], "pooling": "cls" })).unwrap();
+        assert_eq!(request.pooling, Some(PoolingMode::Cls));
+        assert_eq!(request.pooling.unwrap().pooling_type(), PoolingType::Cls);
+        let request: EmbedRequest = serde_json::from_value(serde_json::json!({ "input": ["a"] })).unwrap();
+        assert_eq!(request.pooling, None);
+        assert!(serde_json::from_value::<EmbedRequest>(serde_json::json!({ "input": ["a"], "pooling": "max" })).is_err());
+    }
 
-            // L2 normalize the embedding vector
-            let magnitude = embedding
-                .iter()
-                .fold(0.0f32, |acc, &v| v.mul_add(v, acc))
-                .sqrt();
-            let normalized: Vec<f32> = if magnitude > 0.0 {
-                embedding.iter().map(|&v| v / magnitude).collect()
-            } else {
-                embedding.to_vec()
-            };
+    /// Needs a real model: set SEMBLANCE_TEST_EMBED_GGUF to a small embedding GGUF.
+    #[test]
+    fn test_cls_and_mean_pooling_differ() {
+        let Ok(path) = std::env::var("SEMBLANCE_TEST_EMBED_GGUF") else {
+            return;
+        };
+        let mut runtime = NativeRuntime::new();
+        runtime.load_embedding_model(PathBuf::from(path)).unwrap();
+        let embed = |pooling| {
+            let request = EmbedRequest { model_path: String::new(), input: vec!["The quick brown fox".to_string()], n_ctx: None, pooling: Some(pooling) };
+            runtime.embed(request).unwrap().embeddings.remove(0)
+        };
+        let (cls, mean) = (embed(PoolingMode::Cls), embed(PoolingMode::Mean));
+        assert_eq!(cls.len(), mean.len());
+        assert!(cls.iter().zip(&mean).any(|(a, b)| (a - b).abs() > 1e-4));
 
-            Self::log(&format!(
-                "embed: input {}/{} done (dim={})",
-                text_idx + 1,
-                request.input.len(),
-                normalized.len()
-            ));
-            all_embeddings.push(normalized);
-        }
+        let none = EmbedRequest { model_path: String::new(), input: vec!["fox".to_string()], n_ctx: None, pooling: Some(PoolingMode::None) };
+        assert!(runtime.embed(none).is_err());
+    }
 
-        let duration_ms = start.elapsed().as_millis() as u64;
-        Self::log(&format!(
-            "embed: all {} inputs done in {}ms",
-            all_embeddings.len(),
-            duration_ms
-        ));
+    #[test]
+    fn test_l2_normalize() {
+        let v = l2_normalize(&[3.0, 4.0]);
+        assert!((v[0] - 0.6).abs() < 1e-6 && (v[1] - 0.8).abs() < 1e-6);
+        assert_eq!(l2_normalize(&[0.0, 0.0]), vec![0.0, 0.0]);
+    }
 
-        Ok(EmbedResponse {
-            embeddings: all_embeddings,
-            dimensions: n_embd,
-            duration_ms,
-        })
+    #[test]
+    fn test_reject_non_finite_embeddings() {
+        // A NaN anywhere survives normalization, and an infinite component
+        // normalizes to NaN — both must be caught after normalization.
+        let mut embeddings = vec![
+            l2_normalize(&[3.0, 4.0]),
+            l2_normalize(&[1.0, f32::NAN]),
+            l2_normalize(&[f32::INFINITY, 1.0]),
+            vec![f32::NEG_INFINITY, 0.0],
+            vec![0.0, 0.0],
+        ];
+        assert_eq!(reject_non_finite(&mut embeddings), vec![1, 2, 3]);
+        assert!(embeddings[1].is_empty() && embeddings[2].is_empty() && embeddings[3].is_empty());
+        assert_eq!(embeddings[0].len(), 2);
+        assert_eq!(embeddings[4], vec![0.0, 0.0]);
     }
 
-    /// Unload the reasoning model to free memory.
-    pub fn unload_reasoning_model(&mut self) {
-        self.reasoning_model = None;
-        self.reasoning_model_path = None;
-        if self.embedding_model.is_some() {
-            self.status = RuntimeStatus::Ready;
-        } else {
-            self.status = RuntimeStatus::Uninitialized;
-        }
+    #[test]
+    fn test_plan_warm_evictions_is_lru() {
+        let now = Instant::now();
+        let ago = |secs| now - Duration::from_secs(secs);
+        const GB: u64 = 1024 * 1024 * 1024;
+        let warm = [("a", 4 * GB, ago(30)), ("b", 8 * GB, ago(10)), ("c", 4 * GB, ago(20))];
+        // Active + 3 warm with room for 3: the least recently used goes.
+        assert_eq!(plan_warm_evictions(&warm, 5 * GB, 3, None), vec!["a"]);
+        assert_eq!(plan_warm_evictions(&warm, 5 * GB, 1, None), vec!["a", "c", "b"]);
+        assert!(plan_warm_evictions(&warm, 5 * GB, 4, None).is_empty());
+        // 21 GB resident against an 18 GB budget: evicting "a" (4 GB) is enough.
+        assert_eq!(plan_warm_evictions(&warm, 5 * GB, 4, Some(18 * GB)), vec!["a"]);
+        // The active model alone over budget evicts every warm one but stays.
+        assert_eq!(plan_warm_evictions(&warm, 20 * GB, 4, Some(18 * GB)).len(), 3);
+        assert!(plan_warm_evictions::<&str>(&[], 20 * GB, 1, Some(GB)).is_empty());
     }
 
-    /// Unload the embedding model to free memory.
-    pub fn unload_embedding_model(&mut self) {
-        self.embedding_model = None;
-        self.embedding_model_path = None;
+    #[test]
+    fn test_resident_models_default_to_single() {
+        let mut runtime = NativeRuntime::new();
+        let resident = runtime.resident_models();
+        assert_eq!((resident.max_resident, resident.budget_mb, resident.models.len()), (1, None, 0));
+        // With routing off, any model_path is served by the active model.
+        assert!(runtime.can_route_reasoning("/models/other.gguf"));
+        let resident = runtime.set_resident_models(9, Some(16384));
+        assert_eq!((resident.max_resident, resident.budget_mb), (MAX_RESIDENT_REASONING, Some(16384)));
+        assert!(runtime.can_route_reasoning(""));
+        assert!(!runtime.can_route_reasoning("/models/other.gguf"));
+        // A path that isn't resident fails instead of falling back to the active model.
+        let err = runtime.route_reasoning("/models/other.gguf").err().unwrap();
+        assert!(err.to_string().contains("not loaded"), "{}", err);
+        assert_eq!(runtime.route_reasoning("").err(), Some(RuntimeError::NoReasoningModel));
+        assert_eq!(runtime.set_resident_models(0, None).max_resident, 1);
+        assert_eq!(runtime.route_reasoning("/models/other.gguf").err(), Some(RuntimeError::NoReasoningModel));
     }
 
-    /// Get the current runtime status.
-    pub fn status(&self) -> &RuntimeStatus {
-        &self.status
+    #[test]
+    fn test_progress_step_reports_each_percent_once() {
+        let reported = AtomicU32::new(0);
+        assert!(progress_step(&reported, 0.0));
+        assert!(!progress_step(&reported, 0.004));
+        assert!(progress_step(&reported, 0.013));
+        assert!(!progress_step(&reported, 0.019));
+        // Out-of-order or repeated calls never go backwards.
+        assert!(!progress_step(&reported, 0.005));
+        assert!(progress_step(&reported, 1.0));
+        assert!(!progress_step(&reported, 1.0));
     }
 
-    /// Check if a reasoning model is loaded.
-    pub fn has_reasoning_model(&self) -> bool {
-        self.reasoning_model.is_some()
+    #[test]
+    fn test_prefix_key_detects_changes() {
+        let system: Vec<LlamaToken> = (0..100).collect();
+        let key = PrefixKey::new(&system, 4096, None);
+        assert_eq!(key, PrefixKey::new(&system, 4096, None));
+        assert_eq!(key.len, 100);
+
+        let mut edited = system.clone();
+        edited[50] = 7;
+        assert_ne!(key, PrefixKey::new(&edited, 4096, None));
+        assert_ne!(key, PrefixKey::new(&system[..99], 4096, None));
+        assert_ne!(key, PrefixKey::new(&system, 8192, None));
+        assert_ne!(key, PrefixKey::new(&system, 4096, Some(true)));
     }
 
-    /// Check if an embedding model is loaded.
-    pub fn has_embedding_model(&self) -> bool {
-        self.embedding_model.is_some()
+    #[test]
+    fn test_shared_prefix_len() {
+        assert_eq!(shared_prefix_len(&[1, 2, 3, 4], &[1, 2, 3, 9, 9]), 3);
+        assert_eq!(shared_prefix_len(&[1, 2], &[1, 2, 3]), 2);
+        assert_eq!(shared_prefix_len(&[5], &[1]), 0);
+        assert!(!NativeRuntime::new().reset_session("chat"));
     }
 
-    /// Get reasoning model path if loaded.
-    pub fn reasoning_model_path(&self) -> Option<&PathBuf> {
-        self.reasoning_model_path.as_ref()
+    #[test]
+    fn test_clamp_n_ctx_and_context_fit() {
+        assert_eq!(clamp_n_ctx(None, 4096, 32768), (4096, false));
+        assert_eq!(clamp_n_ctx(Some(16384), 4096, 32768), (16384, false));
+        assert_eq!(clamp_n_ctx(Some(65536), 4096, 32768), (32768, true));
+        assert_eq!(clamp_n_ctx(None, 4096, 2048), (2048, false));
+        assert_eq!(clamp_n_ctx(Some(65536), 4096, 0), (65536, false));
+
+        let mut tokens: Vec<LlamaToken> = (0..3584).collect();
+        assert!(fit_context(&mut tokens, 512, 4096, 0, false).is_ok());
+        let mut tokens: Vec<LlamaToken> = (0..3600).collect();
+        let overflow = fit_context(&mut tokens, 512, 4096, 0, false).unwrap_err();
+        assert_eq!(overflow, ContextOverflow { prompt_tokens: 3600, max_tokens: 512, n_ctx: 4096 });
+        assert!(overflow.to_string().contains("3600 prompt tokens + 512 max_tokens"));
+        assert_eq!(tokens.len(), 3600);
+        assert!(fit_context(&mut tokens, 512, 4096, 100, true).is_ok());
+        assert_eq!(tokens.len(), 3584);
+        // The system prompt and the latest tokens survive; the middle goes.
+        assert_eq!((tokens[99], tokens[100]), (99, 116));
+        assert_eq!(tokens.last(), Some(&3599));
+        assert!(fit_context(&mut tokens, 4096, 4096, 0, true).is_err());
     }
 
-    /// Get embedding model path if loaded.
-    pub fn embedding_model_path(&self) -> Option<&PathBuf> {
-        self.embedding_model_path.as_ref()
+    #[test]
+    fn test_truncate_middle() {
+        let mut tokens: Vec<LlamaToken> = (0..10).collect();
+        truncate_middle(&mut tokens, 0, 4);
+        assert_eq!(tokens, vec![0, 7, 8, 9]);
+        // A system prompt longer than half the budget keeps only half.
+        let mut tokens: Vec<LlamaToken> = (0..10).collect();
+        truncate_middle(&mut tokens, 6, 4);
+        assert_eq!(tokens, vec![0, 1, 8, 9]);
+        let mut tokens: Vec<LlamaToken> = (0..3).collect();
+        truncate_middle(&mut tokens, 1, 4);
+        assert_eq!(tokens, vec![0, 1, 2]);
     }
-}
 
-/// Thread-safe wrapper for NativeRuntime.
-pub type SharedNativeRuntime = Arc<Mutex<NativeRuntime>>;
+    #[test]
+    fn test_check_prompt_tokens() {
+        let mut tokens: Vec<LlamaToken> = (0..10).collect();
+        assert!(check_prompt_tokens(&mut tokens, 10, 0, false).is_ok());
+        let err = check_prompt_tokens(&mut tokens, 8, 0, false).unwrap_err();
+        assert_eq!(err, PromptTooLarge { prompt_tokens: 10, limit: 8 });
+        assert!(err.to_string().contains("10 tokens, limit is 8"));
+        assert_eq!(tokens.len(), 10);
+        assert!(check_prompt_tokens(&mut tokens, 8, 2, true).is_ok());
+        assert_eq!(tokens, vec![0, 1, 4, 5, 6, 7, 8, 9]);
+        assert_eq!(NativeRuntime::new().prompt_token_limit().effective, None);
+    }
 
-/// Create a new shared NativeRuntime instance.
-pub fn create_runtime() -> SharedNativeRuntime {
-    Arc::new(Mutex::new(NativeRuntime::new()))
-}
+    fn penalty_request(prompt: &str, repeat_penalty: Option<f32>) -> GenerateRequest {
+        GenerateRequest {
+            prompt: prompt.to_string(),
+            max_tokens: Some(96),
+            temperature: Some(0.0),
+            repeat_penalty,
+            ..Default::default()
+        }
+    }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+    #[test]
+    fn test_sampler_penalties_from_request() {
+        assert_eq!(SamplerPenalties::from_request(&penalty_request("", None), 4096, 13).unwrap(), None);
+        assert_eq!(SamplerPenalties::from_request(&penalty_request("", Some(1.0)), 4096, 13).unwrap(), None);
+        let p = SamplerPenalties::from_request(&penalty_request("", Some(1.3)), 4096, 13).unwrap().unwrap();
+        assert_eq!((p.last_n, p.repeat, p.newline), (DEFAULT_REPEAT_LAST_N, 1.3, 13));
+
+        let whole = GenerateRequest { repeat_last_n: Some(-1), frequency_penalty: Some(0.5), ..penalty_request("", None) };
+        assert_eq!(SamplerPenalties::from_request(&whole, 2048, 13).unwrap().unwrap().last_n, 2048);
+        let off = GenerateRequest { repeat_last_n: Some(0), ..penalty_request("", Some(1.3)) };
+        assert_eq!(SamplerPenalties::from_request(&off, 4096, 13).unwrap(), None);
+        assert!(SamplerPenalties::from_request(&penalty_request("", Some(0.0)), 4096, 13).is_err());
+        let bad_window = GenerateRequest { repeat_last_n: Some(-2), ..penalty_request("", Some(1.3)) };
+        assert!(SamplerPenalties::from_request(&bad_window, 4096, 13).is_err());
+    }
 
-    #[tokio::test]
-    async fn test_new_runtime_is_uninitialized() {
-        let runtime = NativeRuntime::new();
-        assert!(matches!(runtime.status(), RuntimeStatus::Uninitialized));
-        assert!(!runtime.has_reasoning_model());
-        assert!(!runtime.has_embedding_model());
+    /// Needs a real model: set SEMBLANCE_TEST_GGUF to a small GGUF (a tiny
+    /// chat model that loops under greedy decoding is ideal).
+    #[test]
+    fn test_repeat_penalty_reduces_repeated_trigrams() {
+        let Ok(path) = std::env::var("SEMBLANCE_TEST_GGUF") else {
+            return;
+        };
+        let mut runtime = NativeRuntime::new();
+        runtime.load_reasoning_model(PathBuf::from(path)).unwrap();
+        let repeated_trigrams = |text: &str| {
+            let words: Vec<&str> = text.split_whitespace().collect();
+            let mut seen = std::collections::HashSet::new();
+            words.windows(3).filter(|w| !seen.insert(*w)).count()
+        };
+        let prompt = "Say \"I can help you\" over and over.";
+        let plain = runtime.generate(penalty_request(prompt, None)).unwrap();
+        let penalized = runtime.generate(penalty_request(prompt, Some(1.8))).unwrap();
+        assert!(
+            repeated_trigrams(&penalized.text) < repeated_trigrams(&plain.text),
+            "penalized: {:?}\nplain: {:?}",
+            penalized.text,
+            plain.text
+        );
     }
 
-    #[tokio::test]
-    async fn test_load_nonexistent_model_fails() {
+    #[test]
+    fn test_request_seed() {
+        let request = GenerateRequest { seed: Some(7), ..penalty_request("", None) };
+        assert_eq!(request_seed(&request, None), 7);
+        let unseeded = penalty_request("", None);
+        let state = SamplerState {
+            version: SAMPLER_STATE_VERSION,
+            algorithm: SAMPLER_RNG_ALGORITHM.to_string(),
+            rng: SamplerRng::seeded(3),
+            seed: 3,
+            draws: 10,
+            sampler: String::new(),
+            updated_at_ms: 0,
+        };
+        assert_eq!(request_seed(&unseeded, Some(&state)), 3);
+        let seeds: std::collections::HashSet<u32> = (0..8).map(|_| request_seed(&unseeded, None)).collect();
+        assert!(seeds.len() > 1, "unseeded generations should not share a seed");
+    }
+
+    /// Needs a real model: set SEMBLANCE_TEST_GGUF.
+    #[test]
+    fn test_seed_reproduces_generation() {
+        let Ok(path) = std::env::var("SEMBLANCE_TEST_GGUF") else {
+            return;
+        };
         let mut runtime = NativeRuntime::new();
-        let result = runtime.load_reasoning_model(PathBuf::from("/nonexistent/model.gguf"));
-        assert!(result.is_err());
-        assert!(result.unwrap_err().contains("not found"));
+        runtime.load_reasoning_model(PathBuf::from(path)).unwrap();
+        let seeded = |seed: u32, temperature: f32| GenerateRequest {
+            seed: Some(seed),
+            temperature: Some(temperature),
+            max_tokens: Some(48),
+            ..penalty_request("Write a short poem about the sea.", None)
+        };
+        let first = runtime.generate(seeded(1234, 0.0)).unwrap();
+        let second = runtime.generate(seeded(1234, 0.0)).unwrap();
+        assert_eq!(first.text, second.text);
+        let a = runtime.generate(seeded(1, 1.2)).unwrap();
+        let b = runtime.generate(seeded(2, 1.2)).unwrap();
+        assert_ne!(a.text, b.text);
+        assert_eq!(runtime.generate(seeded(1, 1.2)).unwrap().text, a.text);
     }
 
-    #[tokio::test]
-    async fn test_generate_without_model_fails() {
+    /// Needs a real model with an embedded chat template: set SEMBLANCE_TEST_GGUF.
+    #[test]
+    fn test_chat_prompt_uses_model_template() {
+        let Ok(path) = std::env::var("SEMBLANCE_TEST_GGUF") else {
+            return;
+        };
+        let mut runtime = NativeRuntime::new();
+        runtime.load_reasoning_model(PathBuf::from(path)).unwrap();
+        let model = runtime.reasoning_model.as_ref().unwrap();
+        let turn = |role: &str, content: &str| ChatMessage { role: role.to_string(), content: content.to_string() };
+        let request = GenerateRequest {
+            system_prompt: Some("Be brief.".to_string()),
+            messages: Some(vec![turn("user", "Hi"), turn("assistant", "Hello!"), turn("user", "What is 2 + 2?")]),
+            ..penalty_request("ignored", None)
+        };
+        let prompt = chat_prompt(model, &request, ChatFormat::ChatMl);
+        assert!(prompt.contains("Be brief.") && prompt.contains("Hello!") && prompt.contains("What is 2 + 2?"));
+        assert!(!prompt.contains("ignored"));
+        if model.chat_template().is_some() && render_chat_template(model, &sample_chat_turns()).ok {
+            assert_ne!(prompt, ChatFormat::ChatMl.render(&request_turns(&request)));
+        }
+
+        let raw = GenerateRequest { messages: None, ..request };
+        assert_eq!(chat_prompt(model, &raw, ChatFormat::Falcon3), "<|system|>\nBe brief.\n<|user|>\nignored\n<|assistant|>\n");
+    }
+
+    #[test]
+    fn test_chat_format_render() {
+        let turns = [("system", "Be brief."), ("user", "Hi"), ("assistant", "Hello!"), ("user", "2 + 2?")];
+        assert_eq!(
+            ChatFormat::ChatMl.render(&turns),
+            "<|im_start|>system\nBe brief.<|im_end|>\n<|im_start|>user\nHi<|im_end|>\n<|im_start|>assistant\nHello!<|im_end|>\n<|im_start|>user\n2 + 2?<|im_end|>\n<|im_start|>assistant\n"
+        );
+        assert_eq!(ChatFormat::Falcon3.render(&turns[1..2]), "<|user|>\nHi\n<|assistant|>\n");
+
+        let turn = |role: &str, content: &str| ChatMessage { role: role.to_string(), content: content.to_string() };
+        let request = GenerateRequest { messages: Some(vec![turn("user", "Hi"), turn("assistant", "Hello!")]), ..penalty_request("ignored", None) };
+        assert_eq!(request_turns(&request), vec![("user", "Hi"), ("assistant", "Hello!")]);
+        assert_eq!(request_turns(&penalty_request("Hi", None)), vec![("user", "Hi")]);
+    }
+
+    #[test]
+    fn test_require_no_model_loaded_without_models() {
         let runtime = NativeRuntime::new();
-        let result = runtime.generate(GenerateRequest {
-            model_path: String::new(),
-            prompt: "test".to_string(),
-            system_prompt: None,
-            max_tokens: None,
-            temperature: None,
-            stop: None,
-        });
-        assert!(result.is_err());
-        assert!(result.unwrap_err().contains("not ready"));
+        assert!(ModelKind::ALL.iter().all(|k| !runtime.is_model_loaded(*k)));
+        assert!(runtime.require_no_model_loaded(&ModelKind::ALL, "change the GPU device").is_ok());
+        assert_eq!(ModelKind::parse(" Vision ").unwrap(), ModelKind::Vision);
+        assert!(ModelKind::parse("tokenizer").is_err());
     }
 
-    #[tokio::test]
-    async fn test_embed_without_model_fails() {
+    #[test]
+    fn test_self_test_skips_unloaded_components() {
         let runtime = NativeRuntime::new();
-        let result = runtime.embed(EmbedRequest {
-            model_path: String::new(),
-            input: vec!["test".to_string()],
-        });
-        assert!(result.is_err());
+        let report = runtime.self_test();
+        assert!(report.passed);
+        assert_eq!(report.components.len(), 2);
+        assert!(report.components.iter().all(|c| c.status == SelfTestStatus::Skipped));
+    }
+
+    #[test]
+    fn test_self_test_output_checks() {
+        let gen = |text: &str, tokens: u32| GenerateResponse { text: text.to_string(), tokens_generated: tokens, duration_ms: 0, speculative: None, unmapped_logit_bias: Vec::new(), token_intervals_ms: None, first_token_ms: None, prompt_tokens: 0, prefill_ms: 0, decode_ms: 0, tokens_per_second: 0.0 };
+        assert!(check_generation_output(&gen("OK", 1)).is_ok());
+        assert!(check_generation_output(&gen("  ", 1)).is_err());
+        assert!(check_generation_output(&gen("O\u{FFFD}", 2)).is_err());
+
+        let emb = |v: Vec<f32>, dims: u32| EmbedResponse { embeddings: vec![v], dimensions: dims, duration_ms: 0, sub_batch_size: 1, sequences_clamped: false, failed_inputs: Vec::new(), failed_count: 0 };
+        assert!(check_embedding_output(&emb(vec![0.6, 0.8], 2)).is_ok());
+        assert!(check_embedding_output(&emb(vec![0.6, 0.8], 3)).is_err());
+        assert!(check_embedding_output(&emb(vec![f32::NAN, 0.8], 2)).is_err());
+        assert!(check_embedding_output(&emb(vec![0.0, 0.0], 2)).is_err());
     }
 
     #[tokio::test]
     async fn test_create_shared_runtime() {
         let shared = create_runtime();
-        let runtime = shared.lock().await;
+        let runtime = shared.read().await;
         assert!(matches!(runtime.status(), RuntimeStatus::Uninitialized));
     }
 }
@@ -0,0 +1,507 @@
+// Generation Scheduler — priority-then-FIFO admission of generation requests to the native runtime.
+//
+// Chat, proactive engine, digest, etc. all generate through one NativeRuntime.
+// Without a queue they race for the runtime lock in no particular order. Every
+// generation first takes a slot here: requests are admitted by priority, then
+// in arrival order, at most `max_concurrent` at a time. A running generation
+// that calls `OwnedGenerationPermit::yield_if_preempted` between tokens gives its
+// slot up while a higher-priority request is waiting for one, so interactive
+// chat doesn't wait behind background work. The queue is observable via
+// `snapshot()` so the UI can show what's waiting vs. running.
+
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::Notify;
+
+/// Upper bound for `set_max_concurrent`. Each concurrent generation holds its own
+/// llama.cpp context (KV cache), so memory grows linearly with slots.
+pub const MAX_GENERATION_SLOTS: usize = 8;
+
+/// Longest a preempted generation stays paused. After this it resumes even if
+/// that briefly exceeds `max_concurrent`: while paused it still holds the
+/// runtime read lock, so waiting forever could deadlock against a model load.
+const MAX_PREEMPTED_PAUSE: Duration = Duration::from_secs(30);
+
+/// Admission priority. Interactive chat should be `High`, background work
+/// (proactive engine, digests) `Low`.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum GenerationPriority {
+    Low,
+    #[default]
+    Normal,
+    High,
+}
+
+impl GenerationPriority {
+    pub fn parse(level: &str) -> Result<Self, String> {
+        match level.trim().to_lowercase().as_str() {
+            "low" => Ok(GenerationPriority::Low),
+            "normal" => Ok(GenerationPriority::Normal),
+            "high" => Ok(GenerationPriority::High),
+            other => Err(format!("Unknown generation priority '{}' (expected low, normal or high)", other)),
+        }
+    }
+}
+
+/// One request in the queue, as reported to the UI.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct GenerationQueueEntry {
+    pub ticket: u64,
+    /// "reasoning", "fast", "vision", "summarize", or "self_test".
+    pub kind: String,
+    pub prompt_chars: usize,
+    pub priority: GenerationPriority,
+    /// Times this generation gave up its slot to a higher-priority request.
+    pub preemptions: u32,
+    /// Time spent waiting (for waiting entries) or running (for running entries).
+    pub elapsed_ms: u64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct GenerationQueueSnapshot {
+    pub max_concurrent: usize,
+    /// Priority of requests that don't set one.
+    pub default_priority: GenerationPriority,
+    pub running: Vec<GenerationQueueEntry>,
+    pub waiting: Vec<GenerationQueueEntry>,
+}
+
+struct Entry {
+    ticket: u64,
+    kind: String,
+    prompt_chars: usize,
+    priority: GenerationPriority,
+    preemptions: u32,
+    since: Instant,
+}
+
+impl Entry {
+    fn report(&self) -> GenerationQueueEntry {
+        GenerationQueueEntry {
+            ticket: self.ticket,
+            kind: self.kind.clone(),
+            prompt_chars: self.prompt_chars,
+            priority: self.priority,
+            preemptions: self.preemptions,
+            elapsed_ms: self.since.elapsed().as_millis() as u64,
+        }
+    }
+}
+
+struct State {
+    max_concurrent: usize,
+    default_priority: GenerationPriority,
+    next_ticket: u64,
+    running: Vec<Entry>,
+    /// Highest priority first, then by ticket (arrival order).
+    waiting: VecDeque<Entry>,
+}
+
+impl State {
+    /// Queue `entry` behind every waiter of the same or higher priority that
+    /// arrived before it.
+    fn enqueue(&mut self, entry: Entry) {
+        let pos = self
+            .waiting
+            .iter()
+            .position(|e| e.priority < entry.priority || (e.priority == entry.priority && e.ticket > entry.ticket))
+            .unwrap_or(self.waiting.len());
+        self.waiting.insert(pos, entry);
+    }
+
+    /// Move `ticket` from the front of the queue to running if a slot is free.
+    fn admit(&mut self, ticket: u64) -> bool {
+        let is_next = self.waiting.front().map(|e| e.ticket) == Some(ticket);
+        if !is_next || self.running.len() >= self.max_concurrent {
+            return false;
+        }
+        if let Some(mut entry) = self.waiting.pop_front() {
+            entry.since = Instant::now();
+            self.running.push(entry);
+        }
+        true
+    }
+}
+
+pub struct GenerationScheduler {
+    state: Mutex<State>,
+    notify: Notify,
+    /// Wakes preempted generations, which wait synchronously between tokens.
+    resumed: Condvar,
+}
+
+/// A held generation slot. Released on drop.
+pub struct GenerationPermit<'a> {
+    scheduler: &'a GenerationScheduler,
+    ticket: u64,
+}
+
+impl Drop for GenerationPermit<'_> {
+    fn drop(&mut self) {
+        self.scheduler.release(self.ticket);
+    }
+}
+
+/// A `GenerationPermit` that keeps its scheduler alive, so it can move into
+/// `spawn_blocking` with the generation. Released on drop.
+pub struct OwnedGenerationPermit {
+    scheduler: Arc<GenerationScheduler>,
+    ticket: u64,
+}
+
+impl OwnedGenerationPermit {
+    /// Call between tokens. If a higher-priority request is waiting and no slot
+    /// is free for it, give this slot up and block until re-admitted (at most
+    /// `MAX_PREEMPTED_PAUSE`). The generation's context is kept, so it resumes
+    /// where it stopped. Returns whether it was paused.
+    pub fn yield_if_preempted(&self) -> bool {
+        self.scheduler.yield_ticket(self.ticket)
+    }
+}
+
+impl Drop for OwnedGenerationPermit {
+    fn drop(&mut self) {
+        self.scheduler.release(self.ticket);
+    }
+}
+
+/// Removes a waiter from the queue if `acquire` is cancelled before admission.
+struct WaitGuard<'a> {
+    scheduler: &'a GenerationScheduler,
+    ticket: u64,
+    admitted: bool,
+}
+
+impl Drop for WaitGuard<'_> {
+    fn drop(&mut self) {
+        if !self.admitted {
+            self.scheduler.state().waiting.retain(|e| e.ticket != self.ticket);
+            self.scheduler.wake();
+        }
+    }
+}
+
+impl GenerationScheduler {
+    /// Single slot — generations run one at a time, in arrival order.
+    pub fn new() -> Self {
+        GenerationScheduler {
+            state: Mutex::new(State {
+                max_concurrent: 1,
+                default_priority: GenerationPriority::Normal,
+                next_ticket: 1,
+                running: Vec::new(),
+                waiting: VecDeque::new(),
+            }),
+            notify: Notify::new(),
+            resumed: Condvar::new(),
+        }
+    }
+
+    fn state(&self) -> std::sync::MutexGuard<'_, State> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Wake async waiters and preempted generations to re-check admission.
+    fn wake(&self) {
+        self.notify.notify_waiters();
+        self.resumed.notify_all();
+    }
+
+    /// Give up the running slot of `ticket`.
+    fn release(&self, ticket: u64) {
+        self.state().running.retain(|e| e.ticket != ticket);
+        self.wake();
+    }
+
+    /// `yield_if_preempted` for the running generation holding `ticket`.
+    fn yield_ticket(&self, ticket: u64) -> bool {
+        let mut st = self.state();
+        let Some(idx) = st.running.iter().position(|e| e.ticket == ticket) else {
+            return false;
+        };
+        let priority = st.running[idx].priority;
+        let outranked = st.waiting.front().is_some_and(|e| e.priority > priority);
+        if !outranked || st.running.len() < st.max_concurrent {
+            return false;
+        }
+
+        let mut entry = st.running.swap_remove(idx);
+        entry.preemptions += 1;
+        entry.since = Instant::now();
+        st.enqueue(entry);
+        self.wake();
+
+        let deadline = Instant::now() + MAX_PREEMPTED_PAUSE;
+        loop {
+            if st.admit(ticket) {
+                break;
+            }
+            let now = Instant::now();
+            if now >= deadline {
+                // Resume over the limit rather than risk waiting forever.
+                if let Some(pos) = st.waiting.iter().position(|e| e.ticket == ticket) {
+                    let mut entry = st.waiting.remove(pos).expect("position is in range");
+                    entry.since = Instant::now();
+                    st.running.push(entry);
+                }
+                break;
+            }
+            st = self
+                .resumed
+                .wait_timeout(st, deadline - now)
+                .unwrap_or_else(|e| e.into_inner())
+                .0;
+        }
+        drop(st);
+        // Admission may leave another slot free for the next waiter.
+        self.wake();
+        true
+    }
+
+    /// Wait for a generation slot at the default priority.
+    pub async fn acquire(&self, kind: &str, prompt_chars: usize) -> GenerationPermit<'_> {
+        self.acquire_with_priority(kind, prompt_chars, None).await
+    }
+
+    /// Wait for a generation slot. Requests are admitted highest priority
+    /// first, FIFO within a priority; `None` uses the default priority.
+    pub async fn acquire_with_priority(
+        &self,
+        kind: &str,
+        prompt_chars: usize,
+        priority: Option<GenerationPriority>,
+    ) -> GenerationPermit<'_> {
+        let ticket = self.wait_for_slot(kind, prompt_chars, priority).await;
+        GenerationPermit {
+            scheduler: self,
+            ticket,
+        }
+    }
+
+    /// `acquire_with_priority` for a permit that outlives the borrow of the
+    /// scheduler, e.g. one moved into `native_runtime::run_blocking`.
+    pub async fn acquire_owned(
+        self: &Arc<Self>,
+        kind: &str,
+        prompt_chars: usize,
+        priority: Option<GenerationPriority>,
+    ) -> OwnedGenerationPermit {
+        let ticket = self.wait_for_slot(kind, prompt_chars, priority).await;
+        OwnedGenerationPermit {
+            scheduler: self.clone(),
+            ticket,
+        }
+    }
+
+    /// Queue a request and wait until it is admitted; returns its ticket, now running.
+    async fn wait_for_slot(&self, kind: &str, prompt_chars: usize, priority: Option<GenerationPriority>) -> u64 {
+        let ticket = {
+            let mut st = self.state();
+            let ticket = st.next_ticket;
+            st.next_ticket += 1;
+            let priority = priority.unwrap_or(st.default_priority);
+            st.enqueue(Entry {
+                ticket,
+                kind: kind.to_string(),
+                prompt_chars,
+                priority,
+                preemptions: 0,
+                since: Instant::now(),
+            });
+            ticket
+        };
+        let mut guard = WaitGuard {
+            scheduler: self,
+            ticket,
+            admitted: false,
+        };
+
+        loop {
+            // Register interest before checking so a release between the check
+            // and the await can't be missed.
+            let mut notified = std::pin::pin!(self.notify.notified());
+            notified.as_mut().enable();
+            if self.try_admit(ticket) {
+                guard.admitted = true;
+                return ticket;
+            }
+            notified.await;
+        }
+    }
+
+    fn try_admit(&self, ticket: u64) -> bool {
+        if !self.state().admit(ticket) {
+            return false;
+        }
+        // The next waiter may fit too if more than one slot is free.
+        self.wake();
+        true
+    }
+
+    /// Set the number of generations allowed to run at once (1..=MAX_GENERATION_SLOTS).
+    /// Lowering it never interrupts running requests; it only delays new admissions.
+    pub fn set_max_concurrent(&self, n: usize) -> Result<usize, String> {
+        if n == 0 || n > MAX_GENERATION_SLOTS {
+            return Err(format!(
+                "max concurrent generations must be between 1 and {}",
+                MAX_GENERATION_SLOTS
+            ));
+        }
+        self.state().max_concurrent = n;
+        self.wake();
+        Ok(n)
+    }
+
+    /// Set the priority of requests that don't specify one. Applies to
+    /// requests queued from now on.
+    pub fn set_default_priority(&self, priority: GenerationPriority) {
+        self.state().default_priority = priority;
+    }
+
+    /// Current running requests, and waiting requests in admission order.
+    pub fn snapshot(&self) -> GenerationQueueSnapshot {
+        let st = self.state();
+        GenerationQueueSnapshot {
+            max_concurrent: st.max_concurrent,
+            default_priority: st.default_priority,
+            running: st.running.iter().map(Entry::report).collect(),
+            waiting: st.waiting.iter().map(Entry::report).collect(),
+        }
+    }
+}
+
+impl Default for GenerationScheduler {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    async fn wait_until(f: impl Fn() -> bool) {
+        for _ in 0..200 {
+            if f() {
+                return;
+            }
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+        panic!("condition not reached");
+    }
+
+    #[tokio::test]
+    async fn test_single_slot_queues_in_order() {
+        let scheduler = Arc::new(GenerationScheduler::new());
+        let order = Arc::new(Mutex::new(Vec::new()));
+
+        let first = scheduler.acquire("reasoning", 10).await;
+        let mut handles = Vec::new();
+        for i in 0..3 {
+            let (s, o) = (scheduler.clone(), order.clone());
+            handles.push(tokio::spawn(async move {
+                let _permit = s.acquire("reasoning", i).await;
+                o.lock().unwrap().push(i);
+            }));
+            // Enqueue strictly one after another.
+            let s = scheduler.clone();
+            wait_until(move || s.snapshot().waiting.len() == i + 1).await;
+        }
+
+        let snapshot = scheduler.snapshot();
+        assert_eq!(snapshot.running.len(), 1);
+        assert_eq!(snapshot.waiting.len(), 3);
+
+        drop(first);
+        for h in handles {
+            h.await.unwrap();
+        }
+        assert_eq!(*order.lock().unwrap(), vec![0, 1, 2]);
+        assert!(scheduler.snapshot().running.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_raising_limit_admits_waiters() {
+        let scheduler = Arc::new(GenerationScheduler::new());
+        let _first = scheduler.acquire("reasoning", 0).await;
+
+        let s = scheduler.clone();
+        let waiter = tokio::spawn(async move {
+            let _permit = s.acquire("fast", 0).await;
+        });
+        let s = scheduler.clone();
+        wait_until(move || s.snapshot().waiting.len() == 1).await;
+
+        scheduler.set_max_concurrent(2).unwrap();
+        waiter.await.unwrap();
+        assert!(scheduler.set_max_concurrent(0).is_err());
+        assert!(scheduler.set_max_concurrent(MAX_GENERATION_SLOTS + 1).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_high_priority_admitted_first() {
+        let scheduler = Arc::new(GenerationScheduler::new());
+        scheduler.set_default_priority(GenerationPriority::Low);
+        let order = Arc::new(Mutex::new(Vec::new()));
+
+        let first = scheduler.acquire("reasoning", 0).await;
+        let mut handles = Vec::new();
+        for (i, priority) in [(0, None), (1, Some(GenerationPriority::High)), (2, Some(GenerationPriority::Normal))] {
+            let (s, o) = (scheduler.clone(), order.clone());
+            handles.push(tokio::spawn(async move {
+                let _permit = s.acquire_with_priority("reasoning", i, priority).await;
+                o.lock().unwrap().push(i);
+            }));
+            let s = scheduler.clone();
+            wait_until(move || s.snapshot().waiting.len() == i + 1).await;
+        }
+        let waiting: Vec<GenerationPriority> = scheduler.snapshot().waiting.iter().map(|e| e.priority).collect();
+        assert_eq!(waiting, vec![GenerationPriority::High, GenerationPriority::Normal, GenerationPriority::Low]);
+
+        drop(first);
+        for h in handles {
+            h.await.unwrap();
+        }
+        assert_eq!(*order.lock().unwrap(), vec![1, 2, 0]);
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_background_yields_to_interactive() {
+        let scheduler = Arc::new(GenerationScheduler::new());
+        let background = scheduler.acquire_owned("reasoning", 0, Some(GenerationPriority::Low)).await;
+        assert!(!background.yield_if_preempted());
+
+        let s = scheduler.clone();
+        let interactive = tokio::spawn(async move {
+            let _permit = s.acquire_with_priority("reasoning", 0, Some(GenerationPriority::High)).await;
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        });
+        let s = scheduler.clone();
+        wait_until(move || s.snapshot().waiting.len() == 1).await;
+
+        // Blocks until the interactive request has run and released the slot.
+        let started = Instant::now();
+        assert!(tokio::task::block_in_place(|| background.yield_if_preempted()));
+        assert!(started.elapsed() >= Duration::from_millis(20));
+        interactive.await.unwrap();
+
+        let snapshot = scheduler.snapshot();
+        assert_eq!(snapshot.running.len(), 1);
+        assert_eq!(snapshot.running[0].preemptions, 1);
+        assert!(snapshot.waiting.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_cancelled_waiter_leaves_queue() {
+        let scheduler = GenerationScheduler::new();
+        let _first = scheduler.acquire("reasoning", 0).await;
+        let timed_out = tokio::time::timeout(Duration::from_millis(20), scheduler.acquire("reasoning", 0)).await;
+        assert!(timed_out.is_err());
+        assert!(scheduler.snapshot().waiting.is_empty());
+    }
+}
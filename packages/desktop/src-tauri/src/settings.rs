@@ -0,0 +1,159 @@
+// Desktop Settings — preferences owned and persisted by the Rust side.
+// Stored as JSON in the app data dir so they survive sidecar restarts and are
+// available before the sidecar is up. Sidecar-owned preferences stay in core.db.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use tokio::sync::Mutex;
+
+const SETTINGS_FILE: &str = "desktop-settings.json";
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(rename_all = "camelCase", default)]
+pub struct DesktopSettings {
+    /// Glob patterns excluded from indexing. A pattern without `/` matches any
+    /// file or directory name (`node_modules`, `*.log`); a pattern with `/`
+    /// matches the path relative to the indexed root (`private/**`).
+    pub indexing_exclusions: Vec<String>,
+    /// Unload the reasoning model after this many seconds without generations.
+    /// `None` keeps it resident.
+    pub model_idle_timeout_secs: Option<u64>,
+    /// Emit `semblance://low-memory` when available RAM drops below this many MB.
+    /// `None` disables the alert.
+    pub low_memory_threshold_mb: Option<u64>,
+    /// Flash attention default for native generation. `None` uses llama.cpp's default (off).
+    pub flash_attention: Option<bool>,
+    /// Largest prompt in tokens native generation accepts. `None` uses the
+    /// reasoning model's training context.
+    pub max_prompt_tokens: Option<u32>,
+    /// Autonomy tier per domain, as last set by the user. Authoritative over the
+    /// sidecar's copy; see `autonomy`.
+    pub autonomy_tiers: HashMap<String, String>,
+    /// Kill and respawn the sidecar after it misses several heartbeats in a row.
+    /// Off by default: a sidecar blocked by long synchronous work may recover.
+    pub restart_unresponsive_sidecar: bool,
+    /// Directory models are downloaded to, when it isn't the app data dir. Its
+    /// volume's free space is reported by `detect_hardware`.
+    pub model_storage_dir: Option<String>,
+    /// Most reasoning models kept loaded at once for routing by `model_path`.
+    /// `None` keeps only the active one.
+    pub max_resident_models: Option<u32>,
+    /// Memory budget for resident reasoning models, in MB. `None` for no limit.
+    pub resident_models_budget_mb: Option<u64>,
+    /// Run a throwaway decode after each reasoning model load, so the first
+    /// real generation doesn't pay for kernel compilation.
+    pub warmup_after_load: bool,
+    /// Load reasoning models even when the pre-load memory estimate says they
+    /// won't fit.
+    pub skip_memory_check: bool,
+}
+
+/// Shortest accepted idle timeout — reloading more often than this costs more than it saves.
+pub const MIN_MODEL_IDLE_TIMEOUT_SECS: u64 = 60;
+
+/// Tauri-managed settings state. Mutations go through `update` so every change is persisted.
+pub struct SettingsState {
+    path: PathBuf,
+    settings: Mutex<DesktopSettings>,
+}
+
+impl SettingsState {
+    /// Load settings from `dir`, falling back to defaults if the file is missing or unreadable.
+    pub fn load(dir: &Path) -> Self {
+        let path = dir.join(SETTINGS_FILE);
+        let settings = std::fs::read_to_string(&path)
+            .ok()
+            .and_then(|raw| match serde_json::from_str(&raw) {
+                Ok(s) => Some(s),
+                Err(e) => {
+                    eprintln!("[settings] Ignoring unreadable {:?}: {}", path, e);
+                    None
+                }
+            })
+            .unwrap_or_default();
+        SettingsState {
+            path,
+            settings: Mutex::new(settings),
+        }
+    }
+
+    /// Snapshot of the current settings.
+    pub async fn get(&self) -> DesktopSettings {
+        self.settings.lock().await.clone()
+    }
+
+    /// Apply `f` and persist the result. The in-memory value is only changed if the write succeeds.
+    pub async fn update(&self, f: impl FnOnce(&mut DesktopSettings)) -> Result<DesktopSettings, String> {
+        let mut guard = self.settings.lock().await;
+        let mut next = guard.clone();
+        f(&mut next);
+        let json = serde_json::to_string_pretty(&next)
+            .map_err(|e| format!("Failed to serialize settings: {}", e))?;
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent)
+                .map_err(|e| format!("Failed to create settings dir: {}", e))?;
+        }
+        // Write to a temp file then rename so a crash can't leave a truncated file.
+        let tmp = self.path.with_extension("json.tmp");
+        std::fs::write(&tmp, json).map_err(|e| format!("Failed to write settings: {}", e))?;
+        std::fs::rename(&tmp, &self.path).map_err(|e| format!("Failed to save settings: {}", e))?;
+        *guard = next.clone();
+        Ok(next)
+    }
+}
+
+/// Trim, validate, and de-duplicate indexing exclusion patterns (order preserved).
+pub fn normalize_exclusions(patterns: Vec<String>) -> Result<Vec<String>, String> {
+    let mut out: Vec<String> = Vec::with_capacity(patterns.len());
+    for raw in patterns {
+        let pattern = raw.trim().replace('\\', "/");
+        if pattern.is_empty() {
+            continue;
+        }
+        if pattern.split('/').any(|seg| seg == "..") {
+            return Err(format!("Exclusion pattern must not contain '..': {}", raw));
+        }
+        if !out.contains(&pattern) {
+            out.push(pattern);
+        }
+    }
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize_exclusions() {
+        let patterns = vec![
+            " node_modules ".to_string(),
+            "".to_string(),
+            "private\\**".to_string(),
+            "node_modules".to_string(),
+        ];
+        assert_eq!(
+            normalize_exclusions(patterns).unwrap(),
+            vec!["node_modules".to_string(), "private/**".to_string()]
+        );
+        assert!(normalize_exclusions(vec!["../outside".to_string()]).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_settings_persist_across_loads() {
+        let dir = std::env::temp_dir().join(format!("semblance_settings_test_{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+
+        let state = SettingsState::load(&dir);
+        assert!(state.get().await.indexing_exclusions.is_empty());
+        state
+            .update(|s| s.indexing_exclusions = vec!["*.log".to_string()])
+            .await
+            .unwrap();
+
+        let reloaded = SettingsState::load(&dir);
+        let _ = std::fs::remove_dir_all(&dir);
+        assert_eq!(reloaded.get().await.indexing_exclusions, vec!["*.log".to_string()]);
+    }
+}
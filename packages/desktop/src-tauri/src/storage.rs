@@ -0,0 +1,150 @@
+// Storage Inspection — sizes and ages of everything persisted under the app data dir.
+// Read-only: this module never deletes. Clearing a category goes through that
+// category's own purge command so its owner can clean up in-memory state too.
+
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+/// Root of all persisted app data (`~/.semblance/data`). Shared with the sidecar.
+pub fn data_dir() -> PathBuf {
+    let home = std::env::var("USERPROFILE")
+        .or_else(|_| std::env::var("HOME"))
+        .unwrap_or_else(|_| ".".to_string());
+    PathBuf::from(home).join(".semblance").join("data")
+}
+
+/// One category of persisted artifacts.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct StorageCategory {
+    pub category: String,
+    pub size_bytes: u64,
+    pub file_count: u64,
+    /// Most recent modification time of any file in the category (Unix ms).
+    pub last_modified_ms: Option<u64>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct StorageBreakdown {
+    pub data_dir: String,
+    pub total_bytes: u64,
+    pub categories: Vec<StorageCategory>,
+}
+
+/// Known top-level entries of the data dir and the category they belong to.
+/// Anything not listed here is reported under "other".
+const CATEGORY_ENTRIES: &[(&str, &[&str])] = &[
+    ("database", &["core.db", "core.db-wal", "core.db-shm"]),
+    ("knowledge", &["knowledge"]),
+    ("models", &["models"]),
+    ("gateway", &["gateway"]),
+    ("settings", &["desktop-settings.json"]),
+    ("repros", &["repros"]),
+    ("logs", &["sidecar.log"]),
+];
+
+#[derive(Default)]
+struct Usage {
+    size_bytes: u64,
+    file_count: u64,
+    last_modified_ms: Option<u64>,
+}
+
+impl Usage {
+    fn add_path(&mut self, path: &Path) {
+        let Ok(meta) = std::fs::symlink_metadata(path) else { return };
+        if meta.is_dir() {
+            if let Ok(entries) = std::fs::read_dir(path) {
+                for entry in entries.flatten() {
+                    self.add_path(&entry.path());
+                }
+            }
+        } else if meta.is_file() {
+            self.size_bytes += meta.len();
+            self.file_count += 1;
+            let modified = meta
+                .modified()
+                .ok()
+                .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+                .map(|d| d.as_millis() as u64);
+            self.last_modified_ms = self.last_modified_ms.max(modified);
+        }
+    }
+}
+
+/// Compute the per-category breakdown of `root`. Categories with no files are omitted.
+pub fn storage_breakdown(root: &Path) -> StorageBreakdown {
+    let mut usages: Vec<(String, Usage)> = CATEGORY_ENTRIES
+        .iter()
+        .map(|(category, _)| (category.to_string(), Usage::default()))
+        .collect();
+    let mut other = Usage::default();
+
+    if let Ok(entries) = std::fs::read_dir(root) {
+        for entry in entries.flatten() {
+            let name = entry.file_name().to_string_lossy().to_string();
+            let slot = CATEGORY_ENTRIES
+                .iter()
+                .position(|(_, names)| names.contains(&name.as_str()));
+            match slot {
+                Some(i) => usages[i].1.add_path(&entry.path()),
+                None => other.add_path(&entry.path()),
+            }
+        }
+    }
+    usages.push(("other".to_string(), other));
+
+    let categories: Vec<StorageCategory> = usages
+        .into_iter()
+        .filter(|(_, u)| u.file_count > 0)
+        .map(|(category, u)| StorageCategory {
+            category,
+            size_bytes: u.size_bytes,
+            file_count: u.file_count,
+            last_modified_ms: u.last_modified_ms,
+        })
+        .collect();
+
+    StorageBreakdown {
+        data_dir: root.to_string_lossy().to_string(),
+        total_bytes: categories.iter().map(|c| c.size_bytes).sum(),
+        categories,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_storage_breakdown_categorizes_entries() {
+        let root = std::env::temp_dir().join(format!("semblance_storage_test_{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&root);
+        std::fs::create_dir_all(root.join("knowledge").join("nested")).unwrap();
+        std::fs::write(root.join("core.db"), vec![0u8; 100]).unwrap();
+        std::fs::write(root.join("core.db-wal"), vec![0u8; 20]).unwrap();
+        std::fs::write(root.join("knowledge").join("nested").join("vec.bin"), vec![0u8; 50]).unwrap();
+        std::fs::write(root.join("unknown.tmp"), vec![0u8; 5]).unwrap();
+
+        let breakdown = storage_breakdown(&root);
+        let _ = std::fs::remove_dir_all(&root);
+
+        let get = |name: &str| breakdown.categories.iter().find(|c| c.category == name).cloned();
+        let db = get("database").unwrap();
+        assert_eq!(db.size_bytes, 120);
+        assert_eq!(db.file_count, 2);
+        assert!(db.last_modified_ms.is_some());
+        assert_eq!(get("knowledge").unwrap().size_bytes, 50);
+        assert_eq!(get("other").unwrap().size_bytes, 5);
+        assert!(get("models").is_none());
+        assert_eq!(breakdown.total_bytes, 175);
+    }
+
+    #[test]
+    fn test_storage_breakdown_missing_dir() {
+        let breakdown = storage_breakdown(Path::new("/nonexistent/semblance/data"));
+        assert_eq!(breakdown.total_bytes, 0);
+        assert!(breakdown.categories.is_empty());
+    }
+}
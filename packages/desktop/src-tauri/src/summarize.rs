@@ -0,0 +1,296 @@
+// Document Summarization — map-reduce over text larger than the context window.
+//
+// The document is packed into chunks that fit the reasoning model's context
+// (measured with its own tokenizer), each chunk is summarized, then the chunk
+// summaries are summarized — in more rounds if they still don't fit — down to a
+// single summary of about `target_tokens`. Every generation takes its own
+// scheduler slot so chat isn't starved for the length of a long document.
+
+use crate::native_runtime::{GenerateRequest, RuntimeError, SharedNativeRuntime, GENERATION_N_CTX};
+use crate::scheduler::GenerationScheduler;
+use serde::{Deserialize, Serialize};
+use std::time::Instant;
+
+/// Room for the system prompt, instruction, and chat template around each input.
+const PROMPT_OVERHEAD_TOKENS: usize = 256;
+/// Output budget of each chunk (and intermediate) summary.
+const CHUNK_SUMMARY_TOKENS: u32 = 384;
+/// Upper bound for `target_tokens`, so the final input still has room in the context.
+pub const MAX_TARGET_TOKENS: u32 = 1024;
+/// Reduce rounds before giving up. Each round shrinks the input ~9x, so this is never hit in practice.
+const MAX_REDUCE_ROUNDS: u32 = 8;
+
+const SYSTEM_PROMPT: &str = "You summarize documents accurately and concisely. Keep key facts, names, numbers, dates, and decisions. Do not add information that is not in the text.";
+
+/// Progress of a running summarization, emitted after each generation.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SummarizeProgress {
+    /// "map" (chunk summaries), "reduce" (summaries of summaries), or "final".
+    pub stage: String,
+    /// Reduce round, starting at 1; 0 for the map and final stages.
+    pub round: u32,
+    pub completed: usize,
+    pub total: usize,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct DocumentSummary {
+    pub summary: String,
+    /// One summary per chunk of the original text, in document order. Empty when
+    /// the document fit in a single pass.
+    pub chunk_summaries: Vec<String>,
+    pub chunk_count: usize,
+    pub reduce_rounds: u32,
+    pub duration_ms: u64,
+}
+
+/// Summarize `text` to about `target_tokens` with the reasoning model.
+pub async fn summarize_document(
+    runtime: &SharedNativeRuntime,
+    scheduler: &GenerationScheduler,
+    text: &str,
+    target_tokens: u32,
+    mut on_progress: impl FnMut(SummarizeProgress),
+) -> Result<DocumentSummary, RuntimeError> {
+    if text.trim().is_empty() {
+        return Err("Document is empty".into());
+    }
+    if target_tokens == 0 || target_tokens > MAX_TARGET_TOKENS {
+        return Err(format!("target_tokens must be between 1 and {}", MAX_TARGET_TOKENS).into());
+    }
+    let start = Instant::now();
+    // Chunking counts tokens with the reasoning model, so reload it first if it was idle-unloaded.
+    crate::native_runtime::ensure_reasoning_resident(runtime).await?;
+    let map_budget = input_budget(CHUNK_SUMMARY_TOKENS);
+    let final_budget = input_budget(target_tokens);
+
+    let chunks = {
+        let rt = runtime.read().await;
+        pack_chunks(text, map_budget, &mut |s| rt.count_tokens(s))?
+    };
+    let chunk_count = chunks.len();
+
+    // Fits in one pass — no map stage needed.
+    if chunk_count == 1 {
+        let summary = summarize(runtime, scheduler, &final_prompt(&chunks[0], target_tokens, false), target_tokens).await?;
+        on_progress(progress("final", 0, 1, 1));
+        return Ok(DocumentSummary {
+            summary,
+            chunk_summaries: Vec::new(),
+            chunk_count,
+            reduce_rounds: 0,
+            duration_ms: start.elapsed().as_millis() as u64,
+        });
+    }
+
+    let mut chunk_summaries = Vec::with_capacity(chunk_count);
+    for (i, chunk) in chunks.iter().enumerate() {
+        let prompt = format!("Summarize this section of a longer document:\n\n{}", chunk);
+        chunk_summaries.push(summarize(runtime, scheduler, &prompt, CHUNK_SUMMARY_TOKENS).await?);
+        on_progress(progress("map", 0, i + 1, chunk_count));
+    }
+
+    // Reduce until the combined summaries fit the final pass.
+    let mut current = chunk_summaries.clone();
+    let mut reduce_rounds = 0;
+    loop {
+        let joined = current.join("\n\n");
+        if runtime.read().await.count_tokens(&joined)? <= final_budget {
+            break;
+        }
+        reduce_rounds += 1;
+        if reduce_rounds > MAX_REDUCE_ROUNDS {
+            return Err("Document summary did not converge".into());
+        }
+        let groups = {
+            let rt = runtime.read().await;
+            pack_chunks(&joined, map_budget, &mut |s| rt.count_tokens(s))?
+        };
+        let mut next = Vec::with_capacity(groups.len());
+        for (i, group) in groups.iter().enumerate() {
+            let prompt = format!(
+                "These are summaries of consecutive sections of one document. Combine them into one summary:\n\n{}",
+                group
+            );
+            next.push(summarize(runtime, scheduler, &prompt, CHUNK_SUMMARY_TOKENS).await?);
+            on_progress(progress("reduce", reduce_rounds, i + 1, groups.len()));
+        }
+        current = next;
+    }
+
+    let summary = summarize(runtime, scheduler, &final_prompt(&current.join("\n\n"), target_tokens, true), target_tokens).await?;
+    on_progress(progress("final", 0, 1, 1));
+    Ok(DocumentSummary {
+        summary,
+        chunk_summaries,
+        chunk_count,
+        reduce_rounds,
+        duration_ms: start.elapsed().as_millis() as u64,
+    })
+}
+
+fn progress(stage: &str, round: u32, completed: usize, total: usize) -> SummarizeProgress {
+    SummarizeProgress {
+        stage: stage.to_string(),
+        round,
+        completed,
+        total,
+    }
+}
+
+/// Input tokens available to a call that may generate `max_tokens`.
+fn input_budget(max_tokens: u32) -> usize {
+    GENERATION_N_CTX as usize - PROMPT_OVERHEAD_TOKENS - max_tokens as usize
+}
+
+fn final_prompt(input: &str, target_tokens: u32, from_summaries: bool) -> String {
+    // ~0.75 words per token for English text.
+    let words = (target_tokens as usize * 3 / 4).max(1);
+    let what = if from_summaries {
+        "These are summaries of consecutive sections of one document. Write a single summary of the whole document"
+    } else {
+        "Summarize this document"
+    };
+    format!("{} in at most about {} words:\n\n{}", what, words, input)
+}
+
+async fn summarize(
+    runtime: &SharedNativeRuntime,
+    scheduler: &GenerationScheduler,
+    prompt: &str,
+    max_tokens: u32,
+) -> Result<String, RuntimeError> {
+    let _slot = scheduler.acquire("summarize", SYSTEM_PROMPT.len() + prompt.len()).await;
+    crate::native_runtime::ensure_reasoning_resident(runtime).await?;
+    let request = GenerateRequest {
+        prompt: prompt.to_string(),
+        system_prompt: Some(SYSTEM_PROMPT.to_string()),
+        max_tokens: Some(max_tokens),
+        temperature: Some(0.3),
+        stop: Some(vec!["<|im_end|>".to_string(), "<|endoftext|>".to_string()]),
+        session_id: Some("summarize".to_string()),
+        ..Default::default()
+    };
+    let response = crate::native_runtime::generate_async(runtime, request, None).await?;
+    Ok(response.text.trim().to_string())
+}
+
+/// Pack `text` into chunks of at most `budget` tokens, splitting on paragraph
+/// breaks. Paragraphs that are too large on their own are halved (at whitespace
+/// where possible) until they fit.
+fn pack_chunks<E>(
+    text: &str,
+    budget: usize,
+    count: &mut impl FnMut(&str) -> Result<usize, E>,
+) -> Result<Vec<String>, E> {
+    // Stack of pending pieces, next piece on top.
+    let mut pending: Vec<String> = text
+        .split("\n\n")
+        .map(str::trim)
+        .filter(|p| !p.is_empty())
+        .map(str::to_string)
+        .collect();
+    pending.reverse();
+    let mut chunks = Vec::new();
+    let mut current = String::new();
+    let mut current_tokens = 0;
+
+    while let Some(piece) = pending.pop() {
+        if piece.is_empty() {
+            continue;
+        }
+        let tokens = count(&piece)?;
+        if tokens > budget {
+            if let Some((head, tail)) = split_in_half(&piece) {
+                pending.push(tail.to_string());
+                pending.push(head.to_string());
+                continue;
+            }
+        }
+        if !current.is_empty() && current_tokens + tokens > budget {
+            chunks.push(std::mem::take(&mut current));
+            current_tokens = 0;
+        }
+        if !current.is_empty() {
+            current.push_str("\n\n");
+        }
+        current.push_str(&piece);
+        current_tokens += tokens;
+    }
+    if !current.is_empty() {
+        chunks.push(current);
+    }
+    Ok(chunks)
+}
+
+/// Split near the middle, preferring the last whitespace before it. `None` for a single character.
+fn split_in_half(s: &str) -> Option<(&str, &str)> {
+    let mid = s.char_indices().nth(s.chars().count() / 2).map(|(i, _)| i)?;
+    if mid == 0 {
+        return None;
+    }
+    let at = s[..mid]
+        .rfind(char::is_whitespace)
+        .filter(|&i| i > 0)
+        .unwrap_or(mid);
+    let (head, tail) = s.split_at(at);
+    Some((head.trim_end(), tail.trim_start()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn words(s: &str) -> Result<usize, String> {
+        Ok(s.split_whitespace().count())
+    }
+
+    #[test]
+    fn test_pack_chunks_respects_budget_and_order() {
+        let text = "one two three\n\nfour five\n\nsix seven eight nine\n\n\n\nten";
+        let chunks = pack_chunks(text, 5, &mut words).unwrap();
+        assert_eq!(chunks, vec!["one two three\n\nfour five", "six seven eight nine\n\nten"]);
+    }
+
+    #[test]
+    fn test_pack_chunks_splits_oversized_paragraph() {
+        let paragraph = (0..20).map(|i| format!("w{}", i)).collect::<Vec<_>>().join(" ");
+        let chunks = pack_chunks(&paragraph, 6, &mut words).unwrap();
+        assert!(chunks.len() >= 4);
+        for chunk in &chunks {
+            assert!(words(chunk).unwrap() <= 6, "{:?}", chunk);
+        }
+        assert_eq!(chunks.join(" ").split_whitespace().count(), 20);
+    }
+
+    #[test]
+    fn test_split_in_half() {
+        assert_eq!(split_in_half("abc def ghi"), Some(("abc", "def ghi")));
+        assert_eq!(split_in_half("héllo"), Some(("hé", "llo")));
+        assert_eq!(split_in_half("x"), None);
+    }
+
+    /// Needs a real model: set SEMBLANCE_TEST_GGUF.
+    #[tokio::test]
+    async fn test_summarize_reloads_idle_unloaded_model() {
+        let Ok(path) = std::env::var("SEMBLANCE_TEST_GGUF") else {
+            return;
+        };
+        let runtime = crate::native_runtime::create_runtime();
+        {
+            let mut rt = runtime.write().await;
+            rt.load_reasoning_model(std::path::PathBuf::from(path)).unwrap();
+            assert!(rt.unload_reasoning_if_idle(std::time::Duration::ZERO).is_some());
+        }
+        let scheduler = GenerationScheduler::new();
+        let text = "The meeting moved to Thursday.\n\nAlice will send the agenda by Wednesday noon.";
+        let summary = summarize_document(&runtime, &scheduler, text, 64, |_| {}).await.unwrap();
+        assert!(!summary.summary.is_empty());
+        assert!(runtime.read().await.has_reasoning_model());
+    }
+
+    #[test]
+    fn test_input_budget_leaves_room_for_output() {
+        assert_eq!(input_budget(CHUNK_SUMMARY_TOKENS) + CHUNK_SUMMARY_TOKENS as usize + PROMPT_OVERHEAD_TOKENS, GENERATION_N_CTX as usize);
+    }
+}
@@ -0,0 +1,130 @@
+// Sidecar Supervisor — restart policy for the Node.js sidecar process.
+//
+// When the sidecar's stdout closes outside of shutdown, the bridge respawns it
+// and re-runs `initialize`. Attempts back off exponentially, and after
+// `MAX_RESTARTS` consecutive crashes the supervisor gives up, so a sidecar that
+// dies on startup (a broken script, a missing dependency) isn't respawned in a
+// tight loop. A sidecar that stays up for `STABLE_UPTIME` is healthy again and
+// its next crash starts a fresh count.
+
+use std::time::{Duration, Instant};
+
+/// Consecutive crashes after which the sidecar is left down.
+pub const MAX_RESTARTS: u32 = 5;
+
+/// Wait before the first respawn; doubled for each further attempt.
+const INITIAL_BACKOFF: Duration = Duration::from_secs(1);
+
+const MAX_BACKOFF: Duration = Duration::from_secs(30);
+
+/// Uptime after which a sidecar counts as healthy, resetting the crash count.
+const STABLE_UPTIME: Duration = Duration::from_secs(60);
+
+#[derive(Debug)]
+pub struct RestartPolicy {
+    /// Crashes since the sidecar was last stable.
+    attempts: u32,
+    /// When the current sidecar was (or is being) spawned.
+    spawned_at: Instant,
+    /// Respawns over the bridge's lifetime, for `get_sidecar_health`.
+    total_restarts: u64,
+}
+
+impl RestartPolicy {
+    pub fn new(now: Instant) -> Self {
+        RestartPolicy { attempts: 0, spawned_at: now, total_restarts: 0 }
+    }
+
+    /// The sidecar exited (or failed to spawn) at `now`. Returns how long to
+    /// wait before respawning it, or `None` to give up.
+    pub fn on_exit(&mut self, now: Instant) -> Option<Duration> {
+        if now.duration_since(self.spawned_at) >= STABLE_UPTIME {
+            self.attempts = 0;
+        }
+        if self.attempts >= MAX_RESTARTS {
+            return None;
+        }
+        let delay = INITIAL_BACKOFF.saturating_mul(1 << self.attempts.min(16)).min(MAX_BACKOFF);
+        self.attempts += 1;
+        Some(delay)
+    }
+
+    /// A respawn is being attempted at `now`. Call before spawning, so a spawn
+    /// that fails outright counts as a crash with no uptime.
+    pub fn on_spawn(&mut self, now: Instant) {
+        self.spawned_at = now;
+    }
+
+    /// The respawn attempted by the last `on_spawn` started a process. Only
+    /// these count toward `total_restarts`.
+    pub fn on_spawned(&mut self) {
+        self.total_restarts += 1;
+    }
+
+    /// When the current sidecar was (or is being) spawned.
+    pub fn spawned_at(&self) -> Instant {
+        self.spawned_at
+    }
+
+    pub fn attempts(&self) -> u32 {
+        self.attempts
+    }
+
+    pub fn total_restarts(&self) -> u64 {
+        self.total_restarts
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_restart_policy_backs_off_and_gives_up() {
+        let mut now = Instant::now();
+        let mut policy = RestartPolicy::new(now);
+        let mut delays = Vec::new();
+        while let Some(delay) = policy.on_exit(now) {
+            delays.push(delay.as_secs());
+            now += delay;
+            policy.on_spawn(now);
+            policy.on_spawned();
+            // Crashes immediately, like a broken script.
+            now += Duration::from_millis(200);
+        }
+        assert_eq!(delays, vec![1, 2, 4, 8, 16]);
+        assert_eq!(policy.attempts(), MAX_RESTARTS);
+        assert_eq!(policy.total_restarts(), MAX_RESTARTS as u64);
+        assert_eq!(policy.on_exit(now), None);
+    }
+
+    #[test]
+    fn test_restart_policy_resets_after_stable_uptime() {
+        let start = Instant::now();
+        let mut policy = RestartPolicy::new(start);
+        assert_eq!(policy.on_exit(start + Duration::from_secs(5)), Some(Duration::from_secs(1)));
+        policy.on_spawn(start + Duration::from_secs(6));
+        assert_eq!(policy.on_exit(start + Duration::from_secs(7)), Some(Duration::from_secs(2)));
+        policy.on_spawn(start + Duration::from_secs(9));
+        // Up for longer than STABLE_UPTIME: the next crash starts over.
+        assert_eq!(policy.on_exit(start + Duration::from_secs(9) + STABLE_UPTIME), Some(Duration::from_secs(1)));
+        assert_eq!(policy.attempts(), 1);
+    }
+
+    #[test]
+    fn test_failed_spawns_back_off_but_are_not_restarts() {
+        let mut now = Instant::now();
+        let mut policy = RestartPolicy::new(now);
+        // Long enough ago that the original sidecar counted as stable.
+        now += STABLE_UPTIME * 2;
+        let mut attempts = 0;
+        while let Some(delay) = policy.on_exit(now) {
+            now += delay;
+            policy.on_spawn(now);
+            attempts += 1;
+            // The spawn itself fails; no process, no uptime.
+        }
+        assert_eq!(attempts, MAX_RESTARTS);
+        assert_eq!(policy.total_restarts(), 0);
+    }
+}
@@ -1,4 +1,4 @@
-import { useEffect, useCallback, useState } from 'react';
+import { useEffect, useCallback, useRef, useState } from 'react';
 import { Routes, Route, Navigate, useNavigate, useLocation } from 'react-router-dom';
 import { listen } from '@tauri-apps/api/event';
 import { invoke } from '@tauri-apps/api/core';
@@ -41,7 +41,8 @@ import { CanvasPanel } from './components/CanvasPanel';
 import { NetworkStatusIndicator } from './components/NetworkStatusIndicator';
 import { UpdateChecker } from './components/UpdateChecker';
 import { UpgradeScreen as UpgradeScreenComponent, UpgradeEmailCapture } from '@semblance/ui';
-import { submitUpgradeEmail } from './ipc/commands';
+import { getOnboardingComplete, submitUpgradeEmail } from './ipc/commands';
+import type { SidecarFailedPayload, SidecarReadyPayload } from './ipc/types';
 
 // Lucide-style inline SVG icons (20x20, stroke-based)
 function ChatIcon() {
@@ -323,34 +324,39 @@ function AppContent() {
     }).catch(() => {});
   }, [dispatch]);
 
+  // Leave the loading screen exactly once: when the sidecar is up, or when it won't come up.
+  const leftLoadingRef = useRef(false);
+  const leaveLoading = useCallback((sidecarUp: boolean) => {
+    if (leftLoadingRef.current) return;
+    leftLoadingRef.current = true;
+    setSidecarReady(true);
+    if (sidecarUp) fetchModelStatus();
+  }, [fetchModelStatus]);
+
+  // The sidecar has initialized (its prefs are loaded), so one onboarding read is authoritative
+  const enterWhenReady = useCallback(() => getOnboardingComplete().then((complete) => {
+    if (complete) dispatch({ type: 'SET_ONBOARDING_COMPLETE' });
+    leaveLoading(true);
+  }), [dispatch, leaveLoading]);
+
+  useTauriEvent<SidecarReadyPayload>(
+    'semblance://sidecar-ready',
+    useCallback(() => {
+      enterWhenReady().catch(() => leaveLoading(true));
+    }, [enterWhenReady, leaveLoading]),
+  );
+
+  // A sidecar that won't come up: stop waiting on the loading screen
+  useTauriEvent<SidecarFailedPayload>(
+    'semblance://sidecar-failed',
+    useCallback((payload) => {
+      if (!payload.restarting) leaveLoading(false);
+    }, [leaveLoading]),
+  );
+
   useEffect(() => {
-    // Poll for onboarding status — sidecar prefs may not be loaded on first response
-    const checkOnboarding = (attempt = 0) => {
-      import('./ipc/commands').then(({ getOnboardingComplete }) => {
-        getOnboardingComplete().then((complete) => {
-          if (complete) {
-            dispatch({ type: 'SET_ONBOARDING_COMPLETE' });
-            setSidecarReady(true);
-            fetchModelStatus();
-          } else if (attempt < 5) {
-            // Sidecar responded but prefs may not be loaded yet — retry
-            setTimeout(() => checkOnboarding(attempt + 1), 800);
-          } else {
-            // After 5 attempts still false — genuine first run
-            setSidecarReady(true);
-            fetchModelStatus();
-          }
-        }).catch(() => {
-          // Sidecar not ready at all — retry
-          if (attempt < 20) {
-            setTimeout(() => checkOnboarding(attempt + 1), 1000);
-          } else {
-            setSidecarReady(true); // Give up, show whatever state we have
-          }
-        });
-      });
-    };
-    checkOnboarding();
+    // Already up before this listener attached; otherwise sidecar-ready (or -failed) follows
+    enterWhenReady().catch(() => {});
 
     license.refresh().catch(() => {
       // Not yet initialized — will be free tier by default
@@ -1,7 +1,8 @@
-import { useState } from 'react';
+import { useCallback, useState } from 'react';
 import { Button, Card } from '@semblance/ui';
 import { importStatement } from '../ipc/commands';
-import type { ImportStatementResult } from '../ipc/types';
+import type { ImportStatementResult, StatementImportProgressPayload } from '../ipc/types';
+import { useTauriEvent } from '../hooks/useTauriEvent';
 import './StatementImportDialog.css';
 
 interface StatementImportDialogProps {
@@ -15,6 +16,11 @@ export function StatementImportDialog({ onClose, onImportComplete }: StatementIm
   const [phase, setPhase] = useState<ImportPhase>('select');
   const [result, setResult] = useState<ImportStatementResult | null>(null);
   const [error, setError] = useState<string | null>(null);
+  const [progress, setProgress] = useState<StatementImportProgressPayload | null>(null);
+
+  useTauriEvent<StatementImportProgressPayload>('semblance://statement-import-progress', useCallback((payload) => {
+    setProgress(payload);
+  }, []));
 
   const handleSelectFile = async () => {
     try {
@@ -28,6 +34,7 @@ export function StatementImportDialog({ onClose, onImportComplete }: StatementIm
 
       if (!selected || typeof selected !== 'string') return;
 
+      setProgress(null);
       setPhase('parsing');
 
       const importResult = await importStatement(selected);
@@ -75,7 +82,11 @@ export function StatementImportDialog({ onClose, onImportComplete }: StatementIm
         {phase === 'parsing' && (
           <div style={{ padding: 'var(--sp-8) 0', textAlign: 'center' }}>
             <div className="statement-import__spinner" />
-            <p className="statement-import__status">Analyzing transactions...</p>
+            <p className="statement-import__status">
+              {progress && progress.rowsTotal > 0
+                ? `Reading transactions... ${progress.rowsParsed.toLocaleString()} of ${progress.rowsTotal.toLocaleString()}`
+                : 'Analyzing transactions...'}
+            </p>
             <p className="statement-import__substatus">
               Detecting merchants, recurring charges, and subscription patterns.
             </p>
@@ -89,7 +100,7 @@ export function StatementImportDialog({ onClose, onImportComplete }: StatementIm
 
             <div className="statement-import__grid">
               <div className="statement-import__stat">
-                <p className="statement-import__stat-value">{result.transactionCount}</p>
+                <p className="statement-import__stat-value">{result.transactionsImported}</p>
                 <p className="statement-import__stat-label">Transactions</p>
               </div>
               <div className="statement-import__stat">
@@ -97,12 +108,12 @@ export function StatementImportDialog({ onClose, onImportComplete }: StatementIm
                 <p className="statement-import__stat-label">Merchants</p>
               </div>
               <div className="statement-import__stat">
-                <p className="statement-import__stat-value">{result.recurringCount}</p>
+                <p className="statement-import__stat-value">{result.detectedSubscriptions}</p>
                 <p className="statement-import__stat-label">Recurring</p>
               </div>
-              {result.forgottenCount > 0 && (
+              {result.forgottenSubscriptions > 0 && (
                 <div className="statement-import__stat statement-import__stat--attention">
-                  <p className="statement-import__stat-value">{result.forgottenCount}</p>
+                  <p className="statement-import__stat-value">{result.forgottenSubscriptions}</p>
                   <p className="statement-import__stat-label">Likely Forgotten</p>
                 </div>
               )}
@@ -116,9 +127,17 @@ export function StatementImportDialog({ onClose, onImportComplete }: StatementIm
               </div>
             )}
 
-            <p className="statement-import__date-range">
-              Date range: {result.dateRange.start} to {result.dateRange.end}
-            </p>
+            {result.dateRange.start && (
+              <p className="statement-import__date-range">
+                Date range: {result.dateRange.start} to {result.dateRange.end}
+              </p>
+            )}
+
+            {(result.duplicatesSkipped > 0 || result.unparseableRows > 0) && (
+              <p className="statement-import__date-range">
+                Skipped {result.duplicatesSkipped} already imported and {result.unparseableRows} unreadable rows.
+              </p>
+            )}
 
             <div className="statement-import__result-actions">
               <Button onClick={() => { onImportComplete(); onClose(); }}>View Subscriptions</Button>
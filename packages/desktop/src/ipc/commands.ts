@@ -1,13 +1,14 @@
 // Typed IPC command wrappers for all Tauri invoke() calls.
 // Only this file should import from @tauri-apps/api/core.
 
-import { invoke } from '@tauri-apps/api/core';
+import { invoke as tauriInvoke } from '@tauri-apps/api/core';
+import type { InvokeArgs } from '@tauri-apps/api/core';
 import type {
   HardwareDisplayInfo,
+  LocalModelRecommendation,
   ProviderPreset,
   KnowledgeMoment,
   AddCredentialParams,
-  TestCredentialParams,
   TestCredentialResult,
   AccountInfo,
   AccountStatus,
@@ -18,6 +19,8 @@ import type {
   CalendarEvent,
   ActionsSummary,
   SendEmailParams,
+  PreviewableAction,
+  ActionPreview,
   LogEntry,
   PendingAction,
   DigestSummary,
@@ -75,14 +78,61 @@ import type {
   HardwareVerifyResult,
   SovereigntyReportData,
   SovereigntyReportVerifyResult,
+  IndexingControlStatus,
+  SemblanceErrorPayload,
 } from './types.js';
 
+/**
+ * A failed command. `error.kind` says what went wrong (e.g. `sidecar_not_ready`,
+ * `timeout`, `model_not_loaded`) so callers can branch and localize without
+ * matching on `message`.
+ */
+export class CommandError extends Error {
+  readonly error: SemblanceErrorPayload;
+
+  constructor(error: SemblanceErrorPayload) {
+    super(error.message);
+    this.name = 'CommandError';
+    this.error = error;
+  }
+
+  get kind(): SemblanceErrorPayload['kind'] {
+    return this.error.kind;
+  }
+}
+
+function isSemblanceError(err: unknown): err is SemblanceErrorPayload {
+  return typeof err === 'object' && err !== null
+    && typeof (err as { kind?: unknown }).kind === 'string'
+    && typeof (err as { message?: unknown }).message === 'string';
+}
+
+function invoke<T>(cmd: string, args?: InvokeArgs): Promise<T> {
+  return tauriInvoke<T>(cmd, args).catch((err: unknown) => {
+    throw isSemblanceError(err) ? new CommandError(err) : err;
+  });
+}
+
 // ─── Hardware / Onboarding ──────────────────────────────────────────────────
 
 export function detectHardware(): Promise<HardwareDisplayInfo> {
   return invoke<HardwareDisplayInfo>('detect_hardware');
 }
 
+/** Like `detectHardware`, but cached after the first call (available RAM stays current). */
+export function getHardwareProfile(): Promise<HardwareDisplayInfo> {
+  return invoke<HardwareDisplayInfo>('get_hardware_profile');
+}
+
+/** Available RAM in MB, cheap enough to poll for a live gauge. */
+export function refreshAvailableMemory(): Promise<number> {
+  return invoke<number>('refresh_available_memory');
+}
+
+export function recommendLocalModels(): Promise<LocalModelRecommendation[]> {
+  return invoke<LocalModelRecommendation[]>('recommend_local_models');
+}
+
 export function getProviderPresets(): Promise<Record<string, ProviderPreset>> {
   return invoke<Record<string, ProviderPreset>>('get_provider_presets');
 }
@@ -131,8 +181,8 @@ export function addCredential(params: AddCredentialParams): Promise<void> {
   return invoke<void>('add_credential', { ...params });
 }
 
-export function testCredential(params: TestCredentialParams): Promise<TestCredentialResult> {
-  return invoke<TestCredentialResult>('test_credential', { ...params });
+export function testCredential(id: string, timeoutSecs?: number): Promise<TestCredentialResult> {
+  return invoke<TestCredentialResult>('test_credential', { id, timeoutSecs });
 }
 
 export function listCredentials(): Promise<AccountInfo[]> {
@@ -157,6 +207,21 @@ export function sendMessage(
   return invoke<SendMessageResult>('send_message', { message, conversationId, attachments });
 }
 
+/** Stop streaming a reply; false if it already finished or was cancelled. */
+export function cancelMessage(responseId: string): Promise<boolean> {
+  return invoke<boolean>('cancel_message', { responseId });
+}
+
+/** Abort a send_message (or email index) in the sidecar; false if it already finished. */
+export function cancelRequest(responseId: string): Promise<boolean> {
+  return invoke<boolean>('cancel_request', { responseId });
+}
+
+/** Abort the model load in progress; false if no load is running. */
+export function cancelModelLoad(): Promise<boolean> {
+  return invoke<boolean>('cancel_model_load');
+}
+
 export function documentPickFile(): Promise<string | null> {
   return invoke<string | null>('document_pick_file');
 }
@@ -185,8 +250,23 @@ export function addAttachmentToKnowledge(documentId: string): Promise<void> {
   return invoke<void>('add_attachment_to_knowledge', { documentId });
 }
 
-export function selectModel(modelId: string): Promise<void> {
-  return invoke<void>('select_model', { modelId });
+/** `skipMemoryCheck`: load even if refused with `insufficient_memory`, for this load only. */
+export function selectModel(modelId: string, skipMemoryCheck?: boolean): Promise<void> {
+  return invoke<void>('select_model', { modelId, skipMemoryCheck });
+}
+
+/** Header summary of a model file; the other fields are null when `is_gguf` is false. */
+export interface GgufProbe {
+  is_gguf: boolean;
+  architecture: string | null;
+  n_ctx_train: number | null;
+  quantization: string | null;
+  approx_size_mb: number;
+}
+
+/** Check a picked model file from its header alone, before committing to a load. */
+export function probeModelFile(modelPath: string): Promise<GgufProbe> {
+  return invoke<GgufProbe>('probe_model_file', { modelPath });
 }
 
 // ─── Email / Calendar / Inbox ───────────────────────────────────────────────
@@ -215,6 +295,11 @@ export function undoAction(actionId: string): Promise<void> {
   return invoke<void>('undo_action', { actionId });
 }
 
+/** What `sendEmailAction` or `archiveEmails` would do, without doing it. */
+export function previewAction(action: PreviewableAction): Promise<ActionPreview> {
+  return invoke<ActionPreview>('preview_action', { action });
+}
+
 export function sendEmailAction(params: SendEmailParams): Promise<void> {
   return invoke<void>('send_email_action', { ...params });
 }
@@ -347,6 +432,15 @@ function sidecarRequest<T>(request: SidecarRequest): Promise<T> {
   return invoke<T>('sidecar_request', { request });
 }
 
+/**
+ * Call a read-only sidecar method without a dedicated command, like
+ * `inbox:getItems`. Rust only allows methods under a fixed list of prefixes and
+ * rejects others with a `validation` error.
+ */
+export function invokeSidecar<T>(method: string, params?: Record<string, unknown>): Promise<T> {
+  return invoke<T>('invoke_sidecar', { method, params });
+}
+
 export function listContacts(limit: number, sortBy: 'name' | 'lastInteraction' | 'strength'): Promise<{ contacts: ContactSummary[] }> {
   return sidecarRequest<{ contacts: ContactSummary[] }>({ method: 'contacts:list', params: { limit, sortBy } });
 }
@@ -551,6 +645,18 @@ export function startIndexing(directories: string[]): Promise<void> {
   return invoke<void>('start_indexing', { directories });
 }
 
+export function pauseIndexing(): Promise<IndexingControlStatus> {
+  return invoke<IndexingControlStatus>('pause_indexing');
+}
+
+export function resumeIndexing(): Promise<IndexingControlStatus> {
+  return invoke<IndexingControlStatus>('resume_indexing');
+}
+
+export function cancelIndexing(): Promise<IndexingControlStatus> {
+  return invoke<IndexingControlStatus>('cancel_indexing');
+}
+
 export function getKnowledgeStats(): Promise<{ documentCount: number; chunkCount: number; indexSizeBytes: number; lastIndexedAt: string | null }> {
   return invoke<{ documentCount: number; chunkCount: number; indexSizeBytes: number; lastIndexedAt?: string | null }>('sidecar_request', {
     request: { method: 'get_knowledge_stats', params: {} },
@@ -15,10 +15,27 @@ export interface HardwareDisplayInfo {
   totalRamMb: number;
   cpuCores: number;
   cpuArch: string;
+  /** SIMD extensions detected at runtime (avx2, avx512f, f16c, fma, neon, dotprod) */
+  cpuFeatures?: string[];
   availableRamMb: number;
   os: string;
   gpu: HardwareGpuInfo | null;
+  /** Every GPU detected, primary (`gpu`) first */
+  gpus?: HardwareGpuInfo[];
   voiceCapable: boolean;
+  /** Free space on the volume holding the model directory, in MB */
+  diskFreeMb?: number;
+  /** Size of that volume, in MB */
+  diskTotalMb?: number;
+}
+
+/** A model size this machine can run, from `recommend_local_models`. */
+export interface LocalModelRecommendation {
+  name: string;
+  quant: string;
+  approxRamMb: number;
+  fitsInVram: boolean;
+  reason: string;
 }
 
 export interface ProviderPreset {
@@ -50,19 +67,18 @@ export interface AddCredentialParams {
   displayName: string;
 }
 
-export interface TestCredentialParams {
-  serviceType: string;
-  protocol: string;
-  host: string;
-  port: number;
-  username: string;
-  password: string;
-  useTls: boolean;
-}
+export type CredentialTestPhase = 'dns' | 'tls' | 'auth' | 'list_calendars';
 
 export interface TestCredentialResult {
   success: boolean;
   error?: string;
+  /** On failure, the phase that failed or was still running at the timeout. */
+  phase?: CredentialTestPhase;
+}
+
+export interface CredentialTestProgressPayload {
+  id: string;
+  phase: CredentialTestPhase;
 }
 
 export interface AccountInfo {
@@ -164,6 +180,49 @@ export interface SendEmailParams {
   replyToMessageId?: string;
 }
 
+/** An action `previewAction` can preview, with its command's arguments. */
+export type PreviewableAction =
+  | ({ type: 'send_email' } & SendEmailParams)
+  | { type: 'archive_emails'; messageIds: string[] };
+
+export interface MessagePreview {
+  messageId: string;
+  from: string;
+  subject: string;
+  receivedAt: string;
+  folder: string;
+}
+
+export interface EmailPreview {
+  /** The SMTP account it goes out from; null when sent through a connected Google account. */
+  from: string | null;
+  to: string[];
+  subject: string;
+  body: string;
+  inReplyTo: MessagePreview | null;
+}
+
+export interface ArchivePreview {
+  messages: MessagePreview[];
+  /** Requested ids that aren't in the email index. */
+  unknownMessageIds: string[];
+}
+
+/** What executing an action would do; nothing is performed to build it. */
+export interface ActionPreview {
+  action: 'email.send' | 'email.archive';
+  /** The tier the action is recorded under; null for archives, which skip the action log. */
+  tier: string | null;
+  /** Whether the command queues the action for approval instead of running it. */
+  requiresApproval: boolean;
+  /** Whether `undoAction` can reverse it once it runs; a sent email can't be recalled. */
+  undoable: boolean;
+  /** How long it stays undoable after running; null when it can't be undone or has no deadline. */
+  undoWindowMs: number | null;
+  email: EmailPreview | null;
+  archive: ArchivePreview | null;
+}
+
 // ─── Action Log / Approvals ─────────────────────────────────────────────────
 
 export interface LogEntry {
@@ -377,12 +436,27 @@ export type SidecarRequest =
 // ─── Finance / Subscriptions ────────────────────────────────────────────────
 
 export interface ImportStatementResult {
-  transactionCount: number;
+  transactionsImported: number;
+  /** Transactions an earlier import already stored. */
+  duplicatesSkipped: number;
+  unparseableRows: number;
+  detectedSubscriptions: number;
+  forgottenSubscriptions: number;
   merchantCount: number;
+  /** Empty strings when no transaction was imported. */
   dateRange: { start: string; end: string };
-  recurringCount: number;
-  forgottenCount: number;
   potentialSavings: number;
+  /** What the file was read as: 'utf-8', 'utf-16le', 'utf-16be' or 'windows-1252'. */
+  encoding: string;
+  /** What the file was parsed as. */
+  format: 'csv' | 'ofx' | 'ofx_xml' | 'qfx';
+}
+
+/** Payload of `semblance://statement-import-progress`. */
+export interface StatementImportProgressPayload {
+  filePath: string;
+  rowsParsed: number;
+  rowsTotal: number;
 }
 
 export type FinancialPeriod = '7d' | '30d' | '90d' | 'custom';
@@ -636,10 +710,66 @@ export interface SoundSettings {
   categoryVolumes: Record<'actions' | 'system' | 'voice', number>;
 }
 
+// ─── Sidecar Lifecycle ──────────────────────────────────────────────────────
+
+/** Payload of `semblance://sidecar-ready`, sent once per sidecar after `initialize` succeeds. */
+export interface SidecarReadyPayload {
+  epoch: number;
+  initResult: Record<string, unknown>;
+  spawnDurationMs: number;
+}
+
+/** Payload of `semblance://sidecar-failed`, sent instead of `sidecar-ready` when a sidecar doesn't come up. */
+export interface SidecarFailedPayload {
+  /** Null when the process never started. */
+  epoch: number | null;
+  stage: 'spawn' | 'initialize' | 'exited';
+  reason: string;
+  /** Another sidecar is being spawned and will send its own ready/failed. */
+  restarting: boolean;
+}
+
+// ─── Errors ─────────────────────────────────────────────────────────────────
+
+/** Rejection value of every command — the Rust `SemblanceError`, tagged by `kind`. */
+export type SemblanceErrorPayload = { message: string } & (
+  | { kind: 'sidecar_not_ready' }
+  | { kind: 'timeout'; method: string; timeoutMs: number }
+  | { kind: 'sidecar_crashed'; restarting: boolean }
+  | { kind: 'model_not_loaded' }
+  | { kind: 'context_overflow'; promptTokens: number; maxTokens: number; nCtx: number }
+  | { kind: 'prompt_too_large'; promptTokens: number; limit: number }
+  | { kind: 'insufficient_memory'; requiredMb: number; availableMb: number }
+  | { kind: 'model_load_cancelled' }
+  | { kind: 'model_loaded'; operation: string; kinds: Array<'reasoning' | 'embedding' | 'fast' | 'vision' | 'draft'> }
+  | { kind: 'protocol'; detail: string }
+  | { kind: 'sidecar' }
+  | { kind: 'validation'; field: string }
+  | { kind: 'other' }
+);
+
+// ─── Files / Indexing ───────────────────────────────────────────────────────
+
+export type IndexingState = 'idle' | 'indexing' | 'paused' | 'cancelled';
+
+/** Returned by pause/resume/cancel_indexing — `IndexingStatus` fields are snake_case. */
+export interface IndexingControlStatus {
+  state: IndexingState;
+  files_scanned: number;
+  files_total: number;
+  chunks_created: number;
+  current_file: string | null;
+  error: string | null;
+}
+
 // ─── Event Payloads ─────────────────────────────────────────────────────────
 
+/** `semblance://chat-token` — validated against `ChatToken` on the Rust side. */
 export interface ChatTokenPayload {
-  token: string;
+  responseId: string;
+  delta: string;
+  done: boolean;
+  error: string | null;
 }
 
 export interface ChatCompletePayload {
@@ -660,6 +790,16 @@ export interface LicenseAutoActivatedPayload {
   expiresAt?: string;
 }
 
+export interface RequestCancelledPayload {
+  responseId: string;
+  method: string;
+}
+
+export interface IndexingStateChangedPayload {
+  state: IndexingState;
+  previous: IndexingState;
+}
+
 // ─── Alter Ego Guardrails ──────────────────────────────────────────────────
 
 export interface AlterEgoSettingsData {
@@ -42,6 +42,7 @@ import {
 import { validateAttachment, mimeFromExtension } from '@semblance/core/agent/attachments';
 import { createDesktopVoiceAdapter } from '@semblance/core/platform/desktop-voice';
 import type { DocumentContext, ChatMessage, ChatActionItem } from '../state/AppState';
+import type { ChatTokenPayload } from '../ipc/types';
 
 export function ChatScreen() {
   const { t } = useTranslation();
@@ -432,8 +433,8 @@ export function ChatScreen() {
   }, [parsedArtifacts, state.isResponding, openArtifactPanel]);
 
   // Listen for streaming tokens
-  useTauriEvent<string>('semblance://chat-token', useCallback((token: string) => {
-    dispatch({ type: 'APPEND_TO_LAST_MESSAGE', content: token });
+  useTauriEvent<ChatTokenPayload>('semblance://chat-token', useCallback((token: ChatTokenPayload) => {
+    if (token.delta) dispatch({ type: 'APPEND_TO_LAST_MESSAGE', content: token.delta });
   }, [dispatch]));
 
   // Listen for chat completion — refresh conversation list to show updated preview
@@ -12,7 +12,7 @@ export interface AppState {
   availableModels: string[];
   inferenceEngine: 'native' | 'ollama' | 'none';
   indexingStatus: {
-    state: 'idle' | 'scanning' | 'indexing' | 'complete' | 'error';
+    state: 'idle' | 'scanning' | 'indexing' | 'paused' | 'cancelled' | 'complete' | 'error';
     filesScanned: number;
     filesTotal: number;
     chunksCreated: number;
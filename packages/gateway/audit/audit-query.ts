@@ -42,6 +42,8 @@ export interface QueryOptions {
   action?: string;
   status?: 'success' | 'error' | 'pending' | 'rejected' | 'rate_limited';
   direction?: 'request' | 'response';
+  /** `metadata.rejectionReason` of rejected requests, e.g. 'domain_blocked'. */
+  rejectionReason?: string;
   limit?: number;
   offset?: number;
 }
@@ -105,6 +107,10 @@ export class AuditQuery {
       conditions.push('direction = ?');
       params.push(options.direction);
     }
+    if (options.rejectionReason) {
+      conditions.push("json_extract(metadata, '$.rejectionReason') = ?");
+      params.push(options.rejectionReason);
+    }
 
     const where = conditions.length > 0 ? `WHERE ${conditions.join(' AND ')}` : '';
     const limit = options.limit ? `LIMIT ?` : '';
@@ -145,6 +151,10 @@ export class AuditQuery {
       conditions.push('direction = ?');
       params.push(options.direction);
     }
+    if (options.rejectionReason) {
+      conditions.push("json_extract(metadata, '$.rejectionReason') = ?");
+      params.push(options.rejectionReason);
+    }
 
     const where = conditions.length > 0 ? `WHERE ${conditions.join(' AND ')}` : '';
     const sql = `SELECT COUNT(*) as count FROM audit_log ${where}`;
@@ -11,6 +11,7 @@ import { mkdirSync, existsSync } from 'node:fs';
 
 import { AuditTrail } from './audit/trail.js';
 import { Allowlist } from './security/allowlist.js';
+import { Blocklist } from './security/blocklist.js';
 import { KeyManager } from './security/signing.js';
 import { RateLimiter } from './security/rate-limiter.js';
 import { AnomalyDetector } from './security/anomaly-detector.js';
@@ -47,6 +48,7 @@ export class Gateway {
   private reminderDb: Database.Database | null = null;
   private auditTrail: AuditTrail | null = null;
   private allowlist: Allowlist | null = null;
+  private blocklist: Blocklist | null = null;
   private rateLimiter: RateLimiter | null = null;
   private anomalyDetector: AnomalyDetector | null = null;
   private serviceRegistry: ServiceRegistry | null = null;
@@ -74,6 +76,7 @@ export class Gateway {
     // Initialize components
     this.auditTrail = new AuditTrail(this.auditDb);
     this.allowlist = new Allowlist(this.configDb);
+    this.blocklist = new Blocklist(this.configDb);
     const keyManager = new KeyManager(this.configDb);
     const signingKey = keyManager.getKey();
 
@@ -225,6 +228,7 @@ export class Gateway {
           signingKey,
           auditTrail: this.auditTrail!,
           allowlist: this.allowlist!,
+          blocklist: this.blocklist!,
           rateLimiter: this.rateLimiter!,
           anomalyDetector: this.anomalyDetector!,
           serviceRegistry: this.serviceRegistry!,
@@ -301,6 +305,14 @@ export class Gateway {
     return this.allowlist;
   }
 
+  /**
+   * Get the domain blocklist for management.
+   */
+  getBlocklist(): Blocklist {
+    if (!this.blocklist) throw new Error('Gateway not started');
+    return this.blocklist;
+  }
+
   /**
    * Get the rate limiter for monitoring.
    */
@@ -410,6 +422,8 @@ export class Gateway {
 export { AuditTrail } from './audit/trail.js';
 export { TIME_SAVED_DEFAULTS, TIME_SAVED_GRANULAR, getDefaultTimeSaved } from './audit/time-saved-defaults.js';
 export { Allowlist } from './security/allowlist.js';
+export { Blocklist } from './security/blocklist.js';
+export type { BlockedDomain } from './security/blocklist.js';
 export { KeyManager } from './security/signing.js';
 export { RateLimiter } from './security/rate-limiter.js';
 export { AnomalyDetector } from './security/anomaly-detector.js';
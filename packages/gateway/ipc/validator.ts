@@ -1,5 +1,5 @@
 // Validation Pipeline — Every request passes through this ordered, short-circuiting pipeline.
-// Schema → Signature → Allowlist → Rate Limit → Anomaly → Log → Execute → Log
+// Schema → Signature → Blocklist → Allowlist → Rate Limit → Anomaly → Log → Execute → Log
 // Failure at any step stops the pipeline and logs the rejection.
 
 import {
@@ -12,6 +12,7 @@ import {
 import type { ActionType } from '@semblance/core';
 import type { AuditTrail } from '../audit/trail.js';
 import type { Allowlist } from '../security/allowlist.js';
+import type { Blocklist } from '../security/blocklist.js';
 import type { RateLimiter } from '../security/rate-limiter.js';
 import type { AnomalyDetector } from '../security/anomaly-detector.js';
 import type { ServiceRegistry } from '../services/registry.js';
@@ -21,6 +22,8 @@ export interface ValidatorDeps {
   signingKey: Buffer;
   auditTrail: AuditTrail;
   allowlist: Allowlist;
+  /** Domains refused even when allowlisted. Optional so callers without one keep working. */
+  blocklist?: Blocklist;
   rateLimiter: RateLimiter;
   anomalyDetector: AnomalyDetector;
   serviceRegistry: ServiceRegistry;
@@ -32,6 +35,7 @@ type RejectionReason =
   | 'signature_invalid'
   | 'timestamp_stale'
   | 'request_replayed'
+  | 'domain_blocked'
   | 'domain_not_allowed'
   | 'rate_limited'
   | 'anomaly_detected';
@@ -207,7 +211,20 @@ export async function validateAndExecute(
     return makeErrorResponse(request.id, 'error', 'SIGNATURE_INVALID', 'Request signature verification failed', auditRef);
   }
 
-  // --- Step 3: Allowlist check ---
+  // --- Step 3: Blocklist check ---
+  // Applies to every outbound destination, including ones the allowlist skips (web.fetch).
+  const blockedDomain = extractBlocklistDomain(request.action, request.payload);
+  const blocked = blockedDomain ? deps.blocklist?.match(blockedDomain) : null;
+  if (blocked) {
+    const details = `Domain is blocklisted: ${blockedDomain}${blocked.reason ? ` (${blocked.reason})` : ''}`;
+    const auditRef = logRejection(
+      deps.auditTrail, request.id, request.action, payloadHash,
+      request.signature, 'domain_blocked', details,
+    );
+    return makeErrorResponse(request.id, 'error', 'DOMAIN_BLOCKED', details, auditRef);
+  }
+
+  // --- Step 3b: Allowlist check ---
   // Extract target domain from payload if applicable
   const targetDomain = extractTargetDomain(request.action, request.payload);
   if (targetDomain && !deps.allowlist.isAllowed(targetDomain)) {
@@ -347,3 +364,22 @@ function extractTargetDomain(
   // Reminder actions are local-only — no domain
   return null;
 }
+
+/**
+ * Extract the destination checked against the blocklist. Unlike the allowlist,
+ * the blocklist also covers user-initiated fetches — a blocked domain is
+ * refused no matter who asked for it.
+ */
+function extractBlocklistDomain(
+  action: ActionType,
+  payload: Record<string, unknown>,
+): string | null {
+  if (action === 'web.fetch' && typeof payload['url'] === 'string') {
+    try {
+      return new URL(payload['url']).hostname || null;
+    } catch {
+      return null;
+    }
+  }
+  return extractTargetDomain(action, payload);
+}
@@ -262,18 +262,34 @@ export class NetworkMonitor {
   }
 
   /**
-   * Get a quick trust status summary.
+   * Get a quick trust status summary. Blocklist hits are unauthorized attempts
+   * too, and are also reported on their own: they mean something tried to reach
+   * a destination the user explicitly denied.
    */
-  getTrustStatus(): { clean: boolean; unauthorizedCount: number; activeServiceCount: number } {
+  getTrustStatus(): { clean: boolean; unauthorizedCount: number; blocklistHits: number; activeServiceCount: number } {
     const unauthorizedCount = this.query.count({ status: 'rejected' });
+    const blocklistHits = this.countBlocklistHits();
     const activeServices = this.allowlist.listServices().filter(s => s.isActive);
     return {
-      clean: unauthorizedCount === 0,
+      clean: unauthorizedCount === 0 && blocklistHits === 0,
       unauthorizedCount,
+      blocklistHits,
       activeServiceCount: activeServices.length,
     };
   }
 
+  /**
+   * Count rejected requests whose destination was on the blocklist.
+   */
+  countBlocklistHits(period?: string): number {
+    const after = period ? periodToAfter(period as 'today' | 'week' | 'month' | 'all') : undefined;
+    return this.query.count({
+      after: after ?? undefined,
+      status: 'rejected',
+      rejectionReason: 'domain_blocked',
+    });
+  }
+
   /**
    * Map a domain to a service prefix for matching with audit trail data.
    */
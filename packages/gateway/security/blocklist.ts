@@ -0,0 +1,128 @@
+// Domain Blocklist — Destinations that must never be contacted, even if allowlisted.
+// Checked before the allowlist. Blocking a domain also blocks its subdomains
+// (blocking "tracker.example" blocks "cdn.tracker.example").
+// Default state: empty.
+
+import type Database from 'better-sqlite3';
+import { nanoid } from 'nanoid';
+
+const CREATE_TABLE = `
+  CREATE TABLE IF NOT EXISTS blocked_domains (
+    id TEXT PRIMARY KEY,
+    domain TEXT NOT NULL UNIQUE,
+    reason TEXT NOT NULL DEFAULT '',
+    added_at TEXT NOT NULL,
+    added_by TEXT NOT NULL DEFAULT 'user'
+  );
+`;
+
+export interface BlockedDomain {
+  id: string;
+  domain: string;
+  reason: string;
+  addedAt: string;
+  addedBy: string;
+}
+
+interface BlockedDomainRow {
+  id: string;
+  domain: string;
+  reason: string;
+  added_at: string;
+  added_by: string;
+}
+
+function rowToEntry(row: BlockedDomainRow): BlockedDomain {
+  return {
+    id: row.id,
+    domain: row.domain,
+    reason: row.reason,
+    addedAt: row.added_at,
+    addedBy: row.added_by,
+  };
+}
+
+const DOMAIN_PATTERN = /^[a-z0-9-]+(\.[a-z0-9-]+)*$/;
+
+export class Blocklist {
+  private db: Database.Database;
+
+  constructor(db: Database.Database) {
+    this.db = db;
+    this.db.exec(CREATE_TABLE);
+  }
+
+  /**
+   * Normalize a domain the same way the allowlist does: lowercase, no trailing dot.
+   */
+  private normalizeDomain(domain: string): string {
+    return domain.trim().toLowerCase().replace(/\.$/, '');
+  }
+
+  /**
+   * Return the blocklist entry matching `domain` or one of its parent domains, if any.
+   */
+  match(domain: string): BlockedDomain | null {
+    const labels = this.normalizeDomain(domain).split('.');
+    const candidates = labels.map((_, i) => labels.slice(i).join('.'));
+    const placeholders = candidates.map(() => '?').join(', ');
+    const row = this.db.prepare(
+      `SELECT * FROM blocked_domains WHERE domain IN (${placeholders}) ORDER BY length(domain) DESC LIMIT 1`
+    ).get(...candidates) as BlockedDomainRow | undefined;
+    return row ? rowToEntry(row) : null;
+  }
+
+  isBlocked(domain: string): boolean {
+    return this.match(domain) !== null;
+  }
+
+  /**
+   * Block a domain. Re-adding an existing domain updates its reason.
+   * Rejects wildcards and anything that isn't a bare host name.
+   */
+  add(params: { domain: string; reason?: string; addedBy?: string }): BlockedDomain {
+    const domain = this.normalizeDomain(params.domain);
+    if (domain.includes('*')) {
+      throw new Error(`Wildcard domains are not allowed (subdomains are blocked automatically): ${params.domain}`);
+    }
+    if (!DOMAIN_PATTERN.test(domain)) {
+      throw new Error(`Invalid domain: ${params.domain}`);
+    }
+
+    const reason = params.reason?.trim() ?? '';
+    const existing = this.db.prepare('SELECT * FROM blocked_domains WHERE domain = ?')
+      .get(domain) as BlockedDomainRow | undefined;
+    if (existing) {
+      this.db.prepare('UPDATE blocked_domains SET reason = ? WHERE id = ?').run(reason, existing.id);
+      return rowToEntry({ ...existing, reason });
+    }
+
+    const entry: BlockedDomain = {
+      id: nanoid(),
+      domain,
+      reason,
+      addedAt: new Date().toISOString(),
+      addedBy: params.addedBy ?? 'user',
+    };
+    this.db.prepare(
+      'INSERT INTO blocked_domains (id, domain, reason, added_at, added_by) VALUES (?, ?, ?, ?, ?)'
+    ).run(entry.id, entry.domain, entry.reason, entry.addedAt, entry.addedBy);
+    return entry;
+  }
+
+  /**
+   * Unblock by entry id or domain.
+   */
+  remove(idOrDomain: string): boolean {
+    const result = this.db.prepare('DELETE FROM blocked_domains WHERE id = ? OR domain = ?')
+      .run(idOrDomain, this.normalizeDomain(idOrDomain));
+    return result.changes > 0;
+  }
+
+  list(): BlockedDomain[] {
+    const rows = this.db.prepare(
+      'SELECT * FROM blocked_domains ORDER BY added_at ASC'
+    ).all() as BlockedDomainRow[];
+    return rows.map(rowToEntry);
+  }
+}
@@ -354,8 +354,9 @@ export class CalDAVAdapter {
 
   /**
    * Test CalDAV connection: connect, authenticate, discover calendars.
+   * `onListCalendars` is called once authentication succeeded, before discovery.
    */
-  async testConnection(credential: ServiceCredential, password: string): Promise<{
+  async testConnection(credential: ServiceCredential, password: string, onListCalendars?: () => void): Promise<{
     success: boolean;
     error?: string;
     calendars?: CalendarInfo[];
@@ -371,6 +372,7 @@ export class CalDAVAdapter {
         defaultAccountType: 'caldav',
       });
 
+      onListCalendars?.();
       const calendars = await client.fetchCalendars();
 
       return {
@@ -50,7 +50,7 @@ export const getEscalationPrompts = async () => [];
 
 // Subscriptions / Finance
 export const updateSubscriptionStatus = noop;
-export const importStatement = async () => ({ transactionCount: 42, merchantCount: 15, recurringCount: 4, forgottenCount: 1, potentialSavings: 120, dateRange: { start: '2025-01-01', end: '2025-12-31' } });
+export const importStatement = async () => ({ transactionsImported: 42, duplicatesSkipped: 0, unparseableRows: 0, detectedSubscriptions: 4, forgottenSubscriptions: 1, merchantCount: 15, potentialSavings: 120, dateRange: { start: '2025-01-01', end: '2025-12-31' }, encoding: 'utf-8', format: 'csv' });
 
 // Knowledge Graph
 export const getVisualizationGraph = async () => ({ nodes: [], edges: [], clusters: [], stats: { totalNodes: 0, totalEdges: 0 } });
@@ -0,0 +1,55 @@
+// Tests for EmbeddingCache — lookup by chunk hash and model, round-tripping, LRU eviction.
+
+import { describe, it, expect, beforeEach, afterEach } from 'vitest';
+import Database from 'better-sqlite3';
+import { EmbeddingCache } from '@semblance/core/knowledge/embedding-cache.js';
+import type { DatabaseHandle } from '@semblance/core/platform/types.js';
+
+describe('EmbeddingCache', () => {
+  let db: Database.Database;
+  let cache: EmbeddingCache;
+
+  beforeEach(() => {
+    db = new Database(':memory:');
+    cache = new EmbeddingCache(db as unknown as DatabaseHandle, { maxEntries: 3 });
+  });
+
+  afterEach(() => {
+    db.close();
+  });
+
+  it('returns only hits, keyed by chunk hash', () => {
+    cache.putMany([
+      { chunkHash: 'a', embedding: [0.5, -1, 2] },
+      { chunkHash: 'b', embedding: [1, 1, 1] },
+    ], 'nomic-embed-text');
+
+    const hits = cache.getMany(['a', 'missing', 'b', 'a'], 'nomic-embed-text');
+    expect([...hits.keys()].sort()).toEqual(['a', 'b']);
+    expect(hits.get('a')).toEqual([0.5, -1, 2]);
+  });
+
+  it('keeps embeddings from different models apart', () => {
+    cache.putMany([{ chunkHash: 'a', embedding: [1, 2] }], 'model-a');
+    expect(cache.getMany(['a'], 'model-b').size).toBe(0);
+    expect(cache.getMany(['a'], 'model-a').size).toBe(1);
+  });
+
+  it('skips empty embeddings', () => {
+    cache.putMany([{ chunkHash: 'a', embedding: [] }], 'm');
+    expect(cache.count()).toBe(0);
+  });
+
+  it('evicts least recently used entries past the cap', async () => {
+    cache.putMany([{ chunkHash: 'a', embedding: [1] }, { chunkHash: 'b', embedding: [2] }], 'm');
+    await new Promise(resolve => setTimeout(resolve, 5));
+    cache.putMany([{ chunkHash: 'c', embedding: [3] }], 'm');
+    await new Promise(resolve => setTimeout(resolve, 5));
+    cache.getMany(['a'], 'm'); // touch 'a' so 'b' is now the oldest
+    await new Promise(resolve => setTimeout(resolve, 5));
+    cache.putMany([{ chunkHash: 'd', embedding: [4] }], 'm');
+
+    expect(cache.count()).toBe(3);
+    expect([...cache.getMany(['a', 'b', 'c', 'd'], 'm').keys()].sort()).toEqual(['a', 'c', 'd']);
+  });
+});
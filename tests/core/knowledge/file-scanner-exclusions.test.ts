@@ -0,0 +1,35 @@
+// Tests for user indexing exclusions — glob compilation used by scanDirectory.
+
+import { describe, it, expect } from 'vitest';
+import { compileExclusions } from '@semblance/core/knowledge/file-scanner.js';
+
+describe('compileExclusions', () => {
+  it('matches bare patterns against any entry name', () => {
+    const isExcluded = compileExclusions(['node_modules', '*.log']);
+    expect(isExcluded('node_modules', 'projects/app/node_modules')).toBe(true);
+    expect(isExcluded('debug.LOG', 'logs/debug.LOG')).toBe(true);
+    expect(isExcluded('notes.md', 'notes.md')).toBe(false);
+  });
+
+  it('matches patterns with a slash against the path relative to the root', () => {
+    const isExcluded = compileExclusions(['private/**', 'docs/*.md']);
+    expect(isExcluded('private', 'private')).toBe(true);
+    expect(isExcluded('a.md', 'private/sub/a.md')).toBe(true);
+    expect(isExcluded('private', 'work/private')).toBe(false);
+    expect(isExcluded('x.md', 'docs/x.md')).toBe(true);
+    expect(isExcluded('x.md', 'docs/sub/x.md')).toBe(false);
+  });
+
+  it('supports ** across segments and ? within a segment', () => {
+    const isExcluded = compileExclusions(['**/secret', 'a?c.txt']);
+    expect(isExcluded('secret', 'secret')).toBe(true);
+    expect(isExcluded('secret', 'a/b/secret')).toBe(true);
+    expect(isExcluded('abc.txt', 'abc.txt')).toBe(true);
+    expect(isExcluded('ac.txt', 'ac.txt')).toBe(false);
+  });
+
+  it('excludes nothing for an empty or blank list', () => {
+    expect(compileExclusions([])('anything', 'anything')).toBe(false);
+    expect(compileExclusions(['  '])('anything', 'anything')).toBe(false);
+  });
+});
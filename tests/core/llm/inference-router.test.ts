@@ -278,6 +278,15 @@ describe('InferenceRouter', () => {
     it('getEmbeddingModel returns the embedding model name', () => {
       expect(router.getEmbeddingModel()).toBe('nomic-embed-text-v1.5');
     });
+
+    it('getPrimaryRoute reports BitNet while set, then the reasoning provider', () => {
+      const bitnet = createMockProvider('bitnet');
+      router.setBitNetProvider(bitnet, 'falcon-e-1b');
+      expect(router.getPrimaryRoute()).toEqual({ provider: bitnet, model: 'falcon-e-1b' });
+
+      router.clearBitNetProvider();
+      expect(router.getPrimaryRoute()).toEqual({ provider: reasoningProvider, model: 'qwen3-8b' });
+    });
   });
 
   describe('Provider swapping', () => {
@@ -106,6 +106,28 @@ describe('NativeProvider', () => {
       expect(result.model).toBe('test-model');
     });
 
+    it('passes multi-turn history as messages', async () => {
+      await provider.chat({
+        model: 'test-model',
+        messages: [
+          { role: 'system', content: 'Be helpful' },
+          { role: 'user', content: 'Hi' },
+          { role: 'assistant', content: 'Hello!' },
+          { role: 'user', content: 'What is 2 + 2?' },
+        ],
+      });
+
+      expect(bridge.generate).toHaveBeenCalledWith(expect.objectContaining({
+        prompt: '',
+        systemPrompt: 'Be helpful',
+        messages: [
+          { role: 'user', content: 'Hi' },
+          { role: 'assistant', content: 'Hello!' },
+          { role: 'user', content: 'What is 2 + 2?' },
+        ],
+      }));
+    });
+
     it('uses default model name when not specified', async () => {
       provider = new NativeProvider({ bridge, modelName: 'my-model' });
       const result = await provider.chat({
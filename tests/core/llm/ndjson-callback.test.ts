@@ -3,8 +3,8 @@
 // LOCKED DECISION: Uses NDJSON callbacks, not Tauri invoke from sidecar.
 // This test validates the protocol message format and callback resolution logic.
 
-import { describe, it, expect, beforeEach } from 'vitest';
-import { createCallbackProtocol, type CallbackProtocol } from '../../../packages/desktop/src-tauri/sidecar/ndjson-callback';
+import { describe, it, expect, beforeEach, vi } from 'vitest';
+import { CallbackError, createCallbackProtocol, type CallbackProtocol } from '../../../packages/desktop/src-tauri/sidecar/ndjson-callback';
 
 describe('NDJSON Callback Protocol', () => {
   let protocol: CallbackProtocol;
@@ -60,6 +60,18 @@ describe('NDJSON Callback Protocol', () => {
     await expect(promise).rejects.toBe('Model not loaded');
   });
 
+  it('rejects with the Rust error detail when one is sent', async () => {
+    const promise = protocol.sendCallback('native_load_model', { model_path: '/models/big.gguf' });
+    const detail = { kind: 'insufficient_memory', requiredMb: 14208, availableMb: 8192, message: 'Insufficient memory: ...' };
+
+    protocol.handleCallbackResponse({ id: 'cb-1', error: detail.message, detail });
+
+    const err = await promise.catch(e => e);
+    expect(err).toBeInstanceOf(CallbackError);
+    expect(err.message).toBe(detail.message);
+    expect(err.detail).toEqual(detail);
+  });
+
   it('ignores callback_response for unknown ids', () => {
     protocol.handleCallbackResponse({
       id: 'cb-unknown',
@@ -120,4 +132,19 @@ describe('NDJSON Callback Protocol', () => {
     expect(r3).toEqual({ status: 'ready' });
     expect(protocol.pendingCallbacks.size).toBe(0);
   });
+
+  it('cancels a callback on the Rust side when it times out', async () => {
+    vi.useFakeTimers();
+    try {
+      const promise = protocol.sendCallback('native_generate', { prompt: 'slow' });
+      const rejected = expect(promise).rejects.toBe('Callback native_generate timed out after 5000ms');
+      vi.advanceTimersByTime(5000);
+      await rejected;
+
+      expect(JSON.parse(sentMessages[1]!)).toEqual({ type: 'callback_cancel', id: 'cb-1' });
+      expect(protocol.pendingCallbacks.size).toBe(0);
+    } finally {
+      vi.useRealTimers();
+    }
+  });
 });
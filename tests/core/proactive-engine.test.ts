@@ -168,6 +168,31 @@ describe('ProactiveEngine', () => {
       expect(followUps[0]!.type).toBe('follow_up');
     });
 
+    it('records the source email as evidence', async () => {
+      const twoDaysAgo = new Date(Date.now() - 2 * 86400000);
+      await emailIndexer.indexMessages([{
+        id: 'raw-ev',
+        messageId: 'evidence-1',
+        threadId: 'thread-2',
+        from: { name: 'Boss', address: 'boss@company.com' },
+        to: [{ name: 'You', address: 'you@company.com' }],
+        cc: [],
+        subject: 'Budget review',
+        date: twoDaysAgo.toISOString(),
+        body: { text: 'Could you review the budget and let me know?' },
+        flags: [],
+        attachments: [],
+      }], 'account-1');
+
+      await engine.run();
+      const insight = engine.getActiveInsights().find(i => i.sourceIds.includes('evidence-1'));
+      expect(insight).toBeDefined();
+      const evidence = engine.getInsightEvidence(insight!.id);
+      expect(evidence?.items).toEqual([{ kind: 'email', id: 'evidence-1', label: 'Budget review' }]);
+      expect(evidence?.rationale).toMatch(/Unread for 2 days/);
+      expect(insight!.evidence).toEqual(evidence);
+    });
+
     it('does not flag emails less than 24 hours old', async () => {
       const recent = new Date(Date.now() - 6 * 3600000); // 6 hours ago
       await emailIndexer.indexMessages([{
@@ -260,6 +285,18 @@ describe('ProactiveEngine', () => {
     });
   });
 
+  describe('getInsightEvidence', () => {
+    it('returns null for unknown insights and rows without evidence', () => {
+      db.prepare(`
+        INSERT INTO proactive_insights (id, type, priority, title, summary, source_ids, created_at, estimated_time_saved_seconds, dismissed)
+        VALUES (?, ?, ?, ?, ?, ?, ?, ?, 0)
+      `).run('legacy-insight', 'follow_up', 'normal', 'Test', 'Summary', '[]', new Date().toISOString(), 30);
+
+      expect(engine.getInsightEvidence('legacy-insight')).toBeNull();
+      expect(engine.getInsightEvidence('missing')).toBeNull();
+    });
+  });
+
   describe('periodic run', () => {
     it('starts and stops periodic run without errors', () => {
       const stop = engine.startPeriodicRun();
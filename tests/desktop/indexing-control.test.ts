@@ -0,0 +1,72 @@
+// Tests for indexing pause/resume/cancel — imports real createIndexingControl from source.
+
+import { describe, it, expect } from 'vitest';
+import { createIndexingControl } from '../../packages/desktop/src-tauri/sidecar/indexing-control';
+
+describe('Indexing Control', () => {
+  it('reports every transition', () => {
+    const transitions: string[] = [];
+    const control = createIndexingControl((state, previous) => transitions.push(`${previous}->${state}`));
+    const run = control.start();
+    control.pause();
+    control.resume();
+    run.finish();
+    expect(transitions).toEqual(['idle->indexing', 'indexing->paused', 'paused->indexing', 'indexing->idle']);
+  });
+
+  it('refuses a second start while a run is active', () => {
+    const control = createIndexingControl();
+    control.start();
+    expect(() => control.start()).toThrow('Indexing already in progress (indexing)');
+    control.pause();
+    expect(() => control.start()).toThrow('Indexing already in progress (paused)');
+  });
+
+  it('blocks a paused run until resumed', async () => {
+    const control = createIndexingControl();
+    const run = control.start();
+    control.pause();
+    let passed: boolean | null = null;
+    const checkpoint = run.checkpoint().then(ok => { passed = ok; });
+    await Promise.resolve();
+    expect(passed).toBeNull();
+    control.resume();
+    await checkpoint;
+    expect(passed).toBe(true);
+  });
+
+  it('stops a paused run when cancelled', async () => {
+    const control = createIndexingControl();
+    const run = control.start();
+    control.pause();
+    const checkpoint = run.checkpoint();
+    control.cancel();
+    expect(await checkpoint).toBe(false);
+  });
+
+  it('stops a cancelled run even after a new run starts', async () => {
+    const control = createIndexingControl();
+    const oldRun = control.start();
+    control.cancel();
+    const newRun = control.start();
+
+    // The old run reaches its next checkpoint mid-way through the new run.
+    expect(await oldRun.checkpoint()).toBe(false);
+    expect(await newRun.checkpoint()).toBe(true);
+
+    // Its cleanup doesn't reset the new run's state.
+    oldRun.finish();
+    expect(control.state()).toBe('indexing');
+    newRun.finish();
+    expect(control.state()).toBe('idle');
+  });
+
+  it('stays cancelled after the cancelled run finishes', () => {
+    const control = createIndexingControl();
+    const run = control.start();
+    control.cancel();
+    run.finish();
+    expect(control.state()).toBe('cancelled');
+    expect(() => control.pause()).toThrow('Cannot pause indexing while cancelled');
+  });
+});
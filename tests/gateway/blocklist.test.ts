@@ -0,0 +1,64 @@
+// Blocklist Tests — Proves blocked domains (and their subdomains) are always refused.
+
+import { describe, it, expect, beforeEach, afterEach } from 'vitest';
+import Database from 'better-sqlite3';
+import { Blocklist } from '@semblance/gateway/security/blocklist.js';
+
+describe('Blocklist', () => {
+  let db: Database.Database;
+  let blocklist: Blocklist;
+
+  beforeEach(() => {
+    db = new Database(':memory:');
+    blocklist = new Blocklist(db);
+  });
+
+  afterEach(() => {
+    db.close();
+  });
+
+  it('empty blocklist blocks nothing', () => {
+    expect(blocklist.isBlocked('api.example.com')).toBe(false);
+    expect(blocklist.list()).toEqual([]);
+  });
+
+  it('blocks the domain and its subdomains, but not lookalikes', () => {
+    blocklist.add({ domain: 'tracker.example', reason: 'analytics' });
+    expect(blocklist.isBlocked('tracker.example')).toBe(true);
+    expect(blocklist.isBlocked('cdn.tracker.example')).toBe(true);
+    expect(blocklist.isBlocked('TRACKER.EXAMPLE.')).toBe(true);
+    expect(blocklist.isBlocked('nottracker.example')).toBe(false);
+    expect(blocklist.isBlocked('example')).toBe(false);
+  });
+
+  it('match returns the entry with its reason', () => {
+    blocklist.add({ domain: 'tracker.example', reason: 'analytics' });
+    const entry = blocklist.match('a.b.tracker.example');
+    expect(entry?.domain).toBe('tracker.example');
+    expect(entry?.reason).toBe('analytics');
+    expect(entry?.addedBy).toBe('user');
+  });
+
+  it('re-adding a domain updates its reason instead of duplicating', () => {
+    const first = blocklist.add({ domain: 'tracker.example', reason: 'old' });
+    const second = blocklist.add({ domain: 'Tracker.Example', reason: 'new' });
+    expect(second.id).toBe(first.id);
+    expect(blocklist.list()).toHaveLength(1);
+    expect(blocklist.list()[0]!.reason).toBe('new');
+  });
+
+  it('rejects wildcards and invalid domains', () => {
+    expect(() => blocklist.add({ domain: '*.tracker.example' })).toThrow('Wildcard');
+    expect(() => blocklist.add({ domain: '' })).toThrow('Invalid domain');
+    expect(() => blocklist.add({ domain: 'https://tracker.example/path' })).toThrow('Invalid domain');
+  });
+
+  it('removes by id or by domain', () => {
+    const entry = blocklist.add({ domain: 'a.example' });
+    blocklist.add({ domain: 'b.example' });
+    expect(blocklist.remove(entry.id)).toBe(true);
+    expect(blocklist.remove('B.example')).toBe(true);
+    expect(blocklist.remove('c.example')).toBe(false);
+    expect(blocklist.list()).toEqual([]);
+  });
+});
@@ -221,6 +221,34 @@ describe('NetworkMonitor', () => {
       expect(status.unauthorizedCount).toBe(1);
     });
 
+    it('reports blocklist hits and not clean', () => {
+      trail.append({
+        requestId: 'blocked-1',
+        timestamp: new Date().toISOString(),
+        action: 'web.fetch',
+        direction: 'response',
+        status: 'rejected',
+        payloadHash: 'h',
+        signature: 's',
+        metadata: { rejectionReason: 'domain_blocked', details: 'Domain is blocklisted: tracker.example' },
+      });
+      trail.append({
+        requestId: 'bad-1',
+        timestamp: new Date().toISOString(),
+        action: 'email.fetch',
+        direction: 'response',
+        status: 'rejected',
+        payloadHash: 'h',
+        signature: 's',
+        metadata: { rejectionReason: 'domain_not_allowed', details: 'Domain not on allowlist: x.example' },
+      });
+      const status = monitor.getTrustStatus();
+      expect(status.clean).toBe(false);
+      expect(status.unauthorizedCount).toBe(2);
+      expect(status.blocklistHits).toBe(1);
+      expect(monitor.countBlocklistHits('today')).toBe(1);
+    });
+
     it('includes active service count', () => {
       allowlist.addService({ serviceName: 'Gmail', domain: 'imap.gmail.com', protocol: 'IMAP' });
       allowlist.addService({ serviceName: 'Calendar', domain: 'caldav.google.com', protocol: 'CalDAV' });
@@ -9,6 +9,7 @@ import { signRequest, sha256 } from '@semblance/core';
 import type { ActionType } from '@semblance/core';
 import { AuditTrail } from '@semblance/gateway/audit/trail.js';
 import { Allowlist } from '@semblance/gateway/security/allowlist.js';
+import { Blocklist } from '@semblance/gateway/security/blocklist.js';
 import { RateLimiter } from '@semblance/gateway/security/rate-limiter.js';
 import { AnomalyDetector } from '@semblance/gateway/security/anomaly-detector.js';
 import { ServiceRegistry } from '@semblance/gateway/services/registry.js';
@@ -18,6 +19,7 @@ describe('Validation Pipeline', () => {
   let db: Database.Database;
   let auditTrail: AuditTrail;
   let allowlist: Allowlist;
+  let blocklist: Blocklist;
   let rateLimiter: RateLimiter;
   let anomalyDetector: AnomalyDetector;
   let serviceRegistry: ServiceRegistry;
@@ -54,6 +56,7 @@ describe('Validation Pipeline', () => {
     db = new Database(':memory:');
     auditTrail = new AuditTrail(db);
     allowlist = new Allowlist(db);
+    blocklist = new Blocklist(db);
     signingKey = randomBytes(32);
     rateLimiter = new RateLimiter({
       actionLimits: { 'email.send': 5 },
@@ -78,6 +81,7 @@ describe('Validation Pipeline', () => {
       signingKey,
       auditTrail,
       allowlist,
+      blocklist,
       rateLimiter,
       anomalyDetector,
       serviceRegistry,
@@ -204,6 +208,30 @@ describe('Validation Pipeline', () => {
     expect(response.error?.code).toBe('DOMAIN_NOT_ALLOWED');
   });
 
+  it('blocklisted domain is rejected even when allowlisted', async () => {
+    allowlist.addService({
+      serviceName: 'Tracker',
+      domain: 'cdn.tracker.example',
+      protocol: 'https',
+    });
+    blocklist.add({ domain: 'tracker.example', reason: 'analytics' });
+
+    const request = makeValidRequest({
+      action: 'service.api_call',
+      payload: {
+        service: 'cdn.tracker.example',
+        endpoint: '/collect',
+        method: 'POST',
+      },
+    });
+    const response = await validateAndExecute(request, deps);
+
+    expect(response.status).toBe('error');
+    expect(response.error?.code).toBe('DOMAIN_BLOCKED');
+    const rejections = auditTrail.getByRequestId('req_test_001');
+    expect(rejections.some(e => e.status === 'rejected' && e.metadata?.['rejectionReason'] === 'domain_blocked')).toBe(true);
+  });
+
   it('allowlisted domain passes for service.api_call', async () => {
     allowlist.addService({
       serviceName: 'API Example',